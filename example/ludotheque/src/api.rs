@@ -5,10 +5,13 @@ use axum::middleware::Next;
 use axum::response::{Redirect, Response};
 use axum::routing::get;
 use axum::{middleware, Json, Router};
-use cqrs_rust_lib::dispatchers::ViewDispatcher;
+use cqrs_rust_lib::dispatchers::{BroadcastDispatcher, ViewDispatcher, ViewUpdates};
 use cqrs_rust_lib::es::EventStoreImpl;
 use cqrs_rust_lib::prelude::surrealdb as db;
-use cqrs_rust_lib::rest::{CQRSAuditLogRouter, CQRSCodexReadRouter, CQRSWriteRouter, CqrsHttpQuery};
+use cqrs_rust_lib::rest::{
+    CQRSAuditLogRouter, CQRSCodexReadRouter, CQRSEventStreamRouter, CQRSLiveViewRouter,
+    CQRSWriteRouter, CqrsHttpQuery,
+};
 use cqrs_rust_lib::{Aggregate, CqrsCommandEngine, CqrsContext, Dispatcher};
 use http::header::CONTENT_TYPE;
 use http::StatusCode;
@@ -112,12 +115,19 @@ pub async fn start(config: AppConfig) -> Result<(), Box<dyn std::error::Error +
     let view_storage: Arc<db::ReadStorage<GameView, CqrsHttpQuery<GameQuery>>> =
         Arc::new(db::ReadStorage::new(db.clone(), Game::TYPE, "game_view"));
 
-    // Dispatcher: keeps GameView in sync with Game events
+    // Dispatcher: keeps GameView in sync with Game events, and feeds the live view
+    // subscriptions with every GameView it saves
+    let view_updates = ViewUpdates::<GameView>::default();
     let view_dispatcher =
-        ViewDispatcher::<Game, GameView, CqrsHttpQuery<GameQuery>>::new(view_storage.clone());
+        ViewDispatcher::<Game, GameView, CqrsHttpQuery<GameQuery>>::new(view_storage.clone())
+            .with_updates(view_updates.clone());
+
+    // Broadcast: feeds the SSE event streams with every committed event
+    let live_events = BroadcastDispatcher::<Game>::default();
 
     // CQRS engine
-    let effects: Vec<Box<dyn Dispatcher<Game> + Send + Sync>> = vec![Box::new(view_dispatcher)];
+    let effects: Vec<Box<dyn Dispatcher<Game> + Send + Sync>> =
+        vec![Box::new(view_dispatcher), Box::new(live_events.clone())];
     let engine = Arc::new(CqrsCommandEngine::new(
         event_store.clone(),
         effects,
@@ -128,9 +138,12 @@ pub async fn start(config: AppConfig) -> Result<(), Box<dyn std::error::Error +
     ));
 
     // Routers
-    let read_router = CQRSCodexReadRouter::routes(view_storage, Game::TYPE);
+    let read_router = CQRSCodexReadRouter::routes(view_storage.clone(), Game::TYPE);
+    let live_router =
+        CQRSLiveViewRouter::<Game, GameView, GameQuery>::routes(view_storage, view_updates);
     let write_router = CQRSWriteRouter::routes(engine);
-    let audit_router = CQRSAuditLogRouter::routes(event_store, Game::TYPE);
+    let audit_router = CQRSAuditLogRouter::routes(event_store.clone(), Game::TYPE);
+    let stream_router = CQRSEventStreamRouter::routes(event_store, live_events, Game::TYPE);
 
     // Assemble
     let (router, api) = OpenApiRouter::with_openapi(ApiDoc::openapi())
@@ -142,6 +155,8 @@ pub async fn start(config: AppConfig) -> Result<(), Box<dyn std::error::Error +
         .nest(GAMES_PATH, read_router)
         .nest(GAMES_PATH, write_router)
         .nest(GAMES_PATH, audit_router)
+        .nest(GAMES_PATH, stream_router)
+        .nest(GAMES_PATH, live_router)
         .split_for_parts();
 
     let router = router
@@ -6,12 +6,16 @@ use axum::middleware::Next;
 use axum::response::{Redirect, Response};
 use axum::routing::get;
 use axum::{middleware, Json, Router};
+use cqrs_rust_lib::es::storage::EventStoreStorage;
 use cqrs_rust_lib::es::EventStoreImpl;
-use cqrs_rust_lib::prelude::postgres as db;
+use cqrs_rust_lib::prelude::{postgres as db, redb as kv};
+use cqrs_rust_lib::read::storage::Storage;
 use cqrs_rust_lib::rest::{CQRSAuditLogRouter, CQRSReadRouter, CQRSWriteRouter};
 use cqrs_rust_lib::{Aggregate, CqrsCommandEngine, CqrsContext, Dispatcher};
 use http::header::CONTENT_TYPE;
 use http::StatusCode;
+use std::fmt::Debug;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio_postgres::NoTls;
 use tower_http::cors::{Any, CorsLayer};
@@ -40,9 +44,20 @@ async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
     Json(ApiDoc::openapi())
 }
 
+/// Where the event store and its snapshots live.
+pub enum Backend {
+    Postgres {
+        uri: String,
+    },
+    /// A single redb file: no server, so the app runs offline and keeps its lists.
+    Redb {
+        path: PathBuf,
+    },
+}
+
 pub struct AppConfig {
     pub http_port: u16,
-    pub pg_uri: String,
+    pub backend: Backend,
 }
 
 #[derive(Debug, Clone)]
@@ -77,6 +92,55 @@ pub async fn context_middleware(
 }
 
 pub async fn start(config: AppConfig) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    match config.backend {
+        Backend::Postgres { uri } => {
+            // Connect to Postgres
+            let (client, connection) = tokio_postgres::connect(&uri, NoTls).await?;
+            tokio::spawn(async move {
+                if let Err(e) = connection.await {
+                    eprintln!("Postgres connection error: {}", e);
+                }
+            });
+            let client = Arc::new(client);
+
+            // Ensure tables exist
+            client
+                .batch_execute(&db::EventStorePersist::<TodoList>::schema())
+                .await?;
+
+            // Storages
+            let es_store = db::EventStorePersist::<TodoList>::from_client(client.clone());
+            // Reads the event store's snapshot table directly. It takes the table, not a wrapped
+            // view storage: the two have different schemas, which is what made this route
+            // unreadable before (#10). The default mapper points a filter at `data->>'field'`.
+            let repository = db::FromSnapshotStorage::<TodoList, TodoListQuery>::new(
+                client.clone(),
+                es_store.snapshot_table_name(),
+            );
+            serve(es_store, repository, config.http_port).await
+        }
+        Backend::Redb { path } => {
+            // Tables are created by their first write: there is no schema to apply.
+            let database = kv::open(path)?;
+            let es_store = kv::EventStorePersist::<TodoList>::new(database.clone());
+            let repository = kv::FromSnapshotStorage::<TodoList, TodoListQuery>::new(
+                database,
+                es_store.snapshot_table_name(),
+            );
+            serve(es_store, repository, config.http_port).await
+        }
+    }
+}
+
+async fn serve<ES, R>(
+    es_store: ES,
+    repository: R,
+    http_port: u16,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+where
+    ES: EventStoreStorage<TodoList> + Clone + Debug + Send + Sync + 'static,
+    R: Storage<TodoList, TodoListQuery> + Send + Sync + 'static,
+{
     let cors = CorsLayer::new()
         .allow_methods(Any)
         .allow_origin(Any)
@@ -84,31 +148,9 @@ pub async fn start(config: AppConfig) -> Result<(), Box<dyn std::error::Error +
 
     let context_middleware = middleware::from_fn(context_middleware);
 
-    info!("Starting server on http://localhost:{}", config.http_port);
+    info!("Starting server on http://localhost:{}", http_port);
 
-    // Connect to Postgres
-    let (client, connection) = tokio_postgres::connect(&config.pg_uri, NoTls).await?;
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("Postgres connection error: {}", e);
-        }
-    });
-    let client = Arc::new(client);
-
-    // Ensure tables exist
-    client
-        .batch_execute(&db::EventStorePersist::<TodoList>::schema())
-        .await?;
-
-    // Storages
-    let es_store = db::EventStorePersist::<TodoList>::from_client(client.clone());
-    // Reads the event store's snapshot table directly. It takes the table, not a wrapped
-    // view storage: the two have different schemas, which is what made this route
-    // unreadable before (#10). The default mapper points a filter at `data->>'field'`.
-    let repository = Arc::new(db::FromSnapshotStorage::<TodoList, TodoListQuery>::new(
-        client.clone(),
-        es_store.snapshot_table_name(),
-    ));
+    let repository = Arc::new(repository);
 
     // CQRS Command
     let event_store = EventStoreImpl::new(es_store);
@@ -145,7 +187,7 @@ pub async fn start(config: AppConfig) -> Result<(), Box<dyn std::error::Error +
         .fallback_service(Router::new().route("/", get(|| async { Redirect::to(DOC_PATH) })))
         .layer(cors);
 
-    let listener = tokio::net::TcpListener::bind(("0.0.0.0", config.http_port)).await?;
+    let listener = tokio::net::TcpListener::bind(("0.0.0.0", http_port)).await?;
 
     Ok(axum::serve(listener, router).await?)
 }
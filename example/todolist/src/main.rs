@@ -1,4 +1,5 @@
 use clap::{Parser, Subcommand};
+use std::path::PathBuf;
 use todolist::api;
 use tracing::Level;
 use tracing_subscriber::fmt::layer;
@@ -14,6 +15,13 @@ pub enum Commands {
         #[clap(long, default_value = "8081")]
         http_port: u16,
     },
+    /// Runs without a database server, persisting to a local redb file.
+    StartOffline {
+        #[clap(long, default_value = "todolist.redb")]
+        db_path: PathBuf,
+        #[clap(long, default_value = "8081")]
+        http_port: u16,
+    },
 }
 
 #[derive(Parser, Debug)]
@@ -30,7 +38,19 @@ impl Cli {
         match &self.command {
             Commands::Start { pg_uri, http_port } => {
                 let config = api::AppConfig {
-                    pg_uri: pg_uri.clone(),
+                    backend: api::Backend::Postgres {
+                        uri: pg_uri.clone(),
+                    },
+                    http_port: *http_port,
+                };
+
+                api::start(config).await
+            }
+            Commands::StartOffline { db_path, http_port } => {
+                let config = api::AppConfig {
+                    backend: api::Backend::Redb {
+                        path: db_path.clone(),
+                    },
                     http_port: *http_port,
                 };
 
@@ -50,14 +50,14 @@ pub trait Aggregate: Default + Debug + Clone + Serialize + DeserializeOwned + Ma
 cqrs_async_trait! {
 pub trait CommandHandler: Aggregate {
     #[cfg(feature = "utoipa")]
-    type CreateCommand: DeserializeOwned + MaybeSync + MaybeSend + ToSchema;
+    type CreateCommand: Serialize + DeserializeOwned + MaybeSync + MaybeSend + ToSchema;
     #[cfg(not(feature = "utoipa"))]
-    type CreateCommand: DeserializeOwned + MaybeSync + MaybeSend;
+    type CreateCommand: Serialize + DeserializeOwned + MaybeSync + MaybeSend;
 
     #[cfg(feature = "utoipa")]
-    type UpdateCommand: DeserializeOwned + MaybeSync + MaybeSend + ToSchema;
+    type UpdateCommand: Serialize + DeserializeOwned + MaybeSync + MaybeSend + ToSchema;
     #[cfg(not(feature = "utoipa"))]
-    type UpdateCommand: DeserializeOwned + MaybeSync + MaybeSend;
+    type UpdateCommand: Serialize + DeserializeOwned + MaybeSync + MaybeSend;
 
     type Services: MaybeSend + MaybeSync;
 
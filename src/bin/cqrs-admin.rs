@@ -0,0 +1,339 @@
+//! `cqrs-admin`: the journals of a PostgreSQL, MongoDB, SQLite or SurrealDB database,
+//! without the application that writes them.
+//!
+//! ```text
+//! cqrs-admin --url postgres://localhost/bank types
+//! cqrs-admin --url mongodb://localhost --database bank journal account 0b6f…
+//! cqrs-admin --url sqlite://bank.db verify account
+//! cqrs-admin --url ws://localhost:8000 --namespace bank --database bank export account -o account.ndjson
+//! cqrs-admin schema postgres account --tenant-column
+//! ```
+
+use clap::{Parser, Subcommand, ValueEnum};
+use cqrs_rust_lib::es::journals::{
+    ContinuityCheck, DEFAULT_JOURNAL_BATCH_SIZE, JournalTables, Journals, RawRecord, export_ndjson,
+    import_ndjson,
+};
+use cqrs_rust_lib::es::mongodb::MongoDBJournals;
+use cqrs_rust_lib::es::postgres::{PostgresJournals, SharedClient};
+use cqrs_rust_lib::es::sqlite::{SharedConnection, SqliteJournals};
+use cqrs_rust_lib::es::surrealdb::SurrealDBJournals;
+use cqrs_rust_lib::CqrsError;
+use std::fs::File;
+use flate2::Compression;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+#[derive(Parser)]
+#[command(
+    name = "cqrs-admin",
+    version,
+    about = "Inspects, dumps and checks event journals"
+)]
+struct Cli {
+    /// `postgres://…`, `mongodb://…`, `sqlite://path`, or any SurrealDB endpoint (`ws://…`,
+    /// `http://…`).
+    #[arg(long, global = true)]
+    url: Option<String>,
+    /// The MongoDB or SurrealDB database.
+    #[arg(long, global = true)]
+    database: Option<String>,
+    /// The SurrealDB namespace.
+    #[arg(long, global = true)]
+    namespace: Option<String>,
+    /// A SurrealDB root user to sign in as.
+    #[arg(long, global = true, requires = "password")]
+    username: Option<String>,
+    /// The password of `--username`.
+    #[arg(long, global = true, requires = "username")]
+    password: Option<String>,
+    /// The tables of this tenant, under `Tenancy::Table`.
+    #[arg(long, global = true)]
+    tenant: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Lists the aggregate types that have a journal.
+    Types,
+    /// Prints the events of an aggregate, one JSON record per line.
+    Journal {
+        aggregate_type: String,
+        aggregate_id: String,
+    },
+    /// Prints the snapshot of an aggregate.
+    Snapshot {
+        aggregate_type: String,
+        aggregate_id: String,
+    },
+    /// Writes every event and snapshot of an aggregate type as NDJSON.
+    Export {
+        aggregate_type: String,
+        /// The standard output otherwise.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        #[arg(long, default_value_t = DEFAULT_JOURNAL_BATCH_SIZE)]
+        batch_size: usize,
+        /// Gzips the dump.
+        #[arg(long)]
+        gzip: bool,
+    },
+    /// Writes the records of an NDJSON dump, gzipped or not, ids, versions and timestamps
+    /// kept.
+    Import {
+        aggregate_type: String,
+        /// The standard input otherwise.
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+        #[arg(long, default_value_t = DEFAULT_JOURNAL_BATCH_SIZE)]
+        batch_size: usize,
+    },
+    /// Checks that each aggregate's versions run from 1 without gap or duplicate.
+    Verify {
+        aggregate_type: String,
+        #[arg(long, default_value_t = DEFAULT_JOURNAL_BATCH_SIZE)]
+        batch_size: usize,
+    },
+    /// Prints the DDL of an aggregate type's tables; needs no connection.
+    Schema {
+        backend: Backend,
+        aggregate_type: String,
+        /// The indexes of `Tenancy::Column`.
+        #[arg(long)]
+        tenant_column: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Backend {
+    Postgres,
+    Mongodb,
+    Sqlite,
+    Surrealdb,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", e.message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn io_error(e: std::io::Error) -> CqrsError {
+    CqrsError::internal(e.to_string())
+}
+
+impl Cli {
+    fn tables(&self, aggregate_type: &str) -> Result<JournalTables, CqrsError> {
+        let tables = JournalTables::of(aggregate_type)?;
+        match &self.tenant {
+            Some(tenant) => tables.for_tenant(tenant),
+            None => Ok(tables),
+        }
+    }
+
+    fn required<'a>(value: &'a Option<String>, name: &str) -> Result<&'a str, CqrsError> {
+        value
+            .as_deref()
+            .ok_or_else(|| CqrsError::user_error(format!("--{name} is required")))
+    }
+
+    async fn connect(&self) -> Result<Box<dyn Journals>, CqrsError> {
+        let url = Self::required(&self.url, "url")?;
+        if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+            let (client, connection) = tokio_postgres::connect(url, tokio_postgres::NoTls)
+                .await
+                .map_err(CqrsError::database_error)?;
+            tokio::spawn(async move {
+                if let Err(e) = connection.await {
+                    eprintln!("error: postgres connection: {e}");
+                }
+            });
+            Ok(Box::new(PostgresJournals::new(SharedClient(Arc::new(
+                client,
+            )))))
+        } else if url.starts_with("mongodb://") || url.starts_with("mongodb+srv://") {
+            let client = mongodb::Client::with_uri_str(url)
+                .await
+                .map_err(CqrsError::database_error)?;
+            let database = Self::required(&self.database, "database")?;
+            Ok(Box::new(MongoDBJournals::new(client.database(database))))
+        } else if let Some(path) = url.strip_prefix("sqlite://") {
+            Ok(Box::new(SqliteJournals::new(SharedConnection::open(path)?)))
+        } else {
+            let db = surrealdb::engine::any::connect(url)
+                .await
+                .map_err(CqrsError::database_error)?;
+            if let (Some(username), Some(password)) = (&self.username, &self.password) {
+                db.signin(surrealdb::opt::auth::Root {
+                    username: username.clone(),
+                    password: password.clone(),
+                })
+                .await
+                .map_err(CqrsError::database_error)?;
+            }
+            db.use_ns(Self::required(&self.namespace, "namespace")?)
+                .use_db(Self::required(&self.database, "database")?)
+                .await
+                .map_err(CqrsError::database_error)?;
+            Ok(Box::new(SurrealDBJournals::new(db)))
+        }
+    }
+}
+
+fn print_records(records: impl IntoIterator<Item = RawRecord>) -> Result<(), CqrsError> {
+    let mut output = std::io::stdout().lock();
+    for record in records {
+        serde_json::to_writer(&mut output, &record).map_err(CqrsError::serialization_error)?;
+        writeln!(output).map_err(io_error)?;
+    }
+    Ok(())
+}
+
+async fn run(cli: Cli) -> Result<ExitCode, CqrsError> {
+    if let Command::Schema {
+        backend,
+        aggregate_type,
+        tenant_column,
+    } = &cli.command
+    {
+        let tables = cli.tables(aggregate_type)?;
+        let ddl = match backend {
+            Backend::Postgres => PostgresJournals::<SharedClient>::schema(&tables, *tenant_column),
+            Backend::Mongodb => MongoDBJournals::schema(&tables, *tenant_column),
+            Backend::Sqlite => SqliteJournals::schema(&tables, *tenant_column),
+            Backend::Surrealdb => SurrealDBJournals::schema(&tables, *tenant_column),
+        };
+        println!("{ddl}");
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let journals = cli.connect().await?;
+    match &cli.command {
+        Command::Types => {
+            for aggregate_type in journals.aggregate_types().await? {
+                println!("{aggregate_type}");
+            }
+        }
+        Command::Journal {
+            aggregate_type,
+            aggregate_id,
+        } => {
+            let events = journals
+                .journal(&cli.tables(aggregate_type)?, aggregate_id)
+                .await?;
+            if events.is_empty() {
+                return Err(CqrsError::aggregate_not_found(aggregate_id));
+            }
+            print_records(events.into_iter().map(RawRecord::Event))?;
+        }
+        Command::Snapshot {
+            aggregate_type,
+            aggregate_id,
+        } => {
+            let snapshots = journals
+                .snapshot(&cli.tables(aggregate_type)?, aggregate_id)
+                .await?;
+            if snapshots.is_empty() {
+                return Err(CqrsError::not_found(format!(
+                    "no snapshot of {aggregate_id}"
+                )));
+            }
+            print_records(snapshots.into_iter().map(RawRecord::Snapshot))?;
+        }
+        Command::Export {
+            aggregate_type,
+            output,
+            batch_size,
+            gzip,
+        } => {
+            let tables = cli.tables(aggregate_type)?;
+            let output: Box<dyn Write> = match output {
+                Some(path) => Box::new(BufWriter::new(File::create(path).map_err(io_error)?)),
+                None => Box::new(std::io::stdout().lock()),
+            };
+            let (events, snapshots) = if *gzip {
+                let mut output = GzEncoder::new(output, Compression::default());
+                let counts =
+                    export_ndjson(journals.as_ref(), &tables, *batch_size, &mut output).await?;
+                output
+                    .finish()
+                    .map_err(io_error)?
+                    .flush()
+                    .map_err(io_error)?;
+                counts
+            } else {
+                let mut output = output;
+                let counts =
+                    export_ndjson(journals.as_ref(), &tables, *batch_size, &mut output).await?;
+                output.flush().map_err(io_error)?;
+                counts
+            };
+            eprintln!("exported {events} events and {snapshots} snapshots");
+        }
+        Command::Import {
+            aggregate_type,
+            input,
+            batch_size,
+        } => {
+            let tables = cli.tables(aggregate_type)?;
+            let input: Box<dyn Read> = match input {
+                Some(path) => Box::new(File::open(path).map_err(io_error)?),
+                None => Box::new(std::io::stdin().lock()),
+            };
+            let mut input = BufReader::new(input);
+            let gzipped = input
+                .fill_buf()
+                .map_err(io_error)?
+                .starts_with(&[0x1f, 0x8b]);
+            let (events, snapshots) = if gzipped {
+                let input = BufReader::new(MultiGzDecoder::new(input));
+                import_ndjson(journals.as_ref(), &tables, *batch_size, input).await?
+            } else {
+                import_ndjson(journals.as_ref(), &tables, *batch_size, input).await?
+            };
+            eprintln!("imported {events} events and {snapshots} snapshots");
+        }
+        Command::Verify {
+            aggregate_type,
+            batch_size,
+        } => {
+            let tables = cli.tables(aggregate_type)?;
+            let batch_size = (*batch_size).max(1);
+            let mut check = ContinuityCheck::new();
+            let mut skip = 0;
+            loop {
+                let page = journals.events(&tables, skip, batch_size).await?;
+                skip += page.len();
+                page.iter().for_each(|event| check.push(event));
+                if page.len() < batch_size {
+                    break;
+                }
+            }
+            for issue in check.issues() {
+                println!("{issue}");
+            }
+            let (aggregates, events) = check.checked();
+            eprintln!(
+                "checked {events} events of {aggregates} aggregates: {} issues",
+                check.issues().len()
+            );
+            if !check.issues().is_empty() {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Command::Schema { .. } => unreachable!("answered without a connection"),
+    }
+    Ok(ExitCode::SUCCESS)
+}
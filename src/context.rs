@@ -3,6 +3,8 @@ use chrono::{DateTime, Utc};
 #[derive(Debug, Clone)]
 pub struct CqrsContext {
     current_user: Option<String>,
+    roles: Vec<String>,
+    tenant: Option<String>,
     metadata: Option<serde_json::Value>,
     request_id: String,
     now: DateTime<Utc>,
@@ -13,6 +15,8 @@ impl CqrsContext {
     pub fn new(current_user: Option<String>) -> Self {
         Self {
             current_user,
+            roles: Vec::new(),
+            tenant: None,
             metadata: None,
             request_id: "".to_string(),
             now: Utc::now(),
@@ -24,6 +28,33 @@ impl CqrsContext {
         self.current_user.clone().unwrap_or("anonymous".to_string())
     }
 
+    /// Whether a user was identified; `false` for the anonymous user.
+    pub fn is_authenticated(&self) -> bool {
+        self.current_user.is_some()
+    }
+
+    /// The roles granted to the current user, as the authentication layer read them.
+    pub fn roles(&self) -> &[String] {
+        &self.roles
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
+    pub fn with_roles(self, roles: Vec<String>) -> Self {
+        Self { roles, ..self }
+    }
+
+    /// The tenant the request acts for, if the deployment has tenants.
+    pub fn tenant(&self) -> Option<&str> {
+        self.tenant.as_deref()
+    }
+
+    pub fn with_tenant(self, tenant: Option<String>) -> Self {
+        Self { tenant, ..self }
+    }
+
     pub fn request_id(&self) -> String {
         self.request_id.clone()
     }
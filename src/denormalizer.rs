@@ -1,4 +1,5 @@
 use crate::{Aggregate, CqrsContext, CqrsError, EventEnvelope, MaybeSend, MaybeSync};
+use chrono::{DateTime, Utc};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::fmt::Debug;
@@ -22,6 +23,19 @@ pub trait View<A: Aggregate>:
 
     fn view_id(event: &EventEnvelope<A>) -> String;
     fn update(&self, event: &EventEnvelope<A>) -> Option<Self>;
+
+    /// The version of the view, for a view that tracks one — typically the version of
+    /// the last event it applied. The read routes derive the view's `ETag` from it; the
+    /// default, `None`, has them hash the body instead.
+    fn version(&self) -> Option<usize> {
+        None
+    }
+
+    /// When the view last changed — typically the `at` of the last event it applied —
+    /// served by the read routes as `Last-Modified`.
+    fn last_modified(&self) -> Option<DateTime<Utc>> {
+        None
+    }
 }
 
 pub trait ViewElements<A: Aggregate>: View<A> {
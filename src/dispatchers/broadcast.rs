@@ -0,0 +1,239 @@
+use crate::{Aggregate, CqrsContext, CqrsError, Dispatcher, EventEnvelope};
+use tokio::sync::broadcast;
+use tracing::debug;
+
+/// Default number of events a subscriber may fall behind before it starts missing them.
+pub const DEFAULT_BROADCAST_CAPACITY: usize = 1024;
+
+/// A dispatcher that fans every committed event out to in-process subscribers — the
+/// feed of `CQRSEventStreamRouter`, or of anything else that wants to follow an
+/// aggregate type live.
+///
+/// It keeps nothing: an event is delivered to the receivers subscribed when it is
+/// dispatched, and to no one when there are none. A receiver more than `capacity` events
+/// behind loses the oldest ones and is told so by a `Lagged` error on its next `recv`;
+/// the event store is what it catches up from.
+///
+/// Clones share one channel, so register one clone on the `CqrsCommandEngine` and hand
+/// another to whatever subscribes.
+///
+/// Every tenant's events go through the one channel, each marked with the tenant it was
+/// committed for; a subscriber keeps only those [`visible_to`](BroadcastEvent::visible_to)
+/// its own context.
+pub struct BroadcastDispatcher<A: Aggregate> {
+    sender: broadcast::Sender<BroadcastEvent<A>>,
+}
+
+/// A committed event, and the tenant of the context it was dispatched with.
+#[derive(Debug, Clone)]
+pub struct BroadcastEvent<A: Aggregate> {
+    pub tenant: Option<String>,
+    pub envelope: EventEnvelope<A>,
+}
+
+impl<A: Aggregate> BroadcastEvent<A> {
+    /// Whether a subscriber acting under `context` may receive the event: both are of
+    /// the same tenant, or neither has one.
+    #[must_use]
+    pub fn visible_to(&self, context: &CqrsContext) -> bool {
+        self.tenant.as_deref() == context.tenant()
+    }
+}
+
+impl<A: Aggregate> BroadcastDispatcher<A> {
+    /// A dispatcher whose subscribers may each fall `capacity` events behind.
+    ///
+    /// # Panics
+    ///
+    /// When `capacity` is 0, as `tokio::sync::broadcast::channel` does.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    /// A receiver of every event dispatched from now on.
+    #[must_use]
+    pub fn subscribe(&self) -> broadcast::Receiver<BroadcastEvent<A>> {
+        self.sender.subscribe()
+    }
+
+    /// Number of receivers currently subscribed.
+    #[must_use]
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+impl<A: Aggregate> Clone for BroadcastDispatcher<A> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+impl<A: Aggregate> Default for BroadcastDispatcher<A> {
+    fn default() -> Self {
+        Self::new(DEFAULT_BROADCAST_CAPACITY)
+    }
+}
+
+/// The views a `ViewDispatcher` saves, fanned out the same way as [`BroadcastDispatcher`]
+/// fans out events — the feed of `CQRSLiveViewRouter`. Attach it with
+/// `ViewDispatcher::with_updates`.
+///
+/// Same delivery rules: only to the receivers subscribed at the time, the oldest dropped
+/// past `capacity` with a `Lagged` error to the receiver.
+pub struct ViewUpdates<V> {
+    sender: broadcast::Sender<V>,
+}
+
+impl<V: Clone> ViewUpdates<V> {
+    /// # Panics
+    ///
+    /// When `capacity` is 0, as `tokio::sync::broadcast::channel` does.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    /// A receiver of every view saved from now on.
+    #[must_use]
+    pub fn subscribe(&self) -> broadcast::Receiver<V> {
+        self.sender.subscribe()
+    }
+
+    /// Number of receivers currently subscribed.
+    #[must_use]
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
+    /// Hands a saved view to the current subscribers, if any.
+    pub(crate) fn publish(&self, view: V) {
+        // As for events: nobody listening is not an error.
+        let _ = self.sender.send(view);
+    }
+}
+
+impl<V> Clone for ViewUpdates<V> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+impl<V: Clone> Default for ViewUpdates<V> {
+    fn default() -> Self {
+        Self::new(DEFAULT_BROADCAST_CAPACITY)
+    }
+}
+
+cqrs_async_trait! {
+impl<A: Aggregate> Dispatcher<A> for BroadcastDispatcher<A> {
+    async fn dispatch(
+        &self,
+        aggregate_id: &str,
+        events: &[EventEnvelope<A>],
+        context: &CqrsContext,
+    ) -> Result<(), CqrsError> {
+        let tenant = context.tenant().map(str::to_string);
+        for event in events {
+            let event = BroadcastEvent {
+                tenant: tenant.clone(),
+                envelope: event.clone(),
+            };
+            // `send` fails only when no receiver is subscribed, which is not an error
+            // for a feed nobody is watching.
+            if self.sender.send(event).is_err() {
+                debug!(aggregate_id, "No subscriber for broadcast events");
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{TestAggregate, TestEvent};
+    use chrono::Utc;
+    use std::collections::HashMap;
+    use tokio::sync::broadcast::error::RecvError;
+
+    fn envelope(version: usize) -> EventEnvelope<TestAggregate> {
+        EventEnvelope {
+            event_id: format!("event{version}"),
+            aggregate_id: "agg1".to_string(),
+            version,
+            payload: TestEvent::Updated {
+                name: "toto".to_string(),
+            },
+            metadata: HashMap::new(),
+            at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn every_subscriber_receives_the_events_dispatched_after_it_subscribed() {
+        let dispatcher = BroadcastDispatcher::<TestAggregate>::new(8);
+        let context = CqrsContext::default();
+
+        dispatcher
+            .dispatch("agg1", &[envelope(1)], &context)
+            .await
+            .expect("no subscriber is not an error");
+
+        let mut first = dispatcher.subscribe();
+        let mut second = dispatcher.clone().subscribe();
+        assert_eq!(dispatcher.subscriber_count(), 2);
+
+        dispatcher
+            .dispatch("agg1", &[envelope(2), envelope(3)], &context)
+            .await
+            .unwrap();
+
+        for receiver in [&mut first, &mut second] {
+            assert_eq!(receiver.recv().await.unwrap().envelope.version, 2);
+            assert_eq!(receiver.recv().await.unwrap().envelope.version, 3);
+        }
+    }
+
+    #[tokio::test]
+    async fn a_subscriber_past_capacity_is_told_how_many_it_missed() {
+        let dispatcher = BroadcastDispatcher::<TestAggregate>::new(2);
+        let mut receiver = dispatcher.subscribe();
+
+        let events: Vec<_> = (1..=3).map(envelope).collect();
+        dispatcher
+            .dispatch("agg1", &events, &CqrsContext::default())
+            .await
+            .unwrap();
+
+        assert!(matches!(receiver.recv().await, Err(RecvError::Lagged(1))));
+        assert_eq!(receiver.recv().await.unwrap().envelope.version, 2);
+    }
+
+    #[tokio::test]
+    async fn each_event_carries_the_tenant_it_was_dispatched_for() {
+        let dispatcher = BroadcastDispatcher::<TestAggregate>::new(8);
+        let mut receiver = dispatcher.subscribe();
+        let acme = CqrsContext::default().with_tenant(Some("acme".to_string()));
+
+        dispatcher
+            .dispatch("agg1", &[envelope(1)], &acme)
+            .await
+            .unwrap();
+
+        let event = receiver.recv().await.unwrap();
+        assert_eq!(event.tenant.as_deref(), Some("acme"));
+        assert!(event.visible_to(&acme));
+        assert!(!event.visible_to(&CqrsContext::default()));
+        assert!(!event.visible_to(&CqrsContext::default().with_tenant(Some("globex".to_string()))));
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{Aggregate, CqrsContext, CqrsError, EventEnvelope, MaybeSend, MaybeSync};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// Events a dispatcher of the engine failed to handle, kept to be redelivered with
+/// [`CqrsCommandEngine::redeliver`](crate::CqrsCommandEngine::redeliver).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", bound = "")]
+pub struct FailedDispatch<A>
+where
+    A: Aggregate,
+{
+    pub id: String,
+    /// The position of the dispatcher in the engine.
+    pub dispatcher: usize,
+    pub aggregate_id: String,
+    pub events: Vec<EventEnvelope<A>>,
+    /// The tenant of the commit, which a redelivery is scoped to again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// The message of the last error.
+    pub error: String,
+    pub failed_at: DateTime<Utc>,
+    /// How many times the events were dispatched, the first one included.
+    pub attempts: u32,
+}
+
+impl<A> FailedDispatch<A>
+where
+    A: Aggregate,
+{
+    /// The first failure of `dispatcher` to handle `events`, committed under `context`.
+    #[must_use]
+    pub fn new(
+        dispatcher: usize,
+        aggregate_id: &str,
+        events: &[EventEnvelope<A>],
+        error: &CqrsError,
+        context: &CqrsContext,
+    ) -> Self {
+        Self {
+            id: context.next_uuid(),
+            dispatcher,
+            aggregate_id: aggregate_id.to_string(),
+            events: events.to_vec(),
+            tenant: context.tenant().map(str::to_string),
+            error: error.message.clone(),
+            failed_at: context.now(),
+            attempts: 1,
+        }
+    }
+}
+
+cqrs_async_trait! {
+/// Keeps the dispatches that failed until they are redelivered.
+pub trait DispatchFailureStore<A>: MaybeSend + MaybeSync
+where
+    A: Aggregate + 'static,
+{
+    /// Saves `failure`, replacing the one of the same id.
+    async fn save(&self, failure: FailedDispatch<A>) -> Result<(), CqrsError>;
+
+    async fn find(&self, id: &str) -> Result<Option<FailedDispatch<A>>, CqrsError>;
+
+    /// Every failure kept, oldest first.
+    async fn list(&self) -> Result<Vec<FailedDispatch<A>>, CqrsError>;
+
+    async fn remove(&self, id: &str) -> Result<(), CqrsError>;
+}
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub type DynDispatchFailureStore<A> = Arc<dyn DispatchFailureStore<A> + Send + Sync>;
+#[cfg(target_arch = "wasm32")]
+pub type DynDispatchFailureStore<A> = Arc<dyn DispatchFailureStore<A>>;
+
+/// Failed dispatches held in process memory, and lost with it.
+#[derive(Clone, Debug)]
+pub struct InMemoryDispatchFailures<A>
+where
+    A: Aggregate,
+{
+    failures: Arc<RwLock<BTreeMap<String, FailedDispatch<A>>>>,
+}
+
+impl<A> Default for InMemoryDispatchFailures<A>
+where
+    A: Aggregate,
+{
+    fn default() -> Self {
+        Self {
+            failures: Arc::new(RwLock::new(BTreeMap::new())),
+        }
+    }
+}
+
+impl<A> InMemoryDispatchFailures<A>
+where
+    A: Aggregate,
+{
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+cqrs_async_trait! {
+impl<A> DispatchFailureStore<A> for InMemoryDispatchFailures<A>
+where
+    A: Aggregate + 'static,
+{
+    async fn save(&self, failure: FailedDispatch<A>) -> Result<(), CqrsError> {
+        self.failures
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(failure.id.clone(), failure);
+        Ok(())
+    }
+
+    async fn find(&self, id: &str) -> Result<Option<FailedDispatch<A>>, CqrsError> {
+        Ok(self.failures.read().unwrap_or_else(PoisonError::into_inner).get(id).cloned())
+    }
+
+    async fn list(&self) -> Result<Vec<FailedDispatch<A>>, CqrsError> {
+        let mut failures: Vec<_> = self
+            .failures
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .cloned()
+            .collect();
+        failures.sort_by_key(|failure| failure.failed_at);
+        Ok(failures)
+    }
+
+    async fn remove(&self, id: &str) -> Result<(), CqrsError> {
+        self.failures.write().unwrap_or_else(PoisonError::into_inner).remove(id);
+        Ok(())
+    }
+}
+}
@@ -3,3 +3,11 @@ pub use view_dispatcher::*;
 
 mod memory;
 pub use memory::*;
+
+mod failures;
+pub use failures::*;
+
+#[cfg(feature = "broadcast")]
+mod broadcast;
+#[cfg(feature = "broadcast")]
+pub use broadcast::*;
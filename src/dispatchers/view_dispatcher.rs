@@ -2,9 +2,14 @@ use crate::read::storage::{DynStorage, HasId};
 use crate::{Aggregate, CqrsContext, CqrsError, Dispatcher, EventEnvelope, MaybeSend, MaybeSync, View};
 use std::fmt::Debug;
 
+#[cfg(feature = "broadcast")]
+use crate::dispatchers::ViewUpdates;
+
 pub struct ViewDispatcher<A, V, Q> {
     _phantom: std::marker::PhantomData<(A, V, Q)>,
     storage: DynStorage<V, Q>,
+    #[cfg(feature = "broadcast")]
+    updates: Option<ViewUpdates<V>>,
 }
 
 impl<A, V, Q> ViewDispatcher<A, V, Q>
@@ -17,8 +22,18 @@ where
         Self {
             _phantom: std::marker::PhantomData,
             storage,
+            #[cfg(feature = "broadcast")]
+            updates: None,
         }
     }
+
+    /// Publishes every view this dispatcher saves to `updates`, once the save succeeded.
+    #[cfg(feature = "broadcast")]
+    #[must_use]
+    pub fn with_updates(mut self, updates: ViewUpdates<V>) -> Self {
+        self.updates = Some(updates);
+        self
+    }
 }
 
 cqrs_async_trait! {
@@ -42,6 +57,12 @@ where
                 .await?
                 .unwrap_or_else(|| V::default());
             if let Some(next) = prev.update(event) {
+                #[cfg(feature = "broadcast")]
+                if let Some(updates) = &self.updates {
+                    self.storage.save(next.clone(), context.clone()).await?;
+                    updates.publish(next);
+                    continue;
+                }
                 self.storage.save(next, context.clone()).await?;
             }
         }
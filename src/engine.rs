@@ -1,8 +1,10 @@
 use crate::aggregate::{AggregateIdGenerator, DefaultIdGenerator};
 use crate::context::CqrsContext;
 use crate::denormalizer::Dispatcher;
+use crate::dispatchers::{DynDispatchFailureStore, FailedDispatch};
 use crate::errors::CqrsError;
 use crate::event::Event;
+use crate::policy::{CommandPolicies, Policy};
 use crate::{Aggregate, CommandHandler, DynEventStore, EventEnvelope};
 use std::collections::HashMap;
 use tracing::{debug, error, info};
@@ -63,6 +65,8 @@ where
     id_generator: Box<dyn AggregateIdGenerator<A> + Send + Sync>,
     #[cfg(target_arch = "wasm32")]
     id_generator: Box<dyn AggregateIdGenerator<A>>,
+    policies: CommandPolicies<A>,
+    dispatch_failures: Option<DynDispatchFailureStore<A>>,
 }
 
 impl<A> CqrsCommandEngine<A>
@@ -84,6 +88,8 @@ where
             services,
             error_handler,
             id_generator: Box::new(DefaultIdGenerator),
+            policies: CommandPolicies::default(),
+            dispatch_failures: None,
         }
     }
 
@@ -101,6 +107,8 @@ where
             services,
             error_handler,
             id_generator: Box::new(DefaultIdGenerator),
+            policies: CommandPolicies::default(),
+            dispatch_failures: None,
         }
     }
 
@@ -119,6 +127,73 @@ where
         self
     }
 
+    /// The policies [`execute_create_variant`](Self::execute_create_variant) and
+    /// [`execute_update_variant`](Self::execute_update_variant) enforce.
+    #[must_use]
+    pub fn with_policies(mut self, policies: CommandPolicies<A>) -> Self {
+        self.policies = policies;
+        self
+    }
+
+    pub fn policies(&self) -> &CommandPolicies<A> {
+        &self.policies
+    }
+
+    /// Keeps the events a dispatcher fails to handle in `store`, to be
+    /// [redelivered](Self::redeliver); they are only logged otherwise.
+    #[must_use]
+    pub fn with_dispatch_failures(mut self, store: DynDispatchFailureStore<A>) -> Self {
+        self.dispatch_failures = Some(store);
+        self
+    }
+
+    pub fn dispatch_failures(&self) -> Option<&DynDispatchFailureStore<A>> {
+        self.dispatch_failures.as_ref()
+    }
+
+    pub fn store(&self) -> &DynEventStore<A> {
+        &self.store
+    }
+
+    /// Dispatches the events of a kept failure again, to the dispatcher that failed them
+    /// and under the tenant they were committed by. The failure is forgotten once they
+    /// are handled, and kept with one more attempt and the new error otherwise.
+    pub async fn redeliver(
+        &self,
+        failure_id: &str,
+        context: &CqrsContext,
+    ) -> Result<(), CqrsError> {
+        let failures = self
+            .dispatch_failures
+            .as_ref()
+            .ok_or_else(|| CqrsError::not_implemented("no store keeps the failed dispatches"))?;
+        let mut failure = failures
+            .find(failure_id)
+            .await?
+            .ok_or_else(|| CqrsError::not_found(format!("no failed dispatch {failure_id:?}")))?;
+        let dispatcher = self.dispatchers.get(failure.dispatcher).ok_or_else(|| {
+            CqrsError::internal(format!("no dispatcher at {}", failure.dispatcher))
+        })?;
+        let context = context.clone().with_tenant(failure.tenant.clone());
+        match dispatcher
+            .dispatch(&failure.aggregate_id, &failure.events, &context)
+            .await
+        {
+            Ok(_) => {
+                info!(failure_id = %failure_id, "Redelivered failed dispatch");
+                failures.remove(failure_id).await
+            }
+            Err(e) => {
+                error!(failure_id = %failure_id, error = %e, "Failed to redeliver events");
+                failure.attempts += 1;
+                failure.error = e.message.clone();
+                failure.failed_at = context.now();
+                failures.save(failure).await?;
+                Err(e)
+            }
+        }
+    }
+
     #[cfg(not(target_arch = "wasm32"))]
     pub fn append_dispatcher(&mut self, dispatcher: Box<dyn Dispatcher<A> + Send + Sync>) {
         self.dispatchers.push(dispatcher);
@@ -162,17 +237,73 @@ where
         result
     }
 
+    /// Runs a create command without looking at the policies: the caller is trusted.
     pub async fn execute_create_with_metadata(
         &self,
         command: A::CreateCommand,
         metadata: HashMap<String, String>,
         context: &CqrsContext,
+    ) -> Result<String, CqrsError> {
+        self.create(command, metadata, context, None, None).await
+    }
+
+    /// Runs a create command under the policy of its variant, read off the command (see
+    /// [`CommandPolicies`]): the context is checked first, then the predicates over the
+    /// freshly initialized aggregate, before `handle_create`.
+    ///
+    /// This is the entry point of the front ends: `CQRSWriteRouter`, the GraphQL schema,
+    /// the MCP server and synchronization.
+    pub async fn execute_create_variant(
+        &self,
+        command: A::CreateCommand,
+        metadata: HashMap<String, String>,
+        context: &CqrsContext,
+    ) -> Result<String, CqrsError> {
+        let policy = self.policies.policy_of(&command)?;
+        if let Some(policy) = policy {
+            policy.authorize_context(context)?;
+        }
+        self.create(command, metadata, context, policy, None).await
+    }
+
+    /// [`execute_create_variant`](Self::execute_create_variant) for the aggregate
+    /// `aggregate_id` instead of a generated one: the id a client gave the aggregate it
+    /// created offline, which [`SyncServer`](crate::sync::SyncServer) replays here.
+    pub async fn execute_create_variant_with_id(
+        &self,
+        aggregate_id: &str,
+        command: A::CreateCommand,
+        metadata: HashMap<String, String>,
+        context: &CqrsContext,
+    ) -> Result<String, CqrsError> {
+        let policy = self.policies.policy_of(&command)?;
+        if let Some(policy) = policy {
+            policy.authorize_context(context)?;
+        }
+        self.create(command, metadata, context, policy, Some(aggregate_id))
+            .await
+    }
+
+    async fn create(
+        &self,
+        command: A::CreateCommand,
+        metadata: HashMap<String, String>,
+        context: &CqrsContext,
+        policy: Option<&Policy<A>>,
+        aggregate_id: Option<&str>,
     ) -> Result<String, CqrsError> {
         debug!("Executing create command with metadata");
-        let aggregate_id = self.id_generator.next_id(&command, context);
+        let aggregate_id = match aggregate_id {
+            Some(aggregate_id) => aggregate_id.to_string(),
+            None => self.id_generator.next_id(&command, context),
+        };
         debug!(aggregate_id = %aggregate_id, "Generated new aggregate ID");
 
-        let (aggregate, version) = match self.store.initialize_aggregate(&aggregate_id).await {
+        let (aggregate, version) = match self
+            .store
+            .initialize_aggregate(&aggregate_id, context)
+            .await
+        {
             Ok(result) => {
                 let (_, v) = &result;
                 debug!(version = %v, "Initialized aggregate");
@@ -184,6 +315,10 @@ where
             }
         };
 
+        if let Some(policy) = policy {
+            policy.authorize(context, &aggregate)?;
+        }
+
         let events = match aggregate
             .handle_create(command, &self.services, context)
             .await
@@ -233,22 +368,61 @@ where
                 Err(e) => {
                     error!(dispatcher_index = i, error = %e, "Failed to dispatch events");
                     eh(&e);
+                    if let Some(failures) = &self.dispatch_failures {
+                        let failure = FailedDispatch::new(i, aggregate_id, events, &e, context);
+                        if let Err(e) = failures.save(failure).await {
+                            error!(dispatcher_index = i, error = %e, "Failed to keep the failed dispatch");
+                        }
+                    }
                 }
             };
         }
         debug!("Finished handling events for all dispatchers");
     }
 
+    /// Runs an update command without looking at the policies: the caller is trusted.
     pub async fn execute_update_with_metadata(
         &self,
         aggregate_id: &str,
         command: A::UpdateCommand,
         metadata: HashMap<String, String>,
         context: &CqrsContext,
+    ) -> Result<(), CqrsError> {
+        self.update(aggregate_id, command, metadata, context, None)
+            .await
+    }
+
+    /// Runs an update command under the policy of its variant, read off the command (see
+    /// [`CommandPolicies`]): the context is checked before the aggregate is loaded — a
+    /// caller without the roles gets `403` whether it exists or not — then the
+    /// predicates over the loaded aggregate, before `handle_update`.
+    pub async fn execute_update_variant(
+        &self,
+        aggregate_id: &str,
+        command: A::UpdateCommand,
+        metadata: HashMap<String, String>,
+        context: &CqrsContext,
+    ) -> Result<(), CqrsError> {
+        let policy = self.policies.policy_of(&command)?;
+        if let Some(policy) = policy {
+            policy.authorize_context(context)?;
+        }
+        self.update(aggregate_id, command, metadata, context, policy)
+            .await
+    }
+
+    async fn update(
+        &self,
+        aggregate_id: &str,
+        command: A::UpdateCommand,
+        metadata: HashMap<String, String>,
+        context: &CqrsContext,
+        policy: Option<&Policy<A>>,
     ) -> Result<(), CqrsError> {
         debug!("Executing update command with metadata");
 
-        let (mut aggregate, version) = match self.store.load_aggregate(aggregate_id).await {
+        let (mut aggregate, version) = match self.store.load_aggregate(aggregate_id, context).await
+        {
             Ok(result) => {
                 let (_, v) = &result;
                 debug!(version = %v, "Loaded aggregate");
@@ -260,6 +434,10 @@ where
             }
         };
 
+        if let Some(policy) = policy {
+            policy.authorize(context, &aggregate)?;
+        }
+
         let events = match aggregate
             .handle_update(command, &self.services, context)
             .await
@@ -387,7 +565,11 @@ mod tests {
     use crate::CqrsCommandEngine;
     use crate::CqrsContext;
     use crate::EventEnvelope;
+    use crate::dispatchers::{DynDispatchFailureStore, InMemoryDispatchFailures};
+    use crate::{CommandPolicies, Dispatcher, Policy, Tenancy};
     use futures::StreamExt;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
     #[tokio::test]
     async fn test_create_aggregate() {
@@ -442,7 +624,7 @@ mod tests {
         // Verify via stored events
         let event_stream = engine
             .store
-            .load_events(&aggregate_id)
+            .load_events(&aggregate_id, &context)
             .await
             .expect("Event loading should succeed");
 
@@ -498,7 +680,7 @@ mod tests {
         // Verification
         let event_stream = engine
             .store
-            .load_events(&aggregate_id)
+            .load_events(&aggregate_id, &context)
             .await
             .expect("Event loading should succeed");
 
@@ -519,4 +701,165 @@ mod tests {
         assert!(matches!(events[1].payload, TestEvent::Incremented));
         assert!(matches!(events[2].payload, TestEvent::Incremented));
     }
+
+    #[tokio::test]
+    async fn variant_entry_points_enforce_the_policies() {
+        let persist = InMemoryPersist::<TestAggregate>::new();
+        let store = EventStoreImpl::new(persist);
+        // At most one increment, by an identified user.
+        let policy = Policy::authenticated().require(|_, aggregate: &TestAggregate| {
+            serde_json::to_value(aggregate).unwrap()["counter"].as_i64() < Some(1)
+        });
+        let engine = CqrsCommandEngine::new(store, vec![], (), Box::new(|_e| {}))
+            .with_policies(CommandPolicies::new().otherwise(policy));
+
+        let anonymous = CqrsContext::default();
+        let alice = CqrsContext::new(Some("alice".to_string()));
+        let create = || CreateCommand::Initialize {
+            name: "toto".to_string(),
+        };
+
+        let error = engine
+            .execute_create_variant(create(), Default::default(), &anonymous)
+            .await
+            .unwrap_err();
+        assert_eq!(error.status, 401);
+
+        let aggregate_id = engine
+            .execute_create_variant(create(), Default::default(), &alice)
+            .await
+            .expect("Creation should succeed");
+        engine
+            .execute_update_variant(
+                &aggregate_id,
+                UpdateCommand::Increment,
+                Default::default(),
+                &alice,
+            )
+            .await
+            .expect("The first increment is allowed");
+        let error = engine
+            .execute_update_variant(
+                &aggregate_id,
+                UpdateCommand::Increment,
+                Default::default(),
+                &alice,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(error.status, 403);
+
+        // The trusted entry points do not look at the policies.
+        engine
+            .execute_update(&aggregate_id, UpdateCommand::Increment, &anonymous)
+            .await
+            .expect("In-process calls are not checked");
+    }
+
+    #[tokio::test]
+    async fn an_aggregate_of_one_tenant_is_not_found_from_another() {
+        let persist = InMemoryPersist::<TestAggregate>::new().with_tenancy(Tenancy::Column);
+        let store = EventStoreImpl::new(persist);
+        let engine = CqrsCommandEngine::new(store, vec![], (), Box::new(|_e| {}));
+        let tenant = |tenant: &str| CqrsContext::default().with_tenant(Some(tenant.to_string()));
+
+        let aggregate_id = engine
+            .execute_create(
+                CreateCommand::Initialize {
+                    name: "toto".to_string(),
+                },
+                &tenant("acme"),
+            )
+            .await
+            .expect("Creation should succeed");
+
+        let error = engine
+            .execute_update(&aggregate_id, UpdateCommand::Increment, &tenant("globex"))
+            .await
+            .unwrap_err();
+        assert_eq!(error.status, 404);
+        let error = engine
+            .execute_update(
+                &aggregate_id,
+                UpdateCommand::Increment,
+                &CqrsContext::default(),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(error.status, 403);
+
+        engine
+            .execute_update(&aggregate_id, UpdateCommand::Increment, &tenant("acme"))
+            .await
+            .expect("The owning tenant can update it");
+    }
+
+    /// Fails while `down` is set, and counts the events it handles.
+    struct Flaky {
+        down: Arc<AtomicBool>,
+        handled: Arc<AtomicUsize>,
+    }
+
+    crate::cqrs_async_trait! {
+    impl Dispatcher<TestAggregate> for Flaky {
+        async fn dispatch(
+            &self,
+            _aggregate_id: &str,
+            events: &[EventEnvelope<TestAggregate>],
+            context: &CqrsContext,
+        ) -> Result<(), crate::CqrsError> {
+            if self.down.load(Ordering::SeqCst) {
+                return Err(crate::CqrsError::database_error("projection down"));
+            }
+            assert_eq!(context.tenant(), Some("acme"));
+            self.handled.fetch_add(events.len(), Ordering::SeqCst);
+            Ok(())
+        }
+    }
+    }
+
+    #[tokio::test]
+    async fn a_failed_dispatch_is_kept_until_it_is_redelivered() {
+        let store = EventStoreImpl::new(InMemoryPersist::<TestAggregate>::new());
+        let (down, handled) = (
+            Arc::new(AtomicBool::new(true)),
+            Arc::new(AtomicUsize::new(0)),
+        );
+        let flaky = Flaky {
+            down: down.clone(),
+            handled: handled.clone(),
+        };
+        let failures: DynDispatchFailureStore<TestAggregate> =
+            Arc::new(InMemoryDispatchFailures::new());
+        let engine = CqrsCommandEngine::new(store, vec![Box::new(flaky)], (), Box::new(|_e| {}))
+            .with_dispatch_failures(failures.clone());
+        let acme = CqrsContext::default().with_tenant(Some("acme".to_string()));
+
+        let aggregate_id = engine
+            .execute_create(
+                CreateCommand::Initialize {
+                    name: "toto".to_string(),
+                },
+                &acme,
+            )
+            .await
+            .expect("A failed dispatch does not fail the command");
+        let [failure] = failures.list().await.unwrap().try_into().unwrap();
+        assert_eq!(failure.aggregate_id, aggregate_id);
+        assert_eq!((failure.dispatcher, failure.attempts), (0, 1));
+        assert_eq!(failure.error, "projection down");
+
+        // Redelivered from another tenant's request, it still goes out under acme.
+        let admin = CqrsContext::default();
+        assert!(engine.redeliver(&failure.id, &admin).await.is_err());
+        let failure = failures.find(&failure.id).await.unwrap().unwrap();
+        assert_eq!(failure.attempts, 2);
+
+        down.store(false, Ordering::SeqCst);
+        engine.redeliver(&failure.id, &admin).await.unwrap();
+        assert_eq!(handled.load(Ordering::SeqCst), 1);
+        assert!(failures.list().await.unwrap().is_empty());
+        let error = engine.redeliver(&failure.id, &admin).await.unwrap_err();
+        assert_eq!(error.status, 404);
+    }
 }
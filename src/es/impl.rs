@@ -1,7 +1,9 @@
 use crate::es::storage::{EventStoreStorage, EventStream};
 use crate::{
-    Aggregate, CqrsContext, CqrsError, EventEnvelope, EventStore, MaybeSend, MaybeSync, Snapshot,
+    Aggregate, CqrsContext, CqrsError, EventEnvelope, EventFilter, EventStore, MaybeSend,
+    MaybeSync, Snapshot,
 };
+use futures::StreamExt;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::sync::Arc;
@@ -31,7 +33,7 @@ where
     }
 
     async fn execute_within_session(
-        &self,
+        persist: &P,
         session: &mut P::Session,
         events: Vec<A::Event>,
         aggregate: &A,
@@ -39,7 +41,7 @@ where
         version: usize,
         context: &CqrsContext,
     ) -> Result<Vec<EventEnvelope<A>>, CqrsError> {
-        let latest_event = match self.persist.fetch_latest_event(aggregate, session).await {
+        let latest_event = match persist.fetch_latest_event(aggregate, session).await {
             Ok(event) => {
                 debug!(has_event = event.is_some(), "Fetched latest event");
                 event
@@ -78,7 +80,7 @@ where
             .collect::<Vec<_>>();
 
         debug!(event_count = envelopes.len(), "Saving events");
-        if let Err(e) = self.persist.save_events(envelopes.clone(), session).await {
+        if let Err(e) = persist.save_events(envelopes.clone(), session).await {
             error!(error = %e, "Failed to save events");
             return Err(e);
         }
@@ -86,8 +88,7 @@ where
 
         let next_latest_version = version + envelopes.len();
         debug!(next_version = %next_latest_version, "Saving snapshot");
-        if let Err(e) = self
-            .persist
+        if let Err(e) = persist
             .save_snapshot(aggregate, next_latest_version, session)
             .await
         {
@@ -106,9 +107,18 @@ where
     A: Aggregate + 'static,
     P: EventStoreStorage<A> + MaybeSend + MaybeSync + Clone + Debug + 'static,
 {
-    async fn load_snapshot(&self, aggregate_id: &str) -> Result<Option<Snapshot<A>>, CqrsError> {
+    async fn load_snapshot(
+        &self,
+        aggregate_id: &str,
+        context: &CqrsContext,
+    ) -> Result<Option<Snapshot<A>>, CqrsError> {
         debug!("Loading snapshot for aggregate");
-        match self.persist.fetch_snapshot(aggregate_id).await {
+        match self
+            .persist
+            .for_context(context)?
+            .fetch_snapshot(aggregate_id)
+            .await
+        {
             Ok(Some(snapshot)) => {
                 info!(version = %snapshot.version, "Snapshot loaded successfully");
                 Ok(Some(snapshot))
@@ -128,30 +138,89 @@ where
         &self,
         aggregate_id: &str,
         version: usize,
+        context: &CqrsContext,
     ) -> Result<EventStream<A>, CqrsError> {
         debug!("Loading events from version");
         self.persist
+            .for_context(context)?
             .fetch_events_from_version(aggregate_id, version)
             .await
     }
 
-    async fn load_events(&self, aggregate_id: &str) -> Result<EventStream<A>, CqrsError> {
+    async fn load_events(
+        &self,
+        aggregate_id: &str,
+        context: &CqrsContext,
+    ) -> Result<EventStream<A>, CqrsError> {
         debug!("Loading all events for aggregate");
-        self.persist.fetch_all_events(aggregate_id).await
+        self.persist
+            .for_context(context)?
+            .fetch_all_events(aggregate_id)
+            .await
+    }
+
+    async fn health(&self) -> Result<(), CqrsError> {
+        self.persist.health().await
     }
 
     async fn load_events_paged(
         &self,
-        aggregate_id: &str,
+        filter: &EventFilter,
         page: usize,
         page_size: usize,
+        context: &CqrsContext,
     ) -> Result<(Vec<EventEnvelope<A>>, i64), CqrsError> {
-        debug!("Loading paged events for aggregate");
+        debug!("Loading paged events");
         self.persist
-            .fetch_events_paged(aggregate_id, page, page_size)
+            .for_context(context)?
+            .fetch_events_paged(filter, page, page_size)
             .await
     }
 
+    /// The journal is read before the session is opened, and the snapshot saved only if
+    /// no event was committed in between.
+    async fn rebuild_snapshot(
+        &self,
+        aggregate_id: &str,
+        context: &CqrsContext,
+    ) -> Result<usize, CqrsError> {
+        debug!("Rebuilding snapshot from the journal");
+        let persist = self.persist.for_context(context)?;
+        let mut aggregate = A::default().with_aggregate_id(aggregate_id.to_string());
+        let mut version = 0;
+        let mut event_stream = persist.fetch_all_events(aggregate_id).await?;
+        while let Some(event) = event_stream.next().await {
+            let event = event?;
+            aggregate.apply(event.payload).map_err(CqrsError::user_error)?;
+            version = event.version;
+        }
+        if version == 0 {
+            return Err(CqrsError::aggregate_not_found(aggregate_id));
+        }
+
+        let mut session = persist.start_session().await?;
+        let latest_version = match persist.fetch_latest_event(&aggregate, &session).await {
+            Ok(event) => event.map(|e| e.version).unwrap_or(0),
+            Err(e) => {
+                let _ = persist.abort_session(session).await;
+                return Err(e);
+            }
+        };
+        if latest_version != version {
+            error!(latest_version = %latest_version, replayed_version = %version, "Version conflict detected");
+            let _ = persist.abort_session(session).await;
+            return Err(CqrsError::concurrency_error());
+        }
+        if let Err(e) = persist.save_snapshot(&aggregate, version, &mut session).await {
+            error!(error = %e, "Failed to save snapshot");
+            let _ = persist.abort_session(session).await;
+            return Err(e);
+        }
+        persist.close_session(session).await?;
+        info!(version = %version, "Snapshot rebuilt");
+        Ok(version)
+    }
+
     async fn commit(
         &self,
         events: Vec<A::Event>,
@@ -162,7 +231,8 @@ where
     ) -> Result<Vec<EventEnvelope<A>>, CqrsError> {
         debug!("Starting commit process");
 
-        let mut session = match self.persist.start_session().await {
+        let persist = self.persist.for_context(context)?;
+        let mut session = match persist.start_session().await {
             Ok(session) => {
                 debug!("Session started successfully");
                 session
@@ -173,14 +243,21 @@ where
             }
         };
 
-        let result = self
-            .execute_within_session(&mut session, events, aggregate, metadata, version, context)
-            .await;
+        let result = Self::execute_within_session(
+            &persist,
+            &mut session,
+            events,
+            aggregate,
+            metadata,
+            version,
+            context,
+        )
+        .await;
 
         match result {
             Ok(events) => {
                 debug!("Closing session");
-                if let Err(e) = self.persist.close_session(session).await {
+                if let Err(e) = persist.close_session(session).await {
                     error!(error = %e, "Failed to close session");
                     return Err(e);
                 }
@@ -189,7 +266,7 @@ where
             }
             Err(e) => {
                 error!(error = %e, "Error during commit, aborting session");
-                let _ = self.persist.abort_session(session).await;
+                let _ = persist.abort_session(session).await;
                 Err(e)
             }
         }
@@ -0,0 +1,501 @@
+use crate::errors::CqrsError;
+use crate::es::storage::{EventStoreStorage, EventStream};
+use crate::indexeddb::{
+    committed, js_error, key, parse_all, parse_one, ping, prefix_range, range_from, settle,
+};
+use crate::snapshot::Snapshot;
+use crate::{Aggregate, CqrsContext, EventEnvelope, EventFilter, Tenancy, TenantScope};
+use futures::stream;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{IdbCursorDirection, IdbCursorWithValue, IdbTransactionMode};
+
+pub use crate::indexeddb::{IndexedDb, IndexedDbSchema};
+
+/// A row waiting for its session to close.
+#[derive(Debug)]
+struct Pending {
+    store: String,
+    tenant: String,
+    aggregate_id: String,
+    json: String,
+}
+
+/// The writes of a commit, applied in one `readwrite` transaction when the session
+/// closes.
+///
+/// An IndexedDB transaction commits by itself once no request is pending on it, so it
+/// cannot stay open while the engine runs the command. The session buffers the rows
+/// instead, and a version another commit — another tab — wrote in the meantime fails
+/// the whole transaction.
+#[derive(Debug, Default)]
+pub struct IndexedDbSession {
+    events: Vec<(Pending, usize)>,
+    snapshots: Vec<Pending>,
+}
+
+/// A journal key: the tenant, the aggregate id and the version.
+fn journal_key(tenant: &str, aggregate_id: &str, version: usize) -> JsValue {
+    key(&[
+        JsValue::from_str(tenant),
+        JsValue::from_str(aggregate_id),
+        JsValue::from_f64(version as f64),
+    ])
+}
+
+/// A snapshot key: the tenant and the aggregate id.
+fn snapshot_key(tenant: &str, aggregate_id: &str) -> JsValue {
+    key(&[JsValue::from_str(tenant), JsValue::from_str(aggregate_id)])
+}
+
+/// Applies `session` in one transaction. A version already in the journal — another
+/// commit won the race — aborts it with a concurrency error, and nothing is written.
+async fn apply(database: &IndexedDb, session: &IndexedDbSession) -> Result<(), CqrsError> {
+    let mut stores: Vec<&str> = Vec::new();
+    for pending in session
+        .events
+        .iter()
+        .map(|(pending, _)| pending)
+        .chain(&session.snapshots)
+    {
+        if !stores.contains(&pending.store.as_str()) {
+            stores.push(&pending.store);
+        }
+    }
+    let transaction = database.transaction(&stores, IdbTransactionMode::Readwrite)?;
+    let queued = (|| {
+        for (event, version) in &session.events {
+            transaction.object_store(&event.store)?.add_with_key(
+                &JsValue::from_str(&event.json),
+                &journal_key(&event.tenant, &event.aggregate_id, *version),
+            )?;
+        }
+        for snapshot in &session.snapshots {
+            transaction.object_store(&snapshot.store)?.put_with_key(
+                &JsValue::from_str(&snapshot.json),
+                &snapshot_key(&snapshot.tenant, &snapshot.aggregate_id),
+            )?;
+        }
+        Ok::<_, JsValue>(())
+    })();
+    if let Err(e) = queued {
+        let _ = transaction.abort();
+        return Err(js_error(e));
+    }
+    committed(&transaction).await
+}
+
+/// An event store in the browser's IndexedDB, for offline-first web applications: the
+/// events of the user's commands are kept on the device, across reloads.
+///
+/// The journal and snapshot stores are named as in the other stores, `{TYPE}_journal`
+/// and `{TYPE}_snapshots`, and must be declared on the [`IndexedDbSchema`] the database
+/// is opened with — [`IndexedDbSchema::with_event_store`] declares both.
+///
+/// Only compiled for `wasm32`, where the futures need not be `Send`.
+#[derive(Clone, Debug)]
+pub struct IndexedDbPersist<A>
+where
+    A: Aggregate,
+{
+    _phantom: std::marker::PhantomData<A>,
+    database: IndexedDb,
+    snapshot_store_name: String,
+    journal_store_name: String,
+    scope: TenantScope,
+}
+
+impl<A> IndexedDbPersist<A>
+where
+    A: Aggregate,
+{
+    #[must_use]
+    pub fn new(database: IndexedDb) -> Self {
+        Self {
+            _phantom: std::marker::PhantomData,
+            database,
+            snapshot_store_name: format!("{}_snapshots", A::TYPE),
+            journal_store_name: format!("{}_journal", A::TYPE),
+            scope: TenantScope::default(),
+        }
+    }
+
+    /// Keeps each tenant's aggregates apart: keys led by the tenant in shared stores
+    /// ([`Tenancy::Column`]), or stores of their own ([`Tenancy::Table`]), which
+    /// [`IndexedDbSchema::with_tenant`] declares.
+    #[must_use]
+    pub fn with_tenancy(mut self, tenancy: Tenancy) -> Self {
+        self.scope = TenantScope::new(tenancy);
+        self
+    }
+
+    pub fn snapshot_store_name(&self) -> &str {
+        self.snapshot_store_name.as_str()
+    }
+    pub fn journal_store_name(&self) -> &str {
+        self.journal_store_name.as_str()
+    }
+
+    /// The tenant leading the keys: `""` unless under [`Tenancy::Column`].
+    fn tenant(&self) -> Result<&str, CqrsError> {
+        Ok(self.scope.column()?.unwrap_or_default())
+    }
+
+    /// The events of the keys in `range`, in key order.
+    async fn events_in(
+        &self,
+        range: web_sys::IdbKeyRange,
+    ) -> Result<Vec<EventEnvelope<A>>, CqrsError> {
+        let store = self.database.store(
+            &self.scope.table(&self.journal_store_name)?,
+            IdbTransactionMode::Readonly,
+        )?;
+        let request = store.get_all_with_key(&range).map_err(js_error)?;
+        parse_all(settle(&request).await?)
+    }
+
+    /// The events of `aggregate_id` after `version`, oldest first.
+    async fn events_after(
+        &self,
+        aggregate_id: &str,
+        version: usize,
+    ) -> Result<EventStream<A>, CqrsError>
+    where
+        A: 'static,
+    {
+        let tenant = JsValue::from_str(self.tenant()?);
+        let aggregate = JsValue::from_str(aggregate_id);
+        let range = range_from(
+            &[
+                tenant.clone(),
+                aggregate.clone(),
+                JsValue::from_f64(version as f64 + 1.0),
+            ],
+            &[tenant, aggregate],
+        )?;
+        let events = self.events_in(range).await?;
+        Ok(Box::pin(stream::iter(events.into_iter().map(Ok))))
+    }
+
+    fn pending(
+        &self,
+        store: &str,
+        aggregate_id: String,
+        json: String,
+    ) -> Result<Pending, CqrsError> {
+        Ok(Pending {
+            store: self.scope.table(store)?,
+            tenant: self.tenant()?.to_string(),
+            aggregate_id,
+            json,
+        })
+    }
+}
+
+cqrs_async_trait! {
+impl<A> EventStoreStorage<A> for IndexedDbPersist<A>
+where
+    A: Aggregate + 'static,
+{
+    type Session = IndexedDbSession;
+
+    fn for_context(&self, context: &CqrsContext) -> Result<Self, CqrsError> {
+        Ok(Self {
+            scope: self.scope.for_context(context)?,
+            ..self.clone()
+        })
+    }
+
+    async fn health(&self) -> Result<(), CqrsError> {
+        ping(&self.database)
+    }
+
+    async fn start_session(&self) -> Result<Self::Session, CqrsError> {
+        Ok(IndexedDbSession::default())
+    }
+
+    async fn close_session(&self, session: Self::Session) -> Result<(), CqrsError> {
+        if session.events.is_empty() && session.snapshots.is_empty() {
+            return Ok(());
+        }
+        apply(&self.database, &session).await
+    }
+
+    async fn abort_session(&self, _session: Self::Session) -> Result<(), CqrsError> {
+        Ok(())
+    }
+
+    async fn fetch_snapshot(&self, aggregate_id: &str) -> Result<Option<Snapshot<A>>, CqrsError> {
+        let store = self.database.store(
+            &self.scope.table(&self.snapshot_store_name)?,
+            IdbTransactionMode::Readonly,
+        )?;
+        let request = store
+            .get(&snapshot_key(self.tenant()?, aggregate_id))
+            .map_err(js_error)?;
+        parse_one(settle(&request).await?)
+    }
+
+    async fn fetch_events_from_version(
+        &self,
+        aggregate_id: &str,
+        version: usize,
+    ) -> Result<EventStream<A>, CqrsError> {
+        self.events_after(aggregate_id, version).await
+    }
+
+    async fn fetch_all_events(&self, aggregate_id: &str) -> Result<EventStream<A>, CqrsError> {
+        self.events_after(aggregate_id, 0).await
+    }
+
+    /// Reads the tenant's share of the journal — or the aggregate's, when the filter
+    /// names one — and filters, orders and pages it in memory: the journal has no index
+    /// on `at`.
+    async fn fetch_events_paged(
+        &self,
+        filter: &EventFilter,
+        page: usize,
+        page_size: usize,
+    ) -> Result<(Vec<EventEnvelope<A>>, i64), CqrsError> {
+        let mut prefix = vec![JsValue::from_str(self.tenant()?)];
+        if let Some(aggregate_id) = &filter.aggregate_id {
+            prefix.push(JsValue::from_str(aggregate_id));
+        }
+        let mut items = self.events_in(prefix_range(&prefix)?).await?;
+        filter.sort(&mut items);
+        items.retain(|envelope| filter.matches(envelope));
+        let total = items.len() as i64;
+        let offset = (page.max(1) - 1) * page_size;
+        let events: Vec<EventEnvelope<A>> =
+            items.into_iter().skip(offset).take(page_size).collect();
+        Ok((events, total))
+    }
+
+    /// The latest committed event, read off a cursor walking the aggregate's keys
+    /// backwards: the session's own writes are not applied yet, and `close_session`
+    /// settles a race when they are.
+    async fn fetch_latest_event(
+        &self,
+        aggregate: &A,
+        _session: &Self::Session,
+    ) -> Result<Option<EventEnvelope<A>>, CqrsError> {
+        let store = self.database.store(
+            &self.scope.table(&self.journal_store_name)?,
+            IdbTransactionMode::Readonly,
+        )?;
+        let range = prefix_range(&[
+            JsValue::from_str(self.tenant()?),
+            JsValue::from_str(&aggregate.aggregate_id()),
+        ])?;
+        let request = store
+            .open_cursor_with_range_and_direction(&range, IdbCursorDirection::Prev)
+            .map_err(js_error)?;
+        let cursor = settle(&request).await?;
+        if cursor.is_null() {
+            return Ok(None);
+        }
+        let value = cursor
+            .unchecked_into::<IdbCursorWithValue>()
+            .value()
+            .map_err(js_error)?;
+        parse_one(value)
+    }
+
+    async fn save_events(
+        &self,
+        events: Vec<EventEnvelope<A>>,
+        session: &mut Self::Session,
+    ) -> Result<(), CqrsError> {
+        for e in events {
+            let json = serde_json::to_string(&e).map_err(CqrsError::serialization_error)?;
+            let version = e.version;
+            let pending = self.pending(&self.journal_store_name, e.aggregate_id, json)?;
+            session.events.push((pending, version));
+        }
+        Ok(())
+    }
+
+    async fn save_snapshot(
+        &self,
+        aggregate: &A,
+        version: usize,
+        session: &mut Self::Session,
+    ) -> Result<(), CqrsError> {
+        let snapshot = Snapshot {
+            aggregate_id: aggregate.aggregate_id(),
+            state: aggregate.clone(),
+            version,
+        };
+        let json = serde_json::to_string(&snapshot).map_err(CqrsError::serialization_error)?;
+        let pending = self.pending(&self.snapshot_store_name, snapshot.aggregate_id, json)?;
+        session.snapshots.push(pending);
+        Ok(())
+    }
+}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{TestAggregate, TestEvent};
+    use chrono::Utc;
+    use futures::StreamExt;
+    use std::collections::HashMap;
+    use wasm_bindgen_test::{wasm_bindgen_test, wasm_bindgen_test_configure};
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    /// A database of its own per test: they share the origin.
+    async fn setup() -> IndexedDbPersist<TestAggregate> {
+        let name = format!("cqrs-test-{}", uuid::Uuid::new_v4());
+        let database = IndexedDbSchema::new(&name)
+            .with_event_store::<TestAggregate>()
+            .open()
+            .await
+            .unwrap();
+        IndexedDbPersist::new(database)
+    }
+
+    fn envelope(
+        aggregate_id: &str,
+        version: usize,
+        event: TestEvent,
+    ) -> EventEnvelope<TestAggregate> {
+        EventEnvelope {
+            event_id: format!("{}-v{}", aggregate_id, version),
+            aggregate_id: aggregate_id.to_string(),
+            version,
+            payload: event,
+            metadata: HashMap::new(),
+            at: Utc::now(),
+        }
+    }
+
+    async fn commit(
+        p: &IndexedDbPersist<TestAggregate>,
+        events: Vec<EventEnvelope<TestAggregate>>,
+    ) -> Result<(), CqrsError> {
+        let mut session = p.start_session().await.unwrap();
+        p.save_events(events, &mut session).await.unwrap();
+        p.close_session(session).await
+    }
+
+    async fn all_events(
+        p: &IndexedDbPersist<TestAggregate>,
+        aggregate_id: &str,
+    ) -> Vec<EventEnvelope<TestAggregate>> {
+        p.fetch_all_events(aggregate_id)
+            .await
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect()
+            .await
+    }
+
+    #[wasm_bindgen_test]
+    async fn a_committed_session_is_read_back_in_order() {
+        let p = setup().await;
+        commit(
+            &p,
+            vec![
+                envelope("a1", 1, TestEvent::Created { name: "foo".into() }),
+                envelope("a1", 2, TestEvent::Incremented),
+                envelope("a10", 1, TestEvent::Incremented),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let rows = all_events(&p, "a1").await;
+        assert_eq!(rows.len(), 2, "a1's range stops short of a10");
+        assert!(matches!(&rows[0].payload, TestEvent::Created { name } if name == "foo"));
+        let rows: Vec<_> = p
+            .fetch_events_from_version("a1", 1)
+            .await
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect()
+            .await;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].version, 2);
+
+        let agg = TestAggregate::default().with_aggregate_id("a1".to_string());
+        let latest = p
+            .fetch_latest_event(&agg, &IndexedDbSession::default())
+            .await;
+        assert_eq!(latest.unwrap().unwrap().version, 2);
+    }
+
+    #[wasm_bindgen_test]
+    async fn a_version_written_twice_is_a_concurrency_error_and_rolls_back_the_commit() {
+        let p = setup().await;
+        commit(&p, vec![envelope("a1", 1, TestEvent::Incremented)])
+            .await
+            .unwrap();
+
+        let err = commit(
+            &p,
+            vec![
+                envelope("a2", 1, TestEvent::Incremented),
+                envelope("a1", 1, TestEvent::Decremented),
+            ],
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.status, 409, "expected concurrency error, got: {err}");
+        assert!(all_events(&p, "a2").await.is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    async fn snapshots_are_upserted() {
+        let p = setup().await;
+        let mut agg = TestAggregate::default().with_aggregate_id("a1".to_string());
+        agg.apply(TestEvent::Created { name: "bar".into() })
+            .unwrap();
+        for version in [1, 5] {
+            let mut session = p.start_session().await.unwrap();
+            p.save_snapshot(&agg, version, &mut session).await.unwrap();
+            p.close_session(session).await.unwrap();
+        }
+
+        let snap = p.fetch_snapshot("a1").await.unwrap().unwrap();
+        assert_eq!(snap.version, 5);
+        assert!(p.fetch_snapshot("a2").await.unwrap().is_none());
+    }
+
+    #[wasm_bindgen_test]
+    async fn tenants_only_see_their_own_aggregates() {
+        let p = setup().await.with_tenancy(Tenancy::Column);
+        let tenant = |tenant: &str| {
+            p.for_context(&CqrsContext::default().with_tenant(Some(tenant.to_string())))
+                .unwrap()
+        };
+        let (acme, globex) = (tenant("acme"), tenant("globex"));
+        commit(&acme, vec![envelope("a1", 1, TestEvent::Incremented)])
+            .await
+            .unwrap();
+        commit(&globex, vec![envelope("a1", 1, TestEvent::Decremented)])
+            .await
+            .unwrap();
+
+        let rows = all_events(&acme, "a1").await;
+        assert_eq!(rows.len(), 1);
+        assert!(matches!(rows[0].payload, TestEvent::Incremented));
+        let (_, total) = globex
+            .fetch_events_paged(&EventFilter::default(), 1, 10)
+            .await
+            .unwrap();
+        assert_eq!(total, 1);
+    }
+
+    #[wasm_bindgen_test]
+    async fn a_store_missing_from_the_schema_is_named_in_the_error() {
+        let p = setup().await.with_tenancy(Tenancy::Table);
+        let acme = p
+            .for_context(&CqrsContext::default().with_tenant(Some("acme".to_string())))
+            .unwrap();
+        let err = commit(&acme, vec![envelope("a1", 1, TestEvent::Incremented)])
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("TEST_journal_acme"), "{err}");
+    }
+}
@@ -1,18 +1,24 @@
 use crate::es::storage::{EventStoreStorage, EventStream};
-use crate::{Aggregate, CqrsError, EventEnvelope, Snapshot};
+use crate::{
+    Aggregate, CqrsContext, CqrsError, EventEnvelope, EventFilter, Snapshot, Tenancy, TenantScope,
+};
 use futures::lock::{Mutex, OwnedMutexGuard};
 use futures::stream;
 use std::collections::HashMap;
 use std::sync::Arc;
 
+/// Snapshots, or journals, by tenant then aggregate id; the tenant is `""` when shared.
+type Partitions<T> = HashMap<String, HashMap<String, T>>;
+
 #[derive(Clone, Debug, Default)]
 pub struct InMemoryPersist<A>
 where
     A: Aggregate,
 {
     _phantom: std::marker::PhantomData<A>,
-    snapshot: Arc<Mutex<HashMap<String, Snapshot<A>>>>,
-    journal: Arc<Mutex<HashMap<String, Vec<EventEnvelope<A>>>>>,
+    snapshot: Arc<Mutex<Partitions<Snapshot<A>>>>,
+    journal: Arc<Mutex<Partitions<Vec<EventEnvelope<A>>>>>,
+    scope: TenantScope,
 }
 
 impl<A> InMemoryPersist<A>
@@ -23,6 +29,25 @@ where
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Keeps each tenant's aggregates apart. In memory, [`Tenancy::Column`] and
+    /// [`Tenancy::Table`] both partition the maps by tenant.
+    #[must_use]
+    pub fn with_tenancy(mut self, tenancy: Tenancy) -> Self {
+        self.scope = TenantScope::new(tenancy);
+        self
+    }
+
+    /// The events of `aggregate_id` in the tenant in scope.
+    async fn journal_of(&self, aggregate_id: &str) -> Result<Vec<EventEnvelope<A>>, CqrsError> {
+        let partition = self.scope.partition()?;
+        let journal = self.journal.lock().await;
+        Ok(journal
+            .get(partition)
+            .and_then(|journal| journal.get(aggregate_id))
+            .cloned()
+            .unwrap_or_default())
+    }
 }
 
 cqrs_async_trait! {
@@ -31,10 +56,17 @@ where
     A: Aggregate + 'static,
 {
     type Session = (
-        OwnedMutexGuard<HashMap<String, Snapshot<A>>>,
-        OwnedMutexGuard<HashMap<String, Vec<EventEnvelope<A>>>>,
+        OwnedMutexGuard<Partitions<Snapshot<A>>>,
+        OwnedMutexGuard<Partitions<Vec<EventEnvelope<A>>>>,
     );
 
+    fn for_context(&self, context: &CqrsContext) -> Result<Self, CqrsError> {
+        Ok(Self {
+            scope: self.scope.for_context(context)?,
+            ..self.clone()
+        })
+    }
+
     async fn start_session(&self) -> Result<Self::Session, CqrsError> {
         let journal = self.journal.clone().lock_owned().await;
         let snapshot = self.snapshot.clone().lock_owned().await;
@@ -46,8 +78,12 @@ where
     }
 
     async fn fetch_snapshot(&self, aggregate_id: &str) -> Result<Option<Snapshot<A>>, CqrsError> {
+        let partition = self.scope.partition()?;
         let snapshot = self.snapshot.lock().await;
-        Ok(snapshot.get(aggregate_id).cloned())
+        Ok(snapshot
+            .get(partition)
+            .and_then(|snapshot| snapshot.get(aggregate_id))
+            .cloned())
     }
 
     async fn fetch_events_from_version(
@@ -55,27 +91,37 @@ where
         aggregate_id: &str,
         version: usize,
     ) -> Result<EventStream<A>, CqrsError> {
-        let journal = self.journal.lock().await;
-        let items = journal.get(aggregate_id).cloned().unwrap_or_default();
+        let items = self.journal_of(aggregate_id).await?;
         let events: Vec<EventEnvelope<A>> =
             items.into_iter().filter(|v| v.version > version).collect();
         Ok(Box::pin(stream::iter(events.into_iter().map(Ok))))
     }
 
     async fn fetch_all_events(&self, aggregate_id: &str) -> Result<EventStream<A>, CqrsError> {
-        let journal = self.journal.lock().await;
-        let items = journal.get(aggregate_id).cloned().unwrap_or_default();
+        let items = self.journal_of(aggregate_id).await?;
         Ok(Box::pin(stream::iter(items.into_iter().map(Ok))))
     }
 
     async fn fetch_events_paged(
         &self,
-        aggregate_id: &str,
+        filter: &EventFilter,
         page: usize,
         page_size: usize,
     ) -> Result<(Vec<EventEnvelope<A>>, i64), CqrsError> {
-        let journal = self.journal.lock().await;
-        let items = journal.get(aggregate_id).cloned().unwrap_or_default();
+        let mut items: Vec<EventEnvelope<A>> = match &filter.aggregate_id {
+            Some(aggregate_id) => self.journal_of(aggregate_id).await?,
+            None => {
+                let partition = self.scope.partition()?;
+                let journal = self.journal.lock().await;
+                journal
+                    .get(partition)
+                    .into_iter()
+                    .flat_map(|journal| journal.values().flatten().cloned())
+                    .collect()
+            }
+        };
+        filter.sort(&mut items);
+        items.retain(|envelope| filter.matches(envelope));
         let total = items.len() as i64;
         let offset = (page.max(1) - 1) * page_size;
         let events: Vec<EventEnvelope<A>> =
@@ -88,12 +134,12 @@ where
         aggregate: &A,
         session: &Self::Session,
     ) -> Result<Option<EventEnvelope<A>>, CqrsError> {
-        let events = session
+        let partition = self.scope.partition()?;
+        Ok(session
             .1
-            .get(aggregate.aggregate_id().as_str())
-            .cloned()
-            .unwrap_or_default();
-        Ok(events.last().cloned())
+            .get(partition)
+            .and_then(|journal| journal.get(aggregate.aggregate_id().as_str()))
+            .and_then(|events| events.last().cloned()))
     }
 
     async fn save_events(
@@ -104,9 +150,12 @@ where
         if events.is_empty() {
             return Ok(());
         }
+        let partition = self.scope.partition()?;
         let aggregate_id = events.first().unwrap().aggregate_id.clone();
         session
             .1
+            .entry(partition.to_string())
+            .or_default()
             .entry(aggregate_id)
             .and_modify(|val| {
                 for e in events.iter() {
@@ -123,7 +172,8 @@ where
         version: usize,
         session: &mut Self::Session,
     ) -> Result<(), CqrsError> {
-        session.0.insert(
+        let partition = self.scope.partition()?;
+        session.0.entry(partition.to_string()).or_default().insert(
             aggregate.aggregate_id(),
             Snapshot {
                 aggregate_id: aggregate.aggregate_id(),
@@ -0,0 +1,437 @@
+//! The journals and snapshots of a database read and written as JSON, without knowing the
+//! aggregates they hold: what operational tools — the `cqrs-admin` binary among them —
+//! work on.
+//!
+//! An event is a [`RawEvent`], shaped as an [`EventEnvelope`](crate::EventEnvelope)
+//! serializes, and a snapshot a [`RawSnapshot`], shaped as a
+//! [`Snapshot`](crate::Snapshot) does. A dump is NDJSON: one [`RawRecord`] per line, the
+//! events, then the snapshots, then a [`DumpSummary`] counting them.
+
+use crate::{CqrsError, MaybeSend, MaybeSync};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{BufRead, Write};
+
+/// How many records an export reads, or an import writes, at a time.
+pub const DEFAULT_JOURNAL_BATCH_SIZE: usize = 500;
+
+/// An event as a journal holds it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawEvent {
+    #[serde(rename = "_id")]
+    pub event_id: String,
+    pub aggregate_id: String,
+    pub version: usize,
+    pub payload: JsonValue,
+    pub metadata: HashMap<String, String>,
+    pub at: DateTime<Utc>,
+    /// The journal's `event_type`, for the journals that keep one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_type: Option<String>,
+    /// The tenant under [`Tenancy::Column`](crate::Tenancy::Column).
+    #[serde(default, alias = "tenant_id", skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+}
+
+/// A snapshot as a snapshot table holds it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawSnapshot {
+    #[serde(rename = "_id")]
+    pub aggregate_id: String,
+    pub state: JsonValue,
+    pub version: usize,
+    #[serde(default, alias = "tenant_id", skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+}
+
+/// The last line of a dump: how many events and snapshots precede it, for an import to
+/// check that it read them all.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DumpSummary {
+    pub events: usize,
+    pub snapshots: usize,
+}
+
+impl DumpSummary {
+    /// Refuses a dump that holds other counts than `read`, as a truncated one does.
+    pub fn verify(&self, read: &DumpSummary) -> Result<(), CqrsError> {
+        if self == read {
+            Ok(())
+        } else {
+            Err(CqrsError::user_error(format!(
+                "the dump holds {} events and {} snapshots where its summary counts {} and {}",
+                read.events, read.snapshots, self.events, self.snapshots
+            )))
+        }
+    }
+}
+
+/// A line of a dump.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum RawRecord {
+    Event(RawEvent),
+    Snapshot(RawSnapshot),
+    Summary(DumpSummary),
+}
+
+/// The journal and snapshot tables — or collections — of an aggregate type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JournalTables {
+    pub journal: String,
+    pub snapshots: String,
+}
+
+impl JournalTables {
+    /// `{aggregate_type}_journal` and `{aggregate_type}_snapshots`, as every persist names
+    /// them.
+    pub fn of(aggregate_type: &str) -> Result<Self, CqrsError> {
+        let aggregate_type = identifier(aggregate_type)?;
+        Ok(Self {
+            journal: format!("{aggregate_type}_journal"),
+            snapshots: format!("{aggregate_type}_snapshots"),
+        })
+    }
+
+    /// The tables of `tenant` under [`Tenancy::Table`](crate::Tenancy::Table).
+    pub fn for_tenant(self, tenant: &str) -> Result<Self, CqrsError> {
+        let tenant = crate::validated_tenant(tenant)?;
+        Ok(Self {
+            journal: crate::tenant_table(&self.journal, tenant),
+            snapshots: crate::tenant_table(&self.snapshots, tenant),
+        })
+    }
+
+    /// The aggregate type of a journal table's name, if it is one.
+    pub fn aggregate_type(journal: &str) -> Option<&str> {
+        journal
+            .strip_suffix("_journal")
+            .filter(|aggregate_type| !aggregate_type.is_empty())
+    }
+}
+
+/// Refuses a name that cannot be spliced into a query as an identifier.
+fn identifier(name: &str) -> Result<&str, CqrsError> {
+    if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        Ok(name)
+    } else {
+        Err(CqrsError::user_error(format!(
+            "{name:?} may only hold ASCII letters, digits and '_'"
+        )))
+    }
+}
+
+cqrs_async_trait! {
+/// Every journal of a database, read and written as JSON.
+///
+/// Reads are not scoped to a tenant: under [`Tenancy::Column`](crate::Tenancy::Column)
+/// they return every tenant's records, each carrying its `tenant_id`, and writes keep it.
+pub trait Journals: MaybeSend + MaybeSync {
+    /// The aggregate types that have a journal, in order.
+    async fn aggregate_types(&self) -> Result<Vec<String>, CqrsError>;
+
+    /// The events of `aggregate_id`, by version.
+    async fn journal(
+        &self,
+        tables: &JournalTables,
+        aggregate_id: &str,
+    ) -> Result<Vec<RawEvent>, CqrsError>;
+
+    /// The snapshots of `aggregate_id`: one, or one per tenant under a tenant column.
+    async fn snapshot(
+        &self,
+        tables: &JournalTables,
+        aggregate_id: &str,
+    ) -> Result<Vec<RawSnapshot>, CqrsError>;
+
+    /// `limit` events after the first `skip`, ordered by tenant, aggregate and version.
+    async fn events(
+        &self,
+        tables: &JournalTables,
+        skip: usize,
+        limit: usize,
+    ) -> Result<Vec<RawEvent>, CqrsError>;
+
+    /// `limit` snapshots after the first `skip`, ordered by tenant and aggregate.
+    async fn snapshots(
+        &self,
+        tables: &JournalTables,
+        skip: usize,
+        limit: usize,
+    ) -> Result<Vec<RawSnapshot>, CqrsError>;
+
+    /// Writes `events` as they are, ids, versions and timestamps included, all or none.
+    async fn import_events(
+        &self,
+        tables: &JournalTables,
+        events: Vec<RawEvent>,
+    ) -> Result<(), CqrsError>;
+
+    /// Writes `snapshots`, replacing those of the same aggregates.
+    async fn import_snapshots(
+        &self,
+        tables: &JournalTables,
+        snapshots: Vec<RawSnapshot>,
+    ) -> Result<(), CqrsError>;
+}
+}
+
+/// A break in the versions of an aggregate's journal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContinuityIssue {
+    pub tenant_id: Option<String>,
+    pub aggregate_id: String,
+    /// The version that should have come next.
+    pub expected: usize,
+    pub found: usize,
+}
+
+impl fmt::Display for ContinuityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(tenant) = &self.tenant_id {
+            write!(f, "tenant {tenant}, ")?;
+        }
+        let problem = if self.found < self.expected {
+            "a duplicate"
+        } else {
+            "a gap"
+        };
+        write!(
+            f,
+            "aggregate {}: version {} where {} was expected ({problem})",
+            self.aggregate_id, self.found, self.expected
+        )
+    }
+}
+
+/// Checks that each aggregate's versions run from 1 with neither gap nor duplicate, over
+/// events fed in the order of [`Journals::events`].
+#[derive(Debug, Default)]
+pub struct ContinuityCheck {
+    current: Option<(Option<String>, String, usize)>,
+    aggregates: usize,
+    events: usize,
+    issues: Vec<ContinuityIssue>,
+}
+
+impl ContinuityCheck {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, event: &RawEvent) {
+        self.events += 1;
+        let expected = match &self.current {
+            Some((tenant, aggregate_id, last))
+                if *tenant == event.tenant_id && *aggregate_id == event.aggregate_id =>
+            {
+                last + 1
+            }
+            _ => {
+                self.aggregates += 1;
+                1
+            }
+        };
+        if event.version != expected {
+            self.issues.push(ContinuityIssue {
+                tenant_id: event.tenant_id.clone(),
+                aggregate_id: event.aggregate_id.clone(),
+                expected,
+                found: event.version,
+            });
+        }
+        self.current = Some((
+            event.tenant_id.clone(),
+            event.aggregate_id.clone(),
+            event.version.max(expected - 1),
+        ));
+    }
+
+    /// How many aggregates, and events, were checked.
+    pub fn checked(&self) -> (usize, usize) {
+        (self.aggregates, self.events)
+    }
+
+    pub fn issues(&self) -> &[ContinuityIssue] {
+        &self.issues
+    }
+}
+
+/// Writes every event, then every snapshot, of `tables` to `output` as NDJSON, then their
+/// [`DumpSummary`], and returns how many of each.
+pub async fn export_ndjson<J, W>(
+    journals: &J,
+    tables: &JournalTables,
+    batch_size: usize,
+    output: &mut W,
+) -> Result<(usize, usize), CqrsError>
+where
+    J: Journals + ?Sized,
+    W: Write,
+{
+    let batch_size = batch_size.max(1);
+    let mut write = |record: RawRecord| -> Result<(), CqrsError> {
+        serde_json::to_writer(&mut *output, &record).map_err(CqrsError::serialization_error)?;
+        output
+            .write_all(b"\n")
+            .map_err(|e| CqrsError::internal(e.to_string()))
+    };
+    let mut events = 0;
+    loop {
+        let page = journals.events(tables, events, batch_size).await?;
+        let read = page.len();
+        events += read;
+        page.into_iter()
+            .map(RawRecord::Event)
+            .try_for_each(&mut write)?;
+        if read < batch_size {
+            break;
+        }
+    }
+    let mut snapshots = 0;
+    loop {
+        let page = journals.snapshots(tables, snapshots, batch_size).await?;
+        let read = page.len();
+        snapshots += read;
+        page.into_iter()
+            .map(RawRecord::Snapshot)
+            .try_for_each(&mut write)?;
+        if read < batch_size {
+            break;
+        }
+    }
+    write(RawRecord::Summary(DumpSummary { events, snapshots }))?;
+    Ok((events, snapshots))
+}
+
+/// Writes the NDJSON records of `input` into `tables`, `batch_size` at a time, and
+/// returns how many events and snapshots it wrote. Blank lines are skipped, and the
+/// records are checked against each [`DumpSummary`] met; a dump without one is trusted.
+pub async fn import_ndjson<J, R>(
+    journals: &J,
+    tables: &JournalTables,
+    batch_size: usize,
+    input: R,
+) -> Result<(usize, usize), CqrsError>
+where
+    J: Journals + ?Sized,
+    R: BufRead,
+{
+    let batch_size = batch_size.max(1);
+    let (mut events, mut snapshots) = (Vec::new(), Vec::new());
+    let (mut event_count, mut snapshot_count) = (0, 0);
+    let mut read = DumpSummary::default();
+    for (number, line) in input.lines().enumerate() {
+        let line = line.map_err(|e| CqrsError::internal(e.to_string()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(RawRecord::Event(event)) => {
+                read.events += 1;
+                events.push(event);
+            }
+            Ok(RawRecord::Snapshot(snapshot)) => {
+                read.snapshots += 1;
+                snapshots.push(snapshot);
+            }
+            // Dumps may be concatenated: each summary counts the records since the last.
+            Ok(RawRecord::Summary(summary)) => summary.verify(&std::mem::take(&mut read))?,
+            Err(e) => {
+                return Err(CqrsError::user_error(format!("line {}: {e}", number + 1)));
+            }
+        }
+        if events.len() >= batch_size {
+            event_count += events.len();
+            journals
+                .import_events(tables, std::mem::take(&mut events))
+                .await?;
+        }
+        if snapshots.len() >= batch_size {
+            snapshot_count += snapshots.len();
+            journals
+                .import_snapshots(tables, std::mem::take(&mut snapshots))
+                .await?;
+        }
+    }
+    event_count += events.len();
+    snapshot_count += snapshots.len();
+    if !events.is_empty() {
+        journals.import_events(tables, events).await?;
+    }
+    if !snapshots.is_empty() {
+        journals.import_snapshots(tables, snapshots).await?;
+    }
+    Ok((event_count, snapshot_count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(aggregate_id: &str, version: usize) -> RawEvent {
+        RawEvent {
+            event_id: format!("{aggregate_id}-{version}"),
+            aggregate_id: aggregate_id.to_string(),
+            version,
+            payload: JsonValue::Null,
+            metadata: HashMap::new(),
+            at: Utc::now(),
+            event_type: None,
+            tenant_id: None,
+        }
+    }
+
+    #[test]
+    fn gaps_and_duplicates_are_reported_per_aggregate() {
+        let mut check = ContinuityCheck::new();
+        for (aggregate_id, version) in [("a", 1), ("a", 2), ("a", 4), ("a", 4), ("b", 2), ("c", 1)]
+        {
+            check.push(&event(aggregate_id, version));
+        }
+        assert_eq!(check.checked(), (3, 6));
+        let issues: Vec<_> = check.issues().iter().map(ToString::to_string).collect();
+        assert_eq!(
+            issues,
+            [
+                "aggregate a: version 4 where 3 was expected (a gap)",
+                "aggregate a: version 4 where 5 was expected (a duplicate)",
+                "aggregate b: version 2 where 1 was expected (a gap)",
+            ]
+        );
+    }
+
+    #[test]
+    fn records_keep_the_shape_of_envelopes_and_snapshots() {
+        let line = r#"{"type":"event","_id":"e1","aggregateId":"a","version":1,"payload":{"Created":{}},"metadata":{},"at":"2026-03-01T12:00:00Z","tenant_id":"acme"}"#;
+        let RawRecord::Event(event) = serde_json::from_str(line).unwrap() else {
+            panic!("an event");
+        };
+        assert_eq!(event.tenant_id.as_deref(), Some("acme"));
+        let written = serde_json::to_value(RawRecord::Event(event)).unwrap();
+        assert_eq!(written["tenantId"], "acme");
+        assert_eq!(written["_id"], "e1");
+
+        assert!(JournalTables::of("account").is_ok());
+        assert!(JournalTables::of("account; DROP TABLE x").is_err());
+        assert_eq!(
+            JournalTables::of("account")
+                .unwrap()
+                .for_tenant("acme")
+                .unwrap()
+                .journal,
+            "account_journal_acme"
+        );
+        assert_eq!(
+            JournalTables::aggregate_type("account_journal"),
+            Some("account")
+        );
+        assert_eq!(JournalTables::aggregate_type("account_snapshots"), None);
+    }
+}
@@ -1,11 +1,20 @@
 mod r#impl;
+#[cfg(all(feature = "indexeddb", target_arch = "wasm32"))]
+pub mod indexeddb;
 pub mod inmemory;
+pub mod journals;
 #[cfg(feature = "mongodb")]
 pub mod mongodb;
+pub mod per_tenant;
 #[cfg(feature = "postgres")]
 pub mod postgres;
+#[cfg(feature = "redb")]
+pub mod redb;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
 #[cfg(feature = "surrealdb")]
 pub mod surrealdb;
+pub mod transfer;
 
 pub mod storage;
 pub use r#impl::*;
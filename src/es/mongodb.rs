@@ -1,15 +1,68 @@
 use crate::errors::CqrsError;
+use crate::es::journals::{JournalTables, Journals, RawEvent, RawSnapshot};
 use crate::es::storage::{EventStoreStorage, EventStream};
 use crate::snapshot::Snapshot;
-use crate::{Aggregate, EventEnvelope};
+use crate::{
+    Aggregate, CqrsContext, Event, EventEnvelope, EventFilter, TENANT_COLUMN, Tenancy, TenantScope,
+    USER_ID_METADATA,
+};
 use futures::{StreamExt, TryStreamExt};
-use mongodb::bson::doc;
+use mongodb::bson::{DateTime as BsonDateTime, Document, doc, serialize_to_document};
 use mongodb::{ClientSession, Database};
 
 fn map_mongo_error(e: mongodb::error::Error) -> CqrsError {
     CqrsError::database_error(e)
 }
 
+/// The journal documents `filter` selects, before tenant scoping.
+fn filter_document(filter: &EventFilter) -> Document {
+    let mut document = Document::new();
+    if let Some(aggregate_id) = &filter.aggregate_id {
+        document.insert("aggregateId", aggregate_id);
+    }
+    if !filter.event_types.is_empty() {
+        document.insert("eventType", doc! { "$in": &filter.event_types });
+    }
+    if let Some(user_id) = &filter.user_id {
+        document.insert(format!("metadata.{USER_ID_METADATA}"), user_id);
+    }
+    // `at` is stored as RFC 3339 text, whose fractional digits vary, so it does not sort
+    // as text: it is compared as a date, to the millisecond.
+    let at = doc! { "$toDate": "$at" };
+    let mut bounds = Vec::new();
+    if let Some(from) = &filter.from {
+        bounds.push(doc! { "$gte": [&at, BsonDateTime::from_millis(from.timestamp_millis())] });
+    }
+    if let Some(to) = &filter.to {
+        bounds.push(doc! { "$lt": [&at, BsonDateTime::from_millis(to.timestamp_millis())] });
+    }
+    if !bounds.is_empty() {
+        document.insert("$expr", doc! { "$and": bounds });
+    }
+    document
+}
+
+/// The sort of a page of `filter`'s selection, as [`EventFilter::sort`] orders it.
+fn page_sort(filter: &EventFilter) -> Document {
+    if filter.aggregate_id.is_some() {
+        doc! {"version": 1}
+    } else {
+        doc! {"at": 1, "aggregateId": 1, "version": 1}
+    }
+}
+
+fn ddl(journal_collection: &str, tenant_column: bool) -> String {
+    let key = if tenant_column {
+        format!("{TENANT_COLUMN}: 1, ")
+    } else {
+        String::new()
+    };
+    format!(
+        r#"db.getCollection("{journal_collection}").createIndex({{ {key}aggregateId: 1, version: 1 }}, {{ unique: true }});
+db.getCollection("{journal_collection}").createIndex({{ {key}aggregateId: 1 }});"#
+    )
+}
+
 #[derive(Clone, Debug)]
 pub struct MongoDBPersist<A>
 where
@@ -19,6 +72,7 @@ where
     database: Database,
     snapshot_collection_name: String,
     journal_collection_name: String,
+    scope: TenantScope,
 }
 
 impl<A> MongoDBPersist<A>
@@ -32,9 +86,44 @@ where
             database,
             snapshot_collection_name: format!("{}_snapshots", A::TYPE),
             journal_collection_name: format!("{}_journal", A::TYPE),
+            scope: TenantScope::default(),
         }
     }
 
+    /// Keeps each tenant's aggregates apart: a `tenant_id` field on every document
+    /// ([`Tenancy::Column`]), or collections of their own ([`Tenancy::Table`]).
+    ///
+    /// Under [`Tenancy::Column`] a snapshot's `_id` is still the aggregate id, so ids must
+    /// be unique across tenants — the default UUIDs are. Committing an id another tenant
+    /// already uses fails on the duplicate key, without reading or touching the other
+    /// tenant's aggregate; prefer [`Tenancy::Table`] when ids are chosen by the caller.
+    #[must_use]
+    pub fn with_tenancy(mut self, tenancy: Tenancy) -> Self {
+        self.scope = TenantScope::new(tenancy);
+        self
+    }
+
+    /// The `mongosh` statements creating the journal's indexes: a unique one on
+    /// `(aggregateId, version)`, which turns a concurrent commit of the same version into
+    /// a duplicate key error, and one on `aggregateId`.
+    pub fn schema() -> String {
+        ddl(&format!("{}_journal", A::TYPE), false)
+    }
+
+    /// [`Self::schema`] under [`Tenancy::Column`]: the indexes lead with `tenant_id`.
+    pub fn tenant_column_schema() -> String {
+        ddl(&format!("{}_journal", A::TYPE), true)
+    }
+
+    /// [`Self::schema`] for one tenant under [`Tenancy::Table`].
+    pub fn tenant_table_schema(tenant: &str) -> Result<String, CqrsError> {
+        let tenant = crate::validated_tenant(tenant)?;
+        Ok(ddl(
+            &crate::tenant_table(&format!("{}_journal", A::TYPE), tenant),
+            false,
+        ))
+    }
+
     pub fn snapshot_collection_name(&self) -> &str {
         self.snapshot_collection_name.as_str()
     }
@@ -42,33 +131,49 @@ where
         self.journal_collection_name.as_str()
     }
 
-    fn snapshot_collection(
+    fn collection<T: Send + Sync>(
         &self,
+        name: &str,
         session: Option<&ClientSession>,
-    ) -> mongodb::Collection<Snapshot<A>> {
-        if let Some(session) = session {
+    ) -> Result<mongodb::Collection<T>, CqrsError> {
+        let name = self.scope.table(name)?;
+        Ok(if let Some(session) = session {
             session
                 .client()
                 .database(self.database.name())
-                .collection(self.snapshot_collection_name.as_str())
+                .collection(&name)
         } else {
-            self.database
-                .collection(self.snapshot_collection_name.as_str())
-        }
+            self.database.collection(&name)
+        })
+    }
+
+    fn snapshot_collection(
+        &self,
+        session: Option<&ClientSession>,
+    ) -> Result<mongodb::Collection<Snapshot<A>>, CqrsError> {
+        self.collection(&self.snapshot_collection_name, session)
     }
+
+    /// The journal of the tenant in scope.
     pub fn journal_collection(
         &self,
         session: Option<&ClientSession>,
-    ) -> mongodb::Collection<EventEnvelope<A>> {
-        if let Some(session) = session {
-            session
-                .client()
-                .database(self.database.name())
-                .collection(self.journal_collection_name.as_str())
-        } else {
-            self.database
-                .collection(self.journal_collection_name.as_str())
+    ) -> Result<mongodb::Collection<EventEnvelope<A>>, CqrsError> {
+        self.collection(&self.journal_collection_name, session)
+    }
+
+    /// `filter`, narrowed to the tenant in scope under [`Tenancy::Column`].
+    fn scoped(&self, mut filter: Document) -> Result<Document, CqrsError> {
+        if let Some(tenant) = self.scope.column()? {
+            filter.insert(TENANT_COLUMN, tenant);
         }
+        Ok(filter)
+    }
+
+    /// A document to write, carrying the tenant in scope under [`Tenancy::Column`].
+    fn stamped<T: serde::Serialize>(&self, value: &T) -> Result<Document, CqrsError> {
+        let document = serialize_to_document(value).map_err(CqrsError::serialization_error)?;
+        self.scoped(document)
     }
 }
 
@@ -78,6 +183,22 @@ where
     A: Aggregate + 'static,
 {
     type Session = ClientSession;
+
+    fn for_context(&self, context: &CqrsContext) -> Result<Self, CqrsError> {
+        Ok(Self {
+            scope: self.scope.for_context(context)?,
+            ..self.clone()
+        })
+    }
+
+    async fn health(&self) -> Result<(), CqrsError> {
+        self.database
+            .run_command(doc! {"ping": 1})
+            .await
+            .map(|_| ())
+            .map_err(map_mongo_error)
+    }
+
     async fn start_session(&self) -> Result<Self::Session, CqrsError> {
         let mut session = self
             .database
@@ -94,8 +215,8 @@ where
     }
 
     async fn fetch_snapshot(&self, aggregate_id: &str) -> Result<Option<Snapshot<A>>, CqrsError> {
-        self.snapshot_collection(None)
-            .find_one(doc! { "_id": aggregate_id})
+        self.snapshot_collection(None)?
+            .find_one(self.scoped(doc! { "_id": aggregate_id})?)
             .await
             .map_err(map_mongo_error)
     }
@@ -106,8 +227,10 @@ where
         version: usize,
     ) -> Result<EventStream<A>, CqrsError> {
         let cursor = self
-            .journal_collection(None)
-            .find(doc! {"aggregateId": aggregate_id, "version": {"$gt": version as i64}})
+            .journal_collection(None)?
+            .find(self.scoped(
+                doc! {"aggregateId": aggregate_id, "version": {"$gt": version as i64}},
+            )?)
             .await
             .map_err(map_mongo_error)?;
 
@@ -118,8 +241,8 @@ where
 
     async fn fetch_all_events(&self, aggregate_id: &str) -> Result<EventStream<A>, CqrsError> {
         let cursor = self
-            .journal_collection(None)
-            .find(doc! {"aggregateId": aggregate_id})
+            .journal_collection(None)?
+            .find(self.scoped(doc! {"aggregateId": aggregate_id})?)
             .await
             .map_err(map_mongo_error)?;
 
@@ -130,22 +253,25 @@ where
 
     async fn fetch_events_paged(
         &self,
-        aggregate_id: &str,
+        filter: &EventFilter,
         page: usize,
         page_size: usize,
     ) -> Result<(Vec<EventEnvelope<A>>, i64), CqrsError> {
+        let sort = page_sort(filter);
+        let filter = self.scoped(filter_document(filter))?;
         // Get total count
         let total = self
-            .journal_collection(None)
-            .count_documents(doc! {"aggregateId": aggregate_id})
+            .journal_collection(None)?
+            .count_documents(filter.clone())
             .await
             .map_err(map_mongo_error)?;
 
         // Get paginated events
         let offset = ((page.max(1) - 1) * page_size) as u64;
         let mut cursor = self
-            .journal_collection(None)
-            .find(doc! {"aggregateId": aggregate_id})
+            .journal_collection(None)?
+            .find(filter)
+            .sort(sort)
             .skip(offset)
             .limit(page_size as i64)
             .await
@@ -164,8 +290,8 @@ where
         aggregate: &A,
         session: &Self::Session,
     ) -> Result<Option<EventEnvelope<A>>, CqrsError> {
-        self.journal_collection(Some(session))
-            .find_one(doc! {"aggregateId": aggregate.aggregate_id()})
+        self.journal_collection(Some(session))?
+            .find_one(self.scoped(doc! {"aggregateId": aggregate.aggregate_id()})?)
             .sort(doc! {"version": -1})
             .await
             .map_err(map_mongo_error)
@@ -176,9 +302,18 @@ where
         events: Vec<EventEnvelope<A>>,
         session: &mut Self::Session,
     ) -> Result<(), CqrsError> {
+        let documents = events
+            .iter()
+            .map(|event| {
+                let mut document = self.stamped(event)?;
+                // Not part of the envelope; written for audit queries to filter on.
+                document.insert("eventType", event.payload.event_type());
+                Ok(document)
+            })
+            .collect::<Result<Vec<_>, CqrsError>>()?;
         let _r = self
-            .journal_collection(Some(session))
-            .insert_many(&events)
+            .collection::<Document>(&self.journal_collection_name, Some(session))?
+            .insert_many(documents)
             .await
             .map_err(map_mongo_error)?;
         Ok(())
@@ -190,15 +325,13 @@ where
         version: usize,
         session: &mut Self::Session,
     ) -> Result<(), CqrsError> {
-        self.snapshot_collection(Some(session))
-            .find_one_and_replace(
-                doc! {"_id": aggregate.aggregate_id()},
-                Snapshot::<A> {
-                    aggregate_id: aggregate.aggregate_id(),
-                    state: aggregate.clone(),
-                    version,
-                },
-            )
+        let snapshot = self.stamped(&Snapshot::<A> {
+            aggregate_id: aggregate.aggregate_id(),
+            state: aggregate.clone(),
+            version,
+        })?;
+        self.collection::<Document>(&self.snapshot_collection_name, Some(session))?
+            .find_one_and_replace(self.scoped(doc! {"_id": aggregate.aggregate_id()})?, snapshot)
             .upsert(true)
             .await
             .map_err(map_mongo_error)?;
@@ -210,3 +343,260 @@ where
     }
 }
 }
+
+/// Every journal of a MongoDB database, for operational tools; see
+/// [`Journals`](crate::es::journals::Journals).
+#[derive(Clone, Debug)]
+pub struct MongoDBJournals {
+    database: Database,
+}
+
+impl MongoDBJournals {
+    #[must_use]
+    pub fn new(database: Database) -> Self {
+        Self { database }
+    }
+
+    /// The indexes of [`MongoDBPersist::schema`] for `tables`, led by the tenant when
+    /// `tenant_column`.
+    pub fn schema(tables: &JournalTables, tenant_column: bool) -> String {
+        ddl(&tables.journal, tenant_column)
+    }
+
+    /// `value` as a document, its tenant under the name the persist gives it.
+    fn document<T: serde::Serialize>(value: &T) -> Result<Document, CqrsError> {
+        let mut document = serialize_to_document(value).map_err(CqrsError::serialization_error)?;
+        if let Some(tenant) = document.remove("tenantId") {
+            document.insert(TENANT_COLUMN, tenant);
+        }
+        Ok(document)
+    }
+}
+
+cqrs_async_trait! {
+impl Journals for MongoDBJournals {
+    async fn aggregate_types(&self) -> Result<Vec<String>, CqrsError> {
+        let mut names = self
+            .database
+            .list_collection_names()
+            .await
+            .map_err(map_mongo_error)?;
+        names.sort();
+        Ok(names
+            .iter()
+            .filter_map(|name| JournalTables::aggregate_type(name).map(str::to_string))
+            .collect())
+    }
+
+    async fn journal(
+        &self,
+        tables: &JournalTables,
+        aggregate_id: &str,
+    ) -> Result<Vec<RawEvent>, CqrsError> {
+        self.database
+            .collection::<RawEvent>(&tables.journal)
+            .find(doc! {"aggregateId": aggregate_id})
+            .sort(doc! {TENANT_COLUMN: 1, "version": 1})
+            .await
+            .map_err(map_mongo_error)?
+            .try_collect()
+            .await
+            .map_err(map_mongo_error)
+    }
+
+    async fn snapshot(
+        &self,
+        tables: &JournalTables,
+        aggregate_id: &str,
+    ) -> Result<Vec<RawSnapshot>, CqrsError> {
+        self.database
+            .collection::<RawSnapshot>(&tables.snapshots)
+            .find(doc! {"_id": aggregate_id})
+            .sort(doc! {TENANT_COLUMN: 1})
+            .await
+            .map_err(map_mongo_error)?
+            .try_collect()
+            .await
+            .map_err(map_mongo_error)
+    }
+
+    async fn events(
+        &self,
+        tables: &JournalTables,
+        skip: usize,
+        limit: usize,
+    ) -> Result<Vec<RawEvent>, CqrsError> {
+        self.database
+            .collection::<RawEvent>(&tables.journal)
+            .find(doc! {})
+            .sort(doc! {TENANT_COLUMN: 1, "aggregateId": 1, "version": 1})
+            .skip(skip as u64)
+            .limit(limit as i64)
+            .await
+            .map_err(map_mongo_error)?
+            .try_collect()
+            .await
+            .map_err(map_mongo_error)
+    }
+
+    async fn snapshots(
+        &self,
+        tables: &JournalTables,
+        skip: usize,
+        limit: usize,
+    ) -> Result<Vec<RawSnapshot>, CqrsError> {
+        self.database
+            .collection::<RawSnapshot>(&tables.snapshots)
+            .find(doc! {})
+            .sort(doc! {TENANT_COLUMN: 1, "_id": 1})
+            .skip(skip as u64)
+            .limit(limit as i64)
+            .await
+            .map_err(map_mongo_error)?
+            .try_collect()
+            .await
+            .map_err(map_mongo_error)
+    }
+
+    async fn import_events(
+        &self,
+        tables: &JournalTables,
+        events: Vec<RawEvent>,
+    ) -> Result<(), CqrsError> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        let documents = events
+            .iter()
+            .map(Self::document)
+            .collect::<Result<Vec<_>, CqrsError>>()?;
+        let mut session = self
+            .database
+            .client()
+            .start_session()
+            .await
+            .map_err(map_mongo_error)?;
+        session.start_transaction().await.map_err(map_mongo_error)?;
+        let inserted = self
+            .database
+            .collection::<Document>(&tables.journal)
+            .insert_many(documents)
+            .session(&mut session)
+            .await;
+        match inserted {
+            Ok(_) => session.commit_transaction().await.map_err(map_mongo_error),
+            Err(e) => {
+                let _ = session.abort_transaction().await;
+                Err(map_mongo_error(e))
+            }
+        }
+    }
+
+    async fn import_snapshots(
+        &self,
+        tables: &JournalTables,
+        snapshots: Vec<RawSnapshot>,
+    ) -> Result<(), CqrsError> {
+        let collection = self.database.collection::<Document>(&tables.snapshots);
+        for snapshot in &snapshots {
+            let mut filter = doc! {"_id": &snapshot.aggregate_id};
+            if let Some(tenant) = &snapshot.tenant_id {
+                filter.insert(TENANT_COLUMN, tenant);
+            }
+            collection
+                .find_one_and_replace(filter, Self::document(snapshot)?)
+                .upsert(true)
+                .await
+                .map_err(map_mongo_error)?;
+        }
+        Ok(())
+    }
+}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn each_filter_field_selects_on_its_own_key() {
+        let event_types = EventFilter {
+            event_types: vec!["Created".to_string(), "Renamed".to_string()],
+            ..EventFilter::default()
+        };
+        assert_eq!(
+            filter_document(&event_types),
+            doc! { "eventType": { "$in": ["Created", "Renamed"] } }
+        );
+        let user = EventFilter {
+            user_id: Some("ada".to_string()),
+            ..EventFilter::default()
+        };
+        assert_eq!(filter_document(&user), doc! { "metadata.user_id": "ada" });
+        assert_eq!(filter_document(&EventFilter::default()), Document::new());
+    }
+
+    #[test]
+    fn a_time_window_compares_at_as_a_date() {
+        let from = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let to = Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap();
+        let window = EventFilter {
+            from: Some(from),
+            to: Some(to),
+            ..EventFilter::default()
+        };
+        assert_eq!(
+            filter_document(&window),
+            doc! { "$expr": { "$and": [
+                { "$gte": [{ "$toDate": "$at" }, BsonDateTime::from_millis(from.timestamp_millis())] },
+                { "$lt": [{ "$toDate": "$at" }, BsonDateTime::from_millis(to.timestamp_millis())] },
+            ] } }
+        );
+        let from_only = EventFilter {
+            from: Some(from),
+            ..EventFilter::default()
+        };
+        assert_eq!(
+            filter_document(&from_only),
+            doc! { "$expr": { "$and": [
+                { "$gte": [{ "$toDate": "$at" }, BsonDateTime::from_millis(from.timestamp_millis())] },
+            ] } }
+        );
+    }
+
+    #[test]
+    fn a_combined_filter_selects_on_every_key() {
+        let to = Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap();
+        let filter = EventFilter {
+            aggregate_id: Some("a1".to_string()),
+            event_types: vec!["Created".to_string()],
+            user_id: Some("ada".to_string()),
+            from: None,
+            to: Some(to),
+        };
+        assert_eq!(
+            filter_document(&filter),
+            doc! {
+                "aggregateId": "a1",
+                "eventType": { "$in": ["Created"] },
+                "metadata.user_id": "ada",
+                "$expr": { "$and": [
+                    { "$lt": [{ "$toDate": "$at" }, BsonDateTime::from_millis(to.timestamp_millis())] },
+                ] },
+            }
+        );
+    }
+
+    #[test]
+    fn one_aggregate_pages_by_version_and_the_type_by_time() {
+        assert_eq!(
+            page_sort(&EventFilter::aggregate("a1")),
+            doc! { "version": 1 }
+        );
+        assert_eq!(
+            page_sort(&EventFilter::default()),
+            doc! { "at": 1, "aggregateId": 1, "version": 1 }
+        );
+    }
+}
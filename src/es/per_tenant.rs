@@ -0,0 +1,237 @@
+use crate::es::storage::{EventStoreStorage, EventStream};
+use crate::{Aggregate, CqrsContext, CqrsError, EventEnvelope, EventFilter, Snapshot};
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// A storage per tenant — a database, or a whole server — picked from the tenant of the
+/// [`CqrsContext`] of each load and commit.
+///
+/// A context without a tenant, or with one that was not registered, is refused with
+/// `403`. Each storage is itself scoped to the context once picked, so one of them may
+/// in turn be shared by several tenants under a [`Tenancy`](crate::Tenancy).
+///
+/// ```rust
+/// # use cqrs_rust_lib::es::inmemory::InMemoryPersist;
+/// # use cqrs_rust_lib::es::per_tenant::PerTenantPersist;
+/// # fn persist<A: cqrs_rust_lib::Aggregate>() -> PerTenantPersist<A, InMemoryPersist<A>> {
+/// PerTenantPersist::new()
+///     .with_tenant("acme", InMemoryPersist::new())
+///     .with_tenant("globex", InMemoryPersist::new())
+/// # }
+/// ```
+pub struct PerTenantPersist<A, P> {
+    _phantom: PhantomData<A>,
+    tenants: Arc<BTreeMap<String, P>>,
+    current: Option<P>,
+}
+
+impl<A, P> Clone for PerTenantPersist<A, P>
+where
+    P: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            _phantom: PhantomData,
+            tenants: self.tenants.clone(),
+            current: self.current.clone(),
+        }
+    }
+}
+
+impl<A, P> std::fmt::Debug for PerTenantPersist<A, P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PerTenantPersist")
+            .field("tenants", &self.tenants.keys().collect::<Vec<_>>())
+            .field("resolved", &self.current.is_some())
+            .finish()
+    }
+}
+
+impl<A, P> Default for PerTenantPersist<A, P> {
+    fn default() -> Self {
+        Self {
+            _phantom: PhantomData,
+            tenants: Arc::new(BTreeMap::new()),
+            current: None,
+        }
+    }
+}
+
+impl<A, P> PerTenantPersist<A, P>
+where
+    P: Clone,
+{
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the storage of `tenant`, replacing any it had.
+    #[must_use]
+    pub fn with_tenant(mut self, tenant: impl Into<String>, persist: P) -> Self {
+        Arc::make_mut(&mut self.tenants).insert(tenant.into(), persist);
+        self
+    }
+
+    /// The registered tenants, in order.
+    pub fn tenants(&self) -> impl Iterator<Item = &str> {
+        self.tenants.keys().map(String::as_str)
+    }
+
+    fn current(&self) -> Result<&P, CqrsError> {
+        self.current
+            .as_ref()
+            .ok_or_else(|| CqrsError::internal("the storage was not scoped to a tenant"))
+    }
+}
+
+cqrs_async_trait! {
+impl<A, P> EventStoreStorage<A> for PerTenantPersist<A, P>
+where
+    A: Aggregate + 'static,
+    P: EventStoreStorage<A> + Clone + crate::MaybeSend + crate::MaybeSync,
+{
+    type Session = P::Session;
+
+    fn for_context(&self, context: &CqrsContext) -> Result<Self, CqrsError> {
+        let tenant = context
+            .tenant()
+            .filter(|tenant| !tenant.is_empty())
+            .ok_or_else(|| CqrsError::forbidden("a tenant is required"))?;
+        let persist = self
+            .tenants
+            .get(tenant)
+            .ok_or_else(|| CqrsError::forbidden(format!("unknown tenant {tenant:?}")))?;
+        Ok(Self {
+            current: Some(persist.for_context(context)?),
+            ..self.clone()
+        })
+    }
+
+    /// Every tenant's storage, whether or not this one is scoped.
+    async fn health(&self) -> Result<(), CqrsError> {
+        for (tenant, persist) in self.tenants.iter() {
+            persist.health().await.map_err(|e| {
+                CqrsError::internal(format!("tenant {tenant:?}: {}", e.message))
+            })?;
+        }
+        Ok(())
+    }
+
+    async fn start_session(&self) -> Result<Self::Session, CqrsError> {
+        self.current()?.start_session().await
+    }
+
+    async fn close_session(&self, session: Self::Session) -> Result<(), CqrsError> {
+        self.current()?.close_session(session).await
+    }
+
+    async fn fetch_snapshot(&self, aggregate_id: &str) -> Result<Option<Snapshot<A>>, CqrsError> {
+        self.current()?.fetch_snapshot(aggregate_id).await
+    }
+
+    async fn fetch_events_from_version(
+        &self,
+        aggregate_id: &str,
+        version: usize,
+    ) -> Result<EventStream<A>, CqrsError> {
+        self.current()?
+            .fetch_events_from_version(aggregate_id, version)
+            .await
+    }
+
+    async fn fetch_all_events(&self, aggregate_id: &str) -> Result<EventStream<A>, CqrsError> {
+        self.current()?.fetch_all_events(aggregate_id).await
+    }
+
+    async fn fetch_events_paged(
+        &self,
+        filter: &EventFilter,
+        page: usize,
+        page_size: usize,
+    ) -> Result<(Vec<EventEnvelope<A>>, i64), CqrsError> {
+        self.current()?
+            .fetch_events_paged(filter, page, page_size)
+            .await
+    }
+
+    async fn fetch_latest_event(
+        &self,
+        aggregate: &A,
+        session: &Self::Session,
+    ) -> Result<Option<EventEnvelope<A>>, CqrsError> {
+        self.current()?.fetch_latest_event(aggregate, session).await
+    }
+
+    async fn save_events(
+        &self,
+        events: Vec<EventEnvelope<A>>,
+        session: &mut Self::Session,
+    ) -> Result<(), CqrsError> {
+        self.current()?.save_events(events, session).await
+    }
+
+    async fn save_snapshot(
+        &self,
+        aggregate: &A,
+        version: usize,
+        session: &mut Self::Session,
+    ) -> Result<(), CqrsError> {
+        self.current()?.save_snapshot(aggregate, version, session).await
+    }
+
+    async fn abort_session(&self, session: Self::Session) -> Result<(), CqrsError> {
+        self.current()?.abort_session(session).await
+    }
+}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CqrsCommandEngine;
+    use crate::es::EventStoreImpl;
+    use crate::es::inmemory::InMemoryPersist;
+    use crate::testing::{CreateCommand, TestAggregate};
+    use crate::EventStore;
+
+    #[tokio::test]
+    async fn each_tenant_reads_and_writes_its_own_storage() {
+        let persist = PerTenantPersist::new()
+            .with_tenant("acme", InMemoryPersist::<TestAggregate>::new())
+            .with_tenant("globex", InMemoryPersist::new());
+        let store = EventStoreImpl::new(persist);
+        let engine = CqrsCommandEngine::new(store.clone(), vec![], (), Box::new(|_e| {}));
+        let tenant = |tenant: &str| CqrsContext::default().with_tenant(Some(tenant.to_string()));
+
+        let aggregate_id = engine
+            .execute_create(
+                CreateCommand::Initialize {
+                    name: "toto".to_string(),
+                },
+                &tenant("acme"),
+            )
+            .await
+            .expect("Creation should succeed");
+
+        assert!(
+            store
+                .load_aggregate(&aggregate_id, &tenant("acme"))
+                .await
+                .is_ok()
+        );
+        let error = store
+            .load_aggregate(&aggregate_id, &tenant("globex"))
+            .await
+            .unwrap_err();
+        assert_eq!(error.status, 404);
+        for context in [tenant("initech"), CqrsContext::default()] {
+            let error = store
+                .load_aggregate(&aggregate_id, &context)
+                .await
+                .unwrap_err();
+            assert_eq!(error.status, 403);
+        }
+    }
+}
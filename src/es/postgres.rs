@@ -1,12 +1,17 @@
 use crate::errors::CqrsError;
+use crate::es::journals::{JournalTables, Journals, RawEvent, RawSnapshot};
 use crate::es::storage::{EventStoreStorage, EventStream};
 use crate::snapshot::Snapshot;
-use crate::{Aggregate, EventEnvelope};
+use crate::{
+    Aggregate, CqrsContext, Event, EventEnvelope, EventFilter, TENANT_COLUMN, Tenancy, TenantScope,
+    USER_ID_METADATA,
+};
 use futures::stream;
 use serde_json::Value as JsonValue;
 use std::fmt::Debug;
 use std::sync::Arc;
 use tokio_postgres::Client;
+use tokio_postgres::types::ToSql;
 
 fn map_pg_error<E: std::error::Error + Send + Sync + 'static>(e: E) -> CqrsError {
     CqrsError::database_error(e)
@@ -41,6 +46,108 @@ impl<C: PgConn + 'static> Drop for PgSession<C> {
 unsafe impl<C: PgConn + Send + 'static> Send for PgSession<C> {}
 unsafe impl<C: PgConn + Sync + 'static> Sync for PgSession<C> {}
 
+/// Narrows a query to `tenant` under [`Tenancy::Column`], binding it as the next
+/// parameter; an empty condition otherwise.
+fn tenant_condition<'a>(
+    tenant: &'a Option<&'a str>,
+    params: &mut Vec<&'a (dyn ToSql + Sync)>,
+) -> String {
+    match tenant {
+        Some(tenant) => {
+            params.push(tenant);
+            format!(" AND {TENANT_COLUMN} = ${}", params.len())
+        }
+        None => String::new(),
+    }
+}
+
+/// The conditions of `filter`, each binding the next parameters.
+fn filter_conditions<'a>(
+    filter: &'a EventFilter,
+    params: &mut Vec<&'a (dyn ToSql + Sync)>,
+) -> String {
+    let mut bind = |param: &'a (dyn ToSql + Sync)| {
+        params.push(param);
+        format!("${}", params.len())
+    };
+    let mut conditions = Vec::new();
+    if let Some(aggregate_id) = &filter.aggregate_id {
+        conditions.push(format!("aggregate_id = {}", bind(aggregate_id)));
+    }
+    if !filter.event_types.is_empty() {
+        conditions.push(format!("event_type = ANY({})", bind(&filter.event_types)));
+    }
+    if let Some(user_id) = &filter.user_id {
+        conditions.push(format!(
+            "metadata->>'{USER_ID_METADATA}' = {}",
+            bind(user_id)
+        ));
+    }
+    if let Some(from) = &filter.from {
+        conditions.push(format!("at >= {}", bind(from)));
+    }
+    if let Some(to) = &filter.to {
+        conditions.push(format!("at < {}", bind(to)));
+    }
+    conditions
+        .into_iter()
+        .map(|condition| format!(" AND {condition}"))
+        .collect()
+}
+
+/// The `ORDER BY` of a page of `filter`'s selection, as [`EventFilter::sort`] orders it.
+fn page_order(filter: &EventFilter) -> &'static str {
+    if filter.aggregate_id.is_some() {
+        "version ASC"
+    } else {
+        "at ASC, aggregate_id ASC, version ASC"
+    }
+}
+
+/// The journal and snapshot tables, with a `tenant_id` column leading their keys when
+/// `tenant_column`. The journal's `event_type`, which audit queries filter on, is added
+/// to a journal created before it; it stays `NULL` on the events already there.
+fn ddl(snapshot_table: &str, journal_table: &str, tenant_column: bool) -> String {
+    let (column, key) = if tenant_column {
+        (
+            format!("\n    {TENANT_COLUMN} TEXT NOT NULL,"),
+            format!("{TENANT_COLUMN}, "),
+        )
+    } else {
+        (String::new(), String::new())
+    };
+    let snapshot_key = if tenant_column {
+        format!(",\n    PRIMARY KEY({key}aggregate_id)")
+    } else {
+        String::new()
+    };
+    let aggregate_id = if tenant_column {
+        "aggregate_id TEXT NOT NULL"
+    } else {
+        "aggregate_id TEXT PRIMARY KEY"
+    };
+    format!(
+        r#"CREATE TABLE IF NOT EXISTS {snapshot_table} ({column}
+    {aggregate_id},
+    data JSONB NOT NULL,
+    version BIGINT NOT NULL{snapshot_key}
+);
+CREATE TABLE IF NOT EXISTS {journal_table} (
+    event_id TEXT PRIMARY KEY,{column}
+    aggregate_id TEXT NOT NULL,
+    version BIGINT NOT NULL,
+    payload JSONB NOT NULL,
+    metadata JSONB NOT NULL,
+    at TIMESTAMPTZ NOT NULL,
+    event_type TEXT,
+    UNIQUE({key}aggregate_id, version)
+);
+ALTER TABLE {journal_table} ADD COLUMN IF NOT EXISTS event_type TEXT;
+CREATE INDEX IF NOT EXISTS idx_{journal_table}_agg_ver ON {journal_table}({key}aggregate_id, version);
+CREATE INDEX IF NOT EXISTS idx_{journal_table}_at ON {journal_table}({key}at);"#
+    )
+}
+
 #[derive(Clone, Debug)]
 pub struct PostgresPersist<A, P = SharedClient>
 where
@@ -51,6 +158,7 @@ where
     pool: P,
     snapshot_table_name: String,
     journal_table_name: String,
+    scope: TenantScope,
 }
 
 impl<A> PostgresPersist<A, SharedClient>
@@ -82,9 +190,19 @@ where
             pool,
             snapshot_table_name: format!("{}_snapshots", A::TYPE),
             journal_table_name: format!("{}_journal", A::TYPE),
+            scope: TenantScope::default(),
         }
     }
 
+    /// Keeps each tenant's aggregates apart: a `tenant_id` column in shared tables
+    /// ([`Tenancy::Column`], see [`Self::tenant_column_schema`]), or tables of their own
+    /// ([`Tenancy::Table`], see [`Self::tenant_table_schema`]).
+    #[must_use]
+    pub fn with_tenancy(mut self, tenancy: Tenancy) -> Self {
+        self.scope = TenantScope::new(tenancy);
+        self
+    }
+
     pub fn snapshot_table_name(&self) -> &str {
         self.snapshot_table_name.as_str()
     }
@@ -95,26 +213,43 @@ where
     /// Returns the DDL statements to create the journal and snapshot tables,
     /// including a `UNIQUE(aggregate_id, version)` constraint on the journal.
     pub fn schema() -> String {
-        let snapshot_table = format!("{}_snapshots", A::TYPE);
-        let journal_table = format!("{}_journal", A::TYPE);
-        format!(
-            r#"CREATE TABLE IF NOT EXISTS {snapshot_table} (
-    aggregate_id TEXT PRIMARY KEY,
-    data JSONB NOT NULL,
-    version BIGINT NOT NULL
-);
-CREATE TABLE IF NOT EXISTS {journal_table} (
-    event_id TEXT PRIMARY KEY,
-    aggregate_id TEXT NOT NULL,
-    version BIGINT NOT NULL,
-    payload JSONB NOT NULL,
-    metadata JSONB NOT NULL,
-    at TIMESTAMPTZ NOT NULL,
-    UNIQUE(aggregate_id, version)
-);
-CREATE INDEX IF NOT EXISTS idx_{journal_table}_agg_ver ON {journal_table}(aggregate_id, version);"#
+        ddl(
+            &format!("{}_snapshots", A::TYPE),
+            &format!("{}_journal", A::TYPE),
+            false,
         )
     }
+
+    /// [`Self::schema`] under [`Tenancy::Column`]: both tables carry a `tenant_id`, which
+    /// leads the snapshot key and the journal's `UNIQUE` constraint, so an aggregate id
+    /// is only unique within its tenant.
+    pub fn tenant_column_schema() -> String {
+        ddl(
+            &format!("{}_snapshots", A::TYPE),
+            &format!("{}_journal", A::TYPE),
+            true,
+        )
+    }
+
+    /// [`Self::schema`] for one tenant under [`Tenancy::Table`]: its own
+    /// `{TYPE}_snapshots_{tenant}` and `{TYPE}_journal_{tenant}`. Run it when a tenant is
+    /// onboarded.
+    pub fn tenant_table_schema(tenant: &str) -> Result<String, CqrsError> {
+        let tenant = crate::validated_tenant(tenant)?;
+        Ok(ddl(
+            &crate::tenant_table(&format!("{}_snapshots", A::TYPE), tenant),
+            &crate::tenant_table(&format!("{}_journal", A::TYPE), tenant),
+            false,
+        ))
+    }
+
+    fn snapshot_table(&self) -> Result<String, CqrsError> {
+        self.scope.table(&self.snapshot_table_name)
+    }
+
+    fn journal_table(&self) -> Result<String, CqrsError> {
+        self.scope.table(&self.journal_table_name)
+    }
 }
 
 cqrs_async_trait! {
@@ -125,6 +260,17 @@ where
 {
     type Session = PgSession<P::Connection>;
 
+    fn for_context(&self, context: &CqrsContext) -> Result<Self, CqrsError> {
+        Ok(Self {
+            scope: self.scope.for_context(context)?,
+            ..self.clone()
+        })
+    }
+
+    async fn health(&self) -> Result<(), CqrsError> {
+        crate::pg::ping(&self.pool).await
+    }
+
     async fn start_session(&self) -> Result<Self::Session, CqrsError> {
         let connection = self.pool.acquire().await?;
         connection
@@ -159,14 +305,17 @@ where
     }
 
     async fn fetch_snapshot(&self, aggregate_id: &str) -> Result<Option<Snapshot<A>>, CqrsError> {
-        let conn = self.pool.acquire().await?;
+        let tenant = self.scope.column()?;
+        let mut params: Vec<&(dyn ToSql + Sync)> = vec![&aggregate_id];
         let sql = format!(
-            "SELECT data, version FROM {} WHERE aggregate_id = $1",
-            self.snapshot_table_name
+            "SELECT data, version FROM {} WHERE aggregate_id = $1{}",
+            self.snapshot_table()?,
+            tenant_condition(&tenant, &mut params)
         );
+        let conn = self.pool.acquire().await?;
         let row_opt = conn
             .client()
-            .query_opt(&sql, &[&aggregate_id])
+            .query_opt(&sql, &params)
             .await
             .map_err(map_pg_error)?;
         if let Some(row) = row_opt {
@@ -188,14 +337,18 @@ where
         aggregate_id: &str,
         version: usize,
     ) -> Result<EventStream<A>, CqrsError> {
-        let conn = self.pool.acquire().await?;
+        let tenant = self.scope.column()?;
+        let version = version as i64;
+        let mut params: Vec<&(dyn ToSql + Sync)> = vec![&aggregate_id, &version];
         let sql = format!(
-            "SELECT event_id, aggregate_id, version, payload, metadata, at FROM {} WHERE aggregate_id = $1 AND version > $2 ORDER BY version ASC",
-            self.journal_table_name
+            "SELECT event_id, aggregate_id, version, payload, metadata, at FROM {} WHERE aggregate_id = $1 AND version > $2{} ORDER BY version ASC",
+            self.journal_table()?,
+            tenant_condition(&tenant, &mut params)
         );
+        let conn = self.pool.acquire().await?;
         let rows = conn
             .client()
-            .query(&sql, &[&aggregate_id, &(version as i64)])
+            .query(&sql, &params)
             .await
             .map_err(map_pg_error)?;
 
@@ -224,14 +377,17 @@ where
     }
 
     async fn fetch_all_events(&self, aggregate_id: &str) -> Result<EventStream<A>, CqrsError> {
-        let conn = self.pool.acquire().await?;
+        let tenant = self.scope.column()?;
+        let mut params: Vec<&(dyn ToSql + Sync)> = vec![&aggregate_id];
         let sql = format!(
-            "SELECT event_id, aggregate_id, version, payload, metadata, at FROM {} WHERE aggregate_id = $1 ORDER BY version ASC",
-            self.journal_table_name
+            "SELECT event_id, aggregate_id, version, payload, metadata, at FROM {} WHERE aggregate_id = $1{} ORDER BY version ASC",
+            self.journal_table()?,
+            tenant_condition(&tenant, &mut params)
         );
+        let conn = self.pool.acquire().await?;
         let rows = conn
             .client()
-            .query(&sql, &[&aggregate_id])
+            .query(&sql, &params)
             .await
             .map_err(map_pg_error)?;
 
@@ -261,32 +417,44 @@ where
 
     async fn fetch_events_paged(
         &self,
-        aggregate_id: &str,
+        filter: &EventFilter,
         page: usize,
         page_size: usize,
     ) -> Result<(Vec<EventEnvelope<A>>, i64), CqrsError> {
+        let tenant = self.scope.column()?;
+        let journal_table = self.journal_table()?;
+        let mut params: Vec<&(dyn ToSql + Sync)> = Vec::new();
+        let condition = format!(
+            "TRUE{}{}",
+            filter_conditions(filter, &mut params),
+            tenant_condition(&tenant, &mut params)
+        );
         let conn = self.pool.acquire().await?;
         // Get total count
-        let count_sql = format!(
-            "SELECT COUNT(*) FROM {} WHERE aggregate_id = $1",
-            self.journal_table_name
-        );
+        let count_sql = format!("SELECT COUNT(*) FROM {journal_table} WHERE {condition}");
         let count_row = conn
             .client()
-            .query_one(&count_sql, &[&aggregate_id])
+            .query_one(&count_sql, &params)
             .await
             .map_err(map_pg_error)?;
         let total: i64 = count_row.try_get(0).map_err(map_pg_error)?;
 
         // Get paginated events
         let offset = ((page.max(1) - 1) * page_size) as i64;
+        let limit = page_size as i64;
+        params.push(&limit);
+        params.push(&offset);
         let sql = format!(
-            "SELECT event_id, aggregate_id, version, payload, metadata, at FROM {} WHERE aggregate_id = $1 ORDER BY version ASC LIMIT $2 OFFSET $3",
-            self.journal_table_name
+            "SELECT event_id, aggregate_id, version, payload, metadata, at FROM {} WHERE {} ORDER BY {} LIMIT ${} OFFSET ${}",
+            journal_table,
+            condition,
+            page_order(filter),
+            params.len() - 1,
+            params.len()
         );
         let rows = conn
             .client()
-            .query(&sql, &[&aggregate_id, &(page_size as i64), &offset])
+            .query(&sql, &params)
             .await
             .map_err(map_pg_error)?;
 
@@ -319,13 +487,17 @@ where
         aggregate: &A,
         session: &Self::Session,
     ) -> Result<Option<EventEnvelope<A>>, CqrsError> {
+        let tenant = self.scope.column()?;
+        let aggregate_id = aggregate.aggregate_id();
+        let mut params: Vec<&(dyn ToSql + Sync)> = vec![&aggregate_id];
         let sql = format!(
-            "SELECT event_id, aggregate_id, version, payload, metadata, at FROM {} WHERE aggregate_id = $1 ORDER BY version DESC LIMIT 1 FOR UPDATE",
-            self.journal_table_name
+            "SELECT event_id, aggregate_id, version, payload, metadata, at FROM {} WHERE aggregate_id = $1{} ORDER BY version DESC LIMIT 1 FOR UPDATE",
+            self.journal_table()?,
+            tenant_condition(&tenant, &mut params)
         );
         let row_opt = session
             .client()
-            .query_opt(&sql, &[&aggregate.aggregate_id()])
+            .query_opt(&sql, &params)
             .await
             .map_err(map_pg_error)?;
         if let Some(row) = row_opt {
@@ -355,28 +527,37 @@ where
         if events.is_empty() {
             return Ok(());
         }
+        let tenant = self.scope.column()?;
+        let (column, placeholder) = match tenant {
+            Some(_) => (format!(", {TENANT_COLUMN}"), ",$8"),
+            None => (String::new(), ""),
+        };
         let sql = format!(
-            "INSERT INTO {} (event_id, aggregate_id, version, payload, metadata, at) VALUES ($1,$2,$3,$4,$5,$6)",
-            self.journal_table_name
+            "INSERT INTO {} (event_id, aggregate_id, version, payload, metadata, at, event_type{column}) VALUES ($1,$2,$3,$4,$5,$6,$7{placeholder})",
+            self.journal_table()?
         );
         for e in events.iter() {
             let payload =
                 serde_json::to_value(&e.payload).map_err(CqrsError::serialization_error)?;
             let metadata =
                 serde_json::to_value(&e.metadata).map_err(CqrsError::serialization_error)?;
+            let version = e.version as i64;
+            let event_type = e.payload.event_type();
+            let mut params: Vec<&(dyn ToSql + Sync)> = vec![
+                &e.event_id,
+                &e.aggregate_id,
+                &version,
+                &payload,
+                &metadata,
+                &e.at,
+                &event_type,
+            ];
+            if let Some(tenant) = &tenant {
+                params.push(tenant);
+            }
             session
                 .client()
-                .execute(
-                    &sql,
-                    &[
-                        &e.event_id,
-                        &e.aggregate_id,
-                        &(e.version as i64),
-                        &payload,
-                        &metadata,
-                        &e.at,
-                    ],
-                )
+                .execute(&sql, &params)
                 .await
                 .map_err(map_pg_error)?;
         }
@@ -390,17 +571,367 @@ where
         session: &mut Self::Session,
     ) -> Result<(), CqrsError> {
         let data = serde_json::to_value(aggregate).map_err(CqrsError::serialization_error)?;
+        let tenant = self.scope.column()?;
+        let aggregate_id = aggregate.aggregate_id();
+        let version = version as i64;
+        let mut params: Vec<&(dyn ToSql + Sync)> = vec![&aggregate_id, &data, &version];
+        let (column, placeholder, key) = match &tenant {
+            Some(tenant) => {
+                params.push(tenant);
+                (
+                    format!(", {TENANT_COLUMN}"),
+                    ", $4",
+                    format!("{TENANT_COLUMN}, aggregate_id"),
+                )
+            }
+            None => (String::new(), "", "aggregate_id".to_string()),
+        };
         let sql = format!(
-            "INSERT INTO {} (aggregate_id, data, version) VALUES ($1, $2, $3) \
-             ON CONFLICT (aggregate_id) DO UPDATE SET data = EXCLUDED.data, version = EXCLUDED.version",
-            self.snapshot_table_name
+            "INSERT INTO {} (aggregate_id, data, version{column}) VALUES ($1, $2, $3{placeholder}) \
+             ON CONFLICT ({key}) DO UPDATE SET data = EXCLUDED.data, version = EXCLUDED.version",
+            self.snapshot_table()?
         );
         session
             .client()
-            .execute(&sql, &[&aggregate.aggregate_id(), &data, &(version as i64)])
+            .execute(&sql, &params)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(())
+    }
+}
+}
+
+/// Every journal of a PostgreSQL schema, for operational tools; see
+/// [`Journals`](crate::es::journals::Journals).
+#[derive(Clone, Debug)]
+pub struct PostgresJournals<P = SharedClient>
+where
+    P: PgPool,
+{
+    pool: P,
+}
+
+impl<P> PostgresJournals<P>
+where
+    P: PgPool,
+{
+    #[must_use]
+    pub fn new(pool: P) -> Self {
+        Self { pool }
+    }
+
+    /// The DDL of [`PostgresPersist::schema`] for `tables`, with a tenant column when
+    /// `tenant_column`.
+    pub fn schema(tables: &JournalTables, tenant_column: bool) -> String {
+        ddl(&tables.snapshots, &tables.journal, tenant_column)
+    }
+
+    async fn rows<T: serde::de::DeserializeOwned>(
+        &self,
+        sql: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Vec<T>, CqrsError> {
+        let conn = self.pool.acquire().await?;
+        conn.client()
+            .query(sql, params)
+            .await
+            .map_err(map_pg_error)?
+            .into_iter()
+            .map(|row| {
+                let row: JsonValue = row.try_get(0).map_err(map_pg_error)?;
+                serde_json::from_value(row).map_err(CqrsError::serialization_error)
+            })
+            .collect()
+    }
+}
+
+/// A journal row, as `to_jsonb` renders it.
+#[derive(serde::Deserialize)]
+struct RawJournalRow {
+    event_id: String,
+    aggregate_id: String,
+    version: usize,
+    payload: JsonValue,
+    metadata: std::collections::HashMap<String, String>,
+    at: chrono::DateTime<chrono::Utc>,
+    #[serde(default)]
+    event_type: Option<String>,
+    #[serde(default)]
+    tenant_id: Option<String>,
+}
+
+impl From<RawJournalRow> for RawEvent {
+    fn from(row: RawJournalRow) -> Self {
+        RawEvent {
+            event_id: row.event_id,
+            aggregate_id: row.aggregate_id,
+            version: row.version,
+            payload: row.payload,
+            metadata: row.metadata,
+            at: row.at,
+            event_type: row.event_type,
+            tenant_id: row.tenant_id,
+        }
+    }
+}
+
+/// A snapshot row, as `to_jsonb` renders it.
+#[derive(serde::Deserialize)]
+struct RawSnapshotRow {
+    aggregate_id: String,
+    data: JsonValue,
+    version: usize,
+    #[serde(default)]
+    tenant_id: Option<String>,
+}
+
+impl From<RawSnapshotRow> for RawSnapshot {
+    fn from(row: RawSnapshotRow) -> Self {
+        RawSnapshot {
+            aggregate_id: row.aggregate_id,
+            state: row.data,
+            version: row.version,
+            tenant_id: row.tenant_id,
+        }
+    }
+}
+
+// The tenant column may not exist: read through `to_jsonb`, where it is only absent.
+const BY_TENANT: &str = "to_jsonb(r)->>'tenant_id' NULLS FIRST";
+
+cqrs_async_trait! {
+impl<P> Journals for PostgresJournals<P>
+where
+    P: PgPool,
+{
+    async fn aggregate_types(&self) -> Result<Vec<String>, CqrsError> {
+        let conn = self.pool.acquire().await?;
+        let rows = conn
+            .client()
+            .query(
+                "SELECT table_name::TEXT FROM information_schema.tables \
+                 WHERE table_schema = current_schema() ORDER BY table_name",
+                &[],
+            )
             .await
             .map_err(map_pg_error)?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| row.try_get::<_, String>(0).ok())
+            .filter_map(|table| JournalTables::aggregate_type(&table).map(str::to_string))
+            .collect())
+    }
+
+    async fn journal(
+        &self,
+        tables: &JournalTables,
+        aggregate_id: &str,
+    ) -> Result<Vec<RawEvent>, CqrsError> {
+        let sql = format!(
+            "SELECT to_jsonb(r) FROM {} r WHERE aggregate_id = $1 ORDER BY {BY_TENANT}, version",
+            tables.journal
+        );
+        let rows: Vec<RawJournalRow> = self.rows(&sql, &[&aggregate_id]).await?;
+        Ok(rows.into_iter().map(RawEvent::from).collect())
+    }
+
+    async fn snapshot(
+        &self,
+        tables: &JournalTables,
+        aggregate_id: &str,
+    ) -> Result<Vec<RawSnapshot>, CqrsError> {
+        let sql = format!(
+            "SELECT to_jsonb(r) FROM {} r WHERE aggregate_id = $1 ORDER BY {BY_TENANT}",
+            tables.snapshots
+        );
+        let rows: Vec<RawSnapshotRow> = self.rows(&sql, &[&aggregate_id]).await?;
+        Ok(rows.into_iter().map(RawSnapshot::from).collect())
+    }
+
+    async fn events(
+        &self,
+        tables: &JournalTables,
+        skip: usize,
+        limit: usize,
+    ) -> Result<Vec<RawEvent>, CqrsError> {
+        let (skip, limit) = (skip as i64, limit as i64);
+        let sql = format!(
+            "SELECT to_jsonb(r) FROM {} r ORDER BY {BY_TENANT}, aggregate_id, version OFFSET $1 LIMIT $2",
+            tables.journal
+        );
+        let rows: Vec<RawJournalRow> = self.rows(&sql, &[&skip, &limit]).await?;
+        Ok(rows.into_iter().map(RawEvent::from).collect())
+    }
+
+    async fn snapshots(
+        &self,
+        tables: &JournalTables,
+        skip: usize,
+        limit: usize,
+    ) -> Result<Vec<RawSnapshot>, CqrsError> {
+        let (skip, limit) = (skip as i64, limit as i64);
+        let sql = format!(
+            "SELECT to_jsonb(r) FROM {} r ORDER BY {BY_TENANT}, aggregate_id OFFSET $1 LIMIT $2",
+            tables.snapshots
+        );
+        let rows: Vec<RawSnapshotRow> = self.rows(&sql, &[&skip, &limit]).await?;
+        Ok(rows.into_iter().map(RawSnapshot::from).collect())
+    }
+
+    async fn import_events(
+        &self,
+        tables: &JournalTables,
+        events: Vec<RawEvent>,
+    ) -> Result<(), CqrsError> {
+        let conn = self.pool.acquire().await?;
+        let client = conn.client();
+        client.batch_execute("BEGIN").await.map_err(map_pg_error)?;
+        let mut result = Ok(());
+        for event in &events {
+            let (column, placeholder) = match &event.tenant_id {
+                Some(_) => (format!(", {TENANT_COLUMN}"), ",$8"),
+                None => (String::new(), ""),
+            };
+            let sql = format!(
+                "INSERT INTO {} (event_id, aggregate_id, version, payload, metadata, at, event_type{column}) VALUES ($1,$2,$3,$4,$5,$6,$7{placeholder})",
+                tables.journal
+            );
+            let version = event.version as i64;
+            let metadata = match serde_json::to_value(&event.metadata) {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    result = Err(CqrsError::serialization_error(e));
+                    break;
+                }
+            };
+            let mut params: Vec<&(dyn ToSql + Sync)> = vec![
+                &event.event_id,
+                &event.aggregate_id,
+                &version,
+                &event.payload,
+                &metadata,
+                &event.at,
+                &event.event_type,
+            ];
+            if let Some(tenant) = &event.tenant_id {
+                params.push(tenant);
+            }
+            if let Err(e) = client.execute(&sql, &params).await {
+                result = Err(map_pg_error(e));
+                break;
+            }
+        }
+        let end = if result.is_ok() { "COMMIT" } else { "ROLLBACK" };
+        client.batch_execute(end).await.map_err(map_pg_error)?;
+        result
+    }
+
+    async fn import_snapshots(
+        &self,
+        tables: &JournalTables,
+        snapshots: Vec<RawSnapshot>,
+    ) -> Result<(), CqrsError> {
+        let conn = self.pool.acquire().await?;
+        for snapshot in &snapshots {
+            let version = snapshot.version as i64;
+            let mut params: Vec<&(dyn ToSql + Sync)> =
+                vec![&snapshot.aggregate_id, &snapshot.state, &version];
+            let (column, placeholder, key) = match &snapshot.tenant_id {
+                Some(tenant) => {
+                    params.push(tenant);
+                    (
+                        format!(", {TENANT_COLUMN}"),
+                        ", $4",
+                        format!("{TENANT_COLUMN}, aggregate_id"),
+                    )
+                }
+                None => (String::new(), "", "aggregate_id".to_string()),
+            };
+            let sql = format!(
+                "INSERT INTO {} (aggregate_id, data, version{column}) VALUES ($1, $2, $3{placeholder}) \
+                 ON CONFLICT ({key}) DO UPDATE SET data = EXCLUDED.data, version = EXCLUDED.version",
+                tables.snapshots
+            );
+            conn.client()
+                .execute(&sql, &params)
+                .await
+                .map_err(map_pg_error)?;
+        }
         Ok(())
     }
 }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn conditions(filter: &EventFilter) -> (String, usize) {
+        let mut params = Vec::new();
+        let sql = filter_conditions(filter, &mut params);
+        (sql, params.len())
+    }
+
+    #[test]
+    fn each_filter_field_binds_its_own_condition() {
+        let at = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let event_types = EventFilter {
+            event_types: vec!["Created".to_string(), "Renamed".to_string()],
+            ..EventFilter::default()
+        };
+        assert_eq!(
+            conditions(&event_types),
+            (" AND event_type = ANY($1)".to_string(), 1)
+        );
+        let user = EventFilter {
+            user_id: Some("ada".to_string()),
+            ..EventFilter::default()
+        };
+        assert_eq!(
+            conditions(&user),
+            (" AND metadata->>'user_id' = $1".to_string(), 1)
+        );
+        let window = EventFilter {
+            from: Some(at),
+            to: Some(at),
+            ..EventFilter::default()
+        };
+        assert_eq!(
+            conditions(&window),
+            (" AND at >= $1 AND at < $2".to_string(), 2)
+        );
+        assert_eq!(conditions(&EventFilter::default()), (String::new(), 0));
+    }
+
+    #[test]
+    fn a_combined_filter_numbers_its_parameters_in_order() {
+        let at = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let filter = EventFilter {
+            aggregate_id: Some("a1".to_string()),
+            event_types: vec!["Created".to_string()],
+            user_id: Some("ada".to_string()),
+            from: Some(at),
+            to: Some(at),
+        };
+        let mut params = Vec::new();
+        let sql = filter_conditions(&filter, &mut params);
+        let tenant = Some("acme");
+        let sql = format!("{sql}{}", tenant_condition(&tenant, &mut params));
+        assert_eq!(
+            sql,
+            " AND aggregate_id = $1 AND event_type = ANY($2) \
+             AND metadata->>'user_id' = $3 AND at >= $4 AND at < $5 AND tenant_id = $6"
+        );
+        assert_eq!(params.len(), 6);
+    }
+
+    #[test]
+    fn one_aggregate_pages_by_version_and_the_type_by_time() {
+        assert_eq!(page_order(&EventFilter::aggregate("a1")), "version ASC");
+        assert_eq!(
+            page_order(&EventFilter::default()),
+            "at ASC, aggregate_id ASC, version ASC"
+        );
+    }
+}
@@ -0,0 +1,610 @@
+use crate::errors::CqrsError;
+use crate::es::storage::{EventStoreStorage, EventStream};
+use crate::redb::{map_redb_error, ping, read_table};
+use crate::snapshot::Snapshot;
+use crate::{Aggregate, CqrsContext, EventEnvelope, EventFilter, Tenancy, TenantScope};
+use futures::stream;
+use redb::{Database, ReadOnlyTable, ReadableTable, TableDefinition};
+use std::sync::Arc;
+
+pub use crate::redb::{open, open_in_memory};
+
+/// A journal: each event, as JSON, under its tenant, aggregate id and version. The tenant
+/// is `""` unless the persist is under [`Tenancy::Column`].
+type JournalKey = (&'static str, &'static str, u64);
+/// A snapshot table: each [`Snapshot`], as JSON, under its tenant and aggregate id.
+type SnapshotKey = (&'static str, &'static str);
+
+fn journal(name: &str) -> TableDefinition<'_, JournalKey, &'static str> {
+    TableDefinition::new(name)
+}
+
+fn snapshots(name: &str) -> TableDefinition<'_, SnapshotKey, &'static str> {
+    TableDefinition::new(name)
+}
+
+/// A row waiting for its session to close.
+#[derive(Debug)]
+struct Pending {
+    table: String,
+    tenant: String,
+    aggregate_id: String,
+    json: String,
+}
+
+/// The writes of a commit, applied in one write transaction when the session closes.
+///
+/// redb has a single writer: holding its write transaction from `start_session` would
+/// stall every other commit while the engine runs the command. The session buffers the
+/// rows instead, and a version another commit wrote in the meantime fails the whole
+/// transaction.
+#[derive(Debug, Default)]
+pub struct RedbSession {
+    events: Vec<(Pending, u64)>,
+    snapshots: Vec<Pending>,
+}
+
+fn envelope<A: Aggregate>(json: &str) -> Result<EventEnvelope<A>, CqrsError> {
+    serde_json::from_str(json).map_err(CqrsError::serialization_error)
+}
+
+/// The events of `aggregate_id` in `tenant` from `version` on, oldest first.
+fn events_of<A: Aggregate>(
+    table: &ReadOnlyTable<JournalKey, &'static str>,
+    tenant: &str,
+    aggregate_id: &str,
+    version: u64,
+) -> Result<Vec<EventEnvelope<A>>, CqrsError> {
+    table
+        .range((tenant, aggregate_id, version)..=(tenant, aggregate_id, u64::MAX))
+        .map_err(map_redb_error)?
+        .map(|row| envelope(row.map_err(map_redb_error)?.1.value()))
+        .collect()
+}
+
+/// Applies `session` in one write transaction. A version already in the journal —
+/// another commit won the race — is a concurrency error, and nothing is written.
+fn apply(database: &Database, session: &RedbSession) -> Result<(), CqrsError> {
+    let transaction = database.begin_write().map_err(map_redb_error)?;
+    let written = (|| {
+        for (event, version) in &session.events {
+            let mut table = transaction
+                .open_table(journal(&event.table))
+                .map_err(map_redb_error)?;
+            let key = (event.tenant.as_str(), event.aggregate_id.as_str(), *version);
+            if table.get(key).map_err(map_redb_error)?.is_some() {
+                return Err(CqrsError::concurrency_error());
+            }
+            table
+                .insert(key, event.json.as_str())
+                .map_err(map_redb_error)?;
+        }
+        for snapshot in &session.snapshots {
+            transaction
+                .open_table(snapshots(&snapshot.table))
+                .and_then(|mut table| {
+                    table
+                        .insert(
+                            (snapshot.tenant.as_str(), snapshot.aggregate_id.as_str()),
+                            snapshot.json.as_str(),
+                        )
+                        .map(drop)
+                        .map_err(Into::into)
+                })
+                .map_err(map_redb_error)?;
+        }
+        Ok(())
+    })();
+    match written {
+        Ok(()) => transaction.commit().map_err(map_redb_error),
+        Err(e) => {
+            transaction.abort().map_err(map_redb_error)?;
+            Err(e)
+        }
+    }
+}
+
+/// A file-backed event store on [redb](https://www.redb.org), an embedded key-value
+/// store: no server to run, and nothing but Rust to build.
+///
+/// The journal and snapshot tables are named as in the SQL stores,
+/// `{TYPE}_journal` and `{TYPE}_snapshots`, and created by their first commit.
+#[derive(Clone, Debug)]
+pub struct RedbPersist<A>
+where
+    A: Aggregate,
+{
+    _phantom: std::marker::PhantomData<A>,
+    database: Arc<Database>,
+    snapshot_table_name: String,
+    journal_table_name: String,
+    scope: TenantScope,
+}
+
+impl<A> RedbPersist<A>
+where
+    A: Aggregate,
+{
+    #[must_use]
+    pub fn new(database: Arc<Database>) -> Self {
+        Self {
+            _phantom: std::marker::PhantomData,
+            database,
+            snapshot_table_name: format!("{}_snapshots", A::TYPE),
+            journal_table_name: format!("{}_journal", A::TYPE),
+            scope: TenantScope::default(),
+        }
+    }
+
+    /// Keeps each tenant's aggregates apart: keys led by the tenant in shared tables
+    /// ([`Tenancy::Column`]), or tables of their own ([`Tenancy::Table`]), created on
+    /// the tenant's first commit.
+    #[must_use]
+    pub fn with_tenancy(mut self, tenancy: Tenancy) -> Self {
+        self.scope = TenantScope::new(tenancy);
+        self
+    }
+
+    pub fn snapshot_table_name(&self) -> &str {
+        self.snapshot_table_name.as_str()
+    }
+    pub fn journal_table_name(&self) -> &str {
+        self.journal_table_name.as_str()
+    }
+
+    /// The tenant leading the keys: `""` unless under [`Tenancy::Column`].
+    fn tenant(&self) -> Result<&str, CqrsError> {
+        Ok(self.scope.column()?.unwrap_or_default())
+    }
+
+    /// The events of `aggregate_id` after `version`, oldest first.
+    fn events_after(&self, aggregate_id: &str, version: usize) -> Result<EventStream<A>, CqrsError>
+    where
+        A: 'static,
+    {
+        let tenant = self.tenant()?;
+        let events = read_table(
+            &self.database,
+            &self.scope.table(&self.journal_table_name)?,
+            |table| events_of::<A>(&table, tenant, aggregate_id, version as u64 + 1),
+        )?
+        .unwrap_or_default();
+        Ok(Box::pin(stream::iter(events.into_iter().map(Ok))))
+    }
+
+    fn pending(
+        &self,
+        table: &str,
+        aggregate_id: String,
+        json: String,
+    ) -> Result<Pending, CqrsError> {
+        Ok(Pending {
+            table: self.scope.table(table)?,
+            tenant: self.tenant()?.to_string(),
+            aggregate_id,
+            json,
+        })
+    }
+}
+
+cqrs_async_trait! {
+impl<A> EventStoreStorage<A> for RedbPersist<A>
+where
+    A: Aggregate + 'static,
+{
+    type Session = RedbSession;
+
+    fn for_context(&self, context: &CqrsContext) -> Result<Self, CqrsError> {
+        Ok(Self {
+            scope: self.scope.for_context(context)?,
+            ..self.clone()
+        })
+    }
+
+    async fn health(&self) -> Result<(), CqrsError> {
+        ping(&self.database)
+    }
+
+    async fn start_session(&self) -> Result<Self::Session, CqrsError> {
+        Ok(RedbSession::default())
+    }
+
+    async fn close_session(&self, session: Self::Session) -> Result<(), CqrsError> {
+        if session.events.is_empty() && session.snapshots.is_empty() {
+            return Ok(());
+        }
+        apply(&self.database, &session)
+    }
+
+    async fn abort_session(&self, _session: Self::Session) -> Result<(), CqrsError> {
+        Ok(())
+    }
+
+    async fn fetch_snapshot(&self, aggregate_id: &str) -> Result<Option<Snapshot<A>>, CqrsError> {
+        let tenant = self.tenant()?;
+        let json = read_table(
+            &self.database,
+            &self.scope.table(&self.snapshot_table_name)?,
+            |table: ReadOnlyTable<SnapshotKey, &'static str>| {
+                Ok(table
+                    .get((tenant, aggregate_id))
+                    .map_err(map_redb_error)?
+                    .map(|json| json.value().to_string()))
+            },
+        )?
+        .flatten();
+        json.map(|json| serde_json::from_str(&json).map_err(CqrsError::serialization_error))
+            .transpose()
+    }
+
+    async fn fetch_events_from_version(
+        &self,
+        aggregate_id: &str,
+        version: usize,
+    ) -> Result<EventStream<A>, CqrsError> {
+        self.events_after(aggregate_id, version)
+    }
+
+    async fn fetch_all_events(&self, aggregate_id: &str) -> Result<EventStream<A>, CqrsError> {
+        self.events_after(aggregate_id, 0)
+    }
+
+    /// Reads the tenant's share of the journal — or the aggregate's, when the filter
+    /// names one — and filters, orders and pages it in memory: a key-value store has no
+    /// index on `at`.
+    async fn fetch_events_paged(
+        &self,
+        filter: &EventFilter,
+        page: usize,
+        page_size: usize,
+    ) -> Result<(Vec<EventEnvelope<A>>, i64), CqrsError> {
+        let tenant = self.tenant()?;
+        let mut items = read_table(
+            &self.database,
+            &self.scope.table(&self.journal_table_name)?,
+            |table| match &filter.aggregate_id {
+                Some(aggregate_id) => events_of::<A>(&table, tenant, aggregate_id, 0),
+                None => {
+                    let mut items = Vec::new();
+                    for row in table.range((tenant, "", 0)..).map_err(map_redb_error)? {
+                        let (key, json) = row.map_err(map_redb_error)?;
+                        if key.value().0 != tenant {
+                            break;
+                        }
+                        items.push(envelope::<A>(json.value())?);
+                    }
+                    Ok(items)
+                }
+            },
+        )?
+        .unwrap_or_default();
+        filter.sort(&mut items);
+        items.retain(|envelope| filter.matches(envelope));
+        let total = items.len() as i64;
+        let offset = (page.max(1) - 1) * page_size;
+        let events: Vec<EventEnvelope<A>> =
+            items.into_iter().skip(offset).take(page_size).collect();
+        Ok((events, total))
+    }
+
+    /// The latest committed event: the session's own writes are not applied yet, and
+    /// `close_session` settles a race when they are.
+    async fn fetch_latest_event(
+        &self,
+        aggregate: &A,
+        _session: &Self::Session,
+    ) -> Result<Option<EventEnvelope<A>>, CqrsError> {
+        let tenant = self.tenant()?;
+        let aggregate_id = aggregate.aggregate_id();
+        let latest = read_table(
+            &self.database,
+            &self.scope.table(&self.journal_table_name)?,
+            |table: ReadOnlyTable<JournalKey, &'static str>| {
+                table
+                    .range((tenant, aggregate_id.as_str(), 0)..=(tenant, aggregate_id.as_str(), u64::MAX))
+                    .map_err(map_redb_error)?
+                    .next_back()
+                    .map(|row| envelope(row.map_err(map_redb_error)?.1.value()))
+                    .transpose()
+            },
+        )?;
+        Ok(latest.flatten())
+    }
+
+    async fn save_events(
+        &self,
+        events: Vec<EventEnvelope<A>>,
+        session: &mut Self::Session,
+    ) -> Result<(), CqrsError> {
+        for e in events {
+            let json = serde_json::to_string(&e).map_err(CqrsError::serialization_error)?;
+            let version = e.version as u64;
+            let pending = self.pending(&self.journal_table_name, e.aggregate_id, json)?;
+            session.events.push((pending, version));
+        }
+        Ok(())
+    }
+
+    async fn save_snapshot(
+        &self,
+        aggregate: &A,
+        version: usize,
+        session: &mut Self::Session,
+    ) -> Result<(), CqrsError> {
+        let snapshot = Snapshot {
+            aggregate_id: aggregate.aggregate_id(),
+            state: aggregate.clone(),
+            version,
+        };
+        let json = serde_json::to_string(&snapshot).map_err(CqrsError::serialization_error)?;
+        let pending = self.pending(&self.snapshot_table_name, snapshot.aggregate_id, json)?;
+        session.snapshots.push(pending);
+        Ok(())
+    }
+}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{TestAggregate, TestEvent};
+    use chrono::Utc;
+    use futures::StreamExt;
+    use std::collections::HashMap;
+
+    fn setup() -> RedbPersist<TestAggregate> {
+        RedbPersist::new(open_in_memory().unwrap())
+    }
+
+    fn envelope(
+        aggregate_id: &str,
+        version: usize,
+        event: TestEvent,
+    ) -> EventEnvelope<TestAggregate> {
+        EventEnvelope {
+            event_id: format!("{}-v{}", aggregate_id, version),
+            aggregate_id: aggregate_id.to_string(),
+            version,
+            payload: event,
+            metadata: HashMap::new(),
+            at: Utc::now(),
+        }
+    }
+
+    async fn commit(p: &RedbPersist<TestAggregate>, events: Vec<EventEnvelope<TestAggregate>>) {
+        let mut session = p.start_session().await.unwrap();
+        p.save_events(events, &mut session).await.unwrap();
+        p.close_session(session).await.unwrap();
+    }
+
+    async fn all_events(
+        p: &RedbPersist<TestAggregate>,
+        aggregate_id: &str,
+    ) -> Vec<EventEnvelope<TestAggregate>> {
+        p.fetch_all_events(aggregate_id)
+            .await
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn a_committed_session_is_read_back_in_order() {
+        let p = setup();
+        assert!(
+            all_events(&p, "a1").await.is_empty(),
+            "no table yet reads as empty"
+        );
+        commit(
+            &p,
+            vec![
+                envelope("a1", 1, TestEvent::Created { name: "foo".into() }),
+                envelope("a1", 2, TestEvent::Incremented),
+                envelope("a10", 1, TestEvent::Incremented),
+            ],
+        )
+        .await;
+
+        let rows = all_events(&p, "a1").await;
+        assert_eq!(rows.len(), 2, "a1's range stops short of a10");
+        assert!(matches!(&rows[0].payload, TestEvent::Created { name } if name == "foo"));
+        let rows: Vec<_> = p
+            .fetch_events_from_version("a1", 1)
+            .await
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect()
+            .await;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].version, 2);
+
+        let agg = TestAggregate::default().with_aggregate_id("a1".to_string());
+        let latest = p.fetch_latest_event(&agg, &RedbSession::default()).await;
+        assert_eq!(latest.unwrap().unwrap().version, 2);
+        assert!(p.health().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn an_aborted_session_writes_nothing() {
+        let p = setup();
+        let mut session = p.start_session().await.unwrap();
+        p.save_events(
+            vec![envelope("a1", 1, TestEvent::Incremented)],
+            &mut session,
+        )
+        .await
+        .unwrap();
+        let agg = TestAggregate::default().with_aggregate_id("a1".to_string());
+        p.save_snapshot(&agg, 1, &mut session).await.unwrap();
+        p.abort_session(session).await.unwrap();
+
+        assert!(all_events(&p, "a1").await.is_empty());
+        assert!(p.fetch_snapshot("a1").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn a_version_written_twice_is_a_concurrency_error_and_rolls_back_the_commit() {
+        let p = setup();
+        commit(&p, vec![envelope("a1", 1, TestEvent::Incremented)]).await;
+
+        let mut session = p.start_session().await.unwrap();
+        p.save_events(
+            vec![
+                envelope("a2", 1, TestEvent::Incremented),
+                envelope("a1", 1, TestEvent::Decremented),
+            ],
+            &mut session,
+        )
+        .await
+        .unwrap();
+        let err = p.close_session(session).await.unwrap_err();
+        assert_eq!(err.status, 409, "expected concurrency error, got: {err}");
+        assert!(
+            all_events(&p, "a2").await.is_empty(),
+            "the whole commit is refused, not the conflicting row alone"
+        );
+        assert!(matches!(
+            all_events(&p, "a1").await[0].payload,
+            TestEvent::Incremented
+        ));
+    }
+
+    #[tokio::test]
+    async fn snapshots_are_upserted() {
+        let p = setup();
+        let mut agg = TestAggregate::default().with_aggregate_id("a1".to_string());
+        agg.apply(TestEvent::Created { name: "bar".into() })
+            .unwrap();
+        for version in [1, 5] {
+            let mut session = p.start_session().await.unwrap();
+            p.save_snapshot(&agg, version, &mut session).await.unwrap();
+            p.close_session(session).await.unwrap();
+        }
+
+        let snap = p.fetch_snapshot("a1").await.unwrap().unwrap();
+        assert_eq!(snap.version, 5);
+        assert_eq!(snap.state.aggregate_id(), "a1");
+    }
+
+    #[tokio::test]
+    async fn fetch_events_paged_filters_across_aggregates() {
+        let p = setup();
+        let start = Utc::now();
+        let by = |aggregate_id: &str, version, event, user: &str| {
+            let mut envelope = envelope(aggregate_id, version, event);
+            envelope.metadata = HashMap::from([("user_id".to_string(), user.to_string())]);
+            envelope.at = start + chrono::Duration::seconds(version as i64);
+            envelope
+        };
+        for aggregate_id in ["a1", "a2"] {
+            commit(
+                &p,
+                vec![
+                    by(
+                        aggregate_id,
+                        1,
+                        TestEvent::Created { name: "x".into() },
+                        "alice",
+                    ),
+                    by(aggregate_id, 2, TestEvent::Incremented, "bob"),
+                    by(aggregate_id, 3, TestEvent::Incremented, "alice"),
+                ],
+            )
+            .await;
+        }
+
+        let filter = EventFilter {
+            event_types: vec!["Incremented".to_string()],
+            user_id: Some("alice".to_string()),
+            ..EventFilter::default()
+        };
+        let (events, total) = p.fetch_events_paged(&filter, 1, 10).await.unwrap();
+        assert_eq!(total, 2);
+        let ids: Vec<_> = events.iter().map(|e| e.aggregate_id.as_str()).collect();
+        assert_eq!(ids, ["a1", "a2"]);
+
+        let (events, total) = p
+            .fetch_events_paged(&EventFilter::default(), 2, 4)
+            .await
+            .unwrap();
+        assert_eq!((events.len(), total), (2, 6));
+        assert_eq!(
+            (events[0].aggregate_id.as_str(), events[0].version),
+            ("a1", 3),
+            "ordered by time first"
+        );
+    }
+
+    #[tokio::test]
+    async fn an_aggregate_s_page_is_in_version_order_whatever_the_clock_did() {
+        let p = setup();
+        let start = Utc::now();
+        let events: Vec<_> = (1..=3)
+            .map(|version| {
+                let mut envelope = envelope("a1", version, TestEvent::Incremented);
+                // The clock went back between each commit.
+                envelope.at = start - chrono::Duration::seconds(version as i64);
+                envelope
+            })
+            .collect();
+        commit(&p, events).await;
+
+        let (events, _) = p
+            .fetch_events_paged(&EventFilter::aggregate("a1"), 1, 10)
+            .await
+            .unwrap();
+        let versions: Vec<_> = events.iter().map(|e| e.version).collect();
+        assert_eq!(versions, [1, 2, 3]);
+
+        let (events, _) = p
+            .fetch_events_paged(&EventFilter::default(), 1, 10)
+            .await
+            .unwrap();
+        let versions: Vec<_> = events.iter().map(|e| e.version).collect();
+        assert_eq!(versions, [3, 2, 1], "the type-wide page follows the clock");
+    }
+
+    #[tokio::test]
+    async fn tenants_only_see_their_own_aggregates() {
+        let database = open_in_memory().unwrap();
+        for tenancy in [Tenancy::Column, Tenancy::Table] {
+            let p = RedbPersist::<TestAggregate>::new(database.clone()).with_tenancy(tenancy);
+            let tenant = |tenant: &str| {
+                p.for_context(&CqrsContext::default().with_tenant(Some(tenant.to_string())))
+                    .unwrap()
+            };
+            let (acme, globex) = (tenant("acme"), tenant("globex"));
+
+            // The same aggregate id, and version, in both tenants.
+            commit(&acme, vec![envelope("a1", 1, TestEvent::Incremented)]).await;
+            commit(&globex, vec![envelope("a1", 1, TestEvent::Decremented)]).await;
+
+            let rows = all_events(&acme, "a1").await;
+            assert_eq!(rows.len(), 1, "{tenancy:?}");
+            assert!(matches!(rows[0].payload, TestEvent::Incremented));
+            let (_, total) = globex
+                .fetch_events_paged(&EventFilter::default(), 1, 10)
+                .await
+                .unwrap();
+            assert_eq!(total, 1, "{tenancy:?}");
+
+            let error = p.for_context(&CqrsContext::default()).unwrap_err();
+            assert_eq!(error.status, 403);
+        }
+    }
+
+    #[tokio::test]
+    async fn committed_events_survive_reopening_the_file() {
+        let path = std::env::temp_dir().join(format!("cqrs-redb-{}.redb", uuid::Uuid::new_v4()));
+        {
+            let p = RedbPersist::<TestAggregate>::new(open(&path).unwrap());
+            commit(&p, vec![envelope("a1", 1, TestEvent::Incremented)]).await;
+        }
+
+        let p = RedbPersist::<TestAggregate>::new(open(&path).unwrap());
+        assert_eq!(all_events(&p, "a1").await.len(), 1);
+        drop(p);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,1036 @@
+use crate::errors::CqrsError;
+use crate::es::journals::{JournalTables, Journals, RawEvent, RawSnapshot};
+use crate::es::storage::{EventStoreStorage, EventStream};
+use crate::snapshot::Snapshot;
+use crate::sqlite::{is_unique_violation, map_sqlite_error, parse_timestamp, timestamp};
+use crate::{
+    Aggregate, CqrsContext, Event, EventEnvelope, EventFilter, TENANT_COLUMN, Tenancy, TenantScope,
+    USER_ID_METADATA,
+};
+use futures::stream;
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{Connection, OptionalExtension, Row, params_from_iter};
+use std::collections::HashMap;
+
+pub use crate::sqlite::SharedConnection;
+
+/// The writes of a commit, applied in one transaction when the session closes.
+///
+/// SQLite has a single writer: holding its write lock from `start_session` would stall
+/// every other task on the connection while the engine runs the command. The session
+/// buffers the rows instead, and the journal's `UNIQUE(aggregate_id, version)` refuses
+/// a version another commit wrote in the meantime.
+#[derive(Debug, Default)]
+pub struct SqliteSession {
+    statements: Vec<(String, Vec<SqlValue>)>,
+}
+
+const EVENT_COLUMNS: &str = "event_id, aggregate_id, version, payload, metadata, at";
+
+/// A journal row, before its payload and metadata are deserialized.
+struct JournalRow {
+    event_id: String,
+    aggregate_id: String,
+    version: i64,
+    payload: String,
+    metadata: String,
+    at: String,
+}
+
+impl JournalRow {
+    fn read(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            event_id: row.get("event_id")?,
+            aggregate_id: row.get("aggregate_id")?,
+            version: row.get("version")?,
+            payload: row.get("payload")?,
+            metadata: row.get("metadata")?,
+            at: row.get("at")?,
+        })
+    }
+
+    fn into_envelope<A: Aggregate>(self) -> Result<EventEnvelope<A>, CqrsError> {
+        Ok(EventEnvelope {
+            event_id: self.event_id,
+            aggregate_id: self.aggregate_id,
+            version: self.version as usize,
+            payload: serde_json::from_str(&self.payload).map_err(CqrsError::serialization_error)?,
+            metadata: serde_json::from_str(&self.metadata)
+                .map_err(CqrsError::serialization_error)?,
+            at: parse_timestamp(&self.at)?,
+        })
+    }
+}
+
+/// Runs `sql` and reads every journal row it selects.
+fn journal_rows<A: Aggregate>(
+    conn: &Connection,
+    sql: &str,
+    params: &[SqlValue],
+) -> Result<Vec<EventEnvelope<A>>, CqrsError> {
+    let mut statement = conn.prepare_cached(sql).map_err(map_sqlite_error)?;
+    let rows = statement
+        .query_map(params_from_iter(params), JournalRow::read)
+        .map_err(map_sqlite_error)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(map_sqlite_error)?;
+    rows.into_iter().map(JournalRow::into_envelope).collect()
+}
+
+/// Binds `value` as the next numbered parameter and returns its placeholder.
+fn bind(params: &mut Vec<SqlValue>, value: impl Into<SqlValue>) -> String {
+    params.push(value.into());
+    format!("?{}", params.len())
+}
+
+/// Narrows a query to `tenant` under [`Tenancy::Column`], binding it as the next
+/// parameter; an empty condition otherwise.
+fn tenant_condition(tenant: Option<&str>, params: &mut Vec<SqlValue>) -> String {
+    match tenant {
+        Some(tenant) => format!(
+            " AND {TENANT_COLUMN} = {}",
+            bind(params, tenant.to_string())
+        ),
+        None => String::new(),
+    }
+}
+
+/// The conditions of `filter`, each binding the next parameters. `at` is stored as
+/// fixed-width RFC 3339 text, so the bounds compare as text.
+fn filter_conditions(filter: &EventFilter, params: &mut Vec<SqlValue>) -> String {
+    let mut conditions = Vec::new();
+    if let Some(aggregate_id) = &filter.aggregate_id {
+        conditions.push(format!(
+            "aggregate_id = {}",
+            bind(params, aggregate_id.clone())
+        ));
+    }
+    if !filter.event_types.is_empty() {
+        let types: Vec<_> = filter
+            .event_types
+            .iter()
+            .map(|event_type| bind(params, event_type.clone()))
+            .collect();
+        conditions.push(format!("event_type IN ({})", types.join(", ")));
+    }
+    if let Some(user_id) = &filter.user_id {
+        conditions.push(format!(
+            "json_extract(metadata, '$.\"{USER_ID_METADATA}\"') = {}",
+            bind(params, user_id.clone())
+        ));
+    }
+    if let Some(from) = &filter.from {
+        conditions.push(format!("at >= {}", bind(params, timestamp(from))));
+    }
+    if let Some(to) = &filter.to {
+        conditions.push(format!("at < {}", bind(params, timestamp(to))));
+    }
+    conditions
+        .into_iter()
+        .map(|condition| format!(" AND {condition}"))
+        .collect()
+}
+
+/// Applies `statements` in one transaction. A version already in the journal — another
+/// commit won the race — is a concurrency error, and nothing is written.
+/// The `ORDER BY` of a page of `filter`'s selection, as [`EventFilter::sort`] orders it.
+fn page_order(filter: &EventFilter) -> &'static str {
+    if filter.aggregate_id.is_some() {
+        "version ASC"
+    } else {
+        "at ASC, aggregate_id ASC, version ASC"
+    }
+}
+
+fn apply(conn: &mut Connection, statements: &[(String, Vec<SqlValue>)]) -> Result<(), CqrsError> {
+    let transaction = conn.transaction().map_err(map_sqlite_error)?;
+    for (sql, params) in statements {
+        transaction
+            .prepare_cached(sql)
+            .and_then(|mut statement| statement.execute(params_from_iter(params)))
+            .map_err(|e| {
+                if is_unique_violation(&e) {
+                    CqrsError::concurrency_error()
+                } else {
+                    map_sqlite_error(e)
+                }
+            })?;
+    }
+    transaction.commit().map_err(map_sqlite_error)
+}
+
+/// The statement inserting an event into `journal_table`, with the tenant column when
+/// the event carries one.
+fn insert_event_sql(journal_table: &str, tenant: bool) -> String {
+    let (column, placeholder) = if tenant {
+        (format!(", {TENANT_COLUMN}"), ", ?8")
+    } else {
+        (String::new(), "")
+    };
+    format!(
+        "INSERT INTO {journal_table} (event_id, aggregate_id, version, payload, metadata, at, event_type{column}) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7{placeholder})"
+    )
+}
+
+/// The statement upserting a snapshot into `snapshot_table`, with the tenant column when
+/// the snapshot carries one.
+fn upsert_snapshot_sql(snapshot_table: &str, tenant: bool) -> String {
+    let (column, placeholder, key) = if tenant {
+        (
+            format!(", {TENANT_COLUMN}"),
+            ", ?4",
+            format!("{TENANT_COLUMN}, aggregate_id"),
+        )
+    } else {
+        (String::new(), "", "aggregate_id".to_string())
+    };
+    format!(
+        "INSERT INTO {snapshot_table} (aggregate_id, data, version{column}) VALUES (?1, ?2, ?3{placeholder}) \
+         ON CONFLICT ({key}) DO UPDATE SET data = excluded.data, version = excluded.version"
+    )
+}
+
+/// The journal and snapshot tables, with a `tenant_id` column leading their keys when
+/// `tenant_column`. Payloads, metadata and states are JSON text, which `json_extract`
+/// reads.
+fn ddl(snapshot_table: &str, journal_table: &str, tenant_column: bool) -> String {
+    let (column, key) = if tenant_column {
+        (
+            format!("\n    {TENANT_COLUMN} TEXT NOT NULL,"),
+            format!("{TENANT_COLUMN}, "),
+        )
+    } else {
+        (String::new(), String::new())
+    };
+    let snapshot_key = if tenant_column {
+        format!(",\n    PRIMARY KEY({key}aggregate_id)")
+    } else {
+        String::new()
+    };
+    let aggregate_id = if tenant_column {
+        "aggregate_id TEXT NOT NULL"
+    } else {
+        "aggregate_id TEXT PRIMARY KEY"
+    };
+    format!(
+        r#"CREATE TABLE IF NOT EXISTS {snapshot_table} ({column}
+    {aggregate_id},
+    data TEXT NOT NULL,
+    version INTEGER NOT NULL{snapshot_key}
+);
+CREATE TABLE IF NOT EXISTS {journal_table} (
+    event_id TEXT PRIMARY KEY,{column}
+    aggregate_id TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    payload TEXT NOT NULL,
+    metadata TEXT NOT NULL,
+    at TEXT NOT NULL,
+    event_type TEXT,
+    UNIQUE({key}aggregate_id, version)
+);
+CREATE INDEX IF NOT EXISTS idx_{journal_table}_at ON {journal_table}({key}at);"#
+    )
+}
+
+#[derive(Clone, Debug)]
+pub struct SqlitePersist<A>
+where
+    A: Aggregate,
+{
+    _phantom: std::marker::PhantomData<A>,
+    connection: SharedConnection,
+    snapshot_table_name: String,
+    journal_table_name: String,
+    scope: TenantScope,
+}
+
+impl<A> SqlitePersist<A>
+where
+    A: Aggregate,
+{
+    #[must_use]
+    pub fn new(connection: SharedConnection) -> Self {
+        Self {
+            _phantom: std::marker::PhantomData,
+            connection,
+            snapshot_table_name: format!("{}_snapshots", A::TYPE),
+            journal_table_name: format!("{}_journal", A::TYPE),
+            scope: TenantScope::default(),
+        }
+    }
+
+    /// Keeps each tenant's aggregates apart: a `tenant_id` column in shared tables
+    /// ([`Tenancy::Column`], see [`Self::tenant_column_schema`]), or tables of their own
+    /// ([`Tenancy::Table`], see [`Self::tenant_table_schema`]).
+    #[must_use]
+    pub fn with_tenancy(mut self, tenancy: Tenancy) -> Self {
+        self.scope = TenantScope::new(tenancy);
+        self
+    }
+
+    pub fn snapshot_table_name(&self) -> &str {
+        self.snapshot_table_name.as_str()
+    }
+    pub fn journal_table_name(&self) -> &str {
+        self.journal_table_name.as_str()
+    }
+
+    /// Returns the DDL statements to create the journal and snapshot tables,
+    /// including a `UNIQUE(aggregate_id, version)` constraint on the journal. Apply it
+    /// with [`SharedConnection::execute_batch`].
+    pub fn schema() -> String {
+        ddl(
+            &format!("{}_snapshots", A::TYPE),
+            &format!("{}_journal", A::TYPE),
+            false,
+        )
+    }
+
+    /// [`Self::schema`] under [`Tenancy::Column`]: both tables carry a `tenant_id`, which
+    /// leads the snapshot key and the journal's `UNIQUE` constraint, so an aggregate id
+    /// is only unique within its tenant.
+    pub fn tenant_column_schema() -> String {
+        ddl(
+            &format!("{}_snapshots", A::TYPE),
+            &format!("{}_journal", A::TYPE),
+            true,
+        )
+    }
+
+    /// [`Self::schema`] for one tenant under [`Tenancy::Table`]: its own
+    /// `{TYPE}_snapshots_{tenant}` and `{TYPE}_journal_{tenant}`. Run it when a tenant is
+    /// onboarded.
+    pub fn tenant_table_schema(tenant: &str) -> Result<String, CqrsError> {
+        let tenant = crate::validated_tenant(tenant)?;
+        Ok(ddl(
+            &crate::tenant_table(&format!("{}_snapshots", A::TYPE), tenant),
+            &crate::tenant_table(&format!("{}_journal", A::TYPE), tenant),
+            false,
+        ))
+    }
+
+    fn snapshot_table(&self) -> Result<String, CqrsError> {
+        self.scope.table(&self.snapshot_table_name)
+    }
+
+    fn journal_table(&self) -> Result<String, CqrsError> {
+        self.scope.table(&self.journal_table_name)
+    }
+
+    /// The events of `aggregate_id` after `version`, oldest first.
+    fn events_after(&self, aggregate_id: &str, version: usize) -> Result<EventStream<A>, CqrsError>
+    where
+        A: 'static,
+    {
+        let tenant = self.scope.column()?;
+        let mut params = vec![
+            SqlValue::from(aggregate_id.to_string()),
+            SqlValue::from(version as i64),
+        ];
+        let sql = format!(
+            "SELECT {EVENT_COLUMNS} FROM {} WHERE aggregate_id = ?1 AND version > ?2{} ORDER BY version ASC",
+            self.journal_table()?,
+            tenant_condition(tenant, &mut params)
+        );
+        let events = self
+            .connection
+            .with(|conn| journal_rows::<A>(conn, &sql, &params))?;
+        Ok(Box::pin(stream::iter(events.into_iter().map(Ok))))
+    }
+}
+
+cqrs_async_trait! {
+impl<A> EventStoreStorage<A> for SqlitePersist<A>
+where
+    A: Aggregate + 'static,
+{
+    type Session = SqliteSession;
+
+    fn for_context(&self, context: &CqrsContext) -> Result<Self, CqrsError> {
+        Ok(Self {
+            scope: self.scope.for_context(context)?,
+            ..self.clone()
+        })
+    }
+
+    async fn health(&self) -> Result<(), CqrsError> {
+        self.connection.ping()
+    }
+
+    async fn start_session(&self) -> Result<Self::Session, CqrsError> {
+        Ok(SqliteSession::default())
+    }
+
+    async fn close_session(&self, session: Self::Session) -> Result<(), CqrsError> {
+        if session.statements.is_empty() {
+            return Ok(());
+        }
+        self.connection
+            .with(|conn| apply(conn, &session.statements))
+    }
+
+    async fn abort_session(&self, _session: Self::Session) -> Result<(), CqrsError> {
+        Ok(())
+    }
+
+    async fn fetch_snapshot(&self, aggregate_id: &str) -> Result<Option<Snapshot<A>>, CqrsError> {
+        let tenant = self.scope.column()?;
+        let mut params = vec![SqlValue::from(aggregate_id.to_string())];
+        let sql = format!(
+            "SELECT data, version FROM {} WHERE aggregate_id = ?1{}",
+            self.snapshot_table()?,
+            tenant_condition(tenant, &mut params)
+        );
+        let row = self.connection.with(|conn| {
+            conn.query_row(&sql, params_from_iter(&params), |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })
+            .optional()
+            .map_err(map_sqlite_error)
+        })?;
+        match row {
+            Some((data, version)) => Ok(Some(Snapshot {
+                aggregate_id: aggregate_id.to_string(),
+                state: serde_json::from_str(&data).map_err(CqrsError::serialization_error)?,
+                version: version as usize,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    async fn fetch_events_from_version(
+        &self,
+        aggregate_id: &str,
+        version: usize,
+    ) -> Result<EventStream<A>, CqrsError> {
+        self.events_after(aggregate_id, version)
+    }
+
+    async fn fetch_all_events(&self, aggregate_id: &str) -> Result<EventStream<A>, CqrsError> {
+        self.events_after(aggregate_id, 0)
+    }
+
+    async fn fetch_events_paged(
+        &self,
+        filter: &EventFilter,
+        page: usize,
+        page_size: usize,
+    ) -> Result<(Vec<EventEnvelope<A>>, i64), CqrsError> {
+        let tenant = self.scope.column()?;
+        let journal_table = self.journal_table()?;
+        let mut params = Vec::new();
+        let condition = format!(
+            "1{}{}",
+            filter_conditions(filter, &mut params),
+            tenant_condition(tenant, &mut params)
+        );
+        let count_sql = format!("SELECT COUNT(*) FROM {journal_table} WHERE {condition}");
+        let offset = ((page.max(1) - 1) * page_size) as i64;
+        let mut page_params = params.clone();
+        let limit = bind(&mut page_params, page_size as i64);
+        let offset = bind(&mut page_params, offset);
+        let sql = format!(
+            "SELECT {EVENT_COLUMNS} FROM {journal_table} WHERE {condition} \
+             ORDER BY {} LIMIT {limit} OFFSET {offset}",
+            page_order(filter)
+        );
+        self.connection.with(|conn| {
+            let total = conn
+                .query_row(&count_sql, params_from_iter(&params), |row| row.get(0))
+                .map_err(map_sqlite_error)?;
+            Ok((journal_rows(conn, &sql, &page_params)?, total))
+        })
+    }
+
+    /// The latest committed event: the session's own writes are not applied yet, and the
+    /// journal's `UNIQUE` constraint settles a race when they are.
+    async fn fetch_latest_event(
+        &self,
+        aggregate: &A,
+        _session: &Self::Session,
+    ) -> Result<Option<EventEnvelope<A>>, CqrsError> {
+        let tenant = self.scope.column()?;
+        let mut params = vec![SqlValue::from(aggregate.aggregate_id())];
+        let sql = format!(
+            "SELECT {EVENT_COLUMNS} FROM {} WHERE aggregate_id = ?1{} ORDER BY version DESC LIMIT 1",
+            self.journal_table()?,
+            tenant_condition(tenant, &mut params)
+        );
+        let mut events = self
+            .connection
+            .with(|conn| journal_rows::<A>(conn, &sql, &params))?;
+        Ok(events.pop())
+    }
+
+    async fn save_events(
+        &self,
+        events: Vec<EventEnvelope<A>>,
+        session: &mut Self::Session,
+    ) -> Result<(), CqrsError> {
+        let tenant = self.scope.column()?;
+        let sql = insert_event_sql(&self.journal_table()?, tenant.is_some());
+        for e in events {
+            let payload =
+                serde_json::to_string(&e.payload).map_err(CqrsError::serialization_error)?;
+            let metadata =
+                serde_json::to_string(&e.metadata).map_err(CqrsError::serialization_error)?;
+            let event_type = e.payload.event_type();
+            let mut params = vec![
+                SqlValue::from(e.event_id),
+                SqlValue::from(e.aggregate_id),
+                SqlValue::from(e.version as i64),
+                SqlValue::from(payload),
+                SqlValue::from(metadata),
+                SqlValue::from(timestamp(&e.at)),
+                SqlValue::from(event_type),
+            ];
+            if let Some(tenant) = tenant {
+                params.push(SqlValue::from(tenant.to_string()));
+            }
+            session.statements.push((sql.clone(), params));
+        }
+        Ok(())
+    }
+
+    async fn save_snapshot(
+        &self,
+        aggregate: &A,
+        version: usize,
+        session: &mut Self::Session,
+    ) -> Result<(), CqrsError> {
+        let data = serde_json::to_string(aggregate).map_err(CqrsError::serialization_error)?;
+        let tenant = self.scope.column()?;
+        let mut params = vec![
+            SqlValue::from(aggregate.aggregate_id()),
+            SqlValue::from(data),
+            SqlValue::from(version as i64),
+        ];
+        if let Some(tenant) = tenant {
+            params.push(SqlValue::from(tenant.to_string()));
+        }
+        let sql = upsert_snapshot_sql(&self.snapshot_table()?, tenant.is_some());
+        session.statements.push((sql, params));
+        Ok(())
+    }
+}
+}
+
+/// Every journal of a SQLite database, for operational tools; see
+/// [`Journals`](crate::es::journals::Journals).
+#[derive(Clone, Debug)]
+pub struct SqliteJournals {
+    connection: SharedConnection,
+}
+
+impl SqliteJournals {
+    #[must_use]
+    pub fn new(connection: SharedConnection) -> Self {
+        Self { connection }
+    }
+
+    /// The DDL of [`SqlitePersist::schema`] for `tables`, with a tenant column when
+    /// `tenant_column`.
+    pub fn schema(tables: &JournalTables, tenant_column: bool) -> String {
+        ddl(&tables.snapshots, &tables.journal, tenant_column)
+    }
+}
+
+/// Whether `table` has the tenant column: the journals of both layouts are read alike.
+fn has_tenant_column(conn: &Connection, table: &str) -> Result<bool, CqrsError> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+        [table, TENANT_COLUMN],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|count| count > 0)
+    .map_err(map_sqlite_error)
+}
+
+/// Selects the rows of `table` that `condition` keeps, ordered by tenant first when the
+/// table has one, then by `order`.
+fn raw_rows<T>(
+    conn: &Connection,
+    table: &str,
+    condition: &str,
+    order: &str,
+    params: &[SqlValue],
+    read: impl Fn(&Row<'_>, bool) -> rusqlite::Result<Result<T, CqrsError>>,
+) -> Result<Vec<T>, CqrsError> {
+    let tenant = has_tenant_column(conn, table)?;
+    let by_tenant = if tenant {
+        format!("{TENANT_COLUMN}, ")
+    } else {
+        String::new()
+    };
+    let sql = format!("SELECT * FROM {table} WHERE {condition} ORDER BY {by_tenant}{order}");
+    let mut statement = conn.prepare(&sql).map_err(map_sqlite_error)?;
+    statement
+        .query_map(params_from_iter(params), |row| read(row, tenant))
+        .map_err(map_sqlite_error)?
+        .map(|row| row.map_err(map_sqlite_error)?)
+        .collect()
+}
+
+fn raw_event(row: &Row<'_>, tenant: bool) -> rusqlite::Result<Result<RawEvent, CqrsError>> {
+    let payload: String = row.get("payload")?;
+    let metadata: String = row.get("metadata")?;
+    let at: String = row.get("at")?;
+    let event_id = row.get("event_id")?;
+    let aggregate_id = row.get("aggregate_id")?;
+    let version = row.get::<_, i64>("version")? as usize;
+    let event_type = row.get("event_type")?;
+    let tenant_id = if tenant {
+        row.get(TENANT_COLUMN)?
+    } else {
+        None
+    };
+    Ok((|| {
+        Ok(RawEvent {
+            event_id,
+            aggregate_id,
+            version,
+            payload: serde_json::from_str(&payload).map_err(CqrsError::serialization_error)?,
+            metadata: serde_json::from_str::<HashMap<String, String>>(&metadata)
+                .map_err(CqrsError::serialization_error)?,
+            at: parse_timestamp(&at)?,
+            event_type,
+            tenant_id,
+        })
+    })())
+}
+
+fn raw_snapshot(row: &Row<'_>, tenant: bool) -> rusqlite::Result<Result<RawSnapshot, CqrsError>> {
+    let data: String = row.get("data")?;
+    let aggregate_id = row.get("aggregate_id")?;
+    let version = row.get::<_, i64>("version")? as usize;
+    let tenant_id = if tenant {
+        row.get(TENANT_COLUMN)?
+    } else {
+        None
+    };
+    Ok(serde_json::from_str(&data)
+        .map_err(CqrsError::serialization_error)
+        .map(|state| RawSnapshot {
+            aggregate_id,
+            state,
+            version,
+            tenant_id,
+        }))
+}
+
+cqrs_async_trait! {
+impl Journals for SqliteJournals {
+    async fn aggregate_types(&self) -> Result<Vec<String>, CqrsError> {
+        let tables = self.connection.with(|conn| {
+            let mut statement = conn
+                .prepare("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")
+                .map_err(map_sqlite_error)?;
+            statement
+                .query_map([], |row| row.get::<_, String>(0))
+                .map_err(map_sqlite_error)?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(map_sqlite_error)
+        })?;
+        Ok(tables
+            .iter()
+            .filter_map(|table| JournalTables::aggregate_type(table).map(str::to_string))
+            .collect())
+    }
+
+    async fn journal(
+        &self,
+        tables: &JournalTables,
+        aggregate_id: &str,
+    ) -> Result<Vec<RawEvent>, CqrsError> {
+        let params = [SqlValue::from(aggregate_id.to_string())];
+        self.connection.with(|conn| {
+            raw_rows(conn, &tables.journal, "aggregate_id = ?1", "version", &params, raw_event)
+        })
+    }
+
+    async fn snapshot(
+        &self,
+        tables: &JournalTables,
+        aggregate_id: &str,
+    ) -> Result<Vec<RawSnapshot>, CqrsError> {
+        let params = [SqlValue::from(aggregate_id.to_string())];
+        self.connection.with(|conn| {
+            raw_rows(conn, &tables.snapshots, "aggregate_id = ?1", "aggregate_id", &params, raw_snapshot)
+        })
+    }
+
+    async fn events(
+        &self,
+        tables: &JournalTables,
+        skip: usize,
+        limit: usize,
+    ) -> Result<Vec<RawEvent>, CqrsError> {
+        let params = [SqlValue::from(limit as i64), SqlValue::from(skip as i64)];
+        self.connection.with(|conn| {
+            raw_rows(conn, &tables.journal, "1", "aggregate_id, version LIMIT ?1 OFFSET ?2", &params, raw_event)
+        })
+    }
+
+    async fn snapshots(
+        &self,
+        tables: &JournalTables,
+        skip: usize,
+        limit: usize,
+    ) -> Result<Vec<RawSnapshot>, CqrsError> {
+        let params = [SqlValue::from(limit as i64), SqlValue::from(skip as i64)];
+        self.connection.with(|conn| {
+            raw_rows(conn, &tables.snapshots, "1", "aggregate_id LIMIT ?1 OFFSET ?2", &params, raw_snapshot)
+        })
+    }
+
+    async fn import_events(
+        &self,
+        tables: &JournalTables,
+        events: Vec<RawEvent>,
+    ) -> Result<(), CqrsError> {
+        let statements = events
+            .into_iter()
+            .map(|event| {
+                let metadata = serde_json::to_string(&event.metadata)
+                    .map_err(CqrsError::serialization_error)?;
+                let mut params = vec![
+                    SqlValue::from(event.event_id),
+                    SqlValue::from(event.aggregate_id),
+                    SqlValue::from(event.version as i64),
+                    SqlValue::from(event.payload.to_string()),
+                    SqlValue::from(metadata),
+                    SqlValue::from(timestamp(&event.at)),
+                    event.event_type.map_or(SqlValue::Null, SqlValue::from),
+                ];
+                let sql = insert_event_sql(&tables.journal, event.tenant_id.is_some());
+                params.extend(event.tenant_id.map(SqlValue::from));
+                Ok((sql, params))
+            })
+            .collect::<Result<Vec<_>, CqrsError>>()?;
+        self.connection.with(|conn| apply(conn, &statements))
+    }
+
+    async fn import_snapshots(
+        &self,
+        tables: &JournalTables,
+        snapshots: Vec<RawSnapshot>,
+    ) -> Result<(), CqrsError> {
+        let statements: Vec<_> = snapshots
+            .into_iter()
+            .map(|snapshot| {
+                let sql = upsert_snapshot_sql(&tables.snapshots, snapshot.tenant_id.is_some());
+                let mut params = vec![
+                    SqlValue::from(snapshot.aggregate_id),
+                    SqlValue::from(snapshot.state.to_string()),
+                    SqlValue::from(snapshot.version as i64),
+                ];
+                params.extend(snapshot.tenant_id.map(SqlValue::from));
+                (sql, params)
+            })
+            .collect();
+        self.connection.with(|conn| apply(conn, &statements))
+    }
+}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{TestAggregate, TestEvent};
+    use chrono::{SubsecRound, Utc};
+    use futures::StreamExt;
+
+    fn setup() -> SqlitePersist<TestAggregate> {
+        let connection = SharedConnection::open_in_memory().unwrap();
+        connection
+            .execute_batch(&SqlitePersist::<TestAggregate>::schema())
+            .unwrap();
+        SqlitePersist::new(connection)
+    }
+
+    fn envelope(
+        aggregate_id: &str,
+        version: usize,
+        event: TestEvent,
+    ) -> EventEnvelope<TestAggregate> {
+        EventEnvelope {
+            event_id: format!("{}-v{}", aggregate_id, version),
+            aggregate_id: aggregate_id.to_string(),
+            version,
+            payload: event,
+            metadata: HashMap::new(),
+            at: Utc::now(),
+        }
+    }
+
+    async fn commit(p: &SqlitePersist<TestAggregate>, events: Vec<EventEnvelope<TestAggregate>>) {
+        let mut session = p.start_session().await.unwrap();
+        p.save_events(events, &mut session).await.unwrap();
+        p.close_session(session).await.unwrap();
+    }
+
+    async fn all_events(
+        p: &SqlitePersist<TestAggregate>,
+        aggregate_id: &str,
+    ) -> Vec<EventEnvelope<TestAggregate>> {
+        p.fetch_all_events(aggregate_id)
+            .await
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn a_committed_session_is_read_back_in_order() {
+        let p = setup();
+        let at = Utc::now().trunc_subsecs(6);
+        let mut created = envelope("a1", 1, TestEvent::Created { name: "foo".into() });
+        created.at = at;
+        commit(&p, vec![created, envelope("a1", 2, TestEvent::Incremented)]).await;
+
+        let rows = all_events(&p, "a1").await;
+        assert_eq!(rows.len(), 2);
+        assert!(matches!(&rows[0].payload, TestEvent::Created { name } if name == "foo"));
+        assert_eq!(rows[0].at, at, "the timestamp keeps its microseconds");
+        let rows: Vec<_> = p
+            .fetch_events_from_version("a1", 1)
+            .await
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect()
+            .await;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].version, 2);
+
+        let agg = TestAggregate::default().with_aggregate_id("a1".to_string());
+        let latest = p.fetch_latest_event(&agg, &SqliteSession::default()).await;
+        assert_eq!(latest.unwrap().unwrap().version, 2);
+        assert!(p.health().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn an_aborted_session_writes_nothing() {
+        let p = setup();
+        let mut session = p.start_session().await.unwrap();
+        p.save_events(
+            vec![envelope("a1", 1, TestEvent::Incremented)],
+            &mut session,
+        )
+        .await
+        .unwrap();
+        let agg = TestAggregate::default().with_aggregate_id("a1".to_string());
+        p.save_snapshot(&agg, 1, &mut session).await.unwrap();
+        p.abort_session(session).await.unwrap();
+
+        assert!(all_events(&p, "a1").await.is_empty());
+        assert!(p.fetch_snapshot("a1").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn a_version_written_twice_is_a_concurrency_error_and_rolls_back_the_commit() {
+        let p = setup();
+        commit(&p, vec![envelope("a1", 1, TestEvent::Incremented)]).await;
+
+        let mut session = p.start_session().await.unwrap();
+        p.save_events(
+            vec![
+                envelope("a2", 1, TestEvent::Incremented),
+                envelope("a1", 1, TestEvent::Decremented),
+            ],
+            &mut session,
+        )
+        .await
+        .unwrap();
+        let err = p.close_session(session).await.unwrap_err();
+        assert_eq!(err.status, 409, "expected concurrency error, got: {err}");
+        assert!(
+            all_events(&p, "a2").await.is_empty(),
+            "the whole commit is refused, not the conflicting row alone"
+        );
+    }
+
+    #[tokio::test]
+    async fn snapshots_are_upserted() {
+        let p = setup();
+        let mut agg = TestAggregate::default().with_aggregate_id("a1".to_string());
+        agg.apply(TestEvent::Created { name: "bar".into() })
+            .unwrap();
+        for version in [1, 5] {
+            let mut session = p.start_session().await.unwrap();
+            p.save_snapshot(&agg, version, &mut session).await.unwrap();
+            p.close_session(session).await.unwrap();
+        }
+
+        let snap = p.fetch_snapshot("a1").await.unwrap().unwrap();
+        assert_eq!(snap.version, 5);
+        assert_eq!(snap.state.aggregate_id(), "a1");
+    }
+
+    #[tokio::test]
+    async fn fetch_events_paged_filters_across_aggregates() {
+        let p = setup();
+        let start = Utc::now();
+        let by = |aggregate_id: &str, version, event, user: &str| {
+            let mut envelope = envelope(aggregate_id, version, event);
+            envelope.metadata = HashMap::from([("user_id".to_string(), user.to_string())]);
+            envelope.at = start + chrono::Duration::seconds(version as i64);
+            envelope
+        };
+        for aggregate_id in ["a1", "a2"] {
+            commit(
+                &p,
+                vec![
+                    by(
+                        aggregate_id,
+                        1,
+                        TestEvent::Created { name: "x".into() },
+                        "alice",
+                    ),
+                    by(aggregate_id, 2, TestEvent::Incremented, "bob"),
+                    by(aggregate_id, 3, TestEvent::Incremented, "alice"),
+                ],
+            )
+            .await;
+        }
+
+        let filter = EventFilter {
+            event_types: vec!["Incremented".to_string()],
+            user_id: Some("alice".to_string()),
+            ..EventFilter::default()
+        };
+        let (events, total) = p.fetch_events_paged(&filter, 1, 10).await.unwrap();
+        assert_eq!(total, 2);
+        let ids: Vec<_> = events.iter().map(|e| e.aggregate_id.as_str()).collect();
+        assert_eq!(ids, ["a1", "a2"]);
+
+        let filter = EventFilter {
+            from: Some(start + chrono::Duration::seconds(2)),
+            to: Some(start + chrono::Duration::seconds(3)),
+            ..EventFilter::aggregate("a2")
+        };
+        let (events, total) = p.fetch_events_paged(&filter, 1, 10).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(
+            (events[0].aggregate_id.as_str(), events[0].version),
+            ("a2", 2)
+        );
+
+        let (events, total) = p
+            .fetch_events_paged(&EventFilter::default(), 2, 4)
+            .await
+            .unwrap();
+        assert_eq!((events.len(), total), (2, 6));
+    }
+
+    #[tokio::test]
+    async fn an_aggregate_s_page_is_in_version_order_whatever_the_clock_did() {
+        let p = setup();
+        let start = Utc::now();
+        let events: Vec<_> = (1..=3)
+            .map(|version| {
+                let mut envelope = envelope("a1", version, TestEvent::Incremented);
+                // The clock went back between each commit.
+                envelope.at = start - chrono::Duration::seconds(version as i64);
+                envelope
+            })
+            .collect();
+        commit(&p, events).await;
+
+        let (events, _) = p
+            .fetch_events_paged(&EventFilter::aggregate("a1"), 1, 10)
+            .await
+            .unwrap();
+        let versions: Vec<_> = events.iter().map(|e| e.version).collect();
+        assert_eq!(versions, [1, 2, 3]);
+
+        let (events, _) = p
+            .fetch_events_paged(&EventFilter::default(), 1, 10)
+            .await
+            .unwrap();
+        let versions: Vec<_> = events.iter().map(|e| e.version).collect();
+        assert_eq!(versions, [3, 2, 1], "the type-wide page follows the clock");
+    }
+
+    #[tokio::test]
+    async fn tenants_sharing_the_tables_only_see_their_own_aggregates() {
+        let connection = SharedConnection::open_in_memory().unwrap();
+        connection
+            .execute_batch(&SqlitePersist::<TestAggregate>::tenant_column_schema())
+            .unwrap();
+        let p = SqlitePersist::<TestAggregate>::new(connection).with_tenancy(Tenancy::Column);
+        let tenant = |tenant: &str| {
+            p.for_context(&CqrsContext::default().with_tenant(Some(tenant.to_string())))
+                .unwrap()
+        };
+        let (acme, globex) = (tenant("acme"), tenant("globex"));
+
+        // The same aggregate id, and version, in both tenants.
+        commit(&acme, vec![envelope("a1", 1, TestEvent::Incremented)]).await;
+        let mut decremented = envelope("a1", 1, TestEvent::Decremented);
+        decremented.event_id = "globex-a1-v1".to_string();
+        commit(&globex, vec![decremented]).await;
+
+        let rows = all_events(&acme, "a1").await;
+        assert_eq!(rows.len(), 1);
+        assert!(matches!(rows[0].payload, TestEvent::Incremented));
+
+        let error = p.for_context(&CqrsContext::default()).unwrap_err();
+        assert_eq!(error.status, 403);
+    }
+
+    #[tokio::test]
+    async fn a_journal_exported_as_ndjson_imports_into_another_database() {
+        use crate::es::journals::{export_ndjson, import_ndjson};
+
+        let p = setup();
+        commit(
+            &p,
+            vec![
+                envelope("a1", 1, TestEvent::Created { name: "foo".into() }),
+                envelope("a1", 2, TestEvent::Incremented),
+                envelope("a2", 1, TestEvent::Created { name: "bar".into() }),
+            ],
+        )
+        .await;
+        let aggregate = TestAggregate::default().with_aggregate_id("a1".to_string());
+        let mut session = p.start_session().await.unwrap();
+        p.save_snapshot(&aggregate, 2, &mut session).await.unwrap();
+        p.close_session(session).await.unwrap();
+
+        let source = SqliteJournals::new(p.connection.clone());
+        assert_eq!(
+            source.aggregate_types().await.unwrap(),
+            [TestAggregate::TYPE]
+        );
+        let tables = JournalTables::of(TestAggregate::TYPE).unwrap();
+        let mut dump = Vec::new();
+        assert_eq!(
+            export_ndjson(&source, &tables, 2, &mut dump).await.unwrap(),
+            (3, 1)
+        );
+
+        let target = setup();
+        let journals = SqliteJournals::new(target.connection.clone());
+        assert_eq!(
+            import_ndjson(&journals, &tables, 2, dump.as_slice())
+                .await
+                .unwrap(),
+            (3, 1)
+        );
+        let events = all_events(&target, "a1").await;
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event_id, "a1-v1");
+        assert_eq!(
+            target.fetch_snapshot("a1").await.unwrap().unwrap().version,
+            2
+        );
+        assert_eq!(
+            journals.journal(&tables, "a1").await.unwrap()[1]
+                .event_type
+                .as_deref(),
+            Some("Incremented")
+        );
+    }
+}
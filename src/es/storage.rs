@@ -1,4 +1,6 @@
-use crate::{Aggregate, CqrsError, EventEnvelope, MaybeSend, MaybeSync, Snapshot};
+use crate::{
+    Aggregate, CqrsContext, CqrsError, EventEnvelope, EventFilter, MaybeSend, MaybeSync, Snapshot,
+};
 use futures::stream::Stream;
 use std::pin::Pin;
 
@@ -14,6 +16,25 @@ where
 {
     type Session: MaybeSend + MaybeSync;
 
+    /// This storage, scoped to the request of `context`. `EventStoreImpl` calls it first
+    /// thing in every load and commit, and goes through what it returns.
+    ///
+    /// A storage configured with a [`Tenancy`](crate::Tenancy) resolves it here, and
+    /// refuses a context without a tenant; the default is the storage itself.
+    fn for_context(&self, context: &CqrsContext) -> Result<Self, CqrsError>
+    where
+        Self: Sized + Clone,
+    {
+        let _ = context;
+        Ok(self.clone())
+    }
+
+    /// Checks that the backend answers — a round trip, not a read of the journal — for
+    /// readiness probes. The default, for storages with nothing to reach, succeeds.
+    async fn health(&self) -> Result<(), CqrsError> {
+        Ok(())
+    }
+
     async fn start_session(&self) -> Result<Self::Session, CqrsError>;
     async fn close_session(&self, session: Self::Session) -> Result<(), CqrsError>;
     async fn fetch_snapshot(&self, aggregate_id: &str) -> Result<Option<Snapshot<A>>, CqrsError>;
@@ -26,9 +47,12 @@ where
 
     async fn fetch_all_events(&self, aggregate_id: &str) -> Result<EventStream<A>, CqrsError>;
 
+    /// A page of the events `filter` selects in the tenant in scope, from 1 — `0` reads
+    /// as `1` — oldest first, and how many it selects in all: in version order when the
+    /// filter names an aggregate, as [`EventFilter::sort`] orders them.
     async fn fetch_events_paged(
         &self,
-        aggregate_id: &str,
+        filter: &EventFilter,
         page: usize,
         page_size: usize,
     ) -> Result<(Vec<EventEnvelope<A>>, i64), CqrsError>;
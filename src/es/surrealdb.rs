@@ -1,13 +1,18 @@
 use crate::errors::CqrsError;
+use crate::es::journals::{JournalTables, Journals, RawEvent, RawSnapshot};
 use crate::es::storage::{EventStoreStorage, EventStream};
 use crate::snapshot::Snapshot;
-use crate::{Aggregate, EventEnvelope};
+use crate::{
+    Aggregate, CqrsContext, Event, EventEnvelope, EventFilter, TENANT_COLUMN, Tenancy, TenantScope,
+    USER_ID_METADATA,
+};
 use futures::stream;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use surrealdb::engine::any::Any;
+use surrealdb::method::Query;
 use surrealdb::Surreal;
 use surrealdb_types::{Datetime, RecordId, SurrealValue};
 
@@ -25,12 +30,16 @@ fn is_concurrency_error(e: &surrealdb::Error) -> bool {
 
 #[derive(Debug, Serialize, Deserialize, SurrealValue)]
 struct JournalInsert {
+    /// `NONE`, so absent, unless under [`Tenancy::Column`].
+    tenant_id: Option<String>,
     event_id: String,
     aggregate_id: String,
     version: i64,
     payload: JsonValue,
     metadata: JsonValue,
     at: Datetime,
+    /// Not part of the envelope; written for audit queries to filter on.
+    event_type: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, SurrealValue)]
@@ -89,6 +98,7 @@ where
     db: Surreal<Any>,
     snapshot_table: String,
     journal_table: String,
+    scope: TenantScope,
 }
 
 impl<A> SurrealDBPersist<A>
@@ -102,9 +112,19 @@ where
             db,
             snapshot_table: format!("{}_snapshots", A::TYPE),
             journal_table: format!("{}_journal", A::TYPE),
+            scope: TenantScope::default(),
         }
     }
 
+    /// Keeps each tenant's aggregates apart: a `tenant_id` field on every record
+    /// ([`Tenancy::Column`], see [`Self::tenant_column_schema`]), or tables of their own
+    /// ([`Tenancy::Table`], see [`Self::tenant_table_schema`]).
+    #[must_use]
+    pub fn with_tenancy(mut self, tenancy: Tenancy) -> Self {
+        self.scope = TenantScope::new(tenancy);
+        self
+    }
+
     pub fn snapshot_table(&self) -> &str {
         &self.snapshot_table
     }
@@ -120,16 +140,113 @@ where
     /// db.query(SurrealDBPersist::<MyAggregate>::schema()).await?.check()?;
     /// ```
     pub fn schema() -> String {
-        let snapshot_table = format!("{}_snapshots", A::TYPE);
-        let journal_table = format!("{}_journal", A::TYPE);
-        format!(
-            r#"DEFINE TABLE IF NOT EXISTS {snapshot_table} SCHEMALESS;
+        ddl(
+            &format!("{}_snapshots", A::TYPE),
+            &format!("{}_journal", A::TYPE),
+            false,
+        )
+    }
 
-DEFINE TABLE IF NOT EXISTS {journal_table} SCHEMALESS;
-DEFINE INDEX IF NOT EXISTS idx_{journal_table}_agg_ver ON {journal_table} FIELDS aggregate_id, version UNIQUE;
-DEFINE INDEX IF NOT EXISTS idx_{journal_table}_agg ON {journal_table} FIELDS aggregate_id;"#
+    /// [`Self::schema`] under [`Tenancy::Column`]: the journal's indexes lead with
+    /// `tenant_id`, so a version is only unique within an aggregate of one tenant.
+    pub fn tenant_column_schema() -> String {
+        ddl(
+            &format!("{}_snapshots", A::TYPE),
+            &format!("{}_journal", A::TYPE),
+            true,
         )
     }
+
+    /// [`Self::schema`] for one tenant under [`Tenancy::Table`]: its own
+    /// `{TYPE}_snapshots_{tenant}` and `{TYPE}_journal_{tenant}`.
+    pub fn tenant_table_schema(tenant: &str) -> Result<String, CqrsError> {
+        let tenant = crate::validated_tenant(tenant)?;
+        Ok(ddl(
+            &crate::tenant_table(&format!("{}_snapshots", A::TYPE), tenant),
+            &crate::tenant_table(&format!("{}_journal", A::TYPE), tenant),
+            false,
+        ))
+    }
+
+    /// The tables of the tenant in scope, and the condition narrowing a query to it
+    /// under [`Tenancy::Column`] — bind `$tenant` to [`TenantScope::column`].
+    fn scoped(&self) -> Result<Scoped, CqrsError> {
+        let tenant = self.scope.column()?.map(str::to_string);
+        Ok(Scoped {
+            snapshot_table: self.scope.table(&self.snapshot_table)?,
+            journal_table: self.scope.table(&self.journal_table)?,
+            condition: if tenant.is_some() {
+                format!(" AND {TENANT_COLUMN} = $tenant")
+            } else {
+                String::new()
+            },
+            tenant,
+        })
+    }
+}
+
+struct Scoped {
+    snapshot_table: String,
+    journal_table: String,
+    condition: String,
+    tenant: Option<String>,
+}
+
+fn ddl(snapshot_table: &str, journal_table: &str, tenant_column: bool) -> String {
+    let key = if tenant_column {
+        format!("{TENANT_COLUMN}, ")
+    } else {
+        String::new()
+    };
+    format!(
+        r#"DEFINE TABLE IF NOT EXISTS {snapshot_table} SCHEMALESS;
+
+DEFINE TABLE IF NOT EXISTS {journal_table} SCHEMALESS;
+DEFINE INDEX IF NOT EXISTS idx_{journal_table}_agg_ver ON {journal_table} FIELDS {key}aggregate_id, version UNIQUE;
+DEFINE INDEX IF NOT EXISTS idx_{journal_table}_agg ON {journal_table} FIELDS {key}aggregate_id;"#
+    )
+}
+
+/// The conditions of `filter`, over `$id`, `$types`, `$user`, `$from` and `$to`.
+fn filter_conditions(filter: &EventFilter) -> String {
+    let mut conditions = Vec::new();
+    if filter.aggregate_id.is_some() {
+        conditions.push("aggregate_id = $id".to_string());
+    }
+    if !filter.event_types.is_empty() {
+        conditions.push("event_type INSIDE $types".to_string());
+    }
+    if filter.user_id.is_some() {
+        conditions.push(format!("metadata.{USER_ID_METADATA} = $user"));
+    }
+    if filter.from.is_some() {
+        conditions.push("at >= $from".to_string());
+    }
+    if filter.to.is_some() {
+        conditions.push("at < $to".to_string());
+    }
+    conditions
+        .into_iter()
+        .map(|condition| format!(" AND {condition}"))
+        .collect()
+}
+
+/// The `ORDER BY` of a page of `filter`'s selection, as [`EventFilter::sort`] orders it.
+fn page_order(filter: &EventFilter) -> &'static str {
+    if filter.aggregate_id.is_some() {
+        "version ASC"
+    } else {
+        "at ASC, aggregate_id ASC, version ASC"
+    }
+}
+
+fn bind_filter<'a>(query: Query<'a, Any>, filter: &EventFilter) -> Query<'a, Any> {
+    query
+        .bind(("id", filter.aggregate_id.clone()))
+        .bind(("types", filter.event_types.clone()))
+        .bind(("user", filter.user_id.clone()))
+        .bind(("from", filter.from.map(Datetime::from)))
+        .bind(("to", filter.to.map(Datetime::from)))
 }
 
 // ─── Implementation ───────────────────────────────────────────────────────────
@@ -140,6 +257,17 @@ where
 {
     type Session = ();
 
+    fn for_context(&self, context: &CqrsContext) -> Result<Self, CqrsError> {
+        Ok(Self {
+            scope: self.scope.for_context(context)?,
+            ..self.clone()
+        })
+    }
+
+    async fn health(&self) -> Result<(), CqrsError> {
+        self.db.health().await.map_err(map_surreal_error)
+    }
+
     async fn start_session(&self) -> Result<Self::Session, CqrsError> {
         Ok(())
     }
@@ -150,14 +278,16 @@ where
 
     async fn fetch_snapshot(&self, aggregate_id: &str) -> Result<Option<Snapshot<A>>, CqrsError> {
         let id = aggregate_id.to_string();
+        let scoped = self.scoped()?;
         let sql = format!(
-            "SELECT aggregate_id, data, version FROM {} WHERE aggregate_id = $id LIMIT 1",
-            self.snapshot_table
+            "SELECT aggregate_id, data, version FROM {} WHERE aggregate_id = $id{} LIMIT 1",
+            scoped.snapshot_table, scoped.condition
         );
         let mut result = self
             .db
             .query(sql)
             .bind(("id", id.clone()))
+            .bind(("tenant", scoped.tenant))
             .await
             .map_err(map_surreal_error)?;
         let rows: Vec<SnapshotRow> = result.take(0).map_err(map_surreal_error)?;
@@ -181,14 +311,16 @@ where
         version: usize,
     ) -> Result<EventStream<A>, CqrsError> {
         let id = aggregate_id.to_string();
+        let scoped = self.scoped()?;
         let sql = format!(
-            "SELECT * FROM {} WHERE aggregate_id = $id AND version > $ver ORDER BY version ASC",
-            self.journal_table
+            "SELECT * FROM {} WHERE aggregate_id = $id AND version > $ver{} ORDER BY version ASC",
+            scoped.journal_table, scoped.condition
         );
         let mut result = self
             .db
             .query(sql)
             .bind(("id", id))
+            .bind(("tenant", scoped.tenant))
             .bind(("ver", version as i64))
             .await
             .map_err(map_surreal_error)?;
@@ -199,14 +331,16 @@ where
 
     async fn fetch_all_events(&self, aggregate_id: &str) -> Result<EventStream<A>, CqrsError> {
         let id = aggregate_id.to_string();
+        let scoped = self.scoped()?;
         let sql = format!(
-            "SELECT * FROM {} WHERE aggregate_id = $id ORDER BY version ASC",
-            self.journal_table
+            "SELECT * FROM {} WHERE aggregate_id = $id{} ORDER BY version ASC",
+            scoped.journal_table, scoped.condition
         );
         let mut result = self
             .db
             .query(sql)
             .bind(("id", id))
+            .bind(("tenant", scoped.tenant))
             .await
             .map_err(map_surreal_error)?;
         let rows: Vec<JournalRow> = result.take(0).map_err(map_surreal_error)?;
@@ -216,19 +350,18 @@ where
 
     async fn fetch_events_paged(
         &self,
-        aggregate_id: &str,
+        filter: &EventFilter,
         page: usize,
         page_size: usize,
     ) -> Result<(Vec<EventEnvelope<A>>, i64), CqrsError> {
-        let id = aggregate_id.to_string();
+        let scoped = self.scoped()?;
+        let condition = format!("true{}{}", filter_conditions(filter), scoped.condition);
         let count_sql = format!(
-            "SELECT count() AS cnt FROM {} WHERE aggregate_id = $id GROUP ALL",
-            self.journal_table
+            "SELECT count() AS cnt FROM {} WHERE {condition} GROUP ALL",
+            scoped.journal_table
         );
-        let mut r = self
-            .db
-            .query(count_sql)
-            .bind(("id", id.clone()))
+        let mut r = bind_filter(self.db.query(count_sql), filter)
+            .bind(("tenant", scoped.tenant.clone()))
             .await
             .map_err(map_surreal_error)?;
         let counts: Vec<CountRow> = r.take(0).map_err(map_surreal_error)?;
@@ -236,13 +369,12 @@ where
 
         let offset = (page.max(1) - 1) * page_size;
         let sql = format!(
-            "SELECT * FROM {} WHERE aggregate_id = $id ORDER BY version ASC LIMIT $limit START $offset",
-            self.journal_table
+            "SELECT * FROM {} WHERE {condition} ORDER BY {} LIMIT $limit START $offset",
+            scoped.journal_table,
+            page_order(filter)
         );
-        let mut result = self
-            .db
-            .query(sql)
-            .bind(("id", id))
+        let mut result = bind_filter(self.db.query(sql), filter)
+            .bind(("tenant", scoped.tenant))
             .bind(("limit", page_size as i64))
             .bind(("offset", offset as i64))
             .await
@@ -258,14 +390,16 @@ where
         _session: &Self::Session,
     ) -> Result<Option<EventEnvelope<A>>, CqrsError> {
         let id = aggregate.aggregate_id();
+        let scoped = self.scoped()?;
         let sql = format!(
-            "SELECT * FROM {} WHERE aggregate_id = $id ORDER BY version DESC LIMIT 1",
-            self.journal_table
+            "SELECT * FROM {} WHERE aggregate_id = $id{} ORDER BY version DESC LIMIT 1",
+            scoped.journal_table, scoped.condition
         );
         let mut result = self
             .db
             .query(sql)
             .bind(("id", id))
+            .bind(("tenant", scoped.tenant))
             .await
             .map_err(map_surreal_error)?;
         let rows: Vec<JournalRow> = result.take(0).map_err(map_surreal_error)?;
@@ -283,6 +417,7 @@ where
         if events.is_empty() {
             return Ok(());
         }
+        let scoped = self.scoped()?;
         let inserts: Vec<JournalInsert> = events
             .iter()
             .map(|e| {
@@ -291,17 +426,19 @@ where
                 let metadata =
                     serde_json::to_value(&e.metadata).map_err(CqrsError::serialization_error)?;
                 Ok(JournalInsert {
+                    tenant_id: scoped.tenant.clone(),
                     event_id: e.event_id.clone(),
                     aggregate_id: e.aggregate_id.clone(),
                     version: e.version as i64,
                     payload,
                     metadata,
                     at: e.at.into(),
+                    event_type: e.payload.event_type(),
                 })
             })
             .collect::<Result<_, CqrsError>>()?;
 
-        let sql = format!("INSERT INTO {} $events", self.journal_table);
+        let sql = format!("INSERT INTO {} $events", scoped.journal_table);
         self.db
             .query(sql)
             .bind(("events", inserts))
@@ -326,15 +463,24 @@ where
     ) -> Result<(), CqrsError> {
         let data = serde_json::to_value(aggregate).map_err(CqrsError::serialization_error)?;
         let id = aggregate.aggregate_id();
-        let table = self.snapshot_table.clone();
+        let scoped = self.scoped()?;
         // Use UPSERT with a deterministic record ID derived from aggregate_id.
         // type::record($table, $id) constructs a RecordId that serves as the primary key,
-        // giving us atomic create-or-replace semantics without a separate index.
+        // giving us atomic create-or-replace semantics without a separate index. Under
+        // `Tenancy::Column` the key is `[tenant, aggregate_id]`, so that two tenants'
+        // aggregates of the same id are two records.
+        let sql = match scoped.tenant {
+            Some(_) => format!(
+                "UPSERT type::record($table, [$tenant, $id]) SET {TENANT_COLUMN} = $tenant, \
+                 aggregate_id = $id, data = $data, version = $ver"
+            ),
+            None => "UPSERT type::record($table, $id) SET aggregate_id = $id, data = $data, version = $ver"
+                .to_string(),
+        };
         self.db
-            .query(
-                "UPSERT type::record($table, $id) SET aggregate_id = $id, data = $data, version = $ver",
-            )
-            .bind(("table", table))
+            .query(sql)
+            .bind(("table", scoped.snapshot_table))
+            .bind(("tenant", scoped.tenant))
             .bind(("id", id))
             .bind(("data", data))
             .bind(("ver", version as i64))
@@ -347,6 +493,258 @@ where
 }
 }
 
+/// An event as [`SurrealDBJournals`] reads and writes it, its type and tenant optional.
+#[derive(Debug, Serialize, Deserialize, SurrealValue)]
+struct RawJournalRecord {
+    tenant_id: Option<String>,
+    event_id: String,
+    aggregate_id: String,
+    version: i64,
+    payload: JsonValue,
+    metadata: JsonValue,
+    at: Datetime,
+    event_type: Option<String>,
+}
+
+impl TryFrom<RawJournalRecord> for RawEvent {
+    type Error = CqrsError;
+
+    fn try_from(record: RawJournalRecord) -> Result<Self, Self::Error> {
+        Ok(Self {
+            event_id: record.event_id,
+            aggregate_id: record.aggregate_id,
+            version: record.version as usize,
+            payload: record.payload,
+            metadata: serde_json::from_value(record.metadata)
+                .map_err(CqrsError::serialization_error)?,
+            at: record.at.into(),
+            event_type: record.event_type,
+            tenant_id: record.tenant_id,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, SurrealValue)]
+struct RawSnapshotRecord {
+    tenant_id: Option<String>,
+    aggregate_id: String,
+    data: JsonValue,
+    version: i64,
+}
+
+/// Every journal of a SurrealDB database, for operational tools; see
+/// [`Journals`](crate::es::journals::Journals).
+#[derive(Clone, Debug)]
+pub struct SurrealDBJournals {
+    db: Surreal<Any>,
+}
+
+impl SurrealDBJournals {
+    #[must_use]
+    pub fn new(db: Surreal<Any>) -> Self {
+        Self { db }
+    }
+
+    /// The DDL of [`SurrealDBPersist::schema`] for `tables`, with the tenant leading the
+    /// indexes when `tenant_column`.
+    pub fn schema(tables: &JournalTables, tenant_column: bool) -> String {
+        ddl(&tables.snapshots, &tables.journal, tenant_column)
+    }
+}
+
+cqrs_async_trait! {
+impl Journals for SurrealDBJournals {
+    async fn aggregate_types(&self) -> Result<Vec<String>, CqrsError> {
+        let mut result = self
+            .db
+            .query("INFO FOR DB")
+            .await
+            .map_err(map_surreal_error)?;
+        let info: Option<JsonValue> = result.take(0).map_err(map_surreal_error)?;
+        let mut types: Vec<String> = info
+            .as_ref()
+            .and_then(|info| info.get("tables"))
+            .and_then(JsonValue::as_object)
+            .map(|tables| {
+                tables
+                    .keys()
+                    .filter_map(|name| JournalTables::aggregate_type(name).map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+        types.sort();
+        Ok(types)
+    }
+
+    async fn journal(
+        &self,
+        tables: &JournalTables,
+        aggregate_id: &str,
+    ) -> Result<Vec<RawEvent>, CqrsError> {
+        let sql = format!(
+            "SELECT tenant_id, event_id, aggregate_id, version, payload, metadata, at, event_type \
+             FROM {} WHERE aggregate_id = $id ORDER BY tenant_id, version",
+            tables.journal
+        );
+        let mut result = self
+            .db
+            .query(sql)
+            .bind(("id", aggregate_id.to_string()))
+            .await
+            .map_err(map_surreal_error)?;
+        let records: Vec<RawJournalRecord> = result.take(0).map_err(map_surreal_error)?;
+        records.into_iter().map(RawEvent::try_from).collect()
+    }
+
+    async fn snapshot(
+        &self,
+        tables: &JournalTables,
+        aggregate_id: &str,
+    ) -> Result<Vec<RawSnapshot>, CqrsError> {
+        let sql = format!(
+            "SELECT tenant_id, aggregate_id, data, version FROM {} WHERE aggregate_id = $id \
+             ORDER BY tenant_id",
+            tables.snapshots
+        );
+        let mut result = self
+            .db
+            .query(sql)
+            .bind(("id", aggregate_id.to_string()))
+            .await
+            .map_err(map_surreal_error)?;
+        let records: Vec<RawSnapshotRecord> = result.take(0).map_err(map_surreal_error)?;
+        Ok(records.into_iter().map(RawSnapshot::from).collect())
+    }
+
+    async fn events(
+        &self,
+        tables: &JournalTables,
+        skip: usize,
+        limit: usize,
+    ) -> Result<Vec<RawEvent>, CqrsError> {
+        let sql = format!(
+            "SELECT tenant_id, event_id, aggregate_id, version, payload, metadata, at, event_type \
+             FROM {} ORDER BY tenant_id, aggregate_id, version LIMIT $limit START $skip",
+            tables.journal
+        );
+        let mut result = self
+            .db
+            .query(sql)
+            .bind(("limit", limit as i64))
+            .bind(("skip", skip as i64))
+            .await
+            .map_err(map_surreal_error)?;
+        let records: Vec<RawJournalRecord> = result.take(0).map_err(map_surreal_error)?;
+        records.into_iter().map(RawEvent::try_from).collect()
+    }
+
+    async fn snapshots(
+        &self,
+        tables: &JournalTables,
+        skip: usize,
+        limit: usize,
+    ) -> Result<Vec<RawSnapshot>, CqrsError> {
+        let sql = format!(
+            "SELECT tenant_id, aggregate_id, data, version FROM {} \
+             ORDER BY tenant_id, aggregate_id LIMIT $limit START $skip",
+            tables.snapshots
+        );
+        let mut result = self
+            .db
+            .query(sql)
+            .bind(("limit", limit as i64))
+            .bind(("skip", skip as i64))
+            .await
+            .map_err(map_surreal_error)?;
+        let records: Vec<RawSnapshotRecord> = result.take(0).map_err(map_surreal_error)?;
+        Ok(records.into_iter().map(RawSnapshot::from).collect())
+    }
+
+    /// One `INSERT` statement, which SurrealDB applies whole or not at all.
+    async fn import_events(
+        &self,
+        tables: &JournalTables,
+        events: Vec<RawEvent>,
+    ) -> Result<(), CqrsError> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        let records = events
+            .into_iter()
+            .map(|event| {
+                Ok(RawJournalRecord {
+                    tenant_id: event.tenant_id,
+                    event_id: event.event_id,
+                    aggregate_id: event.aggregate_id,
+                    version: event.version as i64,
+                    payload: event.payload,
+                    metadata: serde_json::to_value(&event.metadata)
+                        .map_err(CqrsError::serialization_error)?,
+                    at: event.at.into(),
+                    event_type: event.event_type,
+                })
+            })
+            .collect::<Result<Vec<_>, CqrsError>>()?;
+        let sql = format!("INSERT INTO {} $events", tables.journal);
+        self.db
+            .query(sql)
+            .bind(("events", records))
+            .await
+            .map_err(map_surreal_error)?
+            .check()
+            .map_err(|e| {
+                if is_concurrency_error(&e) {
+                    CqrsError::concurrency_error()
+                } else {
+                    CqrsError::database_error(e)
+                }
+            })?;
+        Ok(())
+    }
+
+    /// Upserts each snapshot under the record id [`SurrealDBPersist`] gives it.
+    async fn import_snapshots(
+        &self,
+        tables: &JournalTables,
+        snapshots: Vec<RawSnapshot>,
+    ) -> Result<(), CqrsError> {
+        for snapshot in snapshots {
+            let sql = match snapshot.tenant_id {
+                Some(_) => format!(
+                    "UPSERT type::record($table, [$tenant, $id]) SET {TENANT_COLUMN} = $tenant, \
+                     aggregate_id = $id, data = $data, version = $ver"
+                ),
+                None => "UPSERT type::record($table, $id) SET aggregate_id = $id, data = $data, version = $ver"
+                    .to_string(),
+            };
+            self.db
+                .query(sql)
+                .bind(("table", tables.snapshots.clone()))
+                .bind(("tenant", snapshot.tenant_id))
+                .bind(("id", snapshot.aggregate_id))
+                .bind(("data", snapshot.state))
+                .bind(("ver", snapshot.version as i64))
+                .await
+                .map_err(map_surreal_error)?
+                .check()
+                .map_err(map_surreal_error)?;
+        }
+        Ok(())
+    }
+}
+}
+
+impl From<RawSnapshotRecord> for RawSnapshot {
+    fn from(record: RawSnapshotRecord) -> Self {
+        Self {
+            aggregate_id: record.aggregate_id,
+            state: record.data,
+            version: record.version as usize,
+            tenant_id: record.tenant_id,
+        }
+    }
+}
+
 // ─── Tests ───────────────────────────────────────────────────────────────────
 #[cfg(test)]
 mod tests {
@@ -385,6 +783,57 @@ mod tests {
         }
     }
 
+    #[test]
+    fn each_filter_field_adds_its_own_condition() {
+        let at = Utc::now();
+        let event_types = EventFilter {
+            event_types: vec!["Created".to_string()],
+            ..EventFilter::default()
+        };
+        assert_eq!(
+            filter_conditions(&event_types),
+            " AND event_type INSIDE $types"
+        );
+        let user = EventFilter {
+            user_id: Some("ada".to_string()),
+            ..EventFilter::default()
+        };
+        assert_eq!(filter_conditions(&user), " AND metadata.user_id = $user");
+        let window = EventFilter {
+            from: Some(at),
+            to: Some(at),
+            ..EventFilter::default()
+        };
+        assert_eq!(filter_conditions(&window), " AND at >= $from AND at < $to");
+        assert_eq!(filter_conditions(&EventFilter::default()), "");
+    }
+
+    #[test]
+    fn a_combined_filter_adds_every_condition() {
+        let at = Utc::now();
+        let filter = EventFilter {
+            aggregate_id: Some("a1".to_string()),
+            event_types: vec!["Created".to_string()],
+            user_id: Some("ada".to_string()),
+            from: Some(at),
+            to: Some(at),
+        };
+        assert_eq!(
+            filter_conditions(&filter),
+            " AND aggregate_id = $id AND event_type INSIDE $types \
+             AND metadata.user_id = $user AND at >= $from AND at < $to"
+        );
+    }
+
+    #[test]
+    fn one_aggregate_pages_by_version_and_the_type_by_time() {
+        assert_eq!(page_order(&EventFilter::aggregate("a1")), "version ASC");
+        assert_eq!(
+            page_order(&EventFilter::default()),
+            "at ASC, aggregate_id ASC, version ASC"
+        );
+    }
+
     #[tokio::test]
     async fn save_and_fetch_all_events() {
         let p = setup().await;
@@ -439,17 +888,75 @@ mod tests {
         .await
         .unwrap();
 
-        let (page1, total) = p.fetch_events_paged("a1", 1, 2).await.unwrap();
+        let (page1, total) = p
+            .fetch_events_paged(&EventFilter::aggregate("a1"), 1, 2)
+            .await
+            .unwrap();
         assert_eq!(total, 5);
         assert_eq!(page1.len(), 2);
         assert_eq!(page1[0].version, 1);
         assert_eq!(page1[1].version, 2);
 
-        let (page2, _) = p.fetch_events_paged("a1", 2, 2).await.unwrap();
+        let (page2, _) = p
+            .fetch_events_paged(&EventFilter::aggregate("a1"), 2, 2)
+            .await
+            .unwrap();
         assert_eq!(page2.len(), 2);
         assert_eq!(page2[0].version, 3);
     }
 
+    #[tokio::test]
+    async fn fetch_events_paged_filters_across_aggregates() {
+        let p = setup().await;
+        let start = Utc::now();
+        let by = |aggregate_id: &str, version, event, user: &str| {
+            let mut envelope = envelope(aggregate_id, version, event);
+            envelope.metadata = HashMap::from([("user_id".to_string(), user.to_string())]);
+            envelope.at = start + chrono::Duration::seconds(version as i64);
+            envelope
+        };
+        for aggregate_id in ["a1", "a2"] {
+            p.save_events(
+                vec![
+                    by(
+                        aggregate_id,
+                        1,
+                        TestEvent::Created { name: "x".into() },
+                        "alice",
+                    ),
+                    by(aggregate_id, 2, TestEvent::Incremented, "bob"),
+                    by(aggregate_id, 3, TestEvent::Incremented, "alice"),
+                ],
+                &mut (),
+            )
+            .await
+            .unwrap();
+        }
+
+        let filter = EventFilter {
+            event_types: vec!["Incremented".to_string()],
+            user_id: Some("alice".to_string()),
+            ..EventFilter::default()
+        };
+        let (events, total) = p.fetch_events_paged(&filter, 1, 10).await.unwrap();
+        assert_eq!(total, 2);
+        let ids: Vec<_> = events.iter().map(|e| e.aggregate_id.as_str()).collect();
+        assert_eq!(ids, ["a1", "a2"]);
+        assert!(events.iter().all(|e| e.version == 3));
+
+        let filter = EventFilter {
+            from: Some(start + chrono::Duration::seconds(2)),
+            to: Some(start + chrono::Duration::seconds(3)),
+            ..EventFilter::aggregate("a2")
+        };
+        let (events, total) = p.fetch_events_paged(&filter, 1, 10).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(
+            (events[0].aggregate_id.as_str(), events[0].version),
+            ("a2", 2)
+        );
+    }
+
     #[tokio::test]
     async fn fetch_latest_event_returns_highest_version() {
         let p = setup().await;
@@ -469,6 +976,12 @@ mod tests {
         assert_eq!(latest.unwrap().version, 3);
     }
 
+    #[tokio::test]
+    async fn health_answers_on_a_live_database() {
+        let p = setup().await;
+        assert!(p.health().await.is_ok());
+    }
+
     #[tokio::test]
     async fn fetch_latest_event_none_when_empty() {
         let p = setup().await;
@@ -538,4 +1051,107 @@ mod tests {
         assert_eq!(rows.len(), 1);
         assert!(matches!(rows[0].payload, TestEvent::Incremented));
     }
+
+    #[tokio::test]
+    async fn tenants_sharing_the_tables_only_see_their_own_aggregates() {
+        let db = connect("mem://").await.unwrap();
+        db.use_ns("test").use_db("test").await.unwrap();
+        db.query(SurrealDBPersist::<TestAggregate>::tenant_column_schema())
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+        let p = SurrealDBPersist::<TestAggregate>::new(db).with_tenancy(Tenancy::Column);
+        let tenant = |tenant: &str| {
+            p.for_context(&CqrsContext::default().with_tenant(Some(tenant.to_string())))
+                .unwrap()
+        };
+        let (acme, globex) = (tenant("acme"), tenant("globex"));
+
+        // The same aggregate id, and version, in both tenants.
+        acme.save_events(vec![envelope("a1", 1, TestEvent::Incremented)], &mut ())
+            .await
+            .unwrap();
+        globex
+            .save_events(vec![envelope("a1", 1, TestEvent::Decremented)], &mut ())
+            .await
+            .unwrap();
+        let agg = TestAggregate::default().with_aggregate_id("a1".to_string());
+        acme.save_snapshot(&agg, 1, &mut ()).await.unwrap();
+
+        let rows: Vec<_> = acme
+            .fetch_all_events("a1")
+            .await
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect()
+            .await;
+        assert_eq!(rows.len(), 1);
+        assert!(matches!(rows[0].payload, TestEvent::Incremented));
+        assert_eq!(acme.fetch_snapshot("a1").await.unwrap().unwrap().version, 1);
+        assert!(globex.fetch_snapshot("a1").await.unwrap().is_none());
+
+        let error = p.for_context(&CqrsContext::default()).unwrap_err();
+        assert_eq!(error.status, 403);
+    }
+
+    #[tokio::test]
+    async fn a_journal_exported_as_ndjson_imports_into_another_database() {
+        use crate::es::journals::{export_ndjson, import_ndjson, ContinuityCheck};
+
+        let p = setup().await;
+        p.save_events(
+            vec![
+                envelope("a1", 1, TestEvent::Created { name: "foo".into() }),
+                envelope("a1", 2, TestEvent::Incremented),
+                envelope("a2", 1, TestEvent::Created { name: "bar".into() }),
+            ],
+            &mut (),
+        )
+        .await
+        .unwrap();
+        let aggregate = TestAggregate::default().with_aggregate_id("a1".to_string());
+        p.save_snapshot(&aggregate, 2, &mut ()).await.unwrap();
+
+        let source = SurrealDBJournals::new(p.db.clone());
+        assert_eq!(
+            source.aggregate_types().await.unwrap(),
+            [TestAggregate::TYPE]
+        );
+        let tables = JournalTables::of(TestAggregate::TYPE).unwrap();
+        let mut dump = Vec::new();
+        assert_eq!(
+            export_ndjson(&source, &tables, 2, &mut dump).await.unwrap(),
+            (3, 1)
+        );
+
+        let target = setup().await;
+        let journals = SurrealDBJournals::new(target.db.clone());
+        assert_eq!(
+            import_ndjson(&journals, &tables, 2, dump.as_slice())
+                .await
+                .unwrap(),
+            (3, 1)
+        );
+        let events: Vec<_> = target
+            .fetch_all_events("a1")
+            .await
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect()
+            .await;
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event_id, "a1-v1");
+        assert_eq!(
+            target.fetch_snapshot("a1").await.unwrap().unwrap().version,
+            2
+        );
+
+        let mut check = ContinuityCheck::new();
+        for event in journals.events(&tables, 0, 10).await.unwrap() {
+            check.push(&event);
+        }
+        assert_eq!(check.checked(), (2, 3));
+        assert!(check.issues().is_empty());
+    }
 }
@@ -0,0 +1,539 @@
+//! Every event and snapshot of an aggregate type moved between event stores — from
+//! MongoDB to PostgreSQL, or from production to staging — through a portable dump.
+//!
+//! A dump is the NDJSON of [`journals`](crate::es::journals): each [`EventEnvelope`] as it
+//! serializes, then each [`Snapshot`], then a [`DumpSummary`] counting them, optionally
+//! gzipped (feature `gzip`). The `cqrs-admin` binary reads and writes the same files.
+//!
+//! ```rust,ignore
+//! let transfer = EventStoreTransfer::<Account, _>::new(mongo_persist);
+//! let summary = transfer.export(&context, File::create("account.ndjson")?).await?;
+//!
+//! let summary = EventStoreTransfer::<Account, _>::new(postgres_persist)
+//!     .import(&context, File::open("account.ndjson")?)
+//!     .await?;
+//! ```
+
+use crate::es::journals::{DEFAULT_JOURNAL_BATCH_SIZE, DumpSummary};
+use crate::es::storage::EventStoreStorage;
+use crate::{
+    Aggregate, CqrsContext, CqrsError, EventEnvelope, EventFilter, MaybeSend, MaybeSync, Snapshot,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::marker::PhantomData;
+
+/// The first two bytes of a gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// A line of a dump, typed: the same JSON as a
+/// [`RawRecord`](crate::es::journals::RawRecord).
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", bound = "")]
+enum Record<A>
+where
+    A: Aggregate,
+{
+    Event(EventEnvelope<A>),
+    Snapshot(Snapshot<A>),
+    Summary(DumpSummary),
+}
+
+fn io_error(e: std::io::Error) -> CqrsError {
+    CqrsError::internal(e.to_string())
+}
+
+/// Exports the events and snapshots of a storage to a dump, and imports a dump into one.
+///
+/// Both are scoped to the tenant of the [`CqrsContext`] given, as loads and commits are.
+/// An import writes each aggregate's events as a commit does: only after the version
+/// its journal is at, so that importing twice, or over existing aggregates, fails with
+/// `409` instead of forking a journal.
+#[derive(Clone, Debug)]
+pub struct EventStoreTransfer<A, S> {
+    _phantom: PhantomData<A>,
+    storage: S,
+    batch_size: usize,
+    #[cfg(feature = "gzip")]
+    gzip: bool,
+}
+
+impl<A, S> EventStoreTransfer<A, S>
+where
+    A: Aggregate + 'static,
+    S: EventStoreStorage<A> + Clone + MaybeSend + MaybeSync,
+{
+    #[must_use]
+    pub fn new(storage: S) -> Self {
+        Self {
+            _phantom: PhantomData,
+            storage,
+            batch_size: DEFAULT_JOURNAL_BATCH_SIZE,
+            #[cfg(feature = "gzip")]
+            gzip: false,
+        }
+    }
+
+    /// How many events are read, or written, at a time; [`DEFAULT_JOURNAL_BATCH_SIZE`]
+    /// otherwise.
+    #[must_use]
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Gzips the dumps written. Imports detect a gzipped dump by themselves.
+    #[cfg(feature = "gzip")]
+    #[must_use]
+    pub fn with_gzip(mut self) -> Self {
+        self.gzip = true;
+        self
+    }
+
+    /// Writes every event of the aggregate type, oldest first, then the snapshot of each
+    /// aggregate met, then their [`DumpSummary`], which it returns.
+    pub async fn export<W>(
+        &self,
+        context: &CqrsContext,
+        output: W,
+    ) -> Result<DumpSummary, CqrsError>
+    where
+        W: Write,
+    {
+        #[cfg(feature = "gzip")]
+        if self.gzip {
+            let mut encoder = flate2::write::GzEncoder::new(output, flate2::Compression::default());
+            let summary = self.write_dump(context, &mut encoder).await?;
+            encoder.finish().map_err(io_error)?;
+            return Ok(summary);
+        }
+        let mut output = output;
+        self.write_dump(context, &mut output).await
+    }
+
+    async fn write_dump<W>(
+        &self,
+        context: &CqrsContext,
+        output: &mut W,
+    ) -> Result<DumpSummary, CqrsError>
+    where
+        W: Write,
+    {
+        let storage = self.storage.for_context(context)?;
+        let mut write = |record: &Record<A>| -> Result<(), CqrsError> {
+            serde_json::to_writer(&mut *output, record).map_err(CqrsError::serialization_error)?;
+            output.write_all(b"\n").map_err(io_error)
+        };
+        let mut summary = DumpSummary::default();
+        let mut aggregate_ids = BTreeSet::new();
+        for page in 1.. {
+            let (events, _) = storage
+                .fetch_events_paged(&EventFilter::default(), page, self.batch_size)
+                .await?;
+            let read = events.len();
+            for event in events {
+                aggregate_ids.insert(event.aggregate_id.clone());
+                write(&Record::Event(event))?;
+            }
+            summary.events += read;
+            if read < self.batch_size {
+                break;
+            }
+        }
+        for aggregate_id in &aggregate_ids {
+            if let Some(snapshot) = storage.fetch_snapshot(aggregate_id).await? {
+                write(&Record::Snapshot(snapshot))?;
+                summary.snapshots += 1;
+            }
+        }
+        write(&Record::Summary(summary))?;
+        output.flush().map_err(io_error)?;
+        Ok(summary)
+    }
+
+    /// Writes the records of a dump — gzipped or not — with their ids, versions, metadata
+    /// and timestamps, and returns how many events and snapshots it wrote.
+    ///
+    /// The records are checked against the dump's [`DumpSummary`], which must be its last
+    /// line, so that a truncated dump fails; the batches written before it stay written.
+    pub async fn import<R>(&self, context: &CqrsContext, input: R) -> Result<DumpSummary, CqrsError>
+    where
+        R: Read,
+    {
+        let mut input = BufReader::new(input);
+        let gzipped = input.fill_buf().map_err(io_error)?.starts_with(&GZIP_MAGIC);
+        if gzipped {
+            #[cfg(feature = "gzip")]
+            {
+                let input = BufReader::new(flate2::read::MultiGzDecoder::new(input));
+                return self.read_dump(context, input).await;
+            }
+            #[cfg(not(feature = "gzip"))]
+            return Err(CqrsError::user_error(
+                "the dump is gzipped, which needs the `gzip` feature",
+            ));
+        }
+        self.read_dump(context, input).await
+    }
+
+    async fn read_dump<R>(&self, context: &CqrsContext, input: R) -> Result<DumpSummary, CqrsError>
+    where
+        R: BufRead,
+    {
+        let storage = self.storage.for_context(context)?;
+        let mut written = DumpSummary::default();
+        let mut read = DumpSummary::default();
+        let mut events = Vec::new();
+        let mut snapshots = Vec::new();
+        let mut summarized = false;
+        for (number, line) in input.lines().enumerate() {
+            let line = line.map_err(io_error)?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if summarized {
+                return Err(CqrsError::user_error(format!(
+                    "line {}: the dump goes on after its summary",
+                    number + 1
+                )));
+            }
+            let record: Record<A> = serde_json::from_str(&line)
+                .map_err(|e| CqrsError::user_error(format!("line {}: {e}", number + 1)))?;
+            match record {
+                Record::Event(event) => {
+                    read.events += 1;
+                    events.push(event);
+                }
+                Record::Snapshot(snapshot) => {
+                    read.snapshots += 1;
+                    snapshots.push(snapshot);
+                }
+                Record::Summary(summary) => {
+                    summary.verify(&read)?;
+                    summarized = true;
+                }
+            }
+            if events.len() >= self.batch_size {
+                written.events += Self::save_events(&storage, std::mem::take(&mut events)).await?;
+            }
+            if snapshots.len() >= self.batch_size {
+                // A snapshot never lands before the events it was taken of, which may
+                // still be buffered.
+                written.events += Self::save_events(&storage, std::mem::take(&mut events)).await?;
+                written.snapshots +=
+                    Self::save_snapshots(&storage, std::mem::take(&mut snapshots)).await?;
+            }
+        }
+        // A dump cut short loses its summary first.
+        if !summarized {
+            return Err(CqrsError::user_error(
+                "the dump ends without its summary: it is truncated",
+            ));
+        }
+        written.events += Self::save_events(&storage, events).await?;
+        written.snapshots += Self::save_snapshots(&storage, snapshots).await?;
+        Ok(written)
+    }
+
+    /// Saves `events` an aggregate at a time, each in a session of its own that checks
+    /// the journal is at the version before the first of them.
+    async fn save_events(storage: &S, events: Vec<EventEnvelope<A>>) -> Result<usize, CqrsError> {
+        let mut order = Vec::new();
+        let mut by_aggregate: HashMap<String, Vec<EventEnvelope<A>>> = HashMap::new();
+        for event in events {
+            let aggregate_events = by_aggregate.entry(event.aggregate_id.clone()).or_default();
+            if aggregate_events.is_empty() {
+                order.push(event.aggregate_id.clone());
+            }
+            aggregate_events.push(event);
+        }
+        let mut saved = 0;
+        for aggregate_id in order {
+            let mut events = by_aggregate.remove(&aggregate_id).unwrap_or_default();
+            events.sort_by_key(|event| event.version);
+            if let Some(event) = events
+                .windows(2)
+                .find(|pair| pair[1].version != pair[0].version + 1)
+            {
+                return Err(CqrsError::user_error(format!(
+                    "aggregate {aggregate_id}: version {} follows {}",
+                    event[1].version, event[0].version
+                )));
+            }
+            let first_version = events.first().map_or(1, |event| event.version);
+            let aggregate = A::default().with_aggregate_id(aggregate_id);
+            let mut session = storage.start_session().await?;
+            let latest_version = match storage.fetch_latest_event(&aggregate, &session).await {
+                Ok(event) => event.map_or(0, |event| event.version),
+                Err(e) => {
+                    let _ = storage.abort_session(session).await;
+                    return Err(e);
+                }
+            };
+            if latest_version + 1 != first_version {
+                let _ = storage.abort_session(session).await;
+                return Err(CqrsError::concurrency_error());
+            }
+            saved += events.len();
+            if let Err(e) = storage.save_events(events, &mut session).await {
+                let _ = storage.abort_session(session).await;
+                return Err(e);
+            }
+            storage.close_session(session).await?;
+        }
+        Ok(saved)
+    }
+
+    async fn save_snapshots(storage: &S, snapshots: Vec<Snapshot<A>>) -> Result<usize, CqrsError> {
+        if snapshots.is_empty() {
+            return Ok(0);
+        }
+        let mut session = storage.start_session().await?;
+        for snapshot in &snapshots {
+            if let Err(e) = storage
+                .save_snapshot(&snapshot.state, snapshot.version, &mut session)
+                .await
+            {
+                let _ = storage.abort_session(session).await;
+                return Err(e);
+            }
+        }
+        storage.close_session(session).await?;
+        Ok(snapshots.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::es::EventStoreImpl;
+    use crate::es::inmemory::InMemoryPersist;
+    use crate::testing::{CreateCommand, TestAggregate, UpdateCommand};
+    use crate::{CqrsCommandEngine, EventStore};
+    use futures::TryStreamExt;
+
+    async fn source() -> (InMemoryPersist<TestAggregate>, Vec<String>) {
+        let persist = InMemoryPersist::new();
+        let engine = CqrsCommandEngine::new(
+            EventStoreImpl::new(persist.clone()),
+            vec![],
+            (),
+            Box::new(|_e| {}),
+        );
+        let context = CqrsContext::default();
+        let mut ids = Vec::new();
+        for name in ["a", "b"] {
+            let id = engine
+                .execute_create(
+                    CreateCommand::Initialize {
+                        name: name.to_string(),
+                    },
+                    &context,
+                )
+                .await
+                .unwrap();
+            engine
+                .execute_update(&id, UpdateCommand::Increment, &context)
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+        (persist, ids)
+    }
+
+    #[tokio::test]
+    async fn a_dump_moves_every_event_and_snapshot_to_another_store() {
+        let (persist, ids) = source().await;
+        let context = CqrsContext::default();
+        let mut dump = Vec::new();
+        let summary = EventStoreTransfer::<TestAggregate, _>::new(persist.clone())
+            .with_batch_size(3)
+            .export(&context, &mut dump)
+            .await
+            .unwrap();
+        assert_eq!(
+            summary,
+            DumpSummary {
+                events: 4,
+                snapshots: 2
+            }
+        );
+
+        let target = InMemoryPersist::<TestAggregate>::new();
+        let transfer = EventStoreTransfer::new(target.clone()).with_batch_size(3);
+        assert_eq!(
+            transfer.import(&context, dump.as_slice()).await.unwrap(),
+            summary
+        );
+
+        let (source_store, target_store) =
+            (EventStoreImpl::new(persist), EventStoreImpl::new(target));
+        for id in &ids {
+            let expected: Vec<_> = source_store
+                .load_events(id, &context)
+                .await
+                .unwrap()
+                .try_collect()
+                .await
+                .unwrap();
+            let imported: Vec<_> = target_store
+                .load_events(id, &context)
+                .await
+                .unwrap()
+                .try_collect()
+                .await
+                .unwrap();
+            assert_eq!(
+                serde_json::to_value(&imported).unwrap(),
+                serde_json::to_value(&expected).unwrap()
+            );
+            let snapshot = target_store
+                .load_snapshot(id, &context)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(snapshot.version, 2);
+        }
+
+        let error = transfer
+            .import(&context, dump.as_slice())
+            .await
+            .unwrap_err();
+        assert_eq!(error.status, 409);
+    }
+
+    #[tokio::test]
+    async fn snapshots_are_written_after_the_events_they_were_taken_of() {
+        let persist = InMemoryPersist::<TestAggregate>::new();
+        let engine = CqrsCommandEngine::new(
+            EventStoreImpl::new(persist.clone()),
+            vec![],
+            (),
+            Box::new(|_e| {}),
+        );
+        // Events go out in the order they were recorded, snapshots in the order of the
+        // ids: with two to a batch, `a` has its event buffered when its snapshot is
+        // written. Each command has a context of its own, for a time of its own.
+        for id in ["b", "c", "a"] {
+            let command = CreateCommand::Initialize {
+                name: id.to_string(),
+            };
+            engine
+                .execute_create_variant_with_id(
+                    id,
+                    command,
+                    HashMap::new(),
+                    &CqrsContext::default(),
+                )
+                .await
+                .unwrap();
+        }
+        let context = CqrsContext::default();
+        let mut dump = Vec::new();
+        EventStoreTransfer::<TestAggregate, _>::new(persist)
+            .export(&context, &mut dump)
+            .await
+            .unwrap();
+        let text = String::from_utf8(dump).unwrap();
+        let mut lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines.len(), 7);
+        lines.truncate(5);
+
+        let target = InMemoryPersist::<TestAggregate>::new();
+        let error = EventStoreTransfer::<TestAggregate, _>::new(target.clone())
+            .with_batch_size(2)
+            .import(&context, lines.join("\n").as_bytes())
+            .await
+            .unwrap_err();
+        assert_eq!(error.status, 400);
+        let store = EventStoreImpl::new(target);
+        let snapshot = store.load_snapshot("a", &context).await.unwrap().unwrap();
+        let events: Vec<_> = store
+            .load_events("a", &context)
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(events.len(), snapshot.version);
+    }
+
+    #[tokio::test]
+    async fn a_truncated_dump_is_refused() {
+        let (persist, _) = source().await;
+        let context = CqrsContext::default();
+        let mut dump = Vec::new();
+        EventStoreTransfer::<TestAggregate, _>::new(persist)
+            .export(&context, &mut dump)
+            .await
+            .unwrap();
+        let text = String::from_utf8(dump).unwrap();
+        let mut lines: Vec<_> = text.lines().collect();
+        lines.pop();
+
+        let error = EventStoreTransfer::<TestAggregate, _>::new(InMemoryPersist::new())
+            .import(&context, lines.join("\n").as_bytes())
+            .await
+            .unwrap_err();
+        assert_eq!(error.status, 400);
+        assert!(error.message.contains("ends without its summary"));
+    }
+
+    #[tokio::test]
+    async fn a_dump_missing_records_or_going_on_after_its_summary_is_refused() {
+        let (persist, _) = source().await;
+        let context = CqrsContext::default();
+        let mut dump = Vec::new();
+        EventStoreTransfer::<TestAggregate, _>::new(persist)
+            .export(&context, &mut dump)
+            .await
+            .unwrap();
+        let text = String::from_utf8(dump).unwrap();
+        let lines: Vec<_> = text.lines().collect();
+
+        let mut missing = lines.clone();
+        missing.remove(1);
+        let error = EventStoreTransfer::<TestAggregate, _>::new(InMemoryPersist::new())
+            .import(&context, missing.join("\n").as_bytes())
+            .await
+            .unwrap_err();
+        assert_eq!(error.status, 400);
+        assert!(error.message.contains("where its summary counts 4 and 2"));
+
+        let mut trailing = lines.clone();
+        trailing.push(lines[0]);
+        let error = EventStoreTransfer::<TestAggregate, _>::new(InMemoryPersist::new())
+            .import(&context, trailing.join("\n").as_bytes())
+            .await
+            .unwrap_err();
+        assert_eq!(error.status, 400);
+        assert!(error.message.contains("goes on after its summary"));
+    }
+
+    #[cfg(feature = "gzip")]
+    #[tokio::test]
+    async fn gzipped_dumps_are_detected_on_import() {
+        let (persist, _) = source().await;
+        let context = CqrsContext::default();
+        let mut dump = Vec::new();
+        EventStoreTransfer::<TestAggregate, _>::new(persist)
+            .with_gzip()
+            .export(&context, &mut dump)
+            .await
+            .unwrap();
+        assert!(dump.starts_with(&GZIP_MAGIC));
+
+        let summary = EventStoreTransfer::<TestAggregate, _>::new(InMemoryPersist::new())
+            .import(&context, dump.as_slice())
+            .await
+            .unwrap();
+        assert_eq!(
+            summary,
+            DumpSummary {
+                events: 4,
+                snapshots: 2
+            }
+        );
+    }
+}
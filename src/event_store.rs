@@ -1,7 +1,8 @@
 use crate::errors::CqrsError;
 use crate::es::storage::EventStream;
 use crate::snapshot::Snapshot;
-use crate::{Aggregate, CqrsContext, EventEnvelope};
+use crate::{Aggregate, CqrsContext, Event, EventEnvelope};
+use chrono::{DateTime, Utc};
 use futures::StreamExt;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -11,38 +12,139 @@ pub type DynEventStore<A> = Arc<dyn EventStore<A> + Send + Sync + 'static>;
 #[cfg(target_arch = "wasm32")]
 pub type DynEventStore<A> = Arc<dyn EventStore<A> + 'static>;
 
+/// The metadata key the command routers record the current user under, and
+/// [`EventFilter::user_id`] matches.
+pub const USER_ID_METADATA: &str = "user_id";
+
+/// The events an audit query reads: those of one aggregate, or of every aggregate of the
+/// type, narrowed by event type, user and time. Each storage pushes it down to its
+/// journal, oldest first.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EventFilter {
+    /// Every aggregate of the type when `None`.
+    pub aggregate_id: Option<String>,
+    /// Any of these [`Event::event_type`]s; every type when empty.
+    pub event_types: Vec<String>,
+    /// The [`USER_ID_METADATA`] of the events.
+    pub user_id: Option<String>,
+    /// Inclusive.
+    pub from: Option<DateTime<Utc>>,
+    /// Exclusive.
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl EventFilter {
+    /// Every event of `aggregate_id`.
+    #[must_use]
+    pub fn aggregate(aggregate_id: impl Into<String>) -> Self {
+        Self {
+            aggregate_id: Some(aggregate_id.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Whether `envelope` is one of the events selected, for storages filtering in
+    /// memory.
+    pub fn matches<A: Aggregate>(&self, envelope: &EventEnvelope<A>) -> bool {
+        self.aggregate_id
+            .as_ref()
+            .is_none_or(|id| *id == envelope.aggregate_id)
+            && (self.event_types.is_empty()
+                || self.event_types.contains(&envelope.payload.event_type()))
+            && self
+                .user_id
+                .as_ref()
+                .is_none_or(|user| envelope.metadata.get(USER_ID_METADATA) == Some(user))
+            && self.from.is_none_or(|from| envelope.at >= from)
+            && self.to.is_none_or(|to| envelope.at < to)
+    }
+
+    /// Orders `events` the way a page of the selection is ordered, for storages paging in
+    /// memory: by version within one aggregate — its `at` can go backwards when clocks
+    /// do — and by `at`, aggregate and version across the type.
+    pub fn sort<A: Aggregate>(&self, events: &mut [EventEnvelope<A>]) {
+        if self.aggregate_id.is_some() {
+            events.sort_by_key(|e| e.version);
+        } else {
+            events.sort_by(|a, b| {
+                (a.at, &a.aggregate_id, a.version).cmp(&(b.at, &b.aggregate_id, b.version))
+            });
+        }
+    }
+}
+
 cqrs_async_trait! {
 pub trait EventStore<A>
 where
     A: Aggregate + 'static,
 {
-    async fn load_snapshot(&self, aggregate_id: &str) -> Result<Option<Snapshot<A>>, CqrsError>;
+    /// Checks that the storage behind the store answers, for readiness probes; see
+    /// [`EventStoreStorage::health`](crate::es::storage::EventStoreStorage::health).
+    async fn health(&self) -> Result<(), CqrsError> {
+        Ok(())
+    }
+
+    /// Every read is scoped by `context` as the writes are: a storage configured with a
+    /// [`Tenancy`](crate::Tenancy) only sees the aggregates of the context's tenant.
+    async fn load_snapshot(
+        &self,
+        aggregate_id: &str,
+        context: &CqrsContext,
+    ) -> Result<Option<Snapshot<A>>, CqrsError>;
 
     async fn load_events_from_version(
         &self,
         aggregate_id: &str,
         version: usize,
+        context: &CqrsContext,
     ) -> Result<EventStream<A>, CqrsError>;
 
-    async fn load_events(&self, aggregate_id: &str) -> Result<EventStream<A>, CqrsError>;
+    async fn load_events(
+        &self,
+        aggregate_id: &str,
+        context: &CqrsContext,
+    ) -> Result<EventStream<A>, CqrsError>;
 
+    /// A page of the events `filter` selects, from 1, and how many it selects in all.
     async fn load_events_paged(
         &self,
-        aggregate_id: &str,
+        filter: &EventFilter,
         page: usize,
         page_size: usize,
+        context: &CqrsContext,
     ) -> Result<(Vec<EventEnvelope<A>>, i64), CqrsError>;
 
-    async fn initialize_aggregate(&self, aggregate_id: &str) -> Result<(A, usize), CqrsError> {
-        let maybe_snapshot = self.load_snapshot(aggregate_id).await?;
+    /// Replays the whole journal of `aggregate_id` and saves the state it yields as its
+    /// snapshot, at the version it returns; for snapshots lost, or written by a former
+    /// `apply`.
+    async fn rebuild_snapshot(
+        &self,
+        _aggregate_id: &str,
+        _context: &CqrsContext,
+    ) -> Result<usize, CqrsError> {
+        Err(CqrsError::not_implemented(
+            "this event store cannot rebuild snapshots",
+        ))
+    }
+
+    async fn initialize_aggregate(
+        &self,
+        aggregate_id: &str,
+        context: &CqrsContext,
+    ) -> Result<(A, usize), CqrsError> {
+        let maybe_snapshot = self.load_snapshot(aggregate_id, context).await?;
         if maybe_snapshot.is_some() {
             return Err(CqrsError::aggregate_already_exists(aggregate_id));
         }
         Ok((A::default().with_aggregate_id(aggregate_id.to_string()), 0))
     }
 
-    async fn load_aggregate(&self, aggregate_id: &str) -> Result<(A, usize), CqrsError> {
-        let maybe_snapshot = self.load_snapshot(aggregate_id).await?;
+    async fn load_aggregate(
+        &self,
+        aggregate_id: &str,
+        context: &CqrsContext,
+    ) -> Result<(A, usize), CqrsError> {
+        let maybe_snapshot = self.load_snapshot(aggregate_id, context).await?;
         if maybe_snapshot.is_none() {
             return Err(CqrsError::aggregate_not_found(aggregate_id));
         }
@@ -51,7 +153,9 @@ where
         let version = snapshot.version;
 
         let mut latest_version = version;
-        let mut event_stream = self.load_events_from_version(aggregate_id, version).await?;
+        let mut event_stream = self
+            .load_events_from_version(aggregate_id, version, context)
+            .await?;
         while let Some(event) = event_stream.next().await {
             let event = event?;
             agg.apply(event.payload).map_err(CqrsError::user_error)?;
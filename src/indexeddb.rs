@@ -0,0 +1,315 @@
+//! Shared IndexedDB database, in the browser.
+//!
+//! The event store ([`crate::es::indexeddb`]), the read side
+//! ([`crate::read::indexeddb`]) and the sync outbox ([`crate::sync::indexeddb`]) take an
+//! [`IndexedDb`]: one database per origin and name, opened once by the application,
+//! whose storages each keep to their own object stores.
+//!
+//! Unlike a table in redb or SQLite, an object store can only be created while the
+//! database is being upgraded to a new version. So the stores are declared up front, on
+//! an [`IndexedDbSchema`], and [`IndexedDbSchema::open`] upgrades the database when one
+//! of them is missing — stores already there, and their data, are left as they are.
+//!
+//! Every row is JSON under an array key led by its tenant — `""` unless the storage is
+//! under [`Tenancy::Column`](crate::Tenancy::Column) — so a tenant's rows, or an
+//! aggregate's events, are one key range. A commit is one `readwrite` transaction: the
+//! browser applies all of it, or none.
+
+use crate::errors::CqrsError;
+use crate::tenant_table;
+use futures::channel::oneshot;
+use js_sys::{Array, Function, Reflect};
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{
+    DomException, IdbDatabase, IdbFactory, IdbKeyRange, IdbObjectStore, IdbRequest, IdbTransaction,
+    IdbTransactionMode,
+};
+
+/// The object stores an application persists to, declared before the database opens.
+#[derive(Debug, Clone)]
+pub struct IndexedDbSchema {
+    name: String,
+    stores: Vec<String>,
+}
+
+impl IndexedDbSchema {
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            stores: Vec::new(),
+        }
+    }
+
+    /// A store of views, or of anything else keyed the way the storages key it.
+    #[must_use]
+    pub fn with_store(mut self, store: &str) -> Self {
+        if !self.stores.iter().any(|s| s == store) {
+            self.stores.push(store.to_string());
+        }
+        self
+    }
+
+    /// The journal and snapshot stores of `A`, named as
+    /// [`IndexedDbPersist`](crate::es::indexeddb::IndexedDbPersist) names them.
+    #[must_use]
+    pub fn with_event_store<A: crate::Aggregate>(self) -> Self {
+        self.with_store(&format!("{}_journal", A::TYPE))
+            .with_store(&format!("{}_snapshots", A::TYPE))
+    }
+
+    /// The outbox store of `A`, named as
+    /// [`IndexedDbSyncOutbox`](crate::sync::indexeddb::IndexedDbSyncOutbox) names it.
+    #[must_use]
+    pub fn with_sync_outbox<A: crate::Aggregate>(self) -> Self {
+        self.with_store(&format!("{}_sync_outbox", A::TYPE))
+    }
+
+    /// The stores `tenant` has of its own under [`Tenancy::Table`](crate::Tenancy::Table):
+    /// one `{store}_{tenant}` for each store declared so far.
+    #[must_use]
+    pub fn with_tenant(mut self, tenant: &str) -> Self {
+        let stores: Vec<String> = self
+            .stores
+            .iter()
+            .map(|store| tenant_table(store, tenant))
+            .collect();
+        for store in stores {
+            self = self.with_store(&store);
+        }
+        self
+    }
+
+    /// Opens the database, creating it, or upgrading it to the next version when a
+    /// declared store is missing.
+    ///
+    /// An upgrade waits for every other tab holding the database open to close it; the
+    /// `IndexedDb` handles of this crate close on `versionchange` so that they never hold
+    /// up another tab's upgrade.
+    pub async fn open(self) -> Result<IndexedDb, CqrsError> {
+        let factory = factory()?;
+        let request = factory.open(&self.name).map_err(js_error)?;
+        let database: IdbDatabase = settle(&request).await?.unchecked_into();
+        let existing = database.object_store_names();
+        let missing: Vec<String> = self
+            .stores
+            .into_iter()
+            .filter(|store| !existing.contains(store))
+            .collect();
+        if missing.is_empty() {
+            return Ok(IndexedDb::new(database));
+        }
+
+        let version = database.version() as u32 + 1;
+        database.close();
+        let request = factory
+            .open_with_u32(&self.name, version)
+            .map_err(js_error)?;
+        let upgrading = request.clone();
+        let upgrade = Closure::<dyn FnMut()>::new(move || {
+            let Ok(database) = upgrading.result() else {
+                return;
+            };
+            let database: IdbDatabase = database.unchecked_into();
+            for store in &missing {
+                if database.create_object_store(store).is_err() {
+                    // Fails the open request, which reports the transaction's error.
+                    if let Some(transaction) = upgrading.transaction() {
+                        let _ = transaction.abort();
+                    }
+                    return;
+                }
+            }
+        });
+        request.set_onupgradeneeded(Some(upgrade.as_ref().unchecked_ref()));
+        let database = settle(&request).await;
+        request.set_onupgradeneeded(None);
+        Ok(IndexedDb::new(database?.unchecked_into()))
+    }
+}
+
+/// An open IndexedDB database, cheap to clone and share between storages.
+#[derive(Debug, Clone)]
+pub struct IndexedDb {
+    database: IdbDatabase,
+    _on_version_change: Rc<Closure<dyn FnMut()>>,
+}
+
+impl IndexedDb {
+    fn new(database: IdbDatabase) -> Self {
+        let closing = database.clone();
+        let on_version_change = Closure::<dyn FnMut()>::new(move || closing.close());
+        database.set_onversionchange(Some(on_version_change.as_ref().unchecked_ref()));
+        Self {
+            database,
+            _on_version_change: Rc::new(on_version_change),
+        }
+    }
+
+    /// The database's name, as it was opened.
+    pub fn name(&self) -> String {
+        self.database.name()
+    }
+
+    /// Whether `store` was declared, and so can be read from and written to.
+    pub fn has_store(&self, store: &str) -> bool {
+        self.database.object_store_names().contains(store)
+    }
+
+    /// A transaction over `stores`, refusing a store the schema did not declare with a
+    /// message that says so rather than the browser's `NotFoundError`.
+    pub(crate) fn transaction(
+        &self,
+        stores: &[&str],
+        mode: IdbTransactionMode,
+    ) -> Result<IdbTransaction, CqrsError> {
+        if let Some(store) = stores.iter().find(|store| !self.has_store(store)) {
+            return Err(CqrsError::internal(format!(
+                "IndexedDB store `{store}` was not declared: add it to the IndexedDbSchema \
+                 of `{}`",
+                self.name()
+            )));
+        }
+        let names: Array = stores
+            .iter()
+            .map(|store| JsValue::from_str(store))
+            .collect();
+        self.database
+            .transaction_with_str_sequence_and_mode(&names, mode)
+            .map_err(js_error)
+    }
+
+    /// `store` in a transaction of its own.
+    pub(crate) fn store(
+        &self,
+        store: &str,
+        mode: IdbTransactionMode,
+    ) -> Result<IdbObjectStore, CqrsError> {
+        self.transaction(&[store], mode)?
+            .object_store(store)
+            .map_err(js_error)
+    }
+}
+
+/// The database is there to answer a read: a browser has no server to lose.
+pub(crate) fn ping(database: &IndexedDb) -> Result<(), CqrsError> {
+    let _ = database.database.object_store_names();
+    Ok(())
+}
+
+/// The `IDBFactory` of the global scope — a window's or a worker's alike.
+fn factory() -> Result<IdbFactory, CqrsError> {
+    let factory =
+        Reflect::get(&js_sys::global(), &JsValue::from_str("indexedDB")).map_err(js_error)?;
+    if factory.is_undefined() || factory.is_null() {
+        return Err(CqrsError::service_unavailable(
+            "IndexedDB is not available in this context",
+        ));
+    }
+    Ok(factory.unchecked_into())
+}
+
+/// An array key, from its parts.
+pub(crate) fn key(parts: &[JsValue]) -> JsValue {
+    parts.iter().collect::<Array>().into()
+}
+
+/// The keys from `lower` on that start with `prefix`: up to, not including,
+/// `[...prefix, []]` — an array sorts after every string and number in a key.
+pub(crate) fn range_from(lower: &[JsValue], prefix: &[JsValue]) -> Result<IdbKeyRange, CqrsError> {
+    let mut upper = prefix.to_vec();
+    upper.push(Array::new().into());
+    IdbKeyRange::bound_with_lower_open_and_upper_open(&key(lower), &key(&upper), false, true)
+        .map_err(js_error)
+}
+
+/// Every key that starts with `prefix`.
+pub(crate) fn prefix_range(prefix: &[JsValue]) -> Result<IdbKeyRange, CqrsError> {
+    range_from(prefix, prefix)
+}
+
+/// The value of `request` once it succeeds, or its error.
+pub(crate) async fn settle(request: &IdbRequest) -> Result<JsValue, CqrsError> {
+    let (sender, receiver) = oneshot::channel();
+    let sender = Rc::new(RefCell::new(Some(sender)));
+    let done = Closure::<dyn FnMut()>::new(move || {
+        if let Some(sender) = sender.borrow_mut().take() {
+            let _ = sender.send(());
+        }
+    });
+    let callback: &Function = done.as_ref().unchecked_ref();
+    request.set_onsuccess(Some(callback));
+    request.set_onerror(Some(callback));
+    let _ = receiver.await;
+    request.set_onsuccess(None);
+    request.set_onerror(None);
+    match request.error().map_err(js_error)? {
+        Some(error) => Err(dom_error(&error)),
+        None => request.result().map_err(js_error),
+    }
+}
+
+/// Waits for `transaction` to commit. A key written twice by `add` — the version another
+/// commit got to first — aborts it with a `ConstraintError`, a concurrency error here.
+pub(crate) async fn committed(transaction: &IdbTransaction) -> Result<(), CqrsError> {
+    let (sender, receiver) = oneshot::channel();
+    let sender = Rc::new(RefCell::new(Some(sender)));
+    let done = Closure::<dyn FnMut()>::new(move || {
+        if let Some(sender) = sender.borrow_mut().take() {
+            let _ = sender.send(());
+        }
+    });
+    let callback: &Function = done.as_ref().unchecked_ref();
+    // Request errors bubble to `onerror` before the abort; `onabort` carries the cause.
+    transaction.set_oncomplete(Some(callback));
+    transaction.set_onabort(Some(callback));
+    let _ = receiver.await;
+    transaction.set_oncomplete(None);
+    transaction.set_onabort(None);
+    match transaction.error() {
+        Some(error) if error.name() == "ConstraintError" => Err(CqrsError::concurrency_error()),
+        Some(error) => Err(dom_error(&error)),
+        None => Ok(()),
+    }
+}
+
+/// Parses the JSON strings of a `getAll`.
+pub(crate) fn parse_all<T: serde::de::DeserializeOwned>(
+    rows: JsValue,
+) -> Result<Vec<T>, CqrsError> {
+    rows.unchecked_into::<Array>()
+        .iter()
+        .map(|row| parse(&row))
+        .collect()
+}
+
+/// Parses the JSON string of a `get`, `None` when there is no such key.
+pub(crate) fn parse_one<T: serde::de::DeserializeOwned>(
+    row: JsValue,
+) -> Result<Option<T>, CqrsError> {
+    if row.is_undefined() || row.is_null() {
+        return Ok(None);
+    }
+    parse(&row).map(Some)
+}
+
+fn parse<T: serde::de::DeserializeOwned>(row: &JsValue) -> Result<T, CqrsError> {
+    let json = row
+        .as_string()
+        .ok_or_else(|| CqrsError::serialization_error("an IndexedDB row is not a JSON string"))?;
+    serde_json::from_str(&json).map_err(CqrsError::serialization_error)
+}
+
+fn dom_error(error: &DomException) -> CqrsError {
+    CqrsError::database_error(format!("{}: {}", error.name(), error.message()))
+}
+
+pub(crate) fn js_error(error: JsValue) -> CqrsError {
+    match error.dyn_ref::<DomException>() {
+        Some(error) => dom_error(error),
+        None => CqrsError::database_error(format!("{error:?}")),
+    }
+}
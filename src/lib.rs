@@ -21,6 +21,8 @@ mod aggregate;
 pub use aggregate::*;
 mod engine;
 pub use engine::*;
+mod policy;
+pub use policy::*;
 
 mod denormalizer;
 pub use denormalizer::*;
@@ -38,15 +40,27 @@ mod event_store;
 pub use event_store::*;
 
 pub mod es;
+#[cfg(all(feature = "indexeddb", target_arch = "wasm32"))]
+pub mod indexeddb;
 #[cfg(feature = "postgres")]
 pub mod pg;
 pub mod read;
+#[cfg(feature = "redb")]
+pub mod redb;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod sync;
 
 #[cfg(feature = "rest")]
 pub mod rest;
 
+#[cfg(feature = "mcp")]
+pub mod mcp;
+
 mod context;
 pub use context::*;
+mod tenant;
+pub use tenant::*;
 mod snapshot;
 
 pub use snapshot::*;
@@ -54,26 +68,11 @@ pub mod dispatchers;
 
 pub mod prelude;
 pub use rest_sql as rsql;
-// Gated on its two callers, both of which are feature-gated.
-#[cfg(any(
-    feature = "rest",
-    feature = "postgres",
-    feature = "mongodb",
-    feature = "surrealdb"
-))]
+// Not gated: the in-memory read storage warns through it, and that one is always built.
 pub(crate) mod warn_once;
 
-// Test-only, and gated on the features that have something to log: an unused
-// `pub(crate)` module is `dead_code` under CI's narrower feature sets.
-#[cfg(all(
-    test,
-    any(
-        feature = "rest",
-        feature = "postgres",
-        feature = "mongodb",
-        feature = "surrealdb"
-    )
-))]
+// Test-only. Built in every feature set for the same reason as `warn_once`.
+#[cfg(test)]
 pub(crate) mod log_capture;
 
 #[cfg(test)]
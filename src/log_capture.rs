@@ -45,10 +45,6 @@ impl tracing::Subscriber for Recorder {
 }
 
 /// Runs `f` with the recorder installed and returns whatever it logged.
-///
-/// Gated on its callers: only the read backends log from a synchronous path, so under
-/// `--features rest` alone this would be `dead_code`.
-#[cfg(any(feature = "postgres", feature = "mongodb", feature = "surrealdb"))]
 pub(crate) fn events_of(f: impl FnOnce()) -> Vec<String> {
     let recorder = Recorder::default();
     let events = Arc::clone(&recorder.0);
@@ -84,10 +80,7 @@ pub(crate) async fn events_of_async<F: std::future::Future>(f: F) -> Vec<String>
     events.lock().expect("recorder not poisoned").clone()
 }
 
-#[cfg(all(
-    test,
-    any(feature = "postgres", feature = "mongodb", feature = "surrealdb")
-))]
+#[cfg(test)]
 mod tests {
     use super::{containing, events_of};
 
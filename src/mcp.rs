@@ -0,0 +1,1056 @@
+//! An MCP server over the write and read sides (feature `mcp`).
+//!
+//! [`CqrsMcpServer`] is an `rmcp` [`ServerHandler`]: it publishes the commands of each
+//! registered [`CqrsCommandEngine`] as tools, and views and audit logs as resources.
+//! Serve it with any `rmcp` transport:
+//!
+//! ```ignore
+//! use rmcp::ServiceExt;
+//!
+//! let server = CqrsMcpServer::new("ludotheque", env!("CARGO_PKG_VERSION"))
+//!     .with_context(|_| CqrsContext::new(Some("assistant".into())).with_next_request_id())
+//!     .commands(engine)
+//!     .views("game", view_storage)
+//!     .audit_log(event_store);
+//! server.serve(rmcp::transport::stdio()).await?.waiting().await?;
+//! ```
+
+use crate::read::storage::{DynStorage, HasId};
+use crate::policy::authorize_read;
+use crate::read::{Paged, Query};
+use crate::{
+    Aggregate, CommandHandler, CqrsCommandEngine, CqrsContext, CqrsError, DynEventStore,
+    EventFilter, ReadPolicies, USER_ID_METADATA,
+};
+use percent_encoding::percent_decode_str;
+use rmcp::model::{
+    CallToolRequestParams, CallToolResponse, CallToolResult, ErrorCode, Implementation, JsonObject,
+    ListResourceTemplatesResult, ListResourcesResult, ListToolsResult, PaginatedRequestParams,
+    ReadResourceRequestParams, ReadResourceResponse, ReadResourceResult, Resource,
+    ResourceContents, ResourceTemplate, ServerCapabilities, ServerInfo, Tool,
+};
+use rmcp::service::RequestContext;
+use rmcp::{ErrorData as McpError, RoleServer, ServerHandler};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// The scheme of every resource URI this server publishes.
+pub const RESOURCE_SCHEME: &str = "cqrs://";
+
+const JSON_MIME_TYPE: &str = "application/json";
+
+type ContextProvider = Arc<dyn Fn(&RequestContext<RoleServer>) -> CqrsContext + Send + Sync>;
+
+/// Serves registered aggregates and views to MCP clients.
+///
+/// - **Tools** — one per variant of each `CreateCommand` and `UpdateCommand`, named
+///   `{Aggregate::TYPE}_{variant}` in snake case (`todolist_add_todo`). A command enum
+///   tagged as the REST router expects (`#[serde(tag = "type")]`) is split on its tag,
+///   which the tool sets itself; any other command type is a single
+///   `{TYPE}_create` / `{TYPE}_update` tool taking the whole command as `command`.
+///   Update tools take the aggregate id as `{TYPE}_id`, the name of the REST path
+///   param. Input schemas come from `schemars`. A call goes through
+///   [`CqrsCommandEngine`] with the `user_id` / `request_id` metadata the write router
+///   records, and answers `{ "id": ... }` — or the [`CqrsError`] body, flagged as an
+///   error result the client shows to its user.
+/// - **Resources** — `cqrs://{name}/views` (the first page of the default query) and
+///   `cqrs://{name}/views/{id}` for each registered view storage, and
+///   `cqrs://{TYPE}/audit/{id}` for each registered event store, paged with
+///   `?page=&page_size=` — pages from 1, 10 events by default. Views that are children of another
+///   entity take `?parent_id=`. Every resource is JSON.
+///
+/// Each request gets the `CqrsContext` built by [`with_context`](Self::with_context)
+/// from the MCP request, so storages scoping reads by context, and command handlers,
+/// see the MCP client as they would see an HTTP caller.
+#[derive(Clone)]
+pub struct CqrsMcpServer {
+    info: Implementation,
+    instructions: Option<String>,
+    context: ContextProvider,
+    tools: Vec<CommandTool>,
+    resources: Vec<Arc<dyn ResourceSource + Send + Sync>>,
+}
+
+impl CqrsMcpServer {
+    #[must_use]
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            info: Implementation::new(name, version),
+            instructions: None,
+            context: Arc::new(|_| CqrsContext::default().with_next_request_id()),
+            tools: Vec::new(),
+            resources: Vec::new(),
+        }
+    }
+
+    /// Shown to the client on initialization, to tell its model what the server is for.
+    #[must_use]
+    pub fn with_instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = Some(instructions.into());
+        self
+    }
+
+    /// Builds the context of each tool call and resource read. The default is an
+    /// anonymous context with a fresh request id. The request context carries the
+    /// transport's own data — under the streamable HTTP transport, the
+    /// `http::request::Parts` of the request in its extensions.
+    #[must_use]
+    pub fn with_context<F>(mut self, provider: F) -> Self
+    where
+        F: Fn(&RequestContext<RoleServer>) -> CqrsContext + Send + Sync + 'static,
+    {
+        self.context = Arc::new(provider);
+        self
+    }
+
+    /// Publishes every command of `A` as a tool run by `engine`.
+    ///
+    /// # Panics
+    ///
+    /// When a tool name is already taken — the same aggregate registered twice, or two
+    /// whose types and variants collide.
+    #[must_use]
+    pub fn commands<A>(mut self, engine: Arc<CqrsCommandEngine<A>>) -> Self
+    where
+        A: Aggregate + CommandHandler + 'static,
+        A::Error: Into<CqrsError>,
+        A::CreateCommand: JsonSchema,
+        A::UpdateCommand: JsonSchema,
+    {
+        let runner: Arc<dyn CommandRunner + Send + Sync> = engine;
+        let create = schemars::schema_for!(A::CreateCommand).to_value();
+        let update = schemars::schema_for!(A::UpdateCommand).to_value();
+        let tools = command_tools(A::TYPE, CommandKind::Create, create, &runner)
+            .into_iter()
+            .chain(command_tools(A::TYPE, CommandKind::Update, update, &runner));
+        for tool in tools {
+            assert!(
+                self.tools.iter().all(|t| t.tool.name != tool.tool.name),
+                "the MCP tool `{}` is registered twice",
+                tool.tool.name
+            );
+            self.tools.push(tool);
+        }
+        self
+    }
+
+    /// Publishes the views of `storage` as `cqrs://{name}/views[/{id}]`.
+    #[must_use]
+    pub fn views<V, Q>(self, name: impl Into<String>, storage: DynStorage<V, Q>) -> Self
+    where
+        V: Debug + Clone + Default + Serialize + DeserializeOwned + HasId + Send + Sync + 'static,
+        Q: Query + Clone + Debug + Default + Send + Sync + 'static,
+    {
+        self.views_with_policies(name, storage, ReadPolicies::default())
+    }
+
+    /// [`views`](Self::views), `cqrs://{name}/views/{id}` behind the `find_one` policy
+    /// and `cqrs://{name}/views` behind the `find_many` one.
+    #[must_use]
+    pub fn views_with_policies<V, Q>(
+        mut self,
+        name: impl Into<String>,
+        storage: DynStorage<V, Q>,
+        policies: ReadPolicies,
+    ) -> Self
+    where
+        V: Debug + Clone + Default + Serialize + DeserializeOwned + HasId + Send + Sync + 'static,
+        Q: Query + Clone + Debug + Default + Send + Sync + 'static,
+    {
+        self.resources.push(Arc::new(ViewResources {
+            name: name.into(),
+            storage,
+            policies,
+        }));
+        self
+    }
+
+    /// Publishes the event history of each `A` as `cqrs://{A::TYPE}/audit/{id}`.
+    #[must_use]
+    pub fn audit_log<A>(self, store: DynEventStore<A>) -> Self
+    where
+        A: Aggregate + 'static,
+    {
+        self.audit_log_with_policies(store, ReadPolicies::default())
+    }
+
+    /// [`audit_log`](Self::audit_log), behind the `find_one` policy.
+    #[must_use]
+    pub fn audit_log_with_policies<A>(
+        mut self,
+        store: DynEventStore<A>,
+        policies: ReadPolicies,
+    ) -> Self
+    where
+        A: Aggregate + 'static,
+    {
+        self.resources
+            .push(Arc::new(AuditLogResources { store, policies }));
+        self
+    }
+
+    fn tools(&self) -> Vec<Tool> {
+        self.tools.iter().map(|t| t.tool.clone()).collect()
+    }
+
+    async fn run_tool(
+        &self,
+        name: &str,
+        arguments: JsonObject,
+        context: CqrsContext,
+    ) -> Result<CallToolResult, McpError> {
+        let Some(tool) = self.tools.iter().find(|t| t.tool.name == name) else {
+            return Err(McpError::new(
+                ErrorCode::METHOD_NOT_FOUND,
+                format!("unknown tool: {name}"),
+                None,
+            ));
+        };
+        let request_id = context.request_id();
+        Ok(match tool.run(arguments, &context).await {
+            Ok(id) => CallToolResult::structured(serde_json::json!({ "id": id })),
+            Err(error) => {
+                let error = error.with_request_id_if_absent(request_id);
+                CallToolResult::structured_error(
+                    serde_json::to_value(&error).unwrap_or_else(|_| error.message.clone().into()),
+                )
+            }
+        })
+    }
+
+    async fn read_uri(
+        &self,
+        uri: &str,
+        context: CqrsContext,
+    ) -> Result<ReadResourceResult, McpError> {
+        let parsed = ResourceUri::parse(uri)
+            .ok_or_else(|| McpError::resource_not_found(format!("not a resource: {uri}"), None))?;
+        for source in &self.resources {
+            if let Some(read) = source.read(&parsed, context.clone()).await {
+                let document = read.map_err(mcp_error)?;
+                let text = serde_json::to_string(&document)
+                    .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+                return Ok(ReadResourceResult::new(vec![
+                    ResourceContents::text(text, uri).with_mime_type(JSON_MIME_TYPE),
+                ]));
+            }
+        }
+        Err(McpError::resource_not_found(
+            format!("no such resource: {uri}"),
+            None,
+        ))
+    }
+}
+
+impl ServerHandler for CqrsMcpServer {
+    fn get_info(&self) -> ServerInfo {
+        let capabilities = ServerCapabilities::builder()
+            .enable_tools()
+            .enable_resources()
+            .build();
+        let info = ServerInfo::new(capabilities).with_server_info(self.info.clone());
+        match &self.instructions {
+            Some(instructions) => info.with_instructions(instructions.clone()),
+            None => info,
+        }
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParams>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        Ok(ListToolsResult::with_all_items(self.tools()))
+    }
+
+    fn get_tool(&self, name: &str) -> Option<Tool> {
+        self.tools
+            .iter()
+            .find(|t| t.tool.name == name)
+            .map(|t| t.tool.clone())
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResponse, McpError> {
+        let cqrs_context = (self.context)(&context);
+        self.run_tool(
+            &request.name,
+            request.arguments.unwrap_or_default(),
+            cqrs_context,
+        )
+        .await
+        .map(CallToolResponse::from)
+    }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParams>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, McpError> {
+        Ok(ListResourcesResult::with_all_items(
+            self.resources.iter().flat_map(|s| s.resources()).collect(),
+        ))
+    }
+
+    async fn list_resource_templates(
+        &self,
+        _request: Option<PaginatedRequestParams>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, McpError> {
+        Ok(ListResourceTemplatesResult::with_all_items(
+            self.resources.iter().flat_map(|s| s.templates()).collect(),
+        ))
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResponse, McpError> {
+        let cqrs_context = (self.context)(&context);
+        self.read_uri(&request.uri, cqrs_context)
+            .await
+            .map(ReadResourceResponse::from)
+    }
+}
+
+/// A failed read as the protocol error closest to its status. A client shows these
+/// opaquely, which is right for a resource: there is no user-facing result to carry one.
+fn mcp_error(error: CqrsError) -> McpError {
+    let data = serde_json::to_value(&error).ok();
+    let status = error.http_status();
+    if status == http::StatusCode::NOT_FOUND {
+        McpError::resource_not_found(error.message.clone(), data)
+    } else if status.is_client_error() {
+        McpError::invalid_params(error.message.clone(), data)
+    } else {
+        McpError::internal_error(error.message.clone(), data)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tools
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CommandKind {
+    Create,
+    Update,
+}
+
+/// How a tool's arguments become a command body.
+#[derive(Debug, Clone, PartialEq)]
+enum Shape {
+    /// One variant of a tagged enum: the arguments are the variant's fields, and the
+    /// tag `(field, value)` is added back before deserializing.
+    Variant(String, String),
+    /// The whole command, under `command`.
+    Whole,
+}
+
+#[derive(Clone)]
+struct CommandTool {
+    tool: Tool,
+    kind: CommandKind,
+    shape: Shape,
+    /// The aggregate id argument of an update tool.
+    id_field: String,
+    runner: Arc<dyn CommandRunner + Send + Sync>,
+}
+
+impl CommandTool {
+    async fn run(
+        &self,
+        mut arguments: JsonObject,
+        context: &CqrsContext,
+    ) -> Result<String, CqrsError> {
+        let id = match self.kind {
+            CommandKind::Create => None,
+            CommandKind::Update => match arguments.remove(&self.id_field) {
+                Some(JsonValue::String(id)) => Some(id),
+                _ => {
+                    return Err(CqrsError::unprocessable(format!(
+                        "{}: a string is required",
+                        self.id_field
+                    )));
+                }
+            },
+        };
+        let command = match &self.shape {
+            Shape::Variant(field, value) => {
+                arguments.insert(field.clone(), JsonValue::String(value.clone()));
+                JsonValue::Object(arguments)
+            }
+            Shape::Whole => arguments.remove("command").unwrap_or(JsonValue::Null),
+        };
+        match id {
+            None => self.runner.create(command, context).await,
+            Some(id) => self.runner.update(&id, command, context).await.map(|_| id),
+        }
+    }
+}
+
+cqrs_async_trait! {
+/// The engine of one aggregate, with its command types erased to JSON.
+trait CommandRunner {
+    async fn create(&self, command: JsonValue, context: &CqrsContext) -> Result<String, CqrsError>;
+    async fn update(&self, id: &str, command: JsonValue, context: &CqrsContext) -> Result<(), CqrsError>;
+}
+}
+
+cqrs_async_trait! {
+impl<A> CommandRunner for CqrsCommandEngine<A>
+where
+    A: Aggregate + CommandHandler + 'static,
+    A::Error: Into<CqrsError>,
+{
+    async fn create(&self, command: JsonValue, context: &CqrsContext) -> Result<String, CqrsError> {
+        // Arguments that do not match the schema are the caller's error, as a REST body
+        // would be: 422, not the 500 of `serialization_error`.
+        let command = serde_json::from_value::<A::CreateCommand>(command)
+            .map_err(|e| CqrsError::unprocessable(e.to_string()))?;
+        self.execute_create_variant(command, metadata(context), context)
+            .await
+    }
+
+    async fn update(&self, id: &str, command: JsonValue, context: &CqrsContext) -> Result<(), CqrsError> {
+        let command = serde_json::from_value::<A::UpdateCommand>(command)
+            .map_err(|e| CqrsError::unprocessable(e.to_string()))?;
+        self.execute_update_variant(id, command, metadata(context), context)
+            .await
+    }
+}
+}
+
+/// What `CQRSWriteRouter` records on the envelope, so the audit log reads the same
+/// whichever way a command came in.
+fn metadata(context: &CqrsContext) -> HashMap<String, String> {
+    HashMap::from_iter(vec![
+        (USER_ID_METADATA.to_string(), context.current_user()),
+        ("request_id".to_string(), context.request_id()),
+    ])
+}
+
+/// The tools of one command type, from its JSON schema.
+///
+/// Opinionated like the REST router: a variant is an object schema of a `oneOf` /
+/// `anyOf` with exactly one property holding a single string constant, and that is the
+/// tag. When one item is not shaped so, the type is published whole.
+fn command_tools(
+    aggregate_type: &str,
+    kind: CommandKind,
+    root: JsonValue,
+    runner: &Arc<dyn CommandRunner + Send + Sync>,
+) -> Vec<CommandTool> {
+    let JsonValue::Object(mut root) = root else {
+        return vec![];
+    };
+    let defs = root.remove("$defs");
+    let id_field = format!("{aggregate_type}_id");
+    let verb = match kind {
+        CommandKind::Create => "create",
+        CommandKind::Update => "update",
+    };
+
+    let variants = ["oneOf", "anyOf"]
+        .iter()
+        .find_map(|key| root.get(*key).and_then(JsonValue::as_array))
+        .map(|items| items.iter().map(variant).collect::<Option<Vec<_>>>())
+        .unwrap_or_default();
+
+    let parts: Vec<(String, Shape, JsonObject)> = match variants {
+        Some(variants) if !variants.is_empty() => variants
+            .into_iter()
+            .map(|(field, value, schema)| {
+                let name = format!("{aggregate_type}_{}", snake_case(&value));
+                (name, Shape::Variant(field, value), schema)
+            })
+            .collect(),
+        _ => {
+            root.remove("$schema");
+            let mut schema = JsonObject::new();
+            schema.insert("type".into(), "object".into());
+            schema.insert(
+                "properties".into(),
+                serde_json::json!({ "command": JsonValue::Object(root) }),
+            );
+            schema.insert("required".into(), serde_json::json!(["command"]));
+            vec![(format!("{aggregate_type}_{verb}"), Shape::Whole, schema)]
+        }
+    };
+
+    parts
+        .into_iter()
+        .map(|(name, shape, mut schema)| {
+            if kind == CommandKind::Update {
+                add_required_string(&mut schema, &id_field, "The id of the aggregate to update.");
+            }
+            if let Some(defs) = &defs {
+                schema.insert("$defs".into(), defs.clone());
+            }
+            let description = schema
+                .get("description")
+                .and_then(JsonValue::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| match &shape {
+                    Shape::Variant(_, value) => {
+                        format!("Runs `{value}` ({verb}) on a {aggregate_type}.")
+                    }
+                    Shape::Whole => format!("Runs a {verb} command on a {aggregate_type}."),
+                });
+            CommandTool {
+                tool: Tool::new(name, description, Arc::new(schema)),
+                kind,
+                shape,
+                id_field: id_field.clone(),
+                runner: runner.clone(),
+            }
+        })
+        .collect()
+}
+
+/// `(tag field, tag value, schema without the tag)` when `item` is a tagged variant.
+fn variant(item: &JsonValue) -> Option<(String, String, JsonObject)> {
+    let mut schema = item.as_object()?.clone();
+    let properties = schema.get("properties")?.as_object()?;
+    let mut tags = properties.iter().filter_map(|(field, property)| {
+        let value = match (property.get("const"), property.get("enum")) {
+            (Some(JsonValue::String(value)), _) => value.clone(),
+            (None, Some(JsonValue::Array(values))) if values.len() == 1 => {
+                values[0].as_str()?.to_string()
+            }
+            _ => return None,
+        };
+        Some((field.clone(), value))
+    });
+    let (field, value) = tags.next()?;
+    if tags.next().is_some() {
+        return None;
+    }
+
+    if let Some(JsonValue::Object(properties)) = schema.get_mut("properties") {
+        properties.remove(&field);
+    }
+    if let Some(JsonValue::Array(required)) = schema.get_mut("required") {
+        required.retain(|r| r.as_str() != Some(field.as_str()));
+    }
+    Some((field, value, schema))
+}
+
+fn add_required_string(schema: &mut JsonObject, field: &str, description: &str) {
+    let properties = schema
+        .entry("properties")
+        .or_insert_with(|| JsonValue::Object(JsonObject::new()));
+    if let JsonValue::Object(properties) = properties {
+        properties.insert(
+            field.to_string(),
+            serde_json::json!({ "type": "string", "description": description }),
+        );
+    }
+    let required = schema
+        .entry("required")
+        .or_insert_with(|| JsonValue::Array(vec![]));
+    if let JsonValue::Array(required) = required
+        && !required.iter().any(|r| r.as_str() == Some(field))
+    {
+        required.push(field.into());
+    }
+}
+
+/// `AddTodo` → `add_todo`. Only ASCII letters, digits and `_` remain, which every MCP
+/// client accepts in a tool name.
+fn snake_case(name: &str) -> String {
+    let mut result = String::new();
+    let mut prev: Option<char> = None;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            if prev.is_some_and(|p| p.is_ascii_lowercase() || p.is_ascii_digit()) {
+                result.push('_');
+            }
+            result.push(c.to_ascii_lowercase());
+        } else if c.is_ascii_alphanumeric() {
+            result.push(c);
+        } else if !result.ends_with('_') {
+            result.push('_');
+        }
+        prev = Some(c);
+    }
+    result
+}
+
+// ---------------------------------------------------------------------------
+// Resources
+// ---------------------------------------------------------------------------
+
+/// A `cqrs://` URI, its path segments and query params percent-decoded.
+#[derive(Debug, PartialEq)]
+struct ResourceUri {
+    segments: Vec<String>,
+    params: HashMap<String, String>,
+}
+
+impl ResourceUri {
+    fn parse(uri: &str) -> Option<Self> {
+        let rest = uri.strip_prefix(RESOURCE_SCHEME)?;
+        let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let decode = |s: &str| percent_decode_str(s).decode_utf8_lossy().into_owned();
+        let segments = path
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(decode)
+            .collect();
+        let params = query
+            .split('&')
+            .filter(|p| !p.is_empty())
+            .map(|p| {
+                let (k, v) = p.split_once('=').unwrap_or((p, ""));
+                (decode(k), decode(v))
+            })
+            .collect();
+        Some(Self { segments, params })
+    }
+
+    fn path(&self) -> Vec<&str> {
+        self.segments.iter().map(String::as_str).collect()
+    }
+
+    fn param(&self, name: &str) -> Option<String> {
+        self.params.get(name).filter(|v| !v.is_empty()).cloned()
+    }
+
+    fn usize_param(&self, name: &str, default: usize) -> Result<usize, CqrsError> {
+        match self.param(name) {
+            None => Ok(default),
+            Some(raw) => raw
+                .parse()
+                .map_err(|_| CqrsError::validation(format!("{name}: not a number: {raw}"))),
+        }
+    }
+}
+
+cqrs_async_trait! {
+trait ResourceSource {
+    fn resources(&self) -> Vec<Resource>;
+    fn templates(&self) -> Vec<ResourceTemplate>;
+    /// `None` when `uri` is not one of this source's.
+    async fn read(&self, uri: &ResourceUri, context: CqrsContext) -> Option<Result<JsonValue, CqrsError>>;
+}
+}
+
+struct ViewResources<V, Q> {
+    name: String,
+    storage: DynStorage<V, Q>,
+    policies: ReadPolicies,
+}
+
+cqrs_async_trait! {
+impl<V, Q> ResourceSource for ViewResources<V, Q>
+where
+    V: Debug + Clone + Default + Serialize + DeserializeOwned + HasId + Send + Sync + 'static,
+    Q: Query + Clone + Debug + Default + Send + Sync + 'static,
+{
+    fn resources(&self) -> Vec<Resource> {
+        // A child view has no list without its parent, so only the template shows it.
+        if V::parent_field_id().is_some() {
+            return vec![];
+        }
+        vec![
+            Resource::new(format!("{RESOURCE_SCHEME}{}/views", self.name), format!("{} views", self.name))
+                .with_description(format!("The first page of the {} views.", self.name))
+                .with_mime_type(JSON_MIME_TYPE),
+        ]
+    }
+
+    fn templates(&self) -> Vec<ResourceTemplate> {
+        let parent = if V::parent_field_id().is_some() { "{?parent_id}" } else { "" };
+        vec![
+            ResourceTemplate::new(
+                format!("{RESOURCE_SCHEME}{}/views{parent}", self.name),
+                format!("{} view list", self.name),
+            )
+            .with_description(format!("The first page of the {} views.", self.name))
+            .with_mime_type(JSON_MIME_TYPE),
+            ResourceTemplate::new(
+                format!("{RESOURCE_SCHEME}{}/views/{{id}}{parent}", self.name),
+                format!("{} view", self.name),
+            )
+            .with_description(format!("One {} view, by id.", self.name))
+            .with_mime_type(JSON_MIME_TYPE),
+        ]
+    }
+
+    async fn read(&self, uri: &ResourceUri, context: CqrsContext) -> Option<Result<JsonValue, CqrsError>> {
+        let parent_id = uri.param("parent_id");
+        let read = match uri.path()[..] {
+            [name, "views"] if name == self.name => {
+                if let Err(e) = authorize_read(self.policies.find_many(), &context) {
+                    return Some(Err(e));
+                }
+                self.storage
+                    .filter(parent_id, Q::default(), context)
+                    .await
+                    .and_then(|page| serde_json::to_value(page).map_err(CqrsError::serialization_error))
+            }
+            [name, "views", id] if name == self.name => {
+                if let Err(e) = authorize_read(self.policies.find_one(), &context) {
+                    return Some(Err(e));
+                }
+                match self.storage.find_by_id(parent_id, id, context).await {
+                    Ok(Some(view)) => serde_json::to_value(view).map_err(CqrsError::serialization_error),
+                    Ok(None) => Err(CqrsError::not_found(format!("no {} view {id}", self.name))),
+                    Err(e) => Err(e),
+                }
+            }
+            _ => return None,
+        };
+        Some(read)
+    }
+}
+}
+
+struct AuditLogResources<A: Aggregate> {
+    store: DynEventStore<A>,
+    policies: ReadPolicies,
+}
+
+cqrs_async_trait! {
+impl<A> ResourceSource for AuditLogResources<A>
+where
+    A: Aggregate + 'static,
+{
+    fn resources(&self) -> Vec<Resource> {
+        vec![]
+    }
+
+    fn templates(&self) -> Vec<ResourceTemplate> {
+        vec![
+            ResourceTemplate::new(
+                format!("{RESOURCE_SCHEME}{}/audit/{{id}}{{?page,page_size}}", A::TYPE),
+                format!("{} audit log", A::TYPE),
+            )
+            .with_description(format!(
+                "The events of one {}, oldest first, in pages from 1.",
+                A::TYPE
+            ))
+            .with_mime_type(JSON_MIME_TYPE),
+        ]
+    }
+
+    async fn read(&self, uri: &ResourceUri, context: CqrsContext) -> Option<Result<JsonValue, CqrsError>> {
+        let [aggregate_type, "audit", id] = uri.path()[..] else {
+            return None;
+        };
+        if aggregate_type != A::TYPE {
+            return None;
+        }
+        let read = async {
+            authorize_read(self.policies.find_one(), &context)?;
+            let page = uri.usize_param("page", 1)?;
+            let page_size = uri.usize_param("page_size", 10)?;
+            let (events, total) = self
+                .store
+                .load_events_paged(&EventFilter::aggregate(id), page, page_size, &context)
+                .await?;
+            // The event stores count pages from 1, and read 0 as 1.
+            let skip = page.max(1).saturating_sub(1).saturating_mul(page_size);
+            serde_json::to_value(Paged::new(events, total, skip as i64, page_size as i64))
+                .map_err(CqrsError::serialization_error)
+        };
+        Some(read.await)
+    }
+}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::es::inmemory::InMemoryPersist;
+    use crate::es::EventStoreImpl;
+    use crate::read::inmemory::InMemoryStorage;
+    use crate::read::storage::Storage;
+    use crate::testing::TestAggregate;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+    #[serde(tag = "type")]
+    #[allow(dead_code)]
+    enum TodoCommands {
+        /// Adds a todo to the list.
+        AddTodo {
+            title: String,
+        },
+        Clear,
+    }
+
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    struct Game {
+        id: String,
+        title: String,
+    }
+
+    impl HasId for Game {
+        fn field_id() -> &'static str {
+            "id"
+        }
+        fn id(&self) -> &str {
+            &self.id
+        }
+        fn parent_field_id() -> Option<&'static str> {
+            None
+        }
+        fn parent_id(&self) -> Option<&str> {
+            None
+        }
+    }
+
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    struct GameQuery {}
+
+    impl Query for GameQuery {}
+
+    fn server() -> (CqrsMcpServer, DynEventStore<TestAggregate>) {
+        let store: DynEventStore<TestAggregate> = EventStoreImpl::new(InMemoryPersist::new());
+        let engine = Arc::new(CqrsCommandEngine::new(
+            store.clone(),
+            vec![],
+            (),
+            Box::new(|_| {}),
+        ));
+        let server = CqrsMcpServer::new("test", "0.0.0")
+            .commands(engine)
+            .audit_log(store.clone());
+        (server, store)
+    }
+
+    fn context() -> CqrsContext {
+        CqrsContext::new(Some("alice".to_string())).with_request_id("r-1".to_string())
+    }
+
+    fn arguments(value: JsonValue) -> JsonObject {
+        value.as_object().unwrap().clone()
+    }
+
+    fn contents(result: &ReadResourceResult) -> JsonValue {
+        match &result.contents[..] {
+            [
+                ResourceContents::TextResourceContents {
+                    text, mime_type, ..
+                },
+            ] => {
+                assert_eq!(mime_type.as_deref(), Some(JSON_MIME_TYPE));
+                serde_json::from_str(text).unwrap()
+            }
+            other => panic!("expected one JSON document, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn a_tagged_enum_is_one_tool_per_variant_without_its_tag() {
+        let (server, _) = server();
+        let runner = server.tools[0].runner.clone();
+        let root = schemars::schema_for!(TodoCommands).to_value();
+        let tools = command_tools("todolist", CommandKind::Update, root, &runner);
+
+        let names: Vec<_> = tools.iter().map(|t| t.tool.name.to_string()).collect();
+        assert_eq!(names, ["todolist_add_todo", "todolist_clear"]);
+        assert_eq!(
+            tools[0].shape,
+            Shape::Variant("type".to_string(), "AddTodo".to_string())
+        );
+
+        let schema = JsonValue::Object((*tools[0].tool.input_schema).clone());
+        assert!(schema["properties"].get("type").is_none());
+        assert_eq!(schema["properties"]["title"]["type"], "string");
+        assert_eq!(schema["properties"]["todolist_id"]["type"], "string");
+        let required = schema["required"].as_array().unwrap();
+        assert!(required.contains(&json!("todolist_id")));
+        assert!(!required.contains(&json!("type")));
+        assert_eq!(
+            tools[0].tool.description.as_deref(),
+            Some("Adds a todo to the list.")
+        );
+    }
+
+    #[test]
+    fn an_untagged_command_is_one_tool_taking_the_whole_command() {
+        let (server, _) = server();
+        let names: Vec<_> = server.tools().iter().map(|t| t.name.to_string()).collect();
+        assert_eq!(names, ["TEST_create", "TEST_update"]);
+        let schema = &server.tools[0].tool.input_schema;
+        assert_eq!(schema["required"], json!(["command"]));
+    }
+
+    #[tokio::test]
+    async fn tool_calls_run_through_the_engine_and_land_in_the_audit_log() {
+        let (server, _) = server();
+        let created = server
+            .run_tool(
+                "TEST_create",
+                arguments(json!({ "command": { "Initialize": { "name": "toto" } } })),
+                context(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(created.is_error, Some(false));
+        let id = created.structured_content.unwrap()["id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        let updated = server
+            .run_tool(
+                "TEST_update",
+                arguments(json!({ "TEST_id": id, "command": "Increment" })),
+                context(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(updated.structured_content, Some(json!({ "id": id })));
+
+        let audit = server
+            .read_uri(
+                &format!("cqrs://TEST/audit/{id}?page_size=1&page=2"),
+                context(),
+            )
+            .await
+            .unwrap();
+        let audit = contents(&audit);
+        assert_eq!(audit["total"], 2);
+        assert_eq!(audit["items"][0]["version"], 2);
+        assert_eq!(audit["items"][0]["payload"], "Incremented");
+        assert_eq!(audit["items"][0]["metadata"]["user_id"], "alice");
+        assert_eq!(audit["items"][0]["metadata"]["request_id"], "r-1");
+    }
+
+    #[tokio::test]
+    async fn a_failed_command_is_an_error_result_and_an_unknown_tool_a_protocol_error() {
+        let (server, _) = server();
+        let rejected = server
+            .run_tool(
+                "TEST_create",
+                arguments(json!({ "command": { "Nope": {} } })),
+                context(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(rejected.is_error, Some(true));
+        let error = rejected.structured_content.unwrap();
+        assert_eq!(error["code"], "GENERIC_UNPROCESSABLE_ENTITY");
+        assert_eq!(error["requestId"], "r-1");
+
+        let missing_id = server
+            .run_tool(
+                "TEST_update",
+                arguments(json!({ "command": "Increment" })),
+                context(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(missing_id.is_error, Some(true));
+
+        let unknown = server
+            .run_tool("TEST_delete", JsonObject::new(), context())
+            .await
+            .unwrap_err();
+        assert_eq!(unknown.code, ErrorCode::METHOD_NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn views_are_read_through_the_storage() {
+        let storage = Arc::new(InMemoryStorage::<Game, GameQuery>::new("game"));
+        storage
+            .save(
+                Game {
+                    id: "g 1".to_string(),
+                    title: "Catan".to_string(),
+                },
+                CqrsContext::default(),
+            )
+            .await
+            .unwrap();
+        let server = CqrsMcpServer::new("test", "0.0.0").views("game", storage);
+        let uris: Vec<_> = server
+            .resources
+            .iter()
+            .flat_map(|s| s.resources())
+            .map(|r| r.uri)
+            .collect();
+        assert_eq!(uris, ["cqrs://game/views"]);
+
+        let list = contents(
+            &server
+                .read_uri("cqrs://game/views", context())
+                .await
+                .unwrap(),
+        );
+        assert_eq!(list["total"], 1);
+        let one = contents(
+            &server
+                .read_uri("cqrs://game/views/g%201", context())
+                .await
+                .unwrap(),
+        );
+        assert_eq!(one, json!({ "id": "g 1", "title": "Catan" }));
+
+        for uri in ["cqrs://game/views/g2", "cqrs://other/views", "https://game"] {
+            let error = server.read_uri(uri, context()).await.unwrap_err();
+            assert_eq!(error.code, ErrorCode::RESOURCE_NOT_FOUND, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn view_resources_are_behind_the_read_policies() {
+        let storage = Arc::new(InMemoryStorage::<Game, GameQuery>::new("game"));
+        let server = CqrsMcpServer::new("test", "0.0.0").views_with_policies(
+            "game",
+            storage,
+            ReadPolicies::new().with_find_many(crate::Policy::any_role(["librarian"])),
+        );
+
+        let error = server
+            .read_uri("cqrs://game/views", context())
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+        let librarian = context().with_roles(vec!["librarian".to_string()]);
+        let list = contents(
+            &server
+                .read_uri("cqrs://game/views", librarian)
+                .await
+                .unwrap(),
+        );
+        assert_eq!(list["total"], 0);
+
+        // No policy on the read by id: it goes on to the storage.
+        let error = server
+            .read_uri("cqrs://game/views/g1", context())
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::RESOURCE_NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn the_audit_resource_is_behind_the_read_policies() {
+        let store: DynEventStore<TestAggregate> = EventStoreImpl::new(InMemoryPersist::new());
+        let server = CqrsMcpServer::new("test", "0.0.0").audit_log_with_policies(
+            store,
+            ReadPolicies::new().with_find_one(crate::Policy::any_role(["auditor"])),
+        );
+        let uri = format!("cqrs://{}/audit/a1", TestAggregate::TYPE);
+
+        let error = server.read_uri(&uri, context()).await.unwrap_err();
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+        let auditor = context().with_roles(vec!["auditor".to_string()]);
+        let page = contents(&server.read_uri(&uri, auditor).await.unwrap());
+        assert_eq!(page["total"], 0);
+    }
+}
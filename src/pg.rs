@@ -48,6 +48,16 @@ pub trait PgPool: Send + Sync + Debug + Clone + 'static {
 }
 }
 
+/// A round trip through a connection of `pool`, for the `health` of the storages.
+pub(crate) async fn ping<P: PgPool>(pool: &P) -> Result<(), CqrsError> {
+    pool.acquire()
+        .await?
+        .client()
+        .batch_execute("SELECT 1")
+        .await
+        .map_err(CqrsError::database_error)
+}
+
 /// Wraps a single `Arc<Client>`. NOT safe for concurrent transactions.
 #[derive(Debug, Clone)]
 pub struct SharedClient(pub Arc<Client>);
@@ -0,0 +1,526 @@
+use crate::{CqrsContext, CqrsError};
+use serde::Serialize;
+use serde_json::json;
+#[cfg(feature = "utoipa")]
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::sync::Arc;
+#[cfg(feature = "utoipa")]
+use utoipa::openapi::{RefOr, Schema};
+
+/// A rule over the context of a request and what it acts on.
+pub type PolicyPredicate<T> = Arc<dyn Fn(&CqrsContext, &T) -> bool + Send + Sync>;
+
+/// Who may run a command, or call a read route, declared next to the routes rather
+/// than inside `handle_update`.
+///
+/// A policy asks for an identified user ([`authenticated`](Self::authenticated)),
+/// optionally one of a set of roles ([`any_role`](Self::any_role)), and any number of
+/// predicates over the [`CqrsContext`] and the subject `T` — the loaded aggregate for a
+/// command, nothing for a read route. An anonymous caller is refused with `401`, a
+/// caller without the roles or failing a predicate with `403`.
+///
+/// ```rust
+/// use cqrs_rust_lib::{CqrsContext, Policy};
+///
+/// #[derive(Default)]
+/// struct Account {
+///     owner: String,
+/// }
+///
+/// let policy = Policy::<Account>::any_role(["teller", "admin"])
+///     .require(|context, account| context.has_role("admin") || account.owner == context.current_user());
+///
+/// let bob = CqrsContext::new(Some("bob".to_string())).with_roles(vec!["teller".to_string()]);
+/// let account = Account { owner: "bob".to_string() };
+/// assert!(policy.authorize(&bob, &account).is_ok());
+/// assert_eq!(policy.authorize(&CqrsContext::default(), &account).unwrap_err().status, 401);
+/// ```
+pub struct Policy<T> {
+    authenticated: bool,
+    roles: Vec<String>,
+    predicates: Vec<PolicyPredicate<T>>,
+}
+
+impl<T> Clone for Policy<T> {
+    fn clone(&self) -> Self {
+        Self {
+            authenticated: self.authenticated,
+            roles: self.roles.clone(),
+            predicates: self.predicates.clone(),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Policy<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Policy")
+            .field("authenticated", &self.authenticated)
+            .field("roles", &self.roles)
+            .field("predicates", &self.predicates.len())
+            .finish()
+    }
+}
+
+impl<T> Policy<T> {
+    /// Lets everyone through, the anonymous user included, until a predicate is added.
+    #[must_use]
+    pub fn public() -> Self {
+        Self {
+            authenticated: false,
+            roles: Vec::new(),
+            predicates: Vec::new(),
+        }
+    }
+
+    /// Requires an identified user.
+    #[must_use]
+    pub fn authenticated() -> Self {
+        Self {
+            authenticated: true,
+            ..Self::public()
+        }
+    }
+
+    /// Requires an identified user holding at least one of `roles`.
+    #[must_use]
+    pub fn any_role<I, R>(roles: I) -> Self
+    where
+        I: IntoIterator<Item = R>,
+        R: Into<String>,
+    {
+        Self {
+            roles: roles.into_iter().map(Into::into).collect(),
+            ..Self::authenticated()
+        }
+    }
+
+    /// Adds a predicate; every predicate of the policy must hold.
+    #[must_use]
+    pub fn require<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&CqrsContext, &T) -> bool + Send + Sync + 'static,
+    {
+        self.predicates.push(Arc::new(predicate));
+        self
+    }
+
+    /// Whether the policy refuses the anonymous user, and is documented as such.
+    pub fn requires_authentication(&self) -> bool {
+        self.authenticated
+    }
+
+    /// The roles of which the caller needs one; empty when any role will do.
+    pub fn roles(&self) -> &[String] {
+        &self.roles
+    }
+
+    /// The checks that need no subject: the user, then the roles. Run before the
+    /// subject is loaded, so that a caller who may not act learns nothing about it.
+    pub fn authorize_context(&self, context: &CqrsContext) -> Result<(), CqrsError> {
+        if self.authenticated && !context.is_authenticated() {
+            return Err(CqrsError::unauthorized("authentication is required"));
+        }
+        if !self.roles.is_empty() && !self.roles.iter().any(|role| context.has_role(role)) {
+            return Err(CqrsError::forbidden(format!(
+                "one of the roles {} is required",
+                self.roles.join(", ")
+            ))
+            .with_details(json!({ "roles": self.roles })));
+        }
+        Ok(())
+    }
+
+    /// Every check of the policy, the predicates over `subject` included.
+    pub fn authorize(&self, context: &CqrsContext, subject: &T) -> Result<(), CqrsError> {
+        self.authorize_context(context)?;
+        if self
+            .predicates
+            .iter()
+            .all(|predicate| predicate(context, subject))
+        {
+            Ok(())
+        } else {
+            Err(CqrsError::forbidden(
+                "the policy does not allow this request",
+            ))
+        }
+    }
+}
+
+/// The policies of the commands of `A`, keyed by variant: the value of the
+/// discriminator tag, as `CQRSWriteRouter` discovers it from the command schemas
+/// (`"AddTodo"` for `{"type": "AddTodo", ...}`).
+///
+/// The engine reads the variant of a command off the command itself, serialized and
+/// matched against its schema — whichever front end it came in by, so that a command
+/// cannot pass for another. A variant without a policy of its own falls back to
+/// [`otherwise`](Self::otherwise); with no fallback it is not checked. While any variant
+/// has a policy of its own, a command whose tag cannot be read — of an externally
+/// tagged or untagged enum, or any command without the `utoipa` feature — is refused
+/// with `403` rather than run under `otherwise`.
+///
+/// ```rust
+/// use cqrs_rust_lib::{CommandPolicies, Policy};
+/// # #[derive(Default)] struct Todo;
+///
+/// let policies = CommandPolicies::<Todo>::new()
+///     .variant("DeleteTodo", Policy::any_role(["admin"]))
+///     .otherwise(Policy::authenticated());
+/// assert_eq!(policies.policy(Some("DeleteTodo")).unwrap().roles(), ["admin"]);
+/// assert!(policies.policy(Some("AddTodo")).unwrap().roles().is_empty());
+/// ```
+pub struct CommandPolicies<A> {
+    variants: HashMap<String, Policy<A>>,
+    otherwise: Option<Policy<A>>,
+}
+
+impl<A> Default for CommandPolicies<A> {
+    fn default() -> Self {
+        Self {
+            variants: HashMap::new(),
+            otherwise: None,
+        }
+    }
+}
+
+impl<A> Clone for CommandPolicies<A> {
+    fn clone(&self) -> Self {
+        Self {
+            variants: self.variants.clone(),
+            otherwise: self.otherwise.clone(),
+        }
+    }
+}
+
+impl<A> std::fmt::Debug for CommandPolicies<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CommandPolicies")
+            .field("variants", &self.variants)
+            .field("otherwise", &self.otherwise)
+            .finish()
+    }
+}
+
+impl<A> CommandPolicies<A> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The policy of the variant tagged `variant`.
+    #[must_use]
+    pub fn variant(mut self, variant: impl Into<String>, policy: Policy<A>) -> Self {
+        self.variants.insert(variant.into(), policy);
+        self
+    }
+
+    /// The policy of every variant not given one.
+    #[must_use]
+    pub fn otherwise(mut self, policy: Policy<A>) -> Self {
+        self.otherwise = Some(policy);
+        self
+    }
+
+    /// The variants given a policy of their own.
+    pub fn variants(&self) -> impl Iterator<Item = &str> {
+        self.variants.keys().map(String::as_str)
+    }
+
+    /// The policy applying to `variant`; `None` is an untagged command.
+    pub fn policy(&self, variant: Option<&str>) -> Option<&Policy<A>> {
+        variant
+            .and_then(|variant| self.variants.get(variant))
+            .or(self.otherwise.as_ref())
+    }
+
+    /// The policy `command` runs under, by the tag it serializes with: the one place
+    /// the engine picks a policy from.
+    pub(crate) fn policy_of<C: TaggedCommand>(
+        &self,
+        command: &C,
+    ) -> Result<Option<&Policy<A>>, CqrsError> {
+        let tag = command_tag(command);
+        if tag.is_none() && !self.variants.is_empty() {
+            return Err(CqrsError::forbidden(
+                "the variant of the command cannot be read from it, and its policy depends on it",
+            ));
+        }
+        Ok(self.policy(tag.as_deref()))
+    }
+}
+
+/// What the engine reads the tag of a command off: its serialized form, and its schema
+/// when the `utoipa` feature provides one.
+#[cfg(feature = "utoipa")]
+pub(crate) trait TaggedCommand: Serialize + utoipa::PartialSchema {}
+#[cfg(feature = "utoipa")]
+impl<C: Serialize + utoipa::PartialSchema> TaggedCommand for C {}
+#[cfg(not(feature = "utoipa"))]
+pub(crate) trait TaggedCommand: Serialize {}
+#[cfg(not(feature = "utoipa"))]
+impl<C: Serialize> TaggedCommand for C {}
+
+/// The tag `command` serializes with; `None` when it has none to read.
+pub(crate) fn command_tag<C: TaggedCommand>(command: &C) -> Option<String> {
+    #[cfg(feature = "utoipa")]
+    {
+        serde_json::to_value(command)
+            .ok()
+            .and_then(|body| tag_of(&C::schema(), &body))
+    }
+    // Without the schemas, no tag can be read.
+    #[cfg(not(feature = "utoipa"))]
+    {
+        let _ = command;
+        None
+    }
+}
+
+/// The tag `command` carries, among the tagged variants of `schema`: an object with a
+/// property of a single string value, the one `CQRSWriteRouter` splits the routes on.
+#[cfg(feature = "utoipa")]
+fn tag_of(schema: &RefOr<Schema>, command: &JsonValue) -> Option<String> {
+    match schema {
+        RefOr::T(Schema::OneOf(one_of)) => {
+            one_of.items.iter().find_map(|item| tag_of(item, command))
+        }
+        RefOr::T(Schema::AnyOf(any_of)) => {
+            any_of.items.iter().find_map(|item| tag_of(item, command))
+        }
+        RefOr::T(Schema::Object(object)) => {
+            // Opinionated, as the router: one discriminator per variant.
+            let (field, tag) =
+                object
+                    .properties
+                    .iter()
+                    .find_map(|(field, property)| match property {
+                        RefOr::T(Schema::Object(property)) => match property.enum_values.as_deref()
+                        {
+                            Some([JsonValue::String(tag)]) => Some((field, tag)),
+                            _ => None,
+                        },
+                        _ => None,
+                    })?;
+            (command.get(field).and_then(JsonValue::as_str) == Some(tag)).then(|| tag.clone())
+        }
+        _ => None,
+    }
+}
+
+/// The policies of the reads of a view: the routes of `CQRSReadRouter` and
+/// `CQRSCodexReadRouter`, the queries of `CQRSGraphQLSchema`, the resources of
+/// `CqrsMcpServer` and the subscriptions of `CQRSLiveViewRouter`. A read without one is
+/// open to whoever reaches it.
+#[derive(Clone, Debug, Default)]
+pub struct ReadPolicies {
+    find_one: Option<Policy<()>>,
+    find_many: Option<Policy<()>>,
+    aggregate: Option<Policy<()>>,
+}
+
+impl ReadPolicies {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The same policy on every route.
+    #[must_use]
+    pub fn all(policy: Policy<()>) -> Self {
+        Self {
+            find_one: Some(policy.clone()),
+            find_many: Some(policy.clone()),
+            aggregate: Some(policy),
+        }
+    }
+
+    /// `GET {base}/{id}`: one view, by id.
+    #[must_use]
+    pub fn with_find_one(self, policy: Policy<()>) -> Self {
+        Self {
+            find_one: Some(policy),
+            ..self
+        }
+    }
+
+    /// `GET {base}`: the list, and a subscription to it.
+    #[must_use]
+    pub fn with_find_many(self, policy: Policy<()>) -> Self {
+        Self {
+            find_many: Some(policy),
+            ..self
+        }
+    }
+
+    /// `GET {base}/_aggregate`, on the codex router.
+    #[must_use]
+    pub fn with_aggregate(self, policy: Policy<()>) -> Self {
+        Self {
+            aggregate: Some(policy),
+            ..self
+        }
+    }
+
+    /// The policy of a read by id, if any.
+    pub fn find_one(&self) -> Option<&Policy<()>> {
+        self.find_one.as_ref()
+    }
+
+    /// The policy of a list, if any.
+    pub fn find_many(&self) -> Option<&Policy<()>> {
+        self.find_many.as_ref()
+    }
+
+    /// The policy of an aggregation, if any.
+    pub fn aggregate(&self) -> Option<&Policy<()>> {
+        self.aggregate.as_ref()
+    }
+}
+
+/// Checks a read's policy, if it has one.
+#[cfg(any(feature = "rest", feature = "mcp"))]
+pub(crate) fn authorize_read(
+    policy: Option<&Policy<()>>,
+    context: &CqrsContext,
+) -> Result<(), CqrsError> {
+    policy.map_or(Ok(()), |policy| policy.authorize(context, &()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Account {
+        owner: String,
+    }
+
+    fn user(name: &str, roles: &[&str]) -> CqrsContext {
+        CqrsContext::new(Some(name.to_string()))
+            .with_roles(roles.iter().map(|r| r.to_string()).collect())
+    }
+
+    #[test]
+    fn anonymous_callers_get_401_and_callers_without_the_role_403() {
+        let policy = Policy::<()>::any_role(["admin", "auditor"]);
+
+        let error = policy.authorize(&CqrsContext::default(), &()).unwrap_err();
+        assert_eq!(error.status, 401);
+
+        let error = policy
+            .authorize(&user("bob", &["teller"]), &())
+            .unwrap_err();
+        assert_eq!(error.status, 403);
+        assert_eq!(error.message, "one of the roles admin, auditor is required");
+        assert_eq!(
+            error.details,
+            Some(json!({ "roles": ["admin", "auditor"] }))
+        );
+
+        assert!(policy.authorize(&user("eve", &["auditor"]), &()).is_ok());
+        assert!(
+            Policy::<()>::public()
+                .authorize(&CqrsContext::default(), &())
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn predicates_see_the_subject_and_all_must_hold() {
+        let policy = Policy::<Account>::authenticated()
+            .require(|context, account| account.owner == context.current_user())
+            .require(|context, _| context.tenant() == Some("acme"));
+        let account = Account {
+            owner: "bob".to_string(),
+        };
+
+        let bob = user("bob", &[]).with_tenant(Some("acme".to_string()));
+        assert!(policy.authorize(&bob, &account).is_ok());
+        // The context checks alone pass whatever the subject.
+        assert!(policy.authorize_context(&user("alice", &[])).is_ok());
+
+        let error = policy
+            .authorize(
+                &user("alice", &[]).with_tenant(Some("acme".to_string())),
+                &account,
+            )
+            .unwrap_err();
+        assert_eq!(error.status, 403);
+        assert!(policy.authorize(&user("bob", &[]), &account).is_err());
+    }
+
+    #[test]
+    fn variants_fall_back_to_the_default_policy() {
+        let policies =
+            CommandPolicies::<Account>::new().variant("Close", Policy::any_role(["admin"]));
+        assert!(policies.policy(Some("Close")).is_some());
+        assert!(policies.policy(Some("Deposit")).is_none());
+        assert!(policies.policy(None).is_none());
+
+        let policies = policies.otherwise(Policy::authenticated());
+        assert!(
+            policies
+                .policy(Some("Deposit"))
+                .unwrap()
+                .requires_authentication()
+        );
+        assert!(policies.policy(None).unwrap().roles().is_empty());
+    }
+
+    #[cfg(feature = "utoipa")]
+    #[test]
+    fn the_tag_is_read_off_the_command_body() {
+        #[derive(serde::Serialize, utoipa::ToSchema)]
+        #[serde(tag = "type")]
+        #[allow(dead_code)]
+        enum Commands {
+            Rename { name: String },
+            Archive,
+        }
+        let schema = <Commands as utoipa::PartialSchema>::schema();
+        let tag = |command: JsonValue| tag_of(&schema, &command);
+        assert_eq!(
+            tag(serde_json::json!({"type": "Archive"})),
+            Some("Archive".to_string())
+        );
+        assert_eq!(
+            tag(serde_json::json!({"type": "Rename", "name": "n"})),
+            Some("Rename".to_string())
+        );
+        assert_eq!(tag(serde_json::json!({"type": "Delete"})), None);
+    }
+
+    #[cfg(feature = "utoipa")]
+    #[test]
+    fn the_policy_is_picked_by_the_tag_of_the_command() {
+        #[derive(serde::Serialize, utoipa::ToSchema)]
+        #[serde(tag = "type")]
+        enum Tagged {
+            Rename { name: String },
+            Archive,
+        }
+        #[derive(serde::Serialize, utoipa::ToSchema)]
+        enum External {
+            Archive,
+        }
+        let policies = CommandPolicies::<()>::new()
+            .otherwise(Policy::authenticated())
+            .variant("Archive", Policy::any_role(["admin"]));
+        let refuses_bob = |policy: Option<&Policy<()>>| {
+            policy.unwrap().authorize(&user("bob", &[]), &()).is_err()
+        };
+
+        assert!(refuses_bob(policies.policy_of(&Tagged::Archive).unwrap()));
+        let rename = Tagged::Rename {
+            name: "n".to_string(),
+        };
+        assert!(!refuses_bob(policies.policy_of(&rename).unwrap()));
+        let error = policies.policy_of(&External::Archive).unwrap_err();
+        assert_eq!(error.status, 403);
+        let otherwise = CommandPolicies::<()>::new().otherwise(Policy::authenticated());
+        assert!(!refuses_bob(
+            otherwise.policy_of(&External::Archive).unwrap()
+        ));
+    }
+}
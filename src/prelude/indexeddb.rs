@@ -0,0 +1,8 @@
+pub use crate::es::indexeddb::{IndexedDbPersist, IndexedDbSession};
+pub use crate::es::indexeddb::IndexedDbPersist as EventStorePersist;
+pub use crate::read::indexeddb::{IndexedDbFromSnapshotStorage, IndexedDbStorage};
+pub use crate::read::indexeddb::IndexedDbFromSnapshotStorage as FromSnapshotStorage;
+pub use crate::read::indexeddb::IndexedDbStorage as ReadStorage;
+pub use crate::read::query::{Pagination, Query};
+pub use crate::read::{SortDirection, Sorter};
+pub use crate::indexeddb::{IndexedDb, IndexedDbSchema};
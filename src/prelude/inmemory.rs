@@ -1,3 +1,7 @@
 pub use crate::es::inmemory::InMemoryPersist;
 pub use crate::es::inmemory::InMemoryPersist as EventStorePersist;
+pub use crate::read::inmemory::InMemoryStorage;
+pub use crate::read::inmemory::InMemoryStorage as ReadStorage;
+pub use crate::read::query::{Pagination, Query};
 pub use crate::read::InMemoryViewStore;
+pub use crate::read::{SortDirection, Sorter};
@@ -6,5 +6,14 @@ pub mod postgres;
 #[cfg(feature = "mongodb")]
 pub mod mongodb;
 
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+#[cfg(feature = "redb")]
+pub mod redb;
+
+#[cfg(all(feature = "indexeddb", target_arch = "wasm32"))]
+pub mod indexeddb;
+
 #[cfg(feature = "surrealdb")]
 pub mod surrealdb;
@@ -0,0 +1,9 @@
+pub use crate::es::redb::{RedbPersist, RedbSession};
+pub use crate::es::redb::RedbPersist as EventStorePersist;
+pub use crate::read::redb::{RedbFromSnapshotStorage, RedbStorage};
+pub use crate::read::redb::RedbFromSnapshotStorage as FromSnapshotStorage;
+pub use crate::read::redb::RedbStorage as ReadStorage;
+pub use crate::read::query::{Pagination, Query};
+pub use crate::read::{SortDirection, Sorter};
+pub use crate::redb::{open, open_in_memory};
+pub use redb::Database;
@@ -0,0 +1,9 @@
+pub use crate::es::sqlite::{SqliteJournals, SqlitePersist, SqliteSession};
+pub use crate::es::sqlite::SqlitePersist as EventStorePersist;
+pub use crate::read::sqlite::{JsonDataMapper, SqliteFromSnapshotStorage, SqliteStorage};
+pub use crate::read::sqlite::SqliteFromSnapshotStorage as FromSnapshotStorage;
+pub use crate::read::sqlite::SqliteStorage as ReadStorage;
+pub use crate::read::query::{Pagination, Query};
+pub use crate::read::{SortDirection, Sorter};
+pub use crate::sqlite::SharedConnection;
+pub use rest_sql::{FieldMapper, IdentityMapper};
@@ -0,0 +1,361 @@
+//! Group-by queries over a view: keys to group on, and metrics to compute per group.
+//!
+//! An [`Aggregation`] runs **after** the query's filter, over exactly the set
+//! `Storage::filter` would page through — parent scoping included — and is compiled by
+//! each backend into its own grouping construct: `GROUP BY` in Postgres and SurrealDB, a
+//! `$group` stage in MongoDB. The in-memory storage evaluates it directly.
+//!
+//! Every backend answers in the same shape, [`AggregateRow`], one per group, ordered by
+//! the group keys. Internally the backends name their columns positionally (`g0`, `m0`,
+//! …) and [`AggregateRow::from_positional`] puts the real names back: a dotted field
+//! is a legal group key but not a legal `$group` output name in MongoDB, and a metric
+//! name such as `sum(amount)` is not a legal alias anywhere.
+
+use crate::read::sorter::validated_field_name;
+use crate::CqrsError;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+use std::fmt;
+use std::str::FromStr;
+#[cfg(feature = "utoipa")]
+use utoipa::ToSchema;
+
+/// What a [`Metric`] computes over a group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricFunction {
+    /// Number of items in the group. Takes no field.
+    Count,
+    Sum,
+    Min,
+    Max,
+    Avg,
+}
+
+impl MetricFunction {
+    fn as_str(self) -> &'static str {
+        match self {
+            MetricFunction::Count => "count",
+            MetricFunction::Sum => "sum",
+            MetricFunction::Min => "min",
+            MetricFunction::Max => "max",
+            MetricFunction::Avg => "avg",
+        }
+    }
+}
+
+/// One computed value per group: `count`, or a function over a field — `sum(amount)`.
+///
+/// `sum` and `avg` read the field as a number; an item whose value is absent, `null` or
+/// not a number is left out of them, as SQL leaves out a `NULL`. `min` and `max` compare
+/// the stored values as they are, so they also work on ISO dates and strings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Metric {
+    pub function: MetricFunction,
+    /// `None` for [`MetricFunction::Count`], the field for every other function.
+    pub field: Option<String>,
+}
+
+impl Metric {
+    #[must_use]
+    pub fn count() -> Self {
+        Self {
+            function: MetricFunction::Count,
+            field: None,
+        }
+    }
+
+    #[must_use]
+    pub fn sum(field: &str) -> Self {
+        Self::over(MetricFunction::Sum, field)
+    }
+
+    #[must_use]
+    pub fn min(field: &str) -> Self {
+        Self::over(MetricFunction::Min, field)
+    }
+
+    #[must_use]
+    pub fn max(field: &str) -> Self {
+        Self::over(MetricFunction::Max, field)
+    }
+
+    #[must_use]
+    pub fn avg(field: &str) -> Self {
+        Self::over(MetricFunction::Avg, field)
+    }
+
+    fn over(function: MetricFunction, field: &str) -> Self {
+        Self {
+            function,
+            field: Some(field.to_string()),
+        }
+    }
+
+    /// The key this metric is reported under in [`AggregateRow::metrics`] — the way it
+    /// is written in the `metrics` param: `count`, `sum(amount)`.
+    #[must_use]
+    pub fn name(&self) -> String {
+        self.to_string()
+    }
+
+    /// The field, checked against the identifier grammar of [`crate::read::Sorter::validated_field`]:
+    /// it is interpolated into the generated query exactly as a sort field is.
+    pub(crate) fn validated_field(&self) -> Result<Option<&str>, CqrsError> {
+        match (self.function, self.field.as_deref()) {
+            (MetricFunction::Count, None) => Ok(None),
+            (MetricFunction::Count, Some(_)) => Err(CqrsError::validation(
+                "metric count takes no field: it counts the items of each group",
+            )),
+            (function, None) => Err(CqrsError::validation(format!(
+                "metric {} needs a field: {}(field)",
+                function.as_str(),
+                function.as_str()
+            ))),
+            (_, Some(field)) => validated_field_name("metric", field).map(Some),
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.field {
+            Some(field) => write!(f, "{}({field})", self.function.as_str()),
+            None => f.write_str(self.function.as_str()),
+        }
+    }
+}
+
+/// Reads the `metrics` param spelling: `count`, `sum(amount)`, `avg(line.total)`.
+impl FromStr for Metric {
+    type Err = CqrsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (name, field) = match s.split_once('(') {
+            Some((name, rest)) => match rest.strip_suffix(')') {
+                Some(field) => (name.trim(), Some(field.trim())),
+                None => {
+                    return Err(CqrsError::validation(format!(
+                        "metric {s:?} is not of the form function(field)"
+                    )));
+                }
+            },
+            None => (s, None),
+        };
+        let function = match name {
+            "count" => MetricFunction::Count,
+            "sum" => MetricFunction::Sum,
+            "min" => MetricFunction::Min,
+            "max" => MetricFunction::Max,
+            "avg" => MetricFunction::Avg,
+            _ => {
+                return Err(CqrsError::validation(format!(
+                    "metric {s:?} is not one of count, sum, min, max, avg"
+                )));
+            }
+        };
+        let metric = Metric {
+            function,
+            field: field.map(str::to_string),
+        };
+        metric.validated_field()?;
+        Ok(metric)
+    }
+}
+
+/// Group-by keys and the metrics to compute per group.
+///
+/// No key at all is one group over the whole filtered set. A group whose key is absent
+/// or `null` on its items is a group of its own, reported with a `null` key.
+///
+/// The result is not paged: one row per distinct key, so a key with unbounded
+/// cardinality — an id, a timestamp — produces as many rows as there are items. Group on
+/// what the view stores for the purpose, such as a `month` field next to the date.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Aggregation {
+    pub group_by: Vec<String>,
+    pub metrics: Vec<Metric>,
+}
+
+impl Aggregation {
+    #[must_use]
+    pub fn new<S: Into<String>>(
+        group_by: impl IntoIterator<Item = S>,
+        metrics: impl IntoIterator<Item = Metric>,
+    ) -> Self {
+        Self {
+            group_by: group_by.into_iter().map(Into::into).collect(),
+            metrics: metrics.into_iter().collect(),
+        }
+    }
+
+    /// Every field the aggregation names — the keys, then the metrics' fields.
+    #[must_use]
+    pub fn fields(&self) -> Vec<&str> {
+        self.group_by
+            .iter()
+            .map(String::as_str)
+            .chain(self.metrics.iter().filter_map(|m| m.field.as_deref()))
+            .collect()
+    }
+
+    /// Checks every name before a backend interpolates it, and that the result has a
+    /// shape: at least one metric, no key or metric twice.
+    pub(crate) fn validate(&self) -> Result<(), CqrsError> {
+        if self.metrics.is_empty() {
+            return Err(CqrsError::validation(
+                "an aggregation needs at least one metric",
+            ));
+        }
+        for (i, key) in self.group_by.iter().enumerate() {
+            validated_field_name("group_by", key)?;
+            if self.group_by[..i].contains(key) {
+                return Err(CqrsError::validation(format!(
+                    "group_by field {key:?} is named twice"
+                )));
+            }
+        }
+        for (i, metric) in self.metrics.iter().enumerate() {
+            metric.validated_field()?;
+            if self.metrics[..i].contains(metric) {
+                return Err(CqrsError::validation(format!(
+                    "metric {:?} is named twice",
+                    metric.name()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One group: its key values by field name, and its metrics by [`Metric::name`].
+///
+/// ```json
+/// { "group": { "account_id": "a1", "month": "2026-03" },
+///   "metrics": { "count": 12, "sum(amount)": 1530.5 } }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(ToSchema))]
+pub struct AggregateRow {
+    #[cfg_attr(feature = "utoipa", schema(value_type = Object))]
+    pub group: Map<String, JsonValue>,
+    #[cfg_attr(feature = "utoipa", schema(value_type = Object))]
+    pub metrics: Map<String, JsonValue>,
+}
+
+impl AggregateRow {
+    /// The positional name of the `i`-th group key in a backend's own result.
+    pub(crate) fn group_column(i: usize) -> String {
+        format!("g{i}")
+    }
+
+    /// The positional name of the `i`-th metric in a backend's own result.
+    pub(crate) fn metric_column(i: usize) -> String {
+        format!("m{i}")
+    }
+
+    /// Renames a backend row keyed `g0…`/`m0…` to the aggregation's own names. A column
+    /// the backend left out — SurrealDB omits a `NONE` — reads as `null`.
+    pub(crate) fn from_positional(
+        aggregation: &Aggregation,
+        mut row: Map<String, JsonValue>,
+    ) -> Self {
+        let mut take = |column: String| row.remove(&column).unwrap_or(JsonValue::Null);
+        let group = aggregation
+            .group_by
+            .iter()
+            .enumerate()
+            .map(|(i, key)| (key.clone(), take(Self::group_column(i))))
+            .collect();
+        let metrics = aggregation
+            .metrics
+            .iter()
+            .enumerate()
+            .map(|(i, metric)| (metric.name(), take(Self::metric_column(i))))
+            .collect();
+        Self { group, metrics }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn metrics_parse_from_their_param_spelling_and_print_back_to_it() {
+        for raw in [
+            "count",
+            "sum(amount)",
+            "min(date)",
+            "max(a.b)",
+            "avg(total)",
+        ] {
+            let metric: Metric = raw.parse().unwrap();
+            assert_eq!(metric.name(), raw);
+        }
+        assert_eq!(
+            " sum( amount ) ".parse::<Metric>().unwrap(),
+            Metric::sum("amount")
+        );
+    }
+
+    #[test]
+    fn a_malformed_metric_is_a_validation_error_naming_it() {
+        for raw in [
+            "median(x)",
+            "sum",
+            "count(x)",
+            "sum(x",
+            "sum(1 UNION SELECT)",
+            "sum()",
+        ] {
+            let err = raw.parse::<Metric>().unwrap_err();
+            assert_eq!(err.status, 400, "{raw}");
+        }
+    }
+
+    #[test]
+    fn an_aggregation_is_validated_before_any_backend_sees_it() {
+        assert!(
+            Aggregation::new(["account_id"], [Metric::count()])
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            Aggregation::new(Vec::<String>::new(), [Metric::sum("amount")])
+                .validate()
+                .is_ok()
+        );
+
+        for bad in [
+            Aggregation::new(["account_id"], []),
+            Aggregation::new(["id--"], [Metric::count()]),
+            Aggregation::new(["a", "a"], [Metric::count()]),
+            Aggregation::new(["a"], [Metric::count(), Metric::count()]),
+            Aggregation::new(["a"], [Metric::sum("x y")]),
+        ] {
+            let err = bad.validate().unwrap_err();
+            assert_eq!(err.code, "GENERIC_VALIDATION_FAILED", "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn fields_lists_keys_then_metric_fields() {
+        let aggregation =
+            Aggregation::new(["account_id"], [Metric::count(), Metric::sum("amount")]);
+        assert_eq!(aggregation.fields(), ["account_id", "amount"]);
+    }
+
+    #[test]
+    fn positional_columns_are_renamed_and_missing_ones_are_null() {
+        let aggregation = Aggregation::new(["a.b"], [Metric::count(), Metric::avg("x")]);
+        let row = json!({ "g0": "k", "m0": 3 }).as_object().unwrap().clone();
+        let row = AggregateRow::from_positional(&aggregation, row);
+        assert_eq!(
+            serde_json::to_value(row).unwrap(),
+            json!({ "group": { "a.b": "k" }, "metrics": { "count": 3, "avg(x)": null } })
+        );
+    }
+}
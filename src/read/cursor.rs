@@ -0,0 +1,504 @@
+//! Keyset (cursor) pagination, shared by every read backend.
+//!
+//! A keyset page is not compiled by each backend on its own. The "after this item"
+//! condition is built here as a `rest_sql` AST and ANDed with the caller's filter, so it
+//! reaches the database through the same compiler — and the same [`FieldMapper`] — as
+//! any other filter. What a backend has to do is small: apply [`Keyset::order`] instead
+//! of the query's sort, fetch one row more than the limit, and hand the rows to
+//! [`Keyset::finish`].
+//!
+//! [`FieldMapper`]: rest_sql::FieldMapper
+
+use crate::read::{SortDirection, Sorter};
+use crate::CqrsError;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rest_sql::{filter, Ast, RestSql, Value};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+/// Where a keyset page starts, as an opaque token from a previous [`crate::read::Paged`].
+///
+/// `After` takes a `nextCursor` and returns the items following it; `Before` takes a
+/// `prevCursor` and returns the items preceding it, still in the sort's order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PageCursor {
+    After(String),
+    Before(String),
+}
+
+/// What a token carries: the sort it was issued under and the boundary item's values
+/// along it, the id last.
+///
+/// The sort travels with the values so a token cannot be replayed under another sort,
+/// where the same values would describe a different position.
+#[derive(Debug, Serialize, Deserialize)]
+struct Token {
+    s: String,
+    v: Vec<JsonValue>,
+}
+
+fn invalid_cursor(reason: &str) -> CqrsError {
+    CqrsError::validation(format!("cursor: {reason}"))
+}
+
+/// Writes a sort the way the `sort` param reads it — `-created_at,title,id` — which is
+/// also how a token names the sort it belongs to.
+fn sort_spec(sort: &[Sorter]) -> String {
+    sort.iter()
+        .map(|s| match s.direction {
+            SortDirection::Asc => s.field.clone(),
+            SortDirection::Desc => format!("-{}", s.field),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// A JSON scalar as a filter value. `None` for anything a comparison cannot bind: a
+/// `null`, an array, an object.
+fn to_value(json: &JsonValue) -> Option<Value> {
+    match json {
+        JsonValue::Bool(b) => Some(Value::Bool(*b)),
+        JsonValue::Number(n) => n
+            .as_i64()
+            .map(Value::Int)
+            .or_else(|| n.as_f64().map(Value::Float)),
+        JsonValue::String(s) => Some(Value::String(s.clone())),
+        JsonValue::Null | JsonValue::Array(_) | JsonValue::Object(_) => None,
+    }
+}
+
+/// A keyset page: the order a backend applies, and the condition that starts the page
+/// past the cursor.
+///
+/// Planned for every request with a sort in effect, cursor or not, so that the first page
+/// already hands out a `nextCursor`. Planning it costs one extra sort term — the id, as
+/// the tie-breaker that makes the order total — and one extra row. Without a sort there
+/// is no keyset: the library does not invent an order (see [`crate::read::Pagination`]),
+/// and a cursor sent anyway is refused rather than ignored.
+#[derive(Debug)]
+pub(crate) struct Keyset {
+    /// The sort as the caller declared it, id tie-breaker included. Tokens name this one.
+    sort: Vec<Sorter>,
+    condition: Option<Ast>,
+    backwards: bool,
+    has_cursor: bool,
+}
+
+impl Keyset {
+    /// Plans the page, or `None` when no sort is in effect and no cursor was given.
+    ///
+    /// Every sort field goes through [`Sorter::validated_field`] here — the fields are
+    /// interpolated into the keyset condition's column names, exactly as into an
+    /// `ORDER BY`. A token that does not decode, or that was issued under another sort,
+    /// is a [`CqrsError::validation`].
+    pub(crate) fn plan(
+        sort: Option<&[Sorter]>,
+        id_field: &str,
+        cursor: Option<PageCursor>,
+    ) -> Result<Option<Self>, CqrsError> {
+        let mut sort: Vec<Sorter> = sort.unwrap_or_default().to_vec();
+        if sort.is_empty() {
+            return match cursor {
+                Some(_) => Err(invalid_cursor(
+                    "a cursor needs a sort in effect, and this query declares none",
+                )),
+                None => Ok(None),
+            };
+        }
+        for sorter in &sort {
+            sorter.validated_field()?;
+        }
+        if !sort.iter().any(|s| s.field == id_field) {
+            sort.push(Sorter {
+                field: id_field.to_string(),
+                direction: SortDirection::Asc,
+            });
+        }
+
+        let (token, backwards) = match cursor {
+            None => {
+                return Ok(Some(Self {
+                    sort,
+                    condition: None,
+                    backwards: false,
+                    has_cursor: false,
+                }));
+            }
+            Some(PageCursor::After(token)) => (token, false),
+            Some(PageCursor::Before(token)) => (token, true),
+        };
+
+        let bytes = URL_SAFE_NO_PAD
+            .decode(token.as_bytes())
+            .map_err(|_| invalid_cursor("not a cursor issued by this endpoint"))?;
+        let token: Token = serde_json::from_slice(&bytes)
+            .map_err(|_| invalid_cursor("not a cursor issued by this endpoint"))?;
+        if token.s != sort_spec(&sort) {
+            return Err(invalid_cursor(&format!(
+                "issued for sort {:?}, but the sort in effect is {:?}",
+                token.s,
+                sort_spec(&sort)
+            )));
+        }
+        let values = token
+            .v
+            .iter()
+            .map(to_value)
+            .collect::<Option<Vec<_>>>()
+            .filter(|v| v.len() == sort.len())
+            .ok_or_else(|| invalid_cursor("not a cursor issued by this endpoint"))?;
+
+        Ok(Some(Self {
+            condition: Some(Self::condition(&sort, values, backwards)),
+            sort,
+            backwards,
+            has_cursor: true,
+        }))
+    }
+
+    /// `(a > va) or (a == va and b > vb) or …` — each branch ties the terms before it and
+    /// steps past the boundary on its own term. `>` becomes `<` on a descending term and
+    /// flips again when paging backwards.
+    fn condition(sort: &[Sorter], values: Vec<Value>, backwards: bool) -> Ast {
+        let branches = (0..sort.len()).map(|k| {
+            let ties = sort[..k]
+                .iter()
+                .zip(&values)
+                .map(|(s, v)| filter::eq(&s.field, v.clone()));
+            let ascending = matches!(sort[k].direction, SortDirection::Asc) != backwards;
+            let step = if ascending {
+                filter::gt(&sort[k].field, values[k].clone())
+            } else {
+                filter::lt(&sort[k].field, values[k].clone())
+            };
+            Ast::and(ties.chain(std::iter::once(step)))
+        });
+        Ast::or(branches)
+    }
+
+    /// The top-level fields a token is read from — the root of each sort field, id
+    /// included. A projected page has to fetch them even when the caller did not ask.
+    #[cfg(any(
+        feature = "postgres",
+        feature = "mongodb",
+        feature = "sqlite",
+        feature = "surrealdb"
+    ))]
+    pub(crate) fn fields(&self) -> impl Iterator<Item = &str> {
+        self.sort
+            .iter()
+            .map(|s| s.field.split('.').next().unwrap_or(&s.field))
+    }
+
+    /// The sort the backend applies: the declared one, reversed when paging backwards so
+    /// the rows nearest the cursor come first. [`Keyset::finish`] restores the order.
+    pub(crate) fn order(&self) -> Vec<Sorter> {
+        self.sort
+            .iter()
+            .map(|s| Sorter {
+                field: s.field.clone(),
+                direction: match (&s.direction, self.backwards) {
+                    (SortDirection::Asc, false) | (SortDirection::Desc, true) => SortDirection::Asc,
+                    _ => SortDirection::Desc,
+                },
+            })
+            .collect()
+    }
+
+    /// The caller's filter ANDed with the keyset condition — the filter for the page
+    /// itself. The count keeps the caller's filter alone: `total` is the size of the
+    /// result set, not of what is left of it.
+    pub(crate) fn filter(&self, user: Option<RestSql>) -> Result<Option<RestSql>, CqrsError> {
+        let ast = match (user, &self.condition) {
+            (Some(user), Some(condition)) => user.ast().clone() & condition.clone(),
+            (None, Some(condition)) => condition.clone(),
+            (user, None) => return Ok(user),
+        };
+        RestSql::from_ast(ast)
+            .map(Some)
+            .map_err(|e| CqrsError::internal(e.to_string()))
+    }
+
+    /// The offset to apply. A cursor *is* the start of the page, so it replaces the
+    /// offset rather than adding to it.
+    pub(crate) fn offset(&self, requested: i64) -> i64 {
+        if self.has_cursor { 0 } else { requested }
+    }
+
+    /// How many rows to fetch: one past the page, which is how the page learns whether
+    /// anything follows it without a second query.
+    pub(crate) fn fetch_limit(&self, limit: i64) -> i64 {
+        limit.saturating_add(1)
+    }
+
+    /// Trims the extra row, restores the order of a backwards page, and issues the
+    /// `(next, prev)` tokens.
+    ///
+    /// A token is only issued for an item whose every sort value is a present scalar: a
+    /// `null` cannot be stepped past with `>`, so a page bounded by one has no cursor on
+    /// that side — offset paging still reaches it.
+    pub(crate) fn finish<V: Serialize>(
+        &self,
+        mut items: Vec<V>,
+        offset: i64,
+        limit: i64,
+    ) -> (Vec<V>, Option<String>, Option<String>) {
+        let limit = usize::try_from(limit.max(0)).unwrap_or(usize::MAX);
+        let more = items.len() > limit;
+        items.truncate(limit);
+        if self.backwards {
+            items.reverse();
+        }
+
+        // Paging forward, the extra row says whether there is a next page and the cursor
+        // or the offset whether there is a previous one; paging backwards, the other way
+        // round.
+        let (has_next, has_prev) = if self.backwards {
+            (true, more)
+        } else {
+            (more, self.has_cursor || offset > 0)
+        };
+        let next = has_next
+            .then(|| items.last())
+            .flatten()
+            .and_then(|item| self.token(item));
+        let prev = has_prev
+            .then(|| items.first())
+            .flatten()
+            .and_then(|item| self.token(item));
+        (items, next, prev)
+    }
+
+    fn token<V: Serialize>(&self, item: &V) -> Option<String> {
+        let doc = serde_json::to_value(item).ok()?;
+        let values = self
+            .sort
+            .iter()
+            .map(|s| {
+                s.field
+                    .split('.')
+                    .try_fold(&doc, |current, segment| current.get(segment))
+                    .filter(|v| to_value(v).is_some())
+                    .cloned()
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let token = Token {
+            s: sort_spec(&self.sort),
+            v: values,
+        };
+        Some(URL_SAFE_NO_PAD.encode(serde_json::to_vec(&token).ok()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read::inmemory::matches;
+    use serde_json::json;
+
+    fn asc(field: &str) -> Sorter {
+        Sorter {
+            field: field.to_string(),
+            direction: SortDirection::Asc,
+        }
+    }
+
+    fn desc(field: &str) -> Sorter {
+        Sorter {
+            field: field.to_string(),
+            direction: SortDirection::Desc,
+        }
+    }
+
+    fn rows() -> Vec<JsonValue> {
+        vec![
+            json!({"id": "a", "score": 1}),
+            json!({"id": "b", "score": 2}),
+            json!({"id": "c", "score": 2}),
+            json!({"id": "d", "score": 3}),
+        ]
+    }
+
+    /// Applies a keyset the way a backend does, over the rows above sorted by the
+    /// keyset's own order.
+    fn page(keyset: &Keyset, limit: i64) -> (Vec<String>, Option<String>, Option<String>) {
+        let condition = keyset.filter(None).unwrap();
+        let mut rows: Vec<JsonValue> = rows()
+            .into_iter()
+            .filter(|r| {
+                condition
+                    .as_ref()
+                    .is_none_or(|c| matches(c.ast(), r).unwrap())
+            })
+            .collect();
+        let order = keyset.order();
+        rows.sort_by(|a, b| {
+            for s in &order {
+                let ord = match (&a[&s.field], &b[&s.field]) {
+                    (JsonValue::Number(x), JsonValue::Number(y)) => x.as_i64().cmp(&y.as_i64()),
+                    (x, y) => x.as_str().cmp(&y.as_str()),
+                };
+                let ord = match s.direction {
+                    SortDirection::Asc => ord,
+                    SortDirection::Desc => ord.reverse(),
+                };
+                if ord.is_ne() {
+                    return ord;
+                }
+            }
+            std::cmp::Ordering::Equal
+        });
+        rows.truncate(keyset.fetch_limit(limit) as usize);
+        let (items, next, prev) = keyset.finish(rows, 0, limit);
+        let ids = items
+            .iter()
+            .map(|r| r["id"].as_str().unwrap().to_string())
+            .collect();
+        (ids, next, prev)
+    }
+
+    #[test]
+    fn no_sort_and_no_cursor_plans_nothing() {
+        assert!(Keyset::plan(None, "id", None).unwrap().is_none());
+        assert!(Keyset::plan(Some(&[]), "id", None).unwrap().is_none());
+    }
+
+    #[test]
+    fn a_cursor_without_a_sort_is_refused_rather_than_ignored() {
+        let err = Keyset::plan(None, "id", Some(PageCursor::After("x".into()))).unwrap_err();
+        assert_eq!(err.status, 400);
+        assert!(err.message.contains("sort"), "{}", err.message);
+    }
+
+    #[test]
+    fn the_id_breaks_ties_unless_the_sort_already_ends_in_it() {
+        let keyset = Keyset::plan(Some(&[desc("score")]), "id", None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(keyset.order(), vec![desc("score"), asc("id")]);
+
+        let keyset = Keyset::plan(Some(&[desc("id")]), "id", None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(keyset.order(), vec![desc("id")]);
+    }
+
+    #[test]
+    fn a_hostile_sort_field_is_rejected_before_anything_is_built() {
+        let err = Keyset::plan(Some(&[asc("1 UNION SELECT")]), "id", None).unwrap_err();
+        assert_eq!(err.code, "GENERIC_VALIDATION_FAILED");
+    }
+
+    /// Walks the whole set forward then back, across a tie on `score` that only the id
+    /// can break.
+    #[test]
+    fn pages_walk_forward_and_back_across_ties() {
+        let sort = [asc("score")];
+        let first = Keyset::plan(Some(&sort), "id", None).unwrap().unwrap();
+        let (ids, next, prev) = page(&first, 2);
+        assert_eq!(ids, ["a", "b"]);
+        assert!(prev.is_none(), "nothing precedes the first page");
+
+        let second = Keyset::plan(Some(&sort), "id", Some(PageCursor::After(next.unwrap())))
+            .unwrap()
+            .unwrap();
+        let (ids, next, prev) = page(&second, 2);
+        assert_eq!(
+            ids,
+            ["c", "d"],
+            "b and c tie on score; the id tells them apart"
+        );
+        assert!(next.is_none(), "nothing follows the last page");
+
+        let back = Keyset::plan(Some(&sort), "id", Some(PageCursor::Before(prev.unwrap())))
+            .unwrap()
+            .unwrap();
+        let (ids, next, prev) = page(&back, 2);
+        assert_eq!(
+            ids,
+            ["a", "b"],
+            "a backwards page comes back in the sort's order"
+        );
+        assert!(prev.is_none());
+        assert!(next.is_some());
+    }
+
+    #[test]
+    fn a_descending_sort_steps_downwards() {
+        let sort = [desc("score")];
+        let first = Keyset::plan(Some(&sort), "id", None).unwrap().unwrap();
+        let (ids, next, _) = page(&first, 3);
+        assert_eq!(ids, ["d", "b", "c"]);
+
+        let second = Keyset::plan(Some(&sort), "id", Some(PageCursor::After(next.unwrap())))
+            .unwrap()
+            .unwrap();
+        assert_eq!(page(&second, 3).0, ["a"]);
+    }
+
+    #[test]
+    fn a_token_is_refused_under_another_sort() {
+        let first = Keyset::plan(Some(&[asc("score")]), "id", None)
+            .unwrap()
+            .unwrap();
+        let next = page(&first, 1).1.unwrap();
+        let err =
+            Keyset::plan(Some(&[desc("score")]), "id", Some(PageCursor::After(next))).unwrap_err();
+        assert_eq!(err.status, 400);
+        assert!(err.message.contains("-score,id"), "{}", err.message);
+    }
+
+    #[test]
+    fn a_forged_token_is_a_validation_error() {
+        for forged in [
+            "%%%",
+            "bm90LWpzb24",
+            &URL_SAFE_NO_PAD.encode(r#"{"s":"score,id","v":[null,"a"]}"#),
+        ] {
+            let err = Keyset::plan(
+                Some(&[asc("score")]),
+                "id",
+                Some(PageCursor::After(forged.to_string())),
+            )
+            .unwrap_err();
+            assert_eq!(err.code, "GENERIC_VALIDATION_FAILED", "{forged}");
+        }
+    }
+
+    #[test]
+    fn an_item_with_a_null_sort_value_issues_no_cursor() {
+        let keyset = Keyset::plan(Some(&[asc("score")]), "id", None)
+            .unwrap()
+            .unwrap();
+        let rows = vec![
+            json!({"id": "a", "score": 1}),
+            json!({"id": "b", "score": null}),
+        ];
+        let (_, next, _) = keyset.finish(rows, 0, 1);
+        assert!(next.is_some(), "the boundary item has a score");
+
+        let rows = vec![
+            json!({"id": "b", "score": null}),
+            json!({"id": "c", "score": 1}),
+        ];
+        let (_, next, _) = keyset.finish(rows, 0, 1);
+        assert!(next.is_none(), "a null cannot be stepped past");
+    }
+
+    #[test]
+    fn an_offset_page_has_a_previous_cursor() {
+        let keyset = Keyset::plan(Some(&[asc("score")]), "id", None)
+            .unwrap()
+            .unwrap();
+        let (_, _, prev) = keyset.finish(rows(), 2, 2);
+        assert!(prev.is_some());
+        assert_eq!(keyset.offset(2), 2, "without a cursor the offset stands");
+
+        let cursor = keyset.finish(rows(), 0, 1).1.unwrap();
+        let after = Keyset::plan(Some(&[asc("score")]), "id", Some(PageCursor::After(cursor)))
+            .unwrap()
+            .unwrap();
+        assert_eq!(after.offset(2), 0, "a cursor replaces the offset");
+    }
+}
@@ -0,0 +1,476 @@
+use crate::indexeddb::{committed, js_error, key, parse_all, parse_one, ping, prefix_range, settle};
+use crate::read::inmemory::{aggregate_views, filter_views, parent_matches};
+use crate::read::query::Query;
+use crate::read::storage::{HasId, Storage, StorageError};
+use crate::read::{AggregateRow, Aggregation, Paged};
+use crate::snapshot::Snapshot;
+use crate::{Aggregate, CqrsContext, CqrsError, MaybeSend, MaybeSync, Tenancy, TenantScope};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use wasm_bindgen::JsValue;
+use web_sys::IdbTransactionMode;
+
+pub use crate::indexeddb::{IndexedDb, IndexedDbSchema};
+
+/// A view's key, or a snapshot's: the tenant — `""` unless the storage is under
+/// [`Tenancy::Column`] — and the id.
+fn view_key(tenant: &str, id: &str) -> JsValue {
+    key(&[JsValue::from_str(tenant), JsValue::from_str(id)])
+}
+
+/// Every row of `tenant` in `store`, in id order.
+async fn tenant_rows<T: DeserializeOwned>(
+    database: &IndexedDb,
+    store: &str,
+    tenant: &str,
+) -> Result<Vec<T>, CqrsError> {
+    let range = prefix_range(&[JsValue::from_str(tenant)])?;
+    let request = database
+        .store(store, IdbTransactionMode::Readonly)?
+        .get_all_with_key(&range)
+        .map_err(js_error)?;
+    parse_all(settle(&request).await?)
+}
+
+/// The row of `id` in `tenant`, if `store` has one.
+async fn tenant_row<T: DeserializeOwned>(
+    database: &IndexedDb,
+    store: &str,
+    tenant: &str,
+    id: &str,
+) -> Result<Option<T>, CqrsError> {
+    let request = database
+        .store(store, IdbTransactionMode::Readonly)?
+        .get(&view_key(tenant, id))
+        .map_err(js_error)?;
+    parse_one(settle(&request).await?)
+}
+
+/// Read-side storage over an IndexedDB object store, the view serialized as JSON under
+/// its id.
+///
+/// IndexedDB has no query language to compile a filter into: every query reads the
+/// tenant's views and evaluates itself against them exactly as
+/// [`InMemoryStorage`](crate::read::inmemory::InMemoryStorage) does — same operators,
+/// same `null` handling, same search ranking, same aggregation. That is a scan per
+/// query, which suits the few thousand rows a single user keeps on a device.
+///
+/// The store must be declared on the [`IndexedDbSchema`] — under [`Tenancy::Table`],
+/// each tenant's `{store}_{tenant}` too.
+#[derive(Debug, Clone)]
+pub struct IndexedDbStorage<V, Q> {
+    _phantom: PhantomData<(V, Q)>,
+    database: IndexedDb,
+    type_name: String,
+    store_name: String,
+    scope: TenantScope,
+}
+
+impl<V, Q> IndexedDbStorage<V, Q> {
+    #[must_use]
+    pub fn new(database: IndexedDb, type_name: &str, store_name: &str) -> Self {
+        Self {
+            _phantom: PhantomData,
+            database,
+            type_name: type_name.to_string(),
+            store_name: store_name.to_string(),
+            scope: TenantScope::default(),
+        }
+    }
+
+    /// Keeps each tenant's views apart: keys led by the tenant ([`Tenancy::Column`]), or
+    /// a `{store}_{tenant}` store per tenant ([`Tenancy::Table`]).
+    #[must_use]
+    pub fn with_tenancy(mut self, tenancy: Tenancy) -> Self {
+        self.scope = TenantScope::new(tenancy);
+        self
+    }
+
+    /// The views of the tenant in `scope`, in id order.
+    async fn load(&self, scope: &TenantScope) -> Result<Vec<V>, CqrsError>
+    where
+        V: DeserializeOwned,
+    {
+        tenant_rows(
+            &self.database,
+            &scope.table(&self.store_name)?,
+            scope.column()?.unwrap_or_default(),
+        )
+        .await
+    }
+}
+
+cqrs_async_trait! {
+impl<V, Q> Storage<V, Q> for IndexedDbStorage<V, Q>
+where
+    V: Debug + Clone + Default + Serialize + DeserializeOwned + MaybeSend + MaybeSync + HasId,
+    Q: Clone + Debug + MaybeSend + MaybeSync + Query,
+{
+    fn type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    fn id_field(&self) -> Option<&str> {
+        Some(V::field_id())
+    }
+
+    async fn health(&self) -> Result<(), CqrsError> {
+        ping(&self.database)
+    }
+
+    async fn filter(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        context: CqrsContext,
+    ) -> Result<Paged<V>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        filter_views(
+            &self.type_name,
+            V::field_id(),
+            &self.load(&scope).await?,
+            |view| parent_matches(view, parent_id.as_deref()),
+            &query,
+        )
+    }
+
+    async fn find_by_id(
+        &self,
+        parent_id: Option<String>,
+        id: &str,
+        context: CqrsContext,
+    ) -> Result<Option<V>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        // Checked before the read, so a child view read without its parent is refused
+        // whether or not the id exists — as the database backends do.
+        if V::parent_field_id().is_some() && parent_id.is_none() {
+            return Err(CqrsError::validation(
+                StorageError::MissingParentId.to_string(),
+            ));
+        }
+        let view: Option<V> = tenant_row(
+            &self.database,
+            &scope.table(&self.store_name)?,
+            scope.column()?.unwrap_or_default(),
+            id,
+        )
+        .await?;
+        match view {
+            Some(view) if parent_matches(&view, parent_id.as_deref())? => Ok(Some(view)),
+            _ => Ok(None),
+        }
+    }
+
+    async fn save(&self, entity: V, context: CqrsContext) -> Result<(), CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        if V::parent_field_id().is_some() && entity.parent_id().is_none() {
+            return Err(CqrsError::validation(
+                StorageError::MissingParentId.to_string(),
+            ));
+        }
+        let json = serde_json::to_string(&entity).map_err(CqrsError::serialization_error)?;
+        let tenant = scope.column()?.unwrap_or_default();
+        let store = scope.table(&self.store_name)?;
+        let transaction = self
+            .database
+            .transaction(&[&store], IdbTransactionMode::Readwrite)?;
+        transaction
+            .object_store(&store)
+            .and_then(|store| {
+                store.put_with_key(
+                    &JsValue::from_str(&json),
+                    &view_key(tenant, entity.id()),
+                )
+            })
+            .map_err(js_error)?;
+        committed(&transaction).await
+    }
+
+    async fn aggregate(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        aggregation: Aggregation,
+        context: CqrsContext,
+    ) -> Result<Vec<AggregateRow>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        aggregate_views(
+            &self.load(&scope).await?,
+            |view| parent_matches(view, parent_id.as_deref()),
+            &query,
+            &aggregation,
+        )
+    }
+}
+}
+
+/// Rejected when a caller hands a snapshot storage a parent id.
+///
+/// The other backends say the same thing; a caller swapping backends should not have to
+/// learn a second phrasing for the same refusal.
+const NO_PARENT_ON_SNAPSHOT: &str =
+    "a snapshot table has no parent column, so a parent id cannot be filtered on";
+
+/// Rejected when a caller hands a snapshot storage a keyset cursor.
+const NO_CURSOR_ON_SNAPSHOT: &str =
+    "a snapshot table does not support cursor pagination; use skip/limit";
+
+/// Rejected when a caller hands a snapshot storage a text search.
+const NO_SEARCH_ON_SNAPSHOT: &str =
+    "a snapshot table does not support text search; project a view to search";
+
+/// Read-side storage over the event store's **snapshot** store, as
+/// [`IndexedDbPersist`](crate::es::indexeddb::IndexedDbPersist) writes it. Filters and
+/// sorts apply to the aggregate itself, as they would to a view in
+/// [`IndexedDbStorage`]. Writing stays unsupported — the event store owns this store.
+#[derive(Debug, Clone)]
+pub struct IndexedDbFromSnapshotStorage<A, Q> {
+    _phantom: PhantomData<(A, Q)>,
+    database: IndexedDb,
+    snapshot_store: String,
+    scope: TenantScope,
+}
+
+impl<A, Q> IndexedDbFromSnapshotStorage<A, Q> {
+    /// `snapshot_store` is what `IndexedDbPersist::snapshot_store_name()` returns.
+    #[must_use]
+    pub fn new(database: IndexedDb, snapshot_store: &str) -> Self {
+        Self {
+            _phantom: PhantomData,
+            database,
+            snapshot_store: snapshot_store.to_string(),
+            scope: TenantScope::default(),
+        }
+    }
+
+    /// The [`Tenancy`] the `IndexedDbPersist` writing the snapshots was configured with.
+    #[must_use]
+    pub fn with_tenancy(mut self, tenancy: Tenancy) -> Self {
+        self.scope = TenantScope::new(tenancy);
+        self
+    }
+}
+
+cqrs_async_trait! {
+impl<A, Q> Storage<A, Q> for IndexedDbFromSnapshotStorage<A, Q>
+where
+    A: Aggregate,
+    Q: Clone + Debug + MaybeSend + MaybeSync + Query,
+{
+    fn type_name(&self) -> &str {
+        A::TYPE
+    }
+
+    async fn health(&self) -> Result<(), CqrsError> {
+        ping(&self.database)
+    }
+
+    async fn filter(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        context: CqrsContext,
+    ) -> Result<Paged<A>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        if parent_id.is_some() {
+            return Err(CqrsError::validation(NO_PARENT_ON_SNAPSHOT));
+        }
+        if query.cursor().is_some() {
+            return Err(CqrsError::validation(NO_CURSOR_ON_SNAPSHOT));
+        }
+        if query.search().is_some() {
+            return Err(CqrsError::validation(NO_SEARCH_ON_SNAPSHOT));
+        }
+
+        let aggregates: Vec<A> = tenant_rows::<Snapshot<A>>(
+            &self.database,
+            &scope.table(&self.snapshot_store)?,
+            scope.column()?.unwrap_or_default(),
+        )
+        .await?
+        .into_iter()
+        .map(|snapshot| snapshot.state)
+        .collect();
+        // No cursor reaches here, so the id field is never read.
+        filter_views(A::TYPE, "aggregateId", &aggregates, |_| Ok(true), &query)
+    }
+
+    async fn find_by_id(
+        &self,
+        parent_id: Option<String>,
+        id: &str,
+        context: CqrsContext,
+    ) -> Result<Option<A>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        if parent_id.is_some() {
+            return Err(CqrsError::validation(NO_PARENT_ON_SNAPSHOT));
+        }
+        let snapshot: Option<Snapshot<A>> = tenant_row(
+            &self.database,
+            &scope.table(&self.snapshot_store)?,
+            scope.column()?.unwrap_or_default(),
+            id,
+        )
+        .await?;
+        Ok(snapshot.map(|snapshot| snapshot.state))
+    }
+
+    async fn save(&self, _entity: A, _context: CqrsContext) -> Result<(), CqrsError> {
+        Err(CqrsError::database_error(StorageError::UnsupportedMethod(
+            "SnapshotStorage#save".to_string(),
+        )))
+    }
+}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::es::indexeddb::IndexedDbPersist;
+    use crate::es::storage::EventStoreStorage;
+    use crate::read::query::Pagination;
+    use crate::read::sorter::{SortDirection, Sorter};
+    use crate::read::PageCursor;
+    use crate::testing::{TestAggregate, TestEvent};
+    use rest_sql::RestSql;
+    use serde::Deserialize;
+    use wasm_bindgen_test::{wasm_bindgen_test, wasm_bindgen_test_configure};
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+    struct Article {
+        id: String,
+        title: String,
+        words: i64,
+    }
+
+    impl HasId for Article {
+        fn field_id() -> &'static str {
+            "id"
+        }
+        fn id(&self) -> &str {
+            &self.id
+        }
+        fn parent_field_id() -> Option<&'static str> {
+            None
+        }
+        fn parent_id(&self) -> Option<&str> {
+            None
+        }
+    }
+
+    /// A query spelled as RSQL, the way `_q` reaches a storage.
+    #[derive(Debug, Clone, Default, Serialize)]
+    struct ArticleQuery {
+        #[serde(skip)]
+        rsql: Option<&'static str>,
+        #[serde(skip)]
+        sort: Vec<Sorter>,
+    }
+
+    impl Query for ArticleQuery {
+        fn filter(&self) -> Option<RestSql> {
+            self.rsql.map(|rsql| RestSql::new(rsql).unwrap())
+        }
+        fn pagination(&self) -> Option<Pagination> {
+            None
+        }
+        fn cursor(&self) -> Option<PageCursor> {
+            None
+        }
+        fn sort(&self) -> Option<Vec<Sorter>> {
+            Some(self.sort.clone())
+        }
+    }
+
+    async fn open() -> IndexedDb {
+        IndexedDbSchema::new(&format!("cqrs-test-{}", uuid::Uuid::new_v4()))
+            .with_store("articles")
+            .with_event_store::<TestAggregate>()
+            .open()
+            .await
+            .unwrap()
+    }
+
+    fn article(id: &str, title: &str, words: i64) -> Article {
+        Article {
+            id: id.to_string(),
+            title: title.to_string(),
+            words,
+        }
+    }
+
+    #[wasm_bindgen_test]
+    async fn saved_views_are_filtered_sorted_and_found() {
+        let storage =
+            IndexedDbStorage::<Article, ArticleQuery>::new(open().await, "Article", "articles");
+        for view in [
+            article("1", "Lorem", 120),
+            article("2", "Ipsum", 800),
+            article("3", "Dolor", 450),
+        ] {
+            storage.save(view, CqrsContext::default()).await.unwrap();
+        }
+        // Saved again: replaced, not duplicated.
+        storage
+            .save(article("1", "Lorem", 300), CqrsContext::default())
+            .await
+            .unwrap();
+
+        let page = storage
+            .filter(
+                None,
+                ArticleQuery {
+                    rsql: Some("words>=300"),
+                    sort: vec![Sorter {
+                        field: "words".to_string(),
+                        direction: SortDirection::Desc,
+                    }],
+                },
+                CqrsContext::default(),
+            )
+            .await
+            .unwrap();
+        let ids: Vec<_> = page.items.iter().map(|a| a.id.as_str()).collect();
+        assert_eq!(ids, ["2", "3", "1"]);
+
+        let found = storage
+            .find_by_id(None, "1", CqrsContext::default())
+            .await
+            .unwrap();
+        assert_eq!(found.unwrap().words, 300);
+        assert!(
+            storage
+                .find_by_id(None, "9", CqrsContext::default())
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[wasm_bindgen_test]
+    async fn snapshots_are_read_as_aggregates() {
+        let database = open().await;
+        let persist = IndexedDbPersist::<TestAggregate>::new(database.clone());
+        let mut agg = TestAggregate::default().with_aggregate_id("a1".to_string());
+        agg.apply(TestEvent::Created { name: "foo".into() })
+            .unwrap();
+        let mut session = persist.start_session().await.unwrap();
+        persist.save_snapshot(&agg, 1, &mut session).await.unwrap();
+        persist.close_session(session).await.unwrap();
+
+        let storage = IndexedDbFromSnapshotStorage::<TestAggregate, ArticleQuery>::new(
+            database,
+            persist.snapshot_store_name(),
+        );
+        let page = storage
+            .filter(None, ArticleQuery::default(), CqrsContext::default())
+            .await
+            .unwrap();
+        assert_eq!(page.items.len(), 1);
+        assert!(storage.save(agg, CqrsContext::default()).await.is_err());
+    }
+}
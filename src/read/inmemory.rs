@@ -0,0 +1,1300 @@
+use crate::read::page_order::warn_if_page_order_undefined;
+use crate::read::query::{Pagination, Query};
+use crate::read::sorter::{SortDirection, Sorter};
+use crate::read::storage::{HasId, Storage, StorageError};
+use crate::read::search::{plan_keyset, words};
+use crate::read::{AggregateRow, Aggregation, Metric, MetricFunction, Paged, TextSearch};
+use crate::{CqrsContext, CqrsError, MaybeSend, MaybeSync, Tenancy, TenantScope};
+use rest_sql::{Ast, Constraint, Operator, Value};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::sync::{Arc, RwLock};
+
+/// Read-side storage held in process memory.
+///
+/// The database backends compile a query into their own language; this one has no
+/// language to compile into, so it **evaluates** the `rest_sql` AST against each view
+/// serialized to JSON — the same representation the other backends store. Field names
+/// are the serialized ones, and a dotted name walks nested objects, as it does in
+/// MongoDB and SurrealDB.
+///
+/// Where the backends disagree, this one reads like Postgres, the backend whose
+/// semantics are the strictest:
+///
+/// - a comparison against a `null` or absent field is false — `!=` included — and only
+///   `=null=` / `=notnull=` observe the absence;
+/// - a comparison between mismatched types (a string against a number) is false;
+/// - `null` sorts **after** every value in an ascending sort and before in a descending
+///   one, which is Postgres' `NULLS LAST` / `NULLS FIRST` default.
+///
+/// Paging, the default limit of 20 and the warning for a page over an undefined order
+/// all match the database backends, and a sort field goes through
+/// [`Sorter::validated_field`] like everywhere else, so a test that passes here does
+/// not pass *because* it ran in memory.
+///
+/// Without a sort, items come back in id order. That is an accident of the map, not a
+/// promise — declare [`Query::default_sort`] as for any other backend.
+#[derive(Debug)]
+pub struct InMemoryStorage<V, Q> {
+    _phantom: PhantomData<Q>,
+    type_name: String,
+    /// The views by tenant, then id; the tenant is `""` when shared.
+    items: Arc<RwLock<BTreeMap<String, BTreeMap<String, V>>>>,
+    scope: TenantScope,
+}
+
+impl<V, Q> Clone for InMemoryStorage<V, Q> {
+    /// A clone shares the items: a storage handed to a `ViewDispatcher` and a clone of
+    /// it handed to a router see the same views.
+    fn clone(&self) -> Self {
+        Self {
+            _phantom: PhantomData,
+            type_name: self.type_name.clone(),
+            items: Arc::clone(&self.items),
+            scope: self.scope.clone(),
+        }
+    }
+}
+
+impl<V, Q> InMemoryStorage<V, Q> {
+    #[must_use]
+    pub fn new(type_name: &str) -> Self {
+        Self {
+            _phantom: PhantomData,
+            type_name: type_name.to_string(),
+            items: Arc::new(RwLock::new(BTreeMap::new())),
+            scope: TenantScope::default(),
+        }
+    }
+
+    /// Keeps each tenant's views apart. In memory, [`Tenancy::Column`] and
+    /// [`Tenancy::Table`] both partition the views by tenant.
+    #[must_use]
+    pub fn with_tenancy(mut self, tenancy: Tenancy) -> Self {
+        self.scope = TenantScope::new(tenancy);
+        self
+    }
+
+    /// Removes every stored view, of every tenant.
+    pub fn clear(&self) {
+        self.items
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clear();
+    }
+}
+
+/// Checks that a stored view belongs to the requested parent, with the same refusal as
+/// the database backends when a child view is read without one.
+pub(crate) fn parent_matches<V: HasId>(
+    view: &V,
+    parent_id: Option<&str>,
+) -> Result<bool, CqrsError> {
+    match (V::parent_field_id(), parent_id) {
+        (Some(_), Some(pid)) => Ok(view.parent_id() == Some(pid)),
+        (Some(_), None) => Err(CqrsError::validation(
+            StorageError::MissingParentId.to_string(),
+        )),
+        _ => Ok(true),
+    }
+}
+
+/// Reads a possibly dotted field out of a JSON document. A JSON `null` reads as absent:
+/// the two are the same thing to every operator.
+fn field<'a>(doc: &'a JsonValue, path: &str) -> Option<&'a JsonValue> {
+    path.split('.')
+        .try_fold(doc, |current, segment| current.get(segment))
+        .filter(|v| !v.is_null())
+}
+
+/// Orders a document value against a filter value, or `None` when the two are not
+/// comparable. Dates and date-times are compared as the strings they are serialized to,
+/// which orders correctly for the ISO-8601 forms serde emits.
+fn compare_to_value(json: &JsonValue, value: &Value) -> Option<Ordering> {
+    match (json, value) {
+        (JsonValue::Number(n), Value::Int(i)) => match n.as_i64() {
+            Some(n) => Some(n.cmp(i)),
+            None => n.as_f64()?.partial_cmp(&(*i as f64)),
+        },
+        (JsonValue::Number(n), Value::Float(f)) => n.as_f64()?.partial_cmp(f),
+        (JsonValue::String(s), Value::String(v) | Value::Date(v) | Value::DateTime(v)) => {
+            Some(s.as_str().cmp(v.as_str()))
+        }
+        (JsonValue::Bool(b), Value::Bool(v)) => Some(b.cmp(v)),
+        _ => None,
+    }
+}
+
+fn list(constraint: &Constraint) -> Result<&[Value], CqrsError> {
+    match &constraint.value {
+        Value::List(values) => Ok(values),
+        _ => Err(CqrsError::internal(format!(
+            "operator {:?} on {:?} expects a list",
+            constraint.operator, constraint.field
+        ))),
+    }
+}
+
+/// Matches an RSQL `like` pattern: `*` is any run of characters, `_` exactly one.
+///
+/// Backtracks to the last `*` only, never further, so a pattern from a query string
+/// costs at most `text × pattern` steps however many stars it holds.
+fn like(text: &[char], pattern: &[char]) -> bool {
+    let (mut t, mut p) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '_' || c == text[t] => {
+                t += 1;
+                p += 1;
+            }
+            _ => match star {
+                Some((sp, st)) => {
+                    p = sp + 1;
+                    t = st + 1;
+                    star = Some((sp, st + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn matches_constraint(doc: &JsonValue, c: &Constraint) -> Result<bool, CqrsError> {
+    let value = field(doc, &c.field);
+    match &c.operator {
+        Operator::Null => return Ok(value.is_none()),
+        Operator::NotNull => return Ok(value.is_some()),
+        _ => {}
+    }
+    let Some(value) = value else {
+        return Ok(false);
+    };
+    let cmp = |v: &Value| compare_to_value(value, v);
+
+    Ok(match &c.operator {
+        Operator::Eq => cmp(&c.value) == Some(Ordering::Equal),
+        Operator::Neq => cmp(&c.value).is_some_and(Ordering::is_ne),
+        Operator::Lt => cmp(&c.value) == Some(Ordering::Less),
+        Operator::Lte => cmp(&c.value).is_some_and(Ordering::is_le),
+        Operator::Gt => cmp(&c.value) == Some(Ordering::Greater),
+        Operator::Gte => cmp(&c.value).is_some_and(Ordering::is_ge),
+        Operator::In => list(c)?.iter().any(|v| cmp(v) == Some(Ordering::Equal)),
+        Operator::Out => list(c)?.iter().all(|v| cmp(v).is_some_and(Ordering::is_ne)),
+        Operator::Between => match list(c)? {
+            [lo, hi] => {
+                cmp(lo).is_some_and(Ordering::is_ge) && cmp(hi).is_some_and(Ordering::is_le)
+            }
+            _ => {
+                return Err(CqrsError::internal(format!(
+                    "between on {:?} expects two bounds",
+                    c.field
+                )));
+            }
+        },
+        Operator::Like | Operator::Ilike => {
+            let Value::String(pattern) = &c.value else {
+                return Err(CqrsError::internal(format!(
+                    "like on {:?} expects a string pattern",
+                    c.field
+                )));
+            };
+            let JsonValue::String(text) = value else {
+                return Ok(false);
+            };
+            if c.operator == Operator::Ilike {
+                let text: Vec<char> = text.to_lowercase().chars().collect();
+                let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+                like(&text, &pattern)
+            } else {
+                let text: Vec<char> = text.chars().collect();
+                let pattern: Vec<char> = pattern.chars().collect();
+                like(&text, &pattern)
+            }
+        }
+        Operator::Null | Operator::NotNull => unreachable!("handled above"),
+    })
+}
+
+/// Evaluates an RSQL AST against a serialized view.
+pub(crate) fn matches(ast: &Ast, doc: &JsonValue) -> Result<bool, CqrsError> {
+    match ast {
+        Ast::And(children) => {
+            for child in children {
+                if !matches(child, doc)? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+        Ast::Or(children) => {
+            for child in children {
+                if matches(child, doc)? {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        }
+        Ast::Constraint(c) => matches_constraint(doc, c),
+    }
+}
+
+/// Rank of a JSON type when two values of different types meet in a sort. Any fixed
+/// order will do; it only has to be total so the sort is deterministic.
+fn type_rank(value: &JsonValue) -> u8 {
+    match value {
+        JsonValue::Bool(_) => 0,
+        JsonValue::Number(_) => 1,
+        JsonValue::String(_) => 2,
+        JsonValue::Array(_) => 3,
+        JsonValue::Object(_) => 4,
+        JsonValue::Null => 5,
+    }
+}
+
+/// Orders two present values. Arrays and objects have no meaningful order, so they tie
+/// among themselves.
+fn compare_json(a: &JsonValue, b: &JsonValue) -> Ordering {
+    match (a, b) {
+        (JsonValue::Number(a), JsonValue::Number(b)) => match (a.as_i64(), b.as_i64()) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            _ => a
+                .as_f64()
+                .zip(b.as_f64())
+                .and_then(|(a, b)| a.partial_cmp(&b))
+                .unwrap_or(Ordering::Equal),
+        },
+        (JsonValue::String(a), JsonValue::String(b)) => a.cmp(b),
+        (JsonValue::Bool(a), JsonValue::Bool(b)) => a.cmp(b),
+        _ => type_rank(a).cmp(&type_rank(b)),
+    }
+}
+
+/// Compares two documents along a sort. An absent value is greater than any present
+/// one, so the direction flip yields `NULLS LAST` ascending and `NULLS FIRST`
+/// descending, as in Postgres.
+fn compare_by(sort: &[(&str, &SortDirection)], a: &JsonValue, b: &JsonValue) -> Ordering {
+    for (path, direction) in sort {
+        let ordering = match (field(a, path), field(b, path)) {
+            (Some(a), Some(b)) => compare_json(a, b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+        let ordering = match direction {
+            SortDirection::Asc => ordering,
+            SortDirection::Desc => ordering.reverse(),
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// Computes one metric over the documents of a group, with SQL's treatment of a
+/// missing value: `sum`, `avg`, `min` and `max` skip it, and are `null` over a group
+/// that has none. A sum stays an integer while every term is one and it fits.
+fn metric(metric: &Metric, docs: &[&JsonValue]) -> JsonValue {
+    let Some(path) = metric.field.as_deref() else {
+        return JsonValue::from(docs.len());
+    };
+    let present = docs.iter().filter_map(|doc| field(doc, path));
+    match metric.function {
+        MetricFunction::Count => JsonValue::from(docs.len()),
+        MetricFunction::Min => present
+            .min_by(|a, b| compare_json(a, b))
+            .cloned()
+            .unwrap_or_default(),
+        MetricFunction::Max => present
+            .max_by(|a, b| compare_json(a, b))
+            .cloned()
+            .unwrap_or_default(),
+        MetricFunction::Sum | MetricFunction::Avg => {
+            let numbers: Vec<&serde_json::Number> = present
+                .filter_map(|v| match v {
+                    JsonValue::Number(n) => Some(n),
+                    _ => None,
+                })
+                .collect();
+            if numbers.is_empty() {
+                return JsonValue::Null;
+            }
+            let total: f64 = numbers.iter().filter_map(|n| n.as_f64()).sum();
+            if metric.function == MetricFunction::Avg {
+                return JsonValue::from(total / numbers.len() as f64);
+            }
+            numbers
+                .iter()
+                .try_fold(0i64, |acc, n| acc.checked_add(n.as_i64()?))
+                .map_or_else(|| JsonValue::from(total), JsonValue::from)
+        }
+    }
+}
+
+/// How relevant a document is to a search: the number of times the search's words occur
+/// in its searchable fields, or `None` when one of them does not occur at all. A string
+/// field is read as text, an array as the text of its strings.
+fn relevance(search: &TextSearch, doc: &JsonValue) -> Option<usize> {
+    let mut text: Vec<String> = Vec::new();
+    for path in search.fields() {
+        match field(doc, path) {
+            Some(JsonValue::String(s)) => text.extend(words(s)),
+            Some(JsonValue::Array(items)) => {
+                for item in items {
+                    if let JsonValue::String(s) = item {
+                        text.extend(words(s));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    search.terms().iter().try_fold(0, |score, term| {
+        let n = text.iter().filter(|w| *w == term).count();
+        (n > 0).then_some(score + n)
+    })
+}
+
+/// Validates every sort field up front: a bad one fails the whole query, before any
+/// item is looked at, exactly as the clause builders do.
+fn validated_sort(sort: Option<&[Sorter]>) -> Result<Vec<(&str, &SortDirection)>, CqrsError> {
+    sort.unwrap_or_default()
+        .iter()
+        .map(|s| Ok((s.validated_field()?, &s.direction)))
+        .collect()
+}
+
+/// [`Storage::filter`] over views a storage can only list: filters, searches, orders
+/// and pages `views` as the database backends would, after `keep` has scoped them to a
+/// parent. Shared by every storage without a query language — this one, and the
+/// embedded key-value ones.
+pub(crate) fn filter_views<'a, V, Q>(
+    type_name: &str,
+    id_field: &str,
+    views: impl IntoIterator<Item = &'a V>,
+    keep: impl Fn(&V) -> Result<bool, CqrsError>,
+    query: &Q,
+) -> Result<Paged<V>, CqrsError>
+where
+    V: Serialize + Clone + 'a,
+    Q: Query,
+{
+    let Pagination { skip, limit } = query.pagination().unwrap_or_default();
+    let skip_v = skip.unwrap_or(0).max(0);
+    let limit_v = limit.unwrap_or(20);
+
+    let sort = query.sort();
+    let search = TextSearch::plan(query)?;
+    if search.is_none() {
+        warn_if_page_order_undefined(type_name, skip_v, sort.as_deref());
+    }
+    let keyset = plan_keyset(sort.as_deref(), id_field, query.cursor(), search.as_ref())?;
+    let order = match &keyset {
+        Some(keyset) => keyset.order(),
+        None => sort.unwrap_or_default(),
+    };
+    let order = validated_sort(Some(&order))?;
+    let filter = query.filter();
+    // The keyset condition narrows the page, never the count.
+    let condition = match &keyset {
+        Some(keyset) => keyset.filter(None)?,
+        None => None,
+    };
+
+    let mut total = 0;
+    let mut matched: Vec<(usize, JsonValue, V)> = Vec::new();
+    for view in views {
+        if !keep(view)? {
+            continue;
+        }
+        let doc = serde_json::to_value(view).map_err(CqrsError::serialization_error)?;
+        if let Some(rsql) = &filter
+            && !matches(rsql.ast(), &doc)?
+        {
+            continue;
+        }
+        let score = match &search {
+            Some(search) => match relevance(search, &doc) {
+                Some(score) => score,
+                None => continue,
+            },
+            None => 0,
+        };
+        total += 1;
+        if let Some(rsql) = &condition
+            && !matches(rsql.ast(), &doc)?
+        {
+            continue;
+        }
+        matched.push((score, doc, view.clone()));
+    }
+
+    // Stable, so ties keep id order and two identical requests answer identically.
+    // Without a search every score is 0, and the sort alone decides.
+    matched.sort_by(|(sa, a, _), (sb, b, _)| sb.cmp(sa).then_with(|| compare_by(&order, a, b)));
+
+    let Some(keyset) = keyset else {
+        let items = matched
+            .into_iter()
+            .skip(usize::try_from(skip_v).unwrap_or(usize::MAX))
+            .take(usize::try_from(limit_v.max(0)).unwrap_or(usize::MAX))
+            .map(|(_, _, view)| view)
+            .collect();
+        return Ok(Paged::new(items, total, skip_v, limit_v));
+    };
+    let skip_v = keyset.offset(skip_v);
+    let items = matched
+        .into_iter()
+        .skip(usize::try_from(skip_v).unwrap_or(usize::MAX))
+        .take(usize::try_from(keyset.fetch_limit(limit_v.max(0))).unwrap_or(usize::MAX))
+        .map(|(_, _, view)| view)
+        .collect();
+    let (items, next, prev) = keyset.finish(items, skip_v, limit_v);
+    Ok(Paged::new(items, total, skip_v, limit_v).with_cursors(next, prev))
+}
+
+/// [`Storage::aggregate`] over views a storage can only list, the companion of
+/// [`filter_views`].
+pub(crate) fn aggregate_views<'a, V, Q>(
+    views: impl IntoIterator<Item = &'a V>,
+    keep: impl Fn(&V) -> Result<bool, CqrsError>,
+    query: &Q,
+    aggregation: &Aggregation,
+) -> Result<Vec<AggregateRow>, CqrsError>
+where
+    V: Serialize + 'a,
+    Q: Query,
+{
+    aggregation.validate()?;
+    let filter = query.filter();
+    let search = TextSearch::plan(query)?;
+
+    let mut docs: Vec<JsonValue> = Vec::new();
+    for view in views {
+        if !keep(view)? {
+            continue;
+        }
+        let doc = serde_json::to_value(view).map_err(CqrsError::serialization_error)?;
+        if let Some(rsql) = &filter
+            && !matches(rsql.ast(), &doc)?
+        {
+            continue;
+        }
+        if let Some(search) = &search
+            && relevance(search, &doc).is_none()
+        {
+            continue;
+        }
+        docs.push(doc);
+    }
+
+    // Keyed by the serialized key tuple: `JsonValue` is not `Ord`, its text is.
+    let mut groups: BTreeMap<String, (Vec<JsonValue>, Vec<&JsonValue>)> = BTreeMap::new();
+    for doc in &docs {
+        let keys: Vec<JsonValue> = aggregation
+            .group_by
+            .iter()
+            .map(|path| field(doc, path).cloned().unwrap_or_default())
+            .collect();
+        let text = serde_json::to_string(&keys).map_err(CqrsError::serialization_error)?;
+        groups
+            .entry(text)
+            .or_insert_with(|| (keys, Vec::new()))
+            .1
+            .push(doc);
+    }
+    let mut groups: Vec<(Vec<JsonValue>, Vec<&JsonValue>)> = groups.into_values().collect();
+    // Group-key order, `null` last, as the SQL backends' `ORDER BY` sorts it.
+    groups.sort_by(|(a, _), (b, _)| {
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| match (a.is_null(), b.is_null()) {
+                (false, false) => compare_json(a, b),
+                (false, true) => Ordering::Less,
+                (true, false) => Ordering::Greater,
+                (true, true) => Ordering::Equal,
+            })
+            .find(|o| o.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+    // One group over the whole set, even an empty one, as `GROUP ALL` and a
+    // key-less `GROUP BY` answer.
+    if groups.is_empty() && aggregation.group_by.is_empty() {
+        groups.push((Vec::new(), Vec::new()));
+    }
+
+    Ok(groups
+        .into_iter()
+        .map(|(keys, members)| {
+            let mut row = serde_json::Map::new();
+            for (i, key) in keys.into_iter().enumerate() {
+                row.insert(AggregateRow::group_column(i), key);
+            }
+            for (i, m) in aggregation.metrics.iter().enumerate() {
+                row.insert(AggregateRow::metric_column(i), metric(m, &members));
+            }
+            AggregateRow::from_positional(aggregation, row)
+        })
+        .collect())
+}
+
+cqrs_async_trait! {
+impl<V, Q> Storage<V, Q> for InMemoryStorage<V, Q>
+where
+    V: Debug + Clone + Default + Serialize + DeserializeOwned + MaybeSend + MaybeSync + HasId,
+    Q: Clone + Debug + MaybeSend + MaybeSync + Query,
+{
+    fn type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    fn id_field(&self) -> Option<&str> {
+        Some(V::field_id())
+    }
+
+    async fn filter(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        context: CqrsContext,
+    ) -> Result<Paged<V>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        let items = self
+            .items
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        filter_views(
+            &self.type_name,
+            V::field_id(),
+            items.get(scope.partition()?).into_iter().flat_map(BTreeMap::values),
+            |view| parent_matches(view, parent_id.as_deref()),
+            &query,
+        )
+    }
+
+    async fn find_by_id(
+        &self,
+        parent_id: Option<String>,
+        id: &str,
+        context: CqrsContext,
+    ) -> Result<Option<V>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        let items = self
+            .items
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        match items.get(scope.partition()?).and_then(|items| items.get(id)) {
+            Some(view) if parent_matches(view, parent_id.as_deref())? => Ok(Some(view.clone())),
+            Some(_) => Ok(None),
+            None => {
+                // Checked even on a miss, so a child view read without its parent is
+                // refused whether or not the id exists — as the database backends do.
+                if V::parent_field_id().is_some() && parent_id.is_none() {
+                    return Err(CqrsError::validation(
+                        StorageError::MissingParentId.to_string(),
+                    ));
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    async fn save(&self, entity: V, context: CqrsContext) -> Result<(), CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        if V::parent_field_id().is_some() && entity.parent_id().is_none() {
+            return Err(CqrsError::validation(
+                StorageError::MissingParentId.to_string(),
+            ));
+        }
+        self.items
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .entry(scope.partition()?.to_string())
+            .or_default()
+            .insert(entity.id().to_string(), entity);
+        Ok(())
+    }
+
+    async fn aggregate(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        aggregation: Aggregation,
+        context: CqrsContext,
+    ) -> Result<Vec<AggregateRow>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        let items = self
+            .items
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        aggregate_views(
+            items.get(scope.partition()?).into_iter().flat_map(BTreeMap::values),
+            |view| parent_matches(view, parent_id.as_deref()),
+            &query,
+            &aggregation,
+        )
+    }
+}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read::{PageCursor, Projection};
+    use crate::log_capture::{containing, events_of_async};
+    use rest_sql::RestSql;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    struct Game {
+        id: String,
+        title: String,
+        players: Option<i64>,
+        rating: Option<f64>,
+        available: bool,
+        details: Option<Details>,
+    }
+
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    struct Details {
+        publisher: String,
+    }
+
+    impl HasId for Game {
+        fn field_id() -> &'static str {
+            "id"
+        }
+        fn id(&self) -> &str {
+            &self.id
+        }
+        fn parent_field_id() -> Option<&'static str> {
+            None
+        }
+        fn parent_id(&self) -> Option<&str> {
+            None
+        }
+    }
+
+    /// A query that is whatever the test hands it: a raw `_q`, a sort and a window.
+    #[derive(Debug, Clone, Default, Serialize)]
+    struct RawQuery {
+        #[serde(skip)]
+        q: Option<&'static str>,
+        #[serde(skip)]
+        sort: Option<Vec<Sorter>>,
+        #[serde(skip)]
+        skip: Option<i64>,
+        #[serde(skip)]
+        limit: Option<i64>,
+        #[serde(skip)]
+        cursor: Option<PageCursor>,
+        #[serde(skip)]
+        search: Option<&'static str>,
+    }
+
+    impl Query for RawQuery {
+        fn filter(&self) -> Option<RestSql> {
+            self.q.map(|q| RestSql::new(q).expect("test _q must parse"))
+        }
+        fn pagination(&self) -> Option<Pagination> {
+            Some(Pagination {
+                skip: self.skip,
+                limit: self.limit,
+            })
+        }
+        fn sort(&self) -> Option<Vec<Sorter>> {
+            self.sort.clone()
+        }
+        fn cursor(&self) -> Option<PageCursor> {
+            self.cursor.clone()
+        }
+        fn search(&self) -> Option<&str> {
+            self.search
+        }
+        fn searchable_fields(&self) -> Vec<&str> {
+            vec!["title", "details.publisher"]
+        }
+    }
+
+    fn q(filter: &'static str) -> RawQuery {
+        RawQuery {
+            q: Some(filter),
+            ..Default::default()
+        }
+    }
+
+    fn sorted(field: &str, direction: SortDirection) -> RawQuery {
+        RawQuery {
+            sort: Some(vec![Sorter {
+                field: field.to_string(),
+                direction,
+            }]),
+            ..Default::default()
+        }
+    }
+
+    fn game(id: &str, title: &str, players: Option<i64>, available: bool) -> Game {
+        Game {
+            id: id.to_string(),
+            title: title.to_string(),
+            players,
+            rating: None,
+            available,
+            details: None,
+        }
+    }
+
+    async fn storage() -> InMemoryStorage<Game, RawQuery> {
+        let storage = InMemoryStorage::new("game");
+        let mut catan = game("g1", "Catan", Some(4), true);
+        catan.rating = Some(7.5);
+        catan.details = Some(Details {
+            publisher: "Kosmos".into(),
+        });
+        for g in [
+            catan,
+            game("g2", "Carcassonne", Some(5), false),
+            game("g3", "Azul", Some(4), true),
+            game("g4", "Patchwork", None, true),
+        ] {
+            storage.save(g, CqrsContext::default()).await.unwrap();
+        }
+        storage
+    }
+
+    async fn ids(storage: &InMemoryStorage<Game, RawQuery>, query: RawQuery) -> Vec<String> {
+        storage
+            .filter(None, query, CqrsContext::default())
+            .await
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|g| g.id)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn comparison_operators_filter_on_the_serialized_fields() {
+        let s = storage().await;
+        assert_eq!(ids(&s, q("title==Azul")).await, ["g3"]);
+        assert_eq!(ids(&s, q("players>4")).await, ["g2"]);
+        assert_eq!(ids(&s, q("players<=4")).await, ["g1", "g3"]);
+        assert_eq!(ids(&s, q("available==false")).await, ["g2"]);
+        assert_eq!(ids(&s, q("rating>=7.5")).await, ["g1"]);
+        assert_eq!(ids(&s, q("players=in=(5,6)")).await, ["g2"]);
+        assert_eq!(ids(&s, q("players=between=(4,4)")).await, ["g1", "g3"]);
+    }
+
+    #[tokio::test]
+    async fn and_or_combine_as_in_rsql() {
+        let s = storage().await;
+        assert_eq!(ids(&s, q("players==4;title==Azul")).await, ["g3"]);
+        assert_eq!(ids(&s, q("title==Azul,title==Catan")).await, ["g1", "g3"]);
+    }
+
+    /// SQL's reading: nothing compares to an absent value — not even `!=` or `=out=` —
+    /// and only `=null=` / `=notnull=` see it.
+    #[tokio::test]
+    async fn an_absent_field_only_matches_the_null_operators() {
+        let s = storage().await;
+        assert_eq!(ids(&s, q("players!=4")).await, ["g2"]);
+        assert_eq!(ids(&s, q("players=out=(4)")).await, ["g2"]);
+        assert_eq!(ids(&s, q("players=null=true")).await, ["g4"]);
+        assert_eq!(ids(&s, q("players=notnull=true")).await, ["g1", "g2", "g3"]);
+    }
+
+    #[tokio::test]
+    async fn a_dotted_field_reaches_into_nested_objects() {
+        let s = storage().await;
+        assert_eq!(ids(&s, q("details.publisher==Kosmos")).await, ["g1"]);
+    }
+
+    #[tokio::test]
+    async fn like_uses_the_rsql_wildcards() {
+        let s = storage().await;
+        assert_eq!(ids(&s, q("title=like=Ca*")).await, ["g1", "g2"]);
+        assert_eq!(ids(&s, q("title=like=A_ul")).await, ["g3"]);
+        assert!(
+            ids(&s, q("title=like=ca*")).await.is_empty(),
+            "like is case-sensitive"
+        );
+        assert_eq!(ids(&s, q("title=ilike=ca*")).await, ["g1", "g2"]);
+    }
+
+    #[test]
+    fn a_mismatched_type_compares_as_false_rather_than_coercing() {
+        let doc = json!({ "players": 4 });
+        let ast = RestSql::new("players=='4'").unwrap();
+        assert!(!matches(ast.ast(), &doc).unwrap());
+    }
+
+    #[tokio::test]
+    async fn absent_values_sort_last_ascending_and_first_descending() {
+        let s = storage().await;
+        assert_eq!(
+            ids(&s, sorted("players", SortDirection::Asc)).await,
+            ["g1", "g3", "g2", "g4"],
+            "ties keep id order; the absent value is last"
+        );
+        assert_eq!(
+            ids(&s, sorted("players", SortDirection::Desc)).await,
+            ["g4", "g2", "g1", "g3"]
+        );
+        assert_eq!(
+            ids(&s, sorted("title", SortDirection::Asc)).await,
+            ["g3", "g2", "g1", "g4"]
+        );
+    }
+
+    #[tokio::test]
+    async fn the_window_is_applied_after_the_filter_and_the_sort() {
+        let s = storage().await;
+        let page = s
+            .filter(
+                None,
+                RawQuery {
+                    skip: Some(1),
+                    limit: Some(2),
+                    ..sorted("title", SortDirection::Asc)
+                },
+                CqrsContext::default(),
+            )
+            .await
+            .unwrap();
+        let ids: Vec<_> = page.items.iter().map(|g| g.id.as_str()).collect();
+        assert_eq!(ids, ["g2", "g1"]);
+        assert_eq!(page.total, 4, "the total counts every match, not the page");
+        assert_eq!(page.skip, 1);
+        assert_eq!(page.limit, 2);
+    }
+
+    #[tokio::test]
+    async fn the_default_limit_matches_the_database_backends() {
+        let page = storage()
+            .await
+            .filter(None, RawQuery::default(), CqrsContext::default())
+            .await
+            .unwrap();
+        assert_eq!(page.limit, 20);
+        assert_eq!(page.skip, 0);
+    }
+
+    #[tokio::test]
+    async fn a_hostile_sort_field_is_rejected() {
+        let err = storage()
+            .await
+            .filter(
+                None,
+                sorted("1 UNION ALL SELECT data FROM secrets--", SortDirection::Asc),
+                CqrsContext::default(),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, "GENERIC_VALIDATION_FAILED");
+    }
+
+    #[tokio::test]
+    async fn filter_warns_when_paging_without_a_sort() {
+        let storage: InMemoryStorage<Game, RawQuery> = InMemoryStorage::new("mem_unsorted_view");
+        let events = events_of_async(async {
+            let _ = storage
+                .filter(
+                    None,
+                    RawQuery {
+                        skip: Some(20),
+                        ..Default::default()
+                    },
+                    CqrsContext::default(),
+                )
+                .await;
+        })
+        .await;
+        let ours = containing(&events, "no sort in effect");
+        assert_eq!(ours.len(), 1, "got {events:?}");
+        assert!(
+            ours[0].contains("type_name=mem_unsorted_view"),
+            "{}",
+            ours[0]
+        );
+    }
+
+    #[tokio::test]
+    async fn save_replaces_and_clones_share_the_items() {
+        let s = storage().await;
+        let clone = s.clone();
+        clone
+            .save(
+                game("g3", "Azul: Summer Pavilion", Some(4), true),
+                CqrsContext::default(),
+            )
+            .await
+            .unwrap();
+        let found = s
+            .find_by_id(None, "g3", CqrsContext::default())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.title, "Azul: Summer Pavilion");
+        assert!(
+            s.find_by_id(None, "nope", CqrsContext::default())
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn a_tenant_only_sees_its_own_views() {
+        let s = InMemoryStorage::<Game, RawQuery>::new("games").with_tenancy(Tenancy::Column);
+        let acme = CqrsContext::default().with_tenant(Some("acme".to_string()));
+        let globex = CqrsContext::default().with_tenant(Some("globex".to_string()));
+        s.save(game("g1", "Azul", Some(4), true), acme.clone())
+            .await
+            .unwrap();
+        s.save(game("g1", "Catan", Some(4), true), globex.clone())
+            .await
+            .unwrap();
+
+        let found = s.find_by_id(None, "g1", acme.clone()).await.unwrap();
+        assert_eq!(found.unwrap().title, "Azul");
+        let page = s.filter(None, q("title==Azul"), globex).await.unwrap();
+        assert_eq!(page.total, 0);
+
+        let error = s
+            .find_by_id(None, "g1", CqrsContext::default())
+            .await
+            .unwrap_err();
+        assert_eq!(error.status, 403);
+    }
+
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    struct Movement {
+        id: String,
+        account_id: String,
+    }
+
+    impl HasId for Movement {
+        fn field_id() -> &'static str {
+            "id"
+        }
+        fn id(&self) -> &str {
+            &self.id
+        }
+        fn parent_field_id() -> Option<&'static str> {
+            Some("account_id")
+        }
+        fn parent_id(&self) -> Option<&str> {
+            Some(&self.account_id)
+        }
+    }
+
+    #[tokio::test]
+    async fn a_child_view_is_scoped_to_its_parent() {
+        let s: InMemoryStorage<Movement, RawQuery> = InMemoryStorage::new("movement");
+        for (id, account) in [("m1", "a1"), ("m2", "a2"), ("m3", "a1")] {
+            s.save(
+                Movement {
+                    id: id.into(),
+                    account_id: account.into(),
+                },
+                CqrsContext::default(),
+            )
+            .await
+            .unwrap();
+        }
+
+        let page = s
+            .filter(
+                Some("a1".into()),
+                RawQuery::default(),
+                CqrsContext::default(),
+            )
+            .await
+            .unwrap();
+        let ids: Vec<_> = page.items.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["m1", "m3"]);
+
+        assert!(
+            s.find_by_id(Some("a2".into()), "m1", CqrsContext::default())
+                .await
+                .unwrap()
+                .is_none(),
+            "another parent's view is not found"
+        );
+
+        let err = s
+            .filter(None, RawQuery::default(), CqrsContext::default())
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, "GENERIC_VALIDATION_FAILED");
+    }
+
+    /// Keyset pages over the in-memory store: the count ignores the cursor, and a
+    /// backwards page comes back in the sort's order.
+    #[tokio::test]
+    async fn cursors_walk_the_sorted_set() {
+        let storage = storage().await;
+        let page = |cursor| RawQuery {
+            limit: Some(2),
+            cursor,
+            ..sorted("title", SortDirection::Asc)
+        };
+
+        let first = storage
+            .filter(None, page(None), CqrsContext::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            first
+                .items
+                .iter()
+                .map(|g| g.id.as_str())
+                .collect::<Vec<_>>(),
+            ["g3", "g2"]
+        );
+        assert!(first.prev_cursor.is_none());
+
+        let second = storage
+            .filter(
+                None,
+                page(Some(PageCursor::After(first.next_cursor.unwrap()))),
+                CqrsContext::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            second
+                .items
+                .iter()
+                .map(|g| g.id.as_str())
+                .collect::<Vec<_>>(),
+            ["g1", "g4"]
+        );
+        assert_eq!(
+            second.total, 4,
+            "the cursor narrows the page, not the count"
+        );
+        assert!(second.next_cursor.is_none());
+
+        let back = storage
+            .filter(
+                None,
+                page(Some(PageCursor::Before(second.prev_cursor.unwrap()))),
+                CqrsContext::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            back.items.iter().map(|g| g.id.as_str()).collect::<Vec<_>>(),
+            ["g3", "g2"]
+        );
+    }
+
+    #[tokio::test]
+    async fn a_cursor_without_a_sort_is_a_validation_error() {
+        let storage = storage().await;
+        let query = RawQuery {
+            cursor: Some(PageCursor::After("x".into())),
+            ..Default::default()
+        };
+        let err = storage
+            .filter(None, query, CqrsContext::default())
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, 400);
+    }
+
+    #[tokio::test]
+    async fn aggregates_group_the_filtered_set() {
+        let storage = storage().await;
+        let rows = storage
+            .aggregate(
+                None,
+                q("players=notnull=true"),
+                Aggregation::new(
+                    ["available"],
+                    [
+                        Metric::count(),
+                        Metric::sum("players"),
+                        Metric::avg("players"),
+                        Metric::min("title"),
+                        Metric::max("rating"),
+                    ],
+                ),
+                CqrsContext::default(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(rows).unwrap(),
+            json!([
+                {
+                    "group": { "available": false },
+                    "metrics": { "count": 1, "sum(players)": 5, "avg(players)": 5.0,
+                                 "min(title)": "Carcassonne", "max(rating)": null }
+                },
+                {
+                    "group": { "available": true },
+                    "metrics": { "count": 2, "sum(players)": 8, "avg(players)": 4.0,
+                                 "min(title)": "Azul", "max(rating)": 7.5 }
+                }
+            ]),
+            "Patchwork is filtered out first; a metric with no value in a group is null"
+        );
+    }
+
+    #[tokio::test]
+    async fn an_aggregate_without_keys_is_one_group_even_when_empty() {
+        let storage = storage().await;
+        let rows = storage
+            .aggregate(
+                None,
+                q("title==Nope"),
+                Aggregation::new(
+                    Vec::<String>::new(),
+                    [Metric::count(), Metric::sum("players")],
+                ),
+                CqrsContext::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].metrics["count"], 0);
+        assert_eq!(rows[0].metrics["sum(players)"], JsonValue::Null);
+    }
+
+    #[tokio::test]
+    async fn a_null_key_is_a_group_of_its_own_and_sorts_last() {
+        let storage = storage().await;
+        let rows = storage
+            .aggregate(
+                None,
+                RawQuery::default(),
+                Aggregation::new(["players"], [Metric::count()]),
+                CqrsContext::default(),
+            )
+            .await
+            .unwrap();
+        let keys: Vec<JsonValue> = rows.iter().map(|r| r.group["players"].clone()).collect();
+        assert_eq!(keys, [json!(4), json!(5), JsonValue::Null]);
+        assert_eq!(rows[0].metrics["count"], 2);
+    }
+
+    /// The in-memory storage keeps the trait's default: whole items, projected after.
+    #[tokio::test]
+    async fn a_projected_page_keeps_only_the_listed_fields() {
+        let storage = storage().await;
+        let page = storage
+            .filter_projected(
+                None,
+                sorted("title", SortDirection::Asc),
+                Projection::new(["title", "rating"]),
+                CqrsContext::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            page.items[..2],
+            [
+                json!({ "title": "Azul", "rating": null }),
+                json!({ "title": "Carcassonne", "rating": null })
+            ]
+        );
+        assert_eq!(page.total, 4);
+        assert!(page.next_cursor.is_none());
+    }
+
+    fn searching(text: &'static str) -> RawQuery {
+        RawQuery {
+            search: Some(text),
+            ..Default::default()
+        }
+    }
+
+    async fn searchable_storage() -> InMemoryStorage<Game, RawQuery> {
+        let storage = storage().await;
+        let mut cities = game("g5", "Catan: Cities & Knights of Catan", Some(4), true);
+        cities.details = Some(Details {
+            publisher: "Kosmos".into(),
+        });
+        for g in [cities, game("g6", "Catan Junior", Some(4), false)] {
+            storage.save(g, CqrsContext::default()).await.unwrap();
+        }
+        storage
+    }
+
+    #[tokio::test]
+    async fn a_search_needs_every_word_somewhere_and_ranks_by_occurrences() {
+        let storage = searchable_storage().await;
+        let page = storage
+            .filter(None, searching("CATAN kosmos"), CqrsContext::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            page.total, 2,
+            "Catan Junior has no publisher to match kosmos"
+        );
+        assert_eq!(
+            page.items.iter().map(|g| g.id.as_str()).collect::<Vec<_>>(),
+            ["g5", "g1"],
+            "g5 names catan twice"
+        );
+    }
+
+    #[tokio::test]
+    async fn the_sort_breaks_relevance_ties_and_a_search_page_has_no_cursor() {
+        let storage = searchable_storage().await;
+        let query = RawQuery {
+            search: Some("catan"),
+            ..sorted("title", SortDirection::Desc)
+        };
+        let page = storage
+            .filter(None, query.clone(), CqrsContext::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            page.items.iter().map(|g| g.id.as_str()).collect::<Vec<_>>(),
+            ["g5", "g6", "g1"]
+        );
+        assert!(page.next_cursor.is_none() && page.prev_cursor.is_none());
+
+        let with_cursor = RawQuery {
+            cursor: Some(PageCursor::After("x".into())),
+            ..query
+        };
+        let err = storage
+            .filter(None, with_cursor, CqrsContext::default())
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, 400);
+    }
+
+    #[tokio::test]
+    async fn an_aggregate_counts_the_search_matches_only() {
+        let storage = searchable_storage().await;
+        let rows = storage
+            .aggregate(
+                None,
+                searching("catan"),
+                Aggregation::new(["available"], [Metric::count()]),
+                CqrsContext::default(),
+            )
+            .await
+            .unwrap();
+        let counts: Vec<JsonValue> = rows.iter().map(|r| r.metrics["count"].clone()).collect();
+        assert_eq!(counts, [json!(1), json!(2)]);
+    }
+}
@@ -2,23 +2,37 @@ mod sorter;
 pub use sorter::*;
 mod paged;
 pub use paged::*;
+mod aggregation;
+pub use aggregation::{AggregateRow, Aggregation, Metric, MetricFunction};
+mod projection;
+pub use projection::Projection;
+mod cursor;
+mod search;
+pub(crate) use search::TextSearch;
+pub(crate) use cursor::Keyset;
+pub use cursor::PageCursor;
 pub mod query;
 pub use query::{derive_filter_from_serde, Pagination, Query};
 
 mod memory;
 pub use memory::*;
 
-// Only the read backends call this, and they are all feature-gated. Gating the module
-// declaration — rather than each item — keeps the cfg in one place and keeps the
-// function out of a build that has no caller for it, where it would be `dead_code`
-// under CI's `cargo clippy --features rest --all-targets -- -D warnings`.
-#[cfg(any(feature = "postgres", feature = "mongodb", feature = "surrealdb"))]
+// Every read backend calls this, the in-memory one included, so it is built in every
+// feature set.
 pub(crate) mod page_order;
 
+#[cfg(all(feature = "indexeddb", target_arch = "wasm32"))]
+pub mod indexeddb;
+pub mod inmemory;
 #[cfg(feature = "mongodb")]
 pub mod mongodb;
+pub mod per_tenant;
 #[cfg(feature = "postgres")]
 pub mod postgres;
+#[cfg(feature = "redb")]
+pub mod redb;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
 pub mod storage;
 #[cfg(feature = "surrealdb")]
 pub mod surrealdb;
@@ -1,18 +1,22 @@
 use crate::read::page_order::warn_if_page_order_undefined;
 use crate::read::query::{Pagination, Query};
-use crate::read::sorter::{SortDirection, Sorter};
+use crate::read::search::plan_keyset;
+use crate::read::sorter::{validated_field_name, SortDirection, Sorter};
 use crate::read::storage::{HasId, Storage, StorageError};
-use crate::read::Paged;
-use crate::{Aggregate, CqrsContext, CqrsError, Snapshot};
+use crate::read::{AggregateRow, Aggregation, Keyset, MetricFunction, Paged, Projection, TextSearch};
+use crate::{Aggregate, CqrsContext, CqrsError, Snapshot, TENANT_COLUMN, Tenancy, TenantScope};
 use futures::TryStreamExt;
-use mongodb::bson::{doc, serialize_to_document, Bson, Document};
-use mongodb::Database;
-use rest_sql::{FieldMapper, IdentityMapper};
+use mongodb::bson::{deserialize_from_document, doc, serialize_to_document, Bson, Document};
+use mongodb::action::Action;
+use mongodb::options::IndexOptions;
+use mongodb::{Database, IndexModel};
+use rest_sql::{FieldMapper, IdentityMapper, RestSql};
 use std::borrow::Cow;
 use rest_sql_drivers::mongodb::MongoCompiler;
 use rest_sql_drivers::Driver;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
+use serde_json::Value as JsonValue;
 use std::fmt::Debug;
 use std::marker::PhantomData;
 use std::sync::Arc;
@@ -50,6 +54,134 @@ fn sorters_to_mongo_sort(
     Ok(Some(doc))
 }
 
+/// Builds the `$match` / `$group` / `$sort` pipeline behind `Storage::aggregate`.
+///
+/// Keys and metrics are named positionally, `_id.g0` and `m0`: a `$group` output name
+/// may not contain a dot, and a group key may. Two details keep the result in line with
+/// the SQL backends:
+///
+/// - a key is wrapped in `$ifNull`, or a document missing the field and one holding
+///   `null` would land in two groups;
+/// - `$sort` puts `null` first, SQL puts it last, so each key gets a `n{i}` flag that
+///   sorts ahead of it.
+///
+/// One difference is left: `$sum` over a group with no numeric value is `0`, where SQL
+/// answers `null`.
+fn aggregate_pipeline(
+    match_doc: Document,
+    aggregation: &Aggregation,
+    mapper: &impl FieldMapper,
+) -> Result<Vec<Document>, CqrsError> {
+    aggregation.validate()?;
+    let mut id = Document::new();
+    let mut flags = Document::new();
+    let mut sort = Document::new();
+    for (i, key) in aggregation.group_by.iter().enumerate() {
+        let column = AggregateRow::group_column(i);
+        let path = format!("${}", mapper.map(key));
+        id.insert(&column, doc! { "$ifNull": [path, Bson::Null] });
+        flags.insert(
+            format!("n{i}"),
+            doc! { "$cond": [{ "$eq": [format!("$_id.{column}"), Bson::Null] }, 1, 0] },
+        );
+        sort.insert(format!("n{i}"), 1i32);
+        sort.insert(format!("_id.{column}"), 1i32);
+    }
+
+    let mut group = doc! { "_id": if id.is_empty() { Bson::Null } else { Bson::Document(id) } };
+    for (i, metric) in aggregation.metrics.iter().enumerate() {
+        let accumulator = match (metric.function, metric.validated_field()?) {
+            (MetricFunction::Count, _) | (_, None) => doc! { "$sum": 1i32 },
+            (function, Some(field)) => {
+                let operator = match function {
+                    MetricFunction::Sum => "$sum",
+                    MetricFunction::Min => "$min",
+                    MetricFunction::Max => "$max",
+                    _ => "$avg",
+                };
+                doc! { operator: format!("${}", mapper.map(field)) }
+            }
+        };
+        group.insert(AggregateRow::metric_column(i), accumulator);
+    }
+
+    let mut pipeline = vec![doc! { "$match": match_doc }, doc! { "$group": group }];
+    if !sort.is_empty() {
+        pipeline.push(doc! { "$addFields": flags });
+        pipeline.push(doc! { "$sort": sort });
+    }
+    Ok(pipeline)
+}
+
+/// The name a search's relevance is sorted under. Any name would do: a `$meta` sort key
+/// needs no matching projection.
+const SCORE: &str = "__cqrs_score";
+
+/// The `$text` condition of a search. Each word is quoted as a phrase, because MongoDB
+/// matches *any* of a search's bare words but *all* of its phrases, and every other
+/// backend requires every word. The words are letters and digits only, so a quote
+/// cannot close early.
+fn text_condition(search: &TextSearch) -> Document {
+    let phrases: Vec<String> = search.terms().iter().map(|t| format!("\"{t}\"")).collect();
+    doc! { "$search": phrases.join(" ") }
+}
+
+/// A sort with the search's relevance ahead of it, most relevant first.
+fn relevance_first(sort: Option<Document>) -> Document {
+    let mut relevance = doc! { SCORE: { "$meta": "textScore" } };
+    relevance.extend(sort.unwrap_or_default());
+    relevance
+}
+
+/// A `find` projection keeping `fields`. MongoDB returns `_id` unless told otherwise, so
+/// it is excluded when not asked for.
+fn projection_doc(fields: &[&str]) -> Document {
+    let mut projection = Document::new();
+    for field in fields {
+        projection.insert(*field, 1i32);
+    }
+    if !fields.contains(&"_id") {
+        projection.insert("_id", 0i32);
+    }
+    projection
+}
+
+/// Turns a `$group` output document into an [`AggregateRow`]: the keys come out of
+/// `_id`, the sort flags are dropped.
+fn aggregate_row(aggregation: &Aggregation, row: Document) -> Result<AggregateRow, CqrsError> {
+    let JsonValue::Object(mut row) =
+        deserialize_from_document::<JsonValue>(row).map_err(map_bson_error)?
+    else {
+        return Err(CqrsError::internal("a $group output is not a document"));
+    };
+    if let Some(JsonValue::Object(keys)) = row.remove("_id") {
+        row.extend(keys);
+    }
+    Ok(AggregateRow::from_positional(aggregation, row))
+}
+
+/// Leaves the view's id field where `save` puts it — at the top level of the document —
+/// and hands every other field to the view's own mapper.
+///
+/// A keyset page always ends its sort in the id, so the id goes through the mapper even
+/// when the caller never names it; a prefixing mapper such as [`SnapshotStateMapper`]
+/// would otherwise send it to `state._id`, which no document has.
+#[derive(Debug, Clone)]
+struct IdFieldMapper<'a, M> {
+    id_field: &'static str,
+    inner: &'a M,
+}
+
+impl<M: FieldMapper> FieldMapper for IdFieldMapper<'_, M> {
+    fn map<'a>(&self, field: &'a str) -> Cow<'a, str> {
+        if field == self.id_field {
+            Cow::Borrowed(field)
+        } else {
+            self.inner.map(field)
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct MongoDbStorage<V, Q, M = IdentityMapper> {
     _phantom: PhantomData<(V, Q)>,
@@ -57,6 +189,7 @@ pub struct MongoDbStorage<V, Q, M = IdentityMapper> {
     type_name: String,
     collection_name: String,
     mapper: M,
+    scope: TenantScope,
 }
 
 impl<V, Q> MongoDbStorage<V, Q, IdentityMapper> {
@@ -83,17 +216,185 @@ where
             type_name: type_name.to_string(),
             collection_name: collection_name.to_string(),
             mapper,
+            scope: TenantScope::default(),
+        }
+    }
+
+    /// Keeps each tenant's views apart: a `tenant_id` field on every document
+    /// ([`Tenancy::Column`]), or a `{collection}_{tenant}` collection per tenant
+    /// ([`Tenancy::Table`]). Under [`Tenancy::Column`] with `_id` as the view's id field,
+    /// ids must be unique across tenants, as for `MongoDBPersist`.
+    #[must_use]
+    pub fn with_tenancy(mut self, tenancy: Tenancy) -> Self {
+        self.scope = TenantScope::new(tenancy);
+        self
+    }
+
+    fn mapper(&self) -> IdFieldMapper<'_, M>
+    where
+        V: HasId,
+    {
+        IdFieldMapper {
+            id_field: V::field_id(),
+            inner: &self.mapper,
         }
     }
 
+    /// Compiles a filter and scopes it to the parent.
+    ///
+    /// `map_err(...)?`, not `unwrap_or_default()`: an empty `Document` matches
+    /// *everything*, so a filter that parsed but failed to compile used to return the
+    /// whole collection with a 200 and nothing saying the filter had been dropped — the
+    /// same fail-open shape ADR-0001 closes at the HTTP boundary, one layer down.
+    /// Postgres and SurrealDB already propagate this error; MongoDB was the outlier.
+    ///
+    /// A search's `$text` goes at the top level of the filter: MongoDB refuses it under a
+    /// `$nor`, and under an `$or` unless every branch is indexed.
+    fn filter_doc(
+        &self,
+        filter: Option<&RestSql>,
+        parent_id: &Option<String>,
+        tenant: Option<&str>,
+        search: Option<&TextSearch>,
+    ) -> Result<Document, CqrsError>
+    where
+        V: HasId,
+    {
+        let mut user_filter = match filter {
+            Some(rsql) => MongoCompiler::new(self.mapper())
+                .compile(rsql)
+                .map_err(|e| CqrsError::internal(e.to_string()))?,
+            None => Document::new(),
+        };
+        if let Some(search) = search {
+            user_filter.insert("$text", text_condition(search));
+        }
+        self.parent_id_query(user_filter, parent_id, tenant)
+    }
+
+    /// Creates the text index a search runs on, over `fields` — the view's
+    /// [`Query::searchable_fields`]. MongoDB allows one text index per collection and
+    /// searches every field it covers, so the index, not the query, decides where a
+    /// search looks: create it over exactly the fields the view declares. An index
+    /// already there under the same name is left as it is.
+    pub async fn ensure_search_index(&self, fields: &[&str]) -> Result<(), CqrsError>
+    where
+        V: HasId,
+    {
+        let mut keys = Document::new();
+        for field in fields {
+            let field = validated_field_name("search", field)?;
+            keys.insert(self.mapper().map(field).into_owned(), "text");
+        }
+        let index = IndexModel::builder()
+            .keys(keys)
+            .options(
+                IndexOptions::builder()
+                    .name(format!("{}_search", self.collection_name))
+                    .build(),
+            )
+            .build();
+        self.database
+            .collection::<Document>(&self.collection_name)
+            .create_index(index)
+            .await
+            .map_err(map_mongo_error)?;
+        Ok(())
+    }
+
+    /// The body of `filter`, over any document shape: the whole view, or the JSON a
+    /// `projection` document leaves of it.
+    async fn select_page<T>(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        scope: &TenantScope,
+        projection: Option<Document>,
+    ) -> Result<Paged<T>, CqrsError>
+    where
+        V: HasId,
+        Q: Query,
+        T: Serialize + DeserializeOwned + Send + Sync,
+    {
+        let collection = self
+            .database
+            .collection::<T>(&scope.table(&self.collection_name)?);
+        let tenant = scope.column()?;
+
+        let filter = query.filter();
+        let search = TextSearch::plan(&query)?;
+        let filter_doc = self.filter_doc(filter.as_ref(), &parent_id, tenant, search.as_ref())?;
+        let Pagination { skip, limit } = query.pagination().unwrap_or_default();
+        let skip_v = skip.unwrap_or(0).max(0);
+        let limit_v = limit.unwrap_or(20);
+
+        let sort = query.sort();
+        if search.is_none() {
+            warn_if_page_order_undefined(&self.type_name, skip_v, sort.as_deref());
+        }
+        let keyset = plan_keyset(
+            sort.as_deref(),
+            V::field_id(),
+            query.cursor(),
+            search.as_ref(),
+        )?;
+        // Under a keyset the page reads past the cursor, one row long, in the keyset's
+        // order; the count below still takes the caller's filter alone.
+        let (page_doc, sort, skip_v, fetch_limit) = match &keyset {
+            Some(keyset) => (
+                self.filter_doc(keyset.filter(filter)?.as_ref(), &parent_id, tenant, None)?,
+                Some(keyset.order()),
+                keyset.offset(skip_v),
+                keyset.fetch_limit(limit_v),
+            ),
+            None => (filter_doc.clone(), sort, skip_v, limit_v),
+        };
+        let sort_doc = sorters_to_mongo_sort(sort, &self.mapper())?;
+        let sort_doc = match &search {
+            Some(_) => Some(relevance_first(sort_doc)),
+            None => sort_doc,
+        };
+
+        let total = collection
+            .count_documents(filter_doc)
+            .await
+            .map_err(map_mongo_error)?;
+
+        let find = collection
+            .find(page_doc)
+            .skip(skip_v as u64)
+            .limit(fetch_limit)
+            .optional(projection, |find, p| find.projection(p));
+        let cursor = (if let Some(sort) = sort_doc {
+            find.sort(sort)
+        } else {
+            find
+        })
+        .await
+        .map_err(map_mongo_error)?;
+
+        let items = cursor.try_collect().await.map_err(map_mongo_error)?;
+        let Some(keyset) = keyset else {
+            return Ok(Paged::new(items, total as i64, skip_v, limit_v));
+        };
+        let (items, next, prev) = keyset.finish(items, skip_v, limit_v);
+        Ok(Paged::new(items, total as i64, skip_v, limit_v).with_cursors(next, prev))
+    }
+
+    /// Scopes a query to the parent, and to the tenant under [`Tenancy::Column`].
     fn parent_id_query(
         &self,
         base_query: Document,
         parent_id: &Option<String>,
+        tenant: Option<&str>,
     ) -> Result<Document, CqrsError>
     where
         V: HasId,
     {
+        let base_query = match tenant {
+            Some(tenant) => doc! { "$and": [base_query, { TENANT_COLUMN: tenant }] },
+            None => base_query,
+        };
         match (V::parent_field_id(), parent_id) {
             (Some(parent_field_id), Some(parent_id)) => {
                 let parent_id_query = doc! {parent_field_id: parent_id};
@@ -118,73 +419,113 @@ where
         &self.type_name
     }
 
+    fn id_field(&self) -> Option<&str> {
+        Some(V::field_id())
+    }
+
+    async fn health(&self) -> Result<(), CqrsError> {
+        self.database
+            .run_command(doc! {"ping": 1})
+            .await
+            .map(|_| ())
+            .map_err(map_mongo_error)
+    }
+
     async fn filter(
         &self,
         parent_id: Option<String>,
         query: Q,
-        _context: CqrsContext,
+        context: CqrsContext,
     ) -> Result<Paged<V>, CqrsError> {
-        let collection = self.database.collection::<V>(&self.collection_name);
-
-        // `map_err(...)?`, not `unwrap_or_default()`: an empty `Document` matches
-        // *everything*, so a filter that parsed but failed to compile used to return the
-        // whole collection with a 200 and nothing saying the filter had been dropped —
-        // the same fail-open shape ADR-0001 closes at the HTTP boundary, one layer down.
-        // Postgres and SurrealDB already propagate this error; MongoDB was the outlier.
-        let user_filter = match query.filter() {
-            Some(rsql) => MongoCompiler::new(self.mapper.clone())
-                .compile(&rsql)
-                .map_err(|e| CqrsError::internal(e.to_string()))?,
-            None => Document::new(),
-        };
-        let filter_doc = self.parent_id_query(user_filter, &parent_id)?;
-        let Pagination { skip, limit } = query.pagination().unwrap_or_default();
-        let skip_v = skip.unwrap_or(0).max(0);
-        let limit_v = limit.unwrap_or(20);
+        let scope = self.scope.for_context(&context)?;
+        self.select_page(parent_id, query, &scope, None).await
+    }
 
-        let sort = query.sort();
-        warn_if_page_order_undefined(&self.type_name, skip_v, sort.as_deref());
-        let sort_doc = sorters_to_mongo_sort(sort, &self.mapper)?;
+    async fn filter_projected(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        projection: Projection,
+        context: CqrsContext,
+    ) -> Result<Paged<JsonValue>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        projection.validate()?;
+        let search = TextSearch::plan(&query)?;
+        let keyset = plan_keyset(
+            query.sort().as_deref(),
+            V::field_id(),
+            query.cursor(),
+            search.as_ref(),
+        )?;
+        let fetched = projection.widened(keyset.iter().flat_map(Keyset::fields));
+        let page: Paged<JsonValue> = self
+            .select_page(parent_id, query, &scope, Some(projection_doc(&fetched)))
+            .await?;
+        Ok(page.map(|item| projection.apply(item)))
+    }
 
-        let total = collection
-            .count_documents(filter_doc.clone())
+    async fn aggregate(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        aggregation: Aggregation,
+        context: CqrsContext,
+    ) -> Result<Vec<AggregateRow>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        let search = TextSearch::plan(&query)?;
+        let match_doc = self.filter_doc(
+            query.filter().as_ref(),
+            &parent_id,
+            scope.column()?,
+            search.as_ref(),
+        )?;
+        let pipeline = aggregate_pipeline(match_doc, &aggregation, &self.mapper())?;
+        let rows: Vec<Document> = self
+            .database
+            .collection::<Document>(&scope.table(&self.collection_name)?)
+            .aggregate(pipeline)
+            .await
+            .map_err(map_mongo_error)?
+            .try_collect()
             .await
             .map_err(map_mongo_error)?;
-
-        let find = collection
-            .find(filter_doc.clone())
-            .skip(skip_v as u64)
-            .limit(limit_v);
-        let cursor = (if let Some(sort) = sort_doc {
-            find.sort(sort)
-        } else {
-            find
-        })
-        .await
-        .map_err(map_mongo_error)?;
-
-        let items = cursor.try_collect().await.map_err(map_mongo_error)?;
-        Ok(Paged::new(items, total as i64, skip_v, limit_v))
+        rows.into_iter()
+            .map(|row| aggregate_row(&aggregation, row))
+            .collect()
     }
 
     async fn find_by_id(
         &self,
         parent_id: Option<String>,
         id: &str,
-        _context: CqrsContext,
+        context: CqrsContext,
     ) -> Result<Option<V>, CqrsError> {
-        let collection = self.database.collection::<V>(&self.collection_name);
+        let scope = self.scope.for_context(&context)?;
+        let collection = self
+            .database
+            .collection::<V>(&scope.table(&self.collection_name)?);
         collection
-            .find_one(self.parent_id_query(doc! {V::field_id(): id}, &parent_id)?)
+            .find_one(self.parent_id_query(
+                doc! {V::field_id(): id},
+                &parent_id,
+                scope.column()?,
+            )?)
             .await
             .map_err(map_mongo_error)
     }
 
-    async fn save(&self, entity: V, _context: CqrsContext) -> Result<(), CqrsError> {
-        let collection = self.database.collection::<V>(&self.collection_name);
-        let id = doc! {V::field_id(): entity.id()};
+    async fn save(&self, entity: V, context: CqrsContext) -> Result<(), CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        let collection = self
+            .database
+            .collection::<V>(&scope.table(&self.collection_name)?);
+        let mut id = doc! {V::field_id(): entity.id()};
         let mut fields = serialize_to_document(&entity).map_err(map_bson_error)?;
         fields.remove(V::field_id());
+        if let Some(tenant) = scope.column()? {
+            id.insert(TENANT_COLUMN, tenant);
+            fields.insert(TENANT_COLUMN, tenant);
+        }
         collection
             .update_one(
                 id,
@@ -218,6 +559,16 @@ impl FieldMapper for SnapshotStateMapper {
 const NO_PARENT_ON_SNAPSHOT: &str =
     "a snapshot table has no parent column, so a parent id cannot be filtered on";
 
+/// Rejected when a caller hands a snapshot storage a keyset cursor. Same wording as the
+/// other backends.
+const NO_CURSOR_ON_SNAPSHOT: &str =
+    "a snapshot table does not support cursor pagination; use skip/limit";
+
+/// Rejected when a caller hands a snapshot storage a text search. Same wording as the
+/// other backends.
+const NO_SEARCH_ON_SNAPSHOT: &str =
+    "a snapshot table does not support text search; project a view to search";
+
 /// Read-side storage over the event store's **snapshot** collection.
 ///
 /// Unlike the Postgres and SurrealDB ones, this does reuse [`MongoDbStorage`]: the
@@ -265,6 +616,15 @@ where
             )),
         }
     }
+
+    /// The [`Tenancy`] the `MongoDBPersist` writing the snapshots was configured with.
+    #[must_use]
+    pub fn with_tenancy(self, tenancy: Tenancy) -> Self {
+        Self {
+            _phantom: PhantomData,
+            inner: Arc::new((*self.inner).clone().with_tenancy(tenancy)),
+        }
+    }
 }
 
 cqrs_async_trait! {
@@ -278,6 +638,10 @@ where
         self.inner.type_name()
     }
 
+    async fn health(&self) -> Result<(), CqrsError> {
+        self.inner.health().await
+    }
+
     async fn filter(
         &self,
         parent_id: Option<String>,
@@ -287,8 +651,16 @@ where
         if parent_id.is_some() {
             return Err(CqrsError::validation(NO_PARENT_ON_SNAPSHOT));
         }
+        if query.cursor().is_some() {
+            return Err(CqrsError::validation(NO_CURSOR_ON_SNAPSHOT));
+        }
+        if query.search().is_some() {
+            return Err(CqrsError::validation(NO_SEARCH_ON_SNAPSHOT));
+        }
+        // The inner storage plans a keyset over `Snapshot<A>`, whose tokens a later call
+        // here would refuse; none leave this storage.
         let result = self.inner.filter(parent_id, query, context).await?;
-        Ok(result.map(|s| s.state))
+        Ok(result.map(|s| s.state).with_cursors(None, None))
     }
 
     async fn find_by_id(
@@ -319,6 +691,7 @@ where
 mod tests {
     use super::*;
     use crate::log_capture::{containing, events_of_async};
+    use crate::read::Metric;
     use serde::Deserialize;
 
     #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -356,7 +729,138 @@ mod tests {
         assert_eq!(SnapshotStateMapper.map("counter"), "state.counter");
     }
 
+    /// A keyset ends in the id, and the id sits at the top level whatever the mapper
+    /// does with the other fields.
+    #[test]
+    fn the_id_field_bypasses_a_prefixing_mapper() {
+        let mapper = IdFieldMapper {
+            id_field: "_id",
+            inner: &SnapshotStateMapper,
+        };
+        let keyset = Keyset::plan(Some(&[asc("name")]), "_id", None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            sorters_to_mongo_sort(Some(keyset.order()), &mapper).unwrap(),
+            Some(doc! { "state.name": 1i32, "_id": 1i32 })
+        );
+    }
+
+    #[test]
+    fn an_aggregation_compiles_to_a_group_pipeline() {
+        let aggregation =
+            Aggregation::new(["muscle.primary"], [Metric::count(), Metric::avg("score")]);
+        let pipeline =
+            aggregate_pipeline(doc! { "a": 1i32 }, &aggregation, &SnapshotStateMapper).unwrap();
+        assert_eq!(
+            pipeline,
+            vec![
+                doc! { "$match": { "a": 1i32 } },
+                doc! { "$group": {
+                    "_id": { "g0": { "$ifNull": ["$state.muscle.primary", Bson::Null] } },
+                    "m0": { "$sum": 1i32 },
+                    "m1": { "$avg": "$state.score" },
+                } },
+                doc! { "$addFields": {
+                    "n0": { "$cond": [{ "$eq": ["$_id.g0", Bson::Null] }, 1i32, 0i32] },
+                } },
+                doc! { "$sort": { "n0": 1i32, "_id.g0": 1i32 } },
+            ]
+        );
+    }
+
+    #[test]
+    fn an_aggregation_without_keys_groups_on_null_and_does_not_sort() {
+        let aggregation = Aggregation::new(Vec::<String>::new(), [Metric::max("score")]);
+        let pipeline = aggregate_pipeline(Document::new(), &aggregation, &IdentityMapper).unwrap();
+        assert_eq!(
+            pipeline[1],
+            doc! { "$group": { "_id": Bson::Null, "m0": { "$max": "$score" } } }
+        );
+        assert_eq!(pipeline.len(), 2);
+    }
+
+    #[test]
+    fn a_group_output_is_renamed_from_its_positional_columns() {
+        let aggregation = Aggregation::new(["a.b"], [Metric::count()]);
+        let row = aggregate_row(
+            &aggregation,
+            doc! { "_id": { "g0": "x" }, "m0": 3i32, "n0": 0i32 },
+        )
+        .unwrap();
+        assert_eq!(row.group["a.b"], "x");
+        assert_eq!(row.metrics["count"], 3);
+    }
+
+    #[test]
+    fn an_operator_group_key_is_rejected() {
+        let aggregation = Aggregation::new(["$where"], [Metric::count()]);
+        let err = aggregate_pipeline(Document::new(), &aggregation, &IdentityMapper).unwrap_err();
+        assert_eq!(err.code, "GENERIC_VALIDATION_FAILED");
+    }
+
+    #[test]
+    fn a_projection_keeps_its_fields_and_drops_an_unrequested_id() {
+        assert_eq!(
+            projection_doc(&["title", "score"]),
+            doc! { "title": 1i32, "score": 1i32, "_id": 0i32 }
+        );
+        assert_eq!(projection_doc(&["_id"]), doc! { "_id": 1i32 });
+    }
+
     /// No server needed: the sort document is built before the driver is reached.
+    #[derive(Debug, Clone, Default, Serialize)]
+    struct SearchQuery {
+        #[serde(skip)]
+        text: Option<&'static str>,
+    }
+
+    impl Query for SearchQuery {
+        fn search(&self) -> Option<&str> {
+            self.text
+        }
+        fn searchable_fields(&self) -> Vec<&str> {
+            vec!["title"]
+        }
+    }
+
+    #[tokio::test]
+    async fn a_search_is_a_top_level_text_condition_over_quoted_words() {
+        let query = SearchQuery {
+            text: Some("Cities & KNIGHTS"),
+        };
+        let search = TextSearch::plan(&query).unwrap();
+        let storage = unreachable_storage::<SearchQuery>("article").await;
+        assert_eq!(
+            storage
+                .filter_doc(None, &None, None, search.as_ref())
+                .unwrap(),
+            doc! { "$text": { "$search": "\"cities\" \"knights\"" } },
+            "quoted, so that MongoDB requires every word instead of any"
+        );
+        assert_eq!(
+            storage.filter_doc(None, &None, Some("acme"), None).unwrap(),
+            doc! { "$and": [{}, { "tenant_id": "acme" }] },
+            "a tenant narrows every query under Tenancy::Column"
+        );
+    }
+
+    #[test]
+    fn relevance_sorts_ahead_of_the_query_sort() {
+        let sort = sorters_to_mongo_sort(Some(vec![asc("title")]), &IdentityMapper).unwrap();
+        let sort = relevance_first(sort);
+        assert_eq!(
+            sort.keys().collect::<Vec<_>>(),
+            [SCORE, "title"],
+            "a document's key order is its sort order"
+        );
+        assert_eq!(
+            sort.get_document(SCORE).unwrap(),
+            &doc! { "$meta": "textScore" }
+        );
+        assert_eq!(relevance_first(None).len(), 1);
+    }
+
     #[test]
     fn a_valid_sort_compiles_to_the_expected_document() {
         let sorters = vec![
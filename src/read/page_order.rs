@@ -1,8 +1,5 @@
 //! The warning a read backend emits when it is asked for a page over an undefined
 //! order.
-//!
-//! Gated in `read/mod.rs` on the backends that call it, so a build without any of them
-//! does not carry an unused function — see the module declaration for why that matters.
 
 use crate::read::Sorter;
 use crate::warn_once::warn_once;
@@ -10,6 +10,11 @@ use utoipa::ToSchema;
 /// - `page` / `page_size` — the derived page-based form, kept for clients that
 ///   use it. `page` is `skip / limit`, so it is only meaningful when `skip` is a
 ///   multiple of `limit`.
+///
+/// Next to them, `nextCursor` / `prevCursor` carry the keyset form: opaque tokens to send
+/// back as [`crate::read::Query::cursor`] (`after` / `before` over HTTP). A storage
+/// issues them whenever a sort is in effect and a page lies on that side; they are
+/// absent from the JSON otherwise.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[cfg_attr(feature = "utoipa", derive(ToSchema))]
 #[serde(rename_all = "camelCase")]
@@ -24,6 +29,12 @@ pub struct Paged<T> {
     pub page: i64,
     /// Alias of `limit`, kept for page-based clients.
     pub page_size: i64,
+    /// Token for the page after this one, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    /// Token for the page before this one, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prev_cursor: Option<String>,
 }
 
 impl<T> Paged<T> {
@@ -38,9 +49,19 @@ impl<T> Paged<T> {
             limit,
             page: if limit > 0 { (skip / limit).abs() } else { 0 },
             page_size: limit,
+            next_cursor: None,
+            prev_cursor: None,
         }
     }
 
+    /// Attaches the keyset tokens, as returned by the storage's keyset planner.
+    #[must_use]
+    pub fn with_cursors(mut self, next: Option<String>, prev: Option<String>) -> Self {
+        self.next_cursor = next;
+        self.prev_cursor = prev;
+        self
+    }
+
     /// Maps the items while keeping every pagination counter untouched.
     #[must_use]
     pub fn map<U, F: FnMut(T) -> U>(self, f: F) -> Paged<U> {
@@ -51,8 +72,25 @@ impl<T> Paged<T> {
             limit: self.limit,
             page: self.page,
             page_size: self.page_size,
+            next_cursor: self.next_cursor,
+            prev_cursor: self.prev_cursor,
         }
     }
+
+    /// [`Paged::map`] for a fallible mapping: the first error is the result.
+    pub fn try_map<U, E, F: FnMut(T) -> Result<U, E>>(self, f: F) -> Result<Paged<U>, E> {
+        let items = self.items.into_iter().map(f).collect::<Result<_, E>>()?;
+        Ok(Paged {
+            items,
+            total: self.total,
+            skip: self.skip,
+            limit: self.limit,
+            page: self.page,
+            page_size: self.page_size,
+            next_cursor: self.next_cursor,
+            prev_cursor: self.prev_cursor,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -94,4 +132,25 @@ mod tests {
         assert_eq!(p.page, 2);
         assert_eq!(p.page_size, 2);
     }
+
+    #[test]
+    fn map_preserves_cursors() {
+        let p = Paged::new(vec![1], 7, 0, 1)
+            .with_cursors(Some("n".into()), Some("p".into()))
+            .map(|i| i * 2);
+        assert_eq!(p.next_cursor.as_deref(), Some("n"));
+        assert_eq!(p.prev_cursor.as_deref(), Some("p"));
+    }
+
+    #[test]
+    fn absent_cursors_stay_out_of_the_json() {
+        let json = serde_json::to_value(Paged::new(vec![1], 1, 0, 10)).unwrap();
+        assert!(json.get("nextCursor").is_none());
+        assert!(json.get("prevCursor").is_none());
+
+        let json =
+            serde_json::to_value(Paged::new(vec![1], 2, 0, 1).with_cursors(Some("n".into()), None))
+                .unwrap();
+        assert_eq!(json["nextCursor"], "n");
+    }
 }
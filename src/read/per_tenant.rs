@@ -0,0 +1,143 @@
+use crate::read::storage::{DynStorage, Storage};
+use crate::read::{AggregateRow, Aggregation, Paged, Projection};
+use crate::{CqrsContext, CqrsError, MaybeSend, MaybeSync};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+/// A view storage per tenant — a database, or a whole server — picked from the tenant
+/// of the [`CqrsContext`] of each call; the read-side counterpart of
+/// [`PerTenantPersist`](crate::es::per_tenant::PerTenantPersist).
+///
+/// A context without a tenant, or with one that was not registered, is refused with
+/// `403`.
+pub struct PerTenantStorage<V, Q> {
+    type_name: String,
+    tenants: BTreeMap<String, DynStorage<V, Q>>,
+}
+
+impl<V, Q> Clone for PerTenantStorage<V, Q> {
+    fn clone(&self) -> Self {
+        Self {
+            type_name: self.type_name.clone(),
+            tenants: self.tenants.clone(),
+        }
+    }
+}
+
+impl<V, Q> PerTenantStorage<V, Q> {
+    #[must_use]
+    pub fn new(type_name: &str) -> Self {
+        Self {
+            type_name: type_name.to_string(),
+            tenants: BTreeMap::new(),
+        }
+    }
+
+    /// Registers the storage of `tenant`, replacing any it had.
+    #[must_use]
+    pub fn with_tenant(mut self, tenant: impl Into<String>, storage: DynStorage<V, Q>) -> Self {
+        self.tenants.insert(tenant.into(), storage);
+        self
+    }
+
+    /// The registered tenants, in order.
+    pub fn tenants(&self) -> impl Iterator<Item = &str> {
+        self.tenants.keys().map(String::as_str)
+    }
+
+    fn storage(&self, context: &CqrsContext) -> Result<&DynStorage<V, Q>, CqrsError> {
+        let tenant = context
+            .tenant()
+            .filter(|tenant| !tenant.is_empty())
+            .ok_or_else(|| CqrsError::forbidden("a tenant is required"))?;
+        self.tenants
+            .get(tenant)
+            .ok_or_else(|| CqrsError::forbidden(format!("unknown tenant {tenant:?}")))
+    }
+}
+
+cqrs_async_trait! {
+impl<V, Q> Storage<V, Q> for PerTenantStorage<V, Q>
+where
+    V: Debug + Clone + Default + Serialize + DeserializeOwned + MaybeSend + MaybeSync,
+    Q: Clone + Debug + MaybeSend + MaybeSync,
+{
+    fn type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    /// That of the tenants' storages, which hold the same views.
+    fn id_field(&self) -> Option<&str> {
+        self.tenants.values().find_map(|storage| storage.id_field())
+    }
+
+    /// Every tenant's storage.
+    async fn health(&self) -> Result<(), CqrsError> {
+        for (tenant, storage) in &self.tenants {
+            storage.health().await.map_err(|e| {
+                CqrsError::internal(format!("tenant {tenant:?}: {}", e.message))
+            })?;
+        }
+        Ok(())
+    }
+
+    async fn filter(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        context: CqrsContext,
+    ) -> Result<Paged<V>, CqrsError> {
+        self.storage(&context)?
+            .filter(parent_id, query, context)
+            .await
+    }
+
+    async fn find_by_id(
+        &self,
+        parent_id: Option<String>,
+        id: &str,
+        context: CqrsContext,
+    ) -> Result<Option<V>, CqrsError> {
+        self.storage(&context)?
+            .find_by_id(parent_id, id, context)
+            .await
+    }
+
+    async fn save(&self, entity: V, context: CqrsContext) -> Result<(), CqrsError> {
+        self.storage(&context)?.save(entity, context).await
+    }
+
+    async fn filter_projected(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        projection: Projection,
+        context: CqrsContext,
+    ) -> Result<Paged<JsonValue>, CqrsError>
+    where
+        Q: 'async_trait,
+    {
+        self.storage(&context)?
+            .filter_projected(parent_id, query, projection, context)
+            .await
+    }
+
+    async fn aggregate(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        aggregation: Aggregation,
+        context: CqrsContext,
+    ) -> Result<Vec<AggregateRow>, CqrsError>
+    where
+        Q: 'async_trait,
+    {
+        self.storage(&context)?
+            .aggregate(parent_id, query, aggregation, context)
+            .await
+    }
+}
+}
@@ -1,11 +1,12 @@
 use crate::pg::{PgConn, PgPool, SharedClient};
 use crate::read::page_order::warn_if_page_order_undefined;
 use crate::read::query::Query;
-use crate::read::sorter::order_by_clause;
+use crate::read::search::{plan_keyset, relevance_first};
+use crate::read::sorter::{order_by_clause, validated_field_name};
 use crate::read::storage::{HasId, Storage, StorageError};
-use crate::read::Paged;
-use crate::{Aggregate, CqrsContext, CqrsError};
-use rest_sql::{FieldMapper, IdentityMapper};
+use crate::read::{AggregateRow, Aggregation, Keyset, MetricFunction, Paged, Projection, TextSearch};
+use crate::{Aggregate, CqrsContext, CqrsError, TENANT_COLUMN, Tenancy, TenantScope};
+use rest_sql::{FieldMapper, IdentityMapper, RestSql};
 use std::borrow::Cow;
 use rest_sql_drivers::tokio_postgres::PgCompiler;
 use rest_sql_drivers::Driver;
@@ -50,21 +51,43 @@ impl FieldMapper for JsonbDataMapper {
     }
 }
 
-/// Compiles a query's RSQL filter into a `WHERE` fragment and its bound parameters.
+/// Sends the view's id field to the `id` column, and every other field to the view's own
+/// mapper.
+///
+/// `save` moves the id out of `data` and into the primary key, so whatever the mapper
+/// does with other fields, the id is never under it: a [`JsonbDataMapper`] would send
+/// `id` to `data->>'id'`, which is always null. A keyset page needs the id — it is the
+/// tie-breaker every keyset sort ends with — so the view storage compiles through this.
+#[derive(Debug, Clone)]
+struct IdColumnMapper<'a, M> {
+    id_field: &'static str,
+    inner: &'a M,
+}
+
+impl<M: FieldMapper> FieldMapper for IdColumnMapper<'_, M> {
+    fn map<'a>(&self, field: &'a str) -> Cow<'a, str> {
+        if field == self.id_field {
+            Cow::Borrowed("id")
+        } else {
+            self.inner.map(field)
+        }
+    }
+}
+
+/// Compiles an RSQL filter into a `WHERE` fragment and its bound parameters.
 ///
 /// Shared by the view storage and the snapshot storage: they read different tables with
 /// different key columns, but a filter compiles the same way for both.
-fn compile_where<Q, M>(
-    query: &Q,
+fn compile_where<M>(
+    filter: Option<&RestSql>,
     mapper: &M,
 ) -> Result<(String, Vec<Box<dyn ToSql + Sync + Send>>), CqrsError>
 where
-    Q: Query,
     M: FieldMapper + Clone,
 {
-    match query.filter() {
+    match filter {
         Some(rsql) => PgCompiler::new(mapper.clone())
-            .compile(&rsql)
+            .compile(rsql)
             .map_err(|e| CqrsError::internal(e.to_string())),
         None => Ok((String::new(), vec![])),
     }
@@ -82,10 +105,17 @@ where
 /// Split out because it is the only part of `paged_select` a test can reach without a
 /// server, and the placeholder arithmetic is exactly where an off-by-one hides: `LIMIT
 /// $n` instead of `$n+1` silently reuses the offset as the limit.
-fn page_sql(table: &str, where_full: &str, order_by: &str, filter_params: usize) -> String {
+fn page_sql(
+    columns: &str,
+    table: &str,
+    where_full: &str,
+    order_by: &str,
+    filter_params: usize,
+) -> String {
     let offset_placeholder = filter_params + 1;
     format!(
-        "SELECT * FROM {}{}{} OFFSET ${} LIMIT ${}",
+        "SELECT {} FROM {}{}{} OFFSET ${} LIMIT ${}",
+        columns,
         table,
         where_full,
         order_by,
@@ -94,46 +124,73 @@ fn page_sql(table: &str, where_full: &str, order_by: &str, filter_params: usize)
     )
 }
 
+/// A `WHERE` fragment — empty for none — and the parameters it binds.
+type Where = (String, Vec<Box<dyn ToSql + Sync + Send>>);
+
+/// Narrows a `WHERE` fragment to `tenant`, under [`Tenancy::Column`].
+fn tenant_filter((where_sql, mut params): Where, tenant: Option<&str>) -> Where {
+    let Some(tenant) = tenant else {
+        return (where_sql, params);
+    };
+    params.push(Box::new(tenant.to_string()));
+    let matched = format!("{TENANT_COLUMN} = ${}", params.len());
+    if where_sql.trim().is_empty() {
+        (matched, params)
+    } else {
+        (format!("({}) AND {}", where_sql, matched), params)
+    }
+}
+
+fn where_clause(where_sql: &str) -> String {
+    if where_sql.trim().is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", where_sql)
+    }
+}
+
+/// The count and the page carry a `WHERE` each: a keyset page narrows the rows it reads
+/// past the cursor, but `total` still counts the whole filtered set.
 struct PagedSelect<'a> {
+    /// `*`, or the view storage's projected `id, … AS data`.
+    columns: &'a str,
     table: &'a str,
-    where_sql: &'a str,
-    params: Vec<Box<dyn ToSql + Sync + Send>>,
+    count: Where,
+    page: Where,
     order_by: &'a str,
     offset: i64,
     limit: i64,
 }
 
+/// Returns the decoded rows and the total; the caller builds the [`Paged`], because
+/// only it knows whether `limit` was the page size or one past it.
 async fn paged_select<T, P, F>(
     pool: &P,
     select: PagedSelect<'_>,
     decode: F,
-) -> Result<Paged<T>, CqrsError>
+) -> Result<(Vec<T>, i64), CqrsError>
 where
     P: PgPool,
     F: Fn(&tokio_postgres::Row) -> Result<T, CqrsError>,
 {
     let PagedSelect {
+        columns,
         table,
-        where_sql,
-        params,
+        count: (count_where, count_params),
+        page: (page_where, params),
         order_by,
         offset,
         limit,
     } = select;
 
-    let where_full = if where_sql.trim().is_empty() {
-        String::new()
-    } else {
-        format!(" WHERE {}", where_sql)
-    };
-
     let conn = pool.acquire().await?;
 
     let count_sql = format!(
         "SELECT COUNT(*)::BIGINT AS total FROM {}{}",
-        table, where_full
+        table,
+        where_clause(&count_where)
     );
-    let count_params: Vec<&(dyn ToSql + Sync)> = params
+    let count_params: Vec<&(dyn ToSql + Sync)> = count_params
         .iter()
         .map(|b| b.as_ref() as &(dyn ToSql + Sync))
         .collect();
@@ -144,7 +201,13 @@ where
         .map_err(map_pg_error)?;
     let total: i64 = row.try_get::<_, i64>("total").map_err(map_pg_error)?;
 
-    let select_sql = page_sql(table, &where_full, order_by, params.len());
+    let select_sql = page_sql(
+        columns,
+        table,
+        &where_clause(&page_where),
+        order_by,
+        params.len(),
+    );
     let mut select_params = params;
     select_params.push(Box::new(offset));
     select_params.push(Box::new(limit));
@@ -162,7 +225,123 @@ where
     for row in rows {
         items.push(decode(&row)?);
     }
-    Ok(Paged::new(items, total, offset, limit))
+    Ok((items, total))
+}
+
+/// Builds the `GROUP BY` select behind `Storage::aggregate`.
+///
+/// Each row comes back as one JSONB object keyed positionally — `g0`, `m0`, … — which
+/// `tokio-postgres` decodes to JSON whatever the key and metric types are, instead of a
+/// column per key whose SQL type the caller would have to know in advance. `sum` and
+/// `avg` cast to `numeric`, so a [`JsonbDataMapper`]'s text reads as a number; a value
+/// that does not parse as one fails the query rather than being skipped.
+///
+/// Keys, `min` and `max` work on the JSONB value instead — see [`jsonb_value`] — so that
+/// numbers compare as numbers and a key comes back with its JSON type. Postgres has no
+/// `MIN` over JSONB: the least value is the first of the group's sorted values.
+///
+/// `ORDER BY` repeats the keys: ascending, and Postgres sorts `NULL` last by default.
+fn aggregate_sql(
+    table: &str,
+    where_full: &str,
+    aggregation: &Aggregation,
+    mapper: &impl FieldMapper,
+) -> Result<String, CqrsError> {
+    aggregation.validate()?;
+    let keys: Vec<String> = aggregation
+        .group_by
+        .iter()
+        .map(|key| jsonb_value(&mapper.map(key)))
+        .collect();
+    let mut columns: Vec<String> = keys
+        .iter()
+        .enumerate()
+        .map(|(i, key)| format!("'{}', {key}", AggregateRow::group_column(i)))
+        .collect();
+    for (i, metric) in aggregation.metrics.iter().enumerate() {
+        let expr = match (metric.function, metric.validated_field()?) {
+            (MetricFunction::Sum, Some(f)) => format!("SUM(({})::numeric)", mapper.map(f)),
+            (MetricFunction::Avg, Some(f)) => format!("AVG(({})::numeric)", mapper.map(f)),
+            (MetricFunction::Min, Some(f)) => extreme(&jsonb_value(&mapper.map(f)), "ASC"),
+            (MetricFunction::Max, Some(f)) => extreme(&jsonb_value(&mapper.map(f)), "DESC"),
+            _ => "COUNT(*)".to_string(),
+        };
+        columns.push(format!("'{}', {expr}", AggregateRow::metric_column(i)));
+    }
+
+    let mut sql = format!(
+        "SELECT jsonb_build_object({}) AS agg FROM {}{}",
+        columns.join(", "),
+        table,
+        where_full
+    );
+    if !keys.is_empty() {
+        let keys = keys.join(", ");
+        sql.push_str(&format!(" GROUP BY {keys} ORDER BY {keys}"));
+    }
+    Ok(sql)
+}
+
+/// A mapped field as JSONB: `data->'f'` for a [`JsonbDataMapper`]'s `data->>'f'`, which
+/// Postgres orders by type and numbers by value, and `to_jsonb` of any other column. A
+/// JSON `null` becomes SQL `NULL`, as a missing field is, so that both group together
+/// and `min`/`max` skip them.
+fn jsonb_value(mapped: &str) -> String {
+    match mapped.strip_prefix("data->>'") {
+        Some(rest) => format!("NULLIF(data->'{rest}, 'null'::jsonb)"),
+        None => format!("to_jsonb({mapped})"),
+    }
+}
+
+/// The first of the group's non-null values in `direction`: `min` ascending, `max`
+/// descending; `NULL` over a group without any.
+fn extreme(value: &str, direction: &str) -> String {
+    format!(
+        "(array_agg({value} ORDER BY {value} {direction}) FILTER (WHERE {value} IS NOT NULL))[1]"
+    )
+}
+
+/// The page's columns under a projection: the id column, and `data` cut down to the
+/// projected keys. `jsonb_each` rather than `jsonb_build_object`, so that a key the row
+/// does not have stays absent instead of coming back `null`. The keys are interpolated,
+/// which `Projection::validate` and the keyset planner have made safe.
+fn projected_columns(fields: &[&str]) -> String {
+    let keys: Vec<String> = fields.iter().map(|f| format!("'{f}'")).collect();
+    format!(
+        "id, (SELECT COALESCE(jsonb_object_agg(e.key, e.value), '{{}}'::jsonb) \
+         FROM jsonb_each(data) AS e WHERE e.key IN ({})) AS data",
+        keys.join(", ")
+    )
+}
+
+/// The `tsvector` a search matches and ranks against: the searchable fields, mapped, as
+/// text and joined by spaces, parsed under the storage's text search configuration.
+///
+/// `ensure_search_index` builds its GIN index over this very expression, and Postgres
+/// only uses an expression index for the expression it was built over, so both come from
+/// here. That is also why it concatenates with `||` rather than `concat_ws`: an index
+/// expression must be immutable, and `concat_ws` is only stable.
+fn search_vector<'a>(
+    config: &str,
+    fields: impl IntoIterator<Item = &'a str>,
+    mapper: &impl FieldMapper,
+) -> Result<String, CqrsError> {
+    validated_field_name("search", config).map_err(|_| {
+        CqrsError::validation(format!(
+            "text search configuration {config:?} is not an identifier"
+        ))
+    })?;
+    let text = fields
+        .into_iter()
+        .map(|f| {
+            let column = mapper.map(validated_field_name("search", f)?);
+            Ok(format!("coalesce(({column})::text, '')"))
+        })
+        .collect::<Result<Vec<_>, CqrsError>>()?;
+    Ok(format!(
+        "to_tsvector('{config}', {})",
+        text.join(" || ' ' || ")
+    ))
 }
 
 /// Rebuilds a view from a `(id, data)` row.
@@ -190,6 +369,24 @@ where
 /// [`crate::es::postgres::PostgresPersist`]. The default `P = SharedClient`
 /// keeps the single-`Arc<Client>` behaviour; pass a real pool via
 /// [`Self::with_pool`].
+///
+/// A search ([`Query::search`]) matches a `tsvector` over the searchable fields against
+/// `plainto_tsquery`, and ranks with `ts_rank`, under the `simple` text search
+/// configuration unless [`Self::with_search_config`] names another. Create its index with
+/// [`Self::ensure_search_index`].
+///
+/// Under [`Tenancy::Column`] the table carries a `tenant_id` column, and its key is
+/// `(tenant_id, id)`:
+///
+/// ```sql
+/// CREATE TABLE todos (
+///     tenant_id TEXT NOT NULL,
+///     id TEXT NOT NULL,
+///     parent_id TEXT,
+///     data JSONB NOT NULL,
+///     PRIMARY KEY (tenant_id, id)
+/// );
+/// ```
 #[derive(Debug, Clone)]
 pub struct PostgresStorage<V, Q, M = IdentityMapper, P = SharedClient> {
     _phantom: PhantomData<(V, Q)>,
@@ -197,6 +394,8 @@ pub struct PostgresStorage<V, Q, M = IdentityMapper, P = SharedClient> {
     type_name: String,
     table_name: String,
     mapper: M,
+    search_config: String,
+    scope: TenantScope,
 }
 
 impl<V, Q> PostgresStorage<V, Q, IdentityMapper, SharedClient> {
@@ -239,8 +438,26 @@ where
             type_name: type_name.to_string(),
             table_name: table_name.to_string(),
             mapper,
+            search_config: "simple".to_string(),
+            scope: TenantScope::default(),
         }
     }
+
+    /// Keeps each tenant's views apart: a `tenant_id` column ([`Tenancy::Column`]), or a
+    /// `{table}_{tenant}` table per tenant ([`Tenancy::Table`]).
+    #[must_use]
+    pub fn with_tenancy(mut self, tenancy: Tenancy) -> Self {
+        self.scope = TenantScope::new(tenancy);
+        self
+    }
+
+    /// The text search configuration a search parses words under — `english` to stem
+    /// them, say. Defaults to `simple`, which only lower-cases.
+    #[must_use]
+    pub fn with_search_config(mut self, config: &str) -> Self {
+        self.search_config = config.to_string();
+        self
+    }
 }
 
 impl<V, Q, M, P> PostgresStorage<V, Q, M, P>
@@ -250,12 +467,134 @@ where
     M: FieldMapper + Debug + Clone + Send + Sync,
     P: PgPool,
 {
+    fn mapper(&self) -> IdColumnMapper<'_, M> {
+        IdColumnMapper {
+            id_field: V::field_id(),
+            inner: &self.mapper,
+        }
+    }
+
+    fn search_vector<'a>(
+        &self,
+        fields: impl IntoIterator<Item = &'a str>,
+    ) -> Result<String, CqrsError> {
+        search_vector(&self.search_config, fields, &self.mapper())
+    }
+
+    /// Creates the GIN index a search over `fields` runs on, if it does not exist yet.
+    ///
+    /// Pass the view's [`Query::searchable_fields`] **in the same order**: Postgres only
+    /// uses an expression index for the exact expression it was built over. The index is
+    /// named after the table and not rebuilt when the fields change — drop it first.
+    pub async fn ensure_search_index(&self, fields: &[&str]) -> Result<(), CqrsError> {
+        let sql = format!(
+            "CREATE INDEX IF NOT EXISTS {}_search ON {} USING GIN ({})",
+            self.table_name.replace('.', "_"),
+            self.table_name,
+            self.search_vector(fields.iter().copied())?
+        );
+        let conn = self.pool.acquire().await?;
+        conn.client()
+            .execute(&sql, &[])
+            .await
+            .map_err(map_pg_error)?;
+        Ok(())
+    }
+
+    /// The body of `filter`, over any row shape: the whole view or a projection of it.
+    async fn select_page<T, F>(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        scope: &TenantScope,
+        columns: &str,
+        decode: F,
+    ) -> Result<Paged<T>, CqrsError>
+    where
+        T: Serialize,
+        F: Fn(&tokio_postgres::Row) -> Result<T, CqrsError>,
+    {
+        let table = scope.table(&self.table_name)?;
+        let tenant = scope.column()?;
+        let filter = query.filter();
+        let search = TextSearch::plan(&query)?;
+        let count = self.build_filter(filter.as_ref(), &parent_id, tenant, search.as_ref())?;
+
+        let pagination = query.pagination().unwrap_or_default();
+        let limit_v = pagination.limit.unwrap_or(20);
+        let offset_v = pagination.skip.unwrap_or(0);
+
+        let sort = query.sort();
+        if search.is_none() {
+            warn_if_page_order_undefined(&self.type_name, offset_v, sort.as_deref());
+        }
+        let keyset = plan_keyset(
+            sort.as_deref(),
+            V::field_id(),
+            query.cursor(),
+            search.as_ref(),
+        )?;
+
+        let Some(keyset) = keyset else {
+            let mut order_by = order_by_clause(sort, &self.mapper())?;
+            let page = self.build_filter(filter.as_ref(), &parent_id, tenant, search.as_ref())?;
+            if let Some(search) = &search {
+                // The search text is the last parameter `build_filter` binds.
+                let rank = format!(
+                    "ts_rank({}, plainto_tsquery('{}', ${}))",
+                    self.search_vector(search.fields())?,
+                    self.search_config,
+                    page.1.len()
+                );
+                order_by = relevance_first(&rank, &order_by);
+            }
+            let (items, total) = paged_select(
+                &self.pool,
+                PagedSelect {
+                    columns,
+                    table: &table,
+                    count,
+                    page,
+                    order_by: &order_by,
+                    offset: offset_v,
+                    limit: limit_v,
+                },
+                decode,
+            )
+            .await?;
+            return Ok(Paged::new(items, total, offset_v, limit_v));
+        };
+
+        let order_by = order_by_clause(Some(keyset.order()), &self.mapper())?;
+        let page = self.build_filter(keyset.filter(filter)?.as_ref(), &parent_id, tenant, None)?;
+        let offset_v = keyset.offset(offset_v);
+        let (items, total) = paged_select(
+            &self.pool,
+            PagedSelect {
+                columns,
+                table: &table,
+                count,
+                page,
+                order_by: &order_by,
+                offset: offset_v,
+                limit: keyset.fetch_limit(limit_v),
+            },
+            decode,
+        )
+        .await?;
+        let (items, next, prev) = keyset.finish(items, offset_v, limit_v);
+        Ok(Paged::new(items, total, offset_v, limit_v).with_cursors(next, prev))
+    }
+
     fn build_filter(
         &self,
-        query: &Q,
+        filter: Option<&RestSql>,
         parent_id: &Option<String>,
-    ) -> Result<(String, Vec<Box<dyn ToSql + Sync + Send>>), CqrsError> {
-        let (mut where_sql, mut params) = compile_where(query, &self.mapper)?;
+        tenant: Option<&str>,
+        search: Option<&TextSearch>,
+    ) -> Result<Where, CqrsError> {
+        let (mut where_sql, mut params) =
+            tenant_filter(compile_where(filter, &self.mapper())?, tenant);
 
         match (V::parent_field_id(), parent_id) {
             (Some(_), Some(pid)) => {
@@ -274,6 +613,20 @@ where
             }
             _ => {}
         }
+        if let Some(search) = search {
+            params.push(Box::new(search.text()));
+            let matched = format!(
+                "{} @@ plainto_tsquery('{}', ${})",
+                self.search_vector(search.fields())?,
+                self.search_config,
+                params.len()
+            );
+            where_sql = if where_sql.trim().is_empty() {
+                matched
+            } else {
+                format!("({}) AND {}", where_sql, matched)
+            };
+        }
         Ok((where_sql, params))
     }
 }
@@ -290,47 +643,117 @@ where
         &self.type_name
     }
 
+    fn id_field(&self) -> Option<&str> {
+        Some(V::field_id())
+    }
+
+    async fn health(&self) -> Result<(), CqrsError> {
+        crate::pg::ping(&self.pool).await
+    }
+
     async fn filter(
         &self,
         parent_id: Option<String>,
         query: Q,
-        _context: CqrsContext,
+        context: CqrsContext,
     ) -> Result<Paged<V>, CqrsError> {
-        let (where_sql, params) = self.build_filter(&query, &parent_id)?;
+        let scope = self.scope.for_context(&context)?;
+        self.select_page(parent_id, query, &scope, "*", |row| {
+            let id: String = row.try_get("id").map_err(map_pg_error)?;
+            let val: JsonValue = row.try_get::<_, JsonValue>("data").map_err(map_pg_error)?;
+            row_to_view(id, val)
+        })
+        .await
+    }
 
-        let pagination = query.pagination().unwrap_or_default();
-        let limit_v = pagination.limit.unwrap_or(20);
-        let offset_v = pagination.skip.unwrap_or(0);
+    async fn filter_projected(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        projection: Projection,
+        context: CqrsContext,
+    ) -> Result<Paged<JsonValue>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        projection.validate()?;
+        // A keyset reads its tokens from the page's items, so its fields are fetched too
+        // and stripped once the tokens are cut.
+        let search = TextSearch::plan(&query)?;
+        let keyset = plan_keyset(
+            query.sort().as_deref(),
+            V::field_id(),
+            query.cursor(),
+            search.as_ref(),
+        )?;
+        let fetched = projection.widened(keyset.iter().flat_map(Keyset::fields));
+        let with_id = fetched.contains(&V::field_id());
+        let page = self
+            .select_page(parent_id, query, &scope, &projected_columns(&fetched), |row| {
+                let id: String = row.try_get("id").map_err(map_pg_error)?;
+                let mut val: JsonValue =
+                    row.try_get::<_, JsonValue>("data").map_err(map_pg_error)?;
+                // `save` keeps the id in its own column, not in `data`.
+                if let (true, Some(obj)) = (with_id, val.as_object_mut()) {
+                    obj.insert(V::field_id().to_string(), JsonValue::String(id));
+                }
+                Ok(val)
+            })
+            .await?;
+        Ok(page.map(|item| projection.apply(item)))
+    }
 
-        let sort = query.sort();
-        warn_if_page_order_undefined(&self.type_name, offset_v, sort.as_deref());
-        let order_by = order_by_clause(sort, &self.mapper)?;
+    async fn aggregate(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        aggregation: Aggregation,
+        context: CqrsContext,
+    ) -> Result<Vec<AggregateRow>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        let search = TextSearch::plan(&query)?;
+        let (where_sql, params) = self.build_filter(
+            query.filter().as_ref(),
+            &parent_id,
+            scope.column()?,
+            search.as_ref(),
+        )?;
+        let sql = aggregate_sql(
+            &scope.table(&self.table_name)?,
+            &where_clause(&where_sql),
+            &aggregation,
+            &self.mapper(),
+        )?;
+        let params: Vec<&(dyn ToSql + Sync)> = params
+            .iter()
+            .map(|b| b.as_ref() as &(dyn ToSql + Sync))
+            .collect();
 
-        paged_select(
-            &self.pool,
-            PagedSelect {
-                table: &self.table_name,
-                where_sql: &where_sql,
-                params,
-                order_by: &order_by,
-                offset: offset_v,
-                limit: limit_v,
-            },
-            |row| {
-                let id: String = row.try_get("id").map_err(map_pg_error)?;
-                let val: JsonValue = row.try_get::<_, JsonValue>("data").map_err(map_pg_error)?;
-                row_to_view(id, val)
-            },
-        )
-        .await
+        let conn = self.pool.acquire().await?;
+        let rows = conn
+            .client()
+            .query(&sql, &params)
+            .await
+            .map_err(map_pg_error)?;
+        rows.iter()
+            .map(|row| {
+                let row: JsonValue = row.try_get("agg").map_err(map_pg_error)?;
+                match row {
+                    JsonValue::Object(row) => Ok(AggregateRow::from_positional(&aggregation, row)),
+                    other => Err(CqrsError::internal(format!(
+                        "an aggregate row is not an object: {other}"
+                    ))),
+                }
+            })
+            .collect()
     }
 
     async fn find_by_id(
         &self,
         parent_id: Option<String>,
         id: &str,
-        _context: CqrsContext,
+        context: CqrsContext,
     ) -> Result<Option<V>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        let tenant = scope.column()?;
         let mut where_sql = String::from("id = $1");
         let mut params: Vec<&(dyn ToSql + Sync)> = vec![&id];
         if let (Some(_), Some(pid)) = (V::parent_field_id(), parent_id.as_ref()) {
@@ -341,7 +764,15 @@ where
                 StorageError::MissingParentId.to_string(),
             ));
         }
-        let sql = format!("SELECT id, data FROM {} WHERE {}", self.table_name, where_sql);
+        if let Some(tenant) = &tenant {
+            where_sql.push_str(&format!(" AND {TENANT_COLUMN} = ${}", params.len() + 1));
+            params.push(tenant);
+        }
+        let sql = format!(
+            "SELECT id, data FROM {} WHERE {}",
+            scope.table(&self.table_name)?,
+            where_sql
+        );
         let conn = self.pool.acquire().await?;
         let row = conn
             .client()
@@ -357,7 +788,8 @@ where
         }
     }
 
-    async fn save(&self, entity: V, _context: CqrsContext) -> Result<(), CqrsError> {
+    async fn save(&self, entity: V, context: CqrsContext) -> Result<(), CqrsError> {
+        let scope = self.scope.for_context(&context)?;
         let id = entity.id().to_string();
         let parent_id = entity.parent_id().map(|s| s.to_string());
         let data = serde_json::to_value(&entity).map_err(CqrsError::serialization_error)?;
@@ -370,14 +802,23 @@ where
                 StorageError::MissingParentId.to_string(),
             ));
         }
+        let mut params: Vec<&(dyn ToSql + Sync)> = vec![&id, &parent_id, &data_obj];
+        let tenant = scope.column()?;
+        let (column, placeholder, key) = match &tenant {
+            Some(tenant) => {
+                params.push(tenant);
+                (format!(", {TENANT_COLUMN}"), ", $4", format!("{TENANT_COLUMN}, id"))
+            }
+            None => (String::new(), "", "id".to_string()),
+        };
         let sql = format!(
-            "INSERT INTO {} (id, parent_id, data) VALUES ($1, $2, $3) \
-             ON CONFLICT (id) DO UPDATE SET parent_id = EXCLUDED.parent_id, data = EXCLUDED.data",
-            self.table_name
+            "INSERT INTO {} (id, parent_id, data{column}) VALUES ($1, $2, $3{placeholder}) \
+             ON CONFLICT ({key}) DO UPDATE SET parent_id = EXCLUDED.parent_id, data = EXCLUDED.data",
+            scope.table(&self.table_name)?
         );
         let conn = self.pool.acquire().await?;
         conn.client()
-            .execute(&sql, &[&id, &parent_id, &data_obj])
+            .execute(&sql, &params)
             .await
             .map_err(map_pg_error)?;
         Ok(())
@@ -392,6 +833,18 @@ where
 const NO_PARENT_ON_SNAPSHOT: &str =
     "a snapshot table has no parent column, so a parent id cannot be filtered on";
 
+/// Rejected when a caller hands a snapshot storage a keyset cursor.
+///
+/// A keyset page ends its sort in the id, and an aggregate has no declared id field to
+/// read it back from. Offset paging still works; project a view to page by cursor.
+const NO_CURSOR_ON_SNAPSHOT: &str =
+    "a snapshot table does not support cursor pagination; use skip/limit";
+
+/// Rejected when a caller hands a snapshot storage a text search: there is no index to
+/// run it on, and the event store owns the table. Project a view to search.
+const NO_SEARCH_ON_SNAPSHOT: &str =
+    "a snapshot table does not support text search; project a view to search";
+
 /// Read-side storage over the event store's **snapshot** table.
 ///
 /// This does not reuse [`PostgresStorage`], and the reason is the schema. A view table is
@@ -410,6 +863,7 @@ pub struct PostgresFromSnapshotStorage<A, Q, M = JsonbDataMapper, P = SharedClie
     pool: P,
     snapshot_table: String,
     mapper: M,
+    scope: TenantScope,
 }
 
 impl<A, Q> PostgresFromSnapshotStorage<A, Q, JsonbDataMapper, SharedClient> {
@@ -442,8 +896,16 @@ impl<A, Q, M, P> PostgresFromSnapshotStorage<A, Q, M, P> {
             pool,
             snapshot_table: snapshot_table.to_string(),
             mapper,
+            scope: TenantScope::default(),
         }
     }
+
+    /// The [`Tenancy`] the `PostgresPersist` writing the snapshots was configured with.
+    #[must_use]
+    pub fn with_tenancy(mut self, tenancy: Tenancy) -> Self {
+        self.scope = TenantScope::new(tenancy);
+        self
+    }
 }
 
 cqrs_async_trait! {
@@ -458,17 +920,31 @@ where
         A::TYPE
     }
 
+    async fn health(&self) -> Result<(), CqrsError> {
+        crate::pg::ping(&self.pool).await
+    }
+
     async fn filter(
         &self,
         parent_id: Option<String>,
         query: Q,
-        _context: CqrsContext,
+        context: CqrsContext,
     ) -> Result<Paged<A>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
         if parent_id.is_some() {
             return Err(CqrsError::validation(NO_PARENT_ON_SNAPSHOT));
         }
+        if query.cursor().is_some() {
+            return Err(CqrsError::validation(NO_CURSOR_ON_SNAPSHOT));
+        }
+        if query.search().is_some() {
+            return Err(CqrsError::validation(NO_SEARCH_ON_SNAPSHOT));
+        }
 
-        let (where_sql, params) = compile_where(&query, &self.mapper)?;
+        let filter = query.filter();
+        let tenant = scope.column()?;
+        let count = tenant_filter(compile_where(filter.as_ref(), &self.mapper)?, tenant);
+        let page = tenant_filter(compile_where(filter.as_ref(), &self.mapper)?, tenant);
 
         let pagination = query.pagination().unwrap_or_default();
         let limit_v = pagination.limit.unwrap_or(20);
@@ -478,12 +954,13 @@ where
         warn_if_page_order_undefined(A::TYPE, offset_v, sort.as_deref());
         let order_by = order_by_clause(sort, &self.mapper)?;
 
-        paged_select(
+        let (items, total) = paged_select(
             &self.pool,
             PagedSelect {
-                table: &self.snapshot_table,
-                where_sql: &where_sql,
-                params,
+                columns: "*",
+                table: &scope.table(&self.snapshot_table)?,
+                count,
+                page,
                 order_by: &order_by,
                 offset: offset_v,
                 limit: limit_v,
@@ -494,28 +971,36 @@ where
                 serde_json::from_value(val).map_err(CqrsError::serialization_error)
             },
         )
-        .await
+        .await?;
+        Ok(Paged::new(items, total, offset_v, limit_v))
     }
 
     async fn find_by_id(
         &self,
         parent_id: Option<String>,
         id: &str,
-        _context: CqrsContext,
+        context: CqrsContext,
     ) -> Result<Option<A>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
         if parent_id.is_some() {
             return Err(CqrsError::validation(NO_PARENT_ON_SNAPSHOT));
         }
 
         // `aggregate_id`, not `id`: that is the snapshot table's primary key.
-        let sql = format!(
+        let mut sql = format!(
             "SELECT data FROM {} WHERE aggregate_id = $1",
-            self.snapshot_table
+            scope.table(&self.snapshot_table)?
         );
+        let tenant = scope.column()?;
+        let mut params: Vec<&(dyn ToSql + Sync)> = vec![&id];
+        if let Some(tenant) = &tenant {
+            sql.push_str(&format!(" AND {TENANT_COLUMN} = $2"));
+            params.push(tenant);
+        }
         let conn = self.pool.acquire().await?;
         let row = conn
             .client()
-            .query_opt(&sql, &[&id])
+            .query_opt(&sql, &params)
             .await
             .map_err(map_pg_error)?;
 
@@ -543,7 +1028,7 @@ mod tests {
     use super::*;
     use crate::log_capture::{containing, events_of_async};
     use crate::read::sorter::{SortDirection, Sorter};
-    use crate::read::Pagination;
+    use crate::read::{Metric, PageCursor, Pagination};
     use serde::Deserialize;
 
     #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -621,23 +1106,175 @@ mod tests {
     #[test]
     fn the_page_placeholders_follow_the_filter_parameters() {
         assert_eq!(
-            page_sql("articles", "", "", 0),
+            page_sql("*", "articles", "", "", 0),
             "SELECT * FROM articles OFFSET $1 LIMIT $2",
             "with no filter the window binds $1 and $2"
         );
         assert_eq!(
-            page_sql("articles", " WHERE data->>'a' = $1", " ORDER BY id ASC", 1),
+            page_sql(
+                "*",
+                "articles",
+                " WHERE data->>'a' = $1",
+                " ORDER BY id ASC",
+                1
+            ),
             "SELECT * FROM articles WHERE data->>'a' = $1 ORDER BY id ASC OFFSET $2 LIMIT $3",
             "one filter parameter pushes the window to $2 and $3"
         );
         assert_eq!(
-            page_sql("t", "", "", 3),
+            page_sql("*", "t", "", "", 3),
             "SELECT * FROM t OFFSET $4 LIMIT $5",
             "the limit is always one past the offset — reusing the offset would silently \
              page by the wrong amount"
         );
     }
 
+    #[test]
+    fn an_aggregation_compiles_to_a_grouped_jsonb_select() {
+        let aggregation = Aggregation::new(
+            ["author"],
+            [
+                Metric::count(),
+                Metric::sum("words"),
+                Metric::max("published_at"),
+            ],
+        );
+        assert_eq!(
+            aggregate_sql("articles", " WHERE x = $1", &aggregation, &JsonbDataMapper).unwrap(),
+            "SELECT jsonb_build_object('g0', NULLIF(data->'author', 'null'::jsonb), \
+             'm0', COUNT(*), 'm1', SUM((data->>'words')::numeric), \
+             'm2', (array_agg(NULLIF(data->'published_at', 'null'::jsonb) \
+             ORDER BY NULLIF(data->'published_at', 'null'::jsonb) DESC) \
+             FILTER (WHERE NULLIF(data->'published_at', 'null'::jsonb) IS NOT NULL))[1]) AS agg \
+             FROM articles WHERE x = $1 GROUP BY NULLIF(data->'author', 'null'::jsonb) \
+             ORDER BY NULLIF(data->'author', 'null'::jsonb)"
+        );
+    }
+
+    #[test]
+    fn numeric_min_max_and_group_keys_work_on_jsonb_values() {
+        let aggregation = Aggregation::new(["year"], [Metric::min("words"), Metric::max("words")]);
+        let sql = aggregate_sql("articles", "", &aggregation, &JsonbDataMapper).unwrap();
+        // `data->>` is text, which would order "10" before "9" and return the key as a
+        // string; the JSONB value orders numbers by value and keeps them numbers.
+        assert!(!sql.contains("->>"), "{sql}");
+        assert!(sql.contains(
+            "'g0', NULLIF(data->'year', 'null'::jsonb), 'm0', (array_agg(NULLIF(data->'words', \
+             'null'::jsonb) ORDER BY NULLIF(data->'words', 'null'::jsonb) ASC)"
+        ));
+        assert!(sql.contains("ORDER BY NULLIF(data->'words', 'null'::jsonb) DESC)"));
+        assert!(sql.ends_with(
+            "GROUP BY NULLIF(data->'year', 'null'::jsonb) ORDER BY NULLIF(data->'year', 'null'::jsonb)"
+        ));
+    }
+
+    #[test]
+    fn a_column_mapped_key_is_aggregated_as_jsonb() {
+        let aggregation = Aggregation::new(["words"], [Metric::max("words")]);
+        assert_eq!(
+            aggregate_sql("articles", "", &aggregation, &IdentityMapper).unwrap(),
+            "SELECT jsonb_build_object('g0', to_jsonb(words), 'm0', (array_agg(to_jsonb(words) \
+             ORDER BY to_jsonb(words) DESC) FILTER (WHERE to_jsonb(words) IS NOT NULL))[1]) \
+             AS agg FROM articles GROUP BY to_jsonb(words) ORDER BY to_jsonb(words)"
+        );
+    }
+
+    #[test]
+    fn an_aggregation_without_keys_has_no_group_by() {
+        let aggregation = Aggregation::new(Vec::<String>::new(), [Metric::avg("words")]);
+        assert_eq!(
+            aggregate_sql("articles", "", &aggregation, &IdentityMapper).unwrap(),
+            "SELECT jsonb_build_object('m0', AVG((words)::numeric)) AS agg FROM articles"
+        );
+    }
+
+    #[test]
+    fn a_hostile_group_key_never_reaches_the_sql() {
+        let aggregation = Aggregation::new(["1) FROM secrets--"], [Metric::count()]);
+        let err = aggregate_sql("articles", "", &aggregation, &IdentityMapper).unwrap_err();
+        assert_eq!(err.status, 400);
+    }
+
+    #[test]
+    fn a_projection_selects_the_listed_keys_out_of_data() {
+        assert_eq!(
+            projected_columns(&["title", "id"]),
+            "id, (SELECT COALESCE(jsonb_object_agg(e.key, e.value), '{}'::jsonb) \
+             FROM jsonb_each(data) AS e WHERE e.key IN ('title', 'id')) AS data"
+        );
+    }
+
+    #[tokio::test]
+    async fn a_hostile_projection_is_refused_before_touching_the_database() {
+        let err = storage()
+            .filter_projected(
+                None,
+                ArticleQuery::default(),
+                Projection::new(["title') FROM secrets--"]),
+                CqrsContext::default(),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, 400, "the pool would have said 'pool exhausted'");
+    }
+
+    #[derive(Debug, Clone, Default, Serialize)]
+    struct SearchQuery {
+        title: Option<String>,
+        #[serde(skip)]
+        text: Option<&'static str>,
+    }
+
+    impl Query for SearchQuery {
+        fn search(&self) -> Option<&str> {
+            self.text
+        }
+        fn searchable_fields(&self) -> Vec<&str> {
+            vec!["title", "summary"]
+        }
+    }
+
+    fn search_storage() -> PostgresStorage<Article, SearchQuery, JsonbDataMapper, FailingPool> {
+        PostgresStorage::with_pool_and_mapper(FailingPool, "article", "articles", JsonbDataMapper)
+    }
+
+    #[test]
+    fn a_search_matches_one_vector_over_every_searchable_field() {
+        assert_eq!(
+            search_vector("simple", ["title", "summary"], &JsonbDataMapper).unwrap(),
+            "to_tsvector('simple', coalesce((data->>'title')::text, '') || ' ' || \
+             coalesce((data->>'summary')::text, ''))"
+        );
+
+        let query = SearchQuery {
+            title: Some("Catan".into()),
+            text: Some("Cities, KNIGHTS"),
+        };
+        let search = TextSearch::plan(&query).unwrap();
+        let (sql, params) = search_storage()
+            .build_filter(query.filter().as_ref(), &None, None, search.as_ref())
+            .unwrap();
+        assert!(
+            sql.ends_with("@@ plainto_tsquery('simple', $2)"),
+            "the words are bound after the filter's own value, got: {sql}"
+        );
+        assert_eq!(params.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn a_hostile_search_configuration_is_refused_before_touching_the_database() {
+        let query = SearchQuery {
+            text: Some("catan"),
+            ..Default::default()
+        };
+        let err = search_storage()
+            .with_search_config("simple', x) --")
+            .filter(None, query, CqrsContext::default())
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, 400, "the pool would have said 'pool exhausted'");
+    }
+
     /// Pure, and therefore the part `cargo mutants` can reach: the integration tests in
     /// `tests/snapshot_read_path.rs` skip without a server, so they assert nothing under
     /// mutation.
@@ -657,9 +1294,11 @@ mod tests {
     #[test]
     fn compile_where_maps_the_field_and_binds_the_value() {
         let (sql, params) = compile_where(
-            &TitleQuery {
+            TitleQuery {
                 title: Some("Catan".into()),
-            },
+            }
+            .filter()
+            .as_ref(),
             &JsonbDataMapper,
         )
         .expect("a valid filter compiles");
@@ -674,7 +1313,8 @@ mod tests {
     #[test]
     fn compile_where_is_empty_when_the_query_filters_nothing() {
         let (sql, params) =
-            compile_where(&TitleQuery::default(), &JsonbDataMapper).expect("no filter");
+            compile_where(TitleQuery::default().filter().as_ref(), &JsonbDataMapper)
+                .expect("no filter");
         assert!(sql.is_empty(), "got: {sql}");
         assert!(params.is_empty());
     }
@@ -707,15 +1347,21 @@ mod tests {
             PostgresStorage::with_pool(FailingPool, "child", "children");
 
         let err = storage
-            .build_filter(&TitleQuery::default(), &None)
+            .build_filter(None, &None, None, None)
             .expect_err("a child view needs its parent id");
         assert_eq!(err.code, "GENERIC_VALIDATION_FAILED");
 
         let (sql, params) = storage
-            .build_filter(&TitleQuery::default(), &Some("p1".into()))
+            .build_filter(None, &Some("p1".into()), None, None)
             .expect("with the parent it compiles");
         assert_eq!(sql, "parent_id = $1");
         assert_eq!(params.len(), 1);
+
+        let (sql, params) = storage
+            .build_filter(None, &Some("p1".into()), Some("acme"), None)
+            .expect("with the tenant too");
+        assert_eq!(sql, "(tenant_id = $1) AND parent_id = $2");
+        assert_eq!(params.len(), 2);
     }
 
     /// A query whose sort is whatever the caller asked for — the untrusted path.
@@ -821,6 +1467,46 @@ mod tests {
         );
     }
 
+    /// The id lives in its own column, so even under the JSONB mapper a keyset condition
+    /// — which always ends in the id — has to compile to `id`, not `data->>'id'`.
+    #[test]
+    fn the_id_field_compiles_to_the_id_column() {
+        let storage: PostgresStorage<Article, ArticleQuery, JsonbDataMapper, FailingPool> =
+            PostgresStorage::with_pool_and_mapper(
+                FailingPool,
+                "article",
+                "articles",
+                JsonbDataMapper,
+            );
+        let keyset = Keyset::plan(Some(&[asc("title")]), "id", None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            order_by_clause(Some(keyset.order()), &storage.mapper()).unwrap(),
+            " ORDER BY data->>'title' ASC, id ASC"
+        );
+    }
+
+    #[tokio::test]
+    async fn a_cursor_without_a_sort_is_refused_before_touching_the_database() {
+        #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+        struct CursorQuery;
+
+        impl Query for CursorQuery {
+            fn cursor(&self) -> Option<PageCursor> {
+                Some(PageCursor::After("x".into()))
+            }
+        }
+
+        let storage: PostgresStorage<Article, CursorQuery, IdentityMapper, FailingPool> =
+            PostgresStorage::with_pool(FailingPool, "article", "articles");
+        let err = storage
+            .filter(None, CursorQuery, CqrsContext::default())
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, "GENERIC_VALIDATION_FAILED");
+    }
+
     #[test]
     fn default_type_params_keep_the_arc_client_constructors() {
         // Compile-time check: `new`/`with_mapper` still resolve to
@@ -0,0 +1,134 @@
+//! Sparse fieldsets: the top-level fields of a view a caller wants back.
+//!
+//! A [`Projection`] is applied by `Storage::filter_projected`, which returns the page's
+//! items as partial JSON objects instead of whole views. Each backend pushes it down —
+//! a key selection over the JSONB `data` column in Postgres, a projection document in
+//! MongoDB, a filter over the `data` object's entries in SurrealDB — so a wide document is cut down
+//! before it leaves the database.
+//!
+//! Fields are the view's **serialized** names, not logical names for a `FieldMapper`:
+//! every backend stores the view as it serializes, and the projection selects from
+//! that. They are top-level only; a dotted path selects nothing narrower than its root.
+
+use crate::read::sorter::validated_field_name;
+use crate::CqrsError;
+use serde_json::{Map, Value as JsonValue};
+
+/// The fields to keep, in the order asked for. Nothing is added implicitly — not even
+/// the id: a caller that needs it lists it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Projection {
+    fields: Vec<String>,
+}
+
+impl Projection {
+    #[must_use]
+    pub fn new<S: Into<String>>(fields: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            fields: fields.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    #[must_use]
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    /// Checks every name before a backend interpolates it: at least one field, each a
+    /// single identifier segment, none twice.
+    pub(crate) fn validate(&self) -> Result<(), CqrsError> {
+        if self.fields.is_empty() {
+            return Err(CqrsError::validation(
+                "a projection needs at least one field",
+            ));
+        }
+        for (i, field) in self.fields.iter().enumerate() {
+            validated_field_name("projection", field)?;
+            if field.contains('.') {
+                return Err(CqrsError::validation(format!(
+                    "projection field {field:?} is a nested path; project its top-level field"
+                )));
+            }
+            if self.fields[..i].contains(field) {
+                return Err(CqrsError::validation(format!(
+                    "projection field {field:?} is named twice"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Keeps the projected members of a serialized item. A field the item does not have
+    /// stays absent rather than becoming `null`, as in the pushed-down backends.
+    #[must_use]
+    pub fn apply(&self, item: JsonValue) -> JsonValue {
+        let JsonValue::Object(mut members) = item else {
+            return item;
+        };
+        let kept: Map<String, JsonValue> = self
+            .fields
+            .iter()
+            .filter_map(|f| members.remove_entry(f))
+            .collect();
+        JsonValue::Object(kept)
+    }
+
+    /// The projection plus `extra` fields a backend needs to read for itself — a keyset's
+    /// sort keys and id — and strips again with [`Projection::apply`] afterwards.
+    #[cfg(any(
+        feature = "postgres",
+        feature = "mongodb",
+        feature = "sqlite",
+        feature = "surrealdb"
+    ))]
+    pub(crate) fn widened<'a>(&'a self, extra: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+        let mut fields: Vec<&str> = self.fields.iter().map(String::as_str).collect();
+        for field in extra {
+            if !fields.contains(&field) {
+                fields.push(field);
+            }
+        }
+        fields
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn apply_keeps_only_the_listed_members_and_never_invents_one() {
+        let projection = Projection::new(["title", "missing"]);
+        assert_eq!(
+            projection.apply(json!({ "id": "g1", "title": "Azul", "players": 4 })),
+            json!({ "title": "Azul" })
+        );
+    }
+
+    #[test]
+    fn a_projection_is_validated_before_any_backend_sees_it() {
+        assert!(Projection::new(["id", "title"]).validate().is_ok());
+        for bad in [
+            Projection::new(Vec::<String>::new()),
+            Projection::new(["title", "title"]),
+            Projection::new(["details.publisher"]),
+            Projection::new(["x') FROM secrets--"]),
+        ] {
+            let err = bad.validate().unwrap_err();
+            assert_eq!(err.status, 400, "{bad:?}");
+        }
+    }
+
+    #[cfg(any(
+        feature = "postgres",
+        feature = "mongodb",
+        feature = "sqlite",
+        feature = "surrealdb"
+    ))]
+    #[test]
+    fn widened_adds_each_extra_field_once() {
+        let projection = Projection::new(["title"]);
+        assert_eq!(projection.widened(["id", "title", "id"]), ["title", "id"]);
+    }
+}
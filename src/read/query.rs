@@ -1,4 +1,4 @@
-use crate::read::Sorter;
+use crate::read::{PageCursor, Sorter};
 use crate::{MaybeSend, MaybeSync};
 use serde::Serialize;
 use serde_json::Value as JsonValue;
@@ -64,8 +64,8 @@ pub struct Pagination {
 /// `_q=label==x` filters on `label`. `#[serde(rename)]` is fine: it moves both sides.
 ///
 /// A field named after a param the extractor owns — `_q`, `skip`, `limit`, `page`,
-/// `page_size`, `pageSize`, `sort` — is dropped from the set too, and from the published
-/// params: the extractor eats the value, so the field is unreachable either way.
+/// `page_size`, `pageSize`, `sort`, `_search` — is dropped from the set too, and from the
+/// published params: the extractor eats the value, so the field is unreachable either way.
 pub trait Query: Debug + Serialize + MaybeSend + MaybeSync {
     /// Returns a filter derived from the struct's serializable fields.
     /// Override when you need operators other than `==` or custom field names.
@@ -79,6 +79,18 @@ pub trait Query: Debug + Serialize + MaybeSend + MaybeSync {
         None
     }
 
+    /// Keyset position for the storage layer: the `nextCursor` or `prevCursor` of a
+    /// previous page. Defaults to `None` (offset paging, or rely on `CqrsHttpQuery`'s
+    /// `after` / `before` params).
+    ///
+    /// A cursor replaces `skip` — it *is* the start of the page — and needs a sort in
+    /// effect, since it is a position along that sort: with none, the storage refuses it
+    /// with a validation error rather than ignore it. It is also tied to the sort it was
+    /// issued under, so a token replayed under another one is refused the same way.
+    fn cursor(&self) -> Option<PageCursor> {
+        None
+    }
+
     /// Static default sort for this view type, applied when no explicit sort
     /// is requested (neither HTTP `sort` param nor `sort()` override).
     /// Use this instead of `sort()` when the sort is unconditional.
@@ -141,6 +153,41 @@ pub trait Query: Debug + Serialize + MaybeSend + MaybeSync {
     fn sortable_fields(&self) -> Vec<&str> {
         vec![]
     }
+
+    /// The complete set of field names a caller may group on or aggregate over, through
+    /// the codex router's `_aggregate` route.
+    ///
+    /// **Empty — the default — means this view offers no aggregation**, and the route
+    /// refuses every request with `422`, as [`Query::sortable_fields`] does for `sort`.
+    /// A listing that returns a field does not make it cheap to group on: declare what
+    /// the storage can group efficiently.
+    ///
+    /// It does not constrain [`crate::read::storage::Storage::aggregate`] called from
+    /// Rust, which is not a caller.
+    fn aggregatable_fields(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    /// Text to search for across [`Query::searchable_fields`]: an item matches when every
+    /// word of it occurs in at least one of them, and the page is ordered by relevance,
+    /// the query's sort breaking ties. Defaults to `None` (no search, or rely on
+    /// `CqrsHttpQuery`'s `_search` param).
+    ///
+    /// A search page has no keyset — relevance is not a value a cursor can carry — so a
+    /// cursor sent with one is refused. Each backend needs its own index for it; see the
+    /// storages' `ensure_search_index`.
+    fn search(&self) -> Option<&str> {
+        None
+    }
+
+    /// The fields [`Query::search`] looks in. Unlike `sortable_fields` this is not only a
+    /// gate but the search itself: the words are looked for in these fields and no other.
+    ///
+    /// **Empty — the default — means this view offers no search**: `_search` is refused
+    /// with `422`, and a storage handed a search anyway refuses it with `400`.
+    fn searchable_fields(&self) -> Vec<&str> {
+        vec![]
+    }
 }
 
 /// Converts every non-`null` scalar field of a serializable struct into an
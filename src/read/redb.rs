@@ -0,0 +1,619 @@
+use crate::read::inmemory::{aggregate_views, filter_views, parent_matches};
+use crate::read::query::Query;
+use crate::read::storage::{HasId, Storage, StorageError};
+use crate::read::{AggregateRow, Aggregation, Paged};
+use crate::redb::{map_redb_error, ping, read_table};
+use crate::snapshot::Snapshot;
+use crate::{Aggregate, CqrsContext, CqrsError, MaybeSend, MaybeSync, Tenancy, TenantScope};
+use redb::{Database, ReadOnlyTable, TableDefinition};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+pub use crate::redb::{open, open_in_memory};
+
+/// A table of views, or of snapshots: each as JSON under its tenant and id. The tenant is
+/// `""` unless the storage is under [`Tenancy::Column`].
+type ViewKey = (&'static str, &'static str);
+
+fn views(name: &str) -> TableDefinition<'_, ViewKey, &'static str> {
+    TableDefinition::new(name)
+}
+
+/// Deserializes every row of `tenant` in `table`, in id order.
+fn tenant_rows<T: DeserializeOwned>(
+    table: &ReadOnlyTable<ViewKey, &'static str>,
+    tenant: &str,
+) -> Result<Vec<T>, CqrsError> {
+    let mut rows = Vec::new();
+    for row in table.range((tenant, "")..).map_err(map_redb_error)? {
+        let (key, json) = row.map_err(map_redb_error)?;
+        if key.value().0 != tenant {
+            break;
+        }
+        rows.push(serde_json::from_str(json.value()).map_err(CqrsError::serialization_error)?);
+    }
+    Ok(rows)
+}
+
+/// Deserializes the row of `id` in `tenant`, if `table` has one.
+fn tenant_row<T: DeserializeOwned>(
+    database: &Database,
+    table: &str,
+    tenant: &str,
+    id: &str,
+) -> Result<Option<T>, CqrsError> {
+    let json = read_table(
+        database,
+        table,
+        |table: ReadOnlyTable<ViewKey, &'static str>| {
+            Ok(table
+                .get((tenant, id))
+                .map_err(map_redb_error)?
+                .map(|json| json.value().to_string()))
+        },
+    )?
+    .flatten();
+    json.map(|json| serde_json::from_str(&json).map_err(CqrsError::serialization_error))
+        .transpose()
+}
+
+/// Read-side storage over a redb table, the view serialized as JSON under its id.
+///
+/// A key-value store has no query language to compile a filter into: every query reads
+/// the tenant's views and evaluates itself against them exactly as
+/// [`InMemoryStorage`](crate::read::inmemory::InMemoryStorage) does — same operators,
+/// same `null` handling, same search ranking, same aggregation. That is a scan per
+/// query, which suits the embedded deployments redb is for; a view with many thousands
+/// of rows belongs in a database backend.
+///
+/// The table is created by the first save. Under [`Tenancy::Column`] the tenant leads
+/// the key; under [`Tenancy::Table`] each tenant has a `{table}_{tenant}` of its own.
+#[derive(Debug, Clone)]
+pub struct RedbStorage<V, Q> {
+    _phantom: PhantomData<(V, Q)>,
+    database: Arc<Database>,
+    type_name: String,
+    table_name: String,
+    scope: TenantScope,
+}
+
+impl<V, Q> RedbStorage<V, Q> {
+    #[must_use]
+    pub fn new(database: Arc<Database>, type_name: &str, table_name: &str) -> Self {
+        Self {
+            _phantom: PhantomData,
+            database,
+            type_name: type_name.to_string(),
+            table_name: table_name.to_string(),
+            scope: TenantScope::default(),
+        }
+    }
+
+    /// Keeps each tenant's views apart: keys led by the tenant ([`Tenancy::Column`]), or
+    /// a `{table}_{tenant}` table per tenant ([`Tenancy::Table`]).
+    #[must_use]
+    pub fn with_tenancy(mut self, tenancy: Tenancy) -> Self {
+        self.scope = TenantScope::new(tenancy);
+        self
+    }
+
+    /// The views of the tenant in `scope`, in id order.
+    fn load(&self, scope: &TenantScope) -> Result<Vec<V>, CqrsError>
+    where
+        V: DeserializeOwned,
+    {
+        let tenant = scope.column()?.unwrap_or_default();
+        Ok(
+            read_table(&self.database, &scope.table(&self.table_name)?, |table| {
+                tenant_rows(&table, tenant)
+            })?
+            .unwrap_or_default(),
+        )
+    }
+}
+
+cqrs_async_trait! {
+impl<V, Q> Storage<V, Q> for RedbStorage<V, Q>
+where
+    V: Debug + Clone + Default + Serialize + DeserializeOwned + MaybeSend + MaybeSync + HasId,
+    Q: Clone + Debug + MaybeSend + MaybeSync + Query,
+{
+    fn type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    fn id_field(&self) -> Option<&str> {
+        Some(V::field_id())
+    }
+
+    async fn health(&self) -> Result<(), CqrsError> {
+        ping(&self.database)
+    }
+
+    async fn filter(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        context: CqrsContext,
+    ) -> Result<Paged<V>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        filter_views(
+            &self.type_name,
+            V::field_id(),
+            &self.load(&scope)?,
+            |view| parent_matches(view, parent_id.as_deref()),
+            &query,
+        )
+    }
+
+    async fn find_by_id(
+        &self,
+        parent_id: Option<String>,
+        id: &str,
+        context: CqrsContext,
+    ) -> Result<Option<V>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        // Checked before the read, so a child view read without its parent is refused
+        // whether or not the id exists — as the database backends do.
+        if V::parent_field_id().is_some() && parent_id.is_none() {
+            return Err(CqrsError::validation(
+                StorageError::MissingParentId.to_string(),
+            ));
+        }
+        let view: Option<V> = tenant_row(
+            &self.database,
+            &scope.table(&self.table_name)?,
+            scope.column()?.unwrap_or_default(),
+            id,
+        )?;
+        match view {
+            Some(view) if parent_matches(&view, parent_id.as_deref())? => Ok(Some(view)),
+            _ => Ok(None),
+        }
+    }
+
+    async fn save(&self, entity: V, context: CqrsContext) -> Result<(), CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        if V::parent_field_id().is_some() && entity.parent_id().is_none() {
+            return Err(CqrsError::validation(
+                StorageError::MissingParentId.to_string(),
+            ));
+        }
+        let json = serde_json::to_string(&entity).map_err(CqrsError::serialization_error)?;
+        let tenant = scope.column()?.unwrap_or_default();
+        let transaction = self.database.begin_write().map_err(map_redb_error)?;
+        transaction
+            .open_table(views(&scope.table(&self.table_name)?))
+            .and_then(|mut table| {
+                table
+                    .insert(
+                        (tenant, entity.id()),
+                        json.as_str(),
+                    )
+                    .map(drop)
+                    .map_err(Into::into)
+            })
+            .map_err(map_redb_error)?;
+        transaction.commit().map_err(map_redb_error)
+    }
+
+    async fn aggregate(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        aggregation: Aggregation,
+        context: CqrsContext,
+    ) -> Result<Vec<AggregateRow>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        aggregate_views(
+            &self.load(&scope)?,
+            |view| parent_matches(view, parent_id.as_deref()),
+            &query,
+            &aggregation,
+        )
+    }
+}
+}
+
+/// Rejected when a caller hands a snapshot storage a parent id.
+///
+/// The other backends say the same thing; a caller swapping backends should not have to
+/// learn a second phrasing for the same refusal.
+const NO_PARENT_ON_SNAPSHOT: &str =
+    "a snapshot table has no parent column, so a parent id cannot be filtered on";
+
+/// Rejected when a caller hands a snapshot storage a keyset cursor.
+const NO_CURSOR_ON_SNAPSHOT: &str =
+    "a snapshot table does not support cursor pagination; use skip/limit";
+
+/// Rejected when a caller hands a snapshot storage a text search.
+const NO_SEARCH_ON_SNAPSHOT: &str =
+    "a snapshot table does not support text search; project a view to search";
+
+/// Read-side storage over the event store's **snapshot** table, as
+/// [`RedbPersist`](crate::es::redb::RedbPersist) writes it. Filters and sorts apply to
+/// the aggregate itself, as they would to a view in [`RedbStorage`]. Writing stays
+/// unsupported — the event store owns this table.
+#[derive(Debug, Clone)]
+pub struct RedbFromSnapshotStorage<A, Q> {
+    _phantom: PhantomData<(A, Q)>,
+    database: Arc<Database>,
+    snapshot_table: String,
+    scope: TenantScope,
+}
+
+impl<A, Q> RedbFromSnapshotStorage<A, Q> {
+    /// `snapshot_table` is what `RedbPersist::snapshot_table_name()` returns.
+    #[must_use]
+    pub fn new(database: Arc<Database>, snapshot_table: &str) -> Self {
+        Self {
+            _phantom: PhantomData,
+            database,
+            snapshot_table: snapshot_table.to_string(),
+            scope: TenantScope::default(),
+        }
+    }
+
+    /// The [`Tenancy`] the `RedbPersist` writing the snapshots was configured with.
+    #[must_use]
+    pub fn with_tenancy(mut self, tenancy: Tenancy) -> Self {
+        self.scope = TenantScope::new(tenancy);
+        self
+    }
+}
+
+cqrs_async_trait! {
+impl<A, Q> Storage<A, Q> for RedbFromSnapshotStorage<A, Q>
+where
+    A: Aggregate,
+    Q: Clone + Debug + MaybeSend + MaybeSync + Query,
+{
+    fn type_name(&self) -> &str {
+        A::TYPE
+    }
+
+    async fn health(&self) -> Result<(), CqrsError> {
+        ping(&self.database)
+    }
+
+    async fn filter(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        context: CqrsContext,
+    ) -> Result<Paged<A>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        if parent_id.is_some() {
+            return Err(CqrsError::validation(NO_PARENT_ON_SNAPSHOT));
+        }
+        if query.cursor().is_some() {
+            return Err(CqrsError::validation(NO_CURSOR_ON_SNAPSHOT));
+        }
+        if query.search().is_some() {
+            return Err(CqrsError::validation(NO_SEARCH_ON_SNAPSHOT));
+        }
+
+        let tenant = scope.column()?.unwrap_or_default();
+        let aggregates: Vec<A> = read_table(
+            &self.database,
+            &scope.table(&self.snapshot_table)?,
+            |table| tenant_rows::<Snapshot<A>>(&table, tenant),
+        )?
+        .unwrap_or_default()
+        .into_iter()
+        .map(|snapshot| snapshot.state)
+        .collect();
+        // No cursor reaches here, so the id field is never read.
+        filter_views(A::TYPE, "aggregateId", &aggregates, |_| Ok(true), &query)
+    }
+
+    async fn find_by_id(
+        &self,
+        parent_id: Option<String>,
+        id: &str,
+        context: CqrsContext,
+    ) -> Result<Option<A>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        if parent_id.is_some() {
+            return Err(CqrsError::validation(NO_PARENT_ON_SNAPSHOT));
+        }
+        let snapshot: Option<Snapshot<A>> = tenant_row(
+            &self.database,
+            &scope.table(&self.snapshot_table)?,
+            scope.column()?.unwrap_or_default(),
+            id,
+        )?;
+        Ok(snapshot.map(|snapshot| snapshot.state))
+    }
+
+    async fn save(&self, _entity: A, _context: CqrsContext) -> Result<(), CqrsError> {
+        Err(CqrsError::database_error(StorageError::UnsupportedMethod(
+            "SnapshotStorage#save".to_string(),
+        )))
+    }
+}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::es::redb::RedbPersist;
+    use crate::es::storage::EventStoreStorage;
+    use crate::read::query::Pagination;
+    use crate::read::sorter::{SortDirection, Sorter};
+    use crate::read::{Metric, PageCursor};
+    use crate::testing::{TestAggregate, TestEvent};
+    use rest_sql::RestSql;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+    struct Article {
+        id: String,
+        title: String,
+        author: String,
+        words: i64,
+    }
+
+    impl HasId for Article {
+        fn field_id() -> &'static str {
+            "id"
+        }
+        fn id(&self) -> &str {
+            &self.id
+        }
+        fn parent_field_id() -> Option<&'static str> {
+            None
+        }
+        fn parent_id(&self) -> Option<&str> {
+            None
+        }
+    }
+
+    /// A query spelled as RSQL, the way `_q` reaches a storage.
+    #[derive(Debug, Clone, Default, Serialize)]
+    struct ArticleQuery {
+        #[serde(skip)]
+        rsql: Option<&'static str>,
+        #[serde(skip)]
+        sort: Vec<Sorter>,
+        #[serde(skip)]
+        limit: Option<i64>,
+        #[serde(skip)]
+        cursor: Option<PageCursor>,
+        #[serde(skip)]
+        search: Option<&'static str>,
+    }
+
+    impl Query for ArticleQuery {
+        fn filter(&self) -> Option<RestSql> {
+            self.rsql.map(|rsql| RestSql::new(rsql).unwrap())
+        }
+        fn pagination(&self) -> Option<Pagination> {
+            Some(Pagination {
+                skip: None,
+                limit: self.limit,
+            })
+        }
+        fn cursor(&self) -> Option<PageCursor> {
+            self.cursor.clone()
+        }
+        fn sort(&self) -> Option<Vec<Sorter>> {
+            Some(self.sort.clone())
+        }
+        fn search(&self) -> Option<&str> {
+            self.search
+        }
+        fn searchable_fields(&self) -> Vec<&str> {
+            vec!["title", "author"]
+        }
+    }
+
+    fn asc(field: &str) -> Sorter {
+        Sorter {
+            field: field.to_string(),
+            direction: SortDirection::Asc,
+        }
+    }
+
+    fn article(id: &str, title: &str, author: &str, words: i64) -> Article {
+        Article {
+            id: id.to_string(),
+            title: title.to_string(),
+            author: author.to_string(),
+            words,
+        }
+    }
+
+    async fn storage() -> RedbStorage<Article, ArticleQuery> {
+        let storage = RedbStorage::new(open_in_memory().unwrap(), "article", "articles");
+        for article in [
+            article("a1", "Catan", "Teuber", 1200),
+            article("a2", "Carcassonne", "Wrede", 800),
+            article("a3", "Cities and Knights of Catan", "Teuber", 3000),
+            article("a4", "Azul", "Kiesling", 500),
+        ] {
+            storage.save(article, CqrsContext::default()).await.unwrap();
+        }
+        storage
+    }
+
+    fn ids(page: &Paged<Article>) -> Vec<&str> {
+        page.items.iter().map(|a| a.id.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn a_saved_view_reads_back_and_is_replaced_on_save() {
+        let empty: RedbStorage<Article, ArticleQuery> =
+            RedbStorage::new(open_in_memory().unwrap(), "article", "articles");
+        let page = empty
+            .filter(None, ArticleQuery::default(), CqrsContext::default())
+            .await
+            .unwrap();
+        assert_eq!(page.total, 0, "no table yet reads as empty");
+
+        let storage = storage().await;
+        let renamed = article("a1", "Catan (5th edition)", "Teuber", 1200);
+        storage
+            .save(renamed.clone(), CqrsContext::default())
+            .await
+            .unwrap();
+        let found = storage
+            .find_by_id(None, "a1", CqrsContext::default())
+            .await
+            .unwrap();
+        assert_eq!(found, Some(renamed));
+        assert_eq!(
+            storage
+                .find_by_id(None, "a9", CqrsContext::default())
+                .await
+                .unwrap(),
+            None
+        );
+        assert!(storage.health().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn queries_are_evaluated_as_in_memory() {
+        let storage = storage().await;
+        let query = ArticleQuery {
+            rsql: Some("author==Teuber,words=lt=600"),
+            sort: vec![asc("words")],
+            ..Default::default()
+        };
+        let page = storage
+            .filter(None, query, CqrsContext::default())
+            .await
+            .unwrap();
+        assert_eq!(ids(&page), ["a4", "a1", "a3"]);
+
+        let query = ArticleQuery {
+            search: Some("catan"),
+            ..Default::default()
+        };
+        let page = storage
+            .filter(None, query, CqrsContext::default())
+            .await
+            .unwrap();
+        assert_eq!(page.total, 2);
+
+        let query = ArticleQuery {
+            sort: vec![asc("title")],
+            limit: Some(2),
+            ..Default::default()
+        };
+        let first = storage
+            .filter(None, query.clone(), CqrsContext::default())
+            .await
+            .unwrap();
+        assert_eq!(ids(&first), ["a4", "a2"]);
+        let next = ArticleQuery {
+            cursor: Some(PageCursor::After(first.next_cursor.clone().unwrap())),
+            ..query
+        };
+        let second = storage
+            .filter(None, next, CqrsContext::default())
+            .await
+            .unwrap();
+        assert_eq!(ids(&second), ["a1", "a3"]);
+
+        let rows = storage
+            .aggregate(
+                None,
+                ArticleQuery::default(),
+                Aggregation {
+                    group_by: vec!["author".to_string()],
+                    metrics: vec![Metric::count(), Metric::sum("words")],
+                },
+                CqrsContext::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[1].group["author"], "Teuber");
+        assert_eq!(rows[1].metrics["sum(words)"], 4200);
+    }
+
+    #[tokio::test]
+    async fn tenants_sharing_a_table_only_see_their_own_views() {
+        let storage: RedbStorage<Article, ArticleQuery> =
+            RedbStorage::new(open_in_memory().unwrap(), "article", "articles")
+                .with_tenancy(Tenancy::Column);
+        let acting = |tenant: &str| CqrsContext::default().with_tenant(Some(tenant.to_string()));
+        storage
+            .save(article("a1", "Catan", "Teuber", 1200), acting("acme"))
+            .await
+            .unwrap();
+        storage
+            .save(article("a2", "Azul", "Kiesling", 500), acting("globex"))
+            .await
+            .unwrap();
+
+        let page = storage
+            .filter(None, ArticleQuery::default(), acting("acme"))
+            .await
+            .unwrap();
+        assert_eq!(ids(&page), ["a1"]);
+        assert!(
+            storage
+                .find_by_id(None, "a2", acting("acme"))
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn snapshots_written_by_the_persist_are_read_as_aggregates() {
+        let database = open_in_memory().unwrap();
+        let persist = RedbPersist::<TestAggregate>::new(database.clone());
+        let mut session = persist.start_session().await.unwrap();
+        for (id, name) in [("t1", "foo"), ("t2", "bar")] {
+            let mut aggregate = TestAggregate::default().with_aggregate_id(id.to_string());
+            aggregate
+                .apply(TestEvent::Created { name: name.into() })
+                .unwrap();
+            persist
+                .save_snapshot(&aggregate, 1, &mut session)
+                .await
+                .unwrap();
+        }
+        persist.close_session(session).await.unwrap();
+
+        #[derive(Debug, Clone, Default, Serialize)]
+        struct NameQuery {
+            name: Option<String>,
+        }
+        impl Query for NameQuery {}
+
+        let snapshots: RedbFromSnapshotStorage<TestAggregate, NameQuery> =
+            RedbFromSnapshotStorage::new(database, persist.snapshot_table_name());
+        let page = snapshots
+            .filter(
+                None,
+                NameQuery {
+                    name: Some("bar".into()),
+                },
+                CqrsContext::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.items[0].aggregate_id(), "t2");
+        assert!(
+            snapshots
+                .find_by_id(None, "t1", CqrsContext::default())
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert!(
+            snapshots
+                .save(TestAggregate::default(), CqrsContext::default())
+                .await
+                .is_err()
+        );
+    }
+}
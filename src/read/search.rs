@@ -0,0 +1,242 @@
+//! Full-text search: words looked for across the fields a view declares searchable.
+//!
+//! [`Query::search`] carries the text and [`Query::searchable_fields`] the fields. A
+//! storage that sees both keeps an item when **every word** of the text occurs in at
+//! least one of those fields, and orders the page by relevance — most relevant first,
+//! the query's own sort breaking ties. Each backend answers with its own engine:
+//! `tsvector` and `plainto_tsquery` in Postgres, a text index and `$text` in MongoDB,
+//! `FULLTEXT` indexes over an analyzer in SurrealDB. The in-memory storage counts the
+//! words itself, and SQLite scans the fields for them, inside longer words too.
+//!
+//! The engines agree on which items match, not on how they score them, nor on what a
+//! word is beyond the basics: each lower-cases, MongoDB also stems. A test asserting an
+//! exact relevance order belongs to one backend.
+//!
+//! Relevance is computed per request, not stored, so a search page has no keyset: a
+//! cursor sent with a search is refused, and a search page issues none. Offset paging
+//! works as usual, and `total` counts the matches.
+
+use crate::read::query::Query;
+use crate::read::sorter::validated_field_name;
+use crate::read::{Keyset, PageCursor, Sorter};
+use crate::CqrsError;
+
+/// Words beyond this many are refused rather than searched: a SurrealDB search costs a
+/// clause per word and field, and no engine ranks a paragraph usefully anyway.
+pub(crate) const MAX_SEARCH_TERMS: usize = 16;
+
+/// A search planned from a query: its words, lower-cased and deduplicated, and the
+/// fields to look in, each checked against the identifier grammar of a sort field —
+/// they are interpolated into the generated query, not bound.
+#[derive(Debug, Clone)]
+pub(crate) struct TextSearch {
+    terms: Vec<String>,
+    fields: Vec<String>,
+}
+
+impl TextSearch {
+    /// `None` when the query searches for nothing — no text, or text without a single
+    /// word in it, which is a search for everything and so no search at all.
+    pub(crate) fn plan<Q: Query>(query: &Q) -> Result<Option<Self>, CqrsError> {
+        let terms = match query.search() {
+            Some(text) => tokenize(text),
+            None => return Ok(None),
+        };
+        if terms.is_empty() {
+            return Ok(None);
+        }
+        if terms.len() > MAX_SEARCH_TERMS {
+            return Err(CqrsError::validation(format!(
+                "a search takes at most {MAX_SEARCH_TERMS} words, got {}",
+                terms.len()
+            )));
+        }
+        let fields = query.searchable_fields();
+        if fields.is_empty() {
+            return Err(CqrsError::validation(
+                "this view declares no searchable field",
+            ));
+        }
+        let fields = fields
+            .into_iter()
+            .map(|f| validated_field_name("search", f).map(str::to_string))
+            .collect::<Result<_, _>>()?;
+        Ok(Some(Self { terms, fields }))
+    }
+
+    pub(crate) fn terms(&self) -> &[String] {
+        &self.terms
+    }
+
+    pub(crate) fn fields(&self) -> impl Iterator<Item = &str> {
+        self.fields.iter().map(String::as_str)
+    }
+
+    /// The words as one string, for an engine that tokenizes the text itself.
+    #[cfg(feature = "postgres")]
+    pub(crate) fn text(&self) -> String {
+        self.terms.join(" ")
+    }
+}
+
+/// The words of a text, in order and repeated as they occur: runs of letters and
+/// digits, lower-cased.
+pub(crate) fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// The words of a search text, each once.
+fn tokenize(text: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for word in words(text) {
+        if !terms.contains(&word) {
+            terms.push(word);
+        }
+    }
+    terms
+}
+
+/// [`Keyset::plan`], except under a search: relevance leads the order and is not a
+/// value a token could carry, so there is no keyset, and a cursor is refused rather than
+/// ignored.
+pub(crate) fn plan_keyset(
+    sort: Option<&[Sorter]>,
+    id_field: &str,
+    cursor: Option<PageCursor>,
+    search: Option<&TextSearch>,
+) -> Result<Option<Keyset>, CqrsError> {
+    match (search, cursor) {
+        (Some(_), Some(_)) => Err(CqrsError::validation(
+            "cursor: a search page is ordered by relevance and has no cursor; use skip/limit",
+        )),
+        (Some(_), None) => Ok(None),
+        (None, cursor) => Keyset::plan(sort, id_field, cursor),
+    }
+}
+
+/// Puts a relevance expression, descending, ahead of an ` ORDER BY` clause built by
+/// `order_by_clause` — which may be empty, when no sort is in effect.
+#[cfg(any(feature = "postgres", feature = "sqlite", feature = "surrealdb"))]
+pub(crate) fn relevance_first(rank: &str, order_by: &str) -> String {
+    match order_by.strip_prefix(" ORDER BY ") {
+        Some(rest) => format!(" ORDER BY {rank} DESC, {rest}"),
+        None => format!(" ORDER BY {rank} DESC"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+
+    #[derive(Debug, Serialize)]
+    struct GameSearch {
+        #[serde(skip)]
+        text: Option<String>,
+        #[serde(skip)]
+        fields: Vec<&'static str>,
+    }
+
+    impl Query for GameSearch {
+        fn search(&self) -> Option<&str> {
+            self.text.as_deref()
+        }
+
+        fn searchable_fields(&self) -> Vec<&str> {
+            self.fields.clone()
+        }
+    }
+
+    fn search(text: &str, fields: &[&'static str]) -> GameSearch {
+        GameSearch {
+            text: Some(text.to_string()),
+            fields: fields.to_vec(),
+        }
+    }
+
+    #[test]
+    fn words_are_lower_cased_split_on_anything_else_and_kept_once() {
+        assert_eq!(
+            tokenize("Ticket to Ride: Europe, ticket-2 Été"),
+            ["ticket", "to", "ride", "europe", "2", "été"]
+        );
+        assert!(tokenize(" ,;- ").is_empty());
+    }
+
+    #[test]
+    fn a_search_without_a_word_is_no_search() {
+        assert!(
+            TextSearch::plan(&search("  -- ", &["title"]))
+                .unwrap()
+                .is_none()
+        );
+        let none = GameSearch {
+            text: None,
+            fields: vec![],
+        };
+        assert!(TextSearch::plan(&none).unwrap().is_none());
+    }
+
+    #[test]
+    fn a_search_needs_declared_and_well_formed_fields() {
+        let err = TextSearch::plan(&search("azul", &[])).unwrap_err();
+        assert_eq!(err.status, 400);
+        let err = TextSearch::plan(&search("azul", &["title); DROP"])).unwrap_err();
+        assert_eq!(err.status, 400);
+        let plan = TextSearch::plan(&search("azul", &["title", "details.summary"]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            plan.fields().collect::<Vec<_>>(),
+            ["title", "details.summary"]
+        );
+    }
+
+    #[test]
+    fn too_many_words_are_refused() {
+        let text = (0..=MAX_SEARCH_TERMS)
+            .map(|i| format!("w{i} "))
+            .collect::<String>();
+        let err = TextSearch::plan(&search(&text, &["title"])).unwrap_err();
+        assert_eq!(err.status, 400);
+    }
+
+    #[test]
+    fn a_search_plans_no_keyset_and_refuses_a_cursor() {
+        let plan = TextSearch::plan(&search("azul", &["title"])).unwrap();
+        let sort = [Sorter {
+            field: "title".into(),
+            direction: crate::read::SortDirection::Asc,
+        }];
+        assert!(
+            plan_keyset(Some(&sort), "id", None, plan.as_ref())
+                .unwrap()
+                .is_none()
+        );
+        let err = plan_keyset(
+            Some(&sort),
+            "id",
+            Some(PageCursor::After("x".into())),
+            plan.as_ref(),
+        )
+        .unwrap_err();
+        assert_eq!(err.status, 400);
+        assert!(
+            plan_keyset(Some(&sort), "id", None, None)
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[cfg(any(feature = "postgres", feature = "sqlite", feature = "surrealdb"))]
+    #[test]
+    fn relevance_leads_the_order_and_the_sort_breaks_ties() {
+        assert_eq!(relevance_first("r", ""), " ORDER BY r DESC");
+        assert_eq!(
+            relevance_first("r", " ORDER BY title ASC"),
+            " ORDER BY r DESC, title ASC"
+        );
+    }
+}
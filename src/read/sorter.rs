@@ -37,22 +37,28 @@ impl Sorter {
     /// After mapping, a JSONB mapper yields `data->>'field'`, which is no longer an
     /// identifier: a post-mapping check would reject its own legitimate output.
     pub fn validated_field(&self) -> Result<&str, CqrsError> {
-        let field = self.field.as_str();
-        let valid = !field.is_empty()
-            && field.split('.').all(|segment| {
-                let mut chars = segment.chars();
-                matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
-                    && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
-            });
-
-        if valid {
-            Ok(field)
-        } else {
-            Err(CqrsError::validation(format!(
-                "sort field {field:?} is not a valid field name: expected `.`-separated \
-                 segments of [A-Za-z_][A-Za-z0-9_]*"
-            )))
-        }
+        validated_field_name("sort", &self.field)
+    }
+}
+
+/// The grammar behind [`Sorter::validated_field`], for every other place a field name is
+/// interpolated rather than bound — a group-by key or an aggregated field. `kind` names
+/// the role in the error: `sort field "…" is not a valid field name`.
+pub(crate) fn validated_field_name<'a>(kind: &str, field: &'a str) -> Result<&'a str, CqrsError> {
+    let valid = !field.is_empty()
+        && field.split('.').all(|segment| {
+            let mut chars = segment.chars();
+            matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        });
+
+    if valid {
+        Ok(field)
+    } else {
+        Err(CqrsError::validation(format!(
+            "{kind} field {field:?} is not a valid field name: expected `.`-separated \
+             segments of [A-Za-z_][A-Za-z0-9_]*"
+        )))
     }
 }
 
@@ -61,10 +67,10 @@ impl Sorter {
 /// result unconditionally, whether or not a sort is in effect.
 ///
 /// Fallible, and that is the point: the clause is interpolated into the query string,
-/// so every field goes through [`Sorter::validated_field`] first. This is the sink both
-/// SQL-shaped backends converge on — a `Sorter` handed straight to `Storage::filter`
+/// so every field goes through [`Sorter::validated_field`] first. This is the sink every
+/// SQL-shaped backend converges on — a `Sorter` handed straight to `Storage::filter`
 /// passes through here too, not only one that came off an HTTP param.
-#[cfg(any(feature = "postgres", feature = "surrealdb"))]
+#[cfg(any(feature = "postgres", feature = "sqlite", feature = "surrealdb"))]
 pub(crate) fn order_by_clause(
     sort: Option<Vec<Sorter>>,
     mapper: &impl rest_sql::FieldMapper,
@@ -171,7 +177,7 @@ mod tests {
 
     /// The clause builder's tests carry the same gate as the builder itself, and reuse
     /// the parent module's `sorter()` rather than keeping a second copy of it.
-    #[cfg(any(feature = "postgres", feature = "surrealdb"))]
+    #[cfg(any(feature = "postgres", feature = "sqlite", feature = "surrealdb"))]
     mod order_by_clause_tests {
         use super::{sorter, *};
         use rest_sql::IdentityMapper;
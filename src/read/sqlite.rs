@@ -0,0 +1,1318 @@
+use crate::read::page_order::warn_if_page_order_undefined;
+use crate::read::query::Query;
+use crate::read::search::{plan_keyset, relevance_first};
+use crate::read::sorter::{order_by_clause, validated_field_name};
+use crate::read::storage::{HasId, Storage, StorageError};
+use crate::read::{AggregateRow, Aggregation, Keyset, MetricFunction, Paged, Projection, TextSearch};
+use crate::sqlite::{map_sqlite_error, SharedConnection};
+use crate::{Aggregate, CqrsContext, CqrsError, TENANT_COLUMN, Tenancy, TenantScope};
+use rest_sql::{Ast, Constraint, FieldMapper, Operator, RestSql, Value};
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params_from_iter, Connection, OptionalExtension, Row};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::borrow::Cow;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+/// Maps a logical field name onto a member of the `data` column, through JSON1's
+/// `json_extract`.
+///
+/// Unlike Postgres' `->>`, `json_extract` keeps the JSON type — an integer comes back an
+/// integer, `true` comes back `1` — so `counter==3` compares numbers, and a filter works
+/// on the snapshot table as well as on a view. A dotted field reaches into a nested
+/// object: `author.name` reads `$.author.name`.
+///
+/// Interpolating the field name is safe here because nothing unvalidated reaches a
+/// mapper: `_q` names are checked against the query struct and `sort` names against
+/// [`Sorter::validated_field`](crate::read::Sorter::validated_field).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonDataMapper;
+
+impl FieldMapper for JsonDataMapper {
+    fn map<'a>(&self, field: &'a str) -> Cow<'a, str> {
+        Cow::Owned(format!("json_extract(data, '$.{field}')"))
+    }
+}
+
+/// Sends the view's id field to the `id` column, and every other field to the view's own
+/// mapper: `save` moves the id out of `data` and into the primary key, where a keyset
+/// condition — which always ends in the id — has to find it.
+#[derive(Debug, Clone)]
+struct IdColumnMapper<'a, M> {
+    id_field: &'static str,
+    inner: &'a M,
+}
+
+impl<M: FieldMapper> FieldMapper for IdColumnMapper<'_, M> {
+    fn map<'a>(&self, field: &'a str) -> Cow<'a, str> {
+        if field == self.id_field {
+            Cow::Borrowed("id")
+        } else {
+            self.inner.map(field)
+        }
+    }
+}
+
+/// A `WHERE` fragment — empty for none — and the parameters it binds, numbered `?1`,
+/// `?2`, … in order.
+type Where = (String, Vec<SqlValue>);
+
+/// Binds `value` as the next numbered parameter and returns its placeholder.
+fn bind(params: &mut Vec<SqlValue>, value: SqlValue) -> String {
+    params.push(value);
+    format!("?{}", params.len())
+}
+
+/// Compiles an RSQL filter into a `WHERE` fragment and its bound parameters.
+///
+/// rest-sql-drivers has no SQLite driver, so this is the Postgres one's shape with
+/// SQLite's operators. SQLite's `LIKE` ignores ASCII case, so `=like=` compiles to `GLOB`,
+/// which does not, and `=ilike=` to `LIKE`; in both, `*` is the RSQL wildcard.
+fn compile_where<M: FieldMapper>(filter: Option<&RestSql>, mapper: &M) -> Where {
+    let mut params = Vec::new();
+    let sql = filter
+        .map(|rsql| compile_node(rsql.ast(), mapper, &mut params))
+        .unwrap_or_default();
+    (sql, params)
+}
+
+fn compile_node(node: &Ast, mapper: &impl FieldMapper, params: &mut Vec<SqlValue>) -> String {
+    let (children, joint) = match node {
+        Ast::And(children) => (children, " AND "),
+        Ast::Or(children) => (children, " OR "),
+        Ast::Constraint(c) => return compile_constraint(c, mapper, params),
+    };
+    let parts: Vec<_> = children
+        .iter()
+        .map(|child| compile_node(child, mapper, params))
+        .collect();
+    format!("({})", parts.join(joint))
+}
+
+fn compile_constraint(
+    c: &Constraint,
+    mapper: &impl FieldMapper,
+    params: &mut Vec<SqlValue>,
+) -> String {
+    let col = mapper.map(&c.field);
+    let list = || match &c.value {
+        Value::List(values) => values.as_slice(),
+        other => std::slice::from_ref(other),
+    };
+    match &c.operator {
+        Operator::Null => format!("{col} IS NULL"),
+        Operator::NotNull => format!("{col} IS NOT NULL"),
+        Operator::In | Operator::Out => {
+            let placeholders: Vec<_> = list().iter().map(|v| bind(params, scalar(v))).collect();
+            let not = if matches!(c.operator, Operator::Out) {
+                "NOT "
+            } else {
+                ""
+            };
+            format!("{col} {not}IN ({})", placeholders.join(", "))
+        }
+        Operator::Between => {
+            let values = list();
+            let low = bind(params, scalar(&values[0]));
+            let high = bind(params, scalar(values.get(1).unwrap_or(&values[0])));
+            format!("{col} BETWEEN {low} AND {high}")
+        }
+        Operator::Like => {
+            let pattern = bind(params, pattern(&c.value, glob_pattern));
+            format!("{col} GLOB {pattern}")
+        }
+        Operator::Ilike => {
+            let pattern = bind(params, pattern(&c.value, like_pattern));
+            format!("{col} LIKE {pattern}")
+        }
+        op => {
+            let value = bind(params, scalar(&c.value));
+            let op = match op {
+                Operator::Eq => "=",
+                Operator::Neq => "<>",
+                Operator::Lt => "<",
+                Operator::Lte => "<=",
+                Operator::Gt => ">",
+                _ => ">=",
+            };
+            format!("{col} {op} {value}")
+        }
+    }
+}
+
+fn scalar(v: &Value) -> SqlValue {
+    match v {
+        Value::Null | Value::List(_) => SqlValue::Null,
+        Value::Bool(b) => SqlValue::Integer(i64::from(*b)),
+        Value::Int(i) => SqlValue::Integer(*i),
+        Value::Float(f) => SqlValue::Real(*f),
+        Value::String(s) | Value::Date(s) | Value::DateTime(s) => SqlValue::Text(s.clone()),
+    }
+}
+
+fn pattern(v: &Value, translate: fn(&str) -> String) -> SqlValue {
+    match v {
+        Value::String(s) => SqlValue::Text(translate(s)),
+        other => scalar(other),
+    }
+}
+
+/// An RSQL `LIKE` pattern as a `GLOB` one: `*` and `%` match any run, `_` one character,
+/// and `GLOB`'s own `?` and `[` are matched literally.
+fn glob_pattern(pattern: &str) -> String {
+    pattern
+        .chars()
+        .map(|c| match c {
+            '%' => "*".to_string(),
+            '_' => "?".to_string(),
+            '?' => "[?]".to_string(),
+            '[' => "[[]".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// An RSQL `LIKE` pattern as an SQL one: `*` becomes `%`.
+fn like_pattern(pattern: &str) -> String {
+    pattern.replace('*', "%")
+}
+
+/// Narrows a `WHERE` fragment to `tenant`, under [`Tenancy::Column`].
+fn tenant_filter((where_sql, mut params): Where, tenant: Option<&str>) -> Where {
+    let Some(tenant) = tenant else {
+        return (where_sql, params);
+    };
+    let matched = format!(
+        "{TENANT_COLUMN} = {}",
+        bind(&mut params, SqlValue::Text(tenant.to_string()))
+    );
+    (and(where_sql, matched), params)
+}
+
+/// `where_sql AND condition`, or `condition` alone when there is no `WHERE` yet.
+fn and(where_sql: String, condition: String) -> String {
+    if where_sql.trim().is_empty() {
+        condition
+    } else {
+        format!("({where_sql}) AND {condition}")
+    }
+}
+
+fn where_clause(where_sql: &str) -> String {
+    if where_sql.trim().is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", where_sql)
+    }
+}
+
+/// The count and the page carry a `WHERE` each: a keyset page narrows the rows it reads
+/// past the cursor, but `total` still counts the whole filtered set.
+struct PagedSelect<'a> {
+    /// `*`, or the view storage's projected `id, … AS data`.
+    columns: &'a str,
+    table: &'a str,
+    count: Where,
+    page: Where,
+    order_by: &'a str,
+    offset: i64,
+    limit: i64,
+}
+
+/// Returns the decoded rows and the total; the caller builds the [`Paged`], because
+/// only it knows whether `limit` was the page size or one past it.
+fn paged_select<T>(
+    conn: &Connection,
+    select: PagedSelect<'_>,
+    decode: impl Fn(&Row<'_>) -> Result<T, CqrsError>,
+) -> Result<(Vec<T>, i64), CqrsError> {
+    let PagedSelect {
+        columns,
+        table,
+        count: (count_where, count_params),
+        page: (page_where, mut params),
+        order_by,
+        offset,
+        limit,
+    } = select;
+
+    let count_sql = format!("SELECT COUNT(*) FROM {table}{}", where_clause(&count_where));
+    let total: i64 = conn
+        .query_row(&count_sql, params_from_iter(&count_params), |row| {
+            row.get(0)
+        })
+        .map_err(map_sqlite_error)?;
+
+    let limit = bind(&mut params, SqlValue::Integer(limit));
+    let offset = bind(&mut params, SqlValue::Integer(offset));
+    let select_sql = format!(
+        "SELECT {columns} FROM {table}{}{order_by} LIMIT {limit} OFFSET {offset}",
+        where_clause(&page_where)
+    );
+    let mut statement = conn.prepare(&select_sql).map_err(map_sqlite_error)?;
+    let mut rows = statement
+        .query(params_from_iter(&params))
+        .map_err(map_sqlite_error)?;
+    let mut items = Vec::new();
+    while let Some(row) = rows.next().map_err(map_sqlite_error)? {
+        items.push(decode(row)?);
+    }
+    Ok((items, total))
+}
+
+/// Reads a JSON text column.
+fn json_column(row: &Row<'_>, column: &str) -> Result<JsonValue, CqrsError> {
+    let text: String = row.get(column).map_err(map_sqlite_error)?;
+    serde_json::from_str(&text).map_err(CqrsError::serialization_error)
+}
+
+/// Builds the `GROUP BY` select behind `Storage::aggregate`.
+///
+/// Each row comes back as one `json_object` keyed positionally — `g0`, `m0`, … — so that
+/// a row reads the same whatever the key and metric types are. `ORDER BY` repeats the
+/// keys, ascending with `NULL` last, as Postgres sorts them.
+fn aggregate_sql(
+    table: &str,
+    where_full: &str,
+    aggregation: &Aggregation,
+    mapper: &impl FieldMapper,
+) -> Result<String, CqrsError> {
+    aggregation.validate()?;
+    let keys: Vec<String> = aggregation
+        .group_by
+        .iter()
+        .map(|key| mapper.map(key).into_owned())
+        .collect();
+    let mut columns: Vec<String> = keys
+        .iter()
+        .enumerate()
+        .map(|(i, key)| format!("'{}', {key}", AggregateRow::group_column(i)))
+        .collect();
+    for (i, metric) in aggregation.metrics.iter().enumerate() {
+        let expr = match (metric.function, metric.validated_field()?) {
+            (MetricFunction::Sum, Some(f)) => format!("SUM({})", mapper.map(f)),
+            (MetricFunction::Avg, Some(f)) => format!("AVG({})", mapper.map(f)),
+            (MetricFunction::Min, Some(f)) => format!("MIN({})", mapper.map(f)),
+            (MetricFunction::Max, Some(f)) => format!("MAX({})", mapper.map(f)),
+            _ => "COUNT(*)".to_string(),
+        };
+        columns.push(format!("'{}', {expr}", AggregateRow::metric_column(i)));
+    }
+
+    let mut sql = format!(
+        "SELECT json_object({}) AS agg FROM {}{}",
+        columns.join(", "),
+        table,
+        where_full
+    );
+    if !keys.is_empty() {
+        let order: Vec<String> = keys.iter().map(|key| format!("{key} NULLS LAST")).collect();
+        sql.push_str(&format!(
+            " GROUP BY {} ORDER BY {}",
+            keys.join(", "),
+            order.join(", ")
+        ));
+    }
+    Ok(sql)
+}
+
+/// The page's columns under a projection: the id column, and `data` cut down to the
+/// projected keys. `json_each` rather than `json_object`, so that a key the row does not
+/// have stays absent instead of coming back `null`. The keys are interpolated, which
+/// `Projection::validate` and the keyset planner have made safe.
+fn projected_columns(fields: &[&str]) -> String {
+    let keys: Vec<String> = fields.iter().map(|f| format!("'{f}'")).collect();
+    format!(
+        "id, (SELECT json_group_object(e.key, e.value) \
+         FROM json_each(data) AS e WHERE e.key IN ({})) AS data",
+        keys.join(", ")
+    )
+}
+
+/// The text a search looks in: the searchable fields, mapped, lower-cased and joined by
+/// spaces.
+///
+/// SQLite has no text search without the FTS5 tables a view would have to maintain
+/// beside its own, so a search is a scan: a word matches where it occurs in the text —
+/// inside a longer word too — and ranks by how often. `lower` only folds ASCII.
+fn search_text<'a>(
+    fields: impl IntoIterator<Item = &'a str>,
+    mapper: &impl FieldMapper,
+) -> Result<String, CqrsError> {
+    let text = fields
+        .into_iter()
+        .map(|f| {
+            let column = mapper.map(validated_field_name("search", f)?);
+            Ok(format!("coalesce({column}, '')"))
+        })
+        .collect::<Result<Vec<_>, CqrsError>>()?;
+    Ok(format!("lower({})", text.join(" || ' ' || ")))
+}
+
+/// Rebuilds a view from a `(id, data)` row: `save` keeps the id in its own column, so it
+/// goes back into the object before deserializing.
+fn row_to_view<V>(id: String, mut data: JsonValue) -> Result<V, CqrsError>
+where
+    V: DeserializeOwned + HasId,
+{
+    if let Some(obj) = data.as_object_mut() {
+        obj.insert(V::field_id().to_string(), JsonValue::String(id));
+    }
+    serde_json::from_value(data).map_err(CqrsError::serialization_error)
+}
+
+/// The view table: `(id, parent_id, data)`, with a `tenant_id` leading the key when
+/// `tenant_column`.
+fn view_ddl(table: &str, tenant_column: bool) -> String {
+    if tenant_column {
+        format!(
+            "CREATE TABLE IF NOT EXISTS {table} (\n    {TENANT_COLUMN} TEXT NOT NULL,\n    \
+             id TEXT NOT NULL,\n    parent_id TEXT,\n    data TEXT NOT NULL,\n    \
+             PRIMARY KEY ({TENANT_COLUMN}, id)\n);"
+        )
+    } else {
+        format!(
+            "CREATE TABLE IF NOT EXISTS {table} (\n    id TEXT PRIMARY KEY,\n    \
+             parent_id TEXT,\n    data TEXT NOT NULL\n);"
+        )
+    }
+}
+
+/// Read-side storage over a SQLite table, the view serialized as JSON text in `data`.
+///
+/// Filters and sorts compile to JSON1 expressions through the mapper, [`JsonDataMapper`]
+/// by default. A search ([`Query::search`]) scans the searchable fields for every word,
+/// and ranks by how often they occur.
+///
+/// Create the table with [`Self::schema`]; under [`Tenancy::Column`] it carries a
+/// `tenant_id` column, and its key is `(tenant_id, id)`; under [`Tenancy::Table`] each
+/// tenant has a table of its own, see [`Self::tenant_table_schema`].
+#[derive(Debug, Clone)]
+pub struct SqliteStorage<V, Q, M = JsonDataMapper> {
+    _phantom: PhantomData<(V, Q)>,
+    connection: SharedConnection,
+    type_name: String,
+    table_name: String,
+    mapper: M,
+    scope: TenantScope,
+}
+
+impl<V, Q> SqliteStorage<V, Q, JsonDataMapper> {
+    #[must_use]
+    pub fn new(connection: SharedConnection, type_name: &str, table_name: &str) -> Self {
+        Self::with_mapper(connection, type_name, table_name, JsonDataMapper)
+    }
+}
+
+impl<V, Q, M> SqliteStorage<V, Q, M>
+where
+    M: FieldMapper + Debug + Clone + Send + Sync,
+{
+    #[must_use]
+    pub fn with_mapper(
+        connection: SharedConnection,
+        type_name: &str,
+        table_name: &str,
+        mapper: M,
+    ) -> Self {
+        Self {
+            _phantom: PhantomData,
+            connection,
+            type_name: type_name.to_string(),
+            table_name: table_name.to_string(),
+            mapper,
+            scope: TenantScope::default(),
+        }
+    }
+
+    /// Keeps each tenant's views apart: a `tenant_id` column ([`Tenancy::Column`]), or a
+    /// `{table}_{tenant}` table per tenant ([`Tenancy::Table`]).
+    #[must_use]
+    pub fn with_tenancy(mut self, tenancy: Tenancy) -> Self {
+        self.scope = TenantScope::new(tenancy);
+        self
+    }
+
+    /// The DDL of the view table — with the `tenant_id` column under
+    /// [`Tenancy::Column`]. Apply it with [`SharedConnection::execute_batch`].
+    pub fn schema(&self) -> String {
+        view_ddl(
+            &self.table_name,
+            matches!(self.scope.tenancy(), Tenancy::Column),
+        )
+    }
+
+    /// [`Self::schema`] for one tenant under [`Tenancy::Table`]: its own
+    /// `{table}_{tenant}`. Run it when a tenant is onboarded.
+    pub fn tenant_table_schema(&self, tenant: &str) -> Result<String, CqrsError> {
+        let tenant = crate::validated_tenant(tenant)?;
+        Ok(view_ddl(
+            &crate::tenant_table(&self.table_name, tenant),
+            false,
+        ))
+    }
+}
+
+impl<V, Q, M> SqliteStorage<V, Q, M>
+where
+    V: HasId,
+    Q: Query,
+    M: FieldMapper + Debug + Clone + Send + Sync,
+{
+    fn mapper(&self) -> IdColumnMapper<'_, M> {
+        IdColumnMapper {
+            id_field: V::field_id(),
+            inner: &self.mapper,
+        }
+    }
+
+    /// The body of `filter`, over any row shape: the whole view or a projection of it.
+    fn select_page<T>(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        scope: &TenantScope,
+        columns: &str,
+        decode: impl Fn(&Row<'_>) -> Result<T, CqrsError>,
+    ) -> Result<Paged<T>, CqrsError>
+    where
+        T: Serialize,
+    {
+        let table = scope.table(&self.table_name)?;
+        let tenant = scope.column()?;
+        let filter = query.filter();
+        let search = TextSearch::plan(&query)?;
+        let count = self.build_filter(filter.as_ref(), &parent_id, tenant, search.as_ref())?;
+
+        let pagination = query.pagination().unwrap_or_default();
+        let limit_v = pagination.limit.unwrap_or(20);
+        let offset_v = pagination.skip.unwrap_or(0);
+
+        let sort = query.sort();
+        if search.is_none() {
+            warn_if_page_order_undefined(&self.type_name, offset_v, sort.as_deref());
+        }
+        let keyset = plan_keyset(
+            sort.as_deref(),
+            V::field_id(),
+            query.cursor(),
+            search.as_ref(),
+        )?;
+
+        let Some(keyset) = keyset else {
+            let mut order_by = order_by_clause(sort, &self.mapper())?;
+            let page = self.build_filter(filter.as_ref(), &parent_id, tenant, search.as_ref())?;
+            if let Some(search) = &search {
+                // The words are the last parameters `build_filter` binds, one each.
+                let text = search_text(search.fields(), &self.mapper())?;
+                let first = page.1.len() - search.terms().len() + 1;
+                let rank: Vec<String> = (first..=page.1.len())
+                    .map(|n| {
+                        format!(
+                            "(length({text}) - length(replace({text}, ?{n}, ''))) / length(?{n})"
+                        )
+                    })
+                    .collect();
+                order_by = relevance_first(&format!("({})", rank.join(" + ")), &order_by);
+            }
+            let (items, total) = self.connection.with(|conn| {
+                paged_select(
+                    conn,
+                    PagedSelect {
+                        columns,
+                        table: &table,
+                        count,
+                        page,
+                        order_by: &order_by,
+                        offset: offset_v,
+                        limit: limit_v,
+                    },
+                    decode,
+                )
+            })?;
+            return Ok(Paged::new(items, total, offset_v, limit_v));
+        };
+
+        let order_by = order_by_clause(Some(keyset.order()), &self.mapper())?;
+        let page = self.build_filter(keyset.filter(filter)?.as_ref(), &parent_id, tenant, None)?;
+        let offset_v = keyset.offset(offset_v);
+        let (items, total) = self.connection.with(|conn| {
+            paged_select(
+                conn,
+                PagedSelect {
+                    columns,
+                    table: &table,
+                    count,
+                    page,
+                    order_by: &order_by,
+                    offset: offset_v,
+                    limit: keyset.fetch_limit(limit_v),
+                },
+                decode,
+            )
+        })?;
+        let (items, next, prev) = keyset.finish(items, offset_v, limit_v);
+        Ok(Paged::new(items, total, offset_v, limit_v).with_cursors(next, prev))
+    }
+
+    fn build_filter(
+        &self,
+        filter: Option<&RestSql>,
+        parent_id: &Option<String>,
+        tenant: Option<&str>,
+        search: Option<&TextSearch>,
+    ) -> Result<Where, CqrsError> {
+        let (mut where_sql, mut params) =
+            tenant_filter(compile_where(filter, &self.mapper()), tenant);
+
+        match (V::parent_field_id(), parent_id) {
+            (Some(_), Some(pid)) => {
+                let matched = format!(
+                    "parent_id = {}",
+                    bind(&mut params, SqlValue::Text(pid.clone()))
+                );
+                where_sql = and(where_sql, matched);
+            }
+            (Some(_), None) => {
+                return Err(CqrsError::validation(
+                    StorageError::MissingParentId.to_string(),
+                ));
+            }
+            _ => {}
+        }
+        if let Some(search) = search {
+            let text = search_text(search.fields(), &self.mapper())?;
+            for term in search.terms() {
+                let matched = format!(
+                    "instr({text}, {}) > 0",
+                    bind(&mut params, SqlValue::Text(term.clone()))
+                );
+                where_sql = and(where_sql, matched);
+            }
+        }
+        Ok((where_sql, params))
+    }
+}
+
+cqrs_async_trait! {
+impl<V, Q, M> Storage<V, Q> for SqliteStorage<V, Q, M>
+where
+    V: Debug + Clone + Default + Serialize + DeserializeOwned + Send + Sync + HasId,
+    Q: Clone + Debug + Send + Sync + Query,
+    M: FieldMapper + Debug + Clone + Send + Sync,
+{
+    fn type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    fn id_field(&self) -> Option<&str> {
+        Some(V::field_id())
+    }
+
+    async fn health(&self) -> Result<(), CqrsError> {
+        self.connection.ping()
+    }
+
+    async fn filter(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        context: CqrsContext,
+    ) -> Result<Paged<V>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        self.select_page(parent_id, query, &scope, "id, data", |row| {
+            let id: String = row.get("id").map_err(map_sqlite_error)?;
+            row_to_view(id, json_column(row, "data")?)
+        })
+    }
+
+    async fn filter_projected(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        projection: Projection,
+        context: CqrsContext,
+    ) -> Result<Paged<JsonValue>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        projection.validate()?;
+        // A keyset reads its tokens from the page's items, so its fields are fetched too
+        // and stripped once the tokens are cut.
+        let search = TextSearch::plan(&query)?;
+        let keyset = plan_keyset(
+            query.sort().as_deref(),
+            V::field_id(),
+            query.cursor(),
+            search.as_ref(),
+        )?;
+        let fetched = projection.widened(keyset.iter().flat_map(Keyset::fields));
+        let with_id = fetched.contains(&V::field_id());
+        let page = self.select_page(parent_id, query, &scope, &projected_columns(&fetched), |row| {
+            let id: String = row.get("id").map_err(map_sqlite_error)?;
+            let mut val = json_column(row, "data")?;
+            // `save` keeps the id in its own column, not in `data`.
+            if let (true, Some(obj)) = (with_id, val.as_object_mut()) {
+                obj.insert(V::field_id().to_string(), JsonValue::String(id));
+            }
+            Ok(val)
+        })?;
+        Ok(page.map(|item| projection.apply(item)))
+    }
+
+    async fn aggregate(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        aggregation: Aggregation,
+        context: CqrsContext,
+    ) -> Result<Vec<AggregateRow>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        let search = TextSearch::plan(&query)?;
+        let (where_sql, params) = self.build_filter(
+            query.filter().as_ref(),
+            &parent_id,
+            scope.column()?,
+            search.as_ref(),
+        )?;
+        let sql = aggregate_sql(
+            &scope.table(&self.table_name)?,
+            &where_clause(&where_sql),
+            &aggregation,
+            &self.mapper(),
+        )?;
+        let rows = self.connection.with(|conn| {
+            let mut statement = conn.prepare(&sql).map_err(map_sqlite_error)?;
+            let mut rows = statement
+                .query(params_from_iter(&params))
+                .map_err(map_sqlite_error)?;
+            let mut aggregated = Vec::new();
+            while let Some(row) = rows.next().map_err(map_sqlite_error)? {
+                aggregated.push(json_column(row, "agg")?);
+            }
+            Ok(aggregated)
+        })?;
+        rows.into_iter()
+            .map(|row| match row {
+                JsonValue::Object(row) => Ok(AggregateRow::from_positional(&aggregation, row)),
+                other => Err(CqrsError::internal(format!(
+                    "an aggregate row is not an object: {other}"
+                ))),
+            })
+            .collect()
+    }
+
+    async fn find_by_id(
+        &self,
+        parent_id: Option<String>,
+        id: &str,
+        context: CqrsContext,
+    ) -> Result<Option<V>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        let mut params = vec![SqlValue::Text(id.to_string())];
+        let mut where_sql = String::from("id = ?1");
+        match (V::parent_field_id(), parent_id) {
+            (Some(_), Some(pid)) => {
+                where_sql.push_str(&format!(" AND parent_id = {}", bind(&mut params, SqlValue::Text(pid))));
+            }
+            (Some(_), None) => {
+                return Err(CqrsError::validation(
+                    StorageError::MissingParentId.to_string(),
+                ));
+            }
+            _ => {}
+        }
+        if let Some(tenant) = scope.column()? {
+            where_sql.push_str(&format!(
+                " AND {TENANT_COLUMN} = {}",
+                bind(&mut params, SqlValue::Text(tenant.to_string()))
+            ));
+        }
+        let sql = format!(
+            "SELECT id, data FROM {} WHERE {}",
+            scope.table(&self.table_name)?,
+            where_sql
+        );
+        let row = self.connection.with(|conn| {
+            conn.query_row(&sql, params_from_iter(&params), |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .optional()
+            .map_err(map_sqlite_error)
+        })?;
+        match row {
+            Some((id, data)) => Ok(Some(row_to_view(
+                id,
+                serde_json::from_str(&data).map_err(CqrsError::serialization_error)?,
+            )?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn save(&self, entity: V, context: CqrsContext) -> Result<(), CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        let id = entity.id().to_string();
+        let parent_id = entity.parent_id().map(|s| s.to_string());
+        if V::parent_field_id().is_some() && parent_id.is_none() {
+            return Err(CqrsError::validation(
+                StorageError::MissingParentId.to_string(),
+            ));
+        }
+        let mut data = serde_json::to_value(&entity).map_err(CqrsError::serialization_error)?;
+        if let Some(obj) = data.as_object_mut() {
+            obj.remove(V::field_id());
+        }
+        let mut params = vec![
+            SqlValue::Text(id),
+            parent_id.map_or(SqlValue::Null, SqlValue::Text),
+            SqlValue::Text(data.to_string()),
+        ];
+        let (column, placeholder, key) = match scope.column()? {
+            Some(tenant) => {
+                params.push(SqlValue::Text(tenant.to_string()));
+                (format!(", {TENANT_COLUMN}"), ", ?4", format!("{TENANT_COLUMN}, id"))
+            }
+            None => (String::new(), "", "id".to_string()),
+        };
+        let sql = format!(
+            "INSERT INTO {} (id, parent_id, data{column}) VALUES (?1, ?2, ?3{placeholder}) \
+             ON CONFLICT ({key}) DO UPDATE SET parent_id = excluded.parent_id, data = excluded.data",
+            scope.table(&self.table_name)?
+        );
+        self.connection.with(|conn| {
+            conn.execute(&sql, params_from_iter(&params))
+                .map_err(map_sqlite_error)?;
+            Ok(())
+        })
+    }
+}
+}
+
+/// Rejected when a caller hands a snapshot storage a parent id.
+///
+/// The other backends say the same thing; a caller swapping backends should not have to
+/// learn a second phrasing for the same refusal.
+const NO_PARENT_ON_SNAPSHOT: &str =
+    "a snapshot table has no parent column, so a parent id cannot be filtered on";
+
+/// Rejected when a caller hands a snapshot storage a keyset cursor.
+const NO_CURSOR_ON_SNAPSHOT: &str =
+    "a snapshot table does not support cursor pagination; use skip/limit";
+
+/// Rejected when a caller hands a snapshot storage a text search.
+const NO_SEARCH_ON_SNAPSHOT: &str =
+    "a snapshot table does not support text search; project a view to search";
+
+/// Read-side storage over the event store's **snapshot** table, as
+/// [`SqlitePersist`](crate::es::sqlite::SqlitePersist) writes it: `(aggregate_id, data,
+/// version)`, with `data` the bare aggregate. Writing stays unsupported — the event store
+/// owns this table.
+#[derive(Debug, Clone)]
+pub struct SqliteFromSnapshotStorage<A, Q, M = JsonDataMapper> {
+    _phantom: PhantomData<(A, Q)>,
+    connection: SharedConnection,
+    snapshot_table: String,
+    mapper: M,
+    scope: TenantScope,
+}
+
+impl<A, Q> SqliteFromSnapshotStorage<A, Q, JsonDataMapper> {
+    /// `snapshot_table` is what `SqlitePersist::snapshot_table_name()` returns.
+    #[must_use]
+    pub fn new(connection: SharedConnection, snapshot_table: &str) -> Self {
+        Self::with_mapper(connection, snapshot_table, JsonDataMapper)
+    }
+}
+
+impl<A, Q, M> SqliteFromSnapshotStorage<A, Q, M> {
+    #[must_use]
+    pub fn with_mapper(connection: SharedConnection, snapshot_table: &str, mapper: M) -> Self {
+        Self {
+            _phantom: PhantomData,
+            connection,
+            snapshot_table: snapshot_table.to_string(),
+            mapper,
+            scope: TenantScope::default(),
+        }
+    }
+
+    /// The [`Tenancy`] the `SqlitePersist` writing the snapshots was configured with.
+    #[must_use]
+    pub fn with_tenancy(mut self, tenancy: Tenancy) -> Self {
+        self.scope = TenantScope::new(tenancy);
+        self
+    }
+}
+
+cqrs_async_trait! {
+impl<A, Q, M> Storage<A, Q> for SqliteFromSnapshotStorage<A, Q, M>
+where
+    A: Aggregate,
+    Q: Clone + Debug + Send + Sync + Query,
+    M: FieldMapper + Debug + Clone + Send + Sync,
+{
+    fn type_name(&self) -> &str {
+        A::TYPE
+    }
+
+    async fn health(&self) -> Result<(), CqrsError> {
+        self.connection.ping()
+    }
+
+    async fn filter(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        context: CqrsContext,
+    ) -> Result<Paged<A>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        if parent_id.is_some() {
+            return Err(CqrsError::validation(NO_PARENT_ON_SNAPSHOT));
+        }
+        if query.cursor().is_some() {
+            return Err(CqrsError::validation(NO_CURSOR_ON_SNAPSHOT));
+        }
+        if query.search().is_some() {
+            return Err(CqrsError::validation(NO_SEARCH_ON_SNAPSHOT));
+        }
+
+        let filter = query.filter();
+        let tenant = scope.column()?;
+        let count = tenant_filter(compile_where(filter.as_ref(), &self.mapper), tenant);
+        let page = count.clone();
+
+        let pagination = query.pagination().unwrap_or_default();
+        let limit_v = pagination.limit.unwrap_or(20);
+        let offset_v = pagination.skip.unwrap_or(0);
+
+        let sort = query.sort();
+        warn_if_page_order_undefined(A::TYPE, offset_v, sort.as_deref());
+        let order_by = order_by_clause(sort, &self.mapper)?;
+        let table = scope.table(&self.snapshot_table)?;
+
+        let (items, total) = self.connection.with(|conn| {
+            paged_select(
+                conn,
+                PagedSelect {
+                    columns: "data",
+                    table: &table,
+                    count,
+                    page,
+                    order_by: &order_by,
+                    offset: offset_v,
+                    limit: limit_v,
+                },
+                // `data` is the aggregate itself, not a `Snapshot` wrapper.
+                |row| serde_json::from_value(json_column(row, "data")?)
+                    .map_err(CqrsError::serialization_error),
+            )
+        })?;
+        Ok(Paged::new(items, total, offset_v, limit_v))
+    }
+
+    async fn find_by_id(
+        &self,
+        parent_id: Option<String>,
+        id: &str,
+        context: CqrsContext,
+    ) -> Result<Option<A>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        if parent_id.is_some() {
+            return Err(CqrsError::validation(NO_PARENT_ON_SNAPSHOT));
+        }
+
+        // `aggregate_id`, not `id`: that is the snapshot table's primary key.
+        let mut sql = format!(
+            "SELECT data FROM {} WHERE aggregate_id = ?1",
+            scope.table(&self.snapshot_table)?
+        );
+        let mut params = vec![SqlValue::Text(id.to_string())];
+        if let Some(tenant) = scope.column()? {
+            sql.push_str(&format!(" AND {TENANT_COLUMN} = ?2"));
+            params.push(SqlValue::Text(tenant.to_string()));
+        }
+        let data = self.connection.with(|conn| {
+            conn.query_row(&sql, params_from_iter(&params), |row| row.get::<_, String>(0))
+                .optional()
+                .map_err(map_sqlite_error)
+        })?;
+        data.map(|data| serde_json::from_str(&data).map_err(CqrsError::serialization_error))
+            .transpose()
+    }
+
+    async fn save(&self, _entity: A, _context: CqrsContext) -> Result<(), CqrsError> {
+        Err(CqrsError::database_error(StorageError::UnsupportedMethod(
+            "SnapshotStorage#save".to_string(),
+        )))
+    }
+}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read::sorter::{SortDirection, Sorter};
+    use crate::read::{Metric, PageCursor, Pagination};
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+    struct Article {
+        id: String,
+        title: String,
+        author: String,
+        words: i64,
+        published: bool,
+    }
+
+    impl HasId for Article {
+        fn field_id() -> &'static str {
+            "id"
+        }
+        fn id(&self) -> &str {
+            &self.id
+        }
+        fn parent_field_id() -> Option<&'static str> {
+            None
+        }
+        fn parent_id(&self) -> Option<&str> {
+            None
+        }
+    }
+
+    /// A query spelled as RSQL, the way `_q` reaches a storage.
+    #[derive(Debug, Clone, Default, Serialize)]
+    struct ArticleQuery {
+        #[serde(skip)]
+        rsql: Option<&'static str>,
+        #[serde(skip)]
+        sort: Vec<Sorter>,
+        #[serde(skip)]
+        limit: Option<i64>,
+        #[serde(skip)]
+        cursor: Option<PageCursor>,
+        #[serde(skip)]
+        search: Option<&'static str>,
+    }
+
+    impl Query for ArticleQuery {
+        fn filter(&self) -> Option<RestSql> {
+            self.rsql.map(|rsql| RestSql::new(rsql).unwrap())
+        }
+        fn pagination(&self) -> Option<Pagination> {
+            Some(Pagination {
+                skip: None,
+                limit: self.limit,
+            })
+        }
+        fn cursor(&self) -> Option<PageCursor> {
+            self.cursor.clone()
+        }
+        fn sort(&self) -> Option<Vec<Sorter>> {
+            Some(self.sort.clone())
+        }
+        fn search(&self) -> Option<&str> {
+            self.search
+        }
+        fn searchable_fields(&self) -> Vec<&str> {
+            vec!["title", "author"]
+        }
+    }
+
+    fn filtered(rsql: &'static str) -> ArticleQuery {
+        ArticleQuery {
+            rsql: Some(rsql),
+            sort: vec![asc("title")],
+            ..Default::default()
+        }
+    }
+
+    fn asc(field: &str) -> Sorter {
+        Sorter {
+            field: field.to_string(),
+            direction: SortDirection::Asc,
+        }
+    }
+
+    fn article(id: &str, title: &str, author: &str, words: i64, published: bool) -> Article {
+        Article {
+            id: id.to_string(),
+            title: title.to_string(),
+            author: author.to_string(),
+            words,
+            published,
+        }
+    }
+
+    async fn storage() -> SqliteStorage<Article, ArticleQuery> {
+        let storage = SqliteStorage::new(
+            SharedConnection::open_in_memory().unwrap(),
+            "article",
+            "articles",
+        );
+        storage.connection.execute_batch(&storage.schema()).unwrap();
+        for article in [
+            article("a1", "Catan", "Teuber", 1200, true),
+            article("a2", "Carcassonne", "Wrede", 800, true),
+            article("a3", "Cities and Knights of Catan", "Teuber", 3000, false),
+            article("a4", "Azul", "Kiesling", 500, true),
+        ] {
+            storage.save(article, CqrsContext::default()).await.unwrap();
+        }
+        storage
+    }
+
+    fn ids(page: &Paged<Article>) -> Vec<&str> {
+        page.items.iter().map(|a| a.id.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn a_saved_view_reads_back_with_its_id_and_is_replaced_on_save() {
+        let storage = storage().await;
+        let found = storage
+            .find_by_id(None, "a1", CqrsContext::default())
+            .await
+            .unwrap();
+        assert_eq!(found, Some(article("a1", "Catan", "Teuber", 1200, true)));
+
+        let renamed = article("a1", "Catan (5th edition)", "Teuber", 1200, true);
+        storage
+            .save(renamed.clone(), CqrsContext::default())
+            .await
+            .unwrap();
+        let page = storage
+            .filter(None, ArticleQuery::default(), CqrsContext::default())
+            .await
+            .unwrap();
+        assert_eq!(page.total, 4);
+        assert!(page.items.contains(&renamed));
+        assert!(storage.health().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn filters_compare_json_values_with_their_types() {
+        let storage = storage().await;
+        let cases = [
+            ("words=gt=1000", vec!["a1", "a3"]),
+            ("published==false", vec!["a3"]),
+            ("author==Teuber;words=lt=2000", vec!["a1"]),
+            ("author=in=(Wrede,Kiesling)", vec!["a4", "a2"]),
+            ("words=between=(500,800)", vec!["a4", "a2"]),
+            ("title=like=Ca*", vec!["a2", "a1"]),
+            ("title=like=ca*", vec![]),
+            ("title=ilike=ca*", vec!["a2", "a1"]),
+            ("title=ilike=*catan*", vec!["a1", "a3"]),
+        ];
+        for (rsql, expected) in cases {
+            let page = storage
+                .filter(None, filtered(rsql), CqrsContext::default())
+                .await
+                .unwrap();
+            assert_eq!(ids(&page), expected, "{rsql}");
+            assert_eq!(page.total, expected.len() as i64, "{rsql}");
+        }
+    }
+
+    #[tokio::test]
+    async fn a_cursor_pages_through_the_sort() {
+        let storage = storage().await;
+        let mut query = ArticleQuery {
+            sort: vec![asc("words")],
+            limit: Some(3),
+            ..Default::default()
+        };
+        let first = storage
+            .filter(None, query.clone(), CqrsContext::default())
+            .await
+            .unwrap();
+        assert_eq!(ids(&first), ["a4", "a2", "a1"]);
+
+        query.cursor = Some(PageCursor::After(first.next_cursor.clone().unwrap()));
+        let second = storage
+            .filter(None, query, CqrsContext::default())
+            .await
+            .unwrap();
+        assert_eq!(ids(&second), ["a3"]);
+        assert_eq!(second.total, 4, "the count ignores the cursor");
+        assert!(second.next_cursor.is_none());
+    }
+
+    #[tokio::test]
+    async fn a_search_needs_every_word_and_ranks_by_occurrences() {
+        let storage = storage().await;
+        let query = |text| ArticleQuery {
+            search: Some(text),
+            sort: vec![asc("title")],
+            ..Default::default()
+        };
+        let page = storage
+            .filter(None, query("CATAN teuber"), CqrsContext::default())
+            .await
+            .unwrap();
+        assert_eq!(ids(&page), ["a1", "a3"]);
+
+        let page = storage
+            .filter(None, query("knights"), CqrsContext::default())
+            .await
+            .unwrap();
+        assert_eq!(ids(&page), ["a3"]);
+    }
+
+    #[tokio::test]
+    async fn a_projection_keeps_the_listed_keys() {
+        let storage = storage().await;
+        let page = storage
+            .filter_projected(
+                None,
+                filtered("author==Wrede"),
+                Projection::new(["title", "id"]),
+                CqrsContext::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            page.items,
+            [serde_json::json!({"id": "a2", "title": "Carcassonne"})]
+        );
+    }
+
+    #[tokio::test]
+    async fn an_aggregation_groups_and_sums() {
+        let storage = storage().await;
+        let rows = storage
+            .aggregate(
+                None,
+                filtered("published==true"),
+                Aggregation::new(["author"], [Metric::count(), Metric::sum("words")]),
+                CqrsContext::default(),
+            )
+            .await
+            .unwrap();
+        let rows: Vec<_> = rows
+            .iter()
+            .map(|row| {
+                (
+                    row.group["author"].clone(),
+                    row.metrics["count"].clone(),
+                    row.metrics["sum(words)"].clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("Kiesling".into(), 1.into(), 500.into()),
+                ("Teuber".into(), 1.into(), 1200.into()),
+                ("Wrede".into(), 1.into(), 800.into()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn tenants_sharing_the_table_only_see_their_own_views() {
+        let storage: SqliteStorage<Article, ArticleQuery> = SqliteStorage::new(
+            SharedConnection::open_in_memory().unwrap(),
+            "article",
+            "articles",
+        )
+        .with_tenancy(Tenancy::Column);
+        storage.connection.execute_batch(&storage.schema()).unwrap();
+        let context = |tenant: &str| CqrsContext::default().with_tenant(Some(tenant.to_string()));
+        storage
+            .save(article("a1", "Catan", "Teuber", 1, true), context("acme"))
+            .await
+            .unwrap();
+        storage
+            .save(
+                article("a1", "Azul", "Kiesling", 1, true),
+                context("globex"),
+            )
+            .await
+            .unwrap();
+
+        let page = storage
+            .filter(None, ArticleQuery::default(), context("acme"))
+            .await
+            .unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.items[0].title, "Catan");
+        let err = storage
+            .filter(None, ArticleQuery::default(), CqrsContext::default())
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, 403);
+    }
+
+    #[tokio::test]
+    async fn the_snapshot_storage_reads_what_the_event_store_wrote() {
+        use crate::es::sqlite::SqlitePersist;
+        use crate::es::storage::EventStoreStorage;
+        use crate::testing::{TestAggregate, TestEvent};
+
+        let connection = SharedConnection::open_in_memory().unwrap();
+        connection
+            .execute_batch(&SqlitePersist::<TestAggregate>::schema())
+            .unwrap();
+        let persist = SqlitePersist::<TestAggregate>::new(connection.clone());
+        let mut session = persist.start_session().await.unwrap();
+        for (id, name) in [("t1", "foo"), ("t2", "bar")] {
+            let mut aggregate = TestAggregate::default().with_aggregate_id(id.to_string());
+            aggregate
+                .apply(TestEvent::Created { name: name.into() })
+                .unwrap();
+            persist
+                .save_snapshot(&aggregate, 1, &mut session)
+                .await
+                .unwrap();
+        }
+        persist.close_session(session).await.unwrap();
+
+        #[derive(Debug, Clone, Default, Serialize)]
+        struct NameQuery {
+            name: Option<String>,
+        }
+        impl Query for NameQuery {}
+
+        let snapshots: SqliteFromSnapshotStorage<TestAggregate, NameQuery> =
+            SqliteFromSnapshotStorage::new(connection, persist.snapshot_table_name());
+        let page = snapshots
+            .filter(
+                None,
+                NameQuery {
+                    name: Some("bar".into()),
+                },
+                CqrsContext::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.items[0].aggregate_id(), "t2");
+        assert!(
+            snapshots
+                .find_by_id(None, "t1", CqrsContext::default())
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert!(
+            snapshots
+                .save(TestAggregate::default(), CqrsContext::default())
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn like_patterns_translate_the_rsql_wildcards() {
+        assert_eq!(glob_pattern("Ca*_n?[x]"), "Ca*?n[?][[]x]");
+        assert_eq!(like_pattern("*catan*"), "%catan%");
+        let (sql, params) = compile_where(
+            Some(&RestSql::new("title=like=Ca*;author=ilike=*teub*").unwrap()),
+            &JsonDataMapper,
+        );
+        assert_eq!(
+            sql,
+            "(json_extract(data, '$.title') GLOB ?1 AND json_extract(data, '$.author') LIKE ?2)"
+        );
+        assert_eq!(params.len(), 2);
+    }
+}
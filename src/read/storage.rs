@@ -1,7 +1,8 @@
-use crate::read::Paged;
+use crate::read::{AggregateRow, Aggregation, Paged, Projection};
 use crate::{CqrsContext, CqrsError, MaybeSend, MaybeSync};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
+use serde_json::Value as JsonValue;
 use std::fmt::Debug;
 use std::sync::Arc;
 
@@ -40,6 +41,20 @@ where
     Q: Clone + Debug + MaybeSend + MaybeSync,
 {
     fn type_name(&self) -> &str;
+
+    /// The field identifying an item, for the storages that can order by it: an export
+    /// sorts by it last, so that its order — and its keyset — holds across batches.
+    /// `None` for the snapshot storages, which page by offset alone.
+    fn id_field(&self) -> Option<&str> {
+        None
+    }
+
+    /// Checks that the backend answers — a round trip, not a read of the views — for
+    /// readiness probes. The default, for storages with nothing to reach, succeeds.
+    async fn health(&self) -> Result<(), CqrsError> {
+        Ok(())
+    }
+
     async fn filter(
         &self,
         parent_id: Option<String>,
@@ -55,5 +70,52 @@ where
     ) -> Result<Option<V>, CqrsError>;
 
     async fn save(&self, entity: V, context: CqrsContext) -> Result<(), CqrsError>;
+
+    /// [`Storage::filter`], with each item cut down to the projection's fields and
+    /// returned as a partial JSON object. Counting, sorting and cursors are unchanged.
+    ///
+    /// The default reads whole items and projects them afterwards, so every storage
+    /// honours a projection; the database storages override it to select only the
+    /// projected fields — plus whatever a cursor is read from — in the query itself.
+    async fn filter_projected(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        projection: Projection,
+        context: CqrsContext,
+    ) -> Result<Paged<JsonValue>, CqrsError>
+    where
+        Q: 'async_trait,
+    {
+        projection.validate()?;
+        self.filter(parent_id, query, context)
+            .await?
+            .try_map(|item| serde_json::to_value(item).map(|item| projection.apply(item)))
+            .map_err(CqrsError::serialization_error)
+    }
+
+    /// Groups the items [`Storage::filter`] would return for `query` — same filter,
+    /// same parent scoping, no window — and computes the aggregation's metrics per
+    /// group, in group-key order. The query's pagination, sort and cursor do not apply.
+    ///
+    /// Defaults to `501 Not Implemented`, so a storage that cannot group keeps compiling
+    /// and says so. Every storage this crate ships over a view table implements it; the
+    /// snapshot storages do not.
+    async fn aggregate(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        aggregation: Aggregation,
+        context: CqrsContext,
+    ) -> Result<Vec<AggregateRow>, CqrsError>
+    where
+        Q: 'async_trait,
+    {
+        let _ = (parent_id, query, aggregation, context);
+        Err(CqrsError::not_implemented(format!(
+            "{} does not support aggregation",
+            self.type_name()
+        )))
+    }
 }
 }
@@ -1,10 +1,11 @@
 use crate::read::page_order::warn_if_page_order_undefined;
 use crate::read::query::{Pagination, Query};
-use crate::read::sorter::order_by_clause;
+use crate::read::search::{plan_keyset, relevance_first};
+use crate::read::sorter::{order_by_clause, validated_field_name, Sorter};
 use crate::read::storage::{HasId, Storage, StorageError};
-use crate::read::Paged;
-use crate::{Aggregate, CqrsContext, CqrsError};
-use rest_sql::FieldMapper;
+use crate::read::{AggregateRow, Aggregation, Keyset, MetricFunction, Paged, Projection, TextSearch};
+use crate::{Aggregate, CqrsContext, CqrsError, TENANT_COLUMN, Tenancy, TenantScope};
+use rest_sql::{FieldMapper, RestSql};
 use rest_sql_drivers::surrealdb::SurrealCompiler;
 use rest_sql_drivers::Driver;
 use serde::de::DeserializeOwned;
@@ -14,6 +15,7 @@ use std::borrow::Cow;
 use std::fmt::Debug;
 use std::marker::PhantomData;
 use surrealdb::engine::any::Any;
+use surrealdb::method::Query as SurrealQuery;
 use surrealdb::Surreal;
 use surrealdb_types::SurrealValue;
 
@@ -21,6 +23,21 @@ fn map_surreal_error(e: surrealdb::Error) -> CqrsError {
     CqrsError::database_error(e)
 }
 
+/// The record of `id` in `$__cqrs_table`. Under [`Tenancy::Column`] its key is
+/// `[tenant, id]`, as `SurrealDBPersist` keys its snapshots, so that two tenants' records
+/// of the same id are two records.
+fn record(tenant: Option<&str>) -> &'static str {
+    match tenant {
+        Some(_) => "type::record($__cqrs_table, [$__cqrs_tenant, $__cqrs_id])",
+        None => "type::record($__cqrs_table, $__cqrs_id)",
+    }
+}
+
+/// The condition narrowing a query to `$__cqrs_tenant`, under [`Tenancy::Column`].
+fn tenant_clause() -> String {
+    format!("{TENANT_COLUMN} = $__cqrs_tenant")
+}
+
 /// Maps field names with a `data.` prefix — used for CQRS views where entities
 /// are stored under a `data` field in SurrealDB records.
 #[derive(Debug, Clone)]
@@ -42,6 +59,147 @@ struct DataRow {
     data: JsonValue,
 }
 
+/// Where a projected page puts the cut-down `data`: not under `data` itself, which the
+/// sort idioms selected next to it would write into.
+const PROJECTED: &str = "__cqrs_projected";
+
+/// The page's select list under a projection. `data`'s entries are filtered by key
+/// rather than destructured with `data.{a, b}`, which would turn a key the record lacks
+/// into `null` instead of leaving it absent.
+///
+/// SurrealDB v3 refuses an `ORDER BY` on an idiom the statement does not select, so each
+/// sort field is selected too; a `SELECT *` page gets that for free.
+fn projected_columns(
+    fields: &[&str],
+    sort: Option<&[Sorter]>,
+    mapper: &impl FieldMapper,
+) -> Result<String, CqrsError> {
+    let keys: Vec<String> = fields.iter().map(|f| format!("'{f}'")).collect();
+    let mut columns = vec![format!(
+        "object::from_entries(object::entries(data).filter(|$e| $e[0] IN [{}])) AS {PROJECTED}",
+        keys.join(", ")
+    )];
+    for sorter in sort.unwrap_or_default() {
+        columns.push(mapper.map(sorter.validated_field()?).into_owned());
+    }
+    Ok(columns.join(", "))
+}
+
+/// Where a search page puts its relevance, selected so that `ORDER BY` may name it.
+const SCORE: &str = "__cqrs_score";
+
+/// The analyzer `ensure_search_index` defines and indexes the searchable fields with.
+const ANALYZER: &str = "cqrs_search";
+
+/// A search's condition: for each word, a match in at least one searchable field.
+///
+/// A `@@` over one field requires every word of its text *in that field*, while a search
+/// requires each word somewhere, so the words go one clause each, ORed across the
+/// fields. Every match operator carries its own reference — word `j`, field `i` is
+/// `j * fields + i` — which is what [`search_score`] sums. The words are bound as
+/// `$__cqrs_search_{j}`, see [`bind_search`].
+fn search_condition(search: &TextSearch, mapper: &impl FieldMapper) -> String {
+    let fields: Vec<Cow<'_, str>> = search.fields().map(|f| mapper.map(f)).collect();
+    let clauses: Vec<String> = (0..search.terms().len())
+        .map(|j| {
+            let matches: Vec<String> = fields
+                .iter()
+                .enumerate()
+                .map(|(i, field)| format!("{field} @{}@ $__cqrs_search_{j}", j * fields.len() + i))
+                .collect();
+            format!("({})", matches.join(" OR "))
+        })
+        .collect();
+    clauses.join(" AND ")
+}
+
+/// The relevance of a search page: the BM25 score of every match reference. A reference
+/// whose field did not match scores `NONE`, read as `0`.
+fn search_score(search: &TextSearch) -> String {
+    let references = search.terms().len() * search.fields().count();
+    (0..references)
+        .map(|r| format!("(search::score({r}) ?? 0)"))
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+/// Binds the words [`search_condition`] refers to.
+fn bind_search<'r>(
+    mut query: SurrealQuery<'r, Any>,
+    search: Option<&TextSearch>,
+) -> SurrealQuery<'r, Any> {
+    for (j, term) in search.iter().flat_map(|s| s.terms().iter().enumerate()) {
+        query = query.bind((format!("__cqrs_search_{j}"), term.clone()));
+    }
+    query
+}
+
+/// Builds the `GROUP BY` select behind `Storage::aggregate`; `where_clause` is what
+/// `build_where` returns.
+///
+/// What it takes to answer like the other backends:
+///
+/// - a key reads `?? NULL`, or a missing field (`NONE`) and a `null` one would be two
+///   groups;
+/// - SurrealQL has no `NULLS LAST`, so each key carries an `n{i}` flag, grouped on and
+///   sorted ahead of it — it is a function of the key and splits no group;
+/// - `math::min`/`math::max` only take numbers; `array::min` over the group's distinct
+///   non-null values also orders strings and datetimes.
+///
+/// `math::sum` over a group with no numeric value is `0`, not `null`.
+fn aggregate_sql(
+    table: &str,
+    where_clause: &str,
+    aggregation: &Aggregation,
+    mapper: &impl FieldMapper,
+) -> Result<String, CqrsError> {
+    aggregation.validate()?;
+    let mut columns = Vec::new();
+    let mut group_by = Vec::new();
+    let mut order_by = Vec::new();
+    for (i, key) in aggregation.group_by.iter().enumerate() {
+        let column = AggregateRow::group_column(i);
+        let key = mapper.map(key);
+        columns.push(format!("{key} ?? NULL AS {column}"));
+        columns.push(format!("({key} ?? NULL) = NULL AS n{i}"));
+        group_by.push(format!("{column}, n{i}"));
+        order_by.push(format!("n{i}, {column}"));
+    }
+    for (i, metric) in aggregation.metrics.iter().enumerate() {
+        let values = |f: &str| {
+            format!(
+                "array::group({}).filter(|$v| $v != NONE AND $v != NULL)",
+                mapper.map(f)
+            )
+        };
+        let expr = match (metric.function, metric.validated_field()?) {
+            (MetricFunction::Sum, Some(f)) => format!("math::sum({})", mapper.map(f)),
+            (MetricFunction::Avg, Some(f)) => format!("math::mean({})", mapper.map(f)),
+            (MetricFunction::Min, Some(f)) => format!("array::min({})", values(f)),
+            (MetricFunction::Max, Some(f)) => format!("array::max({})", values(f)),
+            _ => "count()".to_string(),
+        };
+        columns.push(format!("{expr} AS {}", AggregateRow::metric_column(i)));
+    }
+
+    let grouping = if group_by.is_empty() {
+        "GROUP ALL".to_string()
+    } else {
+        format!(
+            "GROUP BY {} ORDER BY {}",
+            group_by.join(", "),
+            order_by.join(", ")
+        )
+    };
+    Ok(format!(
+        "SELECT {} FROM {} {} {}",
+        columns.join(", "),
+        table,
+        where_clause,
+        grouping
+    ))
+}
+
 #[derive(Debug, Clone)]
 pub struct SurrealDBStorage<V, Q, M = DataPrefixMapper> {
     _phantom: PhantomData<(V, Q)>,
@@ -49,6 +207,7 @@ pub struct SurrealDBStorage<V, Q, M = DataPrefixMapper> {
     type_name: String,
     table_name: String,
     mapper: M,
+    scope: TenantScope,
 }
 
 impl<V, Q> SurrealDBStorage<V, Q, DataPrefixMapper> {
@@ -70,17 +229,165 @@ where
             type_name: type_name.to_string(),
             table_name: table_name.to_string(),
             mapper,
+            scope: TenantScope::default(),
         }
     }
 
+    /// Keeps each tenant's views apart: a `tenant_id` field on every record
+    /// ([`Tenancy::Column`]), or a `{table}_{tenant}` table per tenant
+    /// ([`Tenancy::Table`]).
+    #[must_use]
+    pub fn with_tenancy(mut self, tenancy: Tenancy) -> Self {
+        self.scope = TenantScope::new(tenancy);
+        self
+    }
+
+    /// Defines what a search over `fields` — the view's [`Query::searchable_fields`] —
+    /// runs on: an analyzer splitting on blanks and punctuation, lower-casing and folding
+    /// accents, and a `FULLTEXT` index per field. SurrealDB refuses a match on a field
+    /// without one. Definitions already there are left as they are.
+    pub async fn ensure_search_index(&self, fields: &[&str]) -> Result<(), CqrsError> {
+        let mut sql = format!(
+            "DEFINE ANALYZER IF NOT EXISTS {ANALYZER} TOKENIZERS blank, punct \
+             FILTERS lowercase, ascii;"
+        );
+        for field in fields {
+            let field = validated_field_name("search", field)?;
+            sql.push_str(&format!(
+                " DEFINE INDEX IF NOT EXISTS {}_search_{} ON {} FIELDS {} \
+                 FULLTEXT ANALYZER {ANALYZER} BM25;",
+                self.table_name,
+                field.replace('.', "_"),
+                self.table_name,
+                self.mapper.map(field)
+            ));
+        }
+        self.db
+            .query(sql)
+            .await
+            .map_err(map_surreal_error)?
+            .check()
+            .map_err(map_surreal_error)?;
+        Ok(())
+    }
+
+    /// The body of `filter`, with the items left as JSON: the whole `data` object, or
+    /// the fields of `projection` out of it.
+    async fn select_page(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        scope: &TenantScope,
+        projection: Option<&[&str]>,
+    ) -> Result<Paged<JsonValue>, CqrsError>
+    where
+        V: HasId,
+        Q: Query,
+    {
+        let table = scope.table(&self.table_name)?;
+        let tenant = scope.column()?;
+        let filter = query.filter();
+        let search = TextSearch::plan(&query)?;
+        let where_clause =
+            self.build_where(filter.as_ref(), &parent_id, tenant, search.as_ref())?;
+        let Pagination { skip, limit } = query.pagination().unwrap_or_default();
+        let limit_v = limit.unwrap_or(20).max(0);
+        let offset_v = skip.unwrap_or(0).max(0);
+
+        let sort = query.sort();
+        if search.is_none() {
+            warn_if_page_order_undefined(&self.type_name, offset_v, sort.as_deref());
+        }
+        let keyset = plan_keyset(
+            sort.as_deref(),
+            V::field_id(),
+            query.cursor(),
+            search.as_ref(),
+        )?;
+        // Under a keyset the page reads past the cursor, one row long, in the keyset's
+        // order; the count keeps the caller's filter alone.
+        let (page_where, sort, offset_v, fetch_limit) = match &keyset {
+            Some(keyset) => (
+                self.build_where(keyset.filter(filter)?.as_ref(), &parent_id, tenant, None)?,
+                Some(keyset.order()),
+                keyset.offset(offset_v),
+                keyset.fetch_limit(limit_v),
+            ),
+            None => (where_clause.clone(), sort, offset_v, limit_v),
+        };
+        let (mut columns, column) = match projection {
+            Some(fields) => (
+                projected_columns(fields, sort.as_deref(), &self.mapper)?,
+                PROJECTED,
+            ),
+            None => ("*".to_string(), "data"),
+        };
+        let mut order_by = order_by_clause(sort, &self.mapper)?;
+        if let Some(search) = &search {
+            columns.push_str(&format!(", {} AS {SCORE}", search_score(search)));
+            order_by = relevance_first(SCORE, &order_by);
+        }
+
+        let count_sql = format!(
+            "SELECT count() AS cnt FROM {} {} GROUP ALL",
+            table, where_clause
+        );
+        let mut count_q = bind_search(self.db.query(count_sql), search.as_ref())
+            .bind(("__cqrs_tenant", tenant.map(str::to_string)));
+        if let Some(pid) = parent_id.as_ref() {
+            count_q = count_q.bind(("__cqrs_parent_id", pid.clone()));
+        }
+        let mut r = count_q.await.map_err(map_surreal_error)?;
+        let counts: Vec<CountRow> = r.take(0).map_err(map_surreal_error)?;
+        let total = counts.first().map(|c| c.cnt).unwrap_or(0);
+
+        let select_sql = format!(
+            "SELECT {} FROM {} {}{} LIMIT $__cqrs_limit START $__cqrs_offset",
+            columns, table, page_where, order_by
+        );
+        let mut select_q = bind_search(self.db.query(select_sql), search.as_ref())
+            .bind(("__cqrs_tenant", tenant.map(str::to_string)))
+            .bind(("__cqrs_limit", fetch_limit))
+            .bind(("__cqrs_offset", offset_v));
+        if let Some(pid) = parent_id.as_ref() {
+            select_q = select_q.bind(("__cqrs_parent_id", pid.clone()));
+        }
+        let mut result = select_q.await.map_err(map_surreal_error)?;
+        let rows: Vec<JsonValue> = result.take(0).map_err(map_surreal_error)?;
+        let items: Vec<JsonValue> = rows
+            .into_iter()
+            .map(|mut row| row.get_mut(column).map(JsonValue::take).unwrap_or_default())
+            .collect();
+        let Some(keyset) = keyset else {
+            return Ok(Paged::new(items, total, offset_v, limit_v));
+        };
+        let (items, next, prev) = keyset.finish(items, offset_v, limit_v);
+        Ok(Paged::new(items, total, offset_v, limit_v).with_cursors(next, prev))
+    }
+
+    /// Compiles a filter and scopes it to the parent, and to the tenant — bound as
+    /// `$__cqrs_tenant` — under [`Tenancy::Column`].
+    ///
+    /// Unlike Postgres, no mapping exception is needed for a keyset's id tie-breaker:
+    /// `save` keeps the whole entity, id included, under `data`.
     fn build_where(
         &self,
-        user_filter: Option<String>,
+        filter: Option<&RestSql>,
         parent_id: &Option<String>,
+        tenant: Option<&str>,
+        search: Option<&TextSearch>,
     ) -> Result<String, CqrsError>
     where
         V: HasId,
     {
+        let user_filter = match filter {
+            Some(rsql) => Some(
+                SurrealCompiler::new(self.mapper.clone())
+                    .compile(rsql)
+                    .map_err(|e| CqrsError::internal(e.to_string()))?,
+            ),
+            None => None,
+        };
         let mut clauses: Vec<String> = Vec::new();
         if let Some(w) = user_filter.filter(|w| !w.trim().is_empty()) {
             clauses.push(format!("({})", w));
@@ -94,6 +401,12 @@ where
             }
             _ => {}
         }
+        if tenant.is_some() {
+            clauses.push(tenant_clause());
+        }
+        if let Some(search) = search {
+            clauses.push(search_condition(search, &self.mapper));
+        }
         if clauses.is_empty() {
             Ok(String::new())
         } else {
@@ -113,73 +426,101 @@ where
         &self.type_name
     }
 
+    fn id_field(&self) -> Option<&str> {
+        Some(V::field_id())
+    }
+
+    async fn health(&self) -> Result<(), CqrsError> {
+        self.db.health().await.map_err(map_surreal_error)
+    }
+
     async fn filter(
         &self,
         parent_id: Option<String>,
         query: Q,
-        _context: CqrsContext,
+        context: CqrsContext,
     ) -> Result<Paged<V>, CqrsError> {
-        let user_filter = match query.filter() {
-            Some(rsql) => Some(
-                SurrealCompiler::new(self.mapper.clone())
-                    .compile(&rsql)
-                    .map_err(|e| CqrsError::internal(e.to_string()))?,
-            ),
-            None => None,
-        };
-        let where_clause = self.build_where(user_filter, &parent_id)?;
-        let Pagination { skip, limit } = query.pagination().unwrap_or_default();
-        let limit_v = limit.unwrap_or(20).max(0);
-        let offset_v = skip.unwrap_or(0).max(0);
-
-        let sort = query.sort();
-        warn_if_page_order_undefined(&self.type_name, offset_v, sort.as_deref());
-        let order_by = order_by_clause(sort, &self.mapper)?;
+        let scope = self.scope.for_context(&context)?;
+        self.select_page(parent_id, query, &scope, None)
+            .await?
+            .try_map(serde_json::from_value)
+            .map_err(CqrsError::serialization_error)
+    }
 
-        let count_sql = format!(
-            "SELECT count() AS cnt FROM {} {} GROUP ALL",
-            self.table_name, where_clause
-        );
-        let mut count_q = self.db.query(count_sql);
-        if let Some(pid) = parent_id.as_ref() {
-            count_q = count_q.bind(("__cqrs_parent_id", pid.clone()));
-        }
-        let mut r = count_q.await.map_err(map_surreal_error)?;
-        let counts: Vec<CountRow> = r.take(0).map_err(map_surreal_error)?;
-        let total = counts.first().map(|c| c.cnt).unwrap_or(0);
+    async fn filter_projected(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        projection: Projection,
+        context: CqrsContext,
+    ) -> Result<Paged<JsonValue>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        projection.validate()?;
+        // `save` keeps the whole entity under `data`, id included, so a keyset's fields
+        // are all there to fetch alongside the projection.
+        let search = TextSearch::plan(&query)?;
+        let keyset = plan_keyset(
+            query.sort().as_deref(),
+            V::field_id(),
+            query.cursor(),
+            search.as_ref(),
+        )?;
+        let fetched = projection.widened(keyset.iter().flat_map(Keyset::fields));
+        let page = self
+            .select_page(parent_id, query, &scope, Some(&fetched))
+            .await?;
+        Ok(page.map(|item| projection.apply(item)))
+    }
 
-        // SELECT * so fields referenced in ORDER BY are projected (SurrealDB v3 requirement).
-        let select_sql = format!(
-            "SELECT * FROM {} {}{} LIMIT $__cqrs_limit START $__cqrs_offset",
-            self.table_name, where_clause, order_by
-        );
-        let mut select_q = self
-            .db
-            .query(select_sql)
-            .bind(("__cqrs_limit", limit_v))
-            .bind(("__cqrs_offset", offset_v));
-        if let Some(pid) = parent_id.as_ref() {
-            select_q = select_q.bind(("__cqrs_parent_id", pid.clone()));
-        }
-        let mut result = select_q.await.map_err(map_surreal_error)?;
-        let rows: Vec<DataRow> = result.take(0).map_err(map_surreal_error)?;
-        let mut items: Vec<V> = Vec::with_capacity(rows.len());
-        for row in rows {
-            let v: V = serde_json::from_value(row.data).map_err(CqrsError::serialization_error)?;
-            items.push(v);
+    async fn aggregate(
+        &self,
+        parent_id: Option<String>,
+        query: Q,
+        aggregation: Aggregation,
+        context: CqrsContext,
+    ) -> Result<Vec<AggregateRow>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        let tenant = scope.column()?;
+        let search = TextSearch::plan(&query)?;
+        let where_clause =
+            self.build_where(query.filter().as_ref(), &parent_id, tenant, search.as_ref())?;
+        let sql = aggregate_sql(
+            &scope.table(&self.table_name)?,
+            &where_clause,
+            &aggregation,
+            &self.mapper,
+        )?;
+        let mut q = bind_search(self.db.query(sql), search.as_ref())
+            .bind(("__cqrs_tenant", tenant.map(str::to_string)));
+        if let Some(pid) = parent_id {
+            q = q.bind(("__cqrs_parent_id", pid));
         }
-        Ok(Paged::new(items, total, offset_v, limit_v))
+        let mut result = q.await.map_err(map_surreal_error)?;
+        let rows: Vec<JsonValue> = result.take(0).map_err(map_surreal_error)?;
+        rows.into_iter()
+            .map(|row| match row {
+                JsonValue::Object(row) => Ok(AggregateRow::from_positional(&aggregation, row)),
+                other => Err(CqrsError::internal(format!(
+                    "an aggregate row is not an object: {other}"
+                ))),
+            })
+            .collect()
     }
 
     async fn find_by_id(
         &self,
         parent_id: Option<String>,
         id: &str,
-        _context: CqrsContext,
+        context: CqrsContext,
     ) -> Result<Option<V>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        let tenant = scope.column()?;
         let id = id.to_string();
-        let table = self.table_name.clone();
-        let mut where_clause = String::from("id = type::record($__cqrs_table, $__cqrs_id)");
+        let table = scope.table(&self.table_name)?;
+        let mut where_clause = format!("id = {}", record(tenant));
+        if tenant.is_some() {
+            where_clause.push_str(&format!(" AND {}", tenant_clause()));
+        }
         match (V::parent_field_id(), parent_id.as_ref()) {
             (Some(_), Some(_)) => where_clause.push_str(" AND parent_id = $__cqrs_parent_id"),
             (Some(_), None) => {
@@ -189,11 +530,12 @@ where
             }
             _ => {}
         }
-        let sql = format!("SELECT data FROM {} WHERE {}", self.table_name, where_clause);
+        let sql = format!("SELECT data FROM {} WHERE {}", table, where_clause);
         let mut q = self
             .db
             .query(sql)
             .bind(("__cqrs_table", table))
+            .bind(("__cqrs_tenant", tenant.map(str::to_string)))
             .bind(("__cqrs_id", id));
         if let Some(pid) = parent_id {
             q = q.bind(("__cqrs_parent_id", pid));
@@ -210,7 +552,9 @@ where
         }
     }
 
-    async fn save(&self, entity: V, _context: CqrsContext) -> Result<(), CqrsError> {
+    async fn save(&self, entity: V, context: CqrsContext) -> Result<(), CqrsError> {
+        let scope = self.scope.for_context(&context)?;
+        let tenant = scope.column()?;
         let id = entity.id().to_string();
         let parent_id = entity.parent_id().map(|s| s.to_string());
         let data = serde_json::to_value(&entity).map_err(CqrsError::serialization_error)?;
@@ -219,12 +563,22 @@ where
                 StorageError::MissingParentId.to_string(),
             ));
         }
-        let table = self.table_name.clone();
+        let table = scope.table(&self.table_name)?;
+        let sql = match tenant {
+            Some(_) => format!(
+                "UPSERT {} SET {TENANT_COLUMN} = $__cqrs_tenant, parent_id = $__cqrs_parent, \
+                 data = $__cqrs_data",
+                record(tenant)
+            ),
+            None => format!(
+                "UPSERT {} SET parent_id = $__cqrs_parent, data = $__cqrs_data",
+                record(tenant)
+            ),
+        };
         self.db
-            .query(
-                "UPSERT type::record($__cqrs_table, $__cqrs_id) SET parent_id = $__cqrs_parent, data = $__cqrs_data",
-            )
+            .query(sql)
             .bind(("__cqrs_table", table))
+            .bind(("__cqrs_tenant", tenant.map(str::to_string)))
             .bind(("__cqrs_id", id))
             .bind(("__cqrs_parent", parent_id))
             .bind(("__cqrs_data", data))
@@ -240,6 +594,12 @@ where
 const NO_PARENT_ON_SNAPSHOT: &str =
     "a snapshot table has no parent column, so a parent id cannot be filtered on";
 
+const NO_CURSOR_ON_SNAPSHOT: &str =
+    "a snapshot table does not support cursor pagination; use skip/limit";
+
+const NO_SEARCH_ON_SNAPSHOT: &str =
+    "a snapshot table does not support text search; project a view to search";
+
 /// Read-side storage over the event store's **snapshot** table.
 ///
 /// This does not reuse [`SurrealDBStorage`], for the same reason as the Postgres one: the
@@ -258,6 +618,7 @@ pub struct SurrealDBFromSnapshotStorage<A, Q, M = DataPrefixMapper> {
     db: Surreal<Any>,
     snapshot_table: String,
     mapper: M,
+    scope: TenantScope,
 }
 
 impl<A, Q> SurrealDBFromSnapshotStorage<A, Q, DataPrefixMapper> {
@@ -276,8 +637,16 @@ impl<A, Q, M> SurrealDBFromSnapshotStorage<A, Q, M> {
             db,
             snapshot_table: snapshot_table.to_string(),
             mapper,
+            scope: TenantScope::default(),
         }
     }
+
+    /// The [`Tenancy`] the `SurrealDBPersist` writing the snapshots was configured with.
+    #[must_use]
+    pub fn with_tenancy(mut self, tenancy: Tenancy) -> Self {
+        self.scope = TenantScope::new(tenancy);
+        self
+    }
 }
 
 cqrs_async_trait! {
@@ -291,24 +660,43 @@ where
         A::TYPE
     }
 
+    async fn health(&self) -> Result<(), CqrsError> {
+        self.db.health().await.map_err(map_surreal_error)
+    }
+
     async fn filter(
         &self,
         parent_id: Option<String>,
         query: Q,
-        _context: CqrsContext,
+        context: CqrsContext,
     ) -> Result<Paged<A>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
         if parent_id.is_some() {
             return Err(CqrsError::validation(NO_PARENT_ON_SNAPSHOT));
         }
+        if query.cursor().is_some() {
+            return Err(CqrsError::validation(NO_CURSOR_ON_SNAPSHOT));
+        }
+        if query.search().is_some() {
+            return Err(CqrsError::validation(NO_SEARCH_ON_SNAPSHOT));
+        }
 
-        let where_clause = match query.filter() {
-            Some(rsql) => {
-                let compiled = SurrealCompiler::new(self.mapper.clone())
-                    .compile(&rsql)
-                    .map_err(|e| CqrsError::internal(e.to_string()))?;
-                format!("WHERE {}", compiled)
-            }
-            None => String::new(),
+        let table = scope.table(&self.snapshot_table)?;
+        let tenant = scope.column()?.map(str::to_string);
+        let mut clauses: Vec<String> = Vec::new();
+        if let Some(rsql) = query.filter() {
+            let compiled = SurrealCompiler::new(self.mapper.clone())
+                .compile(&rsql)
+                .map_err(|e| CqrsError::internal(e.to_string()))?;
+            clauses.push(format!("({})", compiled));
+        }
+        if tenant.is_some() {
+            clauses.push(tenant_clause());
+        }
+        let where_clause = if clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", clauses.join(" AND "))
         };
 
         let Pagination { skip, limit } = query.pagination().unwrap_or_default();
@@ -321,20 +709,26 @@ where
 
         let count_sql = format!(
             "SELECT count() AS cnt FROM {} {} GROUP ALL",
-            self.snapshot_table, where_clause
+            table, where_clause
         );
-        let mut r = self.db.query(count_sql).await.map_err(map_surreal_error)?;
+        let mut r = self
+            .db
+            .query(count_sql)
+            .bind(("__cqrs_tenant", tenant.clone()))
+            .await
+            .map_err(map_surreal_error)?;
         let counts: Vec<CountRow> = r.take(0).map_err(map_surreal_error)?;
         let total = counts.first().map(|c| c.cnt).unwrap_or(0);
 
         // SELECT * so fields referenced in ORDER BY are projected (SurrealDB v3).
         let select_sql = format!(
             "SELECT * FROM {} {}{} LIMIT $__cqrs_limit START $__cqrs_offset",
-            self.snapshot_table, where_clause, order_by
+            table, where_clause, order_by
         );
         let mut result = self
             .db
             .query(select_sql)
+            .bind(("__cqrs_tenant", tenant))
             .bind(("__cqrs_limit", limit_v))
             .bind(("__cqrs_offset", offset_v))
             .await
@@ -353,22 +747,24 @@ where
         &self,
         parent_id: Option<String>,
         id: &str,
-        _context: CqrsContext,
+        context: CqrsContext,
     ) -> Result<Option<A>, CqrsError> {
+        let scope = self.scope.for_context(&context)?;
         if parent_id.is_some() {
             return Err(CqrsError::validation(NO_PARENT_ON_SNAPSHOT));
         }
 
         // The snapshot's record id *is* the aggregate id — `save_snapshot` upserts
-        // `type::record($table, $aggregate_id)`.
-        let sql = format!(
-            "SELECT data FROM {} WHERE id = type::record($__cqrs_table, $__cqrs_id)",
-            self.snapshot_table
-        );
+        // `type::record($table, $aggregate_id)`, or `[$tenant, $aggregate_id]` under
+        // `Tenancy::Column`.
+        let table = scope.table(&self.snapshot_table)?;
+        let tenant = scope.column()?;
+        let sql = format!("SELECT data FROM {} WHERE id = {}", table, record(tenant));
         let mut result = self
             .db
             .query(sql)
-            .bind(("__cqrs_table", self.snapshot_table.clone()))
+            .bind(("__cqrs_table", table))
+            .bind(("__cqrs_tenant", tenant.map(str::to_string)))
             .bind(("__cqrs_id", id.to_string()))
             .await
             .map_err(map_surreal_error)?;
@@ -756,4 +1152,389 @@ mod tests {
         let scores: Vec<i32> = result.items.iter().map(|a| a.score).collect();
         assert_eq!(scores, vec![10, 20, 30]);
     }
+
+    // ── Keyset pagination ────────────────────────────────────────────────────
+
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    struct CursorQuery {
+        min_score: Option<i32>,
+        #[serde(skip)]
+        cursor: Option<crate::read::PageCursor>,
+    }
+
+    impl Query for CursorQuery {
+        fn filter(&self) -> Option<RestSql> {
+            let score = self.min_score?;
+            RestSql::from_ast(filter::gte("score", score as i64)).ok()
+        }
+
+        fn pagination(&self) -> Option<Pagination> {
+            Some(Pagination {
+                skip: None,
+                limit: Some(2),
+            })
+        }
+
+        fn sort(&self) -> Option<Vec<Sorter>> {
+            Some(vec![Sorter {
+                field: "score".into(),
+                direction: crate::read::sorter::SortDirection::Desc,
+            }])
+        }
+
+        fn cursor(&self) -> Option<crate::read::PageCursor> {
+            self.cursor.clone()
+        }
+    }
+
+    /// Through the real compiler and engine: the keyset condition is ANDed with the
+    /// caller's filter, the id breaks the tie on `score`, and `total` ignores the cursor.
+    #[tokio::test]
+    async fn cursors_walk_the_filtered_set_across_ties() {
+        use crate::read::PageCursor;
+
+        let store = setup_for::<CursorQuery>().await;
+        let ctx = CqrsContext::default();
+        for (id, score) in [("a1", 5), ("a2", 30), ("a3", 20), ("a4", 20), ("a5", 10)] {
+            store
+                .save(article(id, "item", score), ctx.clone())
+                .await
+                .unwrap();
+        }
+        let ids =
+            |page: &Paged<Article>| page.items.iter().map(|a| a.id.clone()).collect::<Vec<_>>();
+        let query = |cursor| CursorQuery {
+            min_score: Some(10),
+            cursor,
+        };
+
+        let first = store.filter(None, query(None), ctx.clone()).await.unwrap();
+        assert_eq!(ids(&first), ["a2", "a3"]);
+        assert!(first.prev_cursor.is_none());
+
+        let after = PageCursor::After(first.next_cursor.clone().unwrap());
+        let second = store
+            .filter(None, query(Some(after)), ctx.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            ids(&second),
+            ["a4", "a5"],
+            "a3 and a4 tie; a5 passes the filter, a1 not"
+        );
+        assert_eq!(second.total, 4);
+        assert_eq!(second.skip, 0);
+        assert!(second.next_cursor.is_none());
+
+        let before = PageCursor::Before(second.prev_cursor.clone().unwrap());
+        let back = store.filter(None, query(Some(before)), ctx).await.unwrap();
+        assert_eq!(ids(&back), ["a2", "a3"]);
+        assert!(back.prev_cursor.is_none());
+    }
+
+    #[tokio::test]
+    async fn a_tampered_cursor_is_a_validation_error() {
+        let store = setup_for::<CursorQuery>().await;
+        let err = store
+            .filter(
+                None,
+                CursorQuery {
+                    min_score: None,
+                    cursor: Some(crate::read::PageCursor::After("garbage".into())),
+                },
+                CqrsContext::default(),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, "GENERIC_VALIDATION_FAILED");
+    }
+
+    // ── Aggregation ──────────────────────────────────────────────────────────
+
+    /// Through the real engine: `NONE` and `null` keys share a group that sorts last, and
+    /// `min`/`max` order strings as well as numbers.
+    #[tokio::test]
+    async fn aggregates_group_the_filtered_set() {
+        use crate::read::Metric;
+
+        let store = setup_for::<ArticleQuery>().await;
+        let ctx = CqrsContext::default();
+        for (id, title, score) in [
+            ("a1", "rust", 5),
+            ("a2", "rust", 30),
+            ("a3", "go", 20),
+            ("a4", "go", 1),
+        ] {
+            store
+                .save(article(id, title, score), ctx.clone())
+                .await
+                .unwrap();
+        }
+        store
+            .db
+            .query("UPSERT articles:a5 SET data = { id: 'a5', score: 40 }")
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+
+        let rows = store
+            .aggregate(
+                None,
+                ArticleQuery { min_score: Some(5) },
+                Aggregation::new(
+                    ["title"],
+                    [
+                        Metric::count(),
+                        Metric::sum("score"),
+                        Metric::avg("score"),
+                        Metric::min("id"),
+                        Metric::max("score"),
+                    ],
+                ),
+                ctx,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(rows).unwrap(),
+            serde_json::json!([
+                { "group": { "title": "go" },
+                  "metrics": { "count": 1, "sum(score)": 20, "avg(score)": 20.0,
+                               "min(id)": "a3", "max(score)": 20 } },
+                { "group": { "title": "rust" },
+                  "metrics": { "count": 2, "sum(score)": 35, "avg(score)": 17.5,
+                               "min(id)": "a1", "max(score)": 30 } },
+                { "group": { "title": null },
+                  "metrics": { "count": 1, "sum(score)": 40, "avg(score)": 40.0,
+                               "min(id)": "a5", "max(score)": 40 } },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn an_aggregate_without_keys_is_one_group_even_when_empty() {
+        use crate::read::Metric;
+
+        let store = setup().await;
+        let rows = store
+            .aggregate(
+                None,
+                ArticleQuery::default(),
+                Aggregation::new(
+                    Vec::<String>::new(),
+                    [Metric::count(), Metric::max("title")],
+                ),
+                CqrsContext::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].metrics["count"], 0);
+        assert_eq!(rows[0].metrics["max(title)"], JsonValue::Null);
+    }
+
+    // ── Projection ───────────────────────────────────────────────────────────
+
+    /// Through the real engine and across a keyset: `score` drives the cursor without
+    /// being asked for, and is stripped from the items.
+    #[tokio::test]
+    async fn a_projected_page_keeps_only_the_listed_fields_and_still_pages() {
+        use crate::read::PageCursor;
+
+        let store = setup_for::<CursorQuery>().await;
+        let ctx = CqrsContext::default();
+        for (id, score) in [("a1", 5), ("a2", 30), ("a3", 20), ("a4", 20), ("a5", 10)] {
+            store
+                .save(article(id, "item", score), ctx.clone())
+                .await
+                .unwrap();
+        }
+        let projection = Projection::new(["id", "missing"]);
+        let query = |cursor| CursorQuery {
+            min_score: Some(10),
+            cursor,
+        };
+
+        let first = store
+            .filter_projected(None, query(None), projection.clone(), ctx.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            first.items,
+            [
+                serde_json::json!({ "id": "a2" }),
+                serde_json::json!({ "id": "a3" })
+            ],
+            "an absent field stays absent"
+        );
+        assert_eq!(first.total, 4);
+
+        let after = PageCursor::After(first.next_cursor.unwrap());
+        let second = store
+            .filter_projected(None, query(Some(after)), projection, ctx)
+            .await
+            .unwrap();
+        assert_eq!(
+            second.items,
+            [
+                serde_json::json!({ "id": "a4" }),
+                serde_json::json!({ "id": "a5" })
+            ]
+        );
+    }
+
+    #[derive(Debug, Clone, Default, Serialize)]
+    struct SearchQuery {
+        #[serde(skip)]
+        text: Option<&'static str>,
+    }
+
+    impl Query for SearchQuery {
+        fn search(&self) -> Option<&str> {
+            self.text
+        }
+        fn searchable_fields(&self) -> Vec<&str> {
+            vec!["title", "id"]
+        }
+        fn sort(&self) -> Option<Vec<Sorter>> {
+            Some(vec![Sorter {
+                field: "score".into(),
+                direction: crate::read::sorter::SortDirection::Asc,
+            }])
+        }
+    }
+
+    async fn searchable() -> SurrealDBStorage<Article, SearchQuery> {
+        let storage = setup_for::<SearchQuery>().await;
+        storage.ensure_search_index(&["title", "id"]).await.unwrap();
+        // Idempotent: a second call finds everything defined.
+        storage.ensure_search_index(&["title", "id"]).await.unwrap();
+        for a in [
+            article("a1", "Ticket to Ride", 10),
+            article("ride", "Ticket, ticket!", 20),
+            article("a3", "Azul", 30),
+        ] {
+            storage.save(a, CqrsContext::default()).await.unwrap();
+        }
+        storage
+    }
+
+    #[test]
+    fn each_word_is_matched_in_any_field_under_its_own_reference() {
+        let query = SearchQuery {
+            text: Some("ticket ride"),
+        };
+        let search = TextSearch::plan(&query).unwrap().unwrap();
+        assert_eq!(
+            search_condition(&search, &DataPrefixMapper),
+            "(data.title @0@ $__cqrs_search_0 OR data.id @1@ $__cqrs_search_0) AND \
+             (data.title @2@ $__cqrs_search_1 OR data.id @3@ $__cqrs_search_1)"
+        );
+        assert_eq!(
+            search_score(&search),
+            "(search::score(0) ?? 0) + (search::score(1) ?? 0) + \
+             (search::score(2) ?? 0) + (search::score(3) ?? 0)"
+        );
+    }
+
+    #[tokio::test]
+    async fn a_search_needs_every_word_in_some_field_and_issues_no_cursor() {
+        let storage = searchable().await;
+        let page = storage
+            .filter(
+                None,
+                SearchQuery {
+                    text: Some("RIDE ticket"),
+                },
+                CqrsContext::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            page.total, 2,
+            "`ride` matches one article by title, one by id"
+        );
+        let mut ids: Vec<&str> = page.items.iter().map(|a| a.id.as_str()).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, ["a1", "ride"]);
+        assert!(page.next_cursor.is_none() && page.prev_cursor.is_none());
+    }
+
+    #[tokio::test]
+    async fn a_search_narrows_projections_and_aggregates_too() {
+        let storage = searchable().await;
+        let query = SearchQuery {
+            text: Some("ticket"),
+        };
+        let page = storage
+            .filter_projected(
+                None,
+                query.clone(),
+                Projection::new(["score"]),
+                CqrsContext::default(),
+            )
+            .await
+            .unwrap();
+        let mut scores: Vec<JsonValue> = page.items;
+        scores.sort_by_key(|v| v["score"].as_i64());
+        assert_eq!(
+            scores,
+            [
+                serde_json::json!({ "score": 10 }),
+                serde_json::json!({ "score": 20 })
+            ]
+        );
+
+        let rows = storage
+            .aggregate(
+                None,
+                query,
+                Aggregation::new(Vec::<String>::new(), [crate::read::Metric::count()]),
+                CqrsContext::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(rows[0].metrics["count"], 2);
+    }
+
+    #[tokio::test]
+    async fn tenants_sharing_the_table_only_see_their_own_views() {
+        let storage = setup_for::<ArticleQuery>()
+            .await
+            .with_tenancy(crate::Tenancy::Column);
+        let tenant = |tenant: &str| CqrsContext::default().with_tenant(Some(tenant.to_string()));
+        let article = |score| Article {
+            id: "a1".into(),
+            title: "Same id".into(),
+            score,
+        };
+        storage.save(article(1), tenant("acme")).await.unwrap();
+        storage.save(article(2), tenant("globex")).await.unwrap();
+
+        let found = storage
+            .find_by_id(None, "a1", tenant("acme"))
+            .await
+            .unwrap();
+        assert_eq!(found, Some(article(1)));
+        let page = storage
+            .filter(None, ArticleQuery::default(), tenant("globex"))
+            .await
+            .unwrap();
+        assert_eq!(page.items, [article(2)]);
+        assert!(
+            storage
+                .find_by_id(None, "a1", tenant("initech"))
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let error = storage
+            .find_by_id(None, "a1", CqrsContext::default())
+            .await
+            .unwrap_err();
+        assert_eq!(error.status, 403);
+    }
 }
@@ -0,0 +1,59 @@
+//! Shared redb database.
+//!
+//! Both the event store ([`crate::es::redb`]) and the read side ([`crate::read::redb`])
+//! take an `Arc<redb::Database>`: one file, opened once by the application, whose
+//! storages each keep to their own tables. A table is created by the first write to it,
+//! so there is no schema to apply, and reading a table that does not exist yet reads
+//! nothing.
+//!
+//! redb is embedded and pure Rust: a transaction runs on the task that awaits it. It
+//! has a single writer, and a commit is durable — `fsync`ed, copy-on-write — by the time
+//! it returns, so a crash loses no committed event and never leaves half a commit.
+
+use crate::errors::CqrsError;
+use redb::backends::InMemoryBackend;
+use redb::{Database, Key, ReadOnlyTable, ReadableDatabase, TableDefinition, TableError, Value};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Opens, or creates, the database file at `path`.
+///
+/// redb locks the file: a second process opening it fails until the first closes it.
+pub fn open(path: impl AsRef<Path>) -> Result<Arc<Database>, CqrsError> {
+    Database::create(path).map(Arc::new).map_err(map_redb_error)
+}
+
+/// A private database that lives as long as its last `Arc` — for tests.
+pub fn open_in_memory() -> Result<Arc<Database>, CqrsError> {
+    Database::builder()
+        .create_with_backend(InMemoryBackend::new())
+        .map(Arc::new)
+        .map_err(map_redb_error)
+}
+
+pub(crate) fn map_redb_error(e: impl Into<redb::Error>) -> CqrsError {
+    CqrsError::database_error(e.into())
+}
+
+/// Reads `table` in a read transaction, or answers `None` when it was never written to.
+pub(crate) fn read_table<K, V, T>(
+    database: &Database,
+    table: &str,
+    read: impl FnOnce(ReadOnlyTable<K, V>) -> Result<T, CqrsError>,
+) -> Result<Option<T>, CqrsError>
+where
+    K: Key + 'static,
+    V: Value + 'static,
+{
+    let transaction = database.begin_read().map_err(map_redb_error)?;
+    match transaction.open_table(TableDefinition::<K, V>::new(table)) {
+        Ok(table) => read(table).map(Some),
+        Err(TableError::TableDoesNotExist(_)) => Ok(None),
+        Err(e) => Err(map_redb_error(e)),
+    }
+}
+
+/// A read transaction opened and closed, for the `health` of the storages.
+pub(crate) fn ping(database: &Database) -> Result<(), CqrsError> {
+    database.begin_read().map(drop).map_err(map_redb_error)
+}
@@ -0,0 +1,1245 @@
+use crate::denormalizer::Dispatcher;
+use crate::dispatchers::FailedDispatch;
+use crate::event::Event;
+use crate::read::Paged;
+use crate::{
+    Aggregate, CommandHandler, CqrsCommandEngine, CqrsContext, CqrsError, EventFilter, Policy,
+    Tenancy, TenantScope,
+};
+use axum::extract::{Path, Query, State};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Extension, Json};
+use futures::StreamExt;
+use chrono::{DateTime, Utc};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value as JsonValue, json};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
+use utoipa::openapi::path::ParameterIn;
+use utoipa::openapi::{HttpMethod, Paths, Ref, RefOr, Schema};
+use utoipa::{IntoParams, PartialSchema, ToSchema};
+use utoipa_axum::router::{OpenApiRouter, UtoipaMethodRouter};
+
+use crate::rest::{UpdateResult, helpers, security};
+
+/// How many events a job reads from the journal at a time.
+pub const DEFAULT_ADMIN_BATCH_SIZE: usize = 100;
+
+/// How long [`CQRSAdminRouter`] keeps a job after it finished.
+pub const DEFAULT_ADMIN_JOB_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How many finished jobs [`CQRSAdminRouter`] keeps at most.
+pub const DEFAULT_ADMIN_JOB_CAPACITY: usize = 100;
+
+/// The role [`CQRSAdminRouter`] requires unless told otherwise.
+pub const ADMIN_ROLE: &str = "admin";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum AdminJobKind {
+    ProjectionRebuild,
+    SnapshotRebuild,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AdminJobStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// A long-running operation of [`CQRSAdminRouter`], as `GET /admin/jobs/{job_id}`
+/// reports it.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminJob {
+    pub id: String,
+    pub kind: AdminJobKind,
+    /// The projection a rebuild feeds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub projection: Option<String>,
+    pub status: AdminJobStatus,
+    /// The events dispatched, or the snapshots rebuilt, so far.
+    pub processed: u64,
+    /// How many there are to process, as counted when the job started.
+    pub total: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The user who started the job.
+    pub user_id: String,
+    /// The tenant the job was started for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    #[schema(value_type = String)]
+    pub started_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// An event as the journal holds it.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    pub id: String,
+    pub aggregate_id: String,
+    pub version: usize,
+    pub event_type: String,
+    #[schema(value_type = Object)]
+    pub payload: JsonValue,
+    pub metadata: HashMap<String, String>,
+    #[schema(value_type = String)]
+    pub at: DateTime<Utc>,
+}
+
+/// A failed dispatch, without its events.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DispatchFailureEntry {
+    pub id: String,
+    /// The position of the dispatcher in the engine.
+    pub dispatcher: usize,
+    pub aggregate_id: String,
+    /// The versions of the events that were not handled.
+    pub versions: Vec<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    pub error: String,
+    #[schema(value_type = String)]
+    pub failed_at: DateTime<Utc>,
+    pub attempts: u32,
+}
+
+impl<A: Aggregate> From<FailedDispatch<A>> for DispatchFailureEntry {
+    fn from(failure: FailedDispatch<A>) -> Self {
+        DispatchFailureEntry {
+            id: failure.id,
+            dispatcher: failure.dispatcher,
+            aggregate_id: failure.aggregate_id,
+            versions: failure.events.iter().map(|event| event.version).collect(),
+            tenant: failure.tenant,
+            error: failure.error,
+            failed_at: failure.failed_at,
+            attempts: failure.attempts,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RebuiltSnapshot {
+    pub aggregate_id: String,
+    /// The version the snapshot was saved at.
+    pub version: usize,
+}
+
+/// A page of a journal, from 1.
+#[derive(Clone, Debug, Deserialize, IntoParams)]
+pub struct JournalQuery {
+    /// From 1; `0` reads as `1`.
+    #[serde(default)]
+    pub page: usize,
+    #[serde(default = "default_page_size")]
+    pub page_size: usize,
+}
+
+fn default_page_size() -> usize {
+    10
+}
+
+type DynDispatcher<A> = Arc<dyn Dispatcher<A> + Send + Sync>;
+
+/// Operational routes over the engine of an aggregate type, all of them behind one
+/// policy — by default, the [`ADMIN_ROLE`] role:
+/// - `GET /admin/{TYPE_id}/journal` — the raw events of an aggregate, paged.
+/// - `POST /admin/{TYPE_id}/snapshot` — rebuilds the snapshot of an aggregate from its
+///   journal.
+/// - `POST /admin/snapshots/rebuild` — the same for every aggregate, as a job.
+/// - `POST /admin/projections/{projection}/rebuild` — replays the whole journal into a
+///   registered projection, one aggregate after the other, each in version order, as a
+///   job.
+/// - `GET /admin/jobs` and `GET /admin/jobs/{job_id}` — where the jobs stand.
+/// - `GET /admin/dispatch-failures` and
+///   `POST /admin/dispatch-failures/{failure_id}/redeliver` — the dispatches the engine
+///   kept, see [`CqrsCommandEngine::with_dispatch_failures`].
+///
+/// ```rust,ignore
+/// let admin = CQRSAdminRouter::new(engine.clone())
+///     .with_projection("accounts_view", Arc::new(ViewDispatcher::new(accounts_storage)))
+///     .routes("admin");
+/// ```
+///
+/// A job answers `202` with its record and runs on a spawned task, under the context of
+/// the request that started it. A finished job is evicted once it is older than the
+/// retention, or when more finished jobs than the capacity are kept, oldest first; a
+/// running one stays until it finishes. A projection replays events it may already
+/// hold: rebuild it into emptied storage, unless its updates are idempotent. An
+/// aggregate whose first event is committed after the rebuild started is left to the
+/// engine's own dispatch.
+pub struct CQRSAdminRouter<A>
+where
+    A: Aggregate + CommandHandler + 'static,
+    A::Error: Into<CqrsError>,
+{
+    engine: Arc<CqrsCommandEngine<A>>,
+    policy: Policy<()>,
+    projections: BTreeMap<String, DynDispatcher<A>>,
+    batch_size: usize,
+    tenancy: TenantScope,
+    jobs: Arc<RwLock<BTreeMap<String, AdminJob>>>,
+    job_retention: Duration,
+    job_capacity: usize,
+}
+
+impl<A> Clone for CQRSAdminRouter<A>
+where
+    A: Aggregate + CommandHandler + 'static,
+    A::Error: Into<CqrsError>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            engine: self.engine.clone(),
+            policy: self.policy.clone(),
+            projections: self.projections.clone(),
+            batch_size: self.batch_size,
+            tenancy: self.tenancy.clone(),
+            jobs: self.jobs.clone(),
+            job_retention: self.job_retention,
+            job_capacity: self.job_capacity,
+        }
+    }
+}
+
+impl<A> CQRSAdminRouter<A>
+where
+    A: Aggregate + CommandHandler + 'static,
+    A::Error: Into<CqrsError>,
+{
+    #[must_use]
+    pub fn new(engine: Arc<CqrsCommandEngine<A>>) -> Self {
+        Self {
+            engine,
+            policy: Policy::any_role([ADMIN_ROLE]),
+            projections: BTreeMap::new(),
+            batch_size: DEFAULT_ADMIN_BATCH_SIZE,
+            tenancy: TenantScope::default(),
+            jobs: Arc::new(RwLock::new(BTreeMap::new())),
+            job_retention: DEFAULT_ADMIN_JOB_RETENTION,
+            job_capacity: DEFAULT_ADMIN_JOB_CAPACITY,
+        }
+    }
+
+    /// Who may use the routes, instead of the holders of [`ADMIN_ROLE`].
+    #[must_use]
+    pub fn with_policy(mut self, policy: Policy<()>) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// A projection `POST /admin/projections/{name}/rebuild` can replay the journal into.
+    #[must_use]
+    pub fn with_projection(
+        mut self,
+        name: impl Into<String>,
+        projection: DynDispatcher<A>,
+    ) -> Self {
+        self.projections.insert(name.into(), projection);
+        self
+    }
+
+    /// How many events a job reads at a time; [`DEFAULT_ADMIN_BATCH_SIZE`] otherwise.
+    #[must_use]
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// How long a job is kept after it finished, instead of
+    /// [`DEFAULT_ADMIN_JOB_RETENTION`].
+    #[must_use]
+    pub fn with_job_retention(mut self, retention: Duration) -> Self {
+        self.job_retention = retention;
+        self
+    }
+
+    /// How many finished jobs are kept at most, instead of [`DEFAULT_ADMIN_JOB_CAPACITY`].
+    #[must_use]
+    pub fn with_job_capacity(mut self, capacity: usize) -> Self {
+        self.job_capacity = capacity;
+        self
+    }
+
+    /// The tenancy of the engine's event store, which the dispatch failures and the jobs
+    /// are scoped by: under any but [`Tenancy::Shared`], a caller sees and redelivers the
+    /// failures, and sees the jobs, of its own tenant only.
+    #[must_use]
+    pub fn with_tenancy(mut self, tenancy: Tenancy) -> Self {
+        self.tenancy = TenantScope::new(tenancy);
+        self
+    }
+
+    fn path_aggregate_id_field() -> String {
+        format!("{}_id", A::TYPE)
+    }
+
+    fn schema_name(name: &str) -> String {
+        format!("{}_{name}", A::TYPE)
+    }
+
+    /// Documents the policy, and the status a `POST` answers with instead of `201`.
+    fn document(&self, mut paths: Paths, status: StatusCode) -> Paths {
+        for item in paths.paths.values_mut() {
+            if let Some(operation) = item.post.as_mut()
+                && let Some(response) = operation.responses.responses.remove("201")
+            {
+                operation
+                    .responses
+                    .responses
+                    .insert(status.as_u16().to_string(), response);
+            }
+        }
+        security::secure(paths, Some(&self.policy))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn route(
+        &self,
+        router: OpenApiRouter<CQRSAdminRouter<A>>,
+        tag: &str,
+        method: HttpMethod,
+        path: &str,
+        status: StatusCode,
+        response: (String, RefOr<Schema>),
+        path_parameters: Vec<&str>,
+        query_parameters: Vec<utoipa::openapi::path::Parameter>,
+        handler: axum::routing::MethodRouter<CQRSAdminRouter<A>>,
+    ) -> OpenApiRouter<CQRSAdminRouter<A>> {
+        let (response_name, response_schema) = response;
+        let mut errors = vec![StatusCode::NOT_FOUND, StatusCode::INTERNAL_SERVER_ERROR];
+        if !query_parameters.is_empty() {
+            errors.insert(0, StatusCode::BAD_REQUEST);
+        }
+        let paths = helpers::generate_route(
+            tag,
+            method,
+            path,
+            RefOr::Ref(Ref::from_schema_name(&response_name)),
+            path_parameters
+                .into_iter()
+                .map(|name| (name.to_string(), String::schema()))
+                .collect(),
+            query_parameters,
+            None,
+            &errors,
+        );
+        let schemas = vec![
+            (response_name, response_schema),
+            (AdminJobKind::name().to_string(), AdminJobKind::schema()),
+            (AdminJobStatus::name().to_string(), AdminJobStatus::schema()),
+            helpers::error_schema(),
+        ];
+        router.routes(UtoipaMethodRouter::<CQRSAdminRouter<A>>::from((
+            schemas,
+            self.document(paths, status),
+            handler,
+        )))
+    }
+
+    pub fn routes(self, tag: &'static str) -> OpenApiRouter {
+        let id = Self::path_aggregate_id_field();
+        let job = || (AdminJob::name().to_string(), AdminJob::schema());
+        let jobs = || (Self::schema_name("AdminJobs"), Vec::<AdminJob>::schema());
+        let mut result = OpenApiRouter::<CQRSAdminRouter<A>>::new();
+        result = self.route(
+            result,
+            tag,
+            HttpMethod::Get,
+            &format!("/admin/{{{id}}}/journal"),
+            StatusCode::OK,
+            (
+                Self::schema_name("Paged_Journal"),
+                Paged::<JournalEntry>::schema(),
+            ),
+            vec![&id],
+            JournalQuery::into_params(|| Some(ParameterIn::Query)),
+            get(
+                |State(router): State<CQRSAdminRouter<A>>,
+                 Path(aggregate_id): Path<String>,
+                 Query(query): Query<JournalQuery>,
+                 Extension(context): Extension<CqrsContext>| async move {
+                    router.journal(aggregate_id, query, context).await
+                },
+            ),
+        );
+        result = self.route(
+            result,
+            tag,
+            HttpMethod::Post,
+            &format!("/admin/{{{id}}}/snapshot"),
+            StatusCode::OK,
+            (
+                RebuiltSnapshot::name().to_string(),
+                RebuiltSnapshot::schema(),
+            ),
+            vec![&id],
+            vec![],
+            post(
+                |State(router): State<CQRSAdminRouter<A>>,
+                 Path(aggregate_id): Path<String>,
+                 Extension(context): Extension<CqrsContext>| async move {
+                    router.rebuild_snapshot(aggregate_id, context).await
+                },
+            ),
+        );
+        result = self.route(
+            result,
+            tag,
+            HttpMethod::Post,
+            "/admin/snapshots/rebuild",
+            StatusCode::ACCEPTED,
+            job(),
+            vec![],
+            vec![],
+            post(
+                |State(router): State<CQRSAdminRouter<A>>,
+                 Extension(context): Extension<CqrsContext>| async move {
+                    router.rebuild_snapshots(context).await
+                },
+            ),
+        );
+        result = self.route(
+            result,
+            tag,
+            HttpMethod::Post,
+            "/admin/projections/{projection}/rebuild",
+            StatusCode::ACCEPTED,
+            job(),
+            vec!["projection"],
+            vec![],
+            post(
+                |State(router): State<CQRSAdminRouter<A>>,
+                 Path(projection): Path<String>,
+                 Extension(context): Extension<CqrsContext>| async move {
+                    router.rebuild_projection(projection, context).await
+                },
+            ),
+        );
+        result = self.route(
+            result,
+            tag,
+            HttpMethod::Get,
+            "/admin/jobs",
+            StatusCode::OK,
+            jobs(),
+            vec![],
+            vec![],
+            get(
+                |State(router): State<CQRSAdminRouter<A>>,
+                 Extension(context): Extension<CqrsContext>| async move {
+                    router.jobs(context).await
+                },
+            ),
+        );
+        result = self.route(
+            result,
+            tag,
+            HttpMethod::Get,
+            "/admin/jobs/{job_id}",
+            StatusCode::OK,
+            job(),
+            vec!["job_id"],
+            vec![],
+            get(
+                |State(router): State<CQRSAdminRouter<A>>,
+                 Path(job_id): Path<String>,
+                 Extension(context): Extension<CqrsContext>| async move {
+                    router.job(job_id, context).await
+                },
+            ),
+        );
+        result = self.route(
+            result,
+            tag,
+            HttpMethod::Get,
+            "/admin/dispatch-failures",
+            StatusCode::OK,
+            (
+                Self::schema_name("DispatchFailures"),
+                Vec::<DispatchFailureEntry>::schema(),
+            ),
+            vec![],
+            vec![],
+            get(
+                |State(router): State<CQRSAdminRouter<A>>,
+                 Extension(context): Extension<CqrsContext>| async move {
+                    router.dispatch_failures(context).await
+                },
+            ),
+        );
+        result = self.route(
+            result,
+            tag,
+            HttpMethod::Post,
+            "/admin/dispatch-failures/{failure_id}/redeliver",
+            StatusCode::OK,
+            (UpdateResult::name().to_string(), UpdateResult::schema()),
+            vec!["failure_id"],
+            vec![],
+            post(
+                |State(router): State<CQRSAdminRouter<A>>,
+                 Path(failure_id): Path<String>,
+                 Extension(context): Extension<CqrsContext>| async move {
+                    router.redeliver(failure_id, context).await
+                },
+            ),
+        );
+        result.with_state(self)
+    }
+
+    /// Runs `operation` once the policy lets the context in, and answers with its result.
+    async fn authorized<T, F>(
+        &self,
+        context: &CqrsContext,
+        status: StatusCode,
+        operation: F,
+    ) -> Response
+    where
+        T: Serialize,
+        F: Future<Output = Result<T, CqrsError>>,
+    {
+        let result = match self.policy.authorize(context, &()) {
+            Ok(()) => operation.await,
+            Err(err) => Err(err),
+        };
+        match result {
+            Ok(body) => (status, Json(body)).into_response(),
+            Err(err) => err
+                .with_request_id_if_absent(context.request_id())
+                .into_response(),
+        }
+    }
+
+    pub async fn journal(
+        &self,
+        aggregate_id: String,
+        query: JournalQuery,
+        context: CqrsContext,
+    ) -> Response {
+        self.authorized(&context, StatusCode::OK, async {
+            let (events, total) = self
+                .engine
+                .store()
+                .load_events_paged(
+                    &EventFilter::aggregate(aggregate_id),
+                    query.page,
+                    query.page_size,
+                    &context,
+                )
+                .await?;
+            let items = events
+                .into_iter()
+                .map(|event| {
+                    Ok(JournalEntry {
+                        event_type: event.payload.event_type(),
+                        payload: serde_json::to_value(&event.payload)
+                            .map_err(CqrsError::serialization_error)?,
+                        id: event.event_id,
+                        aggregate_id: event.aggregate_id,
+                        version: event.version,
+                        metadata: event.metadata,
+                        at: event.at,
+                    })
+                })
+                .collect::<Result<Vec<_>, CqrsError>>()?;
+            let skip = query.page.max(1).saturating_sub(1) * query.page_size;
+            Ok(Paged::new(
+                items,
+                total,
+                skip as i64,
+                query.page_size as i64,
+            ))
+        })
+        .await
+    }
+
+    pub async fn rebuild_snapshot(&self, aggregate_id: String, context: CqrsContext) -> Response {
+        self.authorized(&context, StatusCode::OK, async {
+            let version = self
+                .engine
+                .store()
+                .rebuild_snapshot(&aggregate_id, &context)
+                .await?;
+            Ok(RebuiltSnapshot {
+                aggregate_id,
+                version,
+            })
+        })
+        .await
+    }
+
+    pub async fn rebuild_snapshots(&self, context: CqrsContext) -> Response {
+        self.authorized(&context, StatusCode::ACCEPTED, async {
+            let job = self.start(AdminJobKind::SnapshotRebuild, None, &context);
+            let (router, job_id, context) = (self.clone(), job.id.clone(), context.clone());
+            tokio::spawn(async move {
+                let outcome = router.run_snapshot_rebuild(&job_id, &context).await;
+                router.finish(&job_id, outcome);
+            });
+            Ok(job)
+        })
+        .await
+    }
+
+    pub async fn rebuild_projection(&self, projection: String, context: CqrsContext) -> Response {
+        self.authorized(&context, StatusCode::ACCEPTED, async {
+            let dispatcher = self.projections.get(&projection).cloned().ok_or_else(|| {
+                CqrsError::not_found(format!("no projection {projection:?}"))
+                    .with_details(json!({ "projection": projection }))
+            })?;
+            let job = self.start(AdminJobKind::ProjectionRebuild, Some(projection), &context);
+            let (router, job_id, context) = (self.clone(), job.id.clone(), context.clone());
+            tokio::spawn(async move {
+                let outcome = router
+                    .run_projection_rebuild(&job_id, dispatcher.as_ref(), &context)
+                    .await;
+                router.finish(&job_id, outcome);
+            });
+            Ok(job)
+        })
+        .await
+    }
+
+    pub async fn jobs(&self, context: CqrsContext) -> Response {
+        self.authorized(&context, StatusCode::OK, async {
+            let scope = self.tenancy.for_context(&context)?;
+            let now = Utc::now();
+            Ok(self
+                .read_jobs()
+                .values()
+                .filter(|job| !self.expired(job, now) && scope.admits(job.tenant.as_deref()))
+                .cloned()
+                .collect::<Vec<_>>())
+        })
+        .await
+    }
+
+    pub async fn job(&self, job_id: String, context: CqrsContext) -> Response {
+        self.authorized(&context, StatusCode::OK, async {
+            let scope = self.tenancy.for_context(&context)?;
+            let job = self.read_jobs().get(&job_id).cloned();
+            // Another tenant's job is not there, as far as the caller knows.
+            job.filter(|job| !self.expired(job, Utc::now()) && scope.admits(job.tenant.as_deref()))
+                .ok_or_else(|| {
+                    CqrsError::not_found(format!("job '{job_id}' not found"))
+                        .with_details(json!({ "id": job_id }))
+                })
+        })
+        .await
+    }
+
+    pub async fn dispatch_failures(&self, context: CqrsContext) -> Response {
+        self.authorized(&context, StatusCode::OK, async {
+            let scope = self.tenancy.for_context(&context)?;
+            let Some(failures) = self.engine.dispatch_failures() else {
+                return Ok(Vec::new());
+            };
+            Ok(failures
+                .list()
+                .await?
+                .into_iter()
+                .filter(|failure| scope.admits(failure.tenant.as_deref()))
+                .map(DispatchFailureEntry::from)
+                .collect::<Vec<_>>())
+        })
+        .await
+    }
+
+    pub async fn redeliver(&self, failure_id: String, context: CqrsContext) -> Response {
+        self.authorized(&context, StatusCode::OK, async {
+            let scope = self.tenancy.for_context(&context)?;
+            if let Some(failures) = self.engine.dispatch_failures() {
+                // Another tenant's failure is not there, as far as the caller knows.
+                let failure = failures.find(&failure_id).await?;
+                if failure.is_some_and(|failure| !scope.admits(failure.tenant.as_deref())) {
+                    return Err(CqrsError::not_found(format!(
+                        "no failed dispatch {failure_id:?}"
+                    )));
+                }
+            }
+            self.engine.redeliver(&failure_id, &context).await?;
+            Ok(UpdateResult)
+        })
+        .await
+    }
+
+    fn read_jobs(&self) -> std::sync::RwLockReadGuard<'_, BTreeMap<String, AdminJob>> {
+        self.jobs.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn expired(&self, job: &AdminJob, now: DateTime<Utc>) -> bool {
+        job.finished_at.is_some_and(|at| {
+            now.signed_duration_since(at)
+                .to_std()
+                .is_ok_and(|age| age > self.job_retention)
+        })
+    }
+
+    /// Drops the finished jobs past the retention, then the oldest past the capacity.
+    fn evict(&self, jobs: &mut BTreeMap<String, AdminJob>, now: DateTime<Utc>) {
+        jobs.retain(|_, job| !self.expired(job, now));
+        let mut finished: Vec<_> = jobs
+            .values()
+            .filter_map(|job| Some((job.finished_at?, job.id.clone())))
+            .collect();
+        if finished.len() > self.job_capacity {
+            finished.sort();
+            for (_, job_id) in &finished[..finished.len() - self.job_capacity] {
+                jobs.remove(job_id);
+            }
+        }
+    }
+
+    fn update_job(&self, job_id: &str, update: impl FnOnce(&mut AdminJob)) {
+        if let Some(job) = self
+            .jobs
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get_mut(job_id)
+        {
+            update(job);
+        }
+    }
+
+    /// Records a running job, started by the user of `context`.
+    fn start(
+        &self,
+        kind: AdminJobKind,
+        projection: Option<String>,
+        context: &CqrsContext,
+    ) -> AdminJob {
+        let job = AdminJob {
+            id: context.next_uuid(),
+            kind,
+            projection,
+            status: AdminJobStatus::Running,
+            processed: 0,
+            total: 0,
+            error: None,
+            user_id: context.current_user(),
+            tenant: context.tenant().map(str::to_string),
+            started_at: context.now(),
+            finished_at: None,
+        };
+        let mut jobs = self.jobs.write().unwrap_or_else(PoisonError::into_inner);
+        jobs.insert(job.id.clone(), job.clone());
+        self.evict(&mut jobs, Utc::now());
+        job
+    }
+
+    fn finish(&self, job_id: &str, outcome: Result<(), CqrsError>) {
+        if let Err(err) = &outcome {
+            tracing::error!(job_id = %job_id, error = %err.message, "admin job failed");
+        }
+        self.update_job(job_id, |job| {
+            match outcome {
+                Ok(()) => job.status = AdminJobStatus::Succeeded,
+                Err(err) => {
+                    job.status = AdminJobStatus::Failed;
+                    job.error = Some(err.message.clone());
+                }
+            }
+            job.finished_at = Some(Utc::now());
+        });
+        let mut jobs = self.jobs.write().unwrap_or_else(PoisonError::into_inner);
+        self.evict(&mut jobs, Utc::now());
+    }
+
+    /// Every aggregate of the journal, by id, and how many events the journal held when
+    /// it was first read. An event committed meanwhile can only push the events after it
+    /// to a later page, so paging may read an aggregate twice but never misses one.
+    async fn aggregate_ids(
+        &self,
+        context: &CqrsContext,
+    ) -> Result<(BTreeSet<String>, i64), CqrsError> {
+        let mut ids = BTreeSet::new();
+        let mut events_total = None;
+        let mut page = 1;
+        loop {
+            let (events, total) = self
+                .engine
+                .store()
+                .load_events_paged(&EventFilter::default(), page, self.batch_size, context)
+                .await?;
+            let events_total = *events_total.get_or_insert(total);
+            let read = events.len();
+            ids.extend(events.into_iter().map(|event| event.aggregate_id));
+            if read < self.batch_size || (page * self.batch_size) as i64 >= total {
+                return Ok((ids, events_total));
+            }
+            page += 1;
+        }
+    }
+
+    async fn run_snapshot_rebuild(
+        &self,
+        job_id: &str,
+        context: &CqrsContext,
+    ) -> Result<(), CqrsError> {
+        let (ids, _) = self.aggregate_ids(context).await?;
+        self.update_job(job_id, |job| job.total = ids.len() as u64);
+        for aggregate_id in ids {
+            self.engine
+                .store()
+                .rebuild_snapshot(&aggregate_id, context)
+                .await
+                .map_err(|e| {
+                    CqrsError::internal(format!("aggregate {aggregate_id:?}: {}", e.message))
+                })?;
+            self.update_job(job_id, |job| job.processed += 1);
+        }
+        Ok(())
+    }
+
+    async fn run_projection_rebuild(
+        &self,
+        job_id: &str,
+        projection: &(dyn Dispatcher<A> + Send + Sync),
+        context: &CqrsContext,
+    ) -> Result<(), CqrsError> {
+        let (ids, total) = self.aggregate_ids(context).await?;
+        self.update_job(job_id, |job| job.total = total.max(0) as u64);
+        // One aggregate at a time, each from the version the previous batch ended at: a
+        // batch holds the events of one aggregate only, in order, however many are
+        // committed during the rebuild.
+        for aggregate_id in ids {
+            let mut version = 0;
+            loop {
+                let batch = self
+                    .engine
+                    .store()
+                    .load_events_from_version(&aggregate_id, version, context)
+                    .await?
+                    .take(self.batch_size)
+                    .collect::<Vec<_>>()
+                    .await
+                    .into_iter()
+                    .collect::<Result<Vec<_>, CqrsError>>()?;
+                let Some(last) = batch.last() else {
+                    break;
+                };
+                version = last.version;
+                projection.dispatch(&aggregate_id, &batch, context).await?;
+                self.update_job(job_id, |job| job.processed += batch.len() as u64);
+                if batch.len() < self.batch_size {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::es::EventStoreImpl;
+    use crate::es::inmemory::InMemoryPersist;
+    use crate::dispatchers::{DynDispatchFailureStore, InMemoryDispatchFailures};
+    use crate::testing::{CreateCommand, TestAggregate, UpdateCommand};
+    use crate::{DynEventStore, EventEnvelope};
+    use serde_json::Value;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    /// Records the versions it is handed, per aggregate.
+    #[derive(Default)]
+    struct Recorder {
+        seen: Mutex<Vec<(String, usize)>>,
+    }
+
+    crate::cqrs_async_trait! {
+    impl Dispatcher<TestAggregate> for Recorder {
+        async fn dispatch(
+            &self,
+            aggregate_id: &str,
+            events: &[EventEnvelope<TestAggregate>],
+            _context: &CqrsContext,
+        ) -> Result<(), CqrsError> {
+            let mut seen = self.seen.lock().unwrap();
+            seen.extend(events.iter().map(|event| (aggregate_id.to_string(), event.version)));
+            Ok(())
+        }
+    }
+    }
+
+    async fn body(response: Response) -> Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    fn admin() -> CqrsContext {
+        CqrsContext::new(Some("root".to_string())).with_roles(vec![ADMIN_ROLE.to_string()])
+    }
+
+    /// Dispatches one run of events per permit, so that a test holds a job midway.
+    struct Gate {
+        permits: Arc<tokio::sync::Semaphore>,
+    }
+
+    crate::cqrs_async_trait! {
+    impl Dispatcher<TestAggregate> for Gate {
+        async fn dispatch(
+            &self,
+            _aggregate_id: &str,
+            _events: &[EventEnvelope<TestAggregate>],
+            _context: &CqrsContext,
+        ) -> Result<(), CqrsError> {
+            self.permits.acquire().await.unwrap().forget();
+            Ok(())
+        }
+    }
+    }
+
+    /// An engine over two aggregates of two events each.
+    async fn engine() -> Arc<CqrsCommandEngine<TestAggregate>> {
+        let store: DynEventStore<TestAggregate> = EventStoreImpl::new(InMemoryPersist::new());
+        let engine = Arc::new(CqrsCommandEngine::new(store, vec![], (), Box::new(|_| {})));
+        let context = CqrsContext::default();
+        for name in ["one", "two"] {
+            let id = engine
+                .execute_create(
+                    CreateCommand::Initialize {
+                        name: name.to_string(),
+                    },
+                    &context,
+                )
+                .await
+                .unwrap();
+            engine
+                .execute_update(&id, UpdateCommand::Increment, &context)
+                .await
+                .unwrap();
+        }
+        engine
+    }
+
+    /// The job once `done` says it is.
+    async fn job_until(
+        router: &CQRSAdminRouter<TestAggregate>,
+        job_id: &str,
+        done: impl Fn(&Value) -> bool,
+    ) -> Value {
+        loop {
+            let job = body(router.job(job_id.to_string(), admin()).await).await;
+            if done(&job) {
+                return job;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn a_projection_rebuild_replays_the_journal_and_reports_its_progress() {
+        let recorder = Arc::new(Recorder::default());
+        let router = CQRSAdminRouter::new(engine().await)
+            .with_projection("counters", recorder.clone())
+            .with_batch_size(3);
+
+        let user = CqrsContext::new(Some("alice".to_string()));
+        let response = router
+            .rebuild_projection("counters".to_string(), user)
+            .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = router.rebuild_projection("nope".to_string(), admin()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = router
+            .rebuild_projection("counters".to_string(), admin())
+            .await;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let job_id = body(response).await["id"].as_str().unwrap().to_string();
+        let job = job_until(&router, &job_id, |job| job["status"] != "running").await;
+        assert_eq!(job["status"], "succeeded", "{job}");
+        assert_eq!(
+            (job["processed"].as_u64(), job["total"].as_u64()),
+            (Some(4), Some(4))
+        );
+        assert_eq!(job["userId"], "root");
+        let seen = recorder.seen.lock().unwrap().clone();
+        assert_eq!(seen.len(), 4);
+        assert!(seen.iter().all(|(_, version)| *version <= 2));
+
+        let jobs = body(router.jobs(admin()).await).await;
+        assert_eq!(jobs.as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn a_projection_rebuild_replays_each_aggregate_whole_and_in_order() {
+        let store: DynEventStore<TestAggregate> = EventStoreImpl::new(InMemoryPersist::new());
+        let engine = Arc::new(CqrsCommandEngine::new(store, vec![], (), Box::new(|_| {})));
+        let context = CqrsContext::default();
+        let mut ids = Vec::new();
+        for name in ["one", "two"] {
+            let command = CreateCommand::Initialize {
+                name: name.to_string(),
+            };
+            ids.push(engine.execute_create(command, &context).await.unwrap());
+        }
+        // Commits of the two aggregates, interleaved in the journal.
+        for _ in 0..2 {
+            for id in &ids {
+                engine
+                    .execute_update(id, UpdateCommand::Increment, &context)
+                    .await
+                    .unwrap();
+            }
+        }
+        let recorder = Arc::new(Recorder::default());
+        let router = CQRSAdminRouter::new(engine)
+            .with_projection("counters", recorder.clone())
+            .with_batch_size(1);
+
+        let response = router
+            .rebuild_projection("counters".to_string(), admin())
+            .await;
+        let job_id = body(response).await["id"].as_str().unwrap().to_string();
+        let job = job_until(&router, &job_id, |job| job["status"] != "running").await;
+        assert_eq!(job["processed"], 6, "{job}");
+
+        ids.sort();
+        let expected: Vec<_> = ids
+            .iter()
+            .flat_map(|id| (1..=3).map(move |version| (id.clone(), version)))
+            .collect();
+        assert_eq!(*recorder.seen.lock().unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn dispatch_failures_are_scoped_to_the_callers_tenant() {
+        let store: DynEventStore<TestAggregate> = EventStoreImpl::new(InMemoryPersist::new());
+        let failures: DynDispatchFailureStore<TestAggregate> =
+            Arc::new(InMemoryDispatchFailures::new());
+        let recorder: Box<dyn Dispatcher<TestAggregate> + Send + Sync> =
+            Box::new(Recorder::default());
+        let engine = CqrsCommandEngine::new(store, vec![recorder], (), Box::new(|_| {}))
+            .with_dispatch_failures(failures.clone());
+        let router = CQRSAdminRouter::new(Arc::new(engine)).with_tenancy(Tenancy::Column);
+        let admin_of = |tenant: &str| admin().with_tenant(Some(tenant.to_string()));
+        let mut ids = HashMap::new();
+        for tenant in ["acme", "globex"] {
+            let error = CqrsError::internal("down");
+            let failure = FailedDispatch::new(0, "a1", &[], &error, &admin_of(tenant));
+            ids.insert(tenant, failure.id.clone());
+            failures.save(failure).await.unwrap();
+        }
+
+        let listed = body(router.dispatch_failures(admin_of("acme")).await).await;
+        assert_eq!(listed.as_array().unwrap().len(), 1);
+        assert_eq!(listed[0]["id"], ids["acme"].as_str());
+        let response = router.dispatch_failures(admin()).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "no tenant");
+
+        let response = router
+            .redeliver(ids["globex"].clone(), admin_of("acme"))
+            .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(failures.list().await.unwrap().len(), 2);
+        let response = router
+            .redeliver(ids["acme"].clone(), admin_of("acme"))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(failures.list().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn jobs_are_scoped_to_the_callers_tenant() {
+        let router = CQRSAdminRouter::new(engine().await).with_tenancy(Tenancy::Column);
+        let admin_of = |tenant: &str| admin().with_tenant(Some(tenant.to_string()));
+        let acme = router.start(AdminJobKind::SnapshotRebuild, None, &admin_of("acme"));
+        let globex = router.start(AdminJobKind::SnapshotRebuild, None, &admin_of("globex"));
+
+        let listed = body(router.jobs(admin_of("acme")).await).await;
+        assert_eq!(listed.as_array().unwrap().len(), 1);
+        assert_eq!(listed[0]["id"], acme.id.as_str());
+        assert_eq!(listed[0]["tenant"], "acme");
+        let response = router.jobs(admin()).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "no tenant");
+
+        let response = router.job(globex.id.clone(), admin_of("acme")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = router.job(acme.id.clone(), admin_of("acme")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn finished_jobs_are_evicted_past_the_retention_and_the_capacity() {
+        let router = CQRSAdminRouter::new(engine().await)
+            .with_job_retention(Duration::from_secs(60))
+            .with_job_capacity(1);
+        let ids = || -> BTreeSet<String> { router.read_jobs().keys().cloned().collect() };
+
+        let old = router.start(AdminJobKind::SnapshotRebuild, None, &admin());
+        router.finish(&old.id, Ok(()));
+        router.update_job(&old.id, |job| {
+            job.finished_at = Some(Utc::now() - chrono::Duration::minutes(2));
+        });
+        let response = router.job(old.id.clone(), admin()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "expired");
+
+        let running = router.start(AdminJobKind::SnapshotRebuild, None, &admin());
+        assert_eq!(ids(), BTreeSet::from([running.id.clone()]));
+
+        let first = router.start(AdminJobKind::SnapshotRebuild, None, &admin());
+        router.finish(&first.id, Ok(()));
+        let second = router.start(AdminJobKind::SnapshotRebuild, None, &admin());
+        router.finish(&second.id, Err(CqrsError::internal("down")));
+        assert_eq!(
+            ids(),
+            BTreeSet::from([running.id.clone(), second.id.clone()]),
+            "the oldest finished job goes, the running one stays"
+        );
+        let jobs = body(router.jobs(admin()).await).await;
+        assert_eq!(jobs.as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn a_caller_without_the_role_is_refused_every_route() {
+        let router = CQRSAdminRouter::new(engine().await);
+        for (context, status) in [
+            (CqrsContext::default(), StatusCode::UNAUTHORIZED),
+            (
+                CqrsContext::new(Some("alice".to_string())).with_roles(vec!["ops".to_string()]),
+                StatusCode::FORBIDDEN,
+            ),
+        ] {
+            let query = JournalQuery {
+                page: 1,
+                page_size: 10,
+            };
+            let responses = [
+                router
+                    .journal("a1".to_string(), query, context.clone())
+                    .await,
+                router
+                    .rebuild_snapshot("a1".to_string(), context.clone())
+                    .await,
+                router.rebuild_snapshots(context.clone()).await,
+                router
+                    .rebuild_projection("counters".to_string(), context.clone())
+                    .await,
+                router.jobs(context.clone()).await,
+                router.job("j1".to_string(), context.clone()).await,
+                router.dispatch_failures(context.clone()).await,
+                router.redeliver("f1".to_string(), context.clone()).await,
+            ];
+            for response in responses {
+                assert_eq!(response.status(), status);
+            }
+        }
+        let jobs = body(router.jobs(admin()).await).await;
+        assert_eq!(
+            jobs,
+            serde_json::json!([]),
+            "a refused rebuild starts no job"
+        );
+
+        let ops = CqrsContext::new(Some("alice".to_string())).with_roles(vec!["ops".to_string()]);
+        let router = router.with_policy(Policy::any_role(["ops"]));
+        assert_eq!(router.jobs(ops).await.status(), StatusCode::OK);
+        assert_eq!(router.jobs(admin()).await.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn a_running_job_reports_how_far_it_has_got() {
+        let permits = Arc::new(tokio::sync::Semaphore::new(0));
+        let router = CQRSAdminRouter::new(engine().await)
+            .with_projection(
+                "counters",
+                Arc::new(Gate {
+                    permits: permits.clone(),
+                }),
+            )
+            .with_batch_size(3);
+        let response = router
+            .rebuild_projection("counters".to_string(), admin())
+            .await;
+        let job_id = body(response).await["id"].as_str().unwrap().to_string();
+
+        // A batch per aggregate, the first let through.
+        permits.add_permits(1);
+        let job = job_until(&router, &job_id, |job| job["processed"] == 2).await;
+        assert_eq!(job["status"], "running");
+        assert_eq!(job["total"], 4);
+        assert!(job.get("finishedAt").is_none());
+
+        permits.add_permits(1);
+        let job = job_until(&router, &job_id, |job| job["status"] != "running").await;
+        assert_eq!(job["status"], "succeeded", "{job}");
+        assert_eq!(job["processed"], 4);
+        assert!(job["finishedAt"].is_string());
+    }
+
+    #[tokio::test]
+    async fn snapshots_are_rebuilt_and_journals_read_raw() {
+        let store: DynEventStore<TestAggregate> = EventStoreImpl::new(InMemoryPersist::new());
+        let engine = Arc::new(CqrsCommandEngine::new(
+            store.clone(),
+            vec![],
+            (),
+            Box::new(|_| {}),
+        ));
+        let context = CqrsContext::default();
+        let id = engine
+            .execute_create(
+                CreateCommand::Initialize {
+                    name: "counter".to_string(),
+                },
+                &context,
+            )
+            .await
+            .unwrap();
+        for _ in 0..2 {
+            engine
+                .execute_update(&id, UpdateCommand::Increment, &context)
+                .await
+                .unwrap();
+        }
+        let router = CQRSAdminRouter::new(engine);
+
+        let query = JournalQuery {
+            page: 1,
+            page_size: 2,
+        };
+        let page = body(router.journal(id.clone(), query, admin()).await).await;
+        assert_eq!(page["total"], 3);
+        assert_eq!(page["items"][0]["eventType"], "Created");
+        assert_eq!(page["items"][0]["payload"]["Created"]["name"], "counter");
+
+        let response = router.rebuild_snapshot(id.clone(), admin()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body(response).await["version"], 3);
+        let (_, version) = store.load_aggregate(&id, &context).await.unwrap();
+        assert_eq!(version, 3);
+        let response = router
+            .rebuild_snapshot("unknown".to_string(), admin())
+            .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let (_, api) = router.routes("admin").split_for_parts();
+        let path = format!("/admin/{{{}_id}}/snapshot", TestAggregate::TYPE);
+        let operation = api.paths.paths[&path].post.as_ref().unwrap();
+        assert!(operation.security.is_some());
+        assert!(operation.responses.responses.contains_key("200"));
+        let rebuild = api.paths.paths["/admin/snapshots/rebuild"]
+            .post
+            .as_ref()
+            .unwrap();
+        assert!(rebuild.responses.responses.contains_key("202"));
+    }
+}
@@ -1,6 +1,9 @@
 use crate::event::Event;
 use crate::read::Paged;
-use crate::{Aggregate, CqrsContext, DynEventStore, EventEnvelope};
+use crate::rest::security;
+use crate::{
+    Aggregate, CqrsContext, CqrsError, DynEventStore, EventEnvelope, EventFilter, ReadPolicies,
+};
 use axum::extract::{Path, Query, State};
 use axum::response::IntoResponse;
 use axum::routing::get;
@@ -8,7 +11,10 @@ use axum::{Extension, Json};
 use chrono::{DateTime, Utc};
 use http::StatusCode;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use futures::StreamExt;
+use serde_json::Value as JsonValue;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
 use utoipa::openapi::path::ParameterIn;
 use utoipa::openapi::{HttpMethod, Ref, RefOr};
 use utoipa::{IntoParams, PartialSchema, ToSchema};
@@ -16,6 +22,10 @@ use utoipa_axum::router::{OpenApiRouter, UtoipaMethodRouter};
 
 use crate::rest::helpers;
 
+/// How many events a page of an audit log holds at most: a larger `page_size` reads as
+/// this one, as each entry may replay its aggregate for its details.
+pub const MAX_AUDIT_LOG_PAGE_SIZE: usize = 100;
+
 #[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AuditLogEntry {
@@ -26,6 +36,22 @@ pub struct AuditLogEntry {
     pub metadata: HashMap<String, String>,
     #[schema(value_type = String)]
     pub at: DateTime<Utc>,
+    /// The event, redacted; with [`AuditDetails`] only, as are the fields below.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub payload: Option<JsonValue>,
+    /// The aggregate before the event, redacted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub before: Option<JsonValue>,
+    /// The aggregate after the event, redacted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub after: Option<JsonValue>,
+    /// The RFC 6902 JSON Patch from `before` to `after`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Vec<Object>>)]
+    pub diff: Option<JsonValue>,
 }
 
 impl<A: Aggregate> From<EventEnvelope<A>> for AuditLogEntry {
@@ -37,16 +63,200 @@ impl<A: Aggregate> From<EventEnvelope<A>> for AuditLogEntry {
             event_type: envelope.payload.event_type(),
             metadata: envelope.metadata,
             at: envelope.at,
+            payload: None,
+            before: None,
+            after: None,
+            diff: None,
         }
     }
 }
 
+/// What stands in for a redacted value.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// The detailed audit mode of [`CQRSAuditLogRouter::routes_with_details`]: each entry
+/// carries its payload, the aggregate before and after the event, and the JSON Patch
+/// between the two.
+///
+/// The states are rebuilt by replaying each aggregate of the page, from its latest
+/// snapshot when that is not past the page's first event of it, from its first event
+/// otherwise.
+/// Redaction applies to payloads and states before the diff is taken, so the diff of a
+/// redacted field is empty rather than a leak.
+///
+/// ```rust
+/// # use cqrs_rust_lib::rest::AuditDetails;
+/// let details = AuditDetails::new()
+///     .with_redacted_field("password")
+///     .with_redacted_field("iban");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct AuditDetails {
+    redacted_fields: Vec<String>,
+}
+
+impl AuditDetails {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the value of every `field` key, at any depth, with [`REDACTED`].
+    #[must_use]
+    pub fn with_redacted_field(mut self, field: impl Into<String>) -> Self {
+        self.redacted_fields.push(field.into());
+        self
+    }
+
+    fn redact(&self, mut value: JsonValue) -> JsonValue {
+        if !self.redacted_fields.is_empty() {
+            self.redact_in(&mut value);
+        }
+        value
+    }
+
+    fn redact_in(&self, value: &mut JsonValue) {
+        match value {
+            JsonValue::Object(fields) => {
+                for (key, field) in fields.iter_mut() {
+                    if self.redacted_fields.contains(key) {
+                        *field = JsonValue::String(REDACTED.to_string());
+                    } else {
+                        self.redact_in(field);
+                    }
+                }
+            }
+            JsonValue::Array(items) => items.iter_mut().for_each(|item| self.redact_in(item)),
+            _ => {}
+        }
+    }
+
+    fn serialize<T: Serialize>(&self, value: &T) -> Result<JsonValue, CqrsError> {
+        serde_json::to_value(value)
+            .map(|value| self.redact(value))
+            .map_err(CqrsError::serialization_error)
+    }
+
+    /// The entries of `events`, with their details.
+    async fn entries<A: Aggregate + 'static>(
+        &self,
+        store: &DynEventStore<A>,
+        events: Vec<EventEnvelope<A>>,
+        context: &CqrsContext,
+    ) -> Result<Vec<AuditLogEntry>, CqrsError> {
+        // The states each aggregate of the page is wanted in: before and after its events.
+        let mut wanted: BTreeMap<String, BTreeSet<usize>> = BTreeMap::new();
+        for event in &events {
+            wanted
+                .entry(event.aggregate_id.clone())
+                .or_default()
+                .extend([event.version.saturating_sub(1), event.version]);
+        }
+        let mut states: HashMap<(String, usize), JsonValue> = HashMap::new();
+        for (aggregate_id, versions) in wanted {
+            let first = versions.first().copied().unwrap_or_default();
+            let last = versions.last().copied().unwrap_or_default();
+            // The latest snapshot saves the replay up to it, unless it is past the states
+            // wanted: the store keeps no older one.
+            let (mut aggregate, version) = match store.load_snapshot(&aggregate_id, context).await?
+            {
+                Some(snapshot) if snapshot.version <= first => (snapshot.state, snapshot.version),
+                _ => (A::default().with_aggregate_id(aggregate_id.clone()), 0),
+            };
+            if versions.contains(&version) {
+                states.insert((aggregate_id.clone(), version), self.serialize(&aggregate)?);
+            }
+            let mut stream = store
+                .load_events_from_version(&aggregate_id, version, context)
+                .await?;
+            while let Some(event) = stream.next().await {
+                let event = event?;
+                if event.version > last {
+                    break;
+                }
+                aggregate
+                    .apply(event.payload)
+                    .map_err(CqrsError::user_error)?;
+                if versions.contains(&event.version) {
+                    states.insert(
+                        (aggregate_id.clone(), event.version),
+                        self.serialize(&aggregate)?,
+                    );
+                }
+            }
+        }
+
+        events
+            .into_iter()
+            .map(|event| {
+                let payload = self.serialize(&event.payload)?;
+                let before =
+                    states.get(&(event.aggregate_id.clone(), event.version.saturating_sub(1)));
+                let after = states.get(&(event.aggregate_id.clone(), event.version));
+                let diff = match (before, after) {
+                    (Some(before), Some(after)) => Some(
+                        serde_json::to_value(json_patch::diff(before, after))
+                            .map_err(CqrsError::serialization_error)?,
+                    ),
+                    _ => None,
+                };
+                let (before, after) = (before.cloned(), after.cloned());
+                Ok(AuditLogEntry {
+                    payload: Some(payload),
+                    before,
+                    after,
+                    diff,
+                    ..AuditLogEntry::from(event)
+                })
+            })
+            .collect()
+    }
+}
+
+/// A page of an audit log, pushed down to the event store as an [`EventFilter`].
 #[derive(Clone, Debug, Deserialize, IntoParams)]
 pub struct AuditLogQuery {
+    /// From 1; `0` reads as `1`.
     #[serde(default)]
     pub page: usize,
+    /// At most [`MAX_AUDIT_LOG_PAGE_SIZE`].
     #[serde(default = "default_page_size")]
     pub page_size: usize,
+    /// Event types, comma-separated; the events of any of them.
+    pub event_type: Option<String>,
+    /// The user who committed the events.
+    pub user_id: Option<String>,
+    /// RFC 3339; inclusive.
+    #[param(value_type = Option<String>)]
+    pub from: Option<DateTime<Utc>>,
+    /// RFC 3339; exclusive.
+    #[param(value_type = Option<String>)]
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl AuditLogQuery {
+    /// The `page_size` asked for, down to [`MAX_AUDIT_LOG_PAGE_SIZE`].
+    pub fn page_size(&self) -> usize {
+        self.page_size.min(MAX_AUDIT_LOG_PAGE_SIZE)
+    }
+
+    /// The events of `aggregate_id`, or of the whole aggregate type, this query selects.
+    pub fn filter(&self, aggregate_id: Option<String>) -> EventFilter {
+        EventFilter {
+            aggregate_id,
+            event_types: self
+                .event_type
+                .iter()
+                .flat_map(|types| types.split(','))
+                .map(str::trim)
+                .filter(|event_type| !event_type.is_empty())
+                .map(str::to_string)
+                .collect(),
+            user_id: self.user_id.clone(),
+            from: self.from,
+            to: self.to,
+        }
+    }
 }
 
 fn default_page_size() -> usize {
@@ -60,6 +270,8 @@ where
 {
     _phantom: std::marker::PhantomData<A>,
     store: DynEventStore<A>,
+    details: Option<AuditDetails>,
+    policies: Arc<ReadPolicies>,
 }
 
 impl<A> CQRSAuditLogRouter<A>
@@ -67,10 +279,12 @@ where
     A: Aggregate + 'static,
 {
     #[must_use]
-    fn new(store: DynEventStore<A>) -> Self {
+    fn new(store: DynEventStore<A>, details: Option<AuditDetails>, policies: ReadPolicies) -> Self {
         Self {
             _phantom: std::marker::PhantomData,
             store,
+            details,
+            policies: Arc::new(policies),
         }
     }
 
@@ -78,11 +292,7 @@ where
         format!("{}_id", A::TYPE)
     }
 
-    fn audit_log_route(
-        router: OpenApiRouter<CQRSAuditLogRouter<A>>,
-        tag: &str,
-    ) -> OpenApiRouter<CQRSAuditLogRouter<A>> {
-        let path = format!("/{{{}}}/audit", Self::path_aggregate_id_field());
+    fn schemas() -> (String, Vec<(String, RefOr<utoipa::openapi::Schema>)>) {
         let response_schema_name = format!("Paged_{}_AuditLog", A::TYPE);
         let schemas = vec![
             (
@@ -91,6 +301,16 @@ where
             ),
             helpers::error_schema(),
         ];
+        (response_schema_name, schemas)
+    }
+
+    fn audit_log_route(
+        router: OpenApiRouter<CQRSAuditLogRouter<A>>,
+        tag: &str,
+        policies: &ReadPolicies,
+    ) -> OpenApiRouter<CQRSAuditLogRouter<A>> {
+        let path = format!("/{{{}}}/audit", Self::path_aggregate_id_field());
+        let (response_schema_name, schemas) = Self::schemas();
 
         let paths = helpers::generate_route(
             tag,
@@ -106,13 +326,46 @@ where
                 StatusCode::INTERNAL_SERVER_ERROR,
             ],
         );
+        let paths = security::secure(paths, policies.find_one());
 
         let handler = get(
             move |State(router): State<CQRSAuditLogRouter<A>>,
                   Path(aggregate_id): Path<String>,
                   Query(query): Query<AuditLogQuery>,
-                  Extension(_context): Extension<CqrsContext>| async move {
-                Self::get_audit_log(router, aggregate_id, query).await
+                  Extension(context): Extension<CqrsContext>| async move {
+                Self::get_audit_log(router, Some(aggregate_id), query, context).await
+            },
+        );
+
+        router.routes(UtoipaMethodRouter::<CQRSAuditLogRouter<A>>::from((
+            schemas, paths, handler,
+        )))
+    }
+
+    /// `GET /audit`: the audit log of every aggregate of the type, oldest first.
+    fn type_audit_log_route(
+        router: OpenApiRouter<CQRSAuditLogRouter<A>>,
+        tag: &str,
+        policies: &ReadPolicies,
+    ) -> OpenApiRouter<CQRSAuditLogRouter<A>> {
+        let (response_schema_name, schemas) = Self::schemas();
+        let paths = helpers::generate_route(
+            tag,
+            HttpMethod::Get,
+            "/audit",
+            RefOr::Ref(Ref::from_schema_name(response_schema_name)),
+            vec![],
+            AuditLogQuery::into_params(|| Some(ParameterIn::Query)),
+            None,
+            &[StatusCode::BAD_REQUEST, StatusCode::INTERNAL_SERVER_ERROR],
+        );
+        let paths = security::secure(paths, policies.find_many());
+
+        let handler = get(
+            move |State(router): State<CQRSAuditLogRouter<A>>,
+                  Query(query): Query<AuditLogQuery>,
+                  Extension(context): Extension<CqrsContext>| async move {
+                Self::get_audit_log(router, None, query, context).await
             },
         );
 
@@ -122,34 +375,479 @@ where
     }
 
     pub fn routes(store: DynEventStore<A>, tag: &'static str) -> OpenApiRouter {
-        let state = Self::new(store);
+        Self::routes_with_policies(store, tag, ReadPolicies::default())
+    }
+
+    /// [`Self::routes`], each route checking its policy before the event store is read:
+    /// `find_one` for the log of one aggregate, `find_many` for the log of the type.
+    pub fn routes_with_policies(
+        store: DynEventStore<A>,
+        tag: &'static str,
+        policies: ReadPolicies,
+    ) -> OpenApiRouter {
+        Self::build(Self::new(store, None, policies), tag)
+    }
+
+    /// [`Self::routes`] in the detailed audit mode of `details`: the entries carry their
+    /// payload, the aggregate before and after them, and the diff.
+    ///
+    /// Each page replays its aggregates up to its last event of each, from their latest
+    /// snapshot when the page does not start before it: a page of an old stretch of a
+    /// long journal replays the journal from its first event, for every request.
+    pub fn routes_with_details(
+        store: DynEventStore<A>,
+        tag: &'static str,
+        details: AuditDetails,
+    ) -> OpenApiRouter {
+        Self::routes_with_details_and_policies(store, tag, details, ReadPolicies::default())
+    }
+
+    /// [`Self::routes_with_details`], behind `policies` as
+    /// [`Self::routes_with_policies`].
+    pub fn routes_with_details_and_policies(
+        store: DynEventStore<A>,
+        tag: &'static str,
+        details: AuditDetails,
+        policies: ReadPolicies,
+    ) -> OpenApiRouter {
+        Self::build(Self::new(store, Some(details), policies), tag)
+    }
+
+    fn build(state: Self, tag: &'static str) -> OpenApiRouter {
         let mut result = OpenApiRouter::<CQRSAuditLogRouter<A>>::new();
-        result = Self::audit_log_route(result, tag);
+        result = Self::audit_log_route(result, tag, &state.policies);
+        result = Self::type_audit_log_route(result, tag, &state.policies);
         result.with_state(state)
     }
 
     async fn get_audit_log(
         router: CQRSAuditLogRouter<A>,
-        aggregate_id: String,
+        aggregate_id: Option<String>,
         query: AuditLogQuery,
+        context: CqrsContext,
     ) -> impl IntoResponse {
-        match router
-            .store
-            .load_events_paged(&aggregate_id, query.page, query.page_size)
+        let policy = match aggregate_id {
+            Some(_) => router.policies.find_one(),
+            None => router.policies.find_many(),
+        };
+        if let Err(err) = security::authorize_read(policy, &context) {
+            return err.into_response();
+        }
+        let filter = query.filter(aggregate_id);
+        let page_size = query.page_size();
+        let page = async {
+            let (events, total) = router
+                .store
+                .load_events_paged(&filter, query.page, page_size, &context)
+                .await?;
+            let items = match &router.details {
+                Some(details) => details.entries(&router.store, events, &context).await?,
+                None => events.into_iter().map(AuditLogEntry::from).collect(),
+            };
+            // The event stores count pages from 1, and read 0 as 1.
+            let skip = query.page.max(1).saturating_sub(1) * page_size;
+            Ok::<_, CqrsError>(Paged::new(items, total, skip as i64, page_size as i64))
+        };
+        match page.await {
+            Ok(response) => (StatusCode::OK, Json(response)).into_response(),
+            Err(err) => err.into_response(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::es::EventStoreImpl;
+    use crate::es::inmemory::InMemoryPersist;
+    use crate::es::storage::EventStoreStorage;
+    use crate::testing::{CreateCommand, TestAggregate, TestEvent, UpdateCommand};
+    use crate::{CqrsCommandEngine, USER_ID_METADATA};
+    use http::Uri;
+    use serde_json::{Value, json};
+
+    async fn body(response: axum::response::Response) -> Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    /// Writes `events` — aggregate, version, event — to the journal, a second apart and
+    /// in the order given, without snapshots.
+    async fn journal(
+        persist: &InMemoryPersist<TestAggregate>,
+        events: &[(&str, usize, TestEvent)],
+    ) {
+        let start = Utc::now();
+        let mut session = persist.start_session().await.unwrap();
+        for (i, (aggregate_id, version, payload)) in events.iter().enumerate() {
+            let envelope = EventEnvelope {
+                event_id: format!("{aggregate_id}-v{version}"),
+                aggregate_id: aggregate_id.to_string(),
+                version: *version,
+                payload: payload.clone(),
+                metadata: HashMap::new(),
+                at: start + chrono::Duration::seconds(i as i64),
+            };
+            persist
+                .save_events(vec![envelope], &mut session)
+                .await
+                .unwrap();
+        }
+        persist.close_session(session).await.unwrap();
+    }
+
+    fn created(name: &str) -> TestEvent {
+        TestEvent::Created {
+            name: name.to_string(),
+        }
+    }
+
+    async fn detailed_page(
+        persist: &InMemoryPersist<TestAggregate>,
+        aggregate_id: Option<&str>,
+        page: usize,
+        page_size: usize,
+    ) -> Value {
+        let router = CQRSAuditLogRouter::new(
+            EventStoreImpl::new(persist.clone()),
+            Some(AuditDetails::new()),
+            ReadPolicies::default(),
+        );
+        let query = AuditLogQuery {
+            page,
+            page_size,
+            event_type: None,
+            user_id: None,
+            from: None,
+            to: None,
+        };
+        let response = CQRSAuditLogRouter::get_audit_log(
+            router,
+            aggregate_id.map(str::to_string),
+            query,
+            CqrsContext::default(),
+        )
+        .await
+        .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        body(response).await
+    }
+
+    #[test]
+    fn redaction_reaches_nested_objects_and_array_items() {
+        let details = AuditDetails::new().with_redacted_field("iban");
+        let redacted = details.redact(json!({
+            "owner": { "name": "ada", "iban": "FR76" },
+            "accounts": [
+                { "iban": "DE89", "label": "main" },
+                [{ "iban": "GB29" }],
+            ],
+            "iban": { "country": "FR" },
+            "ibans": ["FR76"],
+        }));
+        assert_eq!(
+            redacted,
+            json!({
+                "owner": { "name": "ada", "iban": REDACTED },
+                "accounts": [
+                    { "iban": REDACTED, "label": "main" },
+                    [{ "iban": REDACTED }],
+                ],
+                "iban": REDACTED,
+                "ibans": ["FR76"],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn the_detailed_audit_of_the_type_keeps_each_aggregate_s_states_apart() {
+        let persist = InMemoryPersist::<TestAggregate>::new();
+        journal(
+            &persist,
+            &[
+                ("a1", 1, created("one")),
+                ("a2", 1, created("two")),
+                ("a1", 2, TestEvent::Incremented),
+                ("a2", 2, TestEvent::Decremented),
+                ("a1", 3, TestEvent::Incremented),
+            ],
+        )
+        .await;
+
+        let page = detailed_page(&persist, None, 1, 10).await;
+        let entries: Vec<_> = page["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| {
+                (
+                    e["aggregateId"].as_str().unwrap(),
+                    e["before"]["counter"].clone(),
+                    e["after"]["counter"].clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                ("a1", json!(0), json!(0)),
+                ("a2", json!(0), json!(0)),
+                ("a1", json!(0), json!(1)),
+                ("a2", json!(0), json!(-1)),
+                ("a1", json!(1), json!(2)),
+            ]
+        );
+        assert_eq!(page["items"][3]["before"]["name"], "two");
+    }
+
+    #[tokio::test]
+    async fn a_page_starting_mid_journal_rebuilds_the_state_before_it() {
+        let persist = InMemoryPersist::<TestAggregate>::new();
+        journal(
+            &persist,
+            &[
+                ("a1", 1, created("one")),
+                ("a1", 2, TestEvent::Incremented),
+                ("a1", 3, TestEvent::Incremented),
+                ("a1", 4, TestEvent::Decremented),
+            ],
+        )
+        .await;
+
+        let page = detailed_page(&persist, Some("a1"), 2, 2).await;
+        let [third, fourth] = page["items"].as_array().unwrap().as_slice() else {
+            panic!("two entries expected: {page}");
+        };
+        assert_eq!(third["version"], 3);
+        assert_eq!(
+            third["before"],
+            json!({ "id": "a1", "counter": 1, "name": "one" })
+        );
+        assert_eq!(third["after"]["counter"], 2);
+        assert_eq!(fourth["before"]["counter"], 2);
+        assert_eq!(fourth["after"]["counter"], 1);
+    }
+
+    #[tokio::test]
+    async fn a_page_holds_at_most_the_maximum_page_size() {
+        let persist = InMemoryPersist::<TestAggregate>::new();
+        let events: Vec<_> = (1..=MAX_AUDIT_LOG_PAGE_SIZE + 2)
+            .map(|version| match version {
+                1 => ("a1", version, created("one")),
+                _ => ("a1", version, TestEvent::Incremented),
+            })
+            .collect();
+        journal(&persist, &events).await;
+
+        let page = detailed_page(&persist, Some("a1"), 1, 1000).await;
+        assert_eq!(
+            page["items"].as_array().unwrap().len(),
+            MAX_AUDIT_LOG_PAGE_SIZE
+        );
+        assert_eq!(page["pageSize"], MAX_AUDIT_LOG_PAGE_SIZE);
+        assert_eq!(page["total"], MAX_AUDIT_LOG_PAGE_SIZE + 2);
+    }
+
+    #[tokio::test]
+    async fn the_replay_starts_from_a_snapshot_not_past_the_page() {
+        let persist = InMemoryPersist::<TestAggregate>::new();
+        journal(
+            &persist,
+            &[
+                ("a1", 1, created("one")),
+                ("a1", 2, TestEvent::Incremented),
+                ("a1", 3, TestEvent::Incremented),
+            ],
+        )
+        .await;
+        // A snapshot at version 2 the replay of the journal would not give: only read
+        // from it can a state say so.
+        let mut snapshot = TestAggregate::default().with_aggregate_id("a1".to_string());
+        for _ in 0..10 {
+            snapshot.apply(TestEvent::Incremented).unwrap();
+        }
+        let mut session = persist.start_session().await.unwrap();
+        persist
+            .save_snapshot(&snapshot, 2, &mut session)
             .await
-        {
-            Ok((events, total)) => {
-                let items: Vec<AuditLogEntry> =
-                    events.into_iter().map(AuditLogEntry::from).collect();
-                let response = Paged::new(
-                    items,
-                    total,
-                    (query.page * query.page_size) as i64,
-                    query.page_size as i64,
-                );
-                (StatusCode::OK, Json(response)).into_response()
+            .unwrap();
+        persist.close_session(session).await.unwrap();
+
+        let page = detailed_page(&persist, Some("a1"), 3, 1).await;
+        assert_eq!(page["items"][0]["before"]["counter"], 10);
+        assert_eq!(page["items"][0]["after"]["counter"], 11);
+
+        // The page of version 2 wants the state before it: the snapshot is past that.
+        let page = detailed_page(&persist, Some("a1"), 2, 1).await;
+        assert_eq!(page["items"][0]["before"]["counter"], 0);
+        assert_eq!(page["items"][0]["after"]["counter"], 1);
+    }
+
+    #[tokio::test]
+    async fn the_audit_of_the_whole_type_filters_by_event_type_and_user() {
+        let store: DynEventStore<TestAggregate> = EventStoreImpl::new(InMemoryPersist::new());
+        let engine = CqrsCommandEngine::new(store.clone(), vec![], (), Box::new(|_| {}));
+        let context = CqrsContext::default();
+        let by = |user: &str| HashMap::from([(USER_ID_METADATA.to_string(), user.to_string())]);
+        for name in ["one", "two"] {
+            let id = engine
+                .execute_create_with_metadata(
+                    CreateCommand::Initialize {
+                        name: name.to_string(),
+                    },
+                    by("alice"),
+                    &context,
+                )
+                .await
+                .unwrap();
+            for user in ["alice", "bob"] {
+                engine
+                    .execute_update_with_metadata(&id, UpdateCommand::Increment, by(user), &context)
+                    .await
+                    .unwrap();
             }
-            Err(err) => err.into_response(),
+        }
+
+        let uri: Uri = "/audit?event_type=Incremented,Decremented&user_id=bob&page_size=1"
+            .parse()
+            .unwrap();
+        let Query(query) = Query::<AuditLogQuery>::try_from_uri(&uri).unwrap();
+        let response = CQRSAuditLogRouter::get_audit_log(
+            CQRSAuditLogRouter::new(store.clone(), None, ReadPolicies::default()),
+            None,
+            query,
+            context.clone(),
+        )
+        .await
+        .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let page = body(response).await;
+        assert_eq!(page["total"], 2);
+        assert_eq!(page["items"].as_array().unwrap().len(), 1);
+        assert_eq!(page["items"][0]["eventType"], "Incremented");
+        assert_eq!(page["items"][0]["metadata"]["user_id"], "bob");
+
+        let query = AuditLogQuery {
+            page: 1,
+            page_size: 10,
+            event_type: None,
+            user_id: None,
+            from: Some(Utc::now()),
+            to: None,
+        };
+        let (events, total) = store
+            .load_events_paged(&query.filter(None), 1, 10, &context)
+            .await
+            .unwrap();
+        assert_eq!((events.len(), total), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn detailed_entries_carry_redacted_states_and_their_diff() {
+        let store: DynEventStore<TestAggregate> = EventStoreImpl::new(InMemoryPersist::new());
+        let engine = CqrsCommandEngine::new(store.clone(), vec![], (), Box::new(|_| {}));
+        let context = CqrsContext::default();
+        let id = engine
+            .execute_create(
+                CreateCommand::Initialize {
+                    name: "secret".to_string(),
+                },
+                &context,
+            )
+            .await
+            .unwrap();
+        engine
+            .execute_update(&id, UpdateCommand::Increment, &context)
+            .await
+            .unwrap();
+
+        let router = CQRSAuditLogRouter::new(
+            store,
+            Some(AuditDetails::new().with_redacted_field("name")),
+            ReadPolicies::default(),
+        );
+        let query = AuditLogQuery {
+            page: 1,
+            page_size: 10,
+            event_type: None,
+            user_id: None,
+            from: None,
+            to: None,
+        };
+        let response = CQRSAuditLogRouter::get_audit_log(router, Some(id), query, context)
+            .await
+            .into_response();
+        let page = body(response).await;
+        let [created, incremented] = page["items"].as_array().unwrap().as_slice() else {
+            panic!("two entries expected: {page}");
+        };
+
+        assert_eq!(
+            created["payload"],
+            json!({ "Created": { "name": REDACTED } })
+        );
+        assert_eq!(created["after"]["name"], REDACTED);
+        // Both sides are redacted, so the diff does not leak the name.
+        assert_eq!(created["diff"], json!([]));
+        assert!(!page.to_string().contains("secret"));
+
+        assert_eq!(incremented["payload"], "Incremented");
+        assert_eq!(incremented["before"]["counter"], 0);
+        assert_eq!(incremented["after"]["counter"], 1);
+        assert_eq!(
+            incremented["diff"],
+            json!([{ "op": "replace", "path": "/counter", "value": 1 }])
+        );
+    }
+
+    #[tokio::test]
+    async fn the_audit_routes_are_behind_the_read_policies() {
+        let persist = InMemoryPersist::<TestAggregate>::new();
+        journal(&persist, &[("a1", 1, created("a"))]).await;
+        let store: DynEventStore<TestAggregate> = EventStoreImpl::new(persist);
+        let policies = ReadPolicies::new().with_find_many(crate::Policy::any_role(["auditor"]));
+        let router = CQRSAuditLogRouter::new(store.clone(), None, policies.clone());
+        let query = || AuditLogQuery {
+            page: 1,
+            page_size: 10,
+            event_type: None,
+            user_id: None,
+            from: None,
+            to: None,
+        };
+
+        let response = CQRSAuditLogRouter::get_audit_log(
+            router.clone(),
+            None,
+            query(),
+            CqrsContext::default(),
+        )
+        .await
+        .into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let auditor =
+            CqrsContext::new(Some("ann".to_string())).with_roles(vec!["auditor".to_string()]);
+        let response = CQRSAuditLogRouter::get_audit_log(router.clone(), None, query(), auditor)
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = CQRSAuditLogRouter::get_audit_log(
+            router,
+            Some("a1".to_string()),
+            query(),
+            CqrsContext::default(),
+        )
+        .await
+        .into_response();
+        assert_eq!(response.status(), StatusCode::OK, "no policy on one log");
+
+        let (_, api) =
+            CQRSAuditLogRouter::routes_with_policies(store, "test", policies).split_for_parts();
+        for (path, item) in &api.paths.paths {
+            let secured = item.get.as_ref().unwrap().security.is_some();
+            assert_eq!(secured, path == "/audit", "{path}");
         }
     }
 }
@@ -1,4 +1,7 @@
-use crate::read::{Pagination, Query, SortDirection, Sorter};
+use crate::read::{
+    Aggregation, Metric, PageCursor, Pagination, Projection, Query, SortDirection, Sorter,
+};
+use crate::CqrsError;
 use rest_sql::RestSql;
 use std::fmt::Debug;
 
@@ -14,9 +17,46 @@ use std::fmt::Debug;
 /// - `page` / `page_size` (or its camelCase alias `pageSize`) — page based,
 ///   translated to `skip = page * page_size`.
 ///
+/// A third form, keyset, takes over from the two when a cursor is given:
+/// - `after` / `before` — the `nextCursor` / `prevCursor` of a previous page. The page
+///   starts at the cursor, so neither may be combined with the other nor with `skip` or
+///   `page`; `limit` / `page_size` still set its size. See [`Query::cursor`].
+///
 /// `sort` format: comma-separated field names, prefix `-` for descending.
 /// Example: `sort=-created_at,name` → `[Desc(created_at), Asc(name)]`.
 ///
+/// ## Projection
+///
+/// `fields` — comma-separated top-level fields of the view — cuts each item of a list
+/// page down to those fields, pushed down to the storage through
+/// `Storage::filter_projected`. Every name must be a field of the view; the id is not
+/// added unless listed.
+///
+/// ## Search
+///
+/// `_search` — free text — keeps the items holding every word of it in some field of
+/// `Q::searchable_fields()`, most relevant first; `sort` then only breaks ties. A search
+/// page is offset paged: it issues no cursor and takes none. A view declaring no
+/// searchable field refuses the param. See [`Query::search`].
+///
+/// ## Aggregation
+///
+/// `group_by` and `metrics` are read by the codex router's `_aggregate` route, and
+/// refused by its list route:
+/// - `group_by` — comma-separated fields to group on. None is one group over the set.
+/// - `metrics` — comma-separated `count`, `sum(f)`, `min(f)`, `max(f)`, `avg(f)`;
+///   defaults to `count`.
+///
+/// Every field they name must be in `Q::aggregatable_fields()`. See [`Aggregation`].
+///
+/// ## Export
+///
+/// A list request accepting `text/csv` or `application/x-ndjson` is answered with every
+/// item matching `_q`, `_search` and the typed filters, in `sort` order, one row per
+/// item, streamed batch by batch; `fields` picks the columns. The whole set is the
+/// point, so a window param — `skip`, `limit`, `page`, `page_size`, `after`, `before` —
+/// is refused there.
+///
 /// Use as a handler extractor or as the `Q` type in `CQRSCodexReadRouter` to
 /// enable the Codex convention on REST routes.
 ///
@@ -47,6 +87,20 @@ pub struct CqrsHttpQuery<Q: serde::Serialize> {
     page_size: Option<i64>,
     #[serde(skip)]
     sort: Option<String>,
+    #[serde(skip)]
+    after: Option<String>,
+    #[serde(skip)]
+    before: Option<String>,
+    /// Checked by the router rather than the extractor: the allowed set is the *view's*
+    /// fields, and the extractor only knows `Q`.
+    #[serde(skip)]
+    fields: Option<Vec<String>>,
+    /// Checked during extraction against `Q::searchable_fields()`, as `sort` is.
+    #[serde(skip)]
+    search: Option<String>,
+    /// Parsed and checked against `Q::aggregatable_fields()` during extraction, like `_q`.
+    #[serde(skip)]
+    aggregation: Option<Aggregation>,
     #[serde(flatten)]
     typed: Q,
 }
@@ -64,16 +118,187 @@ const RESERVED_PARAMS: &[&str] = &[
     "page_size",
     "pageSize",
     "sort",
+    "after",
+    "before",
+    "group_by",
+    "metrics",
+    "fields",
+    "_search",
+];
+
+/// The params that shape a list page; the `_aggregate` route answers with every group,
+/// unsorted by the caller, so it refuses them rather than ignore them.
+const WINDOW_PARAMS: &[&str] = &[
+    "skip",
+    "limit",
+    "page",
+    "page_size",
+    "pageSize",
+    "sort",
+    "after",
+    "before",
+    "fields",
 ];
 
 impl<Q: serde::Serialize> CqrsHttpQuery<Q> {
     pub fn typed(&self) -> &Q {
         &self.typed
     }
+
+    /// The fields `fields` asked for, unchecked, if it was given.
+    pub fn fields(&self) -> Option<&[String]> {
+        self.fields.as_deref()
+    }
+
+    /// For the list route: the projection `fields` asks for, checked against the fields
+    /// `V` deserializes — the same derivation `_q` makes from `Q`. A name the view does
+    /// not have, or one named twice, is a `422` naming it.
+    pub(crate) fn projection<V: serde::de::DeserializeOwned>(
+        &self,
+    ) -> Result<Option<Projection>, CqrsError> {
+        let Some(fields) = &self.fields else {
+            return Ok(None);
+        };
+        let reject = |message: String| {
+            CqrsError::unprocessable(format!("invalid query parameters: fields: {message}"))
+        };
+        let allowed = rest_sql::dsl::serde_fields::<V>();
+        if allowed.is_empty() {
+            return Err(reject(
+                "no field could be derived from the view type".to_string(),
+            ));
+        }
+        if let Some(field) = fields.iter().find(|f| !allowed.contains(&f.as_str())) {
+            return Err(reject(format!(
+                "field {field:?} is not a field of this view; allowed: {}",
+                allowed.join(", ")
+            )));
+        }
+        let projection = Projection::new(fields.iter().cloned());
+        projection
+            .validate()
+            .map_err(|e| reject(e.message.clone()))?;
+        Ok(Some(projection))
+    }
+
+    /// For an export: the whole set is exported, so a window param is a `422` rather
+    /// than a truncated file.
+    pub(crate) fn reject_window(&self) -> Result<(), CqrsError> {
+        let window = [
+            ("skip", self.skip.is_some()),
+            ("limit", self.limit.is_some()),
+            ("page", self.page.is_some()),
+            ("page_size", self.page_size.is_some()),
+            ("after", self.after.is_some()),
+            ("before", self.before.is_some()),
+        ];
+        match window.iter().find(|(_, given)| *given) {
+            Some((name, _)) => Err(CqrsError::unprocessable(format!(
+                "invalid query parameters: {name}: an export returns every matching item; \
+                 it is not paged"
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// One batch of an export: the same query, `size` items from `skip` — or after the
+    /// cursor `after`, which then takes over from the offset. The sort ends with
+    /// `id_field`, so that the storage plans a keyset even when the query declares no
+    /// sort, and the order is total under a search.
+    pub(crate) fn batch(
+        &self,
+        after: Option<String>,
+        skip: i64,
+        size: i64,
+        id_field: Option<&str>,
+    ) -> Self
+    where
+        Q: Clone + Query,
+    {
+        let mut sort = Query::sort(self).unwrap_or_default();
+        if let Some(id_field) = id_field
+            && !sort.iter().any(|sorter| sorter.field == id_field)
+        {
+            sort.push(Sorter {
+                field: id_field.to_string(),
+                direction: SortDirection::Asc,
+            });
+        }
+        let sort = sort
+            .iter()
+            .map(|sorter| match sorter.direction {
+                SortDirection::Asc => sorter.field.clone(),
+                SortDirection::Desc => format!("-{}", sorter.field),
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        Self {
+            skip: after.is_none().then_some(skip),
+            limit: Some(size),
+            page: None,
+            page_size: None,
+            after,
+            before: None,
+            sort: (!sort.is_empty()).then_some(sort),
+            ..self.clone()
+        }
+    }
+
+    /// The aggregation `group_by` / `metrics` asked for, if either was given.
+    pub fn aggregation(&self) -> Option<&Aggregation> {
+        self.aggregation.as_ref()
+    }
+
+    /// For the list route: a `group_by` or `metrics` there would be ignored, and the caller
+    /// would read a page as if it were the groups they asked for.
+    pub(crate) fn reject_aggregation(&self) -> Result<(), CqrsError> {
+        match &self.aggregation {
+            Some(_) => Err(CqrsError::unprocessable(
+                "invalid query parameters: group_by and metrics are served by the \
+                 _aggregate route, not the list",
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// For the `_aggregate` route: the aggregation to run — `count` over the whole set
+    /// when neither param was given — or a `422` for a page param, which has no meaning
+    /// over groups, and for a view offering no aggregation at all.
+    pub(crate) fn aggregation_request(&self) -> Result<Aggregation, CqrsError>
+    where
+        Q: Query,
+    {
+        let window = [
+            ("skip", self.skip.is_some()),
+            ("limit", self.limit.is_some()),
+            ("page", self.page.is_some()),
+            ("page_size", self.page_size.is_some()),
+            ("sort", self.sort.as_deref().is_some_and(|s| !s.is_empty())),
+            ("after", self.after.is_some()),
+            ("before", self.before.is_some()),
+            ("fields", self.fields.is_some()),
+        ];
+        if let Some((name, _)) = window.iter().find(|(_, given)| *given) {
+            return Err(CqrsError::unprocessable(format!(
+                "invalid query parameters: {name}: the _aggregate route returns every \
+                 group in group-key order; it is neither paged, sorted nor projected"
+            )));
+        }
+        if self.typed.aggregatable_fields().is_empty() {
+            return Err(CqrsError::unprocessable(
+                "invalid query parameters: group_by: this endpoint offers no aggregation",
+            ));
+        }
+        Ok(self
+            .aggregation
+            .clone()
+            .unwrap_or_else(|| Aggregation::new(Vec::<String>::new(), [Metric::count()])))
+    }
 }
 
 #[cfg(feature = "rest")]
 mod axum_impl {
+    use crate::read::{Aggregation, Metric};
     use super::CqrsHttpQuery;
     use crate::warn_once::warn_once;
     use crate::read::Query;
@@ -218,6 +443,58 @@ mod axum_impl {
         Ok(())
     }
 
+    /// Builds the aggregation from `group_by` and `metrics`, checking every field they
+    /// name against `Q::aggregatable_fields()` — the same rule, and the same phrasing, as
+    /// `sort` against `sortable_fields`. A metric that does not parse, or an aggregation
+    /// naming a key or metric twice, is the same `422`.
+    fn parse_aggregation(
+        group_by: Option<&str>,
+        metrics: Option<&str>,
+        allowed: &[&str],
+    ) -> Result<Aggregation, CodexRejection> {
+        let split = |raw: Option<&str>| -> Vec<String> {
+            raw.unwrap_or("")
+                .split(',')
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+        let mut parsed = split(metrics)
+            .iter()
+            .map(|m| m.parse::<Metric>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| CodexRejection(format!("metrics: {}", e.message)))?;
+        if parsed.is_empty() {
+            parsed.push(Metric::count());
+        }
+        let aggregation = Aggregation::new(split(group_by), parsed);
+
+        if allowed.is_empty() {
+            return Err(CodexRejection(
+                "group_by: this endpoint offers no aggregation".to_string(),
+            ));
+        }
+        for key in &aggregation.group_by {
+            if !allowed.contains(&key.as_str()) {
+                return Err(not_offered("group_by", key, allowed));
+            }
+        }
+        for field in aggregation
+            .metrics
+            .iter()
+            .filter_map(|m| m.field.as_deref())
+        {
+            if !allowed.contains(&field) {
+                return Err(not_offered("metrics", field, allowed));
+            }
+        }
+        aggregation
+            .validate()
+            .map_err(|e| CodexRejection(e.message.clone()))?;
+        Ok(aggregation)
+    }
+
     /// Parses one of the pagination params: a non-negative `i64`, or a rejection.
     ///
     /// Out-of-range is refused rather than clamped because it has no single meaning
@@ -269,14 +546,29 @@ mod axum_impl {
             parts: &mut Parts,
             _state: &S,
         ) -> Result<Self, Self::Rejection> {
-            let raw = parts.uri.query().unwrap_or("");
+            Self::from_query_string(parts.uri.query().unwrap_or(""))
+        }
+    }
 
+    impl<Q> CqrsHttpQuery<Q>
+    where
+        Q: Query + serde::Serialize + DeserializeOwned,
+    {
+        /// The extractor's work on a raw query string — for a caller that holds the
+        /// params outside a request, such as the GraphQL adapter.
+        pub(crate) fn from_query_string(raw: &str) -> Result<Self, CodexRejection> {
             let mut parsed_q: Option<RestSql> = None;
             let mut skip: Option<i64> = None;
             let mut limit: Option<i64> = None;
             let mut page: Option<i64> = None;
             let mut page_size: Option<i64> = None;
             let mut sort: Option<String> = None;
+            let mut after: Option<String> = None;
+            let mut before: Option<String> = None;
+            let mut group_by: Option<String> = None;
+            let mut metrics: Option<String> = None;
+            let mut fields: Option<Vec<String>> = None;
+            let mut search: Option<String> = None;
             let mut rest: Vec<(&str, &str)> = Vec::new();
 
             for pair in raw.split('&').filter(|s| !s.is_empty()) {
@@ -307,7 +599,38 @@ mod axum_impl {
                     // forwarded: a codex param must never reach `Q`, whatever `Q`'s fields
                     // are called. An empty *typed* field still goes to serde_urlencoded
                     // below, where `?name=` keeps whatever meaning `Q` gives it.
-                    "_q" | "skip" | "limit" | "page" | "page_size" | "pageSize" => {}
+                    // A cursor is opaque here: whether it decodes, and under which sort,
+                    // is the storage's question — the same split as `sort`.
+                    "after" if !v.is_empty() => {
+                        after = Some(percent_decode_str(v).decode_utf8_lossy().into_owned());
+                    }
+                    "before" if !v.is_empty() => {
+                        before = Some(percent_decode_str(v).decode_utf8_lossy().into_owned());
+                    }
+                    // Checked after the loop against `aggregatable_fields`, as `sort` is.
+                    "group_by" if !v.is_empty() => {
+                        group_by = Some(percent_decode_str(v).decode_utf8_lossy().into_owned());
+                    }
+                    "metrics" if !v.is_empty() => {
+                        metrics = Some(percent_decode_str(v).decode_utf8_lossy().into_owned());
+                    }
+                    "fields" if !v.is_empty() => {
+                        let decoded = percent_decode_str(v).decode_utf8_lossy();
+                        fields = Some(
+                            decoded
+                                .split(',')
+                                .map(str::trim)
+                                .filter(|f| !f.is_empty())
+                                .map(str::to_string)
+                                .collect(),
+                        );
+                    }
+                    // Checked after the loop against `searchable_fields`, as `sort` is.
+                    "_search" if !v.is_empty() => {
+                        search = Some(percent_decode_str(v).decode_utf8_lossy().into_owned());
+                    }
+                    "_q" | "skip" | "limit" | "page" | "page_size" | "pageSize" | "after"
+                    | "before" | "group_by" | "metrics" | "fields" | "_search" => {}
                     _ => rest.push((k, v)),
                 }
             }
@@ -339,6 +662,24 @@ mod axum_impl {
                 ));
             }
 
+            // A cursor *is* the start of the page. Given with another start — a second
+            // cursor, an offset, a page number — one of the two would have to be dropped
+            // in silence, and ADR-0001 says to refuse instead.
+            if after.is_some() && before.is_some() {
+                return Err(CodexRejection(
+                    "after: cannot be combined with before; send one cursor".to_string(),
+                ));
+            }
+            if let Some(name) = [("after", &after), ("before", &before)]
+                .into_iter()
+                .find_map(|(name, cursor)| cursor.is_some().then_some(name))
+                && (skip.is_some() || page.is_some())
+            {
+                return Err(CodexRejection(format!(
+                    "{name}: a cursor replaces skip and page; send one or the other"
+                )));
+            }
+
             if let (Some(page), Some(size)) = (page, page_size) {
                 page.checked_mul(size).ok_or_else(|| {
                     CodexRejection(format!(
@@ -357,6 +698,33 @@ mod axum_impl {
             if let Some(raw_sort) = sort.as_deref().filter(|s| !s.is_empty()) {
                 check_sort_fields(raw_sort, &typed.sortable_fields())?;
             }
+            // Relevance is recomputed per request, so no cursor can stand for a position
+            // in a search page; the storage would refuse it with 400, and the conflict is
+            // between two params, which is a 422 here like every other one.
+            if search.is_some() {
+                if typed.searchable_fields().is_empty() {
+                    return Err(CodexRejection(
+                        "_search: this endpoint offers no text search".to_string(),
+                    ));
+                }
+                if let Some(name) = [("after", &after), ("before", &before)]
+                    .into_iter()
+                    .find_map(|(name, cursor)| cursor.is_some().then_some(name))
+                {
+                    return Err(CodexRejection(format!(
+                        "{name}: a search page is ordered by relevance and has no cursor; \
+                         use skip/limit"
+                    )));
+                }
+            }
+            let aggregation = match (&group_by, &metrics) {
+                (None, None) => None,
+                _ => Some(parse_aggregation(
+                    group_by.as_deref(),
+                    metrics.as_deref(),
+                    &typed.aggregatable_fields(),
+                )?),
+            };
 
             Ok(CqrsHttpQuery {
                 parsed_q,
@@ -365,6 +733,36 @@ mod axum_impl {
                 page,
                 page_size,
                 sort,
+                after,
+                before,
+                fields,
+                search,
+                aggregation,
+                typed,
+            })
+        }
+
+        /// A query holding only a `_q` filter, checked exactly as the extractor checks
+        /// one — for a caller that receives the filter outside a query string, such as a
+        /// live view subscription.
+        #[cfg(feature = "ws")]
+        pub(crate) fn from_q(raw: &str) -> Result<Self, CqrsError> {
+            // The typed params as the extractor reads them from a query string carrying
+            // none, so a `Q` that needs one is refused here too.
+            let typed =
+                serde_urlencoded::from_str::<Q>("").map_err(|e| CodexRejection(e.to_string()))?;
+            Ok(CqrsHttpQuery {
+                parsed_q: Some(parse_q::<Q>(raw)?),
+                skip: None,
+                limit: None,
+                page: None,
+                page_size: None,
+                sort: None,
+                after: None,
+                before: None,
+                fields: None,
+                search: None,
+                aggregation: None,
                 typed,
             })
         }
@@ -412,6 +810,15 @@ impl<Q: Query> Query for CqrsHttpQuery<Q> {
         }
     }
 
+    /// `after` / `before` when given; otherwise the typed query decides.
+    fn cursor(&self) -> Option<PageCursor> {
+        match (&self.after, &self.before) {
+            (Some(token), _) => Some(PageCursor::After(token.clone())),
+            (None, Some(token)) => Some(PageCursor::Before(token.clone())),
+            (None, None) => self.typed.cursor(),
+        }
+    }
+
     /// Forwards the inner type's sortable fields.
     ///
     /// Enforcement does not go through here — the extractor asks `Q` directly, before a
@@ -421,6 +828,23 @@ impl<Q: Query> Query for CqrsHttpQuery<Q> {
     fn sortable_fields(&self) -> Vec<&str> {
         self.typed.sortable_fields()
     }
+
+    /// Forwards the inner type's aggregatable fields, for the same reason as
+    /// [`Query::sortable_fields`] above.
+    fn aggregatable_fields(&self) -> Vec<&str> {
+        self.typed.aggregatable_fields()
+    }
+
+    /// `_search` when given; otherwise the typed query decides.
+    fn search(&self) -> Option<&str> {
+        self.search.as_deref().or_else(|| self.typed.search())
+    }
+
+    /// Forwards the inner type's searchable fields — here the storage *does* read them:
+    /// they are where the words are looked for.
+    fn searchable_fields(&self) -> Vec<&str> {
+        self.typed.searchable_fields()
+    }
 }
 
 fn parse_sort(s: &str) -> Vec<Sorter> {
@@ -544,6 +968,109 @@ mod utoipa_impl {
                     .schema(Some(String::schema()))
                     .build(),
             );
+            params.push(
+                ParameterBuilder::new()
+                    .name("after")
+                    .parameter_in(ParameterIn::Query)
+                    .description(Some(
+                        "Keyset cursor: the nextCursor of a previous page. Returns the \
+                         items that follow it under the same sort. Combined with before, \
+                         skip or page it is rejected with 422; a token that is not one \
+                         this endpoint issued, or that was issued under another sort, or \
+                         sent while no sort is in effect, is rejected with 400.",
+                    ))
+                    .required(Required::False)
+                    .schema(Some(String::schema()))
+                    .build(),
+            );
+            params.push(
+                ParameterBuilder::new()
+                    .name("before")
+                    .parameter_in(ParameterIn::Query)
+                    .description(Some(
+                        "Keyset cursor: the prevCursor of a previous page. Returns the \
+                         items that precede it, still in the sort's order. Same rules as \
+                         after: 422 when combined with after, skip or page, 400 for a \
+                         token this endpoint cannot use.",
+                    ))
+                    .required(Required::False)
+                    .schema(Some(String::schema()))
+                    .build(),
+            );
+            params.push(
+                ParameterBuilder::new()
+                    .name("fields")
+                    .parameter_in(ParameterIn::Query)
+                    .description(Some(
+                        "Comma-separated top-level fields of the view to return; each \
+                         item then holds only those, the id included only when listed. \
+                         A name that is not a field of the view, or one named twice, is \
+                         rejected with 422.",
+                    ))
+                    .required(Required::False)
+                    .schema(Some(String::schema()))
+                    .build(),
+            );
+            params.push(search_param());
+            params
+        }
+    }
+
+    /// Shared by both routes: a search narrows the groups of `_aggregate` as it narrows
+    /// a list page.
+    fn search_param() -> Parameter {
+        ParameterBuilder::new()
+            .name("_search")
+            .parameter_in(ParameterIn::Query)
+            .description(Some(
+                "Free text. Keeps the items holding every word of it in one of the \
+                 fields this view searches, most relevant first, sort breaking ties. \
+                 A search page is paged by skip/limit and carries no cursor. Rejected \
+                 with 422 on a view that offers no search, or alongside after/before.",
+            ))
+            .required(Required::False)
+            .schema(Some(String::schema()))
+            .build()
+    }
+
+    impl<Q: Query + IntoParams> CqrsHttpQuery<Q> {
+        /// The `_aggregate` route's params: the list route's, less the window ones it
+        /// refuses, plus `group_by` and `metrics`.
+        pub(crate) fn aggregate_params(
+            parameter_in_provider: impl Fn() -> Option<ParameterIn>,
+        ) -> Vec<Parameter> {
+            let mut params: Vec<Parameter> = Self::into_params(parameter_in_provider)
+                .into_iter()
+                .filter(|p| !super::WINDOW_PARAMS.contains(&p.name.as_str()))
+                .collect();
+            params.push(
+                ParameterBuilder::new()
+                    .name("group_by")
+                    .parameter_in(ParameterIn::Query)
+                    .description(Some(
+                        "Comma-separated fields to group on; none is one group over the \
+                         filtered set. A view declares which fields it aggregates and \
+                         offers none by default, so any other field — or any request at \
+                         all on a view declaring none — is rejected with 422.",
+                    ))
+                    .required(Required::False)
+                    .schema(Some(String::schema()))
+                    .build(),
+            );
+            params.push(
+                ParameterBuilder::new()
+                    .name("metrics")
+                    .parameter_in(ParameterIn::Query)
+                    .description(Some(
+                        "Comma-separated metrics per group: count, sum(field), \
+                         min(field), max(field), avg(field). Defaults to count. A \
+                         malformed metric, or a field the view does not aggregate, is \
+                         rejected with 422.",
+                    ))
+                    .required(Required::False)
+                    .schema(Some(String::schema()))
+                    .build(),
+            );
             params
         }
     }
@@ -590,6 +1117,11 @@ mod tests {
             page: None,
             page_size: None,
             sort: None,
+            after: None,
+            before: None,
+            fields: None,
+            search: None,
+            aggregation: None,
             typed,
         }
     }
@@ -1407,6 +1939,239 @@ mod tests {
             assert_eq!(pagination.skip, Some(20));
             assert_eq!(pagination.limit, Some(10));
         }
+
+        #[tokio::test]
+        async fn a_cursor_is_carried_opaque_to_the_storage() {
+            let q = extract("after=eyJzIjoiaWQifQ&limit=10").await;
+            assert_eq!(q.cursor(), Some(PageCursor::After("eyJzIjoiaWQifQ".into())));
+            assert_eq!(q.pagination().expect("pagination").limit, Some(10));
+
+            let q = extract("before=abc").await;
+            assert_eq!(q.cursor(), Some(PageCursor::Before("abc".into())));
+
+            let q = extract("after=&before=").await;
+            assert!(q.cursor().is_none(), "an empty cursor is an absent one");
+        }
+
+        /// Two starts for one page: one of them would have to be ignored.
+        #[tokio::test]
+        async fn a_cursor_with_another_start_is_rejected() {
+            for query in [
+                "after=a&before=b",
+                "after=a&skip=10",
+                "before=b&page=2&page_size=10",
+            ] {
+                let err = try_extract(query).await.unwrap_err();
+                assert_eq!(err.status, 422, "{query}");
+            }
+            let err = try_extract("before=b&skip=5").await.unwrap_err();
+            assert!(err.message.contains("before"), "{}", err.message);
+        }
+
+        // ── `fields` is bounded by the view's own fields ─────────────────────────
+
+        #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+        struct GameView {
+            id: String,
+            title: String,
+            players: Option<i64>,
+        }
+
+        #[tokio::test]
+        async fn fields_extract_into_a_projection_of_the_view() {
+            let q = extract("fields=id,%20title").await;
+            assert_eq!(
+                q.fields(),
+                Some(&["id".to_string(), "title".to_string()][..])
+            );
+            assert_eq!(
+                q.projection::<GameView>().unwrap(),
+                Some(Projection::new(["id", "title"]))
+            );
+
+            let q = extract("fields=").await;
+            assert_eq!(q.projection::<GameView>().unwrap(), None);
+        }
+
+        #[tokio::test]
+        async fn a_field_the_view_does_not_have_is_rejected_and_named() {
+            for (query, named) in [("fields=id,rating", "rating"), ("fields=id,id", "twice")] {
+                let err = extract(query).await.projection::<GameView>().unwrap_err();
+                assert_eq!(err.status, 422, "{query}");
+                assert!(err.message.contains(named), "{query}: {}", err.message);
+            }
+        }
+
+        // ── `_search` is bounded by `searchable_fields` ───────────────────────────
+
+        #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct CatalogQuery {
+            publisher: Option<String>,
+        }
+
+        impl Query for CatalogQuery {
+            fn searchable_fields(&self) -> Vec<&str> {
+                vec!["title", "summary"]
+            }
+        }
+
+        async fn try_extract_catalog(
+            query: &str,
+        ) -> Result<CqrsHttpQuery<CatalogQuery>, CqrsError> {
+            let req = http::Request::builder()
+                .uri(format!("/games?{query}"))
+                .body(())
+                .unwrap();
+            let (mut parts, _) = req.into_parts();
+            CqrsHttpQuery::<CatalogQuery>::from_request_parts(&mut parts, &())
+                .await
+                .map_err(CqrsError::from)
+        }
+
+        #[tokio::test]
+        async fn search_is_decoded_consumed_and_forwarded_with_its_fields() {
+            let q = try_extract_catalog("_search=ticket%20to%20ride&publisher=Days")
+                .await
+                .unwrap();
+            assert_eq!(q.search(), Some("ticket to ride"));
+            assert_eq!(q.searchable_fields(), ["title", "summary"]);
+            assert_eq!(q.typed().publisher.as_deref(), Some("Days"));
+
+            let q = try_extract_catalog("_search=").await.unwrap();
+            assert!(q.search().is_none(), "an empty search is an absent one");
+        }
+
+        #[tokio::test]
+        async fn a_search_is_refused_without_searchable_fields_or_with_a_cursor() {
+            let err = try_extract("_search=azul").await.unwrap_err();
+            assert_eq!(err.status, 422);
+            assert!(
+                err.message.contains("offers no text search"),
+                "a view declaring nothing offers nothing: {}",
+                err.message
+            );
+
+            for (query, named) in [
+                ("_search=azul&after=x", "after"),
+                ("_search=azul&before=x", "before"),
+            ] {
+                let err = try_extract_catalog(query).await.unwrap_err();
+                assert_eq!(err.status, 422, "{query}");
+                assert!(err.message.contains(named), "{query}: {}", err.message);
+            }
+            assert!(
+                try_extract_catalog("_search=azul&skip=10&limit=5")
+                    .await
+                    .is_ok()
+            );
+        }
+
+        // ── group_by / metrics are bounded by `aggregatable_fields` ──────────────
+
+        #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct LedgerQuery {
+            account_id: Option<String>,
+        }
+
+        impl Query for LedgerQuery {
+            fn sortable_fields(&self) -> Vec<&str> {
+                vec!["amount"]
+            }
+            fn aggregatable_fields(&self) -> Vec<&str> {
+                vec!["account_id", "amount"]
+            }
+        }
+
+        async fn try_extract_ledger(query: &str) -> Result<CqrsHttpQuery<LedgerQuery>, CqrsError> {
+            let req = http::Request::builder()
+                .uri(format!("/entries?{query}"))
+                .body(())
+                .unwrap();
+            let (mut parts, _) = req.into_parts();
+            CqrsHttpQuery::<LedgerQuery>::from_request_parts(&mut parts, &())
+                .await
+                .map_err(CqrsError::from)
+        }
+
+        #[tokio::test]
+        async fn group_by_and_metrics_extract_into_an_aggregation() {
+            let q = try_extract_ledger("group_by=account_id&metrics=count,sum(amount)")
+                .await
+                .unwrap();
+            assert_eq!(
+                q.aggregation(),
+                Some(&Aggregation::new(
+                    ["account_id"],
+                    [Metric::count(), Metric::sum("amount")]
+                ))
+            );
+
+            let q = try_extract_ledger("group_by=account_id").await.unwrap();
+            assert_eq!(
+                q.aggregation().unwrap().metrics,
+                [Metric::count()],
+                "count is the default metric"
+            );
+
+            let q = try_extract_ledger("group_by=&metrics=").await.unwrap();
+            assert!(q.aggregation().is_none(), "empty values are absent params");
+        }
+
+        #[tokio::test]
+        async fn an_aggregation_over_a_field_not_on_offer_is_rejected_and_named() {
+            for (query, named) in [
+                ("group_by=owner", "owner"),
+                ("metrics=avg(balance)", "balance"),
+                ("metrics=median(amount)", "median"),
+                ("group_by=account_id,account_id", "twice"),
+            ] {
+                let err = try_extract_ledger(query).await.unwrap_err();
+                assert_eq!(err.status, 422, "{query}");
+                assert!(err.message.contains(named), "{query}: {}", err.message);
+            }
+
+            let err = try_extract("group_by=name").await.unwrap_err();
+            assert_eq!(err.status, 422);
+            assert!(
+                err.message.contains("offers no aggregation"),
+                "a view declaring nothing offers nothing: {}",
+                err.message
+            );
+        }
+
+        #[tokio::test]
+        async fn the_list_and_the_aggregate_route_each_refuse_the_others_params() {
+            let q = try_extract_ledger("group_by=account_id").await.unwrap();
+            assert_eq!(q.reject_aggregation().unwrap_err().status, 422);
+
+            for query in [
+                "limit=10",
+                "sort=amount",
+                "page=1&page_size=5",
+                "after=x",
+                "fields=account_id",
+            ] {
+                let q = try_extract_ledger(query).await.unwrap();
+                let err = q.aggregation_request().unwrap_err();
+                assert_eq!(err.status, 422, "{query}");
+            }
+
+            let q = try_extract_ledger("_q=account_id==a1").await.unwrap();
+            assert!(q.reject_aggregation().is_ok());
+            assert_eq!(
+                q.aggregation_request().unwrap(),
+                Aggregation::new(Vec::<String>::new(), [Metric::count()]),
+                "no param is a count over the filtered set"
+            );
+
+            let err = extract("").await.aggregation_request().unwrap_err();
+            assert_eq!(
+                err.status, 422,
+                "a view declaring nothing refuses the route"
+            );
+        }
     }
 }
 
@@ -1434,16 +2199,49 @@ mod utoipa_tests {
         let names: Vec<String> = params().into_iter().map(|p| p.name).collect();
         assert_eq!(
             names,
-            ["name", "_q", "skip", "limit", "page", "page_size", "sort"],
+            [
+                "name",
+                "_q",
+                "skip",
+                "limit",
+                "page",
+                "page_size",
+                "sort",
+                "after",
+                "before",
+                "fields",
+                "_search"
+            ],
             "the typed query's own params come first, then the codex ones"
         );
     }
 
+    #[test]
+    fn the_aggregate_route_swaps_the_window_params_for_group_by_and_metrics() {
+        let params = CqrsHttpQuery::<TypedQuery>::aggregate_params(|| Some(ParameterIn::Query));
+        let names: Vec<&str> = params.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["name", "_q", "_search", "group_by", "metrics"]);
+        for param in &params[2..] {
+            let description = param.description.clone().unwrap_or_default();
+            assert!(description.contains("422"), "{}: {description}", param.name);
+        }
+    }
+
     /// A caller learns the 422 from the API document or not at all — the same reason
     /// the `sort` grammar is asserted below.
     #[test]
     fn every_extractor_parsed_param_documents_its_422() {
-        for name in ["_q", "skip", "limit", "page", "page_size"] {
+        for name in [
+            "_q",
+            "skip",
+            "limit",
+            "page",
+            "page_size",
+            "after",
+            "before",
+            "fields",
+            "_search",
+        ] {
             let param = params()
                 .into_iter()
                 .find(|p| p.name == name)
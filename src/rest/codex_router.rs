@@ -1,16 +1,20 @@
 use crate::read::storage::DynStorage;
-use crate::read::{Paged, Query};
+use crate::read::{AggregateRow, Paged, Query};
 use crate::rest::codex::CqrsHttpQuery;
+use crate::rest::conditional::{self, Validators};
+use crate::rest::export::{self, Export, Format};
 use crate::rest::helpers;
-use crate::{Aggregate, CqrsContext, CqrsError, View};
+use crate::rest::security;
+use crate::{Aggregate, CqrsContext, CqrsError, ReadPolicies, View};
 use axum::extract::{Path, State};
 use axum::response::IntoResponse;
 use axum::routing::get;
-use axum::{Extension, Json};
-use http::StatusCode;
+use axum::Extension;
+use http::{HeaderMap, StatusCode};
 use serde::de::DeserializeOwned;
 use serde_json::json;
 use std::fmt::Debug;
+use std::sync::Arc;
 use utoipa::openapi::path::ParameterIn;
 use utoipa::openapi::{HttpMethod, Ref, RefOr, Schema};
 use utoipa::{IntoParams, PartialSchema, ToSchema};
@@ -18,6 +22,15 @@ use utoipa_axum::router::{OpenApiRouter, UtoipaMethodRouter};
 
 /// Like `CQRSReadRouter` but uses the HTTP Codex convention for query params:
 /// `_q` (RSQL), `page`, `page_size`, `sort` in addition to typed `Q` fields.
+///
+/// `_search` reaches the storage through the query itself, so the list route and
+/// `_aggregate` both narrow to the matches; a storage that has no index for it answers
+/// with its own error.
+///
+/// Next to the list and find-by-id routes it serves `GET {base}/_aggregate`, which
+/// groups the filtered set by `group_by` and computes `metrics` per group through
+/// [`crate::read::Storage::aggregate`]. It answers `422` unless `Q::aggregatable_fields`
+/// declares the fields it names, and `501` over a storage that cannot group.
 #[derive(Clone)]
 pub struct CQRSCodexReadRouter<A, V, Q>
 where
@@ -27,6 +40,7 @@ where
 {
     _phantom: std::marker::PhantomData<(A, V, Q)>,
     storage: DynStorage<V, CqrsHttpQuery<Q>>,
+    policies: Arc<ReadPolicies>,
 }
 
 impl<A, V, Q> CQRSCodexReadRouter<A, V, Q>
@@ -36,10 +50,11 @@ where
     Q: Clone + Debug + DeserializeOwned + Send + Sync + IntoParams + Query + 'static,
 {
     #[must_use]
-    fn new(storage: DynStorage<V, CqrsHttpQuery<Q>>) -> Self {
+    fn new(storage: DynStorage<V, CqrsHttpQuery<Q>>, policies: ReadPolicies) -> Self {
         Self {
             _phantom: std::marker::PhantomData,
             storage,
+            policies: Arc::new(policies),
         }
     }
 
@@ -64,7 +79,11 @@ where
         }
     }
 
-    fn find_many(router: OpenApiRouter<Self>, tag: &str) -> OpenApiRouter<Self> {
+    fn find_many(
+        router: OpenApiRouter<Self>,
+        tag: &str,
+        policies: &ReadPolicies,
+    ) -> OpenApiRouter<Self> {
         let path = Self::base_path();
         let response_schema_name = format!("{}_{}", Paged::<V>::name(), V::name());
         let schemas = vec![
@@ -89,22 +108,28 @@ where
                 StatusCode::INTERNAL_SERVER_ERROR,
             ],
         );
+        let paths = security::secure(
+            export::document(conditional::document(paths)),
+            policies.find_many(),
+        );
 
         let find_many_handler = if V::IS_CHILD_OF_AGGREGATE {
             get(
                 move |State(router): State<Self>,
                       Path(parent_id): Path<String>,
                       query: CqrsHttpQuery<Q>,
-                      Extension(context): Extension<CqrsContext>| async {
-                    Self::search(router, Some(parent_id), query, context).await
+                      headers: HeaderMap,
+                      Extension(context): Extension<CqrsContext>| async move {
+                    Self::search(router, Some(parent_id), query, context, headers).await
                 },
             )
         } else {
             get(
                 move |State(router): State<Self>,
                       query: CqrsHttpQuery<Q>,
-                      Extension(context): Extension<CqrsContext>| async {
-                    Self::search(router, None, query, context).await
+                      headers: HeaderMap,
+                      Extension(context): Extension<CqrsContext>| async move {
+                    Self::search(router, None, query, context, headers).await
                 },
             )
         };
@@ -116,7 +141,11 @@ where
         )))
     }
 
-    fn find_one(router: OpenApiRouter<Self>, tag: &str) -> OpenApiRouter<Self> {
+    fn find_one(
+        router: OpenApiRouter<Self>,
+        tag: &str,
+        policies: &ReadPolicies,
+    ) -> OpenApiRouter<Self> {
         let path = Self::base_path();
         let response_schema_name = V::name();
         let schemas = vec![
@@ -137,22 +166,25 @@ where
             None,
             &[StatusCode::NOT_FOUND, StatusCode::INTERNAL_SERVER_ERROR],
         );
+        let paths = security::secure(conditional::document(paths), policies.find_one());
 
         let find_one_handler = if V::IS_CHILD_OF_AGGREGATE {
             get(
                 move |State(router): State<Self>,
                       Path(parent_id): Path<String>,
                       Path(id): Path<String>,
-                      Extension(context): Extension<CqrsContext>| async {
-                    Self::by_id(router, Some(parent_id), id, context).await
+                      headers: HeaderMap,
+                      Extension(context): Extension<CqrsContext>| async move {
+                    Self::by_id(router, Some(parent_id), id, context, headers).await
                 },
             )
         } else {
             get(
                 move |State(router): State<Self>,
                       Path(id): Path<String>,
-                      Extension(context): Extension<CqrsContext>| async {
-                    Self::by_id(router, None, id, context).await
+                      headers: HeaderMap,
+                      Extension(context): Extension<CqrsContext>| async move {
+                    Self::by_id(router, None, id, context, headers).await
                 },
             )
         };
@@ -164,12 +196,82 @@ where
         )))
     }
 
+    fn aggregate(
+        router: OpenApiRouter<Self>,
+        tag: &str,
+        policies: &ReadPolicies,
+    ) -> OpenApiRouter<Self> {
+        let path = format!("{}/_aggregate", Self::base_path());
+        let schemas = vec![
+            (AggregateRow::name().to_string(), AggregateRow::schema()),
+            helpers::error_schema(),
+        ];
+
+        let paths = helpers::generate_route(
+            tag,
+            HttpMethod::Get,
+            &path,
+            RefOr::T(Schema::Array(utoipa::openapi::schema::Array::new(
+                RefOr::Ref(Ref::from_schema_name(AggregateRow::name())),
+            ))),
+            Self::base_path_parameters(),
+            CqrsHttpQuery::<Q>::aggregate_params(|| Some(ParameterIn::Query)),
+            None,
+            // 422 for a param the extractor or the route refuses, 400 for a name the
+            // storage refuses, 501 for a storage with no grouping.
+            &[
+                StatusCode::BAD_REQUEST,
+                StatusCode::UNPROCESSABLE_ENTITY,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::NOT_IMPLEMENTED,
+            ],
+        );
+        let paths = security::secure(conditional::document(paths), policies.aggregate());
+
+        let aggregate_handler = if V::IS_CHILD_OF_AGGREGATE {
+            get(
+                move |State(router): State<Self>,
+                      Path(parent_id): Path<String>,
+                      query: CqrsHttpQuery<Q>,
+                      headers: HeaderMap,
+                      Extension(context): Extension<CqrsContext>| async move {
+                    Self::group(router, Some(parent_id), query, context, headers).await
+                },
+            )
+        } else {
+            get(
+                move |State(router): State<Self>,
+                      query: CqrsHttpQuery<Q>,
+                      headers: HeaderMap,
+                      Extension(context): Extension<CqrsContext>| async move {
+                    Self::group(router, None, query, context, headers).await
+                },
+            )
+        };
+
+        router.routes(UtoipaMethodRouter::<Self>::from((
+            schemas,
+            paths,
+            aggregate_handler,
+        )))
+    }
+
     pub fn routes(storage: DynStorage<V, CqrsHttpQuery<Q>>, tag: &'static str) -> OpenApiRouter {
-        let state = Self::new(storage);
+        Self::routes_with_policies(storage, tag, ReadPolicies::default())
+    }
+
+    /// [`routes`](Self::routes), each route checking its policy before the storage is
+    /// read.
+    pub fn routes_with_policies(
+        storage: DynStorage<V, CqrsHttpQuery<Q>>,
+        tag: &'static str,
+        policies: ReadPolicies,
+    ) -> OpenApiRouter {
         let mut result = OpenApiRouter::<Self>::new();
-        result = Self::find_many(result, tag);
-        result = Self::find_one(result, tag);
-        result.with_state(state)
+        result = Self::find_many(result, tag, &policies);
+        result = Self::find_one(result, tag, &policies);
+        result = Self::aggregate(result, tag, &policies);
+        result.with_state(Self::new(storage, policies))
     }
 
     async fn search(
@@ -177,10 +279,82 @@ where
         parent_id: Option<String>,
         query: CqrsHttpQuery<Q>,
         context: CqrsContext,
+        headers: HeaderMap,
+    ) -> impl IntoResponse {
+        let request_id = context.request_id();
+        if let Err(err) = security::authorize_read(router.policies.find_many(), &context) {
+            return err.with_request_id_if_absent(request_id).into_response();
+        }
+        let projection = match query
+            .reject_aggregation()
+            .and_then(|()| query.projection::<V>())
+        {
+            Ok(projection) => projection,
+            Err(err) => return err.with_request_id_if_absent(request_id).into_response(),
+        };
+        let format = Format::negotiate(&headers);
+        if format != Format::Json {
+            if let Err(err) = query.reject_window() {
+                return err.with_request_id_if_absent(request_id).into_response();
+            }
+            let export = Export {
+                storage: router.storage,
+                parent_id,
+                query,
+                projection,
+                context,
+                format,
+            };
+            return match export.respond(V::TYPE).await {
+                Ok(response) => response,
+                Err(err) => err.with_request_id_if_absent(request_id).into_response(),
+            };
+        }
+        // A projected item has lost whatever its timestamp was read from: its page is
+        // only validated by its ETag.
+        let result = match projection {
+            Some(projection) => router
+                .storage
+                .filter_projected(parent_id, query, projection, context)
+                .await
+                .and_then(|page| conditional::json(&headers, &page, Validators::default())),
+            None => router
+                .storage
+                .filter(parent_id, query, context)
+                .await
+                .and_then(|page| {
+                    let validators = Validators::of_page(page.items.iter().map(V::last_modified));
+                    conditional::json(&headers, &page, validators)
+                }),
+        };
+        match result {
+            Ok(response) => response,
+            Err(err) => err.with_request_id_if_absent(request_id).into_response(),
+        }
+    }
+
+    async fn group(
+        router: Self,
+        parent_id: Option<String>,
+        query: CqrsHttpQuery<Q>,
+        context: CqrsContext,
+        headers: HeaderMap,
     ) -> impl IntoResponse {
         let request_id = context.request_id();
-        match router.storage.filter(parent_id, query, context).await {
-            Ok(result) => (StatusCode::OK, Json(result)).into_response(),
+        if let Err(err) = security::authorize_read(router.policies.aggregate(), &context) {
+            return err.with_request_id_if_absent(request_id).into_response();
+        }
+        let aggregation = match query.aggregation_request() {
+            Ok(aggregation) => aggregation,
+            Err(err) => return err.with_request_id_if_absent(request_id).into_response(),
+        };
+        match router
+            .storage
+            .aggregate(parent_id, query, aggregation, context)
+            .await
+            .and_then(|rows| conditional::json(&headers, &rows, Validators::default()))
+        {
+            Ok(response) => response,
             Err(err) => err.with_request_id_if_absent(request_id).into_response(),
         }
     }
@@ -190,10 +364,21 @@ where
         parent_id: Option<String>,
         id: String,
         context: CqrsContext,
+        headers: HeaderMap,
     ) -> impl IntoResponse {
         let request_id = context.request_id();
+        if let Err(err) = security::authorize_read(router.policies.find_one(), &context) {
+            return err.with_request_id_if_absent(request_id).into_response();
+        }
         match router.storage.find_by_id(parent_id, &id, context).await {
-            Ok(Some(x)) => (StatusCode::OK, Json(x)).into_response(),
+            Ok(Some(view)) => {
+                let validators = Validators {
+                    version: view.version(),
+                    last_modified: view.last_modified(),
+                };
+                conditional::json(&headers, &view, validators)
+                    .unwrap_or_else(|err| err.with_request_id_if_absent(request_id).into_response())
+            }
             Ok(None) => CqrsError::not_found(format!("{} '{}' not found", V::TYPE, id))
                 .with_details(json!({ "id": id }))
                 .with_request_id_if_absent(request_id)
@@ -281,7 +466,9 @@ mod tests {
             .paths
             .paths
             .iter()
-            .find(|(path, item)| item.get.is_some() && !path.ends_with(&id_segment))
+            .find(|(path, item)| {
+                item.get.is_some() && !path.ends_with(&id_segment) && !path.ends_with("_aggregate")
+            })
             .expect("the list route is the GET without the id segment");
         item.get
             .as_ref()
@@ -304,13 +491,142 @@ mod tests {
     }
 
     #[test]
-    fn the_router_generates_both_read_routes() {
+    fn the_router_generates_the_three_read_routes() {
         let api = generated_openapi();
         assert_eq!(
             api.paths.paths.len(),
-            2,
-            "one list route and one find-by-id route, got {:?}",
+            3,
+            "a list route, a find-by-id route and an aggregate route, got {:?}",
             api.paths.paths.keys().collect::<Vec<_>>()
         );
     }
+
+    #[test]
+    fn the_aggregate_route_declares_not_implemented() {
+        let api = generated_openapi();
+        let (_, item) = api
+            .paths
+            .paths
+            .iter()
+            .find(|(path, _)| path.ends_with("/_aggregate"))
+            .expect("the aggregate route sits under the base path");
+        let responses = &item.get.as_ref().expect("a GET").responses.responses;
+        for status in ["400", "422", "500", "501"] {
+            assert!(responses.contains_key(status), "missing {status}");
+        }
+    }
+
+    #[tokio::test]
+    async fn a_read_policy_guards_its_route_only() {
+        let storage: DynStorage<TestView, CqrsHttpQuery<TestQuery>> = Arc::new(NoopStorage);
+        let policies = ReadPolicies::new().with_find_one(crate::Policy::any_role(["librarian"]));
+        let router = CQRSCodexReadRouter::<TestAggregate, TestView, TestQuery>::new(
+            storage.clone(),
+            policies.clone(),
+        );
+
+        let anonymous = CqrsContext::default();
+        let response = CQRSCodexReadRouter::by_id(
+            router.clone(),
+            None,
+            "v1".to_string(),
+            anonymous,
+            HeaderMap::new(),
+        )
+        .await
+        .into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let librarian =
+            CqrsContext::new(Some("ann".to_string())).with_roles(vec!["librarian".to_string()]);
+        let response =
+            CQRSCodexReadRouter::by_id(router, None, "v1".to_string(), librarian, HeaderMap::new())
+                .await
+                .into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let (_, api) =
+            CQRSCodexReadRouter::<TestAggregate, TestView, TestQuery>::routes_with_policies(
+                storage, "test", policies,
+            )
+            .split_for_parts();
+        let id_segment = format!("{{{}_id}}", TestView::TYPE);
+        for (path, item) in &api.paths.paths {
+            let secured = item.get.as_ref().unwrap().security.is_some();
+            assert_eq!(secured, path.ends_with(&id_segment), "{path}");
+        }
+    }
+
+    /// A storage holding the one view `v1`, at version 3.
+    #[derive(Debug, Clone)]
+    struct OneViewStorage;
+
+    cqrs_async_trait! {
+    impl<Q> Storage<TestView, Q> for OneViewStorage
+    where
+        Q: Clone + Debug + MaybeSend + MaybeSync + 'static,
+    {
+        fn type_name(&self) -> &str {
+            "test"
+        }
+        async fn filter(
+            &self,
+            _parent_id: Option<String>,
+            _query: Q,
+            _context: CqrsContext,
+        ) -> Result<Paged<TestView>, CqrsError> {
+            Ok(Paged::new(Vec::new(), 0, 0, 20))
+        }
+        async fn find_by_id(
+            &self,
+            _parent_id: Option<String>,
+            id: &str,
+            _context: CqrsContext,
+        ) -> Result<Option<TestView>, CqrsError> {
+            Ok((id == "v1").then(|| TestView {
+                id: "v1".to_string(),
+                name: "toto".to_string(),
+                version: 3,
+            }))
+        }
+        async fn save(&self, _view: TestView, _context: CqrsContext) -> Result<(), CqrsError> {
+            Ok(())
+        }
+    }
+    }
+
+    #[tokio::test]
+    async fn a_view_still_at_the_version_the_client_holds_is_a_304() {
+        let router = CQRSCodexReadRouter::<TestAggregate, TestView, TestQuery>::new(
+            Arc::new(OneViewStorage),
+            ReadPolicies::default(),
+        );
+        let get = |headers| {
+            CQRSCodexReadRouter::by_id(
+                router.clone(),
+                None,
+                "v1".to_string(),
+                CqrsContext::default(),
+                headers,
+            )
+        };
+
+        let response = get(HeaderMap::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[http::header::ETAG], "W/\"v3\"");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            http::header::IF_NONE_MATCH,
+            http::HeaderValue::from_static("W/\"v3\""),
+        );
+        let response = get(headers).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        let api = generated_openapi();
+        for (path, item) in &api.paths.paths {
+            let responses = &item.get.as_ref().unwrap().responses.responses;
+            assert!(responses.contains_key("304"), "{path}");
+        }
+    }
 }
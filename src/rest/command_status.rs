@@ -0,0 +1,265 @@
+use crate::problem::ProblemDetails;
+use crate::rest::CreationResult;
+use crate::{CqrsContext, CqrsError, MaybeSend, MaybeSync};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use utoipa::ToSchema;
+
+/// Where a command accepted with `202` stands.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum CommandStatus {
+    Pending,
+    /// `result.id` is the id of the aggregate the command created or updated.
+    Succeeded {
+        result: CreationResult,
+    },
+    /// The error as an RFC 9457 document, whatever the `problem-json` feature says:
+    /// it is stored, and a problem document keeps its status where the legacy body
+    /// does not.
+    Failed {
+        error: ProblemDetails,
+    },
+}
+
+/// The `202` body of a command run in the background.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AcceptedCommand {
+    /// Where to follow it: `GET /commands/{command_id}`.
+    pub command_id: String,
+}
+
+/// A command accepted with `202`, as `GET /commands/{command_id}` reports it.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandStatusRecord {
+    pub command_id: String,
+    pub aggregate_type: String,
+    /// The user who sent the command; the only one who can read its status.
+    pub user_id: String,
+    /// The tenant the command was sent under; its status is read under that one only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    pub request_id: String,
+    #[schema(value_type = String)]
+    pub submitted_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub completed_at: Option<DateTime<Utc>>,
+    #[serde(flatten)]
+    pub status: CommandStatus,
+}
+
+impl CommandStatusRecord {
+    /// A record for a command accepted under `context`, not yet run.
+    #[must_use]
+    pub fn pending(command_id: String, aggregate_type: &str, context: &CqrsContext) -> Self {
+        Self {
+            command_id,
+            aggregate_type: aggregate_type.to_string(),
+            user_id: context.current_user(),
+            tenant: context.tenant().map(str::to_string),
+            request_id: context.request_id(),
+            submitted_at: context.now(),
+            completed_at: None,
+            status: CommandStatus::Pending,
+        }
+    }
+
+    /// The record once the command has run.
+    #[must_use]
+    pub fn completed(self, result: Result<String, CqrsError>, at: DateTime<Utc>) -> Self {
+        let status = match result {
+            Ok(id) => CommandStatus::Succeeded {
+                result: CreationResult { id },
+            },
+            Err(error) => CommandStatus::Failed {
+                error: error
+                    .with_request_id_if_absent(self.request_id.clone())
+                    .to_problem(),
+            },
+        };
+        Self {
+            completed_at: Some(at),
+            status,
+            ..self
+        }
+    }
+}
+
+cqrs_async_trait! {
+/// Keeps the status of commands accepted with `202`, for `CQRSWriteRouter` to report.
+///
+/// `save` is called twice per command: with the pending record when it is accepted,
+/// and with the completed one when it has run. How long a record is kept is the
+/// backend's business.
+pub trait CommandStatusStore: MaybeSend + MaybeSync {
+    async fn save(&self, record: CommandStatusRecord) -> Result<(), CqrsError>;
+
+    async fn find(&self, command_id: &str) -> Result<Option<CommandStatusRecord>, CqrsError>;
+}
+}
+
+pub type DynCommandStatusStore = Arc<dyn CommandStatusStore + Send + Sync>;
+
+/// How long [`InMemoryCommandStatusStore`] keeps a record after its command completed.
+pub const DEFAULT_COMMAND_STATUS_RETENTION: Duration = Duration::from_secs(60 * 60);
+
+/// How many completed records [`InMemoryCommandStatusStore`] keeps at most.
+pub const DEFAULT_COMMAND_STATUS_CAPACITY: usize = 10_000;
+
+/// Command statuses held in process memory, and lost with it — a command still pending
+/// then is reported as unknown.
+///
+/// A completed record is evicted once it is older than the retention, or when more
+/// completed records than the capacity are kept, oldest first; a pending one stays until
+/// its command completes. Eviction runs on `save`, and `find` never answers an expired
+/// record.
+#[derive(Clone, Debug)]
+pub struct InMemoryCommandStatusStore {
+    records: Arc<RwLock<HashMap<String, CommandStatusRecord>>>,
+    retention: Duration,
+    capacity: usize,
+}
+
+impl Default for InMemoryCommandStatusStore {
+    fn default() -> Self {
+        Self {
+            records: Arc::default(),
+            retention: DEFAULT_COMMAND_STATUS_RETENTION,
+            capacity: DEFAULT_COMMAND_STATUS_CAPACITY,
+        }
+    }
+}
+
+impl InMemoryCommandStatusStore {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How long a record is kept after its command completed, instead of
+    /// [`DEFAULT_COMMAND_STATUS_RETENTION`].
+    #[must_use]
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// How many completed records are kept at most, instead of
+    /// [`DEFAULT_COMMAND_STATUS_CAPACITY`].
+    #[must_use]
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    fn expired(&self, record: &CommandStatusRecord, now: DateTime<Utc>) -> bool {
+        record.completed_at.is_some_and(|at| {
+            now.signed_duration_since(at)
+                .to_std()
+                .is_ok_and(|age| age > self.retention)
+        })
+    }
+
+    fn evict(&self, records: &mut HashMap<String, CommandStatusRecord>, now: DateTime<Utc>) {
+        records.retain(|_, record| !self.expired(record, now));
+        let mut completed: Vec<_> = records
+            .values()
+            .filter_map(|record| Some((record.completed_at?, record.command_id.clone())))
+            .collect();
+        if completed.len() > self.capacity {
+            completed.sort();
+            for (_, command_id) in &completed[..completed.len() - self.capacity] {
+                records.remove(command_id);
+            }
+        }
+    }
+}
+
+cqrs_async_trait! {
+impl CommandStatusStore for InMemoryCommandStatusStore {
+    async fn save(&self, record: CommandStatusRecord) -> Result<(), CqrsError> {
+        let mut records = self
+            .records
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        records.insert(record.command_id.clone(), record);
+        self.evict(&mut records, Utc::now());
+        Ok(())
+    }
+
+    async fn find(&self, command_id: &str) -> Result<Option<CommandStatusRecord>, CqrsError> {
+        Ok(self
+            .records
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .get(command_id)
+            .filter(|record| !self.expired(record, Utc::now()))
+            .cloned())
+    }
+}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn completed(command_id: &str, ago: chrono::Duration) -> CommandStatusRecord {
+        CommandStatusRecord::pending(command_id.to_string(), "game", &CqrsContext::default())
+            .completed(Ok("g1".to_string()), Utc::now() - ago)
+    }
+
+    #[tokio::test]
+    async fn a_completed_record_is_evicted_after_the_retention() {
+        let store = InMemoryCommandStatusStore::new().with_retention(Duration::from_secs(60));
+        store
+            .save(completed("old", chrono::Duration::minutes(2)))
+            .await
+            .unwrap();
+        assert!(
+            store.find("old").await.unwrap().is_none(),
+            "expired on read"
+        );
+
+        store
+            .save(completed("fresh", chrono::Duration::seconds(1)))
+            .await
+            .unwrap();
+        let pending =
+            CommandStatusRecord::pending("pending".to_string(), "game", &CqrsContext::default());
+        store.save(pending).await.unwrap();
+        assert!(store.find("fresh").await.unwrap().is_some());
+        assert!(store.find("pending").await.unwrap().is_some());
+        assert!(
+            !store.records.read().unwrap().contains_key("old"),
+            "and on save"
+        );
+    }
+
+    #[tokio::test]
+    async fn the_oldest_completed_records_are_evicted_past_the_capacity() {
+        let store = InMemoryCommandStatusStore::new().with_capacity(2);
+        let pending =
+            CommandStatusRecord::pending("pending".to_string(), "game", &CqrsContext::default());
+        store.save(pending).await.unwrap();
+        for (command_id, minutes) in [("c1", 3), ("c2", 2), ("c3", 1)] {
+            store
+                .save(completed(command_id, chrono::Duration::minutes(minutes)))
+                .await
+                .unwrap();
+        }
+
+        assert!(store.find("c1").await.unwrap().is_none());
+        assert!(store.find("c2").await.unwrap().is_some());
+        assert!(store.find("c3").await.unwrap().is_some());
+        assert!(
+            store.find("pending").await.unwrap().is_some(),
+            "a pending record does not count"
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use crate::CqrsError;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use http::header::{CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use http::{HeaderMap, HeaderValue, StatusCode};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use utoipa::openapi::header::HeaderBuilder;
+use utoipa::openapi::path::{ParameterBuilder, ParameterIn};
+use utoipa::openapi::{Paths, RefOr, Required, ResponseBuilder, Type};
+use utoipa::openapi::schema::ObjectBuilder;
+
+/// The format of an HTTP date (RFC 9110 §5.6.7), always in GMT.
+const HTTP_DATE: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// What a read route knows about the representation it is about to send, beyond its
+/// bytes.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Validators {
+    /// The version of a single view; the `ETag` is the body's hash without one.
+    pub version: Option<usize>,
+    pub last_modified: Option<DateTime<Utc>>,
+}
+
+impl Validators {
+    /// The validators of a list: no version — the page changes with its membership, not
+    /// with one view — and the latest `last_modified` of its items.
+    pub fn of_page(last_modified: impl IntoIterator<Item = Option<DateTime<Utc>>>) -> Self {
+        Self {
+            version: None,
+            last_modified: last_modified.into_iter().flatten().max(),
+        }
+    }
+}
+
+/// `200` with `body` as JSON, its `ETag` and, when known, its `Last-Modified`; or `304
+/// Not Modified` with the same headers and no body when the request's `If-None-Match`
+/// — or, without one, its `If-Modified-Since` — says the client already holds it.
+pub(crate) fn json<T: Serialize>(
+    headers: &HeaderMap,
+    body: &T,
+    validators: Validators,
+) -> Result<Response, CqrsError> {
+    let bytes = serde_json::to_vec(body).map_err(CqrsError::serialization_error)?;
+    let etag = match validators.version {
+        // Weak: the version says the view is the same, not that its bytes are.
+        Some(version) => format!("W/\"v{version}\""),
+        None => format!("\"{}\"", hex(&Sha256::digest(&bytes)[..16])),
+    };
+    // HTTP dates have no fraction of a second.
+    let last_modified = validators
+        .last_modified
+        .map(|at| at.format(HTTP_DATE).to_string());
+
+    let not_modified = match headers.get(IF_NONE_MATCH) {
+        Some(candidates) => candidates
+            .to_str()
+            .is_ok_and(|candidates| matches(candidates, &etag)),
+        None => last_modified.as_deref().is_some_and(|last_modified| {
+            headers
+                .get(IF_MODIFIED_SINCE)
+                .and_then(|since| since.to_str().ok())
+                .and_then(|since| DateTime::parse_from_rfc2822(since).ok())
+                .zip(DateTime::parse_from_rfc2822(last_modified).ok())
+                .is_some_and(|(since, last_modified)| last_modified <= since)
+        }),
+    };
+
+    let mut response = if not_modified {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        let mut response = (StatusCode::OK, bytes).into_response();
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        response
+    };
+    let headers = response.headers_mut();
+    headers.insert(ETAG, header_value(etag)?);
+    if let Some(last_modified) = last_modified {
+        headers.insert(LAST_MODIFIED, header_value(last_modified)?);
+    }
+    Ok(response)
+}
+
+/// Whether an `If-None-Match` list names `etag`, compared weakly (RFC 9110 §13.1.2).
+fn matches(candidates: &str, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = opaque(etag);
+    candidates
+        .split(',')
+        .any(|candidate| candidate.trim() == "*" || opaque(candidate) == etag)
+}
+
+fn header_value(value: String) -> Result<HeaderValue, CqrsError> {
+    HeaderValue::try_from(value).map_err(|e| CqrsError::internal(e.to_string()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Documents the conditional GET of every operation of `paths`: the `If-None-Match` and
+/// `If-Modified-Since` headers, the `ETag` and `Last-Modified` of the `200`, and the
+/// `304`.
+pub(crate) fn document(mut paths: Paths) -> Paths {
+    let string = || {
+        RefOr::T(utoipa::openapi::Schema::Object(
+            ObjectBuilder::new().schema_type(Type::String).build(),
+        ))
+    };
+    for item in paths.paths.values_mut() {
+        let Some(operation) = item.get.as_mut() else {
+            continue;
+        };
+        let parameters = operation.parameters.get_or_insert_with(Vec::new);
+        parameters.push(
+            ParameterBuilder::new()
+                .name("If-None-Match")
+                .parameter_in(ParameterIn::Header)
+                .description(Some(
+                    "ETags of representations the client holds; 304 when one of them is \
+                     still current.",
+                ))
+                .required(Required::False)
+                .schema(Some(string()))
+                .build(),
+        );
+        parameters.push(
+            ParameterBuilder::new()
+                .name("If-Modified-Since")
+                .parameter_in(ParameterIn::Header)
+                .description(Some(
+                    "An HTTP date; 304 when nothing changed since. Ignored when \
+                     If-None-Match is sent.",
+                ))
+                .required(Required::False)
+                .schema(Some(string()))
+                .build(),
+        );
+        if let Some(RefOr::T(ok)) = operation.responses.responses.get_mut("200") {
+            ok.headers.insert(
+                "ETag".to_string(),
+                HeaderBuilder::new().schema(string()).build(),
+            );
+            ok.headers.insert(
+                "Last-Modified".to_string(),
+                HeaderBuilder::new()
+                    .schema(string())
+                    .description(Some("Sent when the views carry a timestamp."))
+                    .build(),
+            );
+        }
+        operation.responses.responses.insert(
+            StatusCode::NOT_MODIFIED.as_u16().to_string(),
+            RefOr::T(ResponseBuilder::new().description("Not Modified").build()),
+        );
+    }
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use http::header::HeaderName;
+    use serde_json::json;
+
+    fn request(header: HeaderName, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn a_matching_etag_is_a_304_with_the_same_validators() {
+        let body = json!({ "id": "v1", "name": "toto" });
+        let first = json(&HeaderMap::new(), &body, Validators::default()).unwrap();
+        assert_eq!(first.status(), StatusCode::OK);
+        let etag = first.headers()[ETAG].to_str().unwrap().to_string();
+        assert!(etag.starts_with('"') && etag.len() == 34, "{etag}");
+
+        let again = json(&request(IF_NONE_MATCH, &etag), &body, Validators::default()).unwrap();
+        assert_eq!(again.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(again.headers()[ETAG], etag.as_str());
+
+        // Another body, another tag.
+        let changed = json(
+            &request(IF_NONE_MATCH, &format!("\"other\", {etag}")),
+            &json!({ "id": "v1", "name": "titi" }),
+            Validators::default(),
+        )
+        .unwrap();
+        assert_eq!(changed.status(), StatusCode::OK);
+        assert!(
+            json(&request(IF_NONE_MATCH, "*"), &body, Validators::default())
+                .unwrap()
+                .status()
+                .is_redirection()
+        );
+    }
+
+    #[test]
+    fn a_version_gives_a_weak_etag_matched_weakly() {
+        let validators = Validators {
+            version: Some(3),
+            last_modified: None,
+        };
+        let response = json(&HeaderMap::new(), &json!({}), validators).unwrap();
+        assert_eq!(response.headers()[ETAG], "W/\"v3\"");
+        assert!(!response.headers().contains_key(LAST_MODIFIED));
+
+        let response = json(&request(IF_NONE_MATCH, "\"v3\""), &json!({}), validators).unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    }
+
+    #[test]
+    fn if_modified_since_is_compared_to_the_second_and_yields_to_if_none_match() {
+        let at = Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap()
+            + chrono::Duration::milliseconds(250);
+        let validators =
+            Validators::of_page([None, Some(at), Some(at - chrono::Duration::days(1))]);
+
+        let response = json(&HeaderMap::new(), &json!([]), validators).unwrap();
+        assert_eq!(
+            response.headers()[LAST_MODIFIED],
+            "Sun, 01 Mar 2026 12:00:00 GMT"
+        );
+
+        let since = request(IF_MODIFIED_SINCE, "Sun, 01 Mar 2026 12:00:00 GMT");
+        assert_eq!(
+            json(&since, &json!([]), validators).unwrap().status(),
+            StatusCode::NOT_MODIFIED
+        );
+        let earlier = request(IF_MODIFIED_SINCE, "Sun, 01 Mar 2026 11:59:59 GMT");
+        assert_eq!(
+            json(&earlier, &json!([]), validators).unwrap().status(),
+            StatusCode::OK
+        );
+
+        let mut both = since;
+        both.insert(IF_NONE_MATCH, HeaderValue::from_static("\"stale\""));
+        assert_eq!(
+            json(&both, &json!([]), validators).unwrap().status(),
+            StatusCode::OK
+        );
+    }
+}
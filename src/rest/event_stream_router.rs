@@ -0,0 +1,713 @@
+use crate::dispatchers::{BroadcastDispatcher, BroadcastEvent};
+use crate::rest::security;
+use crate::{Aggregate, CqrsContext, CqrsError, DynEventStore, EventEnvelope, ReadPolicies};
+use axum::extract::{Path, State};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Extension;
+use futures::stream::{self, BoxStream};
+use futures::StreamExt;
+use http::{HeaderMap, StatusCode};
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use utoipa::openapi::path::{Parameter, ParameterBuilder, ParameterIn};
+use utoipa::openapi::{HttpMethod, ObjectBuilder, RefOr, Required, Schema, Type};
+use utoipa::PartialSchema;
+use utoipa_axum::router::{OpenApiRouter, UtoipaMethodRouter};
+
+use crate::rest::helpers;
+
+/// Media type of an SSE response.
+pub const EVENT_STREAM_MEDIA_TYPE: &str = "text/event-stream";
+
+/// Request header an `EventSource` sends on reconnect, carrying the last `id` it saw.
+pub const LAST_EVENT_ID: &str = "last-event-id";
+
+/// Name of the SSE event telling a firehose subscriber that it fell behind.
+pub const LAGGED_EVENT: &str = "lagged";
+
+/// What a stream yields before it becomes SSE: an event, or — on the firehose only —
+/// the number of events a slow subscriber missed.
+#[derive(Debug)]
+enum Frame<A: Aggregate> {
+    Event(EventEnvelope<A>),
+    Lagged(u64),
+}
+
+/// Serves committed events as Server-Sent Events, so a front-end follows changes instead
+/// of polling `CQRSAuditLogRouter`:
+/// - `GET /{id}/events/stream` — the events of one aggregate, in version order. Each SSE
+///   `id` is the event's version, so a reconnecting `EventSource` resumes where it left
+///   off: the events after `Last-Event-ID` are backfilled from the event store before
+///   the live ones. Without the header the stream starts at the aggregate's current
+///   version.
+/// - `GET /events/stream` — every event of the aggregate type, live only. Versions are
+///   per aggregate, so there is no position to resume from: its events carry no `id`,
+///   and a `Last-Event-ID` sent to it is refused with `400`.
+///
+/// Each `data` is the [`EventEnvelope`] as JSON. Live events come from a
+/// [`BroadcastDispatcher`], which must be registered on the `CqrsCommandEngine` for
+/// anything to arrive. A per-aggregate subscriber that falls behind the broadcast
+/// capacity catches up from the event store; a firehose subscriber cannot, and receives
+/// a `lagged` event whose `data` is the number of events it missed.
+///
+/// Both streams carry only the events committed for the tenant of the subscriber's
+/// `CqrsContext`.
+#[derive(Clone)]
+pub struct CQRSEventStreamRouter<A>
+where
+    A: Aggregate + 'static,
+{
+    store: DynEventStore<A>,
+    events: BroadcastDispatcher<A>,
+    policies: Arc<ReadPolicies>,
+}
+
+impl<A> CQRSEventStreamRouter<A>
+where
+    A: Aggregate + 'static,
+{
+    #[must_use]
+    fn new(
+        store: DynEventStore<A>,
+        events: BroadcastDispatcher<A>,
+        policies: ReadPolicies,
+    ) -> Self {
+        Self {
+            store,
+            events,
+            policies: Arc::new(policies),
+        }
+    }
+
+    fn path_aggregate_id_field() -> String {
+        format!("{}_id", A::TYPE)
+    }
+
+    fn last_event_id_param() -> Parameter {
+        ParameterBuilder::new()
+            .name("Last-Event-ID")
+            .parameter_in(ParameterIn::Header)
+            .description(Some(
+                "The version of the last event received. The stream backfills the events \
+                 after it from the event store, then continues live. Sent by an \
+                 EventSource on reconnect; a value that is not a version is rejected \
+                 with 400.",
+            ))
+            .required(Required::False)
+            .schema(Some(usize::schema()))
+            .build()
+    }
+
+    /// The 200 body as documented: a stream whose `data` lines are event envelopes.
+    fn stream_schema() -> RefOr<Schema> {
+        RefOr::T(Schema::Object(
+            ObjectBuilder::new()
+                .schema_type(Type::String)
+                .description(Some(
+                    "Server-Sent Events; each data line is an event envelope as JSON",
+                ))
+                .build(),
+        ))
+    }
+
+    fn aggregate_stream_route(
+        router: OpenApiRouter<CQRSEventStreamRouter<A>>,
+        tag: &str,
+        policies: &ReadPolicies,
+    ) -> OpenApiRouter<CQRSEventStreamRouter<A>> {
+        let path = format!("/{{{}}}/events/stream", Self::path_aggregate_id_field());
+        let paths = helpers::generate_route_with_media_type(
+            tag,
+            HttpMethod::Get,
+            &path,
+            EVENT_STREAM_MEDIA_TYPE,
+            Self::stream_schema(),
+            vec![(Self::path_aggregate_id_field(), String::schema())],
+            vec![Self::last_event_id_param()],
+            None,
+            &[StatusCode::BAD_REQUEST, StatusCode::INTERNAL_SERVER_ERROR],
+        );
+        let paths = security::secure(paths, policies.find_one());
+
+        let handler = get(
+            move |State(router): State<CQRSEventStreamRouter<A>>,
+                  Path(aggregate_id): Path<String>,
+                  headers: HeaderMap,
+                  Extension(context): Extension<CqrsContext>| async move {
+                router
+                    .aggregate_stream(aggregate_id, headers, context)
+                    .await
+            },
+        );
+
+        router.routes(UtoipaMethodRouter::<CQRSEventStreamRouter<A>>::from((
+            vec![helpers::error_schema()],
+            paths,
+            handler,
+        )))
+    }
+
+    fn type_stream_route(
+        router: OpenApiRouter<CQRSEventStreamRouter<A>>,
+        tag: &str,
+        policies: &ReadPolicies,
+    ) -> OpenApiRouter<CQRSEventStreamRouter<A>> {
+        let paths = helpers::generate_route_with_media_type(
+            tag,
+            HttpMethod::Get,
+            "/events/stream",
+            EVENT_STREAM_MEDIA_TYPE,
+            Self::stream_schema(),
+            vec![],
+            vec![],
+            None,
+            &[StatusCode::BAD_REQUEST, StatusCode::INTERNAL_SERVER_ERROR],
+        );
+        let paths = security::secure(paths, policies.find_many());
+
+        let handler = get(
+            move |State(router): State<CQRSEventStreamRouter<A>>,
+                  headers: HeaderMap,
+                  Extension(context): Extension<CqrsContext>| async move {
+                router.type_stream(&headers, context)
+            },
+        );
+
+        router.routes(UtoipaMethodRouter::<CQRSEventStreamRouter<A>>::from((
+            vec![helpers::error_schema()],
+            paths,
+            handler,
+        )))
+    }
+
+    /// `events` is the dispatcher registered on the engine, or a clone of it.
+    pub fn routes(
+        store: DynEventStore<A>,
+        events: BroadcastDispatcher<A>,
+        tag: &'static str,
+    ) -> OpenApiRouter {
+        Self::routes_with_policies(store, events, tag, ReadPolicies::default())
+    }
+
+    /// [`routes`](Self::routes), each stream checking its policy before it subscribes:
+    /// `find_one` for one aggregate's, `find_many` for the type's.
+    pub fn routes_with_policies(
+        store: DynEventStore<A>,
+        events: BroadcastDispatcher<A>,
+        tag: &'static str,
+        policies: ReadPolicies,
+    ) -> OpenApiRouter {
+        let mut result = OpenApiRouter::<CQRSEventStreamRouter<A>>::new();
+        result = Self::aggregate_stream_route(result, tag, &policies);
+        result = Self::type_stream_route(result, tag, &policies);
+        result.with_state(Self::new(store, events, policies))
+    }
+
+    async fn aggregate_stream(
+        &self,
+        aggregate_id: String,
+        headers: HeaderMap,
+        context: CqrsContext,
+    ) -> Response {
+        if let Err(err) = security::authorize_read(self.policies.find_one(), &context) {
+            return err.into_response();
+        }
+        let frames = match last_event_id(&headers) {
+            Ok(resume_after) => {
+                self.aggregate_frames(aggregate_id, resume_after, context)
+                    .await
+            }
+            Err(err) => Err(err),
+        };
+        match frames {
+            Ok(frames) => Self::sse(frames).into_response(),
+            Err(err) => err.into_response(),
+        }
+    }
+
+    fn type_stream(&self, headers: &HeaderMap, context: CqrsContext) -> Response {
+        if let Err(err) = security::authorize_read(self.policies.find_many(), &context) {
+            return err.into_response();
+        }
+        // Refused rather than ignored (ADR-0001): a caller sending it expects a backfill
+        // this stream cannot give.
+        if headers.contains_key(LAST_EVENT_ID) {
+            return CqrsError::validation(
+                "Last-Event-ID: the type stream is live only and cannot resume; \
+                 follow one aggregate's stream to resume",
+            )
+            .into_response();
+        }
+        Self::sse(self.type_frames(context)).into_response()
+    }
+
+    fn sse(
+        frames: BoxStream<'static, Frame<A>>,
+    ) -> Sse<impl futures::Stream<Item = Result<Event, Infallible>>> {
+        let events = frames.filter_map(|frame| async move {
+            let event = match frame {
+                Frame::Event(envelope) => {
+                    let id = envelope.version.to_string();
+                    match Event::default().json_data(&envelope) {
+                        Ok(event) => event.id(id),
+                        Err(e) => {
+                            tracing::error!(error = %e, "failed to serialize a streamed event");
+                            return None;
+                        }
+                    }
+                }
+                Frame::Lagged(missed) => Event::default()
+                    .event(LAGGED_EVENT)
+                    .data(missed.to_string()),
+            };
+            Some(Ok(event))
+        });
+        Sse::new(events).keep_alive(KeepAlive::default())
+    }
+
+    /// The events of one aggregate after `resume_after`, or after its current version.
+    ///
+    /// The subscription is taken *before* the store is read, so an event committed in
+    /// between is in both; the version check drops the second copy. The same check lets
+    /// a lagging receiver re-read from the store without repeating itself.
+    async fn aggregate_frames(
+        &self,
+        aggregate_id: String,
+        resume_after: Option<usize>,
+        context: CqrsContext,
+    ) -> Result<BoxStream<'static, Frame<A>>, CqrsError> {
+        let receiver = self.events.subscribe();
+        let position = match resume_after {
+            Some(version) => version,
+            None => self.current_version(&aggregate_id, &context).await?,
+        };
+        let backlog = self.events_after(&aggregate_id, position, &context).await?;
+
+        let state = AggregateStream {
+            store: self.store.clone(),
+            aggregate_id,
+            context,
+            receiver,
+            backlog,
+            position,
+        };
+        Ok(stream::unfold(state, |mut state| async move {
+            let envelope = state.next().await?;
+            Some((Frame::Event(envelope), state))
+        })
+        .boxed())
+    }
+
+    fn type_frames(&self, context: CqrsContext) -> BoxStream<'static, Frame<A>> {
+        let state = (self.events.subscribe(), context);
+        stream::unfold(state, |(mut receiver, context)| async move {
+            let frame = loop {
+                match receiver.recv().await {
+                    Ok(event) if event.visible_to(&context) => break Frame::Event(event.envelope),
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(missed)) => break Frame::Lagged(missed),
+                    Err(RecvError::Closed) => return None,
+                }
+            };
+            Some((frame, (receiver, context)))
+        })
+        .boxed()
+    }
+
+    /// The version of the aggregate's last event, `0` for an aggregate with none yet.
+    async fn current_version(
+        &self,
+        aggregate_id: &str,
+        context: &CqrsContext,
+    ) -> Result<usize, CqrsError> {
+        let from = self
+            .store
+            .load_snapshot(aggregate_id, context)
+            .await?
+            .map_or(0, |snapshot| snapshot.version);
+        let tail = self.events_after(aggregate_id, from, context).await?;
+        Ok(tail.back().map_or(from, |envelope| envelope.version))
+    }
+
+    async fn events_after(
+        &self,
+        aggregate_id: &str,
+        version: usize,
+        context: &CqrsContext,
+    ) -> Result<VecDeque<EventEnvelope<A>>, CqrsError> {
+        load_after(&self.store, aggregate_id, version, context).await
+    }
+}
+
+async fn load_after<A: Aggregate + 'static>(
+    store: &DynEventStore<A>,
+    aggregate_id: &str,
+    version: usize,
+    context: &CqrsContext,
+) -> Result<VecDeque<EventEnvelope<A>>, CqrsError> {
+    let mut events = store
+        .load_events_from_version(aggregate_id, version, context)
+        .await?;
+    let mut loaded = VecDeque::new();
+    while let Some(envelope) = events.next().await {
+        loaded.push_back(envelope?);
+    }
+    Ok(loaded)
+}
+
+/// Reads `Last-Event-ID`: absent, or a version. Anything else is a `400`, not a stream
+/// from the start — the caller asked to resume and would silently get something else.
+fn last_event_id(headers: &HeaderMap) -> Result<Option<usize>, CqrsError> {
+    let Some(value) = headers.get(LAST_EVENT_ID) else {
+        return Ok(None);
+    };
+    value
+        .to_str()
+        .ok()
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .map(Some)
+        .ok_or_else(|| {
+            CqrsError::validation(format!(
+                "Last-Event-ID: expected an event version, got {value:?}"
+            ))
+        })
+}
+
+/// One aggregate's stream: the backlog read from the store, then the broadcast.
+struct AggregateStream<A: Aggregate + 'static> {
+    store: DynEventStore<A>,
+    aggregate_id: String,
+    /// The context of the request, for the store reads catching up after a lag.
+    context: CqrsContext,
+    receiver: Receiver<BroadcastEvent<A>>,
+    backlog: VecDeque<EventEnvelope<A>>,
+    /// The version of the last event yielded, or the one the stream started after.
+    position: usize,
+}
+
+impl<A: Aggregate + 'static> AggregateStream<A> {
+    /// The next event after `position`, or `None` when the stream is over — the
+    /// dispatcher is gone, or the store failed while catching up.
+    async fn next(&mut self) -> Option<EventEnvelope<A>> {
+        loop {
+            let envelope = match self.backlog.pop_front() {
+                Some(envelope) => envelope,
+                None => match self.receiver.recv().await {
+                    Ok(event)
+                        if event.envelope.aggregate_id == self.aggregate_id
+                            && event.visible_to(&self.context) =>
+                    {
+                        event.envelope
+                    }
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(missed)) => {
+                        tracing::debug!(
+                            aggregate_id = %self.aggregate_id,
+                            missed,
+                            "Event stream lagged, catching up from the event store"
+                        );
+                        match load_after(
+                            &self.store,
+                            &self.aggregate_id,
+                            self.position,
+                            &self.context,
+                        )
+                        .await
+                        {
+                            Ok(backlog) => self.backlog = backlog,
+                            Err(e) => {
+                                tracing::error!(error = %e, "failed to catch up an event stream");
+                                return None;
+                            }
+                        }
+                        continue;
+                    }
+                    Err(RecvError::Closed) => return None,
+                },
+            };
+            if envelope.version > self.position {
+                self.position = envelope.version;
+                return Some(envelope);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::es::inmemory::InMemoryPersist;
+    use crate::es::EventStoreImpl;
+    use crate::testing::{CreateCommand, TestAggregate, TestEvent, UpdateCommand};
+    use crate::{CqrsCommandEngine, Dispatcher};
+    use chrono::Utc;
+    use http::HeaderValue;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    struct Fixture {
+        engine: CqrsCommandEngine<TestAggregate>,
+        router: CQRSEventStreamRouter<TestAggregate>,
+    }
+
+    fn fixture(capacity: usize) -> Fixture {
+        let store: DynEventStore<TestAggregate> = EventStoreImpl::new(InMemoryPersist::new());
+        let events = BroadcastDispatcher::new(capacity);
+        let dispatchers: Vec<Box<dyn Dispatcher<TestAggregate> + Send + Sync>> =
+            vec![Box::new(events.clone())];
+        Fixture {
+            engine: CqrsCommandEngine::new(store.clone(), dispatchers, (), Box::new(|_| {})),
+            router: CQRSEventStreamRouter::new(store, events, ReadPolicies::default()),
+        }
+    }
+
+    async fn versions(
+        frames: &mut BoxStream<'static, Frame<TestAggregate>>,
+        n: usize,
+    ) -> Vec<usize> {
+        let mut versions = Vec::new();
+        for _ in 0..n {
+            let frame = tokio::time::timeout(Duration::from_secs(1), frames.next())
+                .await
+                .expect("a frame in time")
+                .expect("the stream is open");
+            match frame {
+                Frame::Event(envelope) => versions.push(envelope.version),
+                Frame::Lagged(missed) => panic!("unexpected lag of {missed}"),
+            }
+        }
+        versions
+    }
+
+    async fn increment(fixture: &Fixture, id: &str, times: usize) {
+        for _ in 0..times {
+            fixture
+                .engine
+                .execute_update(id, UpdateCommand::Increment, &CqrsContext::default())
+                .await
+                .unwrap();
+        }
+    }
+
+    async fn create(fixture: &Fixture) -> String {
+        let command = CreateCommand::Initialize {
+            name: "toto".to_string(),
+        };
+        fixture
+            .engine
+            .execute_create(command, &CqrsContext::default())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn without_last_event_id_the_stream_starts_at_the_current_version() {
+        let fixture = fixture(16);
+        let id = create(&fixture).await;
+        increment(&fixture, &id, 1).await;
+
+        let mut frames = fixture
+            .router
+            .aggregate_frames(id.clone(), None, CqrsContext::default())
+            .await
+            .unwrap();
+        let other = create(&fixture).await;
+        increment(&fixture, &other, 1).await;
+        increment(&fixture, &id, 2).await;
+
+        assert_eq!(
+            versions(&mut frames, 2).await,
+            [3, 4],
+            "only this aggregate, only new"
+        );
+    }
+
+    #[tokio::test]
+    async fn last_event_id_backfills_from_the_store_then_goes_live() {
+        let fixture = fixture(16);
+        let id = create(&fixture).await;
+        increment(&fixture, &id, 2).await;
+
+        let mut frames = fixture
+            .router
+            .aggregate_frames(id.clone(), Some(1), CqrsContext::default())
+            .await
+            .unwrap();
+        increment(&fixture, &id, 1).await;
+
+        assert_eq!(versions(&mut frames, 3).await, [2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn a_lagging_aggregate_stream_catches_up_from_the_store_without_repeats() {
+        let fixture = fixture(1);
+        let id = create(&fixture).await;
+
+        let mut frames = fixture
+            .router
+            .aggregate_frames(id.clone(), Some(0), CqrsContext::default())
+            .await
+            .unwrap();
+        increment(&fixture, &id, 3).await;
+
+        assert_eq!(versions(&mut frames, 4).await, [1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn the_type_stream_reports_what_a_slow_subscriber_missed() {
+        let fixture = fixture(2);
+        let mut frames = fixture.router.type_frames(CqrsContext::default());
+        let first = create(&fixture).await;
+        let second = create(&fixture).await;
+        increment(&fixture, &first, 1).await;
+
+        assert!(matches!(frames.next().await, Some(Frame::Lagged(1))));
+        match frames.next().await {
+            Some(Frame::Event(envelope)) => assert_eq!(envelope.aggregate_id, second),
+            other => panic!("expected the second creation, got {other:?}"),
+        }
+    }
+
+    fn tenant(name: &str) -> CqrsContext {
+        CqrsContext::default().with_tenant(Some(name.to_string()))
+    }
+
+    /// Dispatches one event of `aggregate_id` for `context`'s tenant, its id naming both.
+    async fn dispatch(
+        fixture: &Fixture,
+        aggregate_id: &str,
+        version: usize,
+        context: &CqrsContext,
+    ) {
+        let envelope = EventEnvelope {
+            event_id: format!("{}-{version}", context.tenant().unwrap_or_default()),
+            aggregate_id: aggregate_id.to_string(),
+            version,
+            payload: TestEvent::Updated {
+                name: "toto".to_string(),
+            },
+            metadata: HashMap::new(),
+            at: Utc::now(),
+        };
+        fixture
+            .router
+            .events
+            .dispatch(aggregate_id, &[envelope], context)
+            .await
+            .unwrap();
+    }
+
+    async fn next_event_id(frames: &mut BoxStream<'static, Frame<TestAggregate>>) -> String {
+        match tokio::time::timeout(Duration::from_secs(1), frames.next()).await {
+            Ok(Some(Frame::Event(envelope))) => envelope.event_id,
+            other => panic!("expected an event, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn the_type_stream_carries_only_the_subscribers_tenant() {
+        let fixture = fixture(16);
+        let (acme, globex) = (tenant("acme"), tenant("globex"));
+        let mut frames = fixture.router.type_frames(acme.clone());
+
+        dispatch(&fixture, "g1", 1, &globex).await;
+        dispatch(&fixture, "g1", 1, &acme).await;
+
+        assert_eq!(next_event_id(&mut frames).await, "acme-1");
+    }
+
+    #[tokio::test]
+    async fn an_aggregate_stream_ignores_the_same_id_in_another_tenant() {
+        let fixture = fixture(16);
+        let (acme, globex) = (tenant("acme"), tenant("globex"));
+        let mut frames = fixture
+            .router
+            .aggregate_frames("g1".to_string(), Some(0), acme.clone())
+            .await
+            .unwrap();
+
+        dispatch(&fixture, "g1", 1, &globex).await;
+        dispatch(&fixture, "g1", 1, &acme).await;
+
+        assert_eq!(next_event_id(&mut frames).await, "acme-1");
+    }
+
+    #[tokio::test]
+    async fn each_stream_is_behind_its_read_policy() {
+        let fixture = fixture(1);
+        let policies = ReadPolicies::new().with_find_many(crate::Policy::any_role(["ops"]));
+        let router = CQRSEventStreamRouter::new(
+            fixture.router.store.clone(),
+            fixture.router.events.clone(),
+            policies.clone(),
+        );
+        let headers = HeaderMap::new();
+
+        let response = router.type_stream(&headers, CqrsContext::default());
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let ops = CqrsContext::new(Some("ann".to_string())).with_roles(vec!["ops".to_string()]);
+        assert_eq!(router.type_stream(&headers, ops).status(), StatusCode::OK);
+        let response = router
+            .aggregate_stream("g1".to_string(), headers, CqrsContext::default())
+            .await;
+        assert_eq!(
+            response.status(),
+            StatusCode::OK,
+            "no policy on one aggregate"
+        );
+
+        let (_, api) = CQRSEventStreamRouter::routes_with_policies(
+            fixture.router.store.clone(),
+            fixture.router.events.clone(),
+            "test",
+            policies,
+        )
+        .split_for_parts();
+        for (path, item) in &api.paths.paths {
+            let secured = item.get.as_ref().unwrap().security.is_some();
+            assert_eq!(secured, path == "/events/stream", "{path}");
+        }
+    }
+
+    #[test]
+    fn last_event_id_is_a_version_or_a_400() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(last_event_id(&headers).unwrap(), None);
+
+        headers.insert(LAST_EVENT_ID, HeaderValue::from_static(" 7 "));
+        assert_eq!(last_event_id(&headers).unwrap(), Some(7));
+
+        headers.insert(LAST_EVENT_ID, HeaderValue::from_static("evt-7"));
+        let err = last_event_id(&headers).unwrap_err();
+        assert_eq!(err.status, 400);
+        assert!(err.message.contains("Last-Event-ID"), "{}", err.message);
+    }
+
+    #[test]
+    fn both_streams_are_documented_as_event_streams() {
+        let fixture = fixture(1);
+        let (_router, api) = CQRSEventStreamRouter::routes(
+            fixture.router.store.clone(),
+            fixture.router.events.clone(),
+            "test",
+        )
+        .split_for_parts();
+
+        let mut paths: Vec<&String> = api.paths.paths.keys().collect();
+        paths.sort();
+        assert_eq!(paths, ["/events/stream", "/{TEST_id}/events/stream"]);
+        for item in api.paths.paths.values() {
+            let get = item.get.as_ref().expect("a GET");
+            let RefOr::T(ok) = &get.responses.responses["200"] else {
+                panic!("an inline 200 response");
+            };
+            assert!(ok.content.contains_key(EVENT_STREAM_MEDIA_TYPE));
+            assert!(get.responses.responses.contains_key("400"));
+        }
+    }
+}
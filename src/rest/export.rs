@@ -0,0 +1,433 @@
+use crate::read::storage::DynStorage;
+use crate::read::{Projection, Query};
+use crate::rest::codex::CqrsHttpQuery;
+use crate::{CqrsContext, CqrsError};
+use axum::body::{Body, Bytes};
+use axum::response::Response;
+use futures::stream::{self, StreamExt};
+use http::header::{ACCEPT, CONTENT_DISPOSITION, CONTENT_TYPE};
+use http::{HeaderMap, HeaderValue, StatusCode};
+use serde::de::DeserializeOwned;
+use serde_json::Value as JsonValue;
+use std::fmt::Debug;
+use tracing::error;
+use utoipa::openapi::schema::ObjectBuilder;
+use utoipa::openapi::{Content, Paths, RefOr, Schema, Type};
+
+pub(crate) const CSV: &str = "text/csv";
+pub(crate) const NDJSON: &str = "application/x-ndjson";
+
+/// Items read from the storage per query. An export holds one batch in memory at a time.
+const BATCH_SIZE: i64 = 500;
+
+/// What a list request asked for in its `Accept` header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Format {
+    Json,
+    Csv,
+    Ndjson,
+}
+
+impl Format {
+    /// The best of the media types the client accepts, by `q`, and in the order it listed
+    /// them among equals. JSON when it accepts none of the three, as when it sends no
+    /// `Accept` at all.
+    pub(crate) fn negotiate(headers: &HeaderMap) -> Self {
+        let mut accepted: Vec<(String, f32)> = headers
+            .get_all(ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|range| {
+                let mut parts = range.split(';');
+                let media = parts.next().unwrap_or_default().trim().to_ascii_lowercase();
+                let q = parts
+                    .filter_map(|param| param.trim().strip_prefix("q="))
+                    .find_map(|q| q.trim().parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                (media, q)
+            })
+            .filter(|(_, q)| *q > 0.0)
+            .collect();
+        accepted.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        accepted
+            .iter()
+            .find_map(|(media, _)| match media.as_str() {
+                CSV => Some(Self::Csv),
+                NDJSON => Some(Self::Ndjson),
+                "application/json" | "application/*" | "*/*" => Some(Self::Json),
+                _ => None,
+            })
+            .unwrap_or(Self::Json)
+    }
+}
+
+/// Documents the two export media types on the `200` of every operation of `paths`.
+pub(crate) fn document(mut paths: Paths) -> Paths {
+    for item in paths.paths.values_mut() {
+        let Some(RefOr::T(ok)) = item
+            .get
+            .as_mut()
+            .and_then(|operation| operation.responses.responses.get_mut("200"))
+        else {
+            continue;
+        };
+        for (media_type, description) in [
+            (
+                CSV,
+                "Every matching item, a header row then one row per item.",
+            ),
+            (NDJSON, "Every matching item, one JSON object per line."),
+        ] {
+            ok.content.insert(
+                media_type.to_string(),
+                Content::new(Some(RefOr::T(Schema::Object(
+                    ObjectBuilder::new()
+                        .schema_type(Type::String)
+                        .description(Some(description))
+                        .build(),
+                )))),
+            );
+        }
+    }
+    paths
+}
+
+/// Where the next batch starts.
+enum Position {
+    Offset(i64),
+    After(String),
+    Done,
+}
+
+/// An export of the list `query` selects, in `format`.
+pub(crate) struct Export<V, Q: serde::Serialize> {
+    pub storage: DynStorage<V, CqrsHttpQuery<Q>>,
+    pub parent_id: Option<String>,
+    pub query: CqrsHttpQuery<Q>,
+    pub projection: Option<Projection>,
+    pub context: CqrsContext,
+    pub format: Format,
+}
+
+impl<V, Q> Export<V, Q>
+where
+    V: Debug + Clone + Default + serde::Serialize + DeserializeOwned + Send + Sync + 'static,
+    Q: Query + Clone + Debug + serde::Serialize + Send + Sync + 'static,
+{
+    /// Streams the export. The first batch is read before answering, so that a query the
+    /// storage refuses is still an error response rather than a truncated body; a later
+    /// failure can only cut the body short, and is logged.
+    pub(crate) async fn respond(self, file_name: &str) -> Result<Response, CqrsError> {
+        let (items, next) = self.fetch(Position::Offset(0)).await?;
+        let columns = match &self.projection {
+            Some(projection) => projection.fields().to_vec(),
+            None => columns_of::<V>(&items),
+        };
+        let mut head = match self.format {
+            Format::Csv => csv_line(
+                columns
+                    .iter()
+                    .map(|column| JsonValue::from(column.as_str())),
+            ),
+            _ => String::new(),
+        };
+        head.push_str(&render(self.format, &columns, &items));
+
+        let (media_type, extension) = match self.format {
+            Format::Csv => ("text/csv; charset=utf-8", "csv"),
+            _ => (NDJSON, "ndjson"),
+        };
+        let rest = stream::unfold(
+            (self, columns, next),
+            |(export, columns, position)| async move {
+                if let Position::Done = position {
+                    return None;
+                }
+                match export.fetch(position).await {
+                    Ok((items, next)) => {
+                        let chunk = Bytes::from(render(export.format, &columns, &items));
+                        Some((Ok(chunk), (export, columns, next)))
+                    }
+                    Err(err) => {
+                        error!(error = %err, "export interrupted");
+                        Some((Err(err), (export, columns, Position::Done)))
+                    }
+                }
+            },
+        );
+        let body =
+            Body::from_stream(stream::once(async move { Ok(Bytes::from(head)) }).chain(rest));
+
+        let mut response = Response::new(body);
+        *response.status_mut() = StatusCode::OK;
+        let headers = response.headers_mut();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(media_type));
+        if let Ok(disposition) =
+            HeaderValue::try_from(format!("attachment; filename=\"{file_name}.{extension}\""))
+        {
+            headers.insert(CONTENT_DISPOSITION, disposition);
+        }
+        Ok(response)
+    }
+
+    /// One batch, and where the next one starts: after the page's `nextCursor` — a
+    /// keyset holds its place while items are written, and every batch of a storage
+    /// with an id field is sorted, by the id at least. A search, ordered by relevance,
+    /// and a snapshot storage have no keyset: they go on at the next offset until a
+    /// batch comes back short.
+    async fn fetch(&self, position: Position) -> Result<(Vec<JsonValue>, Position), CqrsError> {
+        let (after, skip) = match position {
+            Position::Offset(skip) => (None, skip),
+            Position::After(cursor) => (Some(cursor), 0),
+            Position::Done => return Ok((Vec::new(), Position::Done)),
+        };
+        let by_cursor = after.is_some();
+        let query = self
+            .query
+            .batch(after, skip, BATCH_SIZE, self.storage.id_field());
+        let page = match &self.projection {
+            Some(projection) => {
+                self.storage
+                    .filter_projected(
+                        self.parent_id.clone(),
+                        query,
+                        projection.clone(),
+                        self.context.clone(),
+                    )
+                    .await?
+            }
+            None => self
+                .storage
+                .filter(self.parent_id.clone(), query, self.context.clone())
+                .await?
+                .try_map(serde_json::to_value)
+                .map_err(CqrsError::serialization_error)?,
+        };
+        let count = page.items.len() as i64;
+        let next = match page.next_cursor {
+            Some(cursor) => Position::After(cursor),
+            None if by_cursor || count < BATCH_SIZE => Position::Done,
+            None => Position::Offset(skip + count),
+        };
+        Ok((page.items, next))
+    }
+}
+
+/// The columns of a CSV export without `fields`: the fields `V` deserializes, or those
+/// of the first item when they cannot be derived.
+fn columns_of<V: DeserializeOwned>(items: &[JsonValue]) -> Vec<String> {
+    let derived = rest_sql::dsl::serde_fields::<V>();
+    if !derived.is_empty() {
+        return derived.iter().map(|field| field.to_string()).collect();
+    }
+    items
+        .first()
+        .and_then(JsonValue::as_object)
+        .map(|item| item.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+fn render(format: Format, columns: &[String], items: &[JsonValue]) -> String {
+    let mut out = String::new();
+    for item in items {
+        match format {
+            Format::Csv => out.push_str(&csv_line(
+                columns
+                    .iter()
+                    .map(|column| item.get(column).cloned().unwrap_or(JsonValue::Null)),
+            )),
+            _ => {
+                out.push_str(&item.to_string());
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+/// One CSV record (RFC 4180): a missing value or `null` is an empty cell, an object or
+/// an array its JSON text. A string a spreadsheet would run as a formula is prefixed
+/// with `'`; a number is written as it is, its sign included.
+fn csv_line(cells: impl Iterator<Item = JsonValue>) -> String {
+    let cells: Vec<String> = cells
+        .map(|cell| {
+            let text = match cell {
+                JsonValue::Null => String::new(),
+                JsonValue::String(text) if text.starts_with(['=', '+', '-', '@', '\t', '\r']) => {
+                    format!("'{text}")
+                }
+                JsonValue::String(text) => text,
+                other => other.to_string(),
+            };
+            if text.contains([',', '"', '\r', '\n']) {
+                format!("\"{}\"", text.replace('"', "\"\""))
+            } else {
+                text
+            }
+        })
+        .collect();
+    format!("{}\r\n", cells.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read::inmemory::InMemoryStorage;
+    use crate::read::storage::{HasId, Storage};
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    struct Item {
+        id: String,
+        n: i64,
+    }
+
+    impl HasId for Item {
+        fn field_id() -> &'static str {
+            "id"
+        }
+        fn id(&self) -> &str {
+            &self.id
+        }
+        fn parent_field_id() -> Option<&'static str> {
+            None
+        }
+        fn parent_id(&self) -> Option<&str> {
+            None
+        }
+    }
+
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    struct ItemQuery {
+        n: Option<i64>,
+    }
+
+    impl Query for ItemQuery {
+        fn sortable_fields(&self) -> Vec<&str> {
+            vec!["id", "n"]
+        }
+    }
+
+    /// Exports `count` items, more than two batches' worth when asked.
+    async fn export(count: i64, query_string: &str, format: Format) -> String {
+        let storage = InMemoryStorage::<Item, CqrsHttpQuery<ItemQuery>>::new("item");
+        for n in 0..count {
+            let item = Item {
+                id: format!("i{n:04}"),
+                n,
+            };
+            storage.save(item, CqrsContext::default()).await.unwrap();
+        }
+        let query = CqrsHttpQuery::<ItemQuery>::from_query_string(query_string).unwrap();
+        let export = Export {
+            storage: Arc::new(storage),
+            parent_id: None,
+            projection: query.projection::<Item>().unwrap(),
+            query,
+            context: CqrsContext::default(),
+            format,
+        };
+        let response = export.respond("items").await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn every_matching_item_is_exported_across_batches() {
+        let total = 2 * BATCH_SIZE + 1;
+        // Keyset batches under a sort, and under the id without one.
+        for query_string in ["sort=-id", ""] {
+            let csv = export(total, query_string, Format::Csv).await;
+            let lines: Vec<&str> = csv.lines().collect();
+            assert_eq!(lines.len() as i64, total + 1, "{query_string:?}");
+            assert_eq!(lines[0], "id,n");
+            let ids: HashSet<&str> = lines[1..].iter().map(|l| &l[..5]).collect();
+            assert_eq!(ids.len() as i64, total, "no item twice: {query_string:?}");
+        }
+        let csv = export(total, "", Format::Csv).await;
+        assert!(csv.lines().skip(1).is_sorted(), "by id without a sort");
+        let csv = export(3, "sort=-id", Format::Csv).await;
+        assert_eq!(csv, "id,n\r\ni0002,2\r\ni0001,1\r\ni0000,0\r\n");
+
+        let ndjson = export(2, "fields=n&_q=n>0", Format::Ndjson).await;
+        assert_eq!(ndjson, "{\"n\":1}\n");
+    }
+
+    fn accepting(accept: &str) -> Format {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_str(accept).unwrap());
+        Format::negotiate(&headers)
+    }
+
+    #[test]
+    fn the_accepted_media_type_with_the_highest_q_wins() {
+        assert_eq!(Format::negotiate(&HeaderMap::new()), Format::Json);
+        assert_eq!(accepting("text/csv"), Format::Csv);
+        assert_eq!(accepting("application/x-ndjson, */*;q=0.1"), Format::Ndjson);
+        assert_eq!(accepting("application/json, text/csv"), Format::Json);
+        assert_eq!(accepting("application/json;q=0.5, text/csv"), Format::Csv);
+        assert_eq!(accepting("text/csv;q=0, text/html"), Format::Json);
+    }
+
+    #[test]
+    fn csv_cells_a_spreadsheet_would_run_are_escaped() {
+        let line = csv_line(
+            [
+                json!("=HYPERLINK(\"http://x\")"),
+                json!("+1"),
+                json!("-2"),
+                json!("@SUM(A1)"),
+                json!(-3),
+                json!("a=b"),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(
+            line,
+            "\"'=HYPERLINK(\"\"http://x\"\")\",'+1,'-2,'@SUM(A1),-3,a=b\r\n"
+        );
+    }
+
+    #[test]
+    fn csv_cells_are_quoted_only_when_they_must_be() {
+        let line = csv_line(
+            [
+                json!("plain"),
+                json!("a, b"),
+                json!("say \"hi\""),
+                json!(null),
+                json!(4.5),
+                json!({ "publisher": "Asmodee" }),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(
+            line,
+            "plain,\"a, b\",\"say \"\"hi\"\"\",,4.5,\"{\"\"publisher\"\":\"\"Asmodee\"\"}\"\r\n"
+        );
+
+        let columns = ["id".to_string(), "missing".to_string()];
+        assert_eq!(
+            render(
+                Format::Csv,
+                &columns,
+                &[json!({ "id": "g1", "title": "Go" })]
+            ),
+            "g1,\r\n"
+        );
+        assert_eq!(
+            render(
+                Format::Ndjson,
+                &columns,
+                &[json!({ "id": "g1" }), json!({ "id": "g2" })]
+            ),
+            "{\"id\":\"g1\"}\n{\"id\":\"g2\"}\n"
+        );
+    }
+}
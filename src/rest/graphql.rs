@@ -0,0 +1,1067 @@
+use crate::engine::CqrsCommandEngine;
+use crate::read::storage::DynStorage;
+use crate::read::Query;
+use crate::rest::codex::CqrsHttpQuery;
+use crate::rest::helpers;
+use crate::rest::helpers::SchemaData;
+use crate::rest::security;
+use crate::{Aggregate, CommandHandler, CqrsContext, CqrsError, ReadPolicies, View, USER_ID_METADATA};
+use async_graphql::dynamic::{
+    Field, FieldFuture, FieldValue, InputValue, Object, ResolverContext, Scalar, Schema,
+    SchemaError, TypeRef,
+};
+use async_graphql::{ErrorExtensions, Value as GraphQLValue};
+use axum::extract::State;
+use axum::response::IntoResponse;
+use axum::routing::post;
+use axum::{Extension, Json};
+use http::StatusCode;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+use utoipa::openapi::path::ParameterIn;
+use utoipa::openapi::{HttpMethod, ObjectBuilder, RefOr, Schema as OpenApiSchema, Type};
+use utoipa::{IntoParams, PartialSchema, ToSchema};
+use utoipa_axum::router::{OpenApiRouter, UtoipaMethodRouter};
+
+/// Name of the scalar carrying any JSON value: nested objects, `$ref`s and unions that
+/// have no GraphQL type of their own.
+pub const JSON_SCALAR: &str = "JSON";
+
+/// Name of the object every mutation answers.
+pub const COMMAND_RESULT: &str = "CommandResult";
+
+/// The codex params of a list query, as GraphQL arguments, and the query string param
+/// each one becomes. A typed `Q` param with one of these names is not offered.
+const LIST_ARGS: [(&str, &str, &str); 9] = [
+    ("filter", "_q", TypeRef::STRING),
+    ("sort", "sort", TypeRef::STRING),
+    ("skip", "skip", TypeRef::INT),
+    ("limit", "limit", TypeRef::INT),
+    ("page", "page", TypeRef::INT),
+    ("page_size", "page_size", TypeRef::INT),
+    ("after", "after", TypeRef::STRING),
+    ("before", "before", TypeRef::STRING),
+    ("search", "_search", TypeRef::STRING),
+];
+
+/// Builds a GraphQL schema from registered engines and view storages, to be served by
+/// [`CQRSGraphQLRouter`]:
+///
+/// - **Mutations** — one per variant of each `CreateCommand` and `UpdateCommand`,
+///   split as `CQRSWriteRouter` splits them: a variant tagged with a single-value
+///   property (`#[serde(tag = "type")]`) is the mutation `{Aggregate::TYPE}_{variant}`
+///   in snake case, taking the variant's fields as arguments and setting the tag
+///   itself. Any other command type is a single `{TYPE}_create` / `{TYPE}_update`
+///   mutation, taking the fields of a struct command as arguments and anything else as
+///   `command: JSON!`. Update mutations take the aggregate id as `{TYPE}_id`. Each
+///   answers a `CommandResult { id }`.
+/// - **Queries** — for each view, `{View::TYPE}(id)` and `{View::TYPE}_list`, the
+///   latter answering a `{View}Page` with the fields of `Paged`. List arguments are the
+///   codex params under GraphQL names — `filter` (RSQL, the `_q` param), `sort`,
+///   `skip`, `limit`, `page`, `page_size`, `after`, `before`, `search` — and the scalar
+///   params of `Q`. They are checked exactly as [`CqrsHttpQuery`] checks a query
+///   string. A view that is a child of its aggregate takes the parent id as
+///   `{Aggregate::TYPE}_id`, as the codex router's path does.
+///
+/// Scalar fields of the OpenAPI schemas map to `String`, `Int`, `Float` and `Boolean`
+/// (and lists of them); every other field is the `JSON` scalar.
+///
+/// A [`CqrsError`] becomes a GraphQL error whose message is the error's and whose
+/// extensions carry its body — `code`, `internalCode`, `requestId`... — and its HTTP
+/// `status`.
+///
+/// ```ignore
+/// let schema = CQRSGraphQLSchema::new()
+///     .engine(engine)
+///     .views::<Game, GameView, GameQuery>(view_storage)
+///     .finish()?;
+/// let app = OpenApiRouter::new().merge(CQRSGraphQLRouter::routes(schema));
+/// ```
+#[derive(Default)]
+pub struct CQRSGraphQLSchema {
+    queries: Vec<Field>,
+    mutations: Vec<Field>,
+    types: Vec<Object>,
+}
+
+impl CQRSGraphQLSchema {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Publishes every command of `A` as a mutation run by `engine`.
+    #[must_use]
+    pub fn engine<A>(mut self, engine: Arc<CqrsCommandEngine<A>>) -> Self
+    where
+        A: Aggregate + CommandHandler + 'static,
+        A::Error: Into<CqrsError>,
+    {
+        let create = helpers::read_schema(&A::CreateCommand::name(), A::CreateCommand::schema());
+        let update = helpers::read_schema(&A::UpdateCommand::name(), A::UpdateCommand::schema());
+        let mutations = command_mutations(A::TYPE, CommandKind::Create, create)
+            .into_iter()
+            .chain(command_mutations(A::TYPE, CommandKind::Update, update));
+        for mutation in mutations {
+            self.mutations.push(mutation.field(engine.clone()));
+        }
+        self
+    }
+
+    /// Publishes the views of `storage` as the queries `{V::TYPE}` and `{V::TYPE}_list`.
+    #[must_use]
+    pub fn views<A, V, Q>(self, storage: DynStorage<V, CqrsHttpQuery<Q>>) -> Self
+    where
+        A: Aggregate + 'static,
+        V: View<A> + ToSchema + 'static,
+        Q: Clone + Debug + DeserializeOwned + Send + Sync + IntoParams + Query + 'static,
+    {
+        self.views_with_policies::<A, V, Q>(storage, ReadPolicies::default())
+    }
+
+    /// [`views`](Self::views), the query by id behind the `find_one` policy and the
+    /// list behind the `find_many` one.
+    #[must_use]
+    pub fn views_with_policies<A, V, Q>(
+        mut self,
+        storage: DynStorage<V, CqrsHttpQuery<Q>>,
+        policies: ReadPolicies,
+    ) -> Self
+    where
+        A: Aggregate + 'static,
+        V: View<A> + ToSchema + 'static,
+        Q: Clone + Debug + DeserializeOwned + Send + Sync + IntoParams + Query + 'static,
+    {
+        let policies = Arc::new(policies);
+        let view_type = graphql_name(&V::name());
+        let page_type = format!("{view_type}Page");
+        let parent_arg = V::IS_CHILD_OF_AGGREGATE.then(|| graphql_name(&format!("{}_id", A::TYPE)));
+
+        self.types.push(view_object(&view_type, &V::schema()));
+        self.types.push(page_object(&page_type, &view_type));
+
+        let mut by_id = Field::new(graphql_name(V::TYPE), TypeRef::named(&view_type), {
+            let storage = storage.clone();
+            let parent_arg = parent_arg.clone();
+            let policies = policies.clone();
+            move |ctx| {
+                let storage = storage.clone();
+                let parent_arg = parent_arg.clone();
+                let policies = policies.clone();
+                FieldFuture::new(async move {
+                    let context = cqrs_context(&ctx);
+                    security::authorize_read(policies.find_one(), &context)
+                        .map_err(|e| graphql_error(e, &context))?;
+                    let id = ctx.args.try_get("id")?.string()?.to_string();
+                    let parent_id = optional_string(&ctx, parent_arg.as_deref())?;
+                    let view = storage
+                        .find_by_id(parent_id, &id, context.clone())
+                        .await
+                        .map_err(|e| graphql_error(e, &context))?;
+                    match view {
+                        Some(view) => Ok(Some(FieldValue::owned_any(to_json(&view, &context)?))),
+                        None => Ok(None),
+                    }
+                })
+            }
+        })
+        .argument(InputValue::new("id", TypeRef::named_nn(TypeRef::ID)));
+
+        let mut list = Field::new(
+            graphql_name(&format!("{}_list", V::TYPE)),
+            TypeRef::named_nn(&page_type),
+            {
+                let storage = storage.clone();
+                let parent_arg = parent_arg.clone();
+                let typed = typed_params::<Q>();
+                move |ctx| {
+                    let storage = storage.clone();
+                    let parent_arg = parent_arg.clone();
+                    let typed = typed.clone();
+                    let policies = policies.clone();
+                    FieldFuture::new(async move {
+                        let context = cqrs_context(&ctx);
+                        security::authorize_read(policies.find_many(), &context)
+                            .map_err(|e| graphql_error(e, &context))?;
+                        let parent_id = optional_string(&ctx, parent_arg.as_deref())?;
+                        let query =
+                            CqrsHttpQuery::<Q>::from_query_string(&query_string(&ctx, &typed))
+                                .map_err(|e| graphql_error(e.into(), &context))?;
+                        let page = storage
+                            .filter(parent_id, query, context.clone())
+                            .await
+                            .map_err(|e| graphql_error(e, &context))?;
+                        Ok(Some(FieldValue::owned_any(to_json(&page, &context)?)))
+                    })
+                }
+            },
+        );
+        for (name, _, ty) in LIST_ARGS {
+            list = list.argument(InputValue::new(name, TypeRef::named(ty)));
+        }
+        for (name, ty) in typed_params::<Q>() {
+            list = list.argument(InputValue::new(name, ty));
+        }
+
+        if let Some(parent_arg) = parent_arg {
+            by_id = by_id.argument(InputValue::new(&parent_arg, TypeRef::named_nn(TypeRef::ID)));
+            list = list.argument(InputValue::new(&parent_arg, TypeRef::named_nn(TypeRef::ID)));
+        }
+        self.queries.push(by_id);
+        self.queries.push(list);
+        self
+    }
+
+    /// The executable schema.
+    ///
+    /// # Errors
+    ///
+    /// When no view is registered — GraphQL requires at least one query — or when two
+    /// registrations produce the same type name.
+    pub fn finish(self) -> Result<Schema, SchemaError> {
+        let query = self
+            .queries
+            .into_iter()
+            .fold(Object::new("Query"), Object::field);
+        let has_mutations = !self.mutations.is_empty();
+        let mutation = self
+            .mutations
+            .into_iter()
+            .fold(Object::new("Mutation"), Object::field);
+
+        let mut builder = Schema::build("Query", has_mutations.then_some("Mutation"), None)
+            .register(query)
+            .register(Scalar::new(JSON_SCALAR))
+            .register(Object::new(COMMAND_RESULT).field(json_field(
+                "id",
+                "id",
+                TypeRef::named_nn(TypeRef::ID),
+            )));
+        if has_mutations {
+            builder = builder.register(mutation);
+        }
+        for ty in self.types {
+            builder = builder.register(ty);
+        }
+        builder.finish()
+    }
+}
+
+/// Serves a schema built by [`CQRSGraphQLSchema`] as `POST /graphql`.
+///
+/// The body is a GraphQL request (`query`, `variables`, `operationName`), and the answer
+/// is always `200` with the GraphQL response: errors are in its `errors`. Resolvers see
+/// the request's `CqrsContext`.
+#[derive(Clone)]
+pub struct CQRSGraphQLRouter {
+    schema: Schema,
+}
+
+impl CQRSGraphQLRouter {
+    pub fn routes(schema: Schema) -> OpenApiRouter {
+        let paths = helpers::generate_route(
+            "graphql",
+            HttpMethod::Post,
+            "/graphql",
+            Self::object_schema("A GraphQL response: `data` and `errors`"),
+            vec![],
+            vec![],
+            Some(Self::object_schema(
+                "A GraphQL request: `query`, `variables` and `operationName`",
+            )),
+            &[StatusCode::BAD_REQUEST],
+        );
+
+        let handler = post(
+            move |State(router): State<CQRSGraphQLRouter>,
+                  Extension(context): Extension<CqrsContext>,
+                  Json(request): Json<async_graphql::Request>| async move {
+                Json(router.schema.execute(request.data(context)).await).into_response()
+            },
+        );
+
+        OpenApiRouter::<CQRSGraphQLRouter>::new()
+            .routes(UtoipaMethodRouter::<CQRSGraphQLRouter>::from((
+                vec![helpers::error_schema()],
+                paths,
+                handler,
+            )))
+            .with_state(CQRSGraphQLRouter { schema })
+    }
+
+    fn object_schema(description: &str) -> RefOr<OpenApiSchema> {
+        RefOr::T(OpenApiSchema::Object(
+            ObjectBuilder::new()
+                .schema_type(Type::Object)
+                .description(Some(description))
+                .build(),
+        ))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Mutations
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CommandKind {
+    Create,
+    Update,
+}
+
+/// A mutation before it is bound to an engine.
+#[derive(Debug, Clone, PartialEq)]
+struct CommandMutation {
+    name: String,
+    kind: CommandKind,
+    /// The aggregate id argument of an update.
+    id_arg: Option<String>,
+    /// `(argument, property, type)` for each field of the command, or `None` when the
+    /// whole command is the `command` argument.
+    fields: Option<Vec<(String, String, TypeRef)>>,
+    discriminator: Option<(String, String)>,
+}
+
+impl CommandMutation {
+    fn field<A>(self, engine: Arc<CqrsCommandEngine<A>>) -> Field
+    where
+        A: Aggregate + CommandHandler + 'static,
+        A::Error: Into<CqrsError>,
+    {
+        let mut arguments = match &self.fields {
+            Some(fields) => fields
+                .iter()
+                .map(|(arg, _, ty)| InputValue::new(arg, ty.clone()))
+                .collect(),
+            None => vec![InputValue::new("command", TypeRef::named_nn(JSON_SCALAR))],
+        };
+        if let Some(id_arg) = &self.id_arg {
+            arguments.push(InputValue::new(id_arg, TypeRef::named_nn(TypeRef::ID)));
+        }
+
+        let name = self.name.clone();
+        let mutation = Arc::new(self);
+        let field = Field::new(name, TypeRef::named_nn(COMMAND_RESULT), move |ctx| {
+            let engine = engine.clone();
+            let mutation = mutation.clone();
+            FieldFuture::new(async move {
+                let context = cqrs_context(&ctx);
+                let command = mutation.command(&ctx)?;
+                let id = match &mutation.id_arg {
+                    Some(id_arg) => Some(ctx.args.try_get(id_arg)?.string()?.to_string()),
+                    None => None,
+                };
+                let id = run(&engine, mutation.kind, id, command, &context)
+                    .await
+                    .map_err(|e| graphql_error(e, &context))?;
+                Ok(Some(FieldValue::owned_any(json!({ "id": id }))))
+            })
+        });
+        arguments.into_iter().fold(field, Field::argument)
+    }
+
+    /// The command body: the arguments under their property names, and the tag.
+    fn command(&self, ctx: &ResolverContext<'_>) -> async_graphql::Result<JsonValue> {
+        let mut command = match &self.fields {
+            Some(fields) => {
+                let mut body = serde_json::Map::new();
+                for (arg, property, _) in fields {
+                    if let Some(value) = ctx.args.get(arg) {
+                        body.insert(property.clone(), value.as_value().clone().into_json()?);
+                    }
+                }
+                JsonValue::Object(body)
+            }
+            None => ctx
+                .args
+                .try_get("command")?
+                .as_value()
+                .clone()
+                .into_json()?,
+        };
+        helpers::add_discriminator(&mut command, self.discriminator.clone());
+        Ok(command)
+    }
+}
+
+/// The mutations of one command type: one per tagged variant, or a single one.
+fn command_mutations(
+    aggregate_type: &str,
+    kind: CommandKind,
+    items: Vec<SchemaData>,
+) -> Vec<CommandMutation> {
+    let id_arg =
+        (kind == CommandKind::Update).then(|| graphql_name(&format!("{aggregate_type}_id")));
+    let suffix = match kind {
+        CommandKind::Create => "create",
+        CommandKind::Update => "update",
+    };
+
+    let tagged = !items.is_empty() && items.iter().all(|item| item.discriminator.is_some());
+    if tagged {
+        return items
+            .into_iter()
+            .map(|item| CommandMutation {
+                name: graphql_name(&format!("{aggregate_type}_{}", snake_case(&item.name))),
+                kind,
+                id_arg: id_arg.clone(),
+                fields: object_fields(&item.schema),
+                discriminator: item.discriminator,
+            })
+            .collect();
+    }
+
+    // Several untagged variants have no name to tell them apart, so they share one
+    // mutation taking the whole command, as a single non-struct command does.
+    let fields = match &items[..] {
+        [item] => object_fields(&item.schema),
+        _ => None,
+    };
+    vec![CommandMutation {
+        name: graphql_name(&format!("{aggregate_type}_{suffix}")),
+        kind,
+        id_arg,
+        fields,
+        discriminator: None,
+    }]
+}
+
+/// `(argument, property, type)` for each property of an object schema, required ones
+/// non-null; `None` for a schema with no properties.
+fn object_fields(schema: &utoipa::openapi::Schema) -> Option<Vec<(String, String, TypeRef)>> {
+    let schema = serde_json::to_value(schema).ok()?;
+    let properties = schema.get("properties")?.as_object()?;
+    if properties.is_empty() {
+        return None;
+    }
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(JsonValue::as_array)
+        .map(|r| r.iter().filter_map(JsonValue::as_str).collect())
+        .unwrap_or_default();
+    Some(
+        properties
+            .iter()
+            .map(|(property, schema)| {
+                let ty = property_type(schema, required.contains(&property.as_str()));
+                (graphql_name(property), property.clone(), ty)
+            })
+            .collect(),
+    )
+}
+
+async fn run<A>(
+    engine: &CqrsCommandEngine<A>,
+    kind: CommandKind,
+    id: Option<String>,
+    command: JsonValue,
+    context: &CqrsContext,
+) -> Result<String, CqrsError>
+where
+    A: Aggregate + CommandHandler + 'static,
+    A::Error: Into<CqrsError>,
+{
+    let metadata = HashMap::from_iter(vec![
+        (USER_ID_METADATA.to_string(), context.current_user()),
+        ("request_id".to_string(), context.request_id()),
+    ]);
+    // A command that does not match the schema is a client error, as on the write router.
+    match (kind, id) {
+        (CommandKind::Update, Some(id)) => {
+            let command = serde_json::from_value::<A::UpdateCommand>(command)
+                .map_err(|e| CqrsError::unprocessable(e.to_string()))?;
+            engine
+                .execute_update_variant(&id, command, metadata, context)
+                .await?;
+            Ok(id)
+        }
+        _ => {
+            let command = serde_json::from_value::<A::CreateCommand>(command)
+                .map_err(|e| CqrsError::unprocessable(e.to_string()))?;
+            engine
+                .execute_create_variant(command, metadata, context)
+                .await
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Queries
+// ---------------------------------------------------------------------------
+
+/// The object type of a view: one field per property of its schema.
+fn view_object(name: &str, schema: &RefOr<utoipa::openapi::Schema>) -> Object {
+    let schema = serde_json::to_value(schema).unwrap_or_default();
+    let properties = schema.get("properties").and_then(JsonValue::as_object);
+    match properties {
+        Some(properties) if !properties.is_empty() => {
+            properties
+                .iter()
+                .fold(Object::new(name), |object, (property, schema)| {
+                    object.field(json_field(
+                        &graphql_name(property),
+                        property,
+                        property_type(schema, false),
+                    ))
+                })
+        }
+        // A view whose schema has no properties still needs a field to be an object.
+        _ => Object::new(name).field(Field::new("value", TypeRef::named(JSON_SCALAR), |ctx| {
+            FieldFuture::new(async move {
+                let parent = ctx.parent_value.try_downcast_ref::<JsonValue>()?;
+                Ok(Some(FieldValue::value(GraphQLValue::from_json(
+                    parent.clone(),
+                )?)))
+            })
+        })),
+    }
+}
+
+/// The page type of a view, with the fields of `Paged`.
+fn page_object(name: &str, view_type: &str) -> Object {
+    let items = Field::new("items", TypeRef::named_nn_list_nn(view_type), |ctx| {
+        FieldFuture::new(async move {
+            let parent = ctx.parent_value.try_downcast_ref::<JsonValue>()?;
+            let items = parent
+                .get("items")
+                .and_then(JsonValue::as_array)
+                .cloned()
+                .unwrap_or_default();
+            Ok(Some(FieldValue::list(
+                items.into_iter().map(FieldValue::owned_any),
+            )))
+        })
+    });
+    Object::new(name)
+        .field(items)
+        .field(json_field(
+            "total",
+            "total",
+            TypeRef::named_nn(TypeRef::INT),
+        ))
+        .field(json_field("skip", "skip", TypeRef::named_nn(TypeRef::INT)))
+        .field(json_field(
+            "limit",
+            "limit",
+            TypeRef::named_nn(TypeRef::INT),
+        ))
+        .field(json_field("page", "page", TypeRef::named_nn(TypeRef::INT)))
+        .field(json_field(
+            "page_size",
+            "pageSize",
+            TypeRef::named_nn(TypeRef::INT),
+        ))
+        .field(json_field(
+            "next_cursor",
+            "nextCursor",
+            TypeRef::named(TypeRef::STRING),
+        ))
+        .field(json_field(
+            "prev_cursor",
+            "prevCursor",
+            TypeRef::named(TypeRef::STRING),
+        ))
+}
+
+/// The scalar params of `Q`, as arguments. Others have no query string form the
+/// extractor reads, and a param named as a codex argument would be shadowed by it.
+fn typed_params<Q: IntoParams>() -> Vec<(String, TypeRef)> {
+    Q::into_params(|| Some(ParameterIn::Query))
+        .into_iter()
+        .filter(|param| LIST_ARGS.iter().all(|(name, _, _)| *name != param.name))
+        .filter_map(|param| {
+            let schema = serde_json::to_value(param.schema?).ok()?;
+            let ty = property_type(&schema, false);
+            [
+                TypeRef::STRING,
+                TypeRef::INT,
+                TypeRef::FLOAT,
+                TypeRef::BOOLEAN,
+            ]
+            .contains(&ty.to_string().as_str())
+            .then_some((param.name, ty))
+        })
+        .filter(|(name, _)| *name == graphql_name(name))
+        .collect()
+}
+
+/// The list arguments as the query string the codex extractor reads.
+fn query_string(ctx: &ResolverContext<'_>, typed: &[(String, TypeRef)]) -> String {
+    let codex = LIST_ARGS.iter().map(|(arg, param, _)| (*arg, *param));
+    let typed = typed.iter().map(|(name, _)| (name.as_str(), name.as_str()));
+    codex
+        .chain(typed)
+        .filter_map(|(arg, param)| {
+            let value = match ctx.args.get(arg)?.as_value() {
+                GraphQLValue::Null => return None,
+                GraphQLValue::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            Some(format!(
+                "{param}={}",
+                utf8_percent_encode(&value, NON_ALPHANUMERIC)
+            ))
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+// ---------------------------------------------------------------------------
+// Shared
+// ---------------------------------------------------------------------------
+
+/// The context the router attached to the request, or an anonymous one for a schema
+/// executed directly.
+fn cqrs_context(ctx: &ResolverContext<'_>) -> CqrsContext {
+    ctx.data_opt::<CqrsContext>()
+        .cloned()
+        .unwrap_or_else(|| CqrsContext::default().with_next_request_id())
+}
+
+fn optional_string(
+    ctx: &ResolverContext<'_>,
+    arg: Option<&str>,
+) -> async_graphql::Result<Option<String>> {
+    match arg {
+        Some(arg) => Ok(Some(ctx.args.try_get(arg)?.string()?.to_string())),
+        None => Ok(None),
+    }
+}
+
+fn to_json<T: serde::Serialize>(
+    value: &T,
+    context: &CqrsContext,
+) -> async_graphql::Result<JsonValue> {
+    serde_json::to_value(value)
+        .map_err(|e| graphql_error(CqrsError::serialization_error(e), context))
+}
+
+/// A field read from the parent JSON value under `property`.
+fn json_field(name: &str, property: &str, ty: TypeRef) -> Field {
+    let property = property.to_string();
+    Field::new(name, ty, move |ctx| {
+        let property = property.clone();
+        FieldFuture::new(async move {
+            let parent = ctx.parent_value.try_downcast_ref::<JsonValue>()?;
+            match parent.get(&property) {
+                None | Some(JsonValue::Null) => Ok(None),
+                Some(value) => Ok(Some(FieldValue::value(GraphQLValue::from_json(
+                    value.clone(),
+                )?))),
+            }
+        })
+    })
+}
+
+/// The GraphQL type of a property schema: a scalar, a list of scalars, or `JSON`.
+fn property_type(schema: &JsonValue, required: bool) -> TypeRef {
+    let scalar = |schema: &JsonValue| {
+        let types: Vec<&str> = match schema.get("type") {
+            Some(JsonValue::String(t)) => vec![t.as_str()],
+            Some(JsonValue::Array(types)) => types
+                .iter()
+                .filter_map(JsonValue::as_str)
+                .filter(|t| *t != "null")
+                .collect(),
+            _ => vec![],
+        };
+        match types[..] {
+            ["string"] => Some(TypeRef::STRING),
+            ["integer"] => Some(TypeRef::INT),
+            ["number"] => Some(TypeRef::FLOAT),
+            ["boolean"] => Some(TypeRef::BOOLEAN),
+            ["array"] => Some("array"),
+            _ => None,
+        }
+    };
+    match (scalar(schema), required) {
+        (Some("array"), _) => {
+            match schema
+                .get("items")
+                .and_then(scalar)
+                .filter(|t| *t != "array")
+            {
+                Some(item) if required => TypeRef::named_nn_list_nn(item),
+                Some(item) => TypeRef::named_nn_list(item),
+                None if required => TypeRef::named_nn(JSON_SCALAR),
+                None => TypeRef::named(JSON_SCALAR),
+            }
+        }
+        (Some(ty), true) => TypeRef::named_nn(ty),
+        (Some(ty), false) => TypeRef::named(ty),
+        (None, true) => TypeRef::named_nn(JSON_SCALAR),
+        (None, false) => TypeRef::named(JSON_SCALAR),
+    }
+}
+
+/// The error body as extensions, next to the HTTP status the REST routes would answer.
+fn graphql_error(error: CqrsError, context: &CqrsContext) -> async_graphql::Error {
+    let error = error.with_request_id_if_absent(context.request_id());
+    let status = error.http_status().as_u16();
+    let body = serde_json::to_value(&error).unwrap_or_default();
+    async_graphql::Error::new(error.message.clone()).extend_with(move |_, extensions| {
+        if let Some(body) = body.as_object() {
+            for (key, value) in body {
+                if let Ok(value) = GraphQLValue::from_json(value.clone()) {
+                    extensions.set(key, value);
+                }
+            }
+        }
+        extensions.set("status", status);
+    })
+}
+
+/// A name GraphQL accepts: letters, digits and `_`, not starting with a digit.
+fn graphql_name(name: &str) -> String {
+    let mut result: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !result.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        result.insert(0, '_');
+    }
+    result
+}
+
+fn snake_case(name: &str) -> String {
+    let mut result = String::new();
+    let mut prev: Option<char> = None;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            if prev.is_some_and(|p| p.is_ascii_lowercase() || p.is_ascii_digit()) {
+                result.push('_');
+            }
+            result.push(c.to_ascii_lowercase());
+        } else if c.is_ascii_alphanumeric() {
+            result.push(c);
+        } else if !result.ends_with('_') {
+            result.push('_');
+        }
+        prev = Some(c);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::es::inmemory::InMemoryPersist;
+    use crate::es::EventStoreImpl;
+    use crate::read::inmemory::InMemoryStorage;
+    use crate::read::storage::{HasId, Storage};
+    use crate::testing::TestAggregate;
+    use crate::{DynEventStore, EventEnvelope, EventFilter, Policy};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+    #[serde(tag = "type")]
+    #[allow(dead_code)]
+    enum TodoCommands {
+        AddTodo { title: String, tags: Vec<String> },
+        Clear,
+    }
+
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+    struct Game {
+        id: String,
+        title: String,
+        players: Option<i64>,
+    }
+
+    impl View<TestAggregate> for Game {
+        const TYPE: &'static str = "game";
+        const IS_CHILD_OF_AGGREGATE: bool = false;
+
+        fn view_id(event: &EventEnvelope<TestAggregate>) -> String {
+            event.aggregate_id.clone()
+        }
+
+        fn update(&self, _event: &EventEnvelope<TestAggregate>) -> Option<Self> {
+            None
+        }
+    }
+
+    impl HasId for Game {
+        fn field_id() -> &'static str {
+            "id"
+        }
+        fn id(&self) -> &str {
+            &self.id
+        }
+        fn parent_field_id() -> Option<&'static str> {
+            None
+        }
+        fn parent_id(&self) -> Option<&str> {
+            None
+        }
+    }
+
+    #[derive(Debug, Clone, Default, Serialize, Deserialize, IntoParams)]
+    struct GameQuery {
+        title: Option<String>,
+    }
+
+    impl Query for GameQuery {
+        fn sortable_fields(&self) -> Vec<&str> {
+            vec!["title"]
+        }
+    }
+
+    async fn schema() -> (Schema, DynEventStore<TestAggregate>) {
+        schema_with_policies(ReadPolicies::default()).await
+    }
+
+    async fn schema_with_policies(
+        policies: ReadPolicies,
+    ) -> (Schema, DynEventStore<TestAggregate>) {
+        let store: DynEventStore<TestAggregate> = EventStoreImpl::new(InMemoryPersist::new());
+        let engine = Arc::new(CqrsCommandEngine::new(
+            store.clone(),
+            vec![],
+            (),
+            Box::new(|_| {}),
+        ));
+        let storage = Arc::new(InMemoryStorage::<Game, CqrsHttpQuery<GameQuery>>::new(
+            "game",
+        ));
+        for (id, title, players) in [("g1", "Catan", Some(4)), ("g2", "Azul", None)] {
+            let game = Game {
+                id: id.to_string(),
+                title: title.to_string(),
+                players,
+            };
+            storage.save(game, CqrsContext::default()).await.unwrap();
+        }
+        let schema = CQRSGraphQLSchema::new()
+            .engine(engine)
+            .views_with_policies::<TestAggregate, Game, GameQuery>(storage, policies)
+            .finish()
+            .unwrap();
+        (schema, store)
+    }
+
+    async fn execute(schema: &Schema, query: &str) -> async_graphql::Response {
+        let context =
+            CqrsContext::new(Some("alice".to_string())).with_request_id("r-1".to_string());
+        schema
+            .execute(async_graphql::Request::new(query).data(context))
+            .await
+    }
+
+    fn data(response: async_graphql::Response) -> JsonValue {
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        response.data.into_json().unwrap()
+    }
+
+    fn error(response: async_graphql::Response) -> JsonValue {
+        assert_eq!(response.errors.len(), 1, "{:?}", response.data);
+        serde_json::to_value(&response.errors[0]).unwrap()
+    }
+
+    #[test]
+    fn a_tagged_command_is_one_mutation_per_variant() {
+        let items = helpers::read_schema(&TodoCommands::name(), TodoCommands::schema());
+        let mutations = command_mutations("todolist", CommandKind::Update, items);
+
+        assert_eq!(
+            mutations,
+            vec![
+                CommandMutation {
+                    name: "todolist_add_todo".to_string(),
+                    kind: CommandKind::Update,
+                    id_arg: Some("todolist_id".to_string()),
+                    fields: Some(vec![
+                        (
+                            "tags".to_string(),
+                            "tags".to_string(),
+                            TypeRef::named_nn_list_nn(TypeRef::STRING),
+                        ),
+                        (
+                            "title".to_string(),
+                            "title".to_string(),
+                            TypeRef::named_nn(TypeRef::STRING),
+                        ),
+                    ]),
+                    discriminator: Some(("type".to_string(), "AddTodo".to_string())),
+                },
+                CommandMutation {
+                    name: "todolist_clear".to_string(),
+                    kind: CommandKind::Update,
+                    id_arg: Some("todolist_id".to_string()),
+                    fields: None,
+                    discriminator: Some(("type".to_string(), "Clear".to_string())),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn mutations_run_commands_through_the_engine() {
+        let (schema, store) = schema().await;
+
+        let created = data(
+            execute(
+                &schema,
+                r#"mutation { TEST_create(Initialize: { name: "counter" }) { id } }"#,
+            )
+            .await,
+        );
+        let id = created["TEST_create"]["id"].as_str().unwrap().to_string();
+
+        let updated = data(
+            execute(
+                &schema,
+                &format!(
+                    r#"mutation {{ TEST_update(TEST_id: "{id}", command: "Increment") {{ id }} }}"#
+                ),
+            )
+            .await,
+        );
+        assert_eq!(updated["TEST_update"]["id"], id.as_str());
+
+        let (events, total) = store
+            .load_events_paged(&EventFilter::aggregate(&id), 1, 10, &CqrsContext::default())
+            .await
+            .unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(events[0].metadata.get("user_id").unwrap(), "alice");
+        assert_eq!(events[0].metadata.get("request_id").unwrap(), "r-1");
+    }
+
+    #[tokio::test]
+    async fn a_command_error_carries_the_error_body_and_status() {
+        let (schema, _) = schema().await;
+
+        let response = execute(
+            &schema,
+            r#"mutation { TEST_update(TEST_id: "missing", command: "Explode") { id } }"#,
+        )
+        .await;
+        let error = error(response);
+
+        assert_eq!(error["extensions"]["code"], "GENERIC_UNPROCESSABLE_ENTITY");
+        assert_eq!(error["extensions"]["status"], 422);
+        assert_eq!(error["extensions"]["requestId"], "r-1");
+    }
+
+    #[tokio::test]
+    async fn a_command_of_no_readable_variant_is_refused_under_variant_policies() {
+        let store: DynEventStore<TestAggregate> = EventStoreImpl::new(InMemoryPersist::new());
+        let engine = CqrsCommandEngine::new(store, vec![], (), Box::new(|_| {})).with_policies(
+            crate::CommandPolicies::new()
+                .variant("Decrement", crate::Policy::any_role(["admin"]))
+                .otherwise(crate::Policy::authenticated()),
+        );
+        let storage = Arc::new(InMemoryStorage::<Game, CqrsHttpQuery<GameQuery>>::new(
+            "game",
+        ));
+        let schema = CQRSGraphQLSchema::new()
+            .engine(Arc::new(engine))
+            .views::<TestAggregate, Game, GameQuery>(storage)
+            .finish()
+            .unwrap();
+
+        // Externally tagged, `Decrement` has no tag to read: it does not slip through
+        // under `otherwise`.
+        let response = execute(
+            &schema,
+            r#"mutation { TEST_update(TEST_id: "a1", command: "Decrement") { id } }"#,
+        )
+        .await;
+        assert_eq!(error(response)["extensions"]["status"], 403);
+    }
+
+    #[tokio::test]
+    async fn list_queries_map_filter_sort_and_paging_onto_the_codex_query() {
+        let (schema, _) = schema().await;
+
+        let page = data(
+            execute(
+                &schema,
+                r#"{ game_list(sort: "title", limit: 1) {
+                    items { id title players } total limit page_size next_cursor
+                } }"#,
+            )
+            .await,
+        );
+        assert_eq!(page["game_list"]["total"], 2);
+        assert_eq!(page["game_list"]["limit"], 1);
+        assert_eq!(page["game_list"]["page_size"], 1);
+        assert_eq!(
+            page["game_list"]["items"],
+            json!([{ "id": "g2", "title": "Azul", "players": null }])
+        );
+
+        let filtered = data(
+            execute(
+                &schema,
+                r#"{ game_list(filter: "title==Catan") { items { title } } }"#,
+            )
+            .await,
+        );
+        assert_eq!(
+            filtered["game_list"]["items"],
+            json!([{ "title": "Catan" }])
+        );
+
+        let typed = data(execute(&schema, r#"{ game_list(title: "Azul") { total } }"#).await);
+        assert_eq!(typed["game_list"]["total"], 1);
+    }
+
+    #[tokio::test]
+    async fn list_arguments_are_checked_as_the_extractor_checks_them() {
+        let (schema, _) = schema().await;
+
+        let error = error(execute(&schema, r#"{ game_list(page: 2) { total } }"#).await);
+
+        assert_eq!(error["extensions"]["status"], 422);
+        assert!(
+            error["message"]
+                .as_str()
+                .unwrap()
+                .ends_with("page: requires page_size (or use skip/limit)")
+        );
+    }
+
+    #[tokio::test]
+    async fn a_view_is_found_by_id_and_a_missing_one_is_null() {
+        let (schema, _) = schema().await;
+
+        let found = data(
+            execute(
+                &schema,
+                r#"{ found: game(id: "g1") { title players } missing: game(id: "nope") { id } }"#,
+            )
+            .await,
+        );
+
+        assert_eq!(found["found"], json!({ "title": "Catan", "players": 4 }));
+        assert_eq!(found["missing"], JsonValue::Null);
+    }
+
+    #[tokio::test]
+    async fn view_queries_are_behind_the_read_policies() {
+        let (schema, _) = schema_with_policies(
+            ReadPolicies::new()
+                .with_find_one(Policy::any_role(["librarian"]))
+                .with_find_many(Policy::any_role(["librarian"])),
+        )
+        .await;
+
+        for query in [r#"{ game(id: "g1") { title } }"#, "{ game_list { total } }"] {
+            let error = error(execute(&schema, query).await);
+            assert_eq!(error["extensions"]["status"], 403, "{query}");
+        }
+
+        let librarian =
+            CqrsContext::new(Some("bob".to_string())).with_roles(vec!["librarian".to_string()]);
+        let response = schema
+            .execute(async_graphql::Request::new("{ game_list { total } }").data(librarian))
+            .await;
+        assert_eq!(data(response)["game_list"]["total"], 2);
+    }
+}
@@ -0,0 +1,319 @@
+use crate::read::storage::DynStorage;
+use crate::{Aggregate, CqrsError, DynEventStore, MaybeSend, MaybeSync};
+use axum::extract::State;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Json;
+use futures::future::{join_all, BoxFuture};
+use futures::FutureExt;
+use http::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use utoipa::openapi::{Content, HttpMethod, Ref, RefOr, ResponseBuilder};
+use utoipa::{PartialSchema, ToSchema};
+use utoipa_axum::router::{OpenApiRouter, UtoipaMethodRouter};
+
+use crate::rest::helpers;
+
+/// How long a component has to answer before it is reported down.
+pub const DEFAULT_HEALTH_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Up,
+    Down,
+}
+
+/// The outcome of one component's check.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentHealth {
+    pub status: HealthStatus,
+    /// How long the check took, timeouts included.
+    pub latency_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The readiness of the service: up when every component is.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub components: BTreeMap<String, ComponentHealth>,
+}
+
+type HealthCheck = Arc<dyn Fn() -> BoxFuture<'static, Result<(), CqrsError>> + Send + Sync>;
+
+/// Liveness and readiness probes:
+/// - `GET /health/live` — `200` as long as the process serves requests; it checks
+///   nothing, so that an orchestrator does not restart the service for a database outage.
+/// - `GET /health/ready` — every registered component checked concurrently, each within
+///   the timeout: a [`HealthReport`] with each component's latency, `200` when all of
+///   them are up and `503` otherwise.
+///
+/// ```rust,ignore
+/// let health = CQRSHealthRouter::new()
+///     .with_event_store("accounts", accounts_event_store.clone())
+///     .with_storage("accounts_view", accounts_storage.clone())
+///     .routes("health");
+/// ```
+///
+/// A check's error message is part of the report; keep the readiness route off public
+/// networks when it may name hosts.
+#[derive(Clone)]
+pub struct CQRSHealthRouter {
+    components: Vec<(String, HealthCheck)>,
+    timeout: Duration,
+}
+
+impl Default for CQRSHealthRouter {
+    fn default() -> Self {
+        Self {
+            components: Vec::new(),
+            timeout: DEFAULT_HEALTH_TIMEOUT,
+        }
+    }
+}
+
+impl CQRSHealthRouter {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks the storage behind `store`, as `name` in the report.
+    #[must_use]
+    pub fn with_event_store<A>(self, name: impl Into<String>, store: DynEventStore<A>) -> Self
+    where
+        A: Aggregate + 'static,
+    {
+        self.with_check(name, move || {
+            let store = store.clone();
+            async move { store.health().await }
+        })
+    }
+
+    /// Checks `storage`, as `name` in the report.
+    #[must_use]
+    pub fn with_storage<V, Q>(self, name: impl Into<String>, storage: DynStorage<V, Q>) -> Self
+    where
+        V: Debug + Clone + Default + Serialize + DeserializeOwned + MaybeSend + MaybeSync + 'static,
+        Q: Clone + Debug + MaybeSend + MaybeSync + 'static,
+    {
+        self.with_check(name, move || {
+            let storage = storage.clone();
+            async move { storage.health().await }
+        })
+    }
+
+    /// Any other dependency of the service — a broker, an HTTP API — as `name`.
+    #[must_use]
+    pub fn with_check<F, Fut>(mut self, name: impl Into<String>, check: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), CqrsError>> + Send + 'static,
+    {
+        self.components
+            .push((name.into(), Arc::new(move || check().boxed())));
+        self
+    }
+
+    /// How long each component has to answer; [`DEFAULT_HEALTH_TIMEOUT`] otherwise.
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Checks every component, concurrently.
+    pub async fn report(&self) -> HealthReport {
+        let checks = self.components.iter().map(|(name, check)| async move {
+            let started = Instant::now();
+            let outcome = tokio::time::timeout(self.timeout, check()).await;
+            let latency_ms = started.elapsed().as_millis() as u64;
+            let error = match outcome {
+                Ok(Ok(())) => None,
+                Ok(Err(e)) => Some(e.message.clone()),
+                Err(_) => Some(format!("no answer within {} ms", self.timeout.as_millis())),
+            };
+            let health = ComponentHealth {
+                status: if error.is_none() {
+                    HealthStatus::Up
+                } else {
+                    HealthStatus::Down
+                },
+                latency_ms,
+                error,
+            };
+            (name.clone(), health)
+        });
+        let components: BTreeMap<_, _> = join_all(checks).await.into_iter().collect();
+        let status = if components
+            .values()
+            .all(|component| component.status == HealthStatus::Up)
+        {
+            HealthStatus::Up
+        } else {
+            HealthStatus::Down
+        };
+        HealthReport { status, components }
+    }
+
+    fn live_route(
+        router: OpenApiRouter<CQRSHealthRouter>,
+        tag: &str,
+    ) -> OpenApiRouter<CQRSHealthRouter> {
+        let paths = helpers::generate_route(
+            tag,
+            HttpMethod::Get,
+            "/health/live",
+            HealthStatus::schema(),
+            vec![],
+            vec![],
+            None,
+            &[],
+        );
+        let handler = get(|| async { Self::live() });
+        router.routes(UtoipaMethodRouter::<CQRSHealthRouter>::from((
+            vec![],
+            paths,
+            handler,
+        )))
+    }
+
+    fn ready_route(
+        router: OpenApiRouter<CQRSHealthRouter>,
+        tag: &str,
+    ) -> OpenApiRouter<CQRSHealthRouter> {
+        let schemas = vec![
+            ("HealthReport".to_string(), HealthReport::schema()),
+            ("ComponentHealth".to_string(), ComponentHealth::schema()),
+            ("HealthStatus".to_string(), HealthStatus::schema()),
+        ];
+        let mut paths = helpers::generate_route(
+            tag,
+            HttpMethod::Get,
+            "/health/ready",
+            RefOr::Ref(Ref::from_schema_name("HealthReport")),
+            vec![],
+            vec![],
+            None,
+            &[],
+        );
+        // Down is still a report, not an error body.
+        for item in paths.paths.values_mut() {
+            if let Some(operation) = item.get.as_mut() {
+                operation.responses.responses.insert(
+                    StatusCode::SERVICE_UNAVAILABLE.as_u16().to_string(),
+                    RefOr::T(
+                        ResponseBuilder::new()
+                            .description("A component is down")
+                            .content(
+                                "application/json",
+                                Content::new(Some(Ref::from_schema_name("HealthReport"))),
+                            )
+                            .build(),
+                    ),
+                );
+            }
+        }
+        let handler =
+            get(|State(router): State<CQRSHealthRouter>| async move { router.ready().await });
+        router.routes(UtoipaMethodRouter::<CQRSHealthRouter>::from((
+            schemas, paths, handler,
+        )))
+    }
+
+    /// `GET /health/live`: up, whatever the components say.
+    pub fn live() -> Response {
+        Json(HealthStatus::Up).into_response()
+    }
+
+    /// `GET /health/ready`: the report, `503` when a component is down.
+    pub async fn ready(&self) -> Response {
+        let report = self.report().await;
+        let status = match report.status {
+            HealthStatus::Up => StatusCode::OK,
+            HealthStatus::Down => StatusCode::SERVICE_UNAVAILABLE,
+        };
+        (status, Json(report)).into_response()
+    }
+
+    pub fn routes(self, tag: &'static str) -> OpenApiRouter {
+        let mut result = OpenApiRouter::<CQRSHealthRouter>::new();
+        result = Self::live_route(result, tag);
+        result = Self::ready_route(result, tag);
+        result.with_state(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::es::inmemory::InMemoryPersist;
+    use crate::es::EventStoreImpl;
+    use crate::testing::TestAggregate;
+
+    #[tokio::test]
+    async fn the_service_is_ready_only_when_every_component_answers_in_time() {
+        let store: DynEventStore<TestAggregate> = EventStoreImpl::new(InMemoryPersist::new());
+        let router = CQRSHealthRouter::new()
+            .with_event_store("events", store)
+            .with_timeout(Duration::from_millis(50));
+
+        let report = router.report().await;
+        assert_eq!(report.status, HealthStatus::Up);
+        assert_eq!(report.components["events"].status, HealthStatus::Up);
+
+        let router = router
+            .with_check("broker", || async {
+                Err(CqrsError::database_error("connection refused"))
+            })
+            .with_check("slow", || async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Ok(())
+            });
+        let report = router.report().await;
+        assert_eq!(report.status, HealthStatus::Down);
+        assert_eq!(report.components["events"].status, HealthStatus::Up);
+        let broker = &report.components["broker"];
+        assert_eq!(broker.status, HealthStatus::Down);
+        assert!(
+            broker
+                .error
+                .as_deref()
+                .unwrap()
+                .contains("connection refused")
+        );
+        let slow = &report.components["slow"];
+        assert_eq!(slow.error.as_deref(), Some("no answer within 50 ms"));
+        assert!(slow.latency_ms >= 50);
+    }
+
+    #[tokio::test]
+    async fn a_failing_component_makes_the_service_unready_but_not_dead() {
+        let router = CQRSHealthRouter::new()
+            .with_check("events", || async { Ok(()) })
+            .with_check("broker", || async {
+                Err(CqrsError::database_error("connection refused"))
+            });
+
+        let ready = router.ready().await;
+        assert_eq!(ready.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let bytes = axum::body::to_bytes(ready.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let report: HealthReport = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(report.status, HealthStatus::Down);
+        assert_eq!(report.components["broker"].status, HealthStatus::Down);
+        assert_eq!(report.components["events"].status, HealthStatus::Up);
+
+        assert_eq!(CQRSHealthRouter::live().status(), StatusCode::OK);
+    }
+}
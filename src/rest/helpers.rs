@@ -45,6 +45,33 @@ pub fn generate_route(
     query_parameters: Vec<Parameter>,
     body: Option<RefOr<Schema>>,
     error_statuses: &[StatusCode],
+) -> Paths {
+    generate_route_with_media_type(
+        type_,
+        method,
+        path,
+        "application/json",
+        response,
+        path_parameters,
+        query_parameters,
+        body,
+        error_statuses,
+    )
+}
+
+/// [`generate_route`], for a success body that is not JSON — an SSE stream, an export.
+/// Error bodies keep their own media type.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_route_with_media_type(
+    type_: &str,
+    method: HttpMethod,
+    path: &str,
+    media_type: &str,
+    response: RefOr<Schema>,
+    path_parameters: Vec<(String, RefOr<Schema>)>,
+    query_parameters: Vec<Parameter>,
+    body: Option<RefOr<Schema>>,
+    error_statuses: &[StatusCode],
 ) -> Paths {
     let code = match &method {
         HttpMethod::Post => "201",
@@ -53,7 +80,7 @@ pub fn generate_route(
     let mut operation = OperationBuilder::new()
         .response(
             code,
-            ResponseBuilder::new().content("application/json", Content::new(Some(response))),
+            ResponseBuilder::new().content(media_type, Content::new(Some(response))),
         )
         .operation_id(Some(format!(
             "{}-{}-{}",
@@ -0,0 +1,658 @@
+use crate::{CqrsContext, CqrsError};
+use axum::response::{IntoResponse, Response};
+use futures::future::{ready, Either, Ready};
+use http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use http::{HeaderMap, HeaderValue, Request, StatusCode};
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Request header carrying the caller's request id, kept when present.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest request id taken from [`REQUEST_ID_HEADER`]; a longer one is replaced.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// A layer that builds the [`CqrsContext`] of each request from its bearer token, for
+/// the routers' `Extension<CqrsContext>` to find.
+///
+/// The token is a JWT checked against keys configured locally — an HS256 secret, an
+/// RS256 public key, or a JWKS document whose keys are picked by `kid` — with its
+/// expiry, and its issuer and audience when they are set. From its claims:
+/// - the current user is `sub` ([`with_user_claim`](Self::with_user_claim));
+/// - the roles are `roles` ([`with_roles_claim`](Self::with_roles_claim)), an array of
+///   strings or a space-separated string as in `scope`;
+/// - the tenant is `tenant_id` ([`with_tenant_claim`](Self::with_tenant_claim)).
+///
+/// A claim name with dots walks nested objects: `realm_access.roles`. The request id is
+/// the `x-request-id` header when the client sent one, a fresh UUID otherwise.
+///
+/// A missing, malformed, expired or badly signed token is refused with `401` and a
+/// `WWW-Authenticate: Bearer` challenge, the body being the `CqrsError` in the form the
+/// `problem-json` feature selects. [`allow_anonymous`](Self::allow_anonymous) lets
+/// requests without an `Authorization` header through as the anonymous user; a token
+/// that is sent is still checked.
+///
+/// ```ignore
+/// let auth = JwtAuth::jwks_file("/etc/app/jwks.json")?
+///     .with_issuer("https://id.example.com")
+///     .with_audience("ludotheque");
+/// let app = router.layer(auth);
+/// ```
+#[derive(Clone)]
+pub struct JwtAuth {
+    config: Arc<JwtConfig>,
+}
+
+#[derive(Clone)]
+struct JwtConfig {
+    keys: Vec<VerificationKey>,
+    issuer: Option<String>,
+    audience: Option<String>,
+    leeway: u64,
+    user_claim: String,
+    roles_claim: String,
+    tenant_claim: String,
+    allow_anonymous: bool,
+}
+
+#[derive(Clone)]
+struct VerificationKey {
+    kid: Option<String>,
+    key: DecodingKey,
+    algorithm: Algorithm,
+}
+
+impl JwtAuth {
+    fn with_keys(keys: Vec<VerificationKey>) -> Self {
+        Self {
+            config: Arc::new(JwtConfig {
+                keys,
+                issuer: None,
+                audience: None,
+                leeway: 60,
+                user_claim: "sub".to_string(),
+                roles_claim: "roles".to_string(),
+                tenant_claim: "tenant_id".to_string(),
+                allow_anonymous: false,
+            }),
+        }
+    }
+
+    /// Tokens signed with HS256 and `secret`.
+    #[must_use]
+    pub fn hs256(secret: impl AsRef<[u8]>) -> Self {
+        Self::with_keys(vec![VerificationKey {
+            kid: None,
+            key: DecodingKey::from_secret(secret.as_ref()),
+            algorithm: Algorithm::HS256,
+        }])
+    }
+
+    /// Tokens signed with RS256, checked with the PEM-encoded public key `pem`.
+    ///
+    /// # Errors
+    ///
+    /// When `pem` is not an RSA public key.
+    pub fn rs256_pem(pem: impl AsRef<[u8]>) -> Result<Self, CqrsError> {
+        let key = DecodingKey::from_rsa_pem(pem.as_ref())
+            .map_err(|e| CqrsError::internal(format!("invalid RS256 public key: {e}")))?;
+        Ok(Self::with_keys(vec![VerificationKey {
+            kid: None,
+            key,
+            algorithm: Algorithm::RS256,
+        }]))
+    }
+
+    /// Tokens signed with one of the keys of a JWKS document. A key is picked by the
+    /// token's `kid`; a token without one is accepted only when the set holds a single
+    /// key. A key's `alg` sets the algorithm it verifies, RS256 when it has none.
+    ///
+    /// # Errors
+    ///
+    /// When `jwks` is not a JWKS document, or holds no key or a key that cannot verify.
+    pub fn jwks(jwks: &str) -> Result<Self, CqrsError> {
+        let set: JwkSet = serde_json::from_str(jwks)
+            .map_err(|e| CqrsError::internal(format!("invalid JWKS document: {e}")))?;
+        let keys = set
+            .keys
+            .iter()
+            .map(|jwk| {
+                let key = DecodingKey::from_jwk(jwk)
+                    .map_err(|e| CqrsError::internal(format!("invalid JWK: {e}")))?;
+                let algorithm = match jwk.common.key_algorithm {
+                    Some(algorithm) => {
+                        Algorithm::from_str(&algorithm.to_string()).map_err(|e| {
+                            CqrsError::internal(format!(
+                                "unsupported JWK algorithm {algorithm}: {e}"
+                            ))
+                        })?
+                    }
+                    None => Algorithm::RS256,
+                };
+                Ok(VerificationKey {
+                    kid: jwk.common.key_id.clone(),
+                    key,
+                    algorithm,
+                })
+            })
+            .collect::<Result<Vec<_>, CqrsError>>()?;
+        if keys.is_empty() {
+            return Err(CqrsError::internal("the JWKS document holds no key"));
+        }
+        Ok(Self::with_keys(keys))
+    }
+
+    /// [`jwks`](Self::jwks), read from a file once, here.
+    ///
+    /// # Errors
+    ///
+    /// When the file cannot be read, or as [`jwks`](Self::jwks).
+    pub fn jwks_file(path: impl AsRef<Path>) -> Result<Self, CqrsError> {
+        let path = path.as_ref();
+        let jwks = std::fs::read_to_string(path).map_err(|e| {
+            CqrsError::internal(format!("cannot read JWKS file {}: {e}", path.display()))
+        })?;
+        Self::jwks(&jwks)
+    }
+
+    /// Refuses tokens whose `iss` is not `issuer`.
+    #[must_use]
+    pub fn with_issuer(mut self, issuer: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.config).issuer = Some(issuer.into());
+        self
+    }
+
+    /// Refuses tokens whose `aud` does not include `audience`.
+    #[must_use]
+    pub fn with_audience(mut self, audience: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.config).audience = Some(audience.into());
+        self
+    }
+
+    /// Clock skew tolerated on `exp` and `nbf`, in seconds. Defaults to 60.
+    #[must_use]
+    pub fn with_leeway(mut self, seconds: u64) -> Self {
+        Arc::make_mut(&mut self.config).leeway = seconds;
+        self
+    }
+
+    /// The claim naming the current user. Defaults to `sub`.
+    #[must_use]
+    pub fn with_user_claim(mut self, claim: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.config).user_claim = claim.into();
+        self
+    }
+
+    /// The claim listing the roles. Defaults to `roles`.
+    #[must_use]
+    pub fn with_roles_claim(mut self, claim: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.config).roles_claim = claim.into();
+        self
+    }
+
+    /// The claim naming the tenant. Defaults to `tenant_id`.
+    #[must_use]
+    pub fn with_tenant_claim(mut self, claim: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.config).tenant_claim = claim.into();
+        self
+    }
+
+    /// Lets requests without an `Authorization` header through as the anonymous user.
+    #[must_use]
+    pub fn allow_anonymous(mut self, allow: bool) -> Self {
+        Arc::make_mut(&mut self.config).allow_anonymous = allow;
+        self
+    }
+
+    /// The context of a request with these headers — what the layer inserts. Exposed
+    /// for transports that do not go through the layer, such as an MCP server's
+    /// context provider.
+    ///
+    /// # Errors
+    ///
+    /// A [`JwtRejection`] when the token is missing or invalid.
+    pub fn authenticate(&self, headers: &HeaderMap) -> Result<CqrsContext, JwtRejection> {
+        let request_id = headers
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN)
+            .map(str::to_string);
+        let context = match request_id {
+            Some(request_id) => CqrsContext::default().with_request_id(request_id),
+            None => CqrsContext::default().with_next_request_id(),
+        };
+        let request_id = context.request_id();
+        let reject = |challenge, message: String| JwtRejection {
+            error: CqrsError::from_status(StatusCode::UNAUTHORIZED, message)
+                .with_request_id_if_absent(request_id.clone()),
+            challenge,
+        };
+
+        let Some(authorization) = headers.get(AUTHORIZATION) else {
+            return if self.config.allow_anonymous {
+                Ok(context)
+            } else {
+                Err(reject(
+                    Challenge::Missing,
+                    "missing bearer token".to_string(),
+                ))
+            };
+        };
+        let token = authorization
+            .to_str()
+            .ok()
+            .and_then(|value| {
+                let (scheme, token) = value.split_once(' ')?;
+                scheme.eq_ignore_ascii_case("bearer").then(|| token.trim())
+            })
+            .filter(|token| !token.is_empty())
+            .ok_or_else(|| {
+                reject(
+                    Challenge::InvalidRequest,
+                    "the Authorization header is not a bearer token".to_string(),
+                )
+            })?;
+
+        let claims = self
+            .claims(token)
+            .map_err(|message| reject(Challenge::InvalidToken, message))?;
+
+        let user = claim(&claims, &self.config.user_claim)
+            .and_then(Value::as_str)
+            .filter(|user| !user.is_empty())
+            .ok_or_else(|| {
+                reject(
+                    Challenge::InvalidToken,
+                    format!("the token has no `{}` claim", self.config.user_claim),
+                )
+            })?;
+        let roles = match claim(&claims, &self.config.roles_claim) {
+            Some(Value::Array(roles)) => roles
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect(),
+            Some(Value::String(roles)) => roles.split_whitespace().map(str::to_string).collect(),
+            _ => Vec::new(),
+        };
+        let tenant = claim(&claims, &self.config.tenant_claim)
+            .and_then(Value::as_str)
+            .map(str::to_string);
+
+        Ok(CqrsContext::new(Some(user.to_string()))
+            .with_request_id(request_id)
+            .with_roles(roles)
+            .with_tenant(tenant))
+    }
+
+    /// The claims of a token whose signature and registered claims check out, or why
+    /// it is refused.
+    fn claims(&self, token: &str) -> Result<Map<String, Value>, String> {
+        let header = jsonwebtoken::decode_header(token).map_err(|e| describe(e.kind()))?;
+        let keys = &self.config.keys;
+        // A single key configured without a kid — `hs256`, `rs256_pem` — signs every
+        // token, whatever kid the issuer stamps on it.
+        let unkeyed = matches!(keys.as_slice(), [key] if key.kid.is_none());
+        let key = match &header.kid {
+            Some(_) if unkeyed => keys.first(),
+            Some(kid) => keys
+                .iter()
+                .find(|key| key.kid.as_deref() == Some(kid.as_str())),
+            None if keys.len() == 1 => keys.first(),
+            None => None,
+        }
+        .ok_or_else(|| "no key matches the token".to_string())?;
+
+        let mut validation = Validation::new(key.algorithm);
+        validation.leeway = self.config.leeway;
+        if let Some(issuer) = &self.config.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        match &self.config.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+        jsonwebtoken::decode::<Map<String, Value>>(token, &key.key, &validation)
+            .map(|data| data.claims)
+            .map_err(|e| describe(e.kind()))
+    }
+}
+
+/// Reads a claim, walking nested objects on dots.
+fn claim<'a>(claims: &'a Map<String, Value>, name: &str) -> Option<&'a Value> {
+    if let Some(value) = claims.get(name) {
+        return Some(value);
+    }
+    let mut parts = name.split('.');
+    let first = claims.get(parts.next()?)?;
+    parts.try_fold(first, |value, part| value.get(part))
+}
+
+/// What the client is told about a refused token: enough to act on, nothing about keys.
+fn describe(kind: &ErrorKind) -> String {
+    match kind {
+        ErrorKind::ExpiredSignature => "the token has expired",
+        ErrorKind::ImmatureSignature => "the token is not valid yet",
+        ErrorKind::InvalidIssuer => "the token has the wrong issuer",
+        ErrorKind::InvalidAudience => "the token has the wrong audience",
+        ErrorKind::InvalidSignature => "the token signature does not verify",
+        ErrorKind::InvalidAlgorithm => "the token is signed with the wrong algorithm",
+        ErrorKind::MissingRequiredClaim(_) => "the token lacks a required claim",
+        _ => "the token is malformed",
+    }
+    .to_string()
+}
+
+impl<S> Layer<S> for JwtAuth {
+    type Service = JwtAuthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        JwtAuthService {
+            inner,
+            auth: self.clone(),
+        }
+    }
+}
+
+/// The service [`JwtAuth`] wraps a router in.
+#[derive(Clone)]
+pub struct JwtAuthService<S> {
+    inner: S,
+    auth: JwtAuth,
+}
+
+impl<S, B> Service<Request<B>> for JwtAuthService<S>
+where
+    S: Service<Request<B>, Response = Response>,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Either<S::Future, Ready<Result<Response, S::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<B>) -> Self::Future {
+        match self.auth.authenticate(request.headers()) {
+            Ok(context) => {
+                request.extensions_mut().insert(context);
+                Either::Left(self.inner.call(request))
+            }
+            Err(rejection) => Either::Right(ready(Ok(rejection.into_response()))),
+        }
+    }
+}
+
+/// The `error` of the `WWW-Authenticate` challenge (RFC 6750).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Challenge {
+    Missing,
+    InvalidRequest,
+    InvalidToken,
+}
+
+/// A request refused by [`JwtAuth`]: `401` with a bearer challenge.
+#[derive(Debug)]
+pub struct JwtRejection {
+    error: CqrsError,
+    challenge: Challenge,
+}
+
+impl fmt::Display for JwtRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.error.message)
+    }
+}
+
+impl From<JwtRejection> for CqrsError {
+    fn from(rejection: JwtRejection) -> Self {
+        rejection.error
+    }
+}
+
+impl IntoResponse for JwtRejection {
+    fn into_response(self) -> Response {
+        let challenge = match self.challenge {
+            Challenge::Missing => "Bearer",
+            Challenge::InvalidRequest => "Bearer error=\"invalid_request\"",
+            Challenge::InvalidToken => "Bearer error=\"invalid_token\"",
+        };
+        let mut response = self.error.into_response();
+        response
+            .headers_mut()
+            .insert(WWW_AUTHENTICATE, HeaderValue::from_static(challenge));
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::routing::get;
+    use axum::{Extension, Router};
+    use jsonwebtoken::{EncodingKey, Header};
+    use serde_json::json;
+
+    const SECRET: &[u8] = b"a secret long enough for HS256 tests";
+
+    fn now() -> i64 {
+        chrono::Utc::now().timestamp()
+    }
+
+    fn token(header: Header, secret: &[u8], claims: Value) -> String {
+        jsonwebtoken::encode(&header, &claims, &EncodingKey::from_secret(secret)).unwrap()
+    }
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {token}")).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn a_valid_token_fills_user_roles_tenant_and_request_id() {
+        let auth = JwtAuth::hs256(SECRET).with_audience("ludotheque");
+        let mut headers = bearer(&token(
+            Header::default(),
+            SECRET,
+            json!({
+                "sub": "alice",
+                "aud": "ludotheque",
+                "exp": now() + 60,
+                "roles": ["admin", "editor"],
+                "tenant_id": "acme",
+            }),
+        ));
+        headers.insert(REQUEST_ID_HEADER, HeaderValue::from_static("req-42"));
+
+        let context = auth.authenticate(&headers).unwrap();
+
+        assert_eq!(context.current_user(), "alice");
+        assert_eq!(context.roles(), ["admin", "editor"]);
+        assert!(context.has_role("admin"));
+        assert_eq!(context.tenant(), Some("acme"));
+        assert_eq!(context.request_id(), "req-42");
+    }
+
+    #[test]
+    fn claims_are_read_through_nested_objects_and_scope_strings() {
+        let auth = JwtAuth::hs256(SECRET)
+            .with_user_claim("preferred_username")
+            .with_roles_claim("realm_access.roles")
+            .with_tenant_claim("org.id");
+        let headers = bearer(&token(
+            Header::default(),
+            SECRET,
+            json!({
+                "preferred_username": "bob",
+                "exp": now() + 60,
+                "realm_access": { "roles": "read write" },
+                "org": { "id": "globex" },
+            }),
+        ));
+
+        let context = auth.authenticate(&headers).unwrap();
+
+        assert_eq!(context.current_user(), "bob");
+        assert_eq!(context.roles(), ["read", "write"]);
+        assert_eq!(context.tenant(), Some("globex"));
+        assert!(!context.request_id().is_empty());
+    }
+
+    #[test]
+    fn invalid_tokens_are_refused_with_a_reason() {
+        let auth = JwtAuth::hs256(SECRET).with_leeway(0);
+        let cases = [
+            (
+                token(
+                    Header::default(),
+                    SECRET,
+                    json!({ "sub": "a", "exp": now() - 10 }),
+                ),
+                "the token has expired",
+            ),
+            (
+                token(
+                    Header::default(),
+                    b"another secret entirely",
+                    json!({ "sub": "a", "exp": now() + 60 }),
+                ),
+                "the token signature does not verify",
+            ),
+            (
+                token(Header::default(), SECRET, json!({ "exp": now() + 60 })),
+                "the token has no `sub` claim",
+            ),
+            ("not.a.jwt".to_string(), "the token is malformed"),
+        ];
+
+        for (token, message) in cases {
+            let rejection = auth.authenticate(&bearer(&token)).unwrap_err();
+            assert_eq!(rejection.challenge, Challenge::InvalidToken);
+            assert_eq!(rejection.error.message, message);
+            assert_eq!(rejection.error.http_status(), StatusCode::UNAUTHORIZED);
+        }
+    }
+
+    #[test]
+    fn a_request_without_a_token_is_anonymous_only_when_allowed() {
+        let rejection = JwtAuth::hs256(SECRET)
+            .authenticate(&HeaderMap::new())
+            .unwrap_err();
+        assert_eq!(rejection.challenge, Challenge::Missing);
+
+        let context = JwtAuth::hs256(SECRET)
+            .allow_anonymous(true)
+            .authenticate(&HeaderMap::new())
+            .unwrap();
+        assert_eq!(context.current_user(), "anonymous");
+        assert!(context.roles().is_empty());
+
+        let mut basic = HeaderMap::new();
+        basic.insert(AUTHORIZATION, HeaderValue::from_static("Basic YTpi"));
+        let rejection = JwtAuth::hs256(SECRET)
+            .allow_anonymous(true)
+            .authenticate(&basic)
+            .unwrap_err();
+        assert_eq!(rejection.challenge, Challenge::InvalidRequest);
+    }
+
+    #[test]
+    fn a_jwks_key_is_picked_by_kid() {
+        let jwks = json!({ "keys": [
+            { "kty": "oct", "kid": "k1", "alg": "HS256", "k": "b25lIHNlY3JldCBmb3IgdGhlIGZpcnN0IGtleQ" },
+            { "kty": "oct", "kid": "k2", "alg": "HS256", "k": "YW5vdGhlciBzZWNyZXQgZm9yIHRoZSBzZWNvbmQ" },
+        ]});
+        let auth = JwtAuth::jwks(&jwks.to_string()).unwrap();
+        let claims = json!({ "sub": "carol", "exp": now() + 60 });
+
+        let header = Header {
+            kid: Some("k2".to_string()),
+            ..Header::default()
+        };
+        let signed = token(
+            header.clone(),
+            b"another secret for the second",
+            claims.clone(),
+        );
+        assert_eq!(
+            auth.authenticate(&bearer(&signed)).unwrap().current_user(),
+            "carol"
+        );
+
+        let header = Header {
+            kid: Some("k3".to_string()),
+            ..header
+        };
+        let unknown = token(header, b"another secret for the second", claims);
+        assert_eq!(
+            auth.authenticate(&bearer(&unknown))
+                .unwrap_err()
+                .error
+                .message,
+            "no key matches the token"
+        );
+    }
+
+    #[test]
+    fn a_single_key_without_a_kid_verifies_a_token_with_one() {
+        let header = Header {
+            kid: Some("rotated-2026".to_string()),
+            ..Header::default()
+        };
+        let signed = token(header, SECRET, json!({ "sub": "dave", "exp": now() + 60 }));
+        let context = JwtAuth::hs256(SECRET)
+            .authenticate(&bearer(&signed))
+            .unwrap();
+        assert_eq!(context.current_user(), "dave");
+    }
+
+    #[tokio::test]
+    async fn the_layer_inserts_the_context_or_answers_401() {
+        let mut app =
+            Router::new()
+                .route(
+                    "/",
+                    get(|Extension(context): Extension<CqrsContext>| async move {
+                        context.current_user()
+                    }),
+                )
+                .layer(JwtAuth::hs256(SECRET));
+
+        let signed = token(
+            Header::default(),
+            SECRET,
+            json!({ "sub": "alice", "exp": now() + 60 }),
+        );
+        let request = Request::builder()
+            .uri("/")
+            .header(AUTHORIZATION, format!("Bearer {signed}"))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"alice");
+
+        let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers().get(WWW_AUTHENTICATE).unwrap(), "Bearer");
+    }
+}
@@ -0,0 +1,636 @@
+use crate::dispatchers::ViewUpdates;
+use crate::read::storage::{DynStorage, HasId};
+use crate::read::inmemory::matches;
+use crate::read::Query;
+use crate::rest::security;
+use crate::rest::CqrsHttpQuery;
+use crate::{Aggregate, CqrsContext, CqrsError, ReadPolicies, View};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::routing::get;
+use axum::{Extension, Router};
+use futures::{stream, SinkExt, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use utoipa_axum::router::OpenApiRouter;
+
+/// Subscriptions one socket may hold at once; one more is refused with an `error`.
+pub const MAX_LIVE_SUBSCRIPTIONS: usize = 64;
+
+/// What a client sends, as a JSON text message.
+///
+/// ```json
+/// { "type": "subscribe", "id": "s1", "viewId": "g1" }
+/// { "type": "subscribe", "id": "s2", "filter": "publisher==Kosmos" }
+/// { "type": "unsubscribe", "id": "s1" }
+/// ```
+///
+/// `id` is the client's name for the subscription, echoed on everything sent for it.
+/// `parentId` goes next to either target for a view that is a child of its aggregate.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum LiveRequest {
+    Subscribe {
+        id: String,
+        view_id: Option<String>,
+        filter: Option<String>,
+        parent_id: Option<String>,
+    },
+    Unsubscribe {
+        id: String,
+    },
+}
+
+/// What the server pushes, as a JSON text message.
+///
+/// - `snapshot` — the subscription's current items, once on subscribe: the view, or
+///   none when it does not exist yet; for a filter, the storage's first page.
+/// - `update` — a saved view the subscription covers.
+/// - `leave` — a view that matched a filter subscription and no longer does.
+/// - `error` — a refused message, with the subscription it concerns when known.
+/// - `lagged` — the socket fell behind the update feed; fresh snapshots of every
+///   subscription follow, in place of the updates it missed.
+#[derive(Debug, Clone, Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum LiveMessage<V> {
+    Snapshot {
+        id: String,
+        items: Vec<V>,
+    },
+    Update {
+        id: String,
+        view: V,
+    },
+    Leave {
+        id: String,
+        view_id: String,
+    },
+    Error {
+        id: Option<String>,
+        error: CqrsError,
+    },
+    Lagged {
+        missed: u64,
+    },
+}
+
+enum Target<Q: Query + Serialize> {
+    View(String),
+    /// The ids pushed so far, so a view that stops matching can be announced as `leave`.
+    Filter {
+        query: Box<CqrsHttpQuery<Q>>,
+        members: HashSet<String>,
+    },
+}
+
+struct Subscription<Q: Query + Serialize> {
+    parent_id: Option<String>,
+    target: Target<Q>,
+}
+
+/// Serves `GET {base}/_live`, a WebSocket over which a client subscribes to views — by
+/// id, or by an RSQL filter — and is pushed each view a `ViewDispatcher` saves that its
+/// subscriptions cover. The protocol is [`LiveRequest`] in, [`LiveMessage`] out.
+///
+/// The feed is a [`ViewUpdates`] attached to the dispatcher with
+/// `ViewDispatcher::with_updates`. A filter is the codex `_q`, bounded by `Q`'s fields as
+/// on the list route, and is matched against each saved view in memory.
+///
+/// Every read goes through the storage under the `CqrsContext` of the upgrade request:
+/// the snapshot, and each pushed view, which is re-read by id before it is sent. So
+/// whatever the storage scopes by context, a subscriber sees no more of over the socket
+/// than over the read routes — the re-read is the price of that. A subscription by id
+/// is behind the `find_one` read policy, a filter behind the `find_many` one.
+///
+/// The route is not in the OpenAPI document, which has no way to describe a WebSocket
+/// protocol.
+#[derive(Clone)]
+pub struct CQRSLiveViewRouter<A, V, Q>
+where
+    A: Aggregate,
+    V: View<A> + HasId,
+    Q: Query + Clone + Debug + Serialize + DeserializeOwned + Send + Sync,
+{
+    _phantom: std::marker::PhantomData<A>,
+    storage: DynStorage<V, CqrsHttpQuery<Q>>,
+    updates: ViewUpdates<V>,
+    policies: Arc<ReadPolicies>,
+}
+
+impl<A, V, Q> CQRSLiveViewRouter<A, V, Q>
+where
+    A: Aggregate + 'static,
+    V: View<A> + HasId + 'static,
+    Q: Query + Clone + Debug + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    #[must_use]
+    fn new(
+        storage: DynStorage<V, CqrsHttpQuery<Q>>,
+        updates: ViewUpdates<V>,
+        policies: ReadPolicies,
+    ) -> Self {
+        Self {
+            _phantom: std::marker::PhantomData,
+            storage,
+            updates,
+            policies: Arc::new(policies),
+        }
+    }
+
+    /// `updates` is the one attached to the `ViewDispatcher` saving into `storage`, or a
+    /// clone of it.
+    pub fn routes(
+        storage: DynStorage<V, CqrsHttpQuery<Q>>,
+        updates: ViewUpdates<V>,
+    ) -> OpenApiRouter {
+        Self::routes_with_policies(storage, updates, ReadPolicies::default())
+    }
+
+    /// [`routes`](Self::routes), each subscription checked against `policies`.
+    pub fn routes_with_policies(
+        storage: DynStorage<V, CqrsHttpQuery<Q>>,
+        updates: ViewUpdates<V>,
+        policies: ReadPolicies,
+    ) -> OpenApiRouter {
+        let router = Router::new()
+            .route(
+                "/_live",
+                get(
+                    |State(router): State<Self>,
+                     Extension(context): Extension<CqrsContext>,
+                     upgrade: WebSocketUpgrade| async move {
+                        upgrade.on_upgrade(move |socket| router.serve(socket, context))
+                    },
+                ),
+            )
+            .with_state(Self::new(storage, updates, policies));
+        OpenApiRouter::from(router)
+    }
+
+    /// Runs one socket until the client leaves. The update subscription is taken before
+    /// the first message is read, so no save after the upgrade is missed.
+    async fn serve(self, socket: WebSocket, context: CqrsContext) {
+        enum Input<V> {
+            Client(Option<Message>),
+            Update(Result<V, RecvError>),
+        }
+
+        let (mut sink, client) = socket.split();
+        let client = client
+            .map(|message| Input::Client(message.ok()))
+            .chain(stream::once(async { Input::Client(None) }));
+        let updates = stream::unfold(self.updates.subscribe(), |mut receiver| async move {
+            let next = receiver.recv().await;
+            Some((Input::Update(next), receiver))
+        });
+        let mut inputs = std::pin::pin!(stream::select(client, updates));
+        let mut session = LiveSession::new(self.storage, self.policies, context);
+
+        while let Some(input) = inputs.next().await {
+            let replies = match input {
+                Input::Client(Some(Message::Text(text))) => session.request(text.as_str()).await,
+                Input::Client(Some(Message::Close(_)) | None) => break,
+                Input::Client(Some(_)) => continue,
+                Input::Update(Ok(view)) => session.update(view).await,
+                Input::Update(Err(RecvError::Lagged(missed))) => session.lagged(missed).await,
+                Input::Update(Err(RecvError::Closed)) => break,
+            };
+            for reply in replies {
+                let text = match serde_json::to_string(&reply) {
+                    Ok(text) => text,
+                    Err(e) => {
+                        tracing::error!(error = %e, "failed to serialize a live view message");
+                        continue;
+                    }
+                };
+                if sink.send(Message::Text(text.into())).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// The subscriptions of one socket, and the answers to what arrives on it. Kept apart
+/// from the socket so the protocol can be exercised without one.
+struct LiveSession<V, Q: Query + Serialize> {
+    storage: DynStorage<V, CqrsHttpQuery<Q>>,
+    policies: Arc<ReadPolicies>,
+    context: CqrsContext,
+    subscriptions: HashMap<String, Subscription<Q>>,
+}
+
+impl<V, Q> LiveSession<V, Q>
+where
+    V: Debug + Clone + Default + Serialize + DeserializeOwned + HasId + Send + Sync,
+    Q: Query + Clone + Debug + Serialize + DeserializeOwned + Send + Sync,
+{
+    fn new(
+        storage: DynStorage<V, CqrsHttpQuery<Q>>,
+        policies: Arc<ReadPolicies>,
+        context: CqrsContext,
+    ) -> Self {
+        Self {
+            storage,
+            policies,
+            context,
+            subscriptions: HashMap::new(),
+        }
+    }
+
+    async fn request(&mut self, text: &str) -> Vec<LiveMessage<V>> {
+        let request = match serde_json::from_str::<LiveRequest>(text) {
+            Ok(request) => request,
+            Err(e) => return vec![refusal(None, format!("unreadable message: {e}"))],
+        };
+        match request {
+            LiveRequest::Subscribe {
+                id,
+                view_id,
+                filter,
+                parent_id,
+            } => match self.subscribe(&id, view_id, filter, parent_id) {
+                Ok(()) => vec![self.snapshot(&id).await],
+                Err(error) => vec![LiveMessage::Error {
+                    id: Some(id),
+                    error,
+                }],
+            },
+            LiveRequest::Unsubscribe { id } => match self.subscriptions.remove(&id) {
+                Some(_) => vec![],
+                None => vec![refusal(Some(id), "no such subscription")],
+            },
+        }
+    }
+
+    fn subscribe(
+        &mut self,
+        id: &str,
+        view_id: Option<String>,
+        filter: Option<String>,
+        parent_id: Option<String>,
+    ) -> Result<(), CqrsError> {
+        if self.subscriptions.contains_key(id) {
+            return Err(CqrsError::validation(
+                "a subscription with this id is already open",
+            ));
+        }
+        if self.subscriptions.len() >= MAX_LIVE_SUBSCRIPTIONS {
+            return Err(CqrsError::validation(format!(
+                "a socket holds at most {MAX_LIVE_SUBSCRIPTIONS} subscriptions"
+            )));
+        }
+        let target = match (view_id, filter) {
+            (Some(view_id), None) => {
+                security::authorize_read(self.policies.find_one(), &self.context)?;
+                Target::View(view_id)
+            }
+            (None, Some(filter)) => {
+                security::authorize_read(self.policies.find_many(), &self.context)?;
+                Target::Filter {
+                    query: Box::new(CqrsHttpQuery::from_q(&filter)?),
+                    members: HashSet::new(),
+                }
+            }
+            _ => {
+                return Err(CqrsError::validation(
+                    "a subscription names either a viewId or a filter",
+                ));
+            }
+        };
+        self.subscriptions
+            .insert(id.to_string(), Subscription { parent_id, target });
+        Ok(())
+    }
+
+    async fn snapshot(&mut self, id: &str) -> LiveMessage<V> {
+        let Some(subscription) = self.subscriptions.get_mut(id) else {
+            return refusal(Some(id.to_string()), "no such subscription");
+        };
+        let items = match &mut subscription.target {
+            Target::View(view_id) => self
+                .storage
+                .find_by_id(
+                    subscription.parent_id.clone(),
+                    view_id,
+                    self.context.clone(),
+                )
+                .await
+                .map(|view| view.into_iter().collect::<Vec<_>>()),
+            Target::Filter { query, members } => self
+                .storage
+                .filter(
+                    subscription.parent_id.clone(),
+                    (**query).clone(),
+                    self.context.clone(),
+                )
+                .await
+                .map(|page| {
+                    *members = page.items.iter().map(|v| v.id().to_string()).collect();
+                    page.items
+                }),
+        };
+        match items {
+            Ok(items) => LiveMessage::Snapshot {
+                id: id.to_string(),
+                items,
+            },
+            Err(error) => {
+                // A subscription whose snapshot failed would go on pushing updates the
+                // client has no base for.
+                self.subscriptions.remove(id);
+                LiveMessage::Error {
+                    id: Some(id.to_string()),
+                    error,
+                }
+            }
+        }
+    }
+
+    /// The messages a saved view causes: an `update` per subscription covering it, a
+    /// `leave` per filter it dropped out of.
+    async fn update(&mut self, saved: V) -> Vec<LiveMessage<V>> {
+        let document = match serde_json::to_value(&saved) {
+            Ok(document) => document,
+            Err(e) => {
+                tracing::error!(error = %e, "failed to serialize a saved view");
+                return vec![];
+            }
+        };
+        let view_id = saved.id().to_string();
+        let mut covering = Vec::new();
+        let mut replies = Vec::new();
+        for (id, subscription) in &mut self.subscriptions {
+            if subscription.parent_id.is_some()
+                && subscription.parent_id.as_deref() != saved.parent_id()
+            {
+                continue;
+            }
+            match &mut subscription.target {
+                Target::View(wanted) => {
+                    if *wanted == view_id {
+                        covering.push(id.clone());
+                    }
+                }
+                Target::Filter { query, members } => {
+                    let matched = match query.filter() {
+                        Some(filter) => matches(filter.ast(), &document).unwrap_or(false),
+                        None => true,
+                    };
+                    if matched {
+                        members.insert(view_id.clone());
+                        covering.push(id.clone());
+                    } else if members.remove(&view_id) {
+                        replies.push(LiveMessage::Leave {
+                            id: id.clone(),
+                            view_id: view_id.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        if covering.is_empty() {
+            return replies;
+        }
+
+        // The saved view is what the dispatcher wrote, not what this subscriber may read.
+        let visible = self
+            .storage
+            .find_by_id(
+                saved.parent_id().map(str::to_string),
+                &view_id,
+                self.context.clone(),
+            )
+            .await;
+        match visible {
+            Ok(Some(view)) => replies.extend(covering.into_iter().map(|id| LiveMessage::Update {
+                id,
+                view: view.clone(),
+            })),
+            Ok(None) => {}
+            Err(e) => tracing::warn!(error = %e, view_id, "failed to re-read a live view"),
+        }
+        replies
+    }
+
+    async fn lagged(&mut self, missed: u64) -> Vec<LiveMessage<V>> {
+        let mut replies = vec![LiveMessage::Lagged { missed }];
+        let ids: Vec<String> = self.subscriptions.keys().cloned().collect();
+        for id in ids {
+            replies.push(self.snapshot(&id).await);
+        }
+        replies
+    }
+}
+
+fn refusal<V>(id: Option<String>, message: impl Into<String>) -> LiveMessage<V> {
+    LiveMessage::Error {
+        id,
+        error: CqrsError::validation(message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read::inmemory::InMemoryStorage;
+    use crate::read::storage::Storage;
+
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    struct Game {
+        id: String,
+        publisher: String,
+        players: i64,
+    }
+
+    impl HasId for Game {
+        fn field_id() -> &'static str {
+            "id"
+        }
+        fn id(&self) -> &str {
+            &self.id
+        }
+        fn parent_field_id() -> Option<&'static str> {
+            None
+        }
+        fn parent_id(&self) -> Option<&str> {
+            None
+        }
+    }
+
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    struct GameQuery {
+        publisher: Option<String>,
+    }
+
+    impl Query for GameQuery {}
+
+    fn game(id: &str, publisher: &str, players: i64) -> Game {
+        Game {
+            id: id.to_string(),
+            publisher: publisher.to_string(),
+            players,
+        }
+    }
+
+    async fn session() -> (
+        Arc<InMemoryStorage<Game, CqrsHttpQuery<GameQuery>>>,
+        LiveSession<Game, GameQuery>,
+    ) {
+        let storage = Arc::new(InMemoryStorage::new("game"));
+        for g in [game("g1", "Kosmos", 4), game("g2", "Days", 5)] {
+            storage.save(g, CqrsContext::default()).await.unwrap();
+        }
+        let session = LiveSession::new(
+            storage.clone(),
+            Arc::new(ReadPolicies::default()),
+            CqrsContext::default(),
+        );
+        (storage, session)
+    }
+
+    fn json(messages: &[LiveMessage<Game>]) -> serde_json::Value {
+        serde_json::to_value(messages).unwrap()
+    }
+
+    #[tokio::test]
+    async fn a_view_subscription_gets_its_snapshot_then_its_updates_only() {
+        let (storage, mut session) = session().await;
+        let replies = session
+            .request(r#"{"type":"subscribe","id":"s1","viewId":"g1"}"#)
+            .await;
+        assert_eq!(
+            json(&replies),
+            serde_json::json!([{ "type": "snapshot", "id": "s1",
+                "items": [{ "id": "g1", "publisher": "Kosmos", "players": 4 }] }])
+        );
+
+        let changed = game("g1", "Kosmos", 2);
+        storage
+            .save(changed.clone(), CqrsContext::default())
+            .await
+            .unwrap();
+        let replies = session.update(changed.clone()).await;
+        assert!(
+            matches!(&replies[..], [LiveMessage::Update { id, view }] if id == "s1" && *view == changed)
+        );
+        assert!(session.update(game("g2", "Days", 1)).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_filter_subscription_follows_views_in_and_out_of_its_filter() {
+        let (storage, mut session) = session().await;
+        let replies = session
+            .request(r#"{"type":"subscribe","id":"k","filter":"publisher==Kosmos"}"#)
+            .await;
+        assert!(matches!(&replies[..], [LiveMessage::Snapshot { items, .. }] if items.len() == 1));
+
+        let joined = game("g2", "Kosmos", 5);
+        storage
+            .save(joined.clone(), CqrsContext::default())
+            .await
+            .unwrap();
+        let replies = session.update(joined).await;
+        assert!(matches!(&replies[..], [LiveMessage::Update { view, .. }] if view.id == "g2"));
+
+        let left = game("g1", "Days", 4);
+        storage
+            .save(left.clone(), CqrsContext::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            json(&session.update(left).await),
+            serde_json::json!([{ "type": "leave", "id": "k", "viewId": "g1" }])
+        );
+    }
+
+    #[tokio::test]
+    async fn a_view_the_storage_does_not_return_is_not_pushed() {
+        let (_storage, mut session) = session().await;
+        session
+            .request(r#"{"type":"subscribe","id":"s","viewId":"g9"}"#)
+            .await;
+        // Published, but not readable through the storage under this context.
+        assert!(session.update(game("g9", "Kosmos", 1)).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn malformed_or_conflicting_requests_are_answered_with_an_error() {
+        let (_storage, mut session) = session().await;
+        for (request, id) in [
+            ("not json", None),
+            (r#"{"type":"subscribe","id":"a"}"#, Some("a")),
+            (
+                r#"{"type":"subscribe","id":"b","viewId":"g1","filter":"players==4"}"#,
+                Some("b"),
+            ),
+            (
+                r#"{"type":"subscribe","id":"c","filter":"secret==1"}"#,
+                Some("c"),
+            ),
+            (r#"{"type":"unsubscribe","id":"d"}"#, Some("d")),
+        ] {
+            let replies = session.request(request).await;
+            match &replies[..] {
+                [LiveMessage::Error { id: got, .. }] => assert_eq!(got.as_deref(), id, "{request}"),
+                other => panic!("{request}: expected an error, got {other:?}"),
+            }
+        }
+
+        session
+            .request(r#"{"type":"subscribe","id":"s","viewId":"g1"}"#)
+            .await;
+        let replies = session
+            .request(r#"{"type":"subscribe","id":"s","viewId":"g2"}"#)
+            .await;
+        assert!(
+            matches!(&replies[..], [LiveMessage::Error { .. }]),
+            "ids are unique"
+        );
+    }
+
+    #[tokio::test]
+    async fn a_lagged_socket_is_resynchronised_with_fresh_snapshots() {
+        let (_storage, mut session) = session().await;
+        session
+            .request(r#"{"type":"subscribe","id":"s","viewId":"g2"}"#)
+            .await;
+        let replies = session.lagged(3).await;
+        assert!(matches!(
+            &replies[..],
+            [LiveMessage::Lagged { missed: 3 }, LiveMessage::Snapshot { id, .. }] if id == "s"
+        ));
+    }
+
+    #[tokio::test]
+    async fn subscriptions_are_behind_the_read_policies() {
+        let (storage, _) = session().await;
+        let mut session = LiveSession::new(
+            storage,
+            Arc::new(ReadPolicies::new().with_find_many(crate::Policy::any_role(["librarian"]))),
+            CqrsContext::new(Some("bob".to_string())),
+        );
+        let replies = session
+            .request(r#"{"type":"subscribe","id":"k","filter":"publisher==Kosmos"}"#)
+            .await;
+        assert!(
+            matches!(&replies[..], [LiveMessage::Error { error, .. }] if error.http_status() == 403)
+        );
+        let replies = session
+            .request(r#"{"type":"subscribe","id":"s","viewId":"g1"}"#)
+            .await;
+        assert!(matches!(&replies[..], [LiveMessage::Snapshot { .. }]));
+    }
+}
@@ -1,14 +1,40 @@
+mod admin_router;
 mod audit_log_router;
 pub mod codex;
 mod codex_router;
+mod command_status;
+mod conditional;
+mod event_stream_router;
+mod export;
+#[cfg(feature = "graphql")]
+mod graphql;
+mod health_router;
 mod helpers;
+#[cfg(feature = "jwt")]
+mod jwt;
+#[cfg(feature = "ws")]
+mod live_view_router;
 mod read_router;
+mod security;
+mod sync_router;
 
 use axum::response::{IntoResponse, Response};
+pub use admin_router::*;
 pub use audit_log_router::*;
 pub use codex::CqrsHttpQuery;
 pub use codex_router::CQRSCodexReadRouter;
+pub use command_status::*;
+pub use event_stream_router::*;
+#[cfg(feature = "graphql")]
+pub use graphql::*;
+pub use health_router::*;
+#[cfg(feature = "jwt")]
+pub use jwt::*;
+#[cfg(feature = "ws")]
+pub use live_view_router::*;
 pub use read_router::*;
+pub use security::*;
+pub use sync_router::*;
 mod write_router;
 use crate::CqrsError;
 pub use write_router::*;
@@ -1,15 +1,18 @@
 use crate::read::storage::DynStorage;
 use crate::read::Paged;
+use crate::rest::conditional::{self, Validators};
 use crate::rest::helpers;
-use crate::{Aggregate, CqrsContext, CqrsError, View};
+use crate::rest::security;
+use crate::{Aggregate, CqrsContext, CqrsError, ReadPolicies, View};
 use axum::extract::{Path, Query, State};
 use axum::response::IntoResponse;
 use axum::routing::get;
-use axum::{Extension, Json};
-use http::StatusCode;
+use axum::Extension;
+use http::{HeaderMap, StatusCode};
 use serde::de::DeserializeOwned;
 use serde_json::json;
 use std::fmt::Debug;
+use std::sync::Arc;
 use utoipa::openapi::path::ParameterIn;
 use utoipa::openapi::{HttpMethod, Ref, RefOr, Schema};
 use utoipa::{IntoParams, PartialSchema, ToSchema};
@@ -24,6 +27,7 @@ where
 {
     _phantom: std::marker::PhantomData<(A, V, Q)>,
     storage: DynStorage<V, Q>,
+    policies: Arc<ReadPolicies>,
 }
 
 impl<A, V, Q> CQRSReadRouter<A, V, Q>
@@ -33,10 +37,11 @@ where
     Q: Clone + Debug + DeserializeOwned + Send + Sync + IntoParams + 'static,
 {
     #[must_use]
-    fn new(storage: DynStorage<V, Q>) -> Self {
+    fn new(storage: DynStorage<V, Q>, policies: ReadPolicies) -> Self {
         Self {
             _phantom: std::marker::PhantomData,
             storage,
+            policies: Arc::new(policies),
         }
     }
 
@@ -66,6 +71,7 @@ where
     fn find_many(
         router: OpenApiRouter<CQRSReadRouter<A, V, Q>>,
         tag: &str,
+        policies: &ReadPolicies,
     ) -> OpenApiRouter<CQRSReadRouter<A, V, Q>> {
         let path = Self::base_path();
         let response_schema_name = format!("{}_{}", Paged::<V>::name(), V::name());
@@ -84,22 +90,25 @@ where
             None,
             &[StatusCode::BAD_REQUEST, StatusCode::INTERNAL_SERVER_ERROR],
         );
+        let paths = security::secure(conditional::document(paths), policies.find_many());
 
         let find_many_handler = if V::IS_CHILD_OF_AGGREGATE {
             get(
                 move |State(router): State<CQRSReadRouter<A, V, Q>>,
                       Path(parent_id): Path<String>,
                       Query(query): Query<Q>,
-                      Extension(context): Extension<CqrsContext>| async {
-                    Self::search(router, Some(parent_id), query, context).await
+                      headers: HeaderMap,
+                      Extension(context): Extension<CqrsContext>| async move {
+                    Self::search(router, Some(parent_id), query, context, headers).await
                 },
             )
         } else {
             get(
                 move |State(router): State<CQRSReadRouter<A, V, Q>>,
                       Query(query): Query<Q>,
-                      Extension(context): Extension<CqrsContext>| async {
-                    Self::search(router, None, query, context).await
+                      headers: HeaderMap,
+                      Extension(context): Extension<CqrsContext>| async move {
+                    Self::search(router, None, query, context, headers).await
                 },
             )
         };
@@ -114,6 +123,7 @@ where
     fn find_one(
         router: OpenApiRouter<CQRSReadRouter<A, V, Q>>,
         tag: &str,
+        policies: &ReadPolicies,
     ) -> OpenApiRouter<CQRSReadRouter<A, V, Q>> {
         let path = Self::base_path();
         let response_schema_name = V::name();
@@ -135,22 +145,25 @@ where
             None,
             &[StatusCode::NOT_FOUND, StatusCode::INTERNAL_SERVER_ERROR],
         );
+        let paths = security::secure(conditional::document(paths), policies.find_one());
 
         let find_one_handler = if V::IS_CHILD_OF_AGGREGATE {
             get(
                 move |State(router): State<CQRSReadRouter<A, V, Q>>,
                       Path(parent_id): Path<String>,
                       Path(id): Path<String>,
-                      Extension(context): Extension<CqrsContext>| async {
-                    Self::by_id(router, Some(parent_id), id, context).await
+                      headers: HeaderMap,
+                      Extension(context): Extension<CqrsContext>| async move {
+                    Self::by_id(router, Some(parent_id), id, context, headers).await
                 },
             )
         } else {
             get(
                 move |State(router): State<CQRSReadRouter<A, V, Q>>,
                       Path(id): Path<String>,
-                      Extension(context): Extension<CqrsContext>| async {
-                    Self::by_id(router, None, id, context).await
+                      headers: HeaderMap,
+                      Extension(context): Extension<CqrsContext>| async move {
+                    Self::by_id(router, None, id, context, headers).await
                 },
             )
         };
@@ -163,12 +176,22 @@ where
     }
 
     pub fn routes(storage: DynStorage<V, Q>, tag: &'static str) -> OpenApiRouter {
-        let state = Self::new(storage);
+        Self::routes_with_policies(storage, tag, ReadPolicies::default())
+    }
 
+    /// [`routes`](Self::routes), each route checking its policy before the storage is
+    /// read.
+    pub fn routes_with_policies(
+        storage: DynStorage<V, Q>,
+        tag: &'static str,
+        policies: ReadPolicies,
+    ) -> OpenApiRouter {
         let mut result = OpenApiRouter::<CQRSReadRouter<A, V, Q>>::new();
         // Find many
-        result = Self::find_many(result, tag);
-        result = Self::find_one(result, tag);
+        result = Self::find_many(result, tag, &policies);
+        result = Self::find_one(result, tag, &policies);
+
+        let state = Self::new(storage, policies);
 
         result.with_state(state)
     }
@@ -178,10 +201,22 @@ where
         parent_id: Option<String>,
         query: Q,
         context: CqrsContext,
+        headers: HeaderMap,
     ) -> impl IntoResponse {
         let request_id = context.request_id();
-        match router.storage.filter(parent_id, query, context).await {
-            Ok(result) => (StatusCode::OK, Json(result)).into_response(),
+        if let Err(err) = security::authorize_read(router.policies.find_many(), &context) {
+            return err.with_request_id_if_absent(request_id).into_response();
+        }
+        let result = router
+            .storage
+            .filter(parent_id, query, context)
+            .await
+            .and_then(|page| {
+                let validators = Validators::of_page(page.items.iter().map(V::last_modified));
+                conditional::json(&headers, &page, validators)
+            });
+        match result {
+            Ok(response) => response,
             Err(err) => err.with_request_id_if_absent(request_id).into_response(),
         }
     }
@@ -191,10 +226,21 @@ where
         parent_id: Option<String>,
         id: String,
         context: CqrsContext,
+        headers: HeaderMap,
     ) -> impl IntoResponse {
         let request_id = context.request_id();
+        if let Err(err) = security::authorize_read(router.policies.find_one(), &context) {
+            return err.with_request_id_if_absent(request_id).into_response();
+        }
         match router.storage.find_by_id(parent_id, &id, context).await {
-            Ok(Some(x)) => (StatusCode::OK, Json(x)).into_response(),
+            Ok(Some(view)) => {
+                let validators = Validators {
+                    version: view.version(),
+                    last_modified: view.last_modified(),
+                };
+                conditional::json(&headers, &view, validators)
+                    .unwrap_or_else(|err| err.with_request_id_if_absent(request_id).into_response())
+            }
             Ok(None) => CqrsError::not_found(format!("{} '{}' not found", V::TYPE, id))
                 .with_details(json!({ "id": id }))
                 .with_request_id_if_absent(request_id)
@@ -0,0 +1,84 @@
+use crate::rest::ERROR_MEDIA_TYPE;
+use crate::rest::helpers::ERROR_SCHEMA_NAME;
+use crate::Policy;
+pub(crate) use crate::policy::authorize_read;
+pub use crate::ReadPolicies;
+use http::StatusCode;
+use utoipa::Modify;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityRequirement, SecurityScheme};
+use utoipa::openapi::{Content, OpenApi, Paths, Ref, RefOr, ResponseBuilder};
+
+/// Name of the security scheme the `security` requirement of a route guarded by a
+/// [`Policy`] refers to. [`BearerSecurity`] declares it.
+pub const BEARER_AUTH: &str = "bearer_auth";
+
+/// Declares the [`BEARER_AUTH`] scheme — an HTTP bearer JWT — in the document's
+/// components, for the requirements of guarded routes to resolve:
+///
+/// ```ignore
+/// #[derive(OpenApi)]
+/// #[openapi(modifiers(&BearerSecurity))]
+/// struct ApiDoc;
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BearerSecurity;
+
+impl Modify for BearerSecurity {
+    fn modify(&self, openapi: &mut OpenApi) {
+        openapi
+            .components
+            .get_or_insert_with(Default::default)
+            .add_security_scheme(
+                BEARER_AUTH,
+                SecurityScheme::Http(
+                    HttpBuilder::new()
+                        .scheme(HttpAuthScheme::Bearer)
+                        .bearer_format("JWT")
+                        .build(),
+                ),
+            );
+    }
+}
+
+/// Documents a route guarded by `policy`: a [`BEARER_AUTH`] requirement with the roles
+/// as scopes, and the `401` and `403` answers. A policy open to the anonymous user
+/// documents nothing.
+pub(crate) fn secure<T>(mut paths: Paths, policy: Option<&Policy<T>>) -> Paths {
+    let Some(policy) = policy.filter(|policy| policy.requires_authentication()) else {
+        return paths;
+    };
+    for item in paths.paths.values_mut() {
+        for operation in [
+            item.get.as_mut(),
+            item.post.as_mut(),
+            item.put.as_mut(),
+            item.patch.as_mut(),
+            item.delete.as_mut(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            operation
+                .security
+                .get_or_insert_with(Vec::new)
+                .push(SecurityRequirement::new(BEARER_AUTH, policy.roles()));
+            for status in [StatusCode::UNAUTHORIZED, StatusCode::FORBIDDEN] {
+                operation.responses.responses.insert(
+                    status.as_u16().to_string(),
+                    RefOr::T(
+                        ResponseBuilder::new()
+                            .description(status.canonical_reason().unwrap_or("Error"))
+                            .content(
+                                ERROR_MEDIA_TYPE,
+                                Content::new(Some(RefOr::Ref(Ref::from_schema_name(
+                                    ERROR_SCHEMA_NAME,
+                                )))),
+                            )
+                            .build(),
+                    ),
+                );
+            }
+        }
+    }
+    paths
+}
@@ -0,0 +1,234 @@
+use crate::sync::{SyncPull, SyncPush, SyncServer, SyncTransport};
+use crate::{Aggregate, CommandHandler, CqrsContext, CqrsError, Policy};
+use axum::extract::State;
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Extension, Json};
+use http::StatusCode;
+use serde::Serialize;
+use std::future::Future;
+use utoipa::openapi::{HttpMethod, ObjectBuilder, Ref, RefOr, Schema, Type};
+use utoipa::{PartialSchema, ToSchema};
+use utoipa_axum::router::{OpenApiRouter, UtoipaMethodRouter};
+
+use crate::rest::{helpers, security};
+
+/// The server side of offline-first synchronization, over HTTP:
+/// - `POST /sync/push` — a [`SyncPush`], answered with the outcome of each command.
+/// - `POST /sync/pull` — a [`SyncPull`], answered with the events the client lacks.
+///
+/// ```rust,ignore
+/// let sync = CQRSSyncRouter::new(SyncServer::new(engine.clone())).routes("sync");
+/// ```
+///
+/// Both answer `200`: a conflicted or rejected command is an outcome of the push, not an
+/// error of it. The routes are behind their own policy — by default, any authenticated
+/// user — and each pushed command behind the engine's policy for its variant as well.
+#[derive(Clone)]
+pub struct CQRSSyncRouter<A>
+where
+    A: Aggregate + CommandHandler + 'static,
+    A::Error: Into<CqrsError>,
+{
+    server: SyncServer<A>,
+    policy: Policy<()>,
+}
+
+impl<A> CQRSSyncRouter<A>
+where
+    A: Aggregate + CommandHandler + 'static,
+    A::Error: Into<CqrsError>,
+{
+    #[must_use]
+    pub fn new(server: SyncServer<A>) -> Self {
+        Self {
+            server,
+            policy: Policy::authenticated(),
+        }
+    }
+
+    /// Who may synchronize, instead of any authenticated user.
+    #[must_use]
+    pub fn with_policy(mut self, policy: Policy<()>) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    fn schema_name(name: &str) -> String {
+        format!("{}_{name}", A::TYPE)
+    }
+
+    /// The events in a result are those of `A`, which has no schema of its own.
+    fn result_schema(description: &str) -> RefOr<Schema> {
+        RefOr::T(Schema::Object(
+            ObjectBuilder::new()
+                .schema_type(Type::Object)
+                .description(Some(description))
+                .build(),
+        ))
+    }
+
+    fn route(
+        &self,
+        router: OpenApiRouter<CQRSSyncRouter<A>>,
+        tag: &str,
+        path: &str,
+        body: (String, RefOr<Schema>),
+        response: (String, RefOr<Schema>),
+        handler: axum::routing::MethodRouter<CQRSSyncRouter<A>>,
+    ) -> OpenApiRouter<CQRSSyncRouter<A>> {
+        let (body_name, body_schema) = body;
+        let (response_name, response_schema) = response;
+        let mut paths = helpers::generate_route(
+            tag,
+            HttpMethod::Post,
+            path,
+            RefOr::Ref(Ref::from_schema_name(&response_name)),
+            vec![],
+            vec![],
+            Some(RefOr::Ref(Ref::from_schema_name(&body_name))),
+            &[
+                StatusCode::UNPROCESSABLE_ENTITY,
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ],
+        );
+        for item in paths.paths.values_mut() {
+            if let Some(operation) = item.post.as_mut()
+                && let Some(response) = operation.responses.responses.remove("201")
+            {
+                operation
+                    .responses
+                    .responses
+                    .insert("200".to_string(), response);
+            }
+        }
+        let schemas = vec![
+            (body_name, body_schema),
+            (response_name, response_schema),
+            helpers::error_schema(),
+        ];
+        router.routes(UtoipaMethodRouter::<CQRSSyncRouter<A>>::from((
+            schemas,
+            security::secure(paths, Some(&self.policy)),
+            handler,
+        )))
+    }
+
+    pub fn routes(self, tag: &'static str) -> OpenApiRouter {
+        let mut result = OpenApiRouter::<CQRSSyncRouter<A>>::new();
+        result = self.route(
+            result,
+            tag,
+            "/sync/push",
+            (SyncPush::name().to_string(), SyncPush::schema()),
+            (
+                Self::schema_name("SyncPushResult"),
+                Self::result_schema("The outcome of each pushed command, in the order pushed"),
+            ),
+            post(
+                |State(router): State<CQRSSyncRouter<A>>,
+                 Extension(context): Extension<CqrsContext>,
+                 Json(push): Json<SyncPush>| async move {
+                    router.push(push, context).await
+                },
+            ),
+        );
+        result = self.route(
+            result,
+            tag,
+            "/sync/pull",
+            (SyncPull::name().to_string(), SyncPull::schema()),
+            (
+                Self::schema_name("SyncPullResult"),
+                Self::result_schema("The events the client lacks, by aggregate and version"),
+            ),
+            post(
+                |State(router): State<CQRSSyncRouter<A>>,
+                 Extension(context): Extension<CqrsContext>,
+                 Json(pull): Json<SyncPull>| async move {
+                    router.pull(pull, context).await
+                },
+            ),
+        );
+        result.with_state(self)
+    }
+
+    async fn authorized<T, F>(&self, context: &CqrsContext, operation: F) -> Response
+    where
+        T: Serialize,
+        F: Future<Output = Result<T, CqrsError>>,
+    {
+        let result = match self.policy.authorize(context, &()) {
+            Ok(()) => operation.await,
+            Err(err) => Err(err),
+        };
+        match result {
+            Ok(body) => (StatusCode::OK, Json(body)).into_response(),
+            Err(err) => err
+                .with_request_id_if_absent(context.request_id())
+                .into_response(),
+        }
+    }
+
+    pub async fn push(&self, push: SyncPush, context: CqrsContext) -> Response {
+        self.authorized(&context, self.server.push(push, &context))
+            .await
+    }
+
+    pub async fn pull(&self, pull: SyncPull, context: CqrsContext) -> Response {
+        self.authorized(&context, self.server.pull(pull, &context))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::es::inmemory::InMemoryPersist;
+    use crate::es::EventStoreImpl;
+    use crate::sync::{SyncCommand, SyncCommandKind};
+    use crate::testing::TestAggregate;
+    use crate::CqrsCommandEngine;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    async fn body(response: Response) -> Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn an_authenticated_user_pushes_and_pulls() {
+        let store = EventStoreImpl::new(InMemoryPersist::<TestAggregate>::new());
+        let engine = Arc::new(CqrsCommandEngine::new(store, vec![], (), Box::new(|_| {})));
+        let router = CQRSSyncRouter::new(SyncServer::new(engine));
+        let push = SyncPush {
+            commands: vec![SyncCommand {
+                id: "c1".to_string(),
+                aggregate_id: "a1".to_string(),
+                kind: SyncCommandKind::Create,
+                variant: None,
+                command: json!({ "Initialize": { "name": "n" } }),
+                known_version: 0,
+            }],
+        };
+
+        let anonymous = router.push(push.clone(), CqrsContext::default()).await;
+        assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+
+        let user = CqrsContext::new(Some("ada".to_string()));
+        let response = router.push(push, user.clone()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let outcome = &body(response).await["outcomes"][0];
+        assert_eq!(outcome["status"], "applied");
+        assert_eq!(outcome["version"], 1);
+
+        let response = router.pull(SyncPull::default(), user).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let events = &body(response).await["events"];
+        assert_eq!(events[0]["aggregateId"], "a1");
+        assert_eq!(events[0]["metadata"]["sync_command_id"], "c1");
+    }
+}
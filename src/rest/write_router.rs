@@ -1,17 +1,22 @@
 use crate::engine::CqrsCommandEngine;
+use crate::policy::TaggedCommand;
 use crate::rest::helpers;
 use crate::rest::helpers::SchemaData;
-use crate::{Aggregate, CommandHandler, CqrsContext, CqrsError};
+use crate::rest::security;
+use crate::rest::{AcceptedCommand, CommandStatusRecord, DynCommandStatusStore};
+use crate::{Aggregate, CommandHandler, CqrsContext, CqrsError, USER_ID_METADATA};
 use axum::extract::{Path, State};
-use axum::response::IntoResponse;
-use axum::routing::{post, put};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, put};
 use axum::{Extension, Json};
-use http::StatusCode;
+use http::{HeaderMap, StatusCode};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
-use utoipa::openapi::{HttpMethod, Ref, RefOr};
+use utoipa::openapi::path::{Parameter, ParameterBuilder, ParameterIn};
+use utoipa::openapi::{Content, HttpMethod, Paths, Ref, RefOr, Required, ResponseBuilder};
 use utoipa::{PartialSchema, ToSchema};
 use utoipa_axum::router::{OpenApiRouter, UtoipaMethodRouter};
 
@@ -23,6 +28,32 @@ pub struct CreationResult {
 #[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
 pub struct UpdateResult;
 
+/// Request header (RFC 7240) a client sends to have a command run in the background.
+pub const PREFER: &str = "prefer";
+
+/// Response header confirming that `Prefer: respond-async` was honoured.
+pub const PREFERENCE_APPLIED: &str = "preference-applied";
+
+const RESPOND_ASYNC: &str = "respond-async";
+
+/// Serves `POST /commands/{variant}` and `PUT /{TYPE_id}/commands/{variant}` for each
+/// command variant of `A`.
+///
+/// Built with [`routes_with_status_store`](Self::routes_with_status_store), a command
+/// sent with `Prefer: respond-async` is answered `202 Accepted` with an
+/// [`AcceptedCommand`] as soon as its body is read, and runs in the background; a body
+/// that does not match the command schema is still a synchronous `422`. Its
+/// [`CommandStatusRecord`] is kept in the store and served by
+/// `GET /commands/{command_id}` — `pending`, then `succeeded` with the
+/// [`CreationResult`] or `failed` with the problem document — to the identified user who
+/// sent it, under the tenant it was sent under, only; anyone else, the anonymous user
+/// included, gets `404`. Commands sent without the header run as before.
+///
+/// Each variant runs under the policy the engine holds for its tag (see
+/// [`CqrsCommandEngine::with_policies`]), which the document reflects as a
+/// [`BEARER_AUTH`](crate::rest::BEARER_AUTH) `security` requirement. The engine reads
+/// the tag off the command, whichever route it came in by (see
+/// [`CommandPolicies`](crate::CommandPolicies)).
 #[derive(Clone)]
 pub struct CQRSWriteRouter<A>
 where
@@ -30,6 +61,7 @@ where
     A::Error: Into<CqrsError>,
 {
     engine: Arc<CqrsCommandEngine<A>>,
+    statuses: Option<DynCommandStatusStore>,
 }
 
 impl<A> CQRSWriteRouter<A>
@@ -38,12 +70,25 @@ where
     A::Error: Into<CqrsError>,
 {
     #[must_use]
-    fn new(engine: Arc<CqrsCommandEngine<A>>) -> Self {
-        Self { engine }
+    fn new(engine: Arc<CqrsCommandEngine<A>>, statuses: Option<DynCommandStatusStore>) -> Self {
+        Self { engine, statuses }
     }
 
     pub fn routes(engine: Arc<CqrsCommandEngine<A>>) -> OpenApiRouter {
-        let context = CQRSWriteRouter::new(engine);
+        Self::build(CQRSWriteRouter::new(engine, None))
+    }
+
+    /// [`routes`](Self::routes), plus `202 Accepted` for commands sent with
+    /// `Prefer: respond-async` and `GET /commands/{command_id}` to follow them.
+    pub fn routes_with_status_store(
+        engine: Arc<CqrsCommandEngine<A>>,
+        statuses: DynCommandStatusStore,
+    ) -> OpenApiRouter {
+        Self::build(CQRSWriteRouter::new(engine, Some(statuses)))
+    }
+
+    fn build(context: CQRSWriteRouter<A>) -> OpenApiRouter {
+        let accepts_async = context.statuses.is_some();
 
         let mut result = OpenApiRouter::<CQRSWriteRouter<A>>::new();
         let mut base_schema = vec![];
@@ -70,6 +115,12 @@ where
             A::schemas(&mut schemas);
             CreationResult::schemas(&mut schemas);
 
+            if accepts_async {
+                schemas.push((
+                    AcceptedCommand::name().to_string(),
+                    AcceptedCommand::schema(),
+                ));
+            }
             let paths = helpers::generate_route(
                 A::TYPE,
                 HttpMethod::Post,
@@ -85,6 +136,15 @@ where
                     StatusCode::INTERNAL_SERVER_ERROR,
                 ],
             );
+            let paths = if accepts_async {
+                Self::with_async_acceptance(paths)
+            } else {
+                paths
+            };
+            let paths = security::secure(
+                paths,
+                context.engine.policies().policy(variant(&discriminator)),
+            );
 
             let current_discriminator = discriminator.clone();
             result = result.routes(UtoipaMethodRouter::<CQRSWriteRouter<A>>::from((
@@ -93,8 +153,16 @@ where
                 post(
                     move |State(router): State<CQRSWriteRouter<A>>,
                           Extension(context): Extension<CqrsContext>,
-                          Json(command): Json<Value>| async {
-                        Self::create(router, command, current_discriminator, context).await
+                          headers: HeaderMap,
+                          Json(command): Json<Value>| async move {
+                        if router.statuses.is_some() && prefers_async(&headers) {
+                            Self::accept_create(router, command, current_discriminator, context)
+                                .await
+                        } else {
+                            Self::create(router, command, current_discriminator, context)
+                                .await
+                                .into_response()
+                        }
                     },
                 ),
             )))
@@ -115,6 +183,12 @@ where
             schemas.push(helpers::error_schema());
             A::UpdateCommand::schemas(&mut schemas);
             UpdateResult::schemas(&mut schemas);
+            if accepts_async {
+                schemas.push((
+                    AcceptedCommand::name().to_string(),
+                    AcceptedCommand::schema(),
+                ));
+            }
 
             let id_path = format!("{}_id", A::TYPE);
             let paths = helpers::generate_route(
@@ -138,6 +212,15 @@ where
                     StatusCode::INTERNAL_SERVER_ERROR,
                 ],
             );
+            let paths = if accepts_async {
+                Self::with_async_acceptance(paths)
+            } else {
+                paths
+            };
+            let paths = security::secure(
+                paths,
+                context.engine.policies().policy(variant(&discriminator)),
+            );
 
             let current_discriminator = discriminator.clone();
             result = result.routes(UtoipaMethodRouter::<CQRSWriteRouter<A>>::from((
@@ -147,19 +230,95 @@ where
                     move |State(router): State<CQRSWriteRouter<A>>,
                           Path(id): Path<String>,
                           Extension(context): Extension<CqrsContext>,
-                          Json(command): Json<Value>| async {
-                        Self::update(router, id, command, current_discriminator, context).await
+                          headers: HeaderMap,
+                          Json(command): Json<Value>| async move {
+                        if router.statuses.is_some() && prefers_async(&headers) {
+                            Self::accept_update(router, id, command, current_discriminator, context)
+                                .await
+                        } else {
+                            Self::update(router, id, command, current_discriminator, context)
+                                .await
+                                .into_response()
+                        }
                     },
                 ),
             )))
         }
 
+        if accepts_async {
+            result = Self::command_status(result);
+        }
+
         result.with_state(context)
     }
 
+    fn command_status(router: OpenApiRouter<Self>) -> OpenApiRouter<Self> {
+        let mut schemas = vec![
+            (
+                CommandStatusRecord::name().to_string(),
+                CommandStatusRecord::schema(),
+            ),
+            helpers::error_schema(),
+        ];
+        CommandStatusRecord::schemas(&mut schemas);
+
+        let paths = helpers::generate_route(
+            A::TYPE,
+            HttpMethod::Get,
+            "/commands/{command_id}",
+            RefOr::Ref(Ref::from_schema_name(CommandStatusRecord::name())),
+            vec![("command_id".to_string(), String::schema())],
+            vec![],
+            None,
+            &[StatusCode::NOT_FOUND, StatusCode::INTERNAL_SERVER_ERROR],
+        );
+
+        router.routes(UtoipaMethodRouter::<Self>::from((
+            schemas,
+            paths,
+            get(
+                move |State(router): State<Self>,
+                      Path(command_id): Path<String>,
+                      Extension(context): Extension<CqrsContext>| async move {
+                    Self::status(router, command_id, context).await
+                },
+            ),
+        )))
+    }
+
+    /// Documents the `202` answer and the `Prefer` header on command routes.
+    fn with_async_acceptance(mut paths: Paths) -> Paths {
+        for item in paths.paths.values_mut() {
+            for operation in [item.post.as_mut(), item.put.as_mut()]
+                .into_iter()
+                .flatten()
+            {
+                operation.responses.responses.insert(
+                    StatusCode::ACCEPTED.as_u16().to_string(),
+                    RefOr::T(
+                        ResponseBuilder::new()
+                            .description("Accepted; runs in the background")
+                            .content(
+                                "application/json",
+                                Content::new(Some(RefOr::Ref(Ref::from_schema_name(
+                                    AcceptedCommand::name(),
+                                )))),
+                            )
+                            .build(),
+                    ),
+                );
+                operation
+                    .parameters
+                    .get_or_insert_with(Vec::new)
+                    .push(prefer_param());
+            }
+        }
+        paths
+    }
+
     fn metadata(context: &CqrsContext) -> HashMap<String, String> {
         HashMap::from_iter(vec![
-            ("user_id".to_string(), context.current_user()),
+            (USER_ID_METADATA.to_string(), context.current_user()),
             ("request_id".to_string(), context.request_id()),
         ])
     }
@@ -175,7 +334,7 @@ where
         match serde_json::from_value::<A::CreateCommand>(command) {
             Ok(cmd) => match router
                 .engine
-                .execute_create_with_metadata(cmd, Self::metadata(&context), &context)
+                .execute_create_variant(cmd, Self::metadata(&context), &context)
                 .await
             {
                 Ok(result) => {
@@ -191,6 +350,35 @@ where
         }
     }
 
+    pub async fn accept_create(
+        router: CQRSWriteRouter<A>,
+        mut command: Value,
+        discriminator: Option<(String, String)>,
+        context: CqrsContext,
+    ) -> Response {
+        helpers::add_discriminator(&mut command, discriminator);
+        match serde_json::from_value::<A::CreateCommand>(command) {
+            Ok(cmd) => {
+                // Who may send the command is known now; whether it may act on the
+                // aggregate is found once it runs, and recorded as its outcome.
+                if let Err(err) = Self::authorize_context(&router, &cmd, &context) {
+                    return err
+                        .with_request_id_if_absent(context.request_id())
+                        .into_response();
+                }
+                let engine = router.engine.clone();
+                let metadata = Self::metadata(&context);
+                Self::accept(router, context, move |context| async move {
+                    engine.execute_create_variant(cmd, metadata, &context).await
+                })
+                .await
+            }
+            Err(err) => CqrsError::unprocessable(err.to_string())
+                .with_request_id_if_absent(context.request_id())
+                .into_response(),
+        }
+    }
+
     pub async fn update(
         router: CQRSWriteRouter<A>,
         id: String,
@@ -203,7 +391,7 @@ where
         match serde_json::from_value::<A::UpdateCommand>(command) {
             Ok(cmd) => match router
                 .engine
-                .execute_update_with_metadata(&id, cmd, Self::metadata(&context), &context)
+                .execute_update_variant(&id, cmd, Self::metadata(&context), &context)
                 .await
             {
                 Ok(_) => (StatusCode::OK, Json(UpdateResult)).into_response(),
@@ -216,4 +404,442 @@ where
                 .into_response(),
         }
     }
+
+    pub async fn accept_update(
+        router: CQRSWriteRouter<A>,
+        id: String,
+        mut command: Value,
+        discriminator: Option<(String, String)>,
+        context: CqrsContext,
+    ) -> Response {
+        helpers::add_discriminator(&mut command, discriminator);
+        match serde_json::from_value::<A::UpdateCommand>(command) {
+            Ok(cmd) => {
+                // Who may send the command is known now; whether it may act on the
+                // aggregate is found once it runs, and recorded as its outcome.
+                if let Err(err) = Self::authorize_context(&router, &cmd, &context) {
+                    return err
+                        .with_request_id_if_absent(context.request_id())
+                        .into_response();
+                }
+                let engine = router.engine.clone();
+                let metadata = Self::metadata(&context);
+                Self::accept(router, context, move |context| async move {
+                    engine
+                        .execute_update_variant(&id, cmd, metadata, &context)
+                        .await
+                        .map(|_| id)
+                })
+                .await
+            }
+            Err(err) => CqrsError::unprocessable(err.to_string())
+                .with_request_id_if_absent(context.request_id())
+                .into_response(),
+        }
+    }
+
+    /// The context checks of the policy `command` runs under, which the engine picks.
+    fn authorize_context<C: TaggedCommand>(
+        router: &CQRSWriteRouter<A>,
+        command: &C,
+        context: &CqrsContext,
+    ) -> Result<(), CqrsError> {
+        match router.engine.policies().policy_of(command)? {
+            Some(policy) => policy.authorize_context(context),
+            None => Ok(()),
+        }
+    }
+
+    /// Records the command as pending, runs it on a spawned task, and answers `202`.
+    async fn accept<F, Fut>(router: CQRSWriteRouter<A>, context: CqrsContext, run: F) -> Response
+    where
+        F: FnOnce(CqrsContext) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<String, CqrsError>> + Send + 'static,
+    {
+        let request_id = context.request_id();
+        let Some(statuses) = router.statuses else {
+            return CqrsError::internal("no command status store")
+                .with_request_id_if_absent(request_id)
+                .into_response();
+        };
+        let record = CommandStatusRecord::pending(context.next_uuid(), A::TYPE, &context);
+        let command_id = record.command_id.clone();
+        if let Err(err) = statuses.save(record.clone()).await {
+            return err.with_request_id_if_absent(request_id).into_response();
+        }
+
+        tokio::spawn(async move {
+            let result = run(context).await;
+            let record = record.completed(result, chrono::Utc::now());
+            let command_id = record.command_id.clone();
+            if let Err(err) = statuses.save(record).await {
+                tracing::error!(
+                    command_id = %command_id,
+                    error = %err.message,
+                    "failed to record the outcome of an accepted command"
+                );
+            }
+        });
+
+        (
+            StatusCode::ACCEPTED,
+            [(PREFERENCE_APPLIED, RESPOND_ASYNC)],
+            Json(AcceptedCommand { command_id }),
+        )
+            .into_response()
+    }
+
+    async fn status(
+        router: CQRSWriteRouter<A>,
+        command_id: String,
+        context: CqrsContext,
+    ) -> Response {
+        let request_id = context.request_id();
+        let Some(statuses) = router.statuses else {
+            return CqrsError::not_found("no command status store")
+                .with_request_id_if_absent(request_id)
+                .into_response();
+        };
+        match statuses.find(&command_id).await {
+            // Another user's command is reported as unknown rather than forbidden, so
+            // that its id gives nothing away.
+            Ok(Some(record)) if is_sender(&record, &context) => {
+                (StatusCode::OK, Json(record)).into_response()
+            }
+            Ok(_) => CqrsError::not_found(format!("command '{command_id}' not found"))
+                .with_details(json!({ "id": command_id }))
+                .with_request_id_if_absent(request_id)
+                .into_response(),
+            Err(err) => err.with_request_id_if_absent(request_id).into_response(),
+        }
+    }
+}
+
+/// Whether `context` sent the command of `record`: the same identified user, under the
+/// same tenant. Every caller without credentials is the same anonymous user, so no one
+/// reads the status of an anonymous command.
+fn is_sender(record: &CommandStatusRecord, context: &CqrsContext) -> bool {
+    context.is_authenticated()
+        && record.user_id == context.current_user()
+        && record.tenant.as_deref() == context.tenant()
+}
+
+/// The tag of the variant a route serves, the key of the policy it documents; `None`
+/// when untagged.
+fn variant(discriminator: &Option<(String, String)>) -> Option<&str> {
+    discriminator.as_ref().map(|(_, value)| value.as_str())
+}
+
+/// Whether the `Prefer` headers ask for `respond-async`, parameters aside.
+fn prefers_async(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(PREFER)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|preference| preference.split(';').next())
+        .any(|preference| preference.trim().eq_ignore_ascii_case(RESPOND_ASYNC))
+}
+
+fn prefer_param() -> Parameter {
+    ParameterBuilder::new()
+        .name("Prefer")
+        .parameter_in(ParameterIn::Header)
+        .description(Some(
+            "`respond-async` runs the command in the background: the answer is 202 with \
+             the id to follow at GET /commands/{command_id}.",
+        ))
+        .required(Required::False)
+        .schema(Some(String::schema()))
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::es::inmemory::InMemoryPersist;
+    use crate::es::EventStoreImpl;
+    use crate::rest::InMemoryCommandStatusStore;
+    use crate::testing::TestAggregate;
+    use crate::DynEventStore;
+    use http::HeaderValue;
+
+    fn router() -> CQRSWriteRouter<TestAggregate> {
+        let store: DynEventStore<TestAggregate> = EventStoreImpl::new(InMemoryPersist::new());
+        let engine = Arc::new(CqrsCommandEngine::new(store, vec![], (), Box::new(|_| {})));
+        CQRSWriteRouter::new(engine, Some(Arc::new(InMemoryCommandStatusStore::new())))
+    }
+
+    fn context(user: &str) -> CqrsContext {
+        CqrsContext::new(Some(user.to_string())).with_request_id("r-1".to_string())
+    }
+
+    async fn body(response: Response) -> Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    /// Polls the status route until the command is no longer pending.
+    async fn completed(router: &CQRSWriteRouter<TestAggregate>, command_id: &str) -> Value {
+        for _ in 0..100 {
+            let response =
+                CQRSWriteRouter::status(router.clone(), command_id.to_string(), context("alice"))
+                    .await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let record = body(response).await;
+            if record["status"] != "pending" {
+                return record;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("command {command_id} is still pending");
+    }
+
+    #[test]
+    fn prefer_respond_async_is_read_among_other_preferences() {
+        let mut headers = HeaderMap::new();
+        assert!(!prefers_async(&headers));
+
+        headers.append(PREFER, HeaderValue::from_static("return=minimal"));
+        assert!(!prefers_async(&headers));
+
+        headers.append(PREFER, HeaderValue::from_static("wait=10, Respond-Async"));
+        assert!(prefers_async(&headers));
+    }
+
+    #[tokio::test]
+    async fn an_accepted_command_runs_in_the_background_and_reports_its_result() {
+        let router = router();
+
+        let response = CQRSWriteRouter::accept_create(
+            router.clone(),
+            json!({ "Initialize": { "name": "counter" } }),
+            None,
+            context("alice"),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        assert_eq!(
+            response.headers().get(PREFERENCE_APPLIED).unwrap(),
+            RESPOND_ASYNC
+        );
+        let command_id = body(response).await["commandId"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        let record = completed(&router, &command_id).await;
+        assert_eq!(record["status"], "succeeded");
+        assert_eq!(record["aggregateType"], "TEST");
+        assert_eq!(record["userId"], "alice");
+        assert!(record["result"]["id"].is_string());
+        assert!(record["completedAt"].is_string());
+
+        // Someone else's command is unknown to them.
+        let response =
+            CQRSWriteRouter::status(router.clone(), command_id, context("mallory")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn a_status_is_read_by_its_identified_sender_under_its_tenant_only() {
+        let router = router();
+        let accept = |context: CqrsContext| {
+            CQRSWriteRouter::accept_create(
+                router.clone(),
+                json!({ "Initialize": { "name": "counter" } }),
+                None,
+                context,
+            )
+        };
+        let status = |command_id: &str, context: CqrsContext| {
+            CQRSWriteRouter::status(router.clone(), command_id.to_string(), context)
+        };
+        let command_id = |response: Response| async {
+            body(response).await["commandId"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        };
+
+        let acme = || context("alice").with_tenant(Some("acme".to_string()));
+        let sent = command_id(accept(acme()).await).await;
+        assert_eq!(status(&sent, acme()).await.status(), StatusCode::OK);
+        let globex = context("alice").with_tenant(Some("globex".to_string()));
+        let response = status(&sent, globex).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "another tenant");
+        let response = status(&sent, context("alice")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "no tenant");
+
+        // Every anonymous caller is the same user: none reads another's status.
+        let sent = command_id(accept(CqrsContext::default()).await).await;
+        let response = status(&sent, CqrsContext::default()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn a_failed_command_reports_the_problem_document() {
+        let router = router();
+
+        let response = CQRSWriteRouter::accept_update(
+            router.clone(),
+            "missing".to_string(),
+            json!("Increment"),
+            None,
+            context("alice"),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let command_id = body(response).await["commandId"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        let record = completed(&router, &command_id).await;
+        assert_eq!(record["status"], "failed");
+        assert_eq!(record["error"]["status"], 404);
+        assert_eq!(record["error"]["requestId"], "r-1");
+    }
+
+    #[tokio::test]
+    async fn a_body_that_is_not_a_command_is_refused_before_acceptance() {
+        let router = router();
+
+        let response = CQRSWriteRouter::accept_update(
+            router,
+            "a1".to_string(),
+            json!("Explode"),
+            None,
+            context("alice"),
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[test]
+    fn the_status_route_and_the_202_exist_only_with_a_store() {
+        let engine = router().engine;
+
+        let (_, sync) = CQRSWriteRouter::routes(engine.clone()).split_for_parts();
+        assert!(!sync.paths.paths.contains_key("/commands/{command_id}"));
+
+        let statuses: DynCommandStatusStore = Arc::new(InMemoryCommandStatusStore::new());
+        let (_, with_store) =
+            CQRSWriteRouter::routes_with_status_store(engine, statuses).split_for_parts();
+        assert!(
+            with_store
+                .paths
+                .paths
+                .contains_key("/commands/{command_id}")
+        );
+        let update = with_store
+            .paths
+            .paths
+            .values()
+            .find_map(|item| item.put.as_ref())
+            .expect("an update route");
+        assert!(update.responses.responses.contains_key("202"));
+    }
+
+    #[tokio::test]
+    async fn a_command_of_no_readable_variant_is_refused_under_variant_policies() {
+        let store: DynEventStore<TestAggregate> = EventStoreImpl::new(InMemoryPersist::new());
+        let engine = Arc::new(
+            CqrsCommandEngine::new(store, vec![], (), Box::new(|_| {})).with_policies(
+                crate::CommandPolicies::new()
+                    .variant("Decrement", crate::Policy::any_role(["admin"]))
+                    .otherwise(crate::Policy::authenticated()),
+            ),
+        );
+        let router =
+            CQRSWriteRouter::new(engine, Some(Arc::new(InMemoryCommandStatusStore::new())));
+
+        // Externally tagged, `Decrement` has no tag to read: it does not slip through
+        // under `otherwise`, synchronously or not.
+        let response = CQRSWriteRouter::update(
+            router.clone(),
+            "a1".to_string(),
+            json!("Decrement"),
+            None,
+            context("alice"),
+        )
+        .await
+        .into_response();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = CQRSWriteRouter::accept_update(
+            router.clone(),
+            "a1".to_string(),
+            json!("Decrement"),
+            None,
+            context("alice"),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // A body that is no command at all is still a 422.
+        let response = CQRSWriteRouter::update(
+            router,
+            "a1".to_string(),
+            json!("Explode"),
+            None,
+            context("alice"),
+        )
+        .await
+        .into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn a_policy_refuses_before_acceptance_and_is_documented() {
+        let store: DynEventStore<TestAggregate> = EventStoreImpl::new(InMemoryPersist::new());
+        let engine = Arc::new(
+            CqrsCommandEngine::new(store, vec![], (), Box::new(|_| {})).with_policies(
+                crate::CommandPolicies::new().otherwise(crate::Policy::any_role(["admin"])),
+            ),
+        );
+        let statuses: DynCommandStatusStore = Arc::new(InMemoryCommandStatusStore::new());
+        let router = CQRSWriteRouter::new(engine.clone(), Some(statuses.clone()));
+
+        let response = CQRSWriteRouter::accept_create(
+            router.clone(),
+            json!({ "Initialize": { "name": "counter" } }),
+            None,
+            context("alice"),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let admin = context("root").with_roles(vec!["admin".to_string()]);
+        let response = CQRSWriteRouter::create(
+            router,
+            json!({ "Initialize": { "name": "counter" } }),
+            None,
+            admin,
+        )
+        .await
+        .into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let (_, api) =
+            CQRSWriteRouter::routes_with_status_store(engine, statuses).split_for_parts();
+        let create = api
+            .paths
+            .paths
+            .values()
+            .find_map(|item| item.post.as_ref())
+            .expect("a create route");
+        let security = serde_json::to_value(create.security.as_ref().unwrap()).unwrap();
+        assert_eq!(security, json!([{ crate::rest::BEARER_AUTH: ["admin"] }]));
+        assert!(create.responses.responses.contains_key("401"));
+        assert!(create.responses.responses.contains_key("403"));
+        // The status route is the sender's own business, not the policy's.
+        let status = api.paths.paths["/commands/{command_id}"]
+            .get
+            .as_ref()
+            .unwrap();
+        assert!(status.security.is_none());
+    }
 }
@@ -0,0 +1,107 @@
+//! Shared SQLite connection.
+//!
+//! Both the event store ([`crate::es::sqlite`]) and the read side
+//! ([`crate::read::sqlite`]) go through a [`SharedConnection`]: one
+//! `rusqlite::Connection` behind a mutex, which every storage of a database clones.
+//!
+//! SQLite is embedded, so a statement runs on the task that awaits it rather than on a
+//! server: on a local file it answers in microseconds, and a mutex is what SQLite would
+//! serialize writers with anyway. A service sharing the database with another process
+//! should open it with [`SharedConnection::open`], which turns on WAL and a busy timeout.
+
+use crate::errors::CqrsError;
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{Connection, ErrorCode};
+use std::fmt::{self, Debug};
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+/// How long a statement waits for another process's write lock before failing.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// One SQLite connection, shared by every storage of a database.
+#[derive(Clone)]
+pub struct SharedConnection(Arc<Mutex<Connection>>);
+
+impl Debug for SharedConnection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SharedConnection").finish()
+    }
+}
+
+impl SharedConnection {
+    /// Wraps a connection opened by the application.
+    #[must_use]
+    pub fn new(connection: Connection) -> Self {
+        Self(Arc::new(Mutex::new(connection)))
+    }
+
+    /// Opens, or creates, the database file at `path`, in WAL mode.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, CqrsError> {
+        let connection = Connection::open(path).map_err(map_sqlite_error)?;
+        connection
+            .pragma_update(None, "journal_mode", "WAL")
+            .map_err(map_sqlite_error)?;
+        connection
+            .busy_timeout(BUSY_TIMEOUT)
+            .map_err(map_sqlite_error)?;
+        Ok(Self::new(connection))
+    }
+
+    /// A private database that lives as long as the connection — for tests.
+    pub fn open_in_memory() -> Result<Self, CqrsError> {
+        Connection::open_in_memory()
+            .map(Self::new)
+            .map_err(map_sqlite_error)
+    }
+
+    /// Runs `sql`, several statements separated by `;` — a schema, say.
+    pub fn execute_batch(&self, sql: &str) -> Result<(), CqrsError> {
+        self.with(|conn| conn.execute_batch(sql).map_err(map_sqlite_error))
+    }
+
+    pub(crate) fn with<T>(
+        &self,
+        f: impl FnOnce(&mut Connection) -> Result<T, CqrsError>,
+    ) -> Result<T, CqrsError> {
+        let mut connection = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        f(&mut connection)
+    }
+
+    /// A round trip through the connection, for the `health` of the storages.
+    pub(crate) fn ping(&self) -> Result<(), CqrsError> {
+        self.with(|conn| {
+            conn.query_row("SELECT 1", [], |_| Ok(()))
+                .map_err(map_sqlite_error)
+        })
+    }
+}
+
+pub(crate) fn map_sqlite_error(e: rusqlite::Error) -> CqrsError {
+    CqrsError::database_error(e)
+}
+
+/// Whether `e` is a `UNIQUE` or primary key violation — a version written twice.
+pub(crate) fn is_unique_violation(e: &rusqlite::Error) -> bool {
+    matches!(
+        e.sqlite_error(),
+        Some(error) if error.code == ErrorCode::ConstraintViolation
+            && matches!(
+                error.extended_code,
+                rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE | rusqlite::ffi::SQLITE_CONSTRAINT_PRIMARYKEY
+            )
+    )
+}
+
+/// A timestamp as SQLite stores it: RFC 3339 in UTC with microseconds, a fixed width,
+/// so that comparing the text compares the instants.
+pub(crate) fn timestamp(at: &DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+pub(crate) fn parse_timestamp(at: &str) -> Result<DateTime<Utc>, CqrsError> {
+    DateTime::parse_from_rfc3339(at)
+        .map(|at| at.with_timezone(&Utc))
+        .map_err(CqrsError::serialization_error)
+}
@@ -0,0 +1,647 @@
+use crate::denormalizer::Dispatcher;
+use crate::es::storage::EventStoreStorage;
+use crate::policy::command_tag;
+use crate::sync::{
+    DynSyncTransport, PendingCommand, SyncCommandKind, SyncConflict, SyncEventStore, SyncOutcome,
+    SyncPull, SyncPush, SyncRejection, SYNC_COMMAND_METADATA,
+};
+use crate::{
+    Aggregate, CommandHandler, CqrsCommandEngine, CqrsContext, CqrsError, EventEnvelope, MaybeSend,
+    MaybeSync, USER_ID_METADATA,
+};
+use serde_json::Value as JsonValue;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Debug;
+use std::sync::Arc;
+use tracing::{debug, error};
+
+/// What a [`SyncClient::sync`] did.
+#[derive(Clone, Debug)]
+pub struct SyncReport<A>
+where
+    A: Aggregate,
+{
+    /// The commands the server applied, by id.
+    pub applied: Vec<String>,
+    /// The commands dropped because the server had moved on.
+    pub conflicts: Vec<SyncConflict<A>>,
+    /// The commands the server refused.
+    pub rejected: Vec<SyncRejection>,
+    /// How many events the pull wrote to the replica.
+    pub pulled: usize,
+}
+
+impl<A> Default for SyncReport<A>
+where
+    A: Aggregate,
+{
+    fn default() -> Self {
+        Self {
+            applied: Vec::new(),
+            conflicts: Vec::new(),
+            rejected: Vec::new(),
+            pulled: 0,
+        }
+    }
+}
+
+/// Runs commands on a client, offline or not, and synchronizes them with the server.
+///
+/// ```rust,ignore
+/// let outbox: DynSyncOutbox<Account> = Arc::new(InMemorySyncOutbox::new());
+/// let store = SyncEventStore::new(replica, outbox);
+/// let engine = CqrsCommandEngine::new(store.clone(), vec![], (), Box::new(|_| {}));
+/// let client = SyncClient::new(engine, store, transport);
+///
+/// let id = client.execute_create(json!({ "Open": { "owner": "ada" } }), &context).await?;
+/// let report = client.sync(&context).await?;
+/// ```
+///
+/// The engine must run over `store`. Commands are given as the JSON the server takes
+/// them as, and answer as soon as they committed locally; the engine's dispatchers see
+/// their events then. Of the events a pull brings, only those of other clients — or of
+/// commands the client issued elsewhere — go to the dispatchers of
+/// [`with_dispatcher`](Self::with_dispatcher). A conflicted or rejected command is
+/// dropped with the events it committed locally: the views it reached must be refreshed
+/// from the report.
+pub struct SyncClient<A, P>
+where
+    A: Aggregate + CommandHandler + 'static,
+    A::Error: Into<CqrsError>,
+    P: EventStoreStorage<A> + MaybeSend + MaybeSync + Clone + Debug + 'static,
+{
+    engine: Arc<CqrsCommandEngine<A>>,
+    store: Arc<SyncEventStore<A, P>>,
+    transport: DynSyncTransport<A>,
+    #[cfg(not(target_arch = "wasm32"))]
+    dispatchers: Vec<Box<dyn Dispatcher<A> + Send + Sync>>,
+    #[cfg(target_arch = "wasm32")]
+    dispatchers: Vec<Box<dyn Dispatcher<A>>>,
+}
+
+impl<A, P> SyncClient<A, P>
+where
+    A: Aggregate + CommandHandler + 'static,
+    A::Error: Into<CqrsError>,
+    P: EventStoreStorage<A> + MaybeSend + MaybeSync + Clone + Debug + 'static,
+{
+    #[must_use]
+    pub fn new(
+        engine: CqrsCommandEngine<A>,
+        store: Arc<SyncEventStore<A, P>>,
+        transport: DynSyncTransport<A>,
+    ) -> Self {
+        Self {
+            engine: Arc::new(engine),
+            store,
+            transport,
+            dispatchers: Vec::new(),
+        }
+    }
+
+    /// Gets the events pulled from the server that the client did not commit itself.
+    #[must_use]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_dispatcher(mut self, dispatcher: Box<dyn Dispatcher<A> + Send + Sync>) -> Self {
+        self.dispatchers.push(dispatcher);
+        self
+    }
+
+    #[must_use]
+    #[cfg(target_arch = "wasm32")]
+    pub fn with_dispatcher(mut self, dispatcher: Box<dyn Dispatcher<A>>) -> Self {
+        self.dispatchers.push(dispatcher);
+        self
+    }
+
+    pub fn engine(&self) -> &Arc<CqrsCommandEngine<A>> {
+        &self.engine
+    }
+
+    pub fn store(&self) -> &Arc<SyncEventStore<A, P>> {
+        &self.store
+    }
+
+    /// The commands the server does not have yet, or whose events the client has not
+    /// pulled, oldest first.
+    pub async fn pending(&self) -> Result<Vec<PendingCommand<A>>, CqrsError> {
+        self.store.outbox().list().await
+    }
+
+    /// Runs a create command locally; answers the id of the aggregate, which the server
+    /// keeps.
+    pub async fn execute_create(
+        &self,
+        command: JsonValue,
+        context: &CqrsContext,
+    ) -> Result<String, CqrsError> {
+        let create = serde_json::from_value::<A::CreateCommand>(command.clone())
+            .map_err(|e| CqrsError::unprocessable(e.to_string()))?;
+        let id = self
+            .stage(
+                SyncCommandKind::Create,
+                command_tag(&create),
+                command,
+                context,
+            )
+            .await?;
+        let result = self
+            .engine
+            .execute_create_variant(create, Self::metadata(&id, context), context)
+            .await;
+        self.settle(&id, result).await
+    }
+
+    /// Runs an update command locally.
+    pub async fn execute_update(
+        &self,
+        aggregate_id: &str,
+        command: JsonValue,
+        context: &CqrsContext,
+    ) -> Result<(), CqrsError> {
+        let update = serde_json::from_value::<A::UpdateCommand>(command.clone())
+            .map_err(|e| CqrsError::unprocessable(e.to_string()))?;
+        let id = self
+            .stage(
+                SyncCommandKind::Update,
+                command_tag(&update),
+                command,
+                context,
+            )
+            .await?;
+        let result = self
+            .engine
+            .execute_update_variant(aggregate_id, update, Self::metadata(&id, context), context)
+            .await;
+        self.settle(&id, result).await
+    }
+
+    fn metadata(command_id: &str, context: &CqrsContext) -> HashMap<String, String> {
+        HashMap::from_iter(vec![
+            (USER_ID_METADATA.to_string(), context.current_user()),
+            ("request_id".to_string(), context.request_id()),
+            (SYNC_COMMAND_METADATA.to_string(), command_id.to_string()),
+        ])
+    }
+
+    /// Records a command in the outbox before the engine runs it, for the store to
+    /// commit its events to. `variant` is the tag the engine reads off the command, the
+    /// label the server checks it against.
+    async fn stage(
+        &self,
+        kind: SyncCommandKind,
+        variant: Option<String>,
+        command: JsonValue,
+        context: &CqrsContext,
+    ) -> Result<String, CqrsError> {
+        let outbox = self.store.outbox();
+        let sequence = outbox
+            .list()
+            .await?
+            .last()
+            .map_or(1, |command| command.sequence + 1);
+        let pending = PendingCommand {
+            id: context.next_uuid(),
+            sequence,
+            kind,
+            variant,
+            command,
+            aggregate_id: None,
+            events: None,
+            recorded_at: context.now(),
+            applied_version: None,
+        };
+        let id = pending.id.clone();
+        outbox.save(pending).await?;
+        Ok(id)
+    }
+
+    /// Forgets a staged command that did not commit.
+    async fn settle<T>(&self, id: &str, result: Result<T, CqrsError>) -> Result<T, CqrsError> {
+        let outbox = self.store.outbox();
+        let staged = match &result {
+            Ok(_) => outbox.find(id).await?.is_some_and(|c| c.events.is_none()),
+            Err(_) => true,
+        };
+        if staged && let Err(e) = outbox.remove(id).await {
+            error!(command_id = %id, error = %e, "Failed to drop a command that did not commit");
+        }
+        result
+    }
+
+    /// Pushes the pending commands, then pulls what the server has that the replica
+    /// does not. A failed push or pull leaves the outbox as it was, to try again.
+    pub async fn sync(&self, context: &CqrsContext) -> Result<SyncReport<A>, CqrsError> {
+        let mut report = SyncReport::default();
+        self.push(&mut report, context).await?;
+        self.pull(&mut report, context).await?;
+        Ok(report)
+    }
+
+    async fn push(
+        &self,
+        report: &mut SyncReport<A>,
+        context: &CqrsContext,
+    ) -> Result<(), CqrsError> {
+        let outbox = self.store.outbox();
+        let checkpoint = outbox.checkpoint().await?;
+        let pending = outbox.list().await?;
+
+        // What the server is known to have: pulled, or applied by an earlier push.
+        let mut known = checkpoint.versions;
+        for command in &pending {
+            if let (Some(aggregate_id), Some(version)) =
+                (&command.aggregate_id, command.applied_version)
+            {
+                let known = known.entry(aggregate_id.clone()).or_default();
+                *known = (*known).max(version);
+            }
+        }
+        let commands: Vec<_> = pending
+            .iter()
+            .filter(|command| command.applied_version.is_none())
+            .filter_map(|command| {
+                let known_version = match command.kind {
+                    SyncCommandKind::Create => 0,
+                    SyncCommandKind::Update => known
+                        .get(command.aggregate_id.as_deref()?)
+                        .copied()
+                        .unwrap_or(0),
+                };
+                command.to_sync_command(known_version)
+            })
+            .collect();
+        if commands.is_empty() {
+            return Ok(());
+        }
+
+        debug!(command_count = commands.len(), "Pushing pending commands");
+        let result = self.transport.push(SyncPush { commands }, context).await?;
+        for outcome in result.outcomes {
+            match outcome {
+                SyncOutcome::Applied {
+                    command_id,
+                    version,
+                    ..
+                } => {
+                    if let Some(mut command) = outbox.find(&command_id).await? {
+                        command.applied_version = Some(version);
+                        outbox.save(command).await?;
+                    }
+                    report.applied.push(command_id);
+                }
+                SyncOutcome::Conflict(conflict) => {
+                    outbox.remove(&conflict.command_id).await?;
+                    report.conflicts.push(conflict);
+                }
+                SyncOutcome::Rejected(rejection) => {
+                    outbox.remove(&rejection.command_id).await?;
+                    report.rejected.push(rejection);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn pull(
+        &self,
+        report: &mut SyncReport<A>,
+        context: &CqrsContext,
+    ) -> Result<(), CqrsError> {
+        let outbox = self.store.outbox();
+        let mut checkpoint = outbox.checkpoint().await?;
+        let pending = outbox.list().await?;
+        let own: HashSet<_> = pending.iter().map(|command| command.id.as_str()).collect();
+
+        let mut since = checkpoint.pulled_at;
+        let mut pulled_at = None;
+        loop {
+            let mut known_versions = checkpoint.versions.clone();
+            for command in &pending {
+                if let Some(aggregate_id) = &command.aggregate_id {
+                    known_versions.entry(aggregate_id.clone()).or_insert(0);
+                }
+            }
+            let result = self
+                .transport
+                .pull(
+                    SyncPull {
+                        known_versions,
+                        since,
+                    },
+                    context,
+                )
+                .await?;
+            // The next synchronization reads from where the first answer began; the
+            // later ones only page through the rest of it.
+            pulled_at.get_or_insert(result.pulled_at);
+
+            let mut pulled: BTreeMap<String, Vec<EventEnvelope<A>>> = BTreeMap::new();
+            for event in result.events {
+                pulled
+                    .entry(event.aggregate_id.clone())
+                    .or_default()
+                    .push(event);
+            }
+            for (aggregate_id, mut events) in pulled {
+                events.sort_by_key(|e| e.version);
+                let last = events.last().map_or(0, |e| e.version);
+                let written = self
+                    .store
+                    .apply_remote(&aggregate_id, events, context)
+                    .await?;
+                report.pulled += written.len();
+                let version = checkpoint.versions.entry(aggregate_id.clone()).or_default();
+                *version = (*version).max(last);
+
+                let foreign: Vec<_> = written
+                    .into_iter()
+                    .filter(|e| {
+                        e.metadata
+                            .get(SYNC_COMMAND_METADATA)
+                            .is_none_or(|id| !own.contains(id.as_str()))
+                    })
+                    .collect();
+                if !foreign.is_empty() {
+                    self.dispatch(&aggregate_id, &foreign, context).await;
+                }
+            }
+
+            match result.continue_from {
+                Some(next) => {
+                    since = Some(next);
+                    outbox.save_checkpoint(checkpoint.clone()).await?;
+                }
+                None => break,
+            }
+        }
+
+        // The replica now holds the events of the applied commands.
+        for command in &pending {
+            if let (Some(aggregate_id), Some(applied)) =
+                (&command.aggregate_id, command.applied_version)
+                && checkpoint
+                    .versions
+                    .get(aggregate_id)
+                    .is_some_and(|v| *v >= applied)
+            {
+                outbox.remove(&command.id).await?;
+            }
+        }
+        checkpoint.pulled_at = pulled_at;
+        outbox.save_checkpoint(checkpoint).await
+    }
+
+    /// The replica already holds the events: a dispatcher failing them is logged, and
+    /// they are not dispatched again.
+    async fn dispatch(
+        &self,
+        aggregate_id: &str,
+        events: &[EventEnvelope<A>],
+        context: &CqrsContext,
+    ) {
+        for (i, dispatcher) in self.dispatchers.iter().enumerate() {
+            if let Err(e) = dispatcher.dispatch(aggregate_id, events, context).await {
+                error!(dispatcher_index = i, error = %e, "Failed to dispatch pulled events");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::es::inmemory::InMemoryPersist;
+    use crate::es::EventStoreImpl;
+    use crate::sync::{
+        DynSyncOutbox, InMemorySyncOutbox, RebaseConflicts, SyncCommand, SyncServer, SyncTransport,
+    };
+    use crate::testing::TestAggregate;
+    use serde_json::json;
+    use std::sync::{Mutex, PoisonError};
+
+    type Client = SyncClient<TestAggregate, InMemoryPersist<TestAggregate>>;
+
+    fn server() -> SyncServer<TestAggregate> {
+        let store = EventStoreImpl::new(InMemoryPersist::<TestAggregate>::new());
+        SyncServer::new(Arc::new(CqrsCommandEngine::new(
+            store,
+            vec![],
+            (),
+            Box::new(|_| {}),
+        )))
+    }
+
+    fn client(server: &SyncServer<TestAggregate>) -> Client {
+        let outbox: DynSyncOutbox<TestAggregate> = Arc::new(InMemorySyncOutbox::new());
+        let store = SyncEventStore::new(InMemoryPersist::new(), outbox);
+        let engine = CqrsCommandEngine::new(store.clone(), vec![], (), Box::new(|_| {}));
+        SyncClient::new(engine, store, Arc::new(server.clone()))
+    }
+
+    /// The counter and version of an aggregate, as `engine` sees it.
+    async fn counter(engine: &CqrsCommandEngine<TestAggregate>, id: &str) -> (i64, usize) {
+        let (aggregate, version) = engine
+            .store()
+            .load_aggregate(id, &CqrsContext::default())
+            .await
+            .unwrap();
+        let state = serde_json::to_value(aggregate).unwrap();
+        (state["counter"].as_i64().unwrap(), version)
+    }
+
+    /// Records the versions of the events dispatched to it.
+    struct Recorder(Arc<Mutex<Vec<usize>>>);
+
+    cqrs_async_trait! {
+    impl Dispatcher<TestAggregate> for Recorder {
+        async fn dispatch(
+            &self,
+            _: &str,
+            events: &[EventEnvelope<TestAggregate>],
+            _: &CqrsContext,
+        ) -> Result<(), CqrsError> {
+            let mut seen = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+            seen.extend(events.iter().map(|e| e.version));
+            Ok(())
+        }
+    }
+    }
+
+    async fn create(client: &Client, context: &CqrsContext) -> String {
+        client
+            .execute_create(json!({ "Initialize": { "name": "n" } }), context)
+            .await
+            .unwrap()
+    }
+
+    async fn increment(client: &Client, id: &str, context: &CqrsContext) {
+        client
+            .execute_update(id, json!("Increment"), context)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn commands_run_offline_reach_the_server_and_come_back_to_the_replica() {
+        let context = CqrsContext::default();
+        let server = server();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let client = client(&server).with_dispatcher(Box::new(Recorder(seen.clone())));
+
+        let id = create(&client, &context).await;
+        increment(&client, &id, &context).await;
+        increment(&client, &id, &context).await;
+        assert_eq!(client.pending().await.unwrap().len(), 3);
+        assert_eq!(counter(client.engine(), &id).await, (2, 3));
+        assert!(
+            client
+                .store()
+                .replica()
+                .fetch_snapshot(&id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let report = client.sync(&context).await.unwrap();
+        assert_eq!(report.applied.len(), 3);
+        assert!(report.conflicts.is_empty() && report.rejected.is_empty());
+        assert_eq!(report.pulled, 3);
+        assert!(client.pending().await.unwrap().is_empty());
+        assert_eq!(counter(server.engine(), &id).await, (2, 3));
+        assert_eq!(counter(client.engine(), &id).await, (2, 3));
+        // The client's own events were dispatched when they committed locally.
+        assert!(seen.lock().unwrap().is_empty());
+
+        let report = client.sync(&context).await.unwrap();
+        assert!(report.applied.is_empty());
+        assert_eq!(report.pulled, 0);
+    }
+
+    #[tokio::test]
+    async fn a_command_against_an_outdated_version_is_reported_as_a_conflict() {
+        let context = CqrsContext::default();
+        let server = server();
+        let alice = client(&server);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let bob = client(&server).with_dispatcher(Box::new(Recorder(seen.clone())));
+
+        let id = create(&alice, &context).await;
+        alice.sync(&context).await.unwrap();
+        assert_eq!(bob.sync(&context).await.unwrap().pulled, 1);
+        increment(&alice, &id, &context).await;
+        alice.sync(&context).await.unwrap();
+
+        increment(&bob, &id, &context).await;
+        let report = bob.sync(&context).await.unwrap();
+        assert!(report.applied.is_empty());
+        let conflict = &report.conflicts[0];
+        assert_eq!((conflict.known_version, conflict.current_version), (1, 2));
+        assert_eq!(conflict.remote_events.len(), 1);
+        assert!(bob.pending().await.unwrap().is_empty());
+        // Bob's increment is gone; Alice's came in.
+        assert_eq!(counter(bob.engine(), &id).await, (1, 2));
+        assert_eq!(counter(server.engine(), &id).await, (1, 2));
+        assert_eq!(*seen.lock().unwrap(), vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn the_rebase_resolver_runs_a_conflicting_command_on_the_remote_events() {
+        let context = CqrsContext::default();
+        let server = server().with_resolver(Arc::new(RebaseConflicts));
+        let alice = client(&server);
+        let bob = client(&server);
+
+        let id = create(&alice, &context).await;
+        alice.sync(&context).await.unwrap();
+        bob.sync(&context).await.unwrap();
+        increment(&alice, &id, &context).await;
+        alice.sync(&context).await.unwrap();
+        increment(&bob, &id, &context).await;
+        increment(&bob, &id, &context).await;
+
+        let report = bob.sync(&context).await.unwrap();
+        assert_eq!(report.applied.len(), 2);
+        assert!(report.conflicts.is_empty());
+        assert_eq!(counter(server.engine(), &id).await, (3, 4));
+        assert_eq!(counter(bob.engine(), &id).await, (3, 4));
+    }
+
+    #[tokio::test]
+    async fn a_push_the_server_already_applied_is_not_applied_twice() {
+        let context = CqrsContext::default();
+        let server = server();
+        let push = SyncPush {
+            commands: vec![SyncCommand {
+                id: "c1".to_string(),
+                aggregate_id: "a1".to_string(),
+                kind: SyncCommandKind::Create,
+                variant: None,
+                command: json!({ "Initialize": { "name": "n" } }),
+                known_version: 0,
+            }],
+        };
+
+        for _ in 0..2 {
+            let result = server.push(push.clone(), &context).await.unwrap();
+            assert!(matches!(
+                &result.outcomes[0],
+                SyncOutcome::Applied { version: 1, .. }
+            ));
+        }
+        assert_eq!(counter(server.engine(), "a1").await, (0, 1));
+    }
+
+    #[tokio::test]
+    async fn a_pull_past_the_server_limit_comes_back_for_the_rest() {
+        let context = CqrsContext::default();
+        let server = server().with_pull_limit(2);
+        let client = client(&server);
+
+        let first = create(&client, &context).await;
+        increment(&client, &first, &context).await;
+        increment(&client, &first, &context).await;
+        let second = create(&client, &context).await;
+
+        let report = client.sync(&context).await.unwrap();
+        assert_eq!(report.applied.len(), 4);
+        assert_eq!(report.pulled, 4);
+        assert!(client.pending().await.unwrap().is_empty());
+        assert_eq!(counter(client.engine(), &first).await, (2, 3));
+        assert_eq!(counter(client.engine(), &second).await, (0, 1));
+    }
+
+    #[tokio::test]
+    async fn a_command_that_does_not_parse_is_rejected() {
+        let context = CqrsContext::default();
+        let server = server();
+        let client = client(&server);
+
+        let err = client
+            .execute_create(json!({ "Nope": {} }), &context)
+            .await
+            .unwrap_err();
+        assert_eq!(err.http_status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(client.pending().await.unwrap().is_empty());
+
+        let push = SyncPush {
+            commands: vec![SyncCommand {
+                id: "c1".to_string(),
+                aggregate_id: "a1".to_string(),
+                kind: SyncCommandKind::Update,
+                variant: None,
+                command: json!("Nope"),
+                known_version: 0,
+            }],
+        };
+        let result = server.push(push, &context).await.unwrap();
+        let SyncOutcome::Rejected(rejection) = &result.outcomes[0] else {
+            panic!("expected a rejection, got {:?}", result.outcomes[0]);
+        };
+        assert_eq!(
+            rejection.error.http_status(),
+            http::StatusCode::UNPROCESSABLE_ENTITY
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use crate::indexeddb::{committed, js_error, key, parse_all, parse_one, prefix_range, settle};
+use crate::sync::{PendingCommand, SyncCheckpoint, SyncOutbox};
+use crate::{Aggregate, CqrsError};
+use wasm_bindgen::JsValue;
+use web_sys::IdbTransactionMode;
+
+pub use crate::indexeddb::{IndexedDb, IndexedDbSchema};
+
+fn command_key(id: &str) -> JsValue {
+    key(&[JsValue::from_str("command"), JsValue::from_str(id)])
+}
+
+fn checkpoint_key() -> JsValue {
+    key(&[JsValue::from_str("checkpoint")])
+}
+
+/// A client's pending commands and checkpoint in the browser's IndexedDB, so that the
+/// commands issued offline survive a reload until a push delivers them.
+///
+/// The store is named `{TYPE}_sync_outbox`, and must be declared on the
+/// [`IndexedDbSchema`] the database is opened with —
+/// [`IndexedDbSchema::with_sync_outbox`] declares it. The replica is an
+/// [`IndexedDbPersist`](crate::es::indexeddb::IndexedDbPersist) of the same database.
+#[derive(Clone, Debug)]
+pub struct IndexedDbSyncOutbox<A>
+where
+    A: Aggregate,
+{
+    _phantom: std::marker::PhantomData<A>,
+    database: IndexedDb,
+    store_name: String,
+}
+
+impl<A> IndexedDbSyncOutbox<A>
+where
+    A: Aggregate,
+{
+    #[must_use]
+    pub fn new(database: IndexedDb) -> Self {
+        Self {
+            _phantom: std::marker::PhantomData,
+            database,
+            store_name: format!("{}_sync_outbox", A::TYPE),
+        }
+    }
+
+    pub fn store_name(&self) -> &str {
+        self.store_name.as_str()
+    }
+
+    async fn put(&self, value: String, key: &JsValue) -> Result<(), CqrsError> {
+        let transaction = self
+            .database
+            .transaction(&[&self.store_name], IdbTransactionMode::Readwrite)?;
+        transaction
+            .object_store(&self.store_name)
+            .and_then(|store| store.put_with_key(&JsValue::from_str(&value), key))
+            .map_err(js_error)?;
+        committed(&transaction).await
+    }
+}
+
+cqrs_async_trait! {
+impl<A> SyncOutbox<A> for IndexedDbSyncOutbox<A>
+where
+    A: Aggregate + 'static,
+{
+    async fn save(&self, command: PendingCommand<A>) -> Result<(), CqrsError> {
+        let json = serde_json::to_string(&command).map_err(CqrsError::serialization_error)?;
+        self.put(json, &command_key(&command.id)).await
+    }
+
+    async fn find(&self, id: &str) -> Result<Option<PendingCommand<A>>, CqrsError> {
+        let store = self
+            .database
+            .store(&self.store_name, IdbTransactionMode::Readonly)?;
+        let request = store.get(&command_key(id)).map_err(js_error)?;
+        parse_one(settle(&request).await?)
+    }
+
+    async fn list(&self) -> Result<Vec<PendingCommand<A>>, CqrsError> {
+        let store = self
+            .database
+            .store(&self.store_name, IdbTransactionMode::Readonly)?;
+        let range = prefix_range(&[JsValue::from_str("command")])?;
+        let request = store.get_all_with_key(&range).map_err(js_error)?;
+        let mut commands: Vec<PendingCommand<A>> = parse_all(settle(&request).await?)?;
+        commands.sort_by_key(|command| command.sequence);
+        Ok(commands)
+    }
+
+    async fn remove(&self, id: &str) -> Result<(), CqrsError> {
+        let transaction = self
+            .database
+            .transaction(&[&self.store_name], IdbTransactionMode::Readwrite)?;
+        transaction
+            .object_store(&self.store_name)
+            .and_then(|store| store.delete(&command_key(id)))
+            .map_err(js_error)?;
+        committed(&transaction).await
+    }
+
+    async fn checkpoint(&self) -> Result<SyncCheckpoint, CqrsError> {
+        let store = self
+            .database
+            .store(&self.store_name, IdbTransactionMode::Readonly)?;
+        let request = store.get(&checkpoint_key()).map_err(js_error)?;
+        Ok(parse_one(settle(&request).await?)?.unwrap_or_default())
+    }
+
+    async fn save_checkpoint(&self, checkpoint: SyncCheckpoint) -> Result<(), CqrsError> {
+        let json = serde_json::to_string(&checkpoint).map_err(CqrsError::serialization_error)?;
+        self.put(json, &checkpoint_key()).await
+    }
+}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::SyncCommandKind;
+    use crate::testing::TestAggregate;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn pending(id: &str, sequence: u64) -> PendingCommand<TestAggregate> {
+        PendingCommand {
+            id: id.to_string(),
+            sequence,
+            kind: SyncCommandKind::Update,
+            variant: None,
+            command: json!("Increment"),
+            aggregate_id: Some("a1".to_string()),
+            events: Some(vec![]),
+            recorded_at: chrono::Utc::now(),
+            applied_version: None,
+        }
+    }
+
+    #[wasm_bindgen_test]
+    async fn pending_commands_and_the_checkpoint_survive_in_the_database() {
+        let database = IndexedDbSchema::new(&format!("cqrs-test-{}", uuid::Uuid::new_v4()))
+            .with_sync_outbox::<TestAggregate>()
+            .open()
+            .await
+            .unwrap();
+        let outbox = IndexedDbSyncOutbox::<TestAggregate>::new(database.clone());
+        outbox.save(pending("c2", 2)).await.unwrap();
+        outbox.save(pending("c1", 1)).await.unwrap();
+        outbox
+            .save_checkpoint(SyncCheckpoint {
+                versions: [("a1".to_string(), 3)].into(),
+                pulled_at: None,
+            })
+            .await
+            .unwrap();
+
+        let reopened = IndexedDbSyncOutbox::<TestAggregate>::new(database);
+        let ids: Vec<_> = reopened
+            .list()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(ids, ["c1", "c2"]);
+        reopened.remove("c1").await.unwrap();
+        assert!(reopened.find("c1").await.unwrap().is_none());
+        assert!(reopened.find("c2").await.unwrap().is_some());
+        assert_eq!(reopened.checkpoint().await.unwrap().versions["a1"], 3);
+    }
+}
@@ -0,0 +1,207 @@
+//! Offline-first synchronization between a client event store and the server.
+//!
+//! The client runs its own [`CqrsCommandEngine`](crate::CqrsCommandEngine) — in the
+//! browser, over IndexedDB — on a [`SyncEventStore`]: the events the server confirmed,
+//! pulled into a replica, with the client's own commands on top of them, kept in a
+//! [`SyncOutbox`] until the server has them. A [`SyncClient`] runs the commands and
+//! synchronizes:
+//!
+//! 1. **push** — each pending command, as the JSON it was issued with, goes to the
+//!    server, which runs it again through its own engine: policies, validation and
+//!    handlers decide there, not on the device. The server's events replace the ones
+//!    the client derived.
+//! 2. **pull** — the events committed on the server since the versions the client
+//!    holds, its own included, land in the replica, a bounded number per request. The
+//!    server's read policy decides which aggregates a client sees.
+//!
+//! A command recorded against a version the server has since moved past is a
+//! [`SyncConflict`]. The server's [`ConflictResolver`] decides what becomes of it:
+//! report it and drop the command ([`RejectConflicts`], the default), or run it on top
+//! of the remote events all the same ([`RebaseConflicts`]), or anything in between.
+//! The [`SyncReport`] of a synchronization names every command the server refused.
+//!
+//! A [`SyncTransport`] carries the exchange: a [`SyncServer`] in process, or HTTP to
+//! `CQRSSyncRouter` with the `rest` feature.
+
+mod client;
+mod outbox;
+mod server;
+mod store;
+
+#[cfg(all(feature = "indexeddb", target_arch = "wasm32"))]
+pub mod indexeddb;
+
+pub use client::*;
+pub use outbox::*;
+pub use server::*;
+pub use store::*;
+
+use crate::{Aggregate, CqrsContext, CqrsError, EventEnvelope, MaybeSend, MaybeSync};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+#[cfg(feature = "utoipa")]
+use utoipa::ToSchema;
+
+/// The metadata key the events of a synchronized command carry its id under, on the
+/// client and on the server alike. A push the server already applied — its answer was
+/// lost on the way back — is recognized by it, and not applied twice.
+pub const SYNC_COMMAND_METADATA: &str = "sync_command_id";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum SyncCommandKind {
+    Create,
+    Update,
+}
+
+/// A command the client ran offline, as it pushes it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct SyncCommand {
+    /// Chosen by the client; see [`SYNC_COMMAND_METADATA`].
+    pub id: String,
+    pub aggregate_id: String,
+    pub kind: SyncCommandKind,
+    /// The tag of the command variant; `None` when untagged. The server reads the tag
+    /// off `command` and refuses the command when it differs from this one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+    /// The command, as `CQRSWriteRouter` would take it.
+    #[cfg_attr(feature = "utoipa", schema(value_type = Object))]
+    pub command: JsonValue,
+    /// The last version of the aggregate the client had from the server: `0` for an
+    /// aggregate it created. Later commands of the same aggregate in one push follow
+    /// the versions the earlier ones were applied at.
+    pub known_version: usize,
+}
+
+/// The commands a client pushes, oldest first.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPush {
+    pub commands: Vec<SyncCommand>,
+}
+
+/// A command recorded at `known_version` when the server had moved on: the events the
+/// client had not seen when it ran the command.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", bound = "")]
+pub struct SyncConflict<A>
+where
+    A: Aggregate,
+{
+    pub command_id: String,
+    pub aggregate_id: String,
+    pub known_version: usize,
+    /// The version of the aggregate on the server.
+    pub current_version: usize,
+    pub remote_events: Vec<EventEnvelope<A>>,
+}
+
+/// A command the server refused: a policy, a validation, a handler, a command that does
+/// not parse, or a create of an aggregate the server already has.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncRejection {
+    pub command_id: String,
+    pub aggregate_id: String,
+    pub error: CqrsError,
+}
+
+/// What the server made of one pushed command.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "camelCase", bound = "")]
+pub enum SyncOutcome<A>
+where
+    A: Aggregate,
+{
+    /// The command ran on the server; its last event has `version`.
+    #[serde(rename_all = "camelCase")]
+    Applied {
+        command_id: String,
+        aggregate_id: String,
+        version: usize,
+    },
+    /// The command was recorded against an outdated version, and the resolver did not
+    /// let it through.
+    Conflict(SyncConflict<A>),
+    Rejected(SyncRejection),
+}
+
+impl<A> SyncOutcome<A>
+where
+    A: Aggregate,
+{
+    pub fn command_id(&self) -> &str {
+        match self {
+            SyncOutcome::Applied { command_id, .. } => command_id,
+            SyncOutcome::Conflict(conflict) => &conflict.command_id,
+            SyncOutcome::Rejected(rejection) => &rejection.command_id,
+        }
+    }
+}
+
+/// The outcome of each pushed command, in the order they were pushed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", bound = "")]
+pub struct SyncPushResult<A>
+where
+    A: Aggregate,
+{
+    pub outcomes: Vec<SyncOutcome<A>>,
+}
+
+/// What a client asks the server for: the events of the aggregates with an event
+/// stamped at or after `since` — of every aggregate when `None` — after the version it
+/// holds of each, from `known_versions`; all of them for one it does not know.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPull {
+    #[serde(default)]
+    pub known_versions: BTreeMap<String, usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "utoipa", schema(value_type = Option<String>))]
+    pub since: Option<DateTime<Utc>>,
+}
+
+/// The events of a pull, by aggregate and then version: as many as the server's pull
+/// limit, the first of them when there are more.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", bound = "")]
+pub struct SyncPullResult<A>
+where
+    A: Aggregate,
+{
+    pub events: Vec<EventEnvelope<A>>,
+    /// When the server began reading, less its [pull overlap](SyncServer::with_pull_overlap):
+    /// the `since` of the next pull.
+    pub pulled_at: DateTime<Utc>,
+    /// Set when the pull stopped at the limit: the `since` of a pull for the rest, with
+    /// the versions just pulled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub continue_from: Option<DateTime<Utc>>,
+}
+
+cqrs_async_trait! {
+/// Carries a client's pushes and pulls to the server.
+pub trait SyncTransport<A>: MaybeSend + MaybeSync
+where
+    A: Aggregate + 'static,
+{
+    async fn push(&self, push: SyncPush, context: &CqrsContext) -> Result<SyncPushResult<A>, CqrsError>;
+
+    async fn pull(&self, pull: SyncPull, context: &CqrsContext) -> Result<SyncPullResult<A>, CqrsError>;
+}
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub type DynSyncTransport<A> = Arc<dyn SyncTransport<A> + Send + Sync>;
+#[cfg(target_arch = "wasm32")]
+pub type DynSyncTransport<A> = Arc<dyn SyncTransport<A>>;
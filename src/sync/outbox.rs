@@ -0,0 +1,172 @@
+use crate::sync::{SyncCommand, SyncCommandKind};
+use crate::{Aggregate, CqrsError, EventEnvelope, MaybeSend, MaybeSync};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// A command the client ran that the server does not have yet — or has, but whose events
+/// the client has not pulled.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", bound = "")]
+pub struct PendingCommand<A>
+where
+    A: Aggregate,
+{
+    pub id: String,
+    /// The order the commands were issued in, which a push keeps.
+    pub sequence: u64,
+    pub kind: SyncCommandKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+    pub command: JsonValue,
+    /// Known once the command committed: a create's aggregate id is only chosen then.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aggregate_id: Option<String>,
+    /// The events the command committed locally; `None` while the engine runs it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub events: Option<Vec<EventEnvelope<A>>>,
+    pub recorded_at: DateTime<Utc>,
+    /// The version of its last event on the server, once a push applied it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub applied_version: Option<usize>,
+}
+
+impl<A> PendingCommand<A>
+where
+    A: Aggregate,
+{
+    /// The aggregate and events of the command, once it committed locally.
+    pub fn committed(&self) -> Option<(&str, &[EventEnvelope<A>])> {
+        Some((self.aggregate_id.as_deref()?, self.events.as_deref()?))
+    }
+
+    /// The command as a push carries it.
+    pub fn to_sync_command(&self, known_version: usize) -> Option<SyncCommand> {
+        Some(SyncCommand {
+            id: self.id.clone(),
+            aggregate_id: self.aggregate_id.clone()?,
+            kind: self.kind,
+            variant: self.variant.clone(),
+            command: self.command.clone(),
+            known_version,
+        })
+    }
+}
+
+/// Where a client's pulls have got to.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncCheckpoint {
+    /// The last version the replica holds of each aggregate.
+    #[serde(default)]
+    pub versions: BTreeMap<String, usize>,
+    /// The `pulledAt` of the last pull.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pulled_at: Option<DateTime<Utc>>,
+}
+
+cqrs_async_trait! {
+/// Keeps a client's pending commands, and its checkpoint, across restarts.
+pub trait SyncOutbox<A>: MaybeSend + MaybeSync
+where
+    A: Aggregate + 'static,
+{
+    /// Saves `command`, replacing the one of the same id.
+    async fn save(&self, command: PendingCommand<A>) -> Result<(), CqrsError>;
+
+    async fn find(&self, id: &str) -> Result<Option<PendingCommand<A>>, CqrsError>;
+
+    /// Every pending command, in `sequence` order.
+    async fn list(&self) -> Result<Vec<PendingCommand<A>>, CqrsError>;
+
+    async fn remove(&self, id: &str) -> Result<(), CqrsError>;
+
+    async fn checkpoint(&self) -> Result<SyncCheckpoint, CqrsError>;
+
+    async fn save_checkpoint(&self, checkpoint: SyncCheckpoint) -> Result<(), CqrsError>;
+}
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub type DynSyncOutbox<A> = Arc<dyn SyncOutbox<A> + Send + Sync>;
+#[cfg(target_arch = "wasm32")]
+pub type DynSyncOutbox<A> = Arc<dyn SyncOutbox<A>>;
+
+/// Pending commands held in process memory, and lost with it.
+#[derive(Clone, Debug)]
+pub struct InMemorySyncOutbox<A>
+where
+    A: Aggregate,
+{
+    commands: Arc<RwLock<BTreeMap<String, PendingCommand<A>>>>,
+    checkpoint: Arc<RwLock<SyncCheckpoint>>,
+}
+
+impl<A> Default for InMemorySyncOutbox<A>
+where
+    A: Aggregate,
+{
+    fn default() -> Self {
+        Self {
+            commands: Arc::new(RwLock::new(BTreeMap::new())),
+            checkpoint: Arc::new(RwLock::new(SyncCheckpoint::default())),
+        }
+    }
+}
+
+impl<A> InMemorySyncOutbox<A>
+where
+    A: Aggregate,
+{
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+cqrs_async_trait! {
+impl<A> SyncOutbox<A> for InMemorySyncOutbox<A>
+where
+    A: Aggregate + 'static,
+{
+    async fn save(&self, command: PendingCommand<A>) -> Result<(), CqrsError> {
+        self.commands
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(command.id.clone(), command);
+        Ok(())
+    }
+
+    async fn find(&self, id: &str) -> Result<Option<PendingCommand<A>>, CqrsError> {
+        Ok(self.commands.read().unwrap_or_else(PoisonError::into_inner).get(id).cloned())
+    }
+
+    async fn list(&self) -> Result<Vec<PendingCommand<A>>, CqrsError> {
+        let mut commands: Vec<_> = self
+            .commands
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .cloned()
+            .collect();
+        commands.sort_by_key(|command| command.sequence);
+        Ok(commands)
+    }
+
+    async fn remove(&self, id: &str) -> Result<(), CqrsError> {
+        self.commands.write().unwrap_or_else(PoisonError::into_inner).remove(id);
+        Ok(())
+    }
+
+    async fn checkpoint(&self) -> Result<SyncCheckpoint, CqrsError> {
+        Ok(self.checkpoint.read().unwrap_or_else(PoisonError::into_inner).clone())
+    }
+
+    async fn save_checkpoint(&self, checkpoint: SyncCheckpoint) -> Result<(), CqrsError> {
+        *self.checkpoint.write().unwrap_or_else(PoisonError::into_inner) = checkpoint;
+        Ok(())
+    }
+}
+}
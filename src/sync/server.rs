@@ -0,0 +1,871 @@
+use crate::sync::{
+    SyncCommand, SyncCommandKind, SyncConflict, SyncOutcome, SyncPull, SyncPullResult, SyncPush,
+    SyncPushResult, SyncRejection, SyncTransport, SYNC_COMMAND_METADATA,
+};
+use crate::policy::command_tag;
+use crate::{
+    Aggregate, CommandHandler, CqrsCommandEngine, CqrsContext, CqrsError, EventEnvelope,
+    EventFilter, MaybeSend, MaybeSync, Policy, USER_ID_METADATA,
+};
+use chrono::TimeDelta;
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+/// How many times a pushed command runs again when another writer commits to its
+/// aggregate between the load and the commit.
+const SYNC_RETRIES: usize = 3;
+
+/// How many events a pull reads at a time to find the aggregates changed since `since`.
+const PULL_PAGE_SIZE: usize = 100;
+
+/// How far before it began reading a pull answers it read from: an event stamped before
+/// then, by a transaction that commits after the pull read, is caught by the next pull.
+pub const DEFAULT_PULL_OVERLAP: TimeDelta = TimeDelta::seconds(60);
+
+/// How many events a pull answers at most; the client pulls again for the rest.
+pub const DEFAULT_PULL_LIMIT: usize = 1000;
+
+/// What becomes of a command pushed against an outdated version.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictResolution {
+    /// Report the conflict; the command is dropped.
+    Reject,
+    /// Run the command on the aggregate as the server has it: its handler decides.
+    Rebase,
+}
+
+/// Decides, on the server, what becomes of each [`SyncConflict`].
+pub trait ConflictResolver<A>: MaybeSend + MaybeSync
+where
+    A: Aggregate,
+{
+    fn resolve(
+        &self,
+        conflict: &SyncConflict<A>,
+        command: &SyncCommand,
+        context: &CqrsContext,
+    ) -> ConflictResolution;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub type DynConflictResolver<A> = Arc<dyn ConflictResolver<A> + Send + Sync>;
+#[cfg(target_arch = "wasm32")]
+pub type DynConflictResolver<A> = Arc<dyn ConflictResolver<A>>;
+
+impl<A, F> ConflictResolver<A> for F
+where
+    A: Aggregate,
+    F: Fn(&SyncConflict<A>, &SyncCommand, &CqrsContext) -> ConflictResolution
+        + MaybeSend
+        + MaybeSync,
+{
+    fn resolve(
+        &self,
+        conflict: &SyncConflict<A>,
+        command: &SyncCommand,
+        context: &CqrsContext,
+    ) -> ConflictResolution {
+        self(conflict, command, context)
+    }
+}
+
+/// Reports every conflict: the client finds out, and issues the command again if it
+/// still makes sense. The default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RejectConflicts;
+
+impl<A: Aggregate> ConflictResolver<A> for RejectConflicts {
+    fn resolve(&self, _: &SyncConflict<A>, _: &SyncCommand, _: &CqrsContext) -> ConflictResolution {
+        ConflictResolution::Reject
+    }
+}
+
+/// Runs every command on top of the remote events: last writer wins, as far as the
+/// handlers allow.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RebaseConflicts;
+
+impl<A: Aggregate> ConflictResolver<A> for RebaseConflicts {
+    fn resolve(&self, _: &SyncConflict<A>, _: &SyncCommand, _: &CqrsContext) -> ConflictResolution {
+        ConflictResolution::Rebase
+    }
+}
+
+/// The server side of synchronization, over the engine of an aggregate type: pushed
+/// commands run through the engine — its policies included, under the pushing user's
+/// context — and pulls read its event store.
+///
+/// ```rust,ignore
+/// let server = SyncServer::new(engine.clone()).with_resolver(Arc::new(RebaseConflicts));
+/// ```
+///
+/// A command is applied once: its events carry its id under [`SYNC_COMMAND_METADATA`],
+/// and a push of a command the server already has answers where it was applied.
+///
+/// A pull answers the events of every aggregate of the type unless a
+/// [read policy](Self::with_read_policy) scopes it.
+pub struct SyncServer<A>
+where
+    A: Aggregate + CommandHandler + 'static,
+    A::Error: Into<CqrsError>,
+{
+    engine: Arc<CqrsCommandEngine<A>>,
+    resolver: DynConflictResolver<A>,
+    pull_overlap: TimeDelta,
+    pull_limit: usize,
+    read_policy: Option<Policy<A>>,
+}
+
+impl<A> Clone for SyncServer<A>
+where
+    A: Aggregate + CommandHandler + 'static,
+    A::Error: Into<CqrsError>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            engine: self.engine.clone(),
+            resolver: self.resolver.clone(),
+            pull_overlap: self.pull_overlap,
+            pull_limit: self.pull_limit,
+            read_policy: self.read_policy.clone(),
+        }
+    }
+}
+
+impl<A> SyncServer<A>
+where
+    A: Aggregate + CommandHandler + 'static,
+    A::Error: Into<CqrsError>,
+{
+    #[must_use]
+    pub fn new(engine: Arc<CqrsCommandEngine<A>>) -> Self {
+        Self {
+            engine,
+            resolver: Arc::new(RejectConflicts),
+            pull_overlap: DEFAULT_PULL_OVERLAP,
+            pull_limit: DEFAULT_PULL_LIMIT,
+            read_policy: None,
+        }
+    }
+
+    /// Who decides on conflicts, instead of [`RejectConflicts`].
+    #[must_use]
+    pub fn with_resolver(mut self, resolver: DynConflictResolver<A>) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// How long a transaction may take to commit after stamping its events, instead of
+    /// [`DEFAULT_PULL_OVERLAP`]: pulls read again that far back from where the previous
+    /// one began.
+    #[must_use]
+    pub fn with_pull_overlap(mut self, overlap: TimeDelta) -> Self {
+        self.pull_overlap = overlap;
+        self
+    }
+
+    /// How many events a pull answers at most, instead of [`DEFAULT_PULL_LIMIT`]; at
+    /// least one.
+    #[must_use]
+    pub fn with_pull_limit(mut self, limit: usize) -> Self {
+        self.pull_limit = limit.max(1);
+        self
+    }
+
+    /// Which aggregates a caller pulls: its context checks refuse the pull, and an
+    /// aggregate failing its predicates, as the server has it, is left out of the pull.
+    #[must_use]
+    pub fn with_read_policy(mut self, policy: Policy<A>) -> Self {
+        self.read_policy = Some(policy);
+        self
+    }
+
+    pub fn engine(&self) -> &Arc<CqrsCommandEngine<A>> {
+        &self.engine
+    }
+
+    fn metadata(command_id: &str, context: &CqrsContext) -> HashMap<String, String> {
+        HashMap::from_iter(vec![
+            (USER_ID_METADATA.to_string(), context.current_user()),
+            ("request_id".to_string(), context.request_id()),
+            (SYNC_COMMAND_METADATA.to_string(), command_id.to_string()),
+        ])
+    }
+
+    async fn events_after(
+        &self,
+        aggregate_id: &str,
+        version: usize,
+        context: &CqrsContext,
+    ) -> Result<Vec<EventEnvelope<A>>, CqrsError> {
+        self.events_after_at_most(aggregate_id, version, usize::MAX, context)
+            .await
+    }
+
+    async fn events_after_at_most(
+        &self,
+        aggregate_id: &str,
+        version: usize,
+        limit: usize,
+        context: &CqrsContext,
+    ) -> Result<Vec<EventEnvelope<A>>, CqrsError> {
+        self.engine
+            .store()
+            .load_events_from_version(aggregate_id, version, context)
+            .await?
+            .take(limit)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect()
+    }
+
+    /// Whether the caller may pull the events of `aggregate_id`.
+    async fn readable(&self, aggregate_id: &str, context: &CqrsContext) -> Result<bool, CqrsError> {
+        let Some(policy) = &self.read_policy else {
+            return Ok(true);
+        };
+        let (aggregate, _) = self
+            .engine
+            .store()
+            .load_aggregate(aggregate_id, context)
+            .await?;
+        Ok(policy.authorize(context, &aggregate).is_ok())
+    }
+
+    /// Checks `command` against its policy, on the aggregate as the server has it, and
+    /// answers whether the caller may also read that aggregate's events. Run before
+    /// anything of the aggregate is read back: a conflict report must not show a caller
+    /// what it could neither have changed nor pulled.
+    async fn authorize(
+        &self,
+        command: &SyncCommand,
+        context: &CqrsContext,
+    ) -> Result<bool, CqrsError> {
+        let policy = self.policy(command)?;
+        if policy.is_none() && self.read_policy.is_none() {
+            return Ok(true);
+        }
+        if let Some(policy) = policy {
+            policy.authorize_context(context)?;
+        }
+        let store = self.engine.store();
+        let (aggregate, _) = match command.kind {
+            // The aggregate of a create is checked as the engine checks it: initialized
+            // with its id. It exists already only if a former push of this very command
+            // made it, or the create is refused when it runs.
+            SyncCommandKind::Create => {
+                match store
+                    .initialize_aggregate(&command.aggregate_id, context)
+                    .await
+                {
+                    Err(e) if e.code == CqrsError::aggregate_already_exists("").code => {
+                        store.load_aggregate(&command.aggregate_id, context).await?
+                    }
+                    initialized => initialized?,
+                }
+            }
+            SyncCommandKind::Update => store.load_aggregate(&command.aggregate_id, context).await?,
+        };
+        if let Some(policy) = policy {
+            policy.authorize(context, &aggregate)?;
+        }
+        Ok(self
+            .read_policy
+            .as_ref()
+            .is_none_or(|policy| policy.authorize(context, &aggregate).is_ok()))
+    }
+
+    /// The policy of `command`, picked by the engine off the typed command. The
+    /// client's label is only checked against its tag, so that a command cannot pass
+    /// for one under a weaker policy.
+    fn policy(&self, command: &SyncCommand) -> Result<Option<&Policy<A>>, CqrsError> {
+        let unprocessable = |e: serde_json::Error| CqrsError::unprocessable(e.to_string());
+        let policies = self.engine.policies();
+        let (tag, policy) = match command.kind {
+            SyncCommandKind::Create => {
+                let create: A::CreateCommand =
+                    serde_json::from_value(command.command.clone()).map_err(unprocessable)?;
+                (command_tag(&create), policies.policy_of(&create))
+            }
+            SyncCommandKind::Update => {
+                let update: A::UpdateCommand =
+                    serde_json::from_value(command.command.clone()).map_err(unprocessable)?;
+                (command_tag(&update), policies.policy_of(&update))
+            }
+        };
+        if tag != command.variant {
+            let label = |variant: &Option<String>| {
+                variant
+                    .as_deref()
+                    .map_or_else(|| "untagged".to_string(), |v| format!("'{v}'"))
+            };
+            return Err(CqrsError::unprocessable(format!(
+                "command '{}' is labelled {} but is {}",
+                command.id,
+                label(&command.variant),
+                label(&tag)
+            )));
+        }
+        policy
+    }
+
+    async fn execute(&self, command: &SyncCommand, context: &CqrsContext) -> Result<(), CqrsError> {
+        let metadata = Self::metadata(&command.id, context);
+        let unprocessable = |e: serde_json::Error| CqrsError::unprocessable(e.to_string());
+        match command.kind {
+            SyncCommandKind::Create => {
+                let create =
+                    serde_json::from_value(command.command.clone()).map_err(unprocessable)?;
+                self.engine
+                    .execute_create_variant_with_id(
+                        &command.aggregate_id,
+                        create,
+                        metadata,
+                        context,
+                    )
+                    .await
+                    .map(|_| ())
+            }
+            SyncCommandKind::Update => {
+                let update =
+                    serde_json::from_value(command.command.clone()).map_err(unprocessable)?;
+                self.engine
+                    .execute_update_variant(&command.aggregate_id, update, metadata, context)
+                    .await
+            }
+        }
+    }
+
+    /// Applies one command, expected at `expected` — the client's known version, or the
+    /// version an earlier command of the same push left the aggregate at.
+    async fn apply(
+        &self,
+        command: SyncCommand,
+        expected: usize,
+        context: &CqrsContext,
+    ) -> Result<SyncOutcome<A>, CqrsError> {
+        let readable = match self.authorize(&command, context).await {
+            Ok(readable) => readable,
+            Err(e) if e.http_status().is_server_error() => return Err(e),
+            Err(error) => {
+                return Ok(SyncOutcome::Rejected(SyncRejection {
+                    command_id: command.id,
+                    aggregate_id: command.aggregate_id,
+                    error,
+                }));
+            }
+        };
+        let mut rebase = false;
+        for attempt in 0..=SYNC_RETRIES {
+            let remote = self
+                .events_after(&command.aggregate_id, expected, context)
+                .await?;
+            if let Some(version) = applied_version(&remote, &command.id) {
+                debug!(command_id = %command.id, "Pushed command already applied");
+                return Ok(SyncOutcome::Applied {
+                    command_id: command.id,
+                    aggregate_id: command.aggregate_id,
+                    version,
+                });
+            }
+            if let (Some(last), false) = (remote.last(), rebase) {
+                let conflict = SyncConflict {
+                    command_id: command.id.clone(),
+                    aggregate_id: command.aggregate_id.clone(),
+                    known_version: expected,
+                    current_version: last.version,
+                    remote_events: if readable { remote } else { Vec::new() },
+                };
+                match self.resolver.resolve(&conflict, &command, context) {
+                    ConflictResolution::Reject => return Ok(SyncOutcome::Conflict(conflict)),
+                    ConflictResolution::Rebase => rebase = true,
+                }
+            }
+            match self.execute(&command, context).await {
+                Ok(()) => {
+                    let events = self
+                        .events_after(&command.aggregate_id, expected, context)
+                        .await?;
+                    let version = applied_version(&events, &command.id)
+                        .or(events.last().map(|e| e.version))
+                        .unwrap_or(expected);
+                    return Ok(SyncOutcome::Applied {
+                        command_id: command.id,
+                        aggregate_id: command.aggregate_id,
+                        version,
+                    });
+                }
+                Err(e) if is_concurrency_error(&e) => {
+                    warn!(command_id = %command.id, attempt, "Pushed command raced another writer");
+                }
+                // The server's fault: the push fails, and the client pushes again later.
+                Err(e) if e.http_status().is_server_error() => return Err(e),
+                Err(error) => {
+                    return Ok(SyncOutcome::Rejected(SyncRejection {
+                        command_id: command.id,
+                        aggregate_id: command.aggregate_id,
+                        error,
+                    }));
+                }
+            }
+        }
+        Err(CqrsError::concurrency_error())
+    }
+}
+
+/// The version of the last event `command_id` committed, among `events`.
+fn applied_version<A: Aggregate>(events: &[EventEnvelope<A>], command_id: &str) -> Option<usize> {
+    events
+        .iter()
+        .rev()
+        .find(|e| e.metadata.get(SYNC_COMMAND_METADATA).map(String::as_str) == Some(command_id))
+        .map(|e| e.version)
+}
+
+fn is_concurrency_error(e: &CqrsError) -> bool {
+    e.code == CqrsError::concurrency_error().code
+}
+
+cqrs_async_trait! {
+impl<A> SyncTransport<A> for SyncServer<A>
+where
+    A: Aggregate + CommandHandler + 'static,
+    A::Error: Into<CqrsError>,
+{
+    /// Applies the commands one after the other, in the order pushed.
+    async fn push(&self, push: SyncPush, context: &CqrsContext) -> Result<SyncPushResult<A>, CqrsError> {
+        let mut versions: HashMap<String, usize> = HashMap::new();
+        let mut outcomes = Vec::with_capacity(push.commands.len());
+        for command in push.commands {
+            let expected = versions
+                .get(&command.aggregate_id)
+                .copied()
+                .unwrap_or(command.known_version);
+            let outcome = self.apply(command, expected, context).await?;
+            if let SyncOutcome::Applied { aggregate_id, version, .. } = &outcome {
+                versions.insert(aggregate_id.clone(), *version);
+            }
+            outcomes.push(outcome);
+        }
+        Ok(SyncPushResult { outcomes })
+    }
+
+    /// Reads the events stamped since `since` for the aggregates they changed, then each
+    /// of those from the version the client holds: an aggregate without an event since
+    /// has nothing new. Past the pull limit, the result says where to pull again from.
+    async fn pull(&self, pull: SyncPull, context: &CqrsContext) -> Result<SyncPullResult<A>, CqrsError> {
+        if let Some(policy) = &self.read_policy {
+            policy.authorize_context(context)?;
+        }
+        // Events are stamped before they commit: those of transactions still running now
+        // are read by the next pull, which starts this far back.
+        let pulled_at = context.now() - self.pull_overlap;
+        let mut filter = EventFilter {
+            from: pull.since,
+            ..EventFilter::default()
+        };
+        // Each aggregate is checked against the read policy once, at its first event.
+        let mut readable: HashMap<String, bool> = HashMap::new();
+        let mut events = Vec::new();
+        let mut continue_from = None;
+        let mut page = 1;
+        'journal: loop {
+            let (journal, _) = self
+                .engine
+                .store()
+                .load_events_paged(&filter, page, PULL_PAGE_SIZE, context)
+                .await?;
+            for event in &journal {
+                if readable.contains_key(&event.aggregate_id) {
+                    continue;
+                }
+                let budget = self.pull_limit - events.len();
+                if budget == 0 {
+                    continue_from = Some(event.at);
+                    break 'journal;
+                }
+                let allowed = self.readable(&event.aggregate_id, context).await?;
+                readable.insert(event.aggregate_id.clone(), allowed);
+                if !allowed {
+                    continue;
+                }
+                let version = pull.known_versions.get(&event.aggregate_id).copied().unwrap_or(0);
+                let mut after = self
+                    .events_after_at_most(&event.aggregate_id, version, budget + 1, context)
+                    .await?;
+                if after.len() > budget {
+                    // The client pulls the rest of the aggregate from the version it
+                    // then holds.
+                    after.truncate(budget);
+                    events.extend(after);
+                    continue_from = Some(event.at);
+                    break 'journal;
+                }
+                events.extend(after);
+            }
+            let Some(last) = journal.last().filter(|_| journal.len() == PULL_PAGE_SIZE) else {
+                break;
+            };
+            // The next page is keyed on the time of the last event read, which the
+            // journal is ordered by: only events stamped at that same instant are paged
+            // by offset.
+            if filter.from == Some(last.at) {
+                page += 1;
+            } else {
+                filter.from = Some(last.at);
+                page = 1;
+            }
+        }
+        Ok(SyncPullResult {
+            events,
+            pulled_at,
+            continue_from,
+        })
+    }
+}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::es::inmemory::InMemoryPersist;
+    use crate::es::storage::EventStoreStorage;
+    use crate::es::EventStoreImpl;
+    use crate::testing::{CreateCommand, TestAggregate, TestEvent, UpdateCommand};
+    use crate::CommandPolicies;
+    use chrono::{DateTime, Utc};
+    use std::collections::{BTreeMap, HashSet};
+
+    fn server(persist: &InMemoryPersist<TestAggregate>) -> SyncServer<TestAggregate> {
+        let store = EventStoreImpl::new(persist.clone());
+        SyncServer::new(Arc::new(CqrsCommandEngine::new(
+            store,
+            vec![],
+            (),
+            Box::new(|_| {}),
+        )))
+    }
+
+    /// Commits an event straight to the journal, stamped `at`.
+    async fn commit(
+        persist: &InMemoryPersist<TestAggregate>,
+        aggregate_id: &str,
+        version: usize,
+        at: DateTime<Utc>,
+    ) {
+        let envelope = EventEnvelope {
+            event_id: format!("{aggregate_id}-v{version}"),
+            aggregate_id: aggregate_id.to_string(),
+            version,
+            payload: TestEvent::Incremented,
+            metadata: HashMap::new(),
+            at,
+        };
+        let mut session = persist.start_session().await.unwrap();
+        persist
+            .save_events(vec![envelope], &mut session)
+            .await
+            .unwrap();
+        persist.close_session(session).await.unwrap();
+    }
+
+    fn pulled(result: &SyncPullResult<TestAggregate>) -> Vec<(&str, usize)> {
+        result
+            .events
+            .iter()
+            .map(|e| (e.aggregate_id.as_str(), e.version))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn an_event_committed_after_a_pull_read_is_caught_by_the_next_one() {
+        for (overlap, caught) in [(DEFAULT_PULL_OVERLAP, true), (TimeDelta::zero(), false)] {
+            let persist = InMemoryPersist::<TestAggregate>::new();
+            let server = server(&persist).with_pull_overlap(overlap);
+            let context = CqrsContext::default();
+            let first = server.pull(SyncPull::default(), &context).await.unwrap();
+            assert!(first.events.is_empty());
+
+            // Stamped before the pull began, committed after it read.
+            commit(&persist, "late", 1, context.now() - TimeDelta::seconds(1)).await;
+            let next = SyncPull {
+                known_versions: BTreeMap::new(),
+                since: Some(first.pulled_at),
+            };
+            let second = server.pull(next, &CqrsContext::default()).await.unwrap();
+            assert_eq!(!second.events.is_empty(), caught, "overlap {overlap}");
+        }
+    }
+
+    #[tokio::test]
+    async fn a_pull_pages_through_events_stamped_at_the_same_instant() {
+        let persist = InMemoryPersist::<TestAggregate>::new();
+        let server = server(&persist);
+        let start = Utc::now();
+        let ids: Vec<_> = (0..2 * PULL_PAGE_SIZE + 1)
+            .map(|i| format!("a{i:03}"))
+            .collect();
+        for (i, aggregate_id) in ids.iter().enumerate() {
+            // More events than a page share the first instant; the rest follow.
+            let at = match i {
+                i if i <= PULL_PAGE_SIZE => start,
+                i => start + TimeDelta::milliseconds(i as i64),
+            };
+            commit(&persist, aggregate_id, 1, at).await;
+            commit(&persist, aggregate_id, 2, at + TimeDelta::seconds(1)).await;
+        }
+
+        let pull = SyncPull {
+            known_versions: BTreeMap::new(),
+            since: Some(start),
+        };
+        let result = server.pull(pull, &CqrsContext::default()).await.unwrap();
+        let pulled: HashSet<_> = pulled(&result).into_iter().collect();
+        assert_eq!(pulled.len(), 2 * ids.len());
+        assert!(result.continue_from.is_none());
+    }
+
+    #[tokio::test]
+    async fn a_pull_reads_the_aggregates_changed_since_after_the_known_versions() {
+        let persist = InMemoryPersist::<TestAggregate>::new();
+        let server = server(&persist);
+        let start = Utc::now();
+        for aggregate_id in ["a1", "a2"] {
+            commit(&persist, aggregate_id, 1, start).await;
+        }
+        commit(&persist, "a2", 2, start + TimeDelta::seconds(10)).await;
+        commit(&persist, "a3", 1, start + TimeDelta::seconds(10)).await;
+
+        let pull = SyncPull {
+            known_versions: BTreeMap::from([("a1".to_string(), 1), ("a2".to_string(), 1)]),
+            since: Some(start + TimeDelta::seconds(5)),
+        };
+        let result = server.pull(pull, &CqrsContext::default()).await.unwrap();
+        let mut events = pulled(&result);
+        events.sort();
+        assert_eq!(events, [("a2", 2), ("a3", 1)]);
+    }
+
+    async fn created(server: &SyncServer<TestAggregate>, aggregate_id: &str, increments: usize) {
+        let context = CqrsContext::default();
+        let engine = server.engine();
+        engine
+            .execute_create_variant_with_id(
+                aggregate_id,
+                CreateCommand::Initialize {
+                    name: aggregate_id.to_string(),
+                },
+                HashMap::new(),
+                &context,
+            )
+            .await
+            .unwrap();
+        for _ in 0..increments {
+            engine
+                .execute_update(aggregate_id, UpdateCommand::Increment, &context)
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn a_pull_leaves_out_the_aggregates_the_read_policy_refuses() {
+        let persist = InMemoryPersist::<TestAggregate>::new();
+        let server = server(&persist).with_read_policy(Policy::authenticated().require(
+            |context, aggregate: &TestAggregate| {
+                aggregate
+                    .aggregate_id()
+                    .starts_with(&context.current_user())
+            },
+        ));
+        for aggregate_id in ["alice-1", "bob-1", "alice-2"] {
+            created(&server, aggregate_id, 0).await;
+        }
+
+        let alice = CqrsContext::new(Some("alice".to_string()));
+        let result = server.pull(SyncPull::default(), &alice).await.unwrap();
+        let mut events = pulled(&result);
+        events.sort();
+        assert_eq!(events, [("alice-1", 1), ("alice-2", 1)]);
+
+        let error = server
+            .pull(SyncPull::default(), &CqrsContext::default())
+            .await
+            .unwrap_err();
+        assert_eq!(error.status, 401);
+    }
+
+    #[tokio::test]
+    async fn a_pull_past_the_limit_says_where_to_pull_the_rest_from() {
+        let persist = InMemoryPersist::<TestAggregate>::new();
+        let server = server(&persist).with_pull_limit(3);
+        created(&server, "a1", 4).await;
+        created(&server, "a2", 0).await;
+
+        let context = CqrsContext::default();
+        let mut pull = SyncPull::default();
+        let mut rounds = Vec::new();
+        loop {
+            let result = server.pull(pull.clone(), &context).await.unwrap();
+            assert!(result.events.len() <= 3);
+            for event in &result.events {
+                pull.known_versions
+                    .insert(event.aggregate_id.clone(), event.version);
+            }
+            rounds.push(pulled(&result).len());
+            match result.continue_from {
+                Some(next) => pull.since = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(rounds, [3, 3]);
+        assert_eq!(
+            pull.known_versions,
+            BTreeMap::from([("a1".to_string(), 5), ("a2".to_string(), 1)])
+        );
+    }
+
+    fn owned_by_the_caller() -> Policy<TestAggregate> {
+        Policy::authenticated().require(|context, aggregate: &TestAggregate| {
+            aggregate
+                .aggregate_id()
+                .starts_with(&context.current_user())
+        })
+    }
+
+    fn stale_increment(aggregate_id: &str) -> SyncPush {
+        SyncPush {
+            commands: vec![SyncCommand {
+                id: "c1".to_string(),
+                aggregate_id: aggregate_id.to_string(),
+                kind: SyncCommandKind::Update,
+                variant: None,
+                command: serde_json::json!("Increment"),
+                known_version: 0,
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn a_conflict_shows_no_events_to_a_caller_who_may_not_see_them() {
+        let persist = InMemoryPersist::<TestAggregate>::new();
+        let bob = CqrsContext::new(Some("bob".to_string()));
+        created(&server(&persist), "alice-1", 2).await;
+
+        // Refused by the command policy: rejected before anything is read.
+        let store = EventStoreImpl::new(persist.clone());
+        let engine = CqrsCommandEngine::new(store, vec![], (), Box::new(|_| {}))
+            .with_policies(CommandPolicies::new().otherwise(owned_by_the_caller()));
+        let guarded = SyncServer::new(Arc::new(engine));
+        let result = guarded
+            .push(stale_increment("alice-1"), &bob)
+            .await
+            .unwrap();
+        match &result.outcomes[..] {
+            [SyncOutcome::Rejected(rejection)] => assert_eq!(rejection.error.status, 403),
+            outcomes => panic!("expected a rejection, got {outcomes:?}"),
+        }
+
+        // Allowed to write, not to read: the conflict comes without the remote events.
+        let server = server(&persist).with_read_policy(owned_by_the_caller());
+        let result = server.push(stale_increment("alice-1"), &bob).await.unwrap();
+        match &result.outcomes[..] {
+            [SyncOutcome::Conflict(conflict)] => {
+                assert_eq!(conflict.current_version, 3);
+                assert!(conflict.remote_events.is_empty());
+            }
+            outcomes => panic!("expected a conflict, got {outcomes:?}"),
+        }
+        let alice = CqrsContext::new(Some("alice".to_string()));
+        let result = server
+            .push(stale_increment("alice-1"), &alice)
+            .await
+            .unwrap();
+        match &result.outcomes[..] {
+            [SyncOutcome::Conflict(conflict)] => assert_eq!(conflict.remote_events.len(), 3),
+            outcomes => panic!("expected a conflict, got {outcomes:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn a_create_is_pushed_under_a_policy_and_a_read_policy() {
+        let persist = InMemoryPersist::<TestAggregate>::new();
+        let store = EventStoreImpl::new(persist.clone());
+        let engine = CqrsCommandEngine::new(store, vec![], (), Box::new(|_| {}))
+            .with_policies(CommandPolicies::new().otherwise(Policy::authenticated()));
+        let server = SyncServer::new(Arc::new(engine)).with_read_policy(owned_by_the_caller());
+        let push = SyncPush {
+            commands: vec![SyncCommand {
+                id: "c1".to_string(),
+                aggregate_id: "alice-1".to_string(),
+                kind: SyncCommandKind::Create,
+                variant: None,
+                command: serde_json::json!({"Initialize": {"name": "a"}}),
+                known_version: 0,
+            }],
+        };
+        let alice = CqrsContext::new(Some("alice".to_string()));
+
+        // Pushed twice, as after an answer lost: applied once, and found the second time.
+        for _ in 0..2 {
+            let result = server.push(push.clone(), &alice).await.unwrap();
+            match &result.outcomes[..] {
+                [SyncOutcome::Applied { version, .. }] => assert_eq!(*version, 1),
+                outcomes => panic!("expected the create applied, got {outcomes:?}"),
+            }
+        }
+    }
+
+    #[cfg(feature = "utoipa")]
+    #[tokio::test]
+    async fn a_push_labelled_as_another_variant_is_refused() {
+        let persist = InMemoryPersist::<TestAggregate>::new();
+        let server = server(&persist);
+        let command = |id: &str, kind, variant: Option<&str>, command| SyncCommand {
+            id: id.to_string(),
+            aggregate_id: "a1".to_string(),
+            kind,
+            variant: variant.map(str::to_string),
+            command,
+            known_version: 0,
+        };
+        let push = SyncPush {
+            commands: vec![
+                command(
+                    "c1",
+                    SyncCommandKind::Create,
+                    None,
+                    serde_json::json!({"Initialize": {"name": "a"}}),
+                ),
+                command(
+                    "c2",
+                    SyncCommandKind::Update,
+                    Some("Decrement"),
+                    serde_json::json!("Increment"),
+                ),
+            ],
+        };
+        let context = CqrsContext::default();
+        let result = server.push(push, &context).await.unwrap();
+        assert!(matches!(
+            result.outcomes[0],
+            SyncOutcome::Applied { version: 1, .. }
+        ));
+        match &result.outcomes[1] {
+            SyncOutcome::Rejected(rejection) => {
+                assert_eq!(
+                    rejection.error.status,
+                    http::StatusCode::UNPROCESSABLE_ENTITY
+                )
+            }
+            outcome => panic!("expected a rejection, got {outcome:?}"),
+        }
+        assert_eq!(
+            server.events_after("a1", 0, &context).await.unwrap().len(),
+            1
+        );
+    }
+}
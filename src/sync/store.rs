@@ -0,0 +1,305 @@
+use crate::es::storage::{EventStoreStorage, EventStream};
+use crate::sync::{DynSyncOutbox, SYNC_COMMAND_METADATA};
+use crate::{
+    Aggregate, CqrsContext, CqrsError, EventEnvelope, EventFilter, EventStore, MaybeSend,
+    MaybeSync, Snapshot,
+};
+use futures::{stream, StreamExt};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::sync::Arc;
+use tracing::{debug, error};
+
+/// How many replica events [`SyncEventStore::load_events_paged`] reads at a time.
+const REPLICA_PAGE_SIZE: usize = 100;
+
+/// The event store of a client's engine: the events the server confirmed, in a replica,
+/// followed by those of the commands still in the outbox.
+///
+/// The replica is any [`EventStoreStorage`] — `IndexedDbPersist` in the browser — and
+/// only a pull writes to it, events and versions exactly as the server committed them.
+/// The engine's commits go to the outbox, so a command the server refuses is dropped
+/// without rewriting the replica. Pending events are numbered after the replica's last
+/// version, whatever it is when they are read: they move up when a pull brings events
+/// from other clients in under them.
+///
+/// Only the commands a [`SyncClient`](crate::sync::SyncClient) runs can commit: they
+/// are staged in the outbox before the engine runs them.
+pub struct SyncEventStore<A, P>
+where
+    A: Aggregate + 'static,
+    P: EventStoreStorage<A> + MaybeSend + MaybeSync + Clone + Debug + 'static,
+{
+    replica: P,
+    outbox: DynSyncOutbox<A>,
+}
+
+impl<A, P> SyncEventStore<A, P>
+where
+    A: Aggregate + 'static,
+    P: EventStoreStorage<A> + MaybeSend + MaybeSync + Clone + Debug + 'static,
+{
+    #[must_use]
+    pub fn new(replica: P, outbox: DynSyncOutbox<A>) -> Arc<Self> {
+        Arc::new(Self { replica, outbox })
+    }
+
+    pub fn replica(&self) -> &P {
+        &self.replica
+    }
+
+    pub fn outbox(&self) -> &DynSyncOutbox<A> {
+        &self.outbox
+    }
+
+    /// The state and version of `aggregate_id` in the replica; the default state at `0`
+    /// when the replica does not have it.
+    async fn replica_state(
+        &self,
+        persist: &P,
+        aggregate_id: &str,
+    ) -> Result<(A, usize), CqrsError> {
+        let (mut state, mut version) = match persist.fetch_snapshot(aggregate_id).await? {
+            Some(snapshot) => (snapshot.state, snapshot.version),
+            None => (A::default().with_aggregate_id(aggregate_id.to_string()), 0),
+        };
+        let mut events = persist
+            .fetch_events_from_version(aggregate_id, version)
+            .await?;
+        while let Some(event) = events.next().await {
+            let event = event?;
+            state.apply(event.payload).map_err(CqrsError::user_error)?;
+            version = event.version;
+        }
+        Ok((state, version))
+    }
+
+    /// The pending events of each aggregate, in the order they were committed, numbered
+    /// after the replica's last version.
+    async fn pending_events(
+        &self,
+        persist: &P,
+        aggregate_id: Option<&str>,
+    ) -> Result<BTreeMap<String, Vec<EventEnvelope<A>>>, CqrsError> {
+        let mut pending: BTreeMap<String, Vec<EventEnvelope<A>>> = BTreeMap::new();
+        for command in self.outbox.list().await? {
+            let Some((id, events)) = command.committed() else {
+                continue;
+            };
+            if aggregate_id.is_none_or(|aggregate_id| aggregate_id == id) {
+                pending
+                    .entry(id.to_string())
+                    .or_default()
+                    .extend_from_slice(events);
+            }
+        }
+        for (id, events) in pending.iter_mut() {
+            let (_, version) = self.replica_state(persist, id).await?;
+            for (i, event) in events.iter_mut().enumerate() {
+                event.version = version + i + 1;
+            }
+        }
+        Ok(pending)
+    }
+
+    /// Writes events pulled from the server to the replica, after the version it holds,
+    /// with the snapshot they lead to; answers those written. Events it already holds
+    /// are skipped, so a pull can overlap the previous one.
+    pub(crate) async fn apply_remote(
+        &self,
+        aggregate_id: &str,
+        events: Vec<EventEnvelope<A>>,
+        context: &CqrsContext,
+    ) -> Result<Vec<EventEnvelope<A>>, CqrsError> {
+        let persist = self.replica.for_context(context)?;
+        let (mut state, version) = self.replica_state(&persist, aggregate_id).await?;
+        let events: Vec<_> = events.into_iter().filter(|e| e.version > version).collect();
+        let Some(last) = events.last().map(|e| e.version) else {
+            return Ok(events);
+        };
+        if events[0].version != version + 1 {
+            return Err(CqrsError::internal(format!(
+                "the pull of '{aggregate_id}' starts at version {}, after the replica's {version}",
+                events[0].version
+            )));
+        }
+        for event in &events {
+            state
+                .apply(event.payload.clone())
+                .map_err(CqrsError::user_error)?;
+        }
+
+        let mut session = persist.start_session().await?;
+        let written = async {
+            let latest = persist.fetch_latest_event(&state, &session).await?;
+            if latest.map(|e| e.version).unwrap_or(0) != version {
+                return Err(CqrsError::concurrency_error());
+            }
+            persist.save_events(events.clone(), &mut session).await?;
+            persist.save_snapshot(&state, last, &mut session).await
+        }
+        .await;
+        match written {
+            Ok(()) => persist.close_session(session).await.map(|_| events),
+            Err(e) => {
+                error!(error = %e, "Failed to write pulled events to the replica");
+                let _ = persist.abort_session(session).await;
+                Err(e)
+            }
+        }
+    }
+}
+
+cqrs_async_trait! {
+impl<A, P> EventStore<A> for SyncEventStore<A, P>
+where
+    A: Aggregate + 'static,
+    P: EventStoreStorage<A> + MaybeSend + MaybeSync + Clone + Debug + 'static,
+{
+    async fn health(&self) -> Result<(), CqrsError> {
+        self.replica.health().await
+    }
+
+    /// The replica's snapshot; the empty aggregate for one only created offline.
+    async fn load_snapshot(
+        &self,
+        aggregate_id: &str,
+        context: &CqrsContext,
+    ) -> Result<Option<Snapshot<A>>, CqrsError> {
+        let persist = self.replica.for_context(context)?;
+        if let Some(snapshot) = persist.fetch_snapshot(aggregate_id).await? {
+            return Ok(Some(snapshot));
+        }
+        let pending = self.pending_events(&persist, Some(aggregate_id)).await?;
+        Ok(pending.contains_key(aggregate_id).then(|| Snapshot {
+            aggregate_id: aggregate_id.to_string(),
+            state: A::default().with_aggregate_id(aggregate_id.to_string()),
+            version: 0,
+        }))
+    }
+
+    async fn load_events_from_version(
+        &self,
+        aggregate_id: &str,
+        version: usize,
+        context: &CqrsContext,
+    ) -> Result<EventStream<A>, CqrsError> {
+        let persist = self.replica.for_context(context)?;
+        let mut events: Vec<_> = persist
+            .fetch_events_from_version(aggregate_id, version)
+            .await?
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<_, _>>()?;
+        let mut pending = self.pending_events(&persist, Some(aggregate_id)).await?;
+        events.extend(
+            pending
+                .remove(aggregate_id)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|e| e.version > version),
+        );
+        Ok(Box::pin(stream::iter(events.into_iter().map(Ok))))
+    }
+
+    async fn load_events(
+        &self,
+        aggregate_id: &str,
+        context: &CqrsContext,
+    ) -> Result<EventStream<A>, CqrsError> {
+        self.load_events_from_version(aggregate_id, 0, context).await
+    }
+
+    /// Reads the whole selection from the replica, adds the pending events it selects,
+    /// and pages the lot: a client's journal is small.
+    async fn load_events_paged(
+        &self,
+        filter: &EventFilter,
+        page: usize,
+        page_size: usize,
+        context: &CqrsContext,
+    ) -> Result<(Vec<EventEnvelope<A>>, i64), CqrsError> {
+        let persist = self.replica.for_context(context)?;
+        let mut items = Vec::new();
+        for replica_page in 1.. {
+            let (events, total) = persist
+                .fetch_events_paged(filter, replica_page, REPLICA_PAGE_SIZE)
+                .await?;
+            let done = events.is_empty() || items.len() + events.len() >= total as usize;
+            items.extend(events);
+            if done {
+                break;
+            }
+        }
+        let pending = self
+            .pending_events(&persist, filter.aggregate_id.as_deref())
+            .await?;
+        items.extend(pending.into_values().flatten().filter(|e| filter.matches(e)));
+        filter.sort(&mut items);
+        let total = items.len() as i64;
+        let offset = (page.max(1) - 1) * page_size;
+        Ok((items.into_iter().skip(offset).take(page_size).collect(), total))
+    }
+
+    /// Records the events in the command's outbox entry, staged by the `SyncClient`
+    /// running it, and leaves the replica alone.
+    async fn commit(
+        &self,
+        events: Vec<A::Event>,
+        aggregate: &A,
+        metadata: HashMap<String, String>,
+        version: usize,
+        context: &CqrsContext,
+    ) -> Result<Vec<EventEnvelope<A>>, CqrsError> {
+        let command_id = metadata.get(SYNC_COMMAND_METADATA).ok_or_else(|| {
+            CqrsError::internal("a SyncEventStore only commits the commands a SyncClient runs")
+        })?;
+        let mut command = self
+            .outbox
+            .find(command_id)
+            .await?
+            .filter(|command| command.events.is_none())
+            .ok_or_else(|| {
+                CqrsError::internal(format!("command '{command_id}' is not staged in the outbox"))
+            })?;
+
+        let aggregate_id = aggregate.aggregate_id();
+        let persist = self.replica.for_context(context)?;
+        let (_, replica_version) = self.replica_state(&persist, &aggregate_id).await?;
+        let pending = self
+            .pending_events(&persist, Some(&aggregate_id))
+            .await?
+            .remove(&aggregate_id)
+            .unwrap_or_default()
+            .len();
+        if version != replica_version + pending {
+            error!(latest_version = %(replica_version + pending), expected_version = %version, "Version conflict detected");
+            return Err(CqrsError::concurrency_error());
+        }
+
+        let envelopes: Vec<_> = events
+            .into_iter()
+            .enumerate()
+            .map(|(i, payload)| EventEnvelope {
+                event_id: context.next_uuid(),
+                aggregate_id: aggregate_id.clone(),
+                version: version + i + 1,
+                payload,
+                metadata: metadata.clone(),
+                at: context.now(),
+            })
+            .collect();
+        if envelopes.is_empty() {
+            // Nothing to tell the server.
+            self.outbox.remove(command_id).await?;
+            return Ok(envelopes);
+        }
+        debug!(event_count = envelopes.len(), "Recording events in the outbox");
+        command.aggregate_id = Some(aggregate_id);
+        command.events = Some(envelopes.clone());
+        self.outbox.save(command).await?;
+        Ok(envelopes)
+    }
+}
+}
@@ -0,0 +1,194 @@
+use crate::{CqrsContext, CqrsError};
+
+/// Name of the column, or document field, holding the tenant under [`Tenancy::Column`].
+pub const TENANT_COLUMN: &str = "tenant_id";
+
+/// How a storage keeps the data of one tenant apart from another's.
+///
+/// Under any strategy but [`Shared`](Self::Shared), the tenant is the one of the
+/// [`CqrsContext`] — [`CqrsContext::tenant`] — and a request without one is refused with
+/// `403` rather than reading or writing outside every tenant. An aggregate id is only
+/// ever looked up within its tenant, so the same id in two tenants names two aggregates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tenancy {
+    /// One data set for everyone: the tenant of the context is ignored.
+    #[default]
+    Shared,
+    /// Every row or document carries its tenant in [`TENANT_COLUMN`], written on save and
+    /// matched by every read.
+    Column,
+    /// A table, or collection, per tenant: `{base}_{tenant}`. The tenant becomes part of
+    /// an identifier, so it may only hold ASCII letters, digits and `_`.
+    Table,
+}
+
+/// A [`Tenancy`], and the tenant it is resolved to for one request.
+///
+/// A storage holds the unresolved scope it was configured with, and resolves it per call
+/// with [`for_context`](Self::for_context); [`column`](Self::column) and
+/// [`table`](Self::table) then say what to filter on and where to look. Asked of a scope
+/// that was never resolved, they refuse, so a storage used outside of a request cannot
+/// fall back to reading every tenant.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TenantScope {
+    tenancy: Tenancy,
+    tenant: Option<String>,
+}
+
+impl TenantScope {
+    #[must_use]
+    pub fn new(tenancy: Tenancy) -> Self {
+        Self {
+            tenancy,
+            tenant: None,
+        }
+    }
+
+    pub fn tenancy(&self) -> Tenancy {
+        self.tenancy
+    }
+
+    /// The scope of a request acting for the tenant of `context`.
+    pub fn for_context(&self, context: &CqrsContext) -> Result<Self, CqrsError> {
+        if self.tenancy == Tenancy::Shared {
+            return Ok(self.clone());
+        }
+        let tenant = context
+            .tenant()
+            .filter(|tenant| !tenant.is_empty())
+            .ok_or_else(|| CqrsError::forbidden("a tenant is required"))?;
+        if self.tenancy == Tenancy::Table {
+            validated_tenant(tenant)?;
+        }
+        Ok(Self {
+            tenancy: self.tenancy,
+            tenant: Some(tenant.to_string()),
+        })
+    }
+
+    /// The tenant a row must carry, under [`Tenancy::Column`]; `None` otherwise.
+    pub fn column(&self) -> Result<Option<&str>, CqrsError> {
+        match self.tenancy {
+            Tenancy::Column => self.tenant().map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// The table, or collection, holding the tenant's share of `base`.
+    pub fn table(&self, base: &str) -> Result<String, CqrsError> {
+        match self.tenancy {
+            Tenancy::Table => Ok(tenant_table(base, self.tenant()?)),
+            _ => Ok(base.to_string()),
+        }
+    }
+
+    /// Whether a record kept for `tenant` is within the scope: any record when shared,
+    /// only those of the scope's own tenant otherwise.
+    pub fn admits(&self, tenant: Option<&str>) -> bool {
+        match self.tenancy {
+            Tenancy::Shared => true,
+            _ => self.tenant.is_some() && tenant == self.tenant.as_deref(),
+        }
+    }
+
+    /// The partition of an in-memory storage: the tenant, or `""` when shared.
+    pub(crate) fn partition(&self) -> Result<&str, CqrsError> {
+        match self.tenancy {
+            Tenancy::Shared => Ok(""),
+            _ => self.tenant(),
+        }
+    }
+
+    fn tenant(&self) -> Result<&str, CqrsError> {
+        self.tenant
+            .as_deref()
+            .ok_or_else(|| CqrsError::internal("the storage was not scoped to a tenant"))
+    }
+}
+
+/// The table, or collection, of `tenant` under [`Tenancy::Table`]; for a storage's
+/// schema helpers to create it the way the storage will look it up.
+pub fn tenant_table(base: &str, tenant: &str) -> String {
+    format!("{base}_{tenant}")
+}
+
+/// Refuses a tenant that cannot be part of an identifier.
+pub fn validated_tenant(tenant: &str) -> Result<&str, CqrsError> {
+    if !tenant.is_empty()
+        && tenant
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        Ok(tenant)
+    } else {
+        Err(CqrsError::forbidden(format!(
+            "tenant {tenant:?} may only hold ASCII letters, digits and '_'"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn acting_for(tenant: Option<&str>) -> CqrsContext {
+        CqrsContext::default().with_tenant(tenant.map(str::to_string))
+    }
+
+    #[test]
+    fn a_shared_scope_ignores_the_tenant() {
+        let scope = TenantScope::default()
+            .for_context(&acting_for(None))
+            .unwrap();
+        assert_eq!(scope.column().unwrap(), None);
+        assert_eq!(scope.table("todo_journal").unwrap(), "todo_journal");
+        assert_eq!(scope.partition().unwrap(), "");
+    }
+
+    #[test]
+    fn a_scope_admits_the_records_of_its_tenant_only() {
+        let shared = TenantScope::default()
+            .for_context(&acting_for(Some("acme")))
+            .unwrap();
+        assert!(shared.admits(Some("globex")) && shared.admits(None));
+
+        let scope = TenantScope::new(Tenancy::Column)
+            .for_context(&acting_for(Some("acme")))
+            .unwrap();
+        assert!(scope.admits(Some("acme")));
+        assert!(!scope.admits(Some("globex")) && !scope.admits(None));
+        assert!(!TenantScope::new(Tenancy::Column).admits(None));
+    }
+
+    #[test]
+    fn a_scoped_storage_refuses_a_request_without_a_tenant() {
+        for tenancy in [Tenancy::Column, Tenancy::Table] {
+            let error = TenantScope::new(tenancy)
+                .for_context(&acting_for(None))
+                .unwrap_err();
+            assert_eq!(error.status, 403);
+        }
+        // Never resolved: used outside of a request.
+        assert!(TenantScope::new(Tenancy::Column).column().is_err());
+    }
+
+    #[test]
+    fn the_tenant_picks_the_column_value_or_the_table() {
+        let column = TenantScope::new(Tenancy::Column)
+            .for_context(&acting_for(Some("acme")))
+            .unwrap();
+        assert_eq!(column.column().unwrap(), Some("acme"));
+        assert_eq!(column.table("todo_journal").unwrap(), "todo_journal");
+
+        let table = TenantScope::new(Tenancy::Table)
+            .for_context(&acting_for(Some("acme")))
+            .unwrap();
+        assert_eq!(table.column().unwrap(), None);
+        assert_eq!(table.table("todo_journal").unwrap(), "todo_journal_acme");
+
+        let error = TenantScope::new(Tenancy::Table)
+            .for_context(&acting_for(Some("acme; DROP TABLE x")))
+            .unwrap_err();
+        assert_eq!(error.status, 403);
+    }
+}
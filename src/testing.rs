@@ -23,12 +23,14 @@ impl From<TestError> for crate::CqrsError {
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[cfg_attr(feature = "utoipa", derive(ToSchema))]
+#[cfg_attr(feature = "mcp", derive(schemars::JsonSchema))]
 pub enum CreateCommand {
     Initialize { name: String },
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[cfg_attr(feature = "utoipa", derive(ToSchema))]
+#[cfg_attr(feature = "mcp", derive(schemars::JsonSchema))]
 pub enum UpdateCommand {
     Increment,
     Decrement,
@@ -58,6 +60,7 @@ impl crate::Event for TestEvent {
 
 // Define a simple aggregate for testing
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(ToSchema))]
 pub struct TestAggregate {
     id: String,
     counter: i32,
@@ -144,6 +147,10 @@ impl View<TestAggregate> for TestView {
         event.aggregate_id.clone()
     }
 
+    fn version(&self) -> Option<usize> {
+        Some(self.version)
+    }
+
     fn update(&self, event: &EventEnvelope<TestAggregate>) -> Option<Self> {
         let mut updated = self.clone();
         updated.id = event.aggregate_id.clone();
@@ -1,5 +1,5 @@
 //! End-to-end check of the pagination chain: HTTP query string →
-//! `CqrsHttpQuery` → `Pagination` → SQL → `Paged`.
+//! `CqrsHttpQuery` → `Pagination` / `Projection` → SQL → `Paged`.
 //!
 //! Needs both `rest` (for the extractor) and `surrealdb` (for a real storage
 //! backend), so it only runs under `--all-features`.
@@ -9,7 +9,7 @@ use axum::extract::FromRequestParts;
 use cqrs_rust_lib::read::query::Query;
 use cqrs_rust_lib::read::storage::{HasId, Storage};
 use cqrs_rust_lib::read::surrealdb::SurrealDBStorage;
-use cqrs_rust_lib::read::Paged;
+use cqrs_rust_lib::read::{Paged, Projection};
 use cqrs_rust_lib::rest::CqrsHttpQuery;
 use cqrs_rust_lib::read::{SortDirection, Sorter};
 use cqrs_rust_lib::CqrsContext;
@@ -153,3 +153,22 @@ async fn defaults_apply_when_no_pagination_param_is_given() {
     assert_eq!(page.limit, 20);
     assert_eq!(page.items.len(), 6);
 }
+
+#[tokio::test]
+async fn fields_reach_the_storage_as_a_projection() {
+    let query = query("fields=score&skip=1&limit=2").await;
+    let projection = Projection::new(query.fields().unwrap().iter().map(String::as_str));
+    let page = store()
+        .await
+        .filter_projected(None, query, projection, CqrsContext::default())
+        .await
+        .unwrap();
+    assert_eq!(page.total, 6);
+    assert_eq!(
+        page.items,
+        vec![
+            serde_json::json!({ "score": 20 }),
+            serde_json::json!({ "score": 30 })
+        ]
+    );
+}